@@ -0,0 +1,26 @@
+import pyarrow as pa
+
+from similari import Sort, BoundingBox, PositionalMetricType
+
+if __name__ == '__main__':
+    sort = Sort(shards=4, bbox_history=10, max_idle_epochs=5,
+                method=PositionalMetricType.iou(threshold=0.3),
+                kalman_position_weight=0.1,
+                kalman_velocity_weight=0.1)
+
+    for i in range(3):
+        box = BoundingBox(10. + i, 5., 7., 7.).as_xyaah()
+        sort.predict([(box, 11111)])
+
+    summaries, observations = sort.export_arrow()
+
+    # the track attributes are the struct column, flatten it to get a column per attribute
+    summaries = pa.ipc.open_stream(summaries).read_all()
+    print(summaries.schema)
+    print(summaries.flatten().to_pandas())
+
+    # SORT keeps no features, so there are no feature_<class> columns, the observation attributes
+    # are the boxes
+    observations = pa.ipc.open_stream(observations).read_all()
+    print(observations.schema)
+    print(observations.to_pandas())
@@ -1,5 +1,6 @@
-use crate::track::Feature;
+use crate::track::{Feature, FeatureMask};
 use std::ops::{Mul, MulAssign, SubAssign};
+use ultraviolet::f32x8;
 
 /// Euclidian distance between two feature vectors
 ///
@@ -46,10 +47,72 @@ pub fn cosine(f1: &Feature, f2: &Feature) -> f32 {
     divided / (f1_divisor * f2_divisor).sqrt()
 }
 
+/// The lanes valid in both features, the lanes of the feature without the mask are valid
+///
+fn joint_mask(m1: Option<&FeatureMask>, m2: Option<&FeatureMask>, i: usize) -> f32x8 {
+    let lane = |m: Option<&FeatureMask>| match m {
+        Some(m) => m.get(i).copied().unwrap_or(f32x8::ZERO),
+        None => f32x8::ONE,
+    };
+    lane(m1) * lane(m2)
+}
+
+/// Euclidian distance between two feature vectors over the lanes valid in both of them, so the
+/// padding of the variable-length descriptors is not treated as data
+///
+/// # Parameters
+/// * `f1`, `f2` - the features, the longer one is truncated to the shorter one
+/// * `m1`, `m2` - the validity masks of the features, `None` if all the lanes are valid
+///
+pub fn masked_euclidean(
+    f1: &Feature,
+    m1: Option<&FeatureMask>,
+    f2: &Feature,
+    m2: Option<&FeatureMask>,
+) -> f32 {
+    let mut acc = 0.0;
+    for i in 0..f1.len().min(f2.len()) {
+        let mut block1 = f1[i];
+        block1.sub_assign(f2[i]);
+        block1.mul_assign(joint_mask(m1, m2, i));
+        block1.mul_assign(block1);
+        acc += block1.reduce_add();
+    }
+    acc.sqrt()
+}
+
+/// Cosine distance between two feature vectors over the lanes valid in both of them, the norms
+/// are calculated over the same lanes
+///
+/// # Parameters
+/// * `f1`, `f2` - the features, the longer one is truncated to the shorter one
+/// * `m1`, `m2` - the validity masks of the features, `None` if all the lanes are valid
+///
+pub fn masked_cosine(
+    f1: &Feature,
+    m1: Option<&FeatureMask>,
+    f2: &Feature,
+    m2: Option<&FeatureMask>,
+) -> f32 {
+    let mut divided = 0.0;
+    let mut f1_divisor = 0.0;
+    let mut f2_divisor = 0.0;
+    for i in 0..f1.len().min(f2.len()) {
+        let mask = joint_mask(m1, m2, i);
+        let block1 = f1[i].mul(mask);
+        let block2 = f2[i].mul(mask);
+        divided += block1.mul(block2).reduce_add();
+        f1_divisor += block1.mul(block1).reduce_add();
+        f2_divisor += block2.mul(block2).reduce_add();
+    }
+
+    divided / (f1_divisor * f2_divisor).sqrt()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::distance::{cosine, euclidean};
-    use crate::track::utils::FromVec;
+    use crate::distance::{cosine, euclidean, masked_cosine, masked_euclidean};
+    use crate::track::utils::{feature_with_padding, prefix_feature_mask, FromVec};
     use crate::track::Feature;
     use crate::EPS;
 
@@ -76,4 +139,27 @@ mod tests {
         let d = cosine(&v1, &v2);
         assert!(d.abs() < EPS);
     }
+
+    #[test]
+    fn masked_distances() {
+        let v1 = feature_with_padding(&[1.0, 0.0, 3.0], 5.0);
+        let v2 = feature_with_padding(&[0.0, 1.0], -5.0);
+        let m1 = prefix_feature_mask(3, 3);
+        let m2 = prefix_feature_mask(2, 3);
+
+        let d = masked_euclidean(&v1, Some(&m1), &v2, Some(&m2));
+        assert!((d - 2.0f32.sqrt()).abs() < EPS);
+        let d = masked_euclidean(&v1, Some(&m1), &v1, None);
+        assert!(d.abs() < EPS);
+        let d = masked_euclidean(&v1, None, &v2, None);
+        assert!((d - euclidean(&v1, &v2)).abs() < EPS);
+
+        let d = masked_cosine(&v1, Some(&m1), &v2, Some(&m2));
+        assert!(d.abs() < EPS);
+        let v3 = feature_with_padding(&[2.0, 0.0, -7.0], 0.0);
+        let d = masked_cosine(&v1, None, &v3, Some(&prefix_feature_mask(1, 3)));
+        assert!((d - 1.0).abs() < EPS);
+        let d = masked_cosine(&v1, None, &v2, None);
+        assert!((d - cosine(&v1, &v2)).abs() < EPS);
+    }
 }
@@ -75,30 +75,143 @@ pub enum Errors {
     /// Index is out of range
     #[error("The index is out of range")]
     OutOfRange,
+
+    /// Projection matrix doesn't map input dimension to the canonical dimension
+    ///
+    #[error("Projection matrix must have {0} rows and {1} columns")]
+    ProjectionShapeMismatch(usize, usize),
+
+    /// There is no projection for the feature of the dimension
+    ///
+    #[error("No projection registered for features of dimension={0}")]
+    FeatureProjectionNotFound(usize),
+
+    /// The metric panicked when the distances between the tracks were calculated
+    ///
+    #[error("Metric panicked when track={0} was compared with track={1}: {2}")]
+    MetricPanic(u64, u64, String),
+
+    /// The merge into the track panicked, the track may be partially updated
+    ///
+    #[error("Merge into track={0} panicked: {1}")]
+    MergePanic(u64, String),
+
+    /// The attributes of the track panicked when the track status or the lookup were evaluated
+    ///
+    #[error("Attributes of track={0} panicked: {1}")]
+    AttributesPanic(u64, String),
+
+    /// The executor of the store shard is dead and can't be restarted
+    ///
+    #[error("Executor {0} is unavailable")]
+    ExecutorUnavailable(usize),
+
+    /// The metric returned NaN distance for the track pair
+    ///
+    #[error("Metric returned NaN distance when track={0} was compared with track={1}")]
+    NanDistance(u64, u64),
+
+    /// The Arrow IPC stream can't be read
+    ///
+    #[error("Arrow IPC stream is malformed or unsupported: {0}")]
+    ArrowStream(String),
+
+    /// The observation lacks the parts required by the builder or the metric
+    ///
+    #[error("Observation is malformed: {0}")]
+    MalformedObservation(String),
+
+    /// The maximal distance of the spatio-temporal constraint is not a positive number
+    ///
+    #[error("Constraint for epoch delta={0} has invalid maximal distance={1}, a positive float is expected")]
+    InvalidConstraint(usize, f32),
+
+    /// The worker of the batch tracker made no progress for longer than the watchdog allows
+    ///
+    #[error("Worker {0} made no progress for {1:?}")]
+    WorkerStalled(String, std::time::Duration),
+
+    /// The stores are incompatible, the tracks of one store can't be used with the other
+    ///
+    #[error("Store descriptor mismatch in {0}: expected {1}, found {2}")]
+    StoreDescriptorMismatch(String, String, String),
+
+    /// The batch tracker is shut down and doesn't accept the batches
+    ///
+    #[error("The tracker is shut down")]
+    TrackerShutDown,
+
+    /// The queue of the vector store sink is full, the templates are postponed to the next export
+    ///
+    #[error("Vector store sink is behind, {0} templates are postponed")]
+    VectorStoreBacklog(usize),
 }
 
 pub const EPS: f32 = 0.00001;
 
 #[cfg(feature = "python")]
 mod python {
+    use crate::store::nan_distances::python::PyNanDistancePolicy;
+    use crate::store::shard_stats::python::PyShardDistanceStats;
+    use crate::track::calibration::python::PySceneCalibration;
+    #[cfg(feature = "batch")]
     use crate::trackers::batch::python::PyPredictionBatchResult;
+    use crate::trackers::candidate_clustering::python::PyCandidateClustering;
+    use crate::trackers::density::python::PyDensityOptions;
+    use crate::trackers::distance_export::python::PyDistanceCollector;
+    use crate::trackers::duplicate_ids::python::PyDuplicateObjectIdPolicy;
+    use crate::trackers::frame_clamping::python::PyFrameClamping;
+    use crate::trackers::history_fallback::python::PyHistoryFallback;
+    use crate::trackers::latency_budget::python::PyLatencyBudget;
+    use crate::trackers::motion_model::python::PyMotionModel;
+    use crate::trackers::noise_model::python::PyNoiseModelOptions;
+    use crate::trackers::observation_pipeline::python::{
+        PyObservationPipeline, PyObservationTransformer,
+    };
+    use crate::trackers::output_filter::python::PyTrackOutputFilter;
+    use crate::trackers::predict_overrides::python::PyPredictOverrides;
+    use crate::trackers::quality::python::PyQualityOptions;
+    use crate::trackers::scene_zones::python::PySceneZones;
+    #[cfg(feature = "batch")]
     use crate::trackers::sort::batch_api::python::{PyBatchSort, PySortPredictionBatchRequest};
-    use crate::trackers::sort::python::{PyPositionalMetricType, PySortTrack, PyWastedSortTrack};
+    use crate::trackers::sort::python::{
+        PyPositionalMetricType, PySortObservation, PySortTrack, PyWastedSortTrack,
+    };
     use crate::trackers::sort::simple_api::python::PySort;
     use crate::trackers::spatio_temporal_constraints::python::PySpatioTemporalConstraints;
+    use crate::trackers::track_fusion::python::{PyFusedTrack, PyTrackFusion};
+    #[cfg(feature = "visual")]
+    use crate::trackers::visual_sort::adaptive_threshold::python::PyAdaptiveVisualThreshold;
+    #[cfg(all(feature = "batch", feature = "visual"))]
     use crate::trackers::visual_sort::batch_api::python::{
         PyBatchVisualSort, PyVisualSortPredictionBatchRequest,
     };
+    #[cfg(feature = "visual")]
+    use crate::trackers::visual_sort::cold_store::python::PyColdStoreOptions;
+    #[cfg(feature = "visual")]
+    use crate::trackers::visual_sort::metric::class_options::python::PyVisualClassOptions;
+    #[cfg(feature = "visual")]
     use crate::trackers::visual_sort::metric::python::PyVisualSortMetricType;
+    #[cfg(feature = "visual")]
     use crate::trackers::visual_sort::options::python::PyVisualSortOptions;
+    #[cfg(feature = "visual")]
     use crate::trackers::visual_sort::python::{
         PyVisualSortObservation, PyVisualSortObservationSet, PyWastedVisualSortTrack,
     };
+    #[cfg(feature = "visual")]
+    use crate::trackers::visual_sort::reactivation::python::PyReactivationOptions;
+    #[cfg(feature = "visual")]
     use crate::trackers::visual_sort::simple_api::python::PyVisualSort;
+    #[cfg(feature = "visual")]
+    use crate::trackers::visual_sort::whitening::python::PyFeatureWhitening;
+    use crate::trackers::waste_triggers::python::PyWasteTriggers;
+    use crate::trackers::watchdog::python::PyWatchdogOptions;
+    use crate::utils::bbox::coordinates::python::PyCoordinateSystem;
     use crate::utils::bbox::python::{PyBoundingBox, PyUniversal2DBox};
     use crate::utils::clipping::clipping_py::{
         intersection_area_py, sutherland_hodgman_clip_py, PyPolygon,
     };
+    use crate::utils::kalman::gating::python::PyMahalanobisGating;
     use crate::utils::kalman::kalman_2d_box::python::{
         PyUniversal2DBoxKalmanFilter, PyUniversal2DBoxKalmanFilterState,
     };
@@ -121,7 +234,9 @@ mod python {
 
         m.add_class::<PyBoundingBox>()?;
         m.add_class::<PyUniversal2DBox>()?;
+        m.add_class::<PyCoordinateSystem>()?;
         m.add_class::<PyPolygon>()?;
+        m.add_class::<PySortObservation>()?;
         m.add_class::<PySortTrack>()?;
         m.add_class::<PyWastedSortTrack>()?;
 
@@ -133,25 +248,61 @@ mod python {
 
         m.add_class::<PyVec2DKalmanFilter>()?;
 
-        m.add_class::<PySortPredictionBatchRequest>()?;
         m.add_class::<PySpatioTemporalConstraints>()?;
+        m.add_class::<PyTrackOutputFilter>()?;
+        m.add_class::<PySceneZones>()?;
+        m.add_class::<PyWasteTriggers>()?;
+        m.add_class::<PySceneCalibration>()?;
+        m.add_class::<PyDuplicateObjectIdPolicy>()?;
+        m.add_class::<PyNanDistancePolicy>()?;
+        m.add_class::<PyShardDistanceStats>()?;
+        m.add_class::<PyFrameClamping>()?;
+        m.add_class::<PyMahalanobisGating>()?;
+        m.add_class::<PyCandidateClustering>()?;
+        m.add_class::<PyNoiseModelOptions>()?;
+        m.add_class::<PyDensityOptions>()?;
+        m.add_class::<PyLatencyBudget>()?;
+        m.add_class::<PyPredictOverrides>()?;
+        m.add_class::<PyObservationTransformer>()?;
+        m.add_class::<PyObservationPipeline>()?;
         m.add_class::<PySort>()?;
+        m.add_class::<PyFusedTrack>()?;
+        m.add_class::<PyTrackFusion>()?;
+        m.add_class::<PyDistanceCollector>()?;
+        m.add_class::<PyHistoryFallback>()?;
+        m.add_class::<PyWatchdogOptions>()?;
+        m.add_class::<PyMotionModel>()?;
+        m.add_class::<PyQualityOptions>()?;
 
         m.add_class::<PyPositionalMetricType>()?;
-        m.add_class::<PyVisualSortMetricType>()?;
-        m.add_class::<PyVisualSortOptions>()?;
-        m.add_class::<PyVisualSortObservation>()?;
-        m.add_class::<PyVisualSortObservationSet>()?;
-        m.add_class::<PyVisualSortPredictionBatchRequest>()?;
-        m.add_class::<PyWastedVisualSortTrack>()?;
-        m.add_class::<PyVisualSort>()?;
 
-        m.add_class::<PyPredictionBatchResult>()?;
+        #[cfg(feature = "visual")]
+        {
+            m.add_class::<PyVisualSortMetricType>()?;
+            m.add_class::<PyVisualClassOptions>()?;
+            m.add_class::<PyVisualSortOptions>()?;
+            m.add_class::<PyReactivationOptions>()?;
+            m.add_class::<PyColdStoreOptions>()?;
+            m.add_class::<PyAdaptiveVisualThreshold>()?;
+            m.add_class::<PyFeatureWhitening>()?;
+            m.add_class::<PyVisualSortObservation>()?;
+            m.add_class::<PyVisualSortObservationSet>()?;
+            m.add_class::<PyWastedVisualSortTrack>()?;
+            m.add_class::<PyVisualSort>()?;
+        }
 
-        m.add_class::<PySortPredictionBatchRequest>()?;
-        m.add_class::<PyBatchSort>()?;
+        #[cfg(feature = "batch")]
+        {
+            m.add_class::<PyPredictionBatchResult>()?;
+            m.add_class::<PySortPredictionBatchRequest>()?;
+            m.add_class::<PyBatchSort>()?;
+        }
 
-        m.add_class::<PyBatchVisualSort>()?;
+        #[cfg(all(feature = "batch", feature = "visual"))]
+        {
+            m.add_class::<PyVisualSortPredictionBatchRequest>()?;
+            m.add_class::<PyBatchVisualSort>()?;
+        }
 
         m.add_function(wrap_pyfunction!(version, m)?)?;
         m.add_function(wrap_pyfunction!(nms_py, m)?)?;
@@ -2,23 +2,31 @@ use crate::track;
 use crate::trackers;
 use crate::utils;
 
-pub use track::builder::{ObservationBuilder, TrackBuilder};
+pub use track::builder::{MultiObservationBuilder, ObservationBuilder, TrackBuilder};
+pub use track::feature_class::FeatureClass;
+pub use track::lookup::{lookup_fn, LookupRequestExt};
 pub use track::notify::NoopNotifier;
 pub use track::store::builder::TrackStoreBuilder;
 
 pub use crate::trackers::sort::PositionalMetricType;
+#[cfg(feature = "batch")]
 pub use trackers::sort::batch_api::BatchSort;
 pub use trackers::sort::simple_api::Sort;
 pub use trackers::sort::SortTrack;
 pub use trackers::spatio_temporal_constraints::SpatioTemporalConstraints;
 
+#[cfg(feature = "visual")]
 pub use crate::trackers::visual_sort::options::VisualSortOptions;
+#[cfg(feature = "visual")]
 pub use trackers::visual_sort::metric::VisualSortMetricType;
+#[cfg(feature = "visual")]
 pub use trackers::visual_sort::simple_api::VisualSort;
+#[cfg(feature = "visual")]
 pub use trackers::visual_sort::VisualSortObservation;
 
 pub use utils::bbox::BoundingBox;
 pub use utils::bbox::Universal2DBox;
 
+#[cfg(feature = "clipping")]
 pub use utils::clipping::sutherland_hodgman_clip;
 pub use utils::nms;
@@ -1,4 +1,8 @@
+use crate::track::calibration::SceneCalibration;
+use crate::track::feature_class::FeatureClass;
+use crate::track::merge_history::{MergeHistory, MergeHistoryOptions};
 use crate::track::notify::{ChangeNotifier, NoopNotifier};
+use crate::track::projection::FeatureProjections;
 use crate::Errors;
 use anyhow::Result;
 use itertools::Itertools;
@@ -6,10 +10,25 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 use std::marker::PhantomData;
 use std::mem::take;
+use std::sync::Arc;
 use ultraviolet::f32x8;
 
 pub mod builder;
+/// Per-scene calibrations shared by the metrics and the trackers
+///
+pub mod calibration;
+pub mod feature_class;
+/// Combinators of the lookup requests
+///
+pub mod lookup;
+/// Bounded history of the merges of the tracks
+///
+pub mod merge_history;
 pub mod notify;
+pub mod projection;
+/// Serializable states of the tracks used to save and restore the stores
+///
+pub mod state;
 pub mod store;
 pub mod utils;
 pub mod voting;
@@ -54,6 +73,11 @@ where
 ///
 pub type Feature = Vec<f32x8>;
 
+/// Validity mask of the feature vector lanes: `1.0` marks the valid lane, `0.0` marks the masked
+/// one. The lanes beyond the mask length are masked.
+///
+pub type FeatureMask = Vec<f32x8>;
+
 /// Number of SIMD lanes used to store observation parts internally
 const FEATURE_LANES_SIZE: usize = 8;
 
@@ -112,6 +136,34 @@ pub type ObservationsDb<T> = HashMap<u64, Vec<Observation<T>>>;
 pub trait ObservationAttributes: Send + Sync + Clone + 'static {
     type MetricObject: Debug + Send + Sync + Clone + 'static;
     fn calculate_metric_object(l: &Option<&Self>, r: &Option<&Self>) -> Option<Self::MetricObject>;
+
+    /// Checks that the metric object is not a number, such distances are handled according to the
+    /// [NanDistancePolicy](crate::track::store::nan_distances::NanDistancePolicy) of the store
+    ///
+    fn metric_object_is_nan(_o: &Self::MetricObject) -> bool {
+        false
+    }
+
+    /// The stable name of the attributes reported by the [store descriptor](crate::store::descriptor::StoreDescriptor),
+    /// the stores are compatible only if their attributes have the same name. The empty name
+    /// is reported by the attributes which don't declare it.
+    ///
+    fn schema_name() -> &'static str
+    where
+        Self: Sized,
+    {
+        ""
+    }
+
+    /// The version of the attributes layout reported by the [store descriptor](crate::store::descriptor::StoreDescriptor),
+    /// increase it when the attributes become incompatible with the previous ones.
+    ///
+    fn schema_version() -> u32
+    where
+        Self: Sized,
+    {
+        0
+    }
 }
 
 /// Output result type used by metric when pairwise metric is calculated
@@ -144,6 +196,15 @@ pub struct MetricQuery<'a, TA, OA: ObservationAttributes> {
     pub track_observation: &'a Observation<OA>,
 }
 
+impl<'a, TA: TrackAttributes<TA, OA>, OA: ObservationAttributes> MetricQuery<'a, TA, OA> {
+    /// The calibration of the scene of the store track, the metric may use it to measure the
+    /// distances on the ground plane or in meters
+    ///
+    pub fn scene_calibration(&self) -> Option<Arc<SceneCalibration>> {
+        self.track_attrs.scene_calibration()
+    }
+}
+
 /// The trait that implements the methods for observations comparison, optimization and filtering.
 ///
 /// This is the one of the most important elements of the track. It defines how track distances are
@@ -163,7 +224,8 @@ pub trait ObservationMetric<TA, OA: ObservationAttributes>: Send + Sync + Clone
     /// # Arguments
     ///
     /// * `feature_class` - the feature class
-    /// * `merge_history` - the vector of track identifiers collected upon every merge
+    /// * `merge_history` - the vector of track identifiers collected upon every merge, only the most
+    ///   recent ones when the [history is bounded](crate::track::merge_history::MergeHistoryOptions)
     /// * `attributes` - mutable track attributes that can be updated or read during optimization
     /// * `observations` - observations to optimize
     /// * `prev_length` - previous length of observations (before the current observation was added or merge occurred)
@@ -194,6 +256,27 @@ pub trait ObservationMetric<TA, OA: ObservationAttributes>: Send + Sync + Clone
     ) -> Vec<ObservationMetricOk<OA>> {
         unfiltered
     }
+
+    /// The stable name of the metric reported by the [store descriptor](crate::store::descriptor::StoreDescriptor),
+    /// the stores are compatible only if their metrics have the same name. The empty name is
+    /// reported by the metrics which don't declare it.
+    ///
+    fn schema_name() -> &'static str
+    where
+        Self: Sized,
+    {
+        ""
+    }
+
+    /// The version of the metric reported by the [store descriptor](crate::store::descriptor::StoreDescriptor),
+    /// increase it when the distances become incomparable with the previous ones.
+    ///
+    fn schema_version() -> u32
+    where
+        Self: Sized,
+    {
+        0
+    }
 }
 
 /// Enum which specifies the status of feature tracks in storage. When the feature tracks are collected,
@@ -320,6 +403,69 @@ pub trait TrackAttributes<TA: TrackAttributes<TA, OA>, OA: ObservationAttributes
     ///          `now - end_timestamp > 30s` (no features collected during the last 30 seconds).
     ///
     fn baked(&self, observations: &ObservationsDb<OA>) -> Result<TrackStatus>;
+
+    /// The method is called by the trackers for the live tracks of the scene when the epoch of the
+    /// scene advances, regardless the tracks are updated within the epoch or not, if the
+    /// [epoch notifications](crate::trackers::epoch_db::EpochDb::epoch_notifications) are enabled.
+    /// It allows implementing the attributes that change with time, e.g. decay or aging. When the
+    /// epochs are skipped, the method is called once with the last skipped epoch.
+    ///
+    /// # Parameters
+    /// * `epoch` - the new epoch of the scene
+    /// * `scene_id` - the scene which epoch is advanced; [TrackStore::on_epoch](crate::store::TrackStore::on_epoch)
+    ///   notifies the tracks of the other scenes as well
+    ///
+    fn on_epoch(&mut self, _epoch: usize, _scene_id: u64) {}
+
+    /// The method is called by the trackers when the track is moved to the wasted tracks. It allows
+    /// recording when the track ended.
+    ///
+    fn on_wasted(&mut self) {}
+
+    /// The calibration of the scene the track belongs to, `None` when the attributes don't keep
+    /// the calibrations or the scene is not calibrated.
+    ///
+    fn scene_calibration(&self) -> Option<Arc<SceneCalibration>> {
+        None
+    }
+
+    /// The method is used when the track is split (forked) in two, e.g. when the track turns out
+    /// to follow two objects that moved together. The method defines which attributes the new
+    /// track inherits, `self` stays with the original track and may be changed as well.
+    ///
+    /// The default implementation clones the attributes, so both tracks inherit everything.
+    ///
+    /// # Returns
+    /// the attributes of the new track
+    ///
+    fn split(&mut self) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(self.clone())
+    }
+
+    /// The stable name of the attributes reported by the [store descriptor](crate::store::descriptor::StoreDescriptor),
+    /// the stores are compatible only if their attributes have the same name. The empty name
+    /// is reported by the attributes which don't declare it.
+    ///
+    fn schema_name() -> &'static str
+    where
+        Self: Sized,
+    {
+        ""
+    }
+
+    /// The version of the attributes layout reported by the [store descriptor](crate::store::descriptor::StoreDescriptor),
+    /// increase it when the attributes become incompatible with the previous ones, so the stores
+    /// of the different versions are not mixed.
+    ///
+    fn schema_version() -> u32
+    where
+        Self: Sized,
+    {
+        0
+    }
 }
 
 /// The attribute update information that is sent with new features to the track is represented by the trait.
@@ -352,7 +498,7 @@ where
     track_id: u64,
     observations: ObservationsDb<OA>,
     metric: M,
-    merge_history: Vec<u64>,
+    merge_history: MergeHistory,
     notifier: N,
 }
 
@@ -376,7 +522,7 @@ where
             track_id,
             metric,
             observations: ObservationsDb::default(),
-            merge_history: vec![track_id],
+            merge_history: MergeHistory::new(track_id),
         };
         v.notifier.send(track_id);
         v
@@ -402,29 +548,90 @@ where
         &self.attributes
     }
 
-    pub fn get_observations(&self, feature_class: u64) -> Option<&Vec<Observation<OA>>> {
-        self.observations.get(&feature_class)
+    /// Notifies the track attributes that the epoch of the scene advanced
+    ///
+    pub fn on_epoch(&mut self, epoch: usize, scene_id: u64) {
+        self.attributes.on_epoch(epoch, scene_id);
+    }
+
+    /// Notifies the track attributes that the track is wasted
+    ///
+    pub fn on_wasted(&mut self) {
+        self.attributes.on_wasted();
+    }
+
+    pub fn get_observations(
+        &self,
+        feature_class: impl FeatureClass,
+    ) -> Option<&Vec<Observation<OA>>> {
+        self.observations.get(&feature_class.class_id())
     }
 
     pub fn get_mut_observations(
         &mut self,
-        feature_class: u64,
+        feature_class: impl FeatureClass,
     ) -> Option<&mut Vec<Observation<OA>>> {
-        self.observations.get_mut(&feature_class)
+        self.observations.get_mut(&feature_class.class_id())
     }
 
     /// Returns the current track merge history for the track
     ///
     pub fn get_merge_history(&self) -> &Vec<u64> {
+        self.merge_history.ids()
+    }
+
+    /// Returns the merge history with the number of the merges and the evicted ids
+    ///
+    pub fn get_merge_history_summary(&self) -> &MergeHistory {
         &self.merge_history
     }
 
+    /// Bounds the merge history, the ids beyond the limit are evicted
+    ///
+    pub fn set_merge_history_options(&mut self, options: Option<MergeHistoryOptions>) {
+        self.merge_history.configure(options);
+    }
+
     /// Returns all classes present
     ///
     pub fn get_feature_classes(&self) -> Vec<u64> {
         self.observations.keys().cloned().collect()
     }
 
+    /// Returns all classes present, mapped to the user-defined classes. The raw classes missing in
+    /// the class registry are skipped.
+    ///
+    pub fn get_typed_feature_classes<C: FeatureClass>(&self) -> Vec<C> {
+        self.observations
+            .keys()
+            .flat_map(|c| C::from_class_id(*c))
+            .collect()
+    }
+
+    /// Maps the features of the observations to the canonical dimensions of their classes.
+    ///
+    /// The track is left unchanged if any of the features cannot be projected.
+    ///
+    pub fn project_features(&mut self, projections: &FeatureProjections) -> Result<()> {
+        let mut projected = Vec::default();
+        for (feature_class, observations) in &self.observations {
+            if let Some(projection) = projections.get(feature_class) {
+                let observations = observations
+                    .iter()
+                    .map(|o| {
+                        Ok(Observation(
+                            o.0.clone(),
+                            o.1.as_ref().map(|f| projection.project(f)).transpose()?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                projected.push((*feature_class, observations));
+            }
+        }
+        self.observations.extend(projected);
+        Ok(())
+    }
+
     fn update_attributes(&mut self, update: &TA::Update) -> Result<()> {
         update.apply(&mut self.attributes)
     }
@@ -446,10 +653,32 @@ where
     ///
     pub fn add_observation(
         &mut self,
-        feature_class: u64,
+        feature_class: impl FeatureClass,
         feature_attributes: Option<OA>,
         feature: Option<Feature>,
         track_attributes_update: Option<TA::Update>,
+    ) -> Result<()> {
+        self.add_observations(
+            vec![(feature_class.class_id(), feature_attributes, feature)],
+            track_attributes_update,
+        )
+    }
+
+    /// Adds the observations of several feature classes produced by the same detection.
+    ///
+    /// The track attributes are updated once with `track_attributes_update`, then every
+    /// observation is placed into the features of its class and the features of the class are
+    /// optimized. The observations that have neither the feature nor the attributes are skipped.
+    /// When any step fails, the track is restored to the state it had before the call.
+    ///
+    /// # Arguments
+    /// * `observations` - the `(feature_class, feature_attributes, feature)` tuples
+    /// * `track_attributes_update` - attribute update message
+    ///
+    pub fn add_observations(
+        &mut self,
+        observations: Vec<(u64, Option<OA>, Option<Feature>)>,
+        track_attributes_update: Option<TA::Update>,
     ) -> Result<()> {
         let last_attributes = self.attributes.clone();
         let last_observations = self.observations.clone();
@@ -464,40 +693,31 @@ where
             }
         }
 
-        if feature.is_none() && feature_attributes.is_none() {
-            self.notifier.send(self.track_id);
-            return Ok(());
-        }
-
-        match self.observations.get_mut(&feature_class) {
-            None => {
-                self.observations.insert(
-                    feature_class,
-                    vec![Observation(feature_attributes, feature)],
-                );
+        for (feature_class, feature_attributes, feature) in observations {
+            if feature.is_none() && feature_attributes.is_none() {
+                continue;
             }
-            Some(observations) => {
-                observations.push(Observation(feature_attributes, feature));
+
+            let observations = self.observations.entry(feature_class).or_default();
+            observations.push(Observation(feature_attributes, feature));
+            let prev_length = observations.len() - 1;
+
+            let res = self.metric.optimize(
+                feature_class,
+                self.merge_history.ids(),
+                &mut self.attributes,
+                observations,
+                prev_length,
+                false,
+            );
+            if res.is_err() {
+                self.attributes = last_attributes;
+                self.observations = last_observations;
+                self.metric = last_metric;
+                res?;
+                unreachable!();
             }
         }
-        let observations = self.observations.get_mut(&feature_class).unwrap();
-        let prev_length = observations.len() - 1;
-
-        let res = self.metric.optimize(
-            feature_class,
-            &self.merge_history,
-            &mut self.attributes,
-            observations,
-            prev_length,
-            false,
-        );
-        if res.is_err() {
-            self.attributes = last_attributes;
-            self.observations = last_observations;
-            self.metric = last_metric;
-            res?;
-            unreachable!();
-        }
         self.notifier.send(self.track_id);
         Ok(())
     }
@@ -520,6 +740,19 @@ where
     /// * `merge_history` - defines add merged track id into self merge history or not
     ///
     pub fn merge(&mut self, other: &Self, classes: &[u64], merge_history: bool) -> Result<()> {
+        self.merge_silent(other, classes, merge_history)?;
+        self.notifier.send(self.track_id);
+        Ok(())
+    }
+
+    /// Merges the track without sending the change notification
+    ///
+    pub(crate) fn merge_silent(
+        &mut self,
+        other: &Self,
+        classes: &[u64],
+        merge_history: bool,
+    ) -> Result<()> {
         let last_attributes = self.attributes.clone();
         let res = self.attributes.merge(&other.attributes);
         if res.is_err() {
@@ -553,11 +786,9 @@ where
                 _ => None,
             };
             let merge_history = if merge_history {
-                self.merge_history
-                    .iter()
-                    .chain(other.merge_history.iter())
-                    .cloned()
-                    .collect::<Vec<_>>()
+                let mut history = self.merge_history.clone();
+                history.extend(&other.merge_history);
+                history
             } else {
                 take(&mut self.merge_history)
             };
@@ -565,7 +796,7 @@ where
             if let Some(prev_length) = prev_length {
                 let res = self.metric.optimize(
                     *cls,
-                    &merge_history,
+                    merge_history.ids(),
                     &mut self.attributes,
                     self.observations.get_mut(cls).unwrap(),
                     prev_length,
@@ -582,8 +813,6 @@ where
                 self.merge_history = merge_history;
             }
         }
-
-        self.notifier.send(self.track_id);
         Ok(())
     }
 
@@ -604,8 +833,23 @@ where
     pub fn distances(
         &self,
         other: &Self,
-        feature_class: u64,
+        feature_class: impl FeatureClass,
     ) -> Result<Vec<ObservationMetricOk<OA>>> {
+        let mut distances = Vec::new();
+        self.distances_into(other, feature_class, &mut distances)?;
+        Ok(distances)
+    }
+
+    /// Calculates the distances like [distances](Track::distances) does, but appends them to the
+    /// buffer, so the caller can reuse the memory across the calls.
+    ///
+    pub fn distances_into(
+        &self,
+        other: &Self,
+        feature_class: impl FeatureClass,
+        distances: &mut Vec<ObservationMetricOk<OA>>,
+    ) -> Result<()> {
+        let feature_class = feature_class.class_id();
         if !self.attributes.compatible(&other.attributes) {
             Err(Errors::IncompatibleAttributes.into())
         } else {
@@ -613,34 +857,35 @@ where
                 self.observations.get(&feature_class),
                 other.observations.get(&feature_class),
             ) {
-                (Some(left), Some(right)) => Ok(left
-                    .iter()
-                    .cartesian_product(right.iter())
-                    .flat_map(|(l, r)| {
-                        let mq = MetricQuery {
-                            feature_class,
-                            candidate_attrs: self.get_attributes(),
-                            candidate_observation: l,
-                            track_attrs: other.get_attributes(),
-                            track_observation: r,
-                        };
-
-                        // let (attribute_metric, feature_distance) = self.metric.new_metric(&mq)?;
-                        let (attribute_metric, feature_distance) = self.metric.metric(
-                            &mq, // feature_class,
-                                // self.get_attributes(),
-                                // other.get_attributes(),
-                                // l,
-                                // r,
-                        )?;
-                        Some(ObservationMetricOk {
-                            from: self.track_id,
-                            to: other.track_id,
-                            attribute_metric,
-                            feature_distance,
-                        })
-                    })
-                    .collect()),
+                (Some(left), Some(right)) => {
+                    distances.extend(left.iter().cartesian_product(right.iter()).flat_map(
+                        |(l, r)| {
+                            let mq = MetricQuery {
+                                feature_class,
+                                candidate_attrs: self.get_attributes(),
+                                candidate_observation: l,
+                                track_attrs: other.get_attributes(),
+                                track_observation: r,
+                            };
+
+                            // let (attribute_metric, feature_distance) = self.metric.new_metric(&mq)?;
+                            let (attribute_metric, feature_distance) = self.metric.metric(
+                                &mq, // feature_class,
+                                    // self.get_attributes(),
+                                    // other.get_attributes(),
+                                    // l,
+                                    // r,
+                            )?;
+                            Some(ObservationMetricOk {
+                                from: self.track_id,
+                                to: other.track_id,
+                                attribute_metric,
+                                feature_distance,
+                            })
+                        },
+                    ));
+                    Ok(())
+                }
                 _ => Err(Errors::ObservationForClassNotFound(
                     self.track_id,
                     other.track_id,
@@ -651,8 +896,12 @@ where
         }
     }
 
-    pub fn lookup(&self, query: &TA::Lookup) -> bool {
-        query.lookup(&self.attributes, &self.observations, &self.merge_history)
+    pub fn lookup<L: LookupRequest<TA, OA>>(&self, query: &L) -> bool {
+        query.lookup(
+            &self.attributes,
+            &self.observations,
+            self.merge_history.ids(),
+        )
     }
 }
 
@@ -1124,10 +1373,10 @@ mod tests {
 
         let mut track_with_merge_history = t1.clone();
         let _r = track_with_merge_history.merge(&t2, &[0], true);
-        assert_eq!(track_with_merge_history.merge_history, vec![0, 1]);
+        assert_eq!(track_with_merge_history.get_merge_history(), &vec![0, 1]);
 
         let _r = t1.merge(&t2, &[0], false);
-        assert_eq!(t1.merge_history, vec![0]);
+        assert_eq!(t1.get_merge_history(), &vec![0]);
 
         Ok(())
     }
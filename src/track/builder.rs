@@ -1,10 +1,15 @@
+use crate::track::feature_class::FeatureClass;
 use crate::track::notify::{ChangeNotifier, NoopNotifier};
 use crate::track::{Feature, ObservationAttributes, ObservationMetric, Track, TrackAttributes};
+use crate::Errors;
 use anyhow::Result;
 use rand::Rng;
 
 type TrackBuilderObservationRepr<OA, TAU> = (u64, Option<OA>, Option<Feature>, Option<TAU>);
 
+type TrackBuilderMultiObservationRepr<OA, TAU> =
+    (Vec<(u64, Option<OA>, Option<Feature>)>, Option<TAU>);
+
 /// Builder is used to build an observation
 ///
 pub struct ObservationBuilder<TAU, OA>
@@ -28,15 +33,36 @@ where
     /// # Parameters
     /// * `feature_cals` - feature class
     ///
-    pub fn new(feature_class: u64) -> Self {
+    pub fn new(feature_class: impl FeatureClass) -> Self {
         Self {
-            feature_class,
+            feature_class: feature_class.class_id(),
             observation_attributes: None,
             observation: None,
             track_attributes_update: None,
         }
     }
 
+    /// Constructor of the observations of several feature classes produced by the same detection,
+    /// e.g. the detection that carries both the face and the body descriptors
+    ///
+    /// The observations are added to the track with the single attributes update, see
+    /// [Track::add_observations](crate::track::Track::add_observations).
+    ///
+    /// # Parameters
+    /// * `observations` - the `(feature_class, observation_attributes, feature)` tuples
+    ///
+    pub fn new_multi<C: FeatureClass>(
+        observations: &[(C, Option<OA>, Option<Feature>)],
+    ) -> MultiObservationBuilder<TAU, OA> {
+        MultiObservationBuilder {
+            observations: observations
+                .iter()
+                .map(|(c, attrs, feature)| (c.class_id(), attrs.clone(), feature.clone()))
+                .collect(),
+            track_attributes_update: None,
+        }
+    }
+
     /// Sets observation custom attributes
     ///
     pub fn observation_attributes(mut self, attrs: OA) -> Self {
@@ -58,7 +84,10 @@ where
         self
     }
 
-    /// Builds observation tuple suitable for [TrackBuilder::observation](TrackBuilder::observation) method
+    /// Builds observation tuple suitable for [TrackBuilder::observation](TrackBuilder::observation) method.
+    ///
+    /// The observation is not validated, the malformed one is reported by
+    /// [TrackBuilder::build](TrackBuilder::build), use [try_build](Self::try_build) to get the error here.
     ///
     pub fn build(self) -> TrackBuilderObservationRepr<OA, TAU> {
         (
@@ -68,6 +97,74 @@ where
             self.track_attributes_update,
         )
     }
+
+    /// Builds observation tuple, the observation must have either the feature or the attributes
+    ///
+    pub fn try_build(self) -> Result<TrackBuilderObservationRepr<OA, TAU>> {
+        let observation = self.build();
+        validate(&observation)?;
+        Ok(observation)
+    }
+}
+
+/// Builder is used to build the observations of several feature classes sharing the track
+/// attributes update, created with [ObservationBuilder::new_multi](ObservationBuilder::new_multi)
+///
+pub struct MultiObservationBuilder<TAU, OA>
+where
+    OA: ObservationAttributes,
+{
+    observations: Vec<(u64, Option<OA>, Option<Feature>)>,
+    track_attributes_update: Option<TAU>,
+}
+
+impl<TAU, OA> MultiObservationBuilder<TAU, OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Sets the track attributes update, applied once for all the observations
+    ///
+    pub fn track_attributes_update(mut self, upd: TAU) -> Self {
+        self.track_attributes_update = Some(upd);
+        self
+    }
+
+    /// Builds observations tuple suitable for [TrackBuilder::observations](TrackBuilder::observations) method.
+    ///
+    pub fn build(self) -> TrackBuilderMultiObservationRepr<OA, TAU> {
+        (self.observations, self.track_attributes_update)
+    }
+
+    /// Builds observations tuple, every observation must have either the feature or the attributes
+    ///
+    pub fn try_build(self) -> Result<TrackBuilderMultiObservationRepr<OA, TAU>> {
+        let observations = self.build();
+        validate_multi(&observations)?;
+        Ok(observations)
+    }
+}
+
+fn validate<OA, TAU>(observation: &TrackBuilderObservationRepr<OA, TAU>) -> Result<()> {
+    let (feature_class, observation_attributes, observation, _) = observation;
+    if observation_attributes.is_none() && observation.is_none() {
+        return Err(Errors::MalformedObservation(format!(
+            "the observation of class={feature_class} has neither feature nor attributes"
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+fn validate_multi<OA, TAU>(observations: &TrackBuilderMultiObservationRepr<OA, TAU>) -> Result<()> {
+    for (feature_class, observation_attributes, observation) in &observations.0 {
+        if observation_attributes.is_none() && observation.is_none() {
+            return Err(Errors::MalformedObservation(format!(
+                "the observation of class={feature_class} has neither feature nor attributes"
+            ))
+            .into());
+        }
+    }
+    Ok(())
 }
 
 /// Builder object for Track
@@ -83,7 +180,7 @@ where
     track_attrs: Option<TA>,
     metric: Option<M>,
     notifier: Option<N>,
-    observations: Vec<TrackBuilderObservationRepr<OA, TA::Update>>,
+    observations: Vec<TrackBuilderMultiObservationRepr<OA, TA::Update>>,
 }
 
 impl<TA, M, OA, N> Default for TrackBuilder<TA, M, OA, N>
@@ -157,23 +254,42 @@ where
         let (feature_class, observation_attributes, observation, track_attributes_update) =
             observation;
         self.observations.push((
-            feature_class,
-            observation_attributes,
-            observation,
+            vec![(feature_class, observation_attributes, observation)],
             track_attributes_update,
         ));
         self
     }
 
+    /// Sets the observations of several feature classes sharing the attributes update. The method can be
+    /// called multiple times.
+    ///
+    /// # Parameters
+    /// * `observations` is the tuple produced by [MultiObservationBuilder](MultiObservationBuilder)
+    ///
+    pub fn observations(
+        mut self,
+        observations: TrackBuilderMultiObservationRepr<OA, TA::Update>,
+    ) -> Self {
+        self.observations.push(observations);
+        self
+    }
+
+    /// Builds the track, the observations that have neither the feature nor the attributes and
+    /// the ones rejected by the metric are reported with
+    /// [MalformedObservation](crate::Errors::MalformedObservation) error
+    ///
     pub fn build(self) -> Result<Track<TA, M, OA, N>> {
+        for observations in &self.observations {
+            validate_multi(observations)?;
+        }
         let mut track = Track::new(
             self.id,
             self.metric.unwrap(),
             self.track_attrs.unwrap(),
             self.notifier.unwrap(),
         );
-        for (cls, oa, feat, upd) in self.observations {
-            track.add_observation(cls, oa, feat, upd)?;
+        for (observations, upd) in self.observations {
+            track.add_observations(observations, upd)?;
         }
         Ok(track)
     }
@@ -186,6 +302,7 @@ mod tests {
     use crate::track::notify::NoopNotifier;
     use crate::track::utils::FromVec;
     use crate::track::Feature;
+    use crate::Errors;
     use anyhow::Result;
 
     #[test]
@@ -223,4 +340,30 @@ mod tests {
         assert!(track.get_track_id() > 0);
         Ok(())
     }
+
+    #[test]
+    fn malformed_observation() {
+        let observation = ObservationBuilder::<UnboundAttributeUpdate, f32>::new(3)
+            .track_attributes_update(UnboundAttributeUpdate);
+        match observation.try_build() {
+            Err(e) => assert!(matches!(
+                e.downcast_ref::<Errors>(),
+                Some(Errors::MalformedObservation(_))
+            )),
+            Ok(_) => unreachable!(),
+        }
+
+        let res = TrackBuilder::new(10)
+            .notifier(NoopNotifier)
+            .metric(UnboundMetric)
+            .attributes(UnboundAttrs)
+            .observation(
+                ObservationBuilder::new(0)
+                    .observation_attributes(0.1)
+                    .build(),
+            )
+            .observation(ObservationBuilder::new(1).build())
+            .build();
+        assert!(res.is_err());
+    }
 }
@@ -0,0 +1,348 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The polygon defined by the list of its vertices
+///
+pub type Vertices = Vec<(f32, f32)>;
+
+/// Calibration of the scene (camera).
+///
+/// The calibration keeps the scene-scoped configuration in one place: the size of the frame, the
+/// homography that maps the image onto the ground plane, the scale of the scene, the region of
+/// interest and the entry and exit zones. The calibration is registered once on the tracker and is
+/// available to the metrics with [MetricQuery::scene_calibration](crate::track::MetricQuery::scene_calibration)
+/// and to the track attributes with [TrackAttributes::scene_calibration](crate::track::TrackAttributes::scene_calibration).
+///
+/// The fields left unset are unknown for the scene, the helpers return `None` for them.
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneCalibration {
+    frame_size: Option<(f32, f32)>,
+    homography: Option<[[f64; 3]; 3]>,
+    pixels_per_meter: Option<f32>,
+    roi: Option<Vertices>,
+    entry_zones: Vec<Vertices>,
+    exit_zones: Vec<Vertices>,
+}
+
+fn check_polygon(vertices: &[(f32, f32)]) {
+    assert!(
+        vertices.len() >= 3,
+        "The zone must be defined by at least 3 vertices"
+    );
+}
+
+/// Checks if the point lies within the polygon with the even-odd rule
+///
+fn polygon_contains(vertices: &[(f32, f32)], x: f32, y: f32) -> bool {
+    let mut inside = false;
+    let mut j = vertices.len() - 1;
+    for (i, &(xi, yi)) in vertices.iter().enumerate() {
+        let (xj, yj) = vertices[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+impl SceneCalibration {
+    /// Sets the width and the height of the frame
+    ///
+    pub fn frame_size(mut self, width: f32, height: f32) -> Self {
+        assert!(
+            width > 0.0 && height > 0.0,
+            "The frame sizes must be positive numbers"
+        );
+        self.frame_size = Some((width, height));
+        self
+    }
+
+    /// Sets the row-major homography matrix that maps the image coordinates onto the ground plane
+    ///
+    pub fn homography(mut self, matrix: [[f64; 3]; 3]) -> Self {
+        self.homography = Some(matrix);
+        self
+    }
+
+    /// Sets the scale of the scene
+    ///
+    pub fn pixels_per_meter(mut self, pixels_per_meter: f32) -> Self {
+        assert!(
+            pixels_per_meter > 0.0,
+            "The scale must be a positive number"
+        );
+        self.pixels_per_meter = Some(pixels_per_meter);
+        self
+    }
+
+    /// Sets the region of interest of the scene
+    ///
+    pub fn roi(mut self, vertices: &[(f32, f32)]) -> Self {
+        check_polygon(vertices);
+        self.roi = Some(vertices.to_vec());
+        self
+    }
+
+    /// Adds the entry zone of the scene
+    ///
+    pub fn entry_zone(mut self, vertices: &[(f32, f32)]) -> Self {
+        check_polygon(vertices);
+        self.entry_zones.push(vertices.to_vec());
+        self
+    }
+
+    /// Adds the exit zone of the scene
+    ///
+    pub fn exit_zone(mut self, vertices: &[(f32, f32)]) -> Self {
+        check_polygon(vertices);
+        self.exit_zones.push(vertices.to_vec());
+        self
+    }
+
+    pub fn get_frame_size(&self) -> Option<(f32, f32)> {
+        self.frame_size
+    }
+
+    pub fn get_homography(&self) -> Option<[[f64; 3]; 3]> {
+        self.homography
+    }
+
+    pub fn get_pixels_per_meter(&self) -> Option<f32> {
+        self.pixels_per_meter
+    }
+
+    pub fn get_roi(&self) -> Option<&[(f32, f32)]> {
+        self.roi.as_deref()
+    }
+
+    pub fn get_entry_zones(&self) -> &[Vertices] {
+        &self.entry_zones
+    }
+
+    pub fn get_exit_zones(&self) -> &[Vertices] {
+        &self.exit_zones
+    }
+
+    /// Maps the image point onto the ground plane
+    ///
+    /// # Returns
+    /// the ground plane point, `None` if the homography is unknown or the point is mapped to infinity
+    ///
+    pub fn to_ground(&self, x: f32, y: f32) -> Option<(f64, f64)> {
+        let h = self.homography.as_ref()?;
+        let (x, y) = (x as f64, y as f64);
+        let w = h[2][0] * x + h[2][1] * y + h[2][2];
+        if w.abs() < f64::EPSILON {
+            return None;
+        }
+        Some((
+            (h[0][0] * x + h[0][1] * y + h[0][2]) / w,
+            (h[1][0] * x + h[1][1] * y + h[1][2]) / w,
+        ))
+    }
+
+    /// Converts the distance in pixels to meters, `None` if the scale is unknown
+    ///
+    pub fn to_meters(&self, pixels: f32) -> Option<f32> {
+        self.pixels_per_meter.map(|ppm| pixels / ppm)
+    }
+
+    /// Checks if the point lies within the frame, the points of the scene without the frame size
+    /// are always within
+    ///
+    pub fn in_frame(&self, x: f32, y: f32) -> bool {
+        self.frame_size
+            .map(|(w, h)| (0.0..=w).contains(&x) && (0.0..=h).contains(&y))
+            .unwrap_or(true)
+    }
+
+    /// Checks if the point lies within the region of interest, the points of the scene without the
+    /// region are always within
+    ///
+    pub fn in_roi(&self, x: f32, y: f32) -> bool {
+        self.roi
+            .as_ref()
+            .map(|roi| polygon_contains(roi, x, y))
+            .unwrap_or(true)
+    }
+}
+
+/// Calibrations of the scenes.
+///
+/// The calibrations are kept behind `Arc`, so the metrics can hold the calibration of the scene
+/// without copying it.
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CalibrationRegistry {
+    #[serde(serialize_with = "serialize_scenes")]
+    #[serde(deserialize_with = "deserialize_scenes")]
+    scenes: HashMap<u64, Arc<SceneCalibration>>,
+}
+
+fn serialize_scenes<S: Serializer>(
+    scenes: &HashMap<u64, Arc<SceneCalibration>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    scenes
+        .iter()
+        .map(|(scene_id, c)| (*scene_id, c.as_ref()))
+        .collect::<HashMap<_, _>>()
+        .serialize(serializer)
+}
+
+fn deserialize_scenes<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<u64, Arc<SceneCalibration>>, D::Error> {
+    Ok(HashMap::<u64, SceneCalibration>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(scene_id, c)| (scene_id, Arc::new(c)))
+        .collect())
+}
+
+impl CalibrationRegistry {
+    /// Registers the calibration of the scene, the previous calibration is replaced
+    ///
+    pub fn set(&mut self, scene_id: u64, calibration: SceneCalibration) {
+        self.scenes.insert(scene_id, Arc::new(calibration));
+    }
+
+    /// Removes the calibration of the scene
+    ///
+    pub fn remove(&mut self, scene_id: u64) -> Option<Arc<SceneCalibration>> {
+        self.scenes.remove(&scene_id)
+    }
+
+    pub fn get(&self, scene_id: u64) -> Option<Arc<SceneCalibration>> {
+        self.scenes.get(&scene_id).cloned()
+    }
+
+    /// The scenes with the registered calibrations
+    ///
+    pub fn scenes(&self) -> Vec<u64> {
+        self.scenes.keys().copied().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::SceneCalibration;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "SceneCalibration")]
+    #[derive(Debug, Clone, Default)]
+    pub struct PySceneCalibration(pub(crate) SceneCalibration);
+
+    #[pymethods]
+    impl PySceneCalibration {
+        #[new]
+        pub(crate) fn new() -> Self {
+            Self::default()
+        }
+
+        #[pyo3(text_signature = "($self, width, height)")]
+        pub(crate) fn frame_size(&mut self, width: f32, height: f32) {
+            self.0 = self.0.clone().frame_size(width, height);
+        }
+
+        #[pyo3(text_signature = "($self, matrix)")]
+        pub(crate) fn homography(&mut self, matrix: [[f64; 3]; 3]) {
+            self.0 = self.0.clone().homography(matrix);
+        }
+
+        #[pyo3(text_signature = "($self, pixels_per_meter)")]
+        pub(crate) fn pixels_per_meter(&mut self, pixels_per_meter: f32) {
+            self.0 = self.0.clone().pixels_per_meter(pixels_per_meter);
+        }
+
+        #[pyo3(text_signature = "($self, vertices)")]
+        pub(crate) fn roi(&mut self, vertices: Vec<(f32, f32)>) {
+            self.0 = self.0.clone().roi(&vertices);
+        }
+
+        #[pyo3(text_signature = "($self, vertices)")]
+        pub(crate) fn entry_zone(&mut self, vertices: Vec<(f32, f32)>) {
+            self.0 = self.0.clone().entry_zone(&vertices);
+        }
+
+        #[pyo3(text_signature = "($self, vertices)")]
+        pub(crate) fn exit_zone(&mut self, vertices: Vec<(f32, f32)>) {
+            self.0 = self.0.clone().exit_zone(&vertices);
+        }
+
+        #[pyo3(text_signature = "($self, x, y)")]
+        fn to_ground(&self, x: f32, y: f32) -> Option<(f64, f64)> {
+            self.0.to_ground(x, y)
+        }
+
+        #[pyo3(text_signature = "($self, pixels)")]
+        fn to_meters(&self, pixels: f32) -> Option<f32> {
+            self.0.to_meters(pixels)
+        }
+
+        #[pyo3(text_signature = "($self, x, y)")]
+        fn in_roi(&self, x: f32, y: f32) -> bool {
+            self.0.in_roi(x, y)
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::calibration::{CalibrationRegistry, SceneCalibration};
+
+    #[test]
+    fn calibration() {
+        let calibration = SceneCalibration::default()
+            .frame_size(100.0, 50.0)
+            .homography([[2.0, 0.0, 1.0], [0.0, 2.0, -1.0], [0.0, 0.0, 1.0]])
+            .pixels_per_meter(20.0)
+            .roi(&[(0.0, 0.0), (40.0, 0.0), (40.0, 40.0), (0.0, 40.0)]);
+
+        assert_eq!(calibration.to_ground(1.0, 2.0), Some((3.0, 3.0)));
+        assert_eq!(calibration.to_meters(10.0), Some(0.5));
+        assert!(calibration.in_frame(100.0, 50.0));
+        assert!(!calibration.in_frame(100.0, 51.0));
+        assert!(calibration.in_roi(10.0, 10.0));
+        assert!(!calibration.in_roi(50.0, 10.0));
+
+        let unknown = SceneCalibration::default();
+        assert!(unknown.to_ground(1.0, 2.0).is_none());
+        assert!(unknown.to_meters(10.0).is_none());
+        assert!(unknown.in_roi(1000.0, 1000.0));
+    }
+
+    #[test]
+    fn registry_serde() {
+        let mut registry = CalibrationRegistry::default();
+        registry.set(
+            1,
+            SceneCalibration::default()
+                .pixels_per_meter(10.0)
+                .entry_zone(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]),
+        );
+        let json = serde_json::to_string(&registry).unwrap();
+        let restored: CalibrationRegistry = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.get(1), registry.get(1));
+        assert!(restored.get(2).is_none());
+        assert_eq!(restored.scenes(), vec![1]);
+    }
+}
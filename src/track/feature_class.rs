@@ -0,0 +1,199 @@
+/// Key that identifies the class of observations within the track.
+///
+/// The tracks keep observations by `u64` class id, however user code may define an enum for the
+/// classes in use, so the classes are named at the call site and can't be confused between
+/// galleries. The trait is implemented for `u64`, so the raw ids are still accepted everywhere.
+///
+pub trait FeatureClass: Copy + Send + Sync + 'static {
+    /// Raw class id used within the track
+    ///
+    fn class_id(self) -> u64;
+
+    /// All the classes known for the type, used to map raw class ids back to the classes
+    ///
+    fn registry() -> &'static [Self];
+
+    /// Maps the raw class id to the class
+    ///
+    fn from_class_id(class_id: u64) -> Option<Self> {
+        Self::registry()
+            .iter()
+            .find(|c| c.class_id() == class_id)
+            .copied()
+    }
+}
+
+impl FeatureClass for u64 {
+    fn class_id(self) -> u64 {
+        self
+    }
+
+    fn registry() -> &'static [Self] {
+        &[]
+    }
+
+    fn from_class_id(class_id: u64) -> Option<Self> {
+        Some(class_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::{FeatureClass, NoopNotifier, ObservationBuilder, TrackBuilder};
+    use crate::track::utils::FromVec;
+    use crate::track::{
+        Feature, MetricOutput, MetricQuery, NoopLookup, Observation, ObservationAttributes,
+        ObservationMetric, ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackStatus,
+    };
+    use anyhow::Result;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Gallery {
+        Face,
+        Body,
+    }
+
+    impl FeatureClass for Gallery {
+        fn class_id(self) -> u64 {
+            self as u64
+        }
+
+        fn registry() -> &'static [Self] {
+            &[Gallery::Face, Gallery::Body]
+        }
+    }
+
+    #[derive(Default, Clone)]
+    struct Attrs {
+        updates: usize,
+    }
+
+    #[derive(Default, Clone)]
+    struct Update;
+
+    impl TrackAttributesUpdate<Attrs> for Update {
+        fn apply(&self, attrs: &mut Attrs) -> Result<()> {
+            attrs.updates += 1;
+            Ok(())
+        }
+    }
+
+    impl TrackAttributes<Attrs, f32> for Attrs {
+        type Update = Update;
+        type Lookup = NoopLookup<Attrs, f32>;
+
+        fn compatible(&self, _other: &Attrs) -> bool {
+            true
+        }
+
+        fn merge(&mut self, _other: &Attrs) -> Result<()> {
+            Ok(())
+        }
+
+        fn baked(&self, _observations: &ObservationsDb<f32>) -> Result<TrackStatus> {
+            Ok(TrackStatus::Pending)
+        }
+    }
+
+    #[derive(Default, Clone)]
+    struct Metric;
+
+    impl ObservationMetric<Attrs, f32> for Metric {
+        fn metric(&self, mq: &MetricQuery<'_, Attrs, f32>) -> MetricOutput<f32> {
+            let (e1, e2) = (mq.candidate_observation, mq.track_observation);
+            Some((
+                f32::calculate_metric_object(&e1.attr().as_ref(), &e2.attr().as_ref()),
+                None,
+            ))
+        }
+
+        fn optimize(
+            &mut self,
+            _feature_class: u64,
+            _merge_history: &[u64],
+            _attrs: &mut Attrs,
+            _features: &mut Vec<Observation<f32>>,
+            _prev_length: usize,
+            _is_merge: bool,
+        ) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn registry() {
+        assert_eq!(Gallery::Body.class_id(), 1);
+        assert_eq!(Gallery::from_class_id(0), Some(Gallery::Face));
+        assert_eq!(Gallery::from_class_id(2), None);
+        assert_eq!(u64::from_class_id(2), Some(2));
+    }
+
+    #[test]
+    fn typed_classes() -> Result<()> {
+        let mut track = TrackBuilder::new(1)
+            .metric(Metric)
+            .attributes(Attrs::default())
+            .notifier(NoopNotifier)
+            .observation(
+                ObservationBuilder::new(Gallery::Body)
+                    .observation_attributes(0.5)
+                    .observation(Feature::from_vec(vec![1.0, 0.0]))
+                    .build(),
+            )
+            .build()?;
+        track.add_observation(Gallery::Face, Some(0.3), None, None)?;
+        track.add_observation(5, Some(0.3), None, None)?;
+
+        assert_eq!(track.get_observations(Gallery::Body).unwrap().len(), 1);
+        assert_eq!(track.get_observations(1).unwrap().len(), 1);
+        let mut classes = track.get_typed_feature_classes::<Gallery>();
+        classes.sort_by_key(|c| c.class_id());
+        assert_eq!(classes, vec![Gallery::Face, Gallery::Body]);
+        assert_eq!(track.get_feature_classes().len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn multi_class_observation() -> Result<()> {
+        let mut track = TrackBuilder::new(1)
+            .metric(Metric)
+            .attributes(Attrs::default())
+            .notifier(NoopNotifier)
+            .observations(
+                ObservationBuilder::new_multi(&[
+                    (
+                        Gallery::Face,
+                        Some(0.9),
+                        Some(Feature::from_vec(vec![1.0, 0.0])),
+                    ),
+                    (
+                        Gallery::Body,
+                        Some(0.5),
+                        Some(Feature::from_vec(vec![0.0, 1.0])),
+                    ),
+                ])
+                .track_attributes_update(Update)
+                .build(),
+            )
+            .build()?;
+        assert_eq!(track.get_attributes().updates, 1);
+        assert_eq!(track.get_observations(Gallery::Face).unwrap().len(), 1);
+        assert_eq!(track.get_observations(Gallery::Body).unwrap().len(), 1);
+
+        track.add_observations(
+            vec![(Gallery::Body.class_id(), Some(0.4), None), (7, None, None)],
+            Some(Update),
+        )?;
+        assert_eq!(track.get_attributes().updates, 2);
+        assert_eq!(track.get_observations(Gallery::Body).unwrap().len(), 2);
+        assert!(track.get_observations(7).is_none());
+
+        let malformed = ObservationBuilder::<Update, f32>::new_multi(&[
+            (Gallery::Face, Some(0.9), None),
+            (Gallery::Body, None, None),
+        ])
+        .try_build();
+        assert!(malformed.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use crate::track::{LookupRequest, ObservationAttributes, ObservationsDb, TrackAttributes};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// The lookup matching the tracks matched by both lookups
+///
+#[derive(Debug, Clone)]
+pub struct And<L1, L2>(pub L1, pub L2);
+
+/// The lookup matching the tracks matched by any of the lookups
+///
+#[derive(Debug, Clone)]
+pub struct Or<L1, L2>(pub L1, pub L2);
+
+/// The lookup matching the tracks not matched by the lookup
+///
+#[derive(Debug, Clone)]
+pub struct Not<L>(pub L);
+
+impl<TA, OA, L1, L2> LookupRequest<TA, OA> for And<L1, L2>
+where
+    TA: TrackAttributes<TA, OA>,
+    OA: ObservationAttributes,
+    L1: LookupRequest<TA, OA>,
+    L2: LookupRequest<TA, OA>,
+{
+    fn lookup(
+        &self,
+        attributes: &TA,
+        observations: &ObservationsDb<OA>,
+        merge_history: &[u64],
+    ) -> bool {
+        self.0.lookup(attributes, observations, merge_history)
+            && self.1.lookup(attributes, observations, merge_history)
+    }
+}
+
+impl<TA, OA, L1, L2> LookupRequest<TA, OA> for Or<L1, L2>
+where
+    TA: TrackAttributes<TA, OA>,
+    OA: ObservationAttributes,
+    L1: LookupRequest<TA, OA>,
+    L2: LookupRequest<TA, OA>,
+{
+    fn lookup(
+        &self,
+        attributes: &TA,
+        observations: &ObservationsDb<OA>,
+        merge_history: &[u64],
+    ) -> bool {
+        self.0.lookup(attributes, observations, merge_history)
+            || self.1.lookup(attributes, observations, merge_history)
+    }
+}
+
+impl<TA, OA, L> LookupRequest<TA, OA> for Not<L>
+where
+    TA: TrackAttributes<TA, OA>,
+    OA: ObservationAttributes,
+    L: LookupRequest<TA, OA>,
+{
+    fn lookup(
+        &self,
+        attributes: &TA,
+        observations: &ObservationsDb<OA>,
+        merge_history: &[u64],
+    ) -> bool {
+        !self.0.lookup(attributes, observations, merge_history)
+    }
+}
+
+/// The lookup defined by the closure over the attributes, the observations and the merge history
+/// of the track, look at [lookup_fn]
+///
+pub struct LookupFn<TA, OA, F: ?Sized> {
+    f: Arc<F>,
+    _attributes: PhantomData<fn(&TA, &OA)>,
+}
+
+impl<TA, OA, F: ?Sized> Clone for LookupFn<TA, OA, F> {
+    fn clone(&self) -> Self {
+        Self {
+            f: self.f.clone(),
+            _attributes: PhantomData,
+        }
+    }
+}
+
+impl<TA, OA, F> LookupRequest<TA, OA> for LookupFn<TA, OA, F>
+where
+    TA: TrackAttributes<TA, OA>,
+    OA: ObservationAttributes,
+    F: Fn(&TA, &ObservationsDb<OA>, &[u64]) -> bool + Send + Sync + ?Sized + 'static,
+{
+    fn lookup(
+        &self,
+        attributes: &TA,
+        observations: &ObservationsDb<OA>,
+        merge_history: &[u64],
+    ) -> bool {
+        (self.f)(attributes, observations, merge_history)
+    }
+}
+
+/// The type-erased lookup, any lookup can be converted into it with [boxed](LookupRequestExt::boxed)
+///
+pub type DynLookup<TA, OA> =
+    LookupFn<TA, OA, dyn Fn(&TA, &ObservationsDb<OA>, &[u64]) -> bool + Send + Sync>;
+
+/// Creates the lookup from the closure, so the store can be queried without the lookup type
+/// defined for the query
+///
+pub fn lookup_fn<TA, OA, F>(f: F) -> LookupFn<TA, OA, F>
+where
+    TA: TrackAttributes<TA, OA>,
+    OA: ObservationAttributes,
+    F: Fn(&TA, &ObservationsDb<OA>, &[u64]) -> bool + Send + Sync + 'static,
+{
+    LookupFn {
+        f: Arc::new(f),
+        _attributes: PhantomData,
+    }
+}
+
+/// Combinators of the lookups, e.g. `SortLookup::IdleLookup(3).and(lookup_fn(...))`
+///
+pub trait LookupRequestExt<TA, OA>: LookupRequest<TA, OA> + Sized
+where
+    TA: TrackAttributes<TA, OA>,
+    OA: ObservationAttributes,
+{
+    fn and<L: LookupRequest<TA, OA>>(self, other: L) -> And<Self, L> {
+        And(self, other)
+    }
+
+    fn or<L: LookupRequest<TA, OA>>(self, other: L) -> Or<Self, L> {
+        Or(self, other)
+    }
+
+    fn not(self) -> Not<Self> {
+        Not(self)
+    }
+
+    fn boxed(self) -> DynLookup<TA, OA> {
+        LookupFn {
+            f: Arc::new(
+                move |attributes: &TA, observations: &ObservationsDb<OA>, merge_history: &[u64]| {
+                    self.lookup(attributes, observations, merge_history)
+                },
+            ),
+            _attributes: PhantomData,
+        }
+    }
+}
+
+impl<TA, OA, L> LookupRequestExt<TA, OA> for L
+where
+    TA: TrackAttributes<TA, OA>,
+    OA: ObservationAttributes,
+    L: LookupRequest<TA, OA>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::lookup::{lookup_fn, LookupRequestExt};
+    use crate::track::ObservationsDb;
+    use crate::trackers::sort::metric::DEFAULT_MINIMAL_SORT_CONFIDENCE;
+    use crate::trackers::sort::simple_api::Sort;
+    use crate::trackers::sort::PositionalMetricType::IoU;
+    use crate::trackers::sort::{SortAttributes, SortLookup, DEFAULT_SORT_IOU_THRESHOLD};
+    use crate::trackers::tracker_api::TrackerAPI;
+    use crate::utils::bbox::{BoundingBox, Universal2DBox};
+
+    #[test]
+    fn combinators() {
+        let mut t = Sort::new(
+            1,
+            10,
+            5,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+        let mut long = 0;
+        let mut other_scene = 0;
+        for _ in 0..3 {
+            long = t.predict_with_scene(3, &[(bb.into(), None)])[0].id;
+            other_scene = t.predict_with_scene(1, &[(bb.into(), None)])[0].id;
+        }
+        let far = BoundingBox::new(100.0, 100.0, 10.0, 20.0);
+        let short = t.predict_with_scene(3, &[(far.into(), None)])[0].id;
+
+        let found = |q| {
+            let mut ids = t
+                .get_main_store()
+                .lookup(q)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>();
+            ids.sort_unstable();
+            ids
+        };
+        let is_long = lookup_fn(
+            |attrs: &SortAttributes, _: &ObservationsDb<Universal2DBox>, _: &[u64]| {
+                attrs.track_length > 2
+            },
+        );
+
+        assert_eq!(found(SortLookup::IdleLookup(3).boxed()), vec![long]);
+        assert_eq!(
+            found(SortLookup::SceneLookup(3).and(is_long.clone()).boxed()),
+            vec![long]
+        );
+        let mut expected = vec![long, other_scene];
+        expected.sort_unstable();
+        assert_eq!(
+            found(
+                SortLookup::IdleLookup(3)
+                    .or(SortLookup::SceneLookup(1))
+                    .boxed()
+            ),
+            expected
+        );
+        assert_eq!(found(is_long.not().boxed()), vec![short]);
+    }
+}
@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+
+/// The bounds of the merge history of the tracks.
+///
+/// The merge history collects the ids of the tracks merged into the track, so it grows with every
+/// merge and becomes large for the long-living tracks merged every epoch. The bounded history keeps
+/// the total number of the merges and the `limit` most recent ids, the older ids are evicted. The
+/// optional Bloom filter of `bits` bits and `hashes` hash functions remembers all the ids, so the
+/// membership of the evicted ids can be queried with a small false positive rate.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeHistoryOptions {
+    limit: usize,
+    bloom: Option<(usize, usize)>,
+}
+
+impl MergeHistoryOptions {
+    pub fn new(limit: usize) -> Self {
+        assert!(limit > 0, "The limit must be a positive number");
+        Self { limit, bloom: None }
+    }
+
+    /// Keeps the Bloom filter of all the ids
+    ///
+    /// # Parameters
+    /// * `bits` - the size of the filter in bits, rounded up to 64
+    /// * `hashes` - the number of the hash functions
+    ///
+    pub fn bloom_filter(mut self, bits: usize, hashes: usize) -> Self {
+        assert!(
+            bits > 0 && hashes > 0,
+            "The number of bits and hashes must be positive numbers"
+        );
+        self.bloom = Some((bits, hashes));
+        self
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn bloom(&self) -> Option<(usize, usize)> {
+        self.bloom
+    }
+}
+
+/// Bloom filter of the track ids
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeBloomFilter {
+    bits: Vec<u64>,
+    hashes: usize,
+}
+
+impl MergeBloomFilter {
+    pub fn new(bits: usize, hashes: usize) -> Self {
+        Self {
+            bits: vec![0; (bits + 63) / 64],
+            hashes,
+        }
+    }
+
+    fn mix(mut x: u64) -> u64 {
+        x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        x ^ (x >> 31)
+    }
+
+    fn positions(&self, id: u64) -> impl Iterator<Item = usize> {
+        let h1 = Self::mix(id);
+        let h2 = Self::mix(h1) | 1;
+        let size = self.bits.len() as u64 * 64;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % size) as usize)
+    }
+
+    pub fn insert(&mut self, id: u64) {
+        for p in self.positions(id).collect::<Vec<_>>() {
+            self.bits[p / 64] |= 1 << (p % 64);
+        }
+    }
+
+    /// Checks if the id may be in the filter, false positives are possible
+    ///
+    pub fn contains(&self, id: u64) -> bool {
+        self.positions(id)
+            .all(|p| self.bits[p / 64] & (1 << (p % 64)) != 0)
+    }
+
+    /// Adds the ids of the filter of the same configuration
+    ///
+    fn union(&mut self, other: &Self) -> bool {
+        if self.bits.len() != other.bits.len() || self.hashes != other.hashes {
+            return false;
+        }
+        self.bits
+            .iter_mut()
+            .zip(&other.bits)
+            .for_each(|(l, r)| *l |= *r);
+        true
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MergeHistoryRepr {
+    Ids(Vec<u64>),
+    Bounded {
+        ids: Vec<u64>,
+        count: usize,
+        #[serde(default)]
+        options: Option<MergeHistoryOptions>,
+        #[serde(default)]
+        bloom: Option<MergeBloomFilter>,
+    },
+}
+
+/// The ids of the tracks merged into the track, the first one is the id of the track itself.
+///
+/// Without the [options](MergeHistoryOptions) all the ids are kept, otherwise only the most recent
+/// ones. The history serialized before the bounds were introduced (the plain list of the ids) is
+/// deserialized as the unbounded one.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "MergeHistoryRepr")]
+pub struct MergeHistory {
+    ids: Vec<u64>,
+    count: usize,
+    options: Option<MergeHistoryOptions>,
+    bloom: Option<MergeBloomFilter>,
+}
+
+impl From<MergeHistoryRepr> for MergeHistory {
+    fn from(repr: MergeHistoryRepr) -> Self {
+        match repr {
+            MergeHistoryRepr::Ids(ids) => ids.into(),
+            MergeHistoryRepr::Bounded {
+                ids,
+                count,
+                options,
+                bloom,
+            } => Self {
+                ids,
+                count,
+                options,
+                bloom,
+            },
+        }
+    }
+}
+
+impl From<Vec<u64>> for MergeHistory {
+    fn from(ids: Vec<u64>) -> Self {
+        Self {
+            count: ids.len(),
+            ids,
+            options: None,
+            bloom: None,
+        }
+    }
+}
+
+impl MergeHistory {
+    pub fn new(track_id: u64) -> Self {
+        vec![track_id].into()
+    }
+
+    /// The kept ids from the oldest to the most recent
+    ///
+    pub fn ids(&self) -> &Vec<u64> {
+        &self.ids
+    }
+
+    /// The number of the ids collected, including the evicted ones
+    ///
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The number of the evicted ids
+    ///
+    pub fn evicted(&self) -> usize {
+        self.count - self.ids.len()
+    }
+
+    pub fn options(&self) -> Option<MergeHistoryOptions> {
+        self.options
+    }
+
+    /// Checks if the id was collected. The evicted ids are found with the Bloom filter only, so the
+    /// false positives are possible for them, and they are not found at all without the filter.
+    ///
+    pub fn contains(&self, id: u64) -> bool {
+        self.ids.contains(&id)
+            || (self.evicted() > 0 && self.bloom.as_ref().map_or(false, |b| b.contains(id)))
+    }
+
+    /// Applies the bounds, the ids beyond the limit are evicted
+    ///
+    pub fn configure(&mut self, options: Option<MergeHistoryOptions>) {
+        if self.options == options {
+            return;
+        }
+        self.options = options;
+        self.bloom = options
+            .and_then(|o| o.bloom())
+            .map(|(bits, hashes)| MergeBloomFilter::new(bits, hashes));
+        if let Some(bloom) = &mut self.bloom {
+            self.ids.iter().for_each(|id| bloom.insert(*id));
+        }
+        self.evict();
+    }
+
+    /// Appends the history of the other track
+    ///
+    pub fn extend(&mut self, other: &MergeHistory) {
+        self.count += other.count;
+        self.ids.extend_from_slice(&other.ids);
+        if let Some(bloom) = &mut self.bloom {
+            let merged = match &other.bloom {
+                Some(other_bloom) if other.evicted() > 0 => bloom.union(other_bloom),
+                _ => false,
+            };
+            if !merged {
+                other.ids.iter().for_each(|id| bloom.insert(*id));
+            }
+        }
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        if let Some(options) = self.options {
+            if self.ids.len() > options.limit() {
+                self.ids.drain(..self.ids.len() - options.limit());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::merge_history::{MergeHistory, MergeHistoryOptions};
+
+    #[test]
+    fn bounded() {
+        let mut history = MergeHistory::new(0);
+        history.configure(Some(MergeHistoryOptions::new(3).bloom_filter(1024, 3)));
+        for id in 1..10 {
+            history.extend(&MergeHistory::new(id));
+        }
+        assert_eq!(history.ids(), &vec![7, 8, 9]);
+        assert_eq!(history.count(), 10);
+        assert_eq!(history.evicted(), 7);
+        assert!((0..10).all(|id| history.contains(id)));
+        assert!(!history.contains(1000));
+
+        let mut unbounded = MergeHistory::new(0);
+        unbounded.extend(&MergeHistory::new(1));
+        unbounded.configure(Some(MergeHistoryOptions::new(1)));
+        assert_eq!(unbounded.ids(), &vec![1]);
+        assert!(!unbounded.contains(0));
+
+        history.extend(&unbounded);
+        assert_eq!(history.count(), 12);
+        assert!(history.contains(0));
+    }
+
+    #[test]
+    fn serialization() {
+        let legacy: MergeHistory = serde_json::from_str("[3, 5]").unwrap();
+        assert_eq!(legacy, MergeHistory::from(vec![3, 5]));
+
+        let mut history = MergeHistory::new(0);
+        history.configure(Some(MergeHistoryOptions::new(1).bloom_filter(64, 2)));
+        history.extend(&MergeHistory::new(1));
+        let restored: MergeHistory =
+            serde_json::from_str(&serde_json::to_string(&history).unwrap()).unwrap();
+        assert_eq!(restored, history);
+        assert!(restored.contains(0));
+    }
+}
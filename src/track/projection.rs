@@ -0,0 +1,111 @@
+use crate::track::utils::FromVec;
+use crate::track::{Feature, FEATURE_LANES_SIZE};
+use crate::Errors;
+use anyhow::Result;
+use nalgebra::{DMatrix, DVector};
+use std::collections::HashMap;
+
+/// Maps the features of various dimensions to the canonical dimension of the feature class.
+///
+/// It is used when the features produced by different models (e.g. 256-d features of the old
+/// gallery and 512-d features of the upgraded model) are kept within the same tracks. For every
+/// non-canonical dimension the projection matrix (e.g. PCA) is registered, the features of the
+/// canonical dimension are kept as is.
+///
+/// Features are stored padded to the multiple of 8 values, so the dimensions that share the
+/// padded size cannot be distinguished.
+///
+#[derive(Debug, Clone)]
+pub struct FeatureProjection {
+    canonical_dim: usize,
+    matrices: HashMap<usize, DMatrix<f32>>,
+}
+
+fn lanes(dim: usize) -> usize {
+    dim / FEATURE_LANES_SIZE + usize::from(dim % FEATURE_LANES_SIZE > 0)
+}
+
+impl FeatureProjection {
+    pub fn new(canonical_dim: usize) -> Self {
+        Self {
+            canonical_dim,
+            matrices: HashMap::default(),
+        }
+    }
+
+    /// Registers the projection for the features of `input_dim` dimension.
+    ///
+    /// # Parameters
+    /// * `input_dim` - the dimension of the features to project
+    /// * `matrix` - row-major matrix of `canonical_dim` rows and `input_dim` columns
+    ///
+    pub fn with_matrix(mut self, input_dim: usize, matrix: &[Vec<f32>]) -> Result<Self> {
+        if matrix.len() != self.canonical_dim || matrix.iter().any(|r| r.len() != input_dim) {
+            return Err(Errors::ProjectionShapeMismatch(self.canonical_dim, input_dim).into());
+        }
+        let matrix = DMatrix::from_fn(self.canonical_dim, input_dim, |r, c| matrix[r][c]);
+        self.matrices.insert(lanes(input_dim), matrix);
+        Ok(self)
+    }
+
+    pub fn canonical_dim(&self) -> usize {
+        self.canonical_dim
+    }
+
+    /// Maps the feature to the canonical dimension
+    ///
+    pub fn project(&self, feature: &Feature) -> Result<Feature> {
+        if feature.len() == lanes(self.canonical_dim) {
+            return Ok(feature.clone());
+        }
+        let matrix = self
+            .matrices
+            .get(&feature.len())
+            .ok_or(Errors::FeatureProjectionNotFound(
+                feature.len() * FEATURE_LANES_SIZE,
+            ))?;
+        let values = Vec::from_vec(feature);
+        let input = DVector::from_fn(matrix.ncols(), |i, _| values[i]);
+        let output = matrix * input;
+        Ok(Feature::from_vec(output.as_slice().to_vec()))
+    }
+}
+
+/// Projections registered for feature classes
+///
+pub type FeatureProjections = HashMap<u64, FeatureProjection>;
+
+#[cfg(test)]
+mod tests {
+    use crate::track::projection::FeatureProjection;
+    use crate::track::utils::FromVec;
+    use crate::track::Feature;
+
+    #[test]
+    fn project() {
+        let projection = FeatureProjection::new(2)
+            .with_matrix(
+                10,
+                &[
+                    vec![1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0],
+                    vec![0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+                ],
+            )
+            .unwrap();
+        assert!(FeatureProjection::new(2)
+            .with_matrix(3, &[vec![1.0]])
+            .is_err());
+
+        let canonical = Feature::from_vec(vec![3.0, 4.0]);
+        let res = projection.project(&canonical).unwrap();
+        assert_eq!(Vec::from_vec(&res)[..2], [3.0, 4.0]);
+
+        let f = Feature::from_vec(vec![1.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 5.0]);
+        let res = projection.project(&f).unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(Vec::from_vec(&res)[..2], [6.0, 4.0]);
+
+        let f = Feature::from_vec(vec![1.0; 20]);
+        assert!(projection.project(&f).is_err());
+    }
+}
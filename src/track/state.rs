@@ -0,0 +1,123 @@
+use crate::track::merge_history::MergeHistory;
+use crate::track::notify::ChangeNotifier;
+use crate::track::utils::FromVec;
+use crate::track::{
+    Feature, Observation, ObservationAttributes, ObservationMetric, Track, TrackAttributes,
+};
+use serde::{Deserialize, Serialize};
+
+/// Serializable state of the observation, the feature is kept as the plain vector
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObservationState<OA> {
+    pub attributes: Option<OA>,
+    pub feature: Option<Vec<f32>>,
+}
+
+/// Serializable state of the track.
+///
+/// The state keeps everything the track is made of but the metric and the notifier objects, which
+/// are taken from the store the track is restored into.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackState<TA, OA> {
+    pub track_id: u64,
+    pub attributes: TA,
+    pub observations: Vec<(u64, Vec<ObservationState<OA>>)>,
+    pub merge_history: MergeHistory,
+}
+
+impl<TA, M, OA, N> From<&Track<TA, M, OA, N>> for TrackState<TA, OA>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    fn from(track: &Track<TA, M, OA, N>) -> Self {
+        let mut observations = track
+            .observations
+            .iter()
+            .map(|(feature_class, observations)| {
+                (
+                    *feature_class,
+                    observations
+                        .iter()
+                        .map(|o| ObservationState {
+                            attributes: o.0.clone(),
+                            feature: o.1.as_ref().map(Vec::from_vec),
+                        })
+                        .collect(),
+                )
+            })
+            .collect::<Vec<_>>();
+        observations.sort_by_key(|(feature_class, _)| *feature_class);
+        Self {
+            track_id: track.track_id,
+            attributes: track.attributes.clone(),
+            observations,
+            merge_history: track.merge_history.clone(),
+        }
+    }
+}
+
+impl<TA, OA> TrackState<TA, OA>
+where
+    TA: TrackAttributes<TA, OA>,
+    OA: ObservationAttributes,
+{
+    /// Builds the track from the state without running the attribute updates and the metric
+    /// optimization, so the restored track is the same as the one the state was taken from
+    ///
+    pub fn into_track<M, N>(self, metric: M, notifier: N) -> Track<TA, M, OA, N>
+    where
+        M: ObservationMetric<TA, OA>,
+        N: ChangeNotifier,
+    {
+        let mut track = Track::new(self.track_id, metric, self.attributes, notifier);
+        track.merge_history = self.merge_history;
+        track.observations = self
+            .observations
+            .into_iter()
+            .map(|(feature_class, observations)| {
+                (
+                    feature_class,
+                    observations
+                        .into_iter()
+                        .map(|o| Observation(o.attributes, o.feature.map(Feature::from_vec)))
+                        .collect(),
+                )
+            })
+            .collect();
+        track
+    }
+}
+
+/// Serde helpers for the feature history kept in the track attributes
+///
+pub mod features {
+    use crate::track::utils::FromVec;
+    use crate::track::Feature;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::VecDeque;
+
+    pub fn serialize<S: Serializer>(
+        features: &VecDeque<Option<Feature>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        features
+            .iter()
+            .map(|f| f.as_ref().map(Vec::from_vec))
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<VecDeque<Option<Feature>>, D::Error> {
+        Ok(Vec::<Option<Vec<f32>>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|f| f.map(Feature::from_vec))
+            .collect())
+    }
+}
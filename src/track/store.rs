@@ -1,20 +1,56 @@
+/// Export and import of the tracks in the Arrow IPC streaming format
+pub mod arrow;
 pub mod builder;
+/// Self-description of the store used to check the compatibility of the stores
+pub mod descriptor;
+/// The reusable buffers of the distance results
+pub mod distance_pool;
+/// Reservation of the track ids shared by the id producers
+pub mod id_registry;
+/// Handling of the NaN distances returned by the metrics
+pub mod nan_distances;
+/// Recovery of the store shards and executors after the panics in the user code
+pub mod recovery;
+/// Distance computation statistics of the store shards
+pub mod shard_stats;
+/// Assignment of the tracks to the store shards
+pub mod sharding;
+pub mod snapshot;
 mod store_tests;
 pub mod track_distance;
 
 use crate::prelude::TrackBuilder;
+use crate::track::feature_class::FeatureClass;
+use crate::track::lookup::{DynLookup, LookupRequestExt};
+use crate::track::merge_history::{MergeHistory, MergeHistoryOptions};
 use crate::track::notify::{ChangeNotifier, NoopNotifier};
+use crate::track::projection::{FeatureProjection, FeatureProjections};
+use crate::track::state::TrackState;
 use crate::track::{
-    Feature, Observation, ObservationAttributes, ObservationMetric, ObservationMetricOk, Track,
-    TrackAttributes, TrackStatus,
+    Feature, LookupRequest, NoopLookup, Observation, ObservationAttributes, ObservationMetric,
+    ObservationMetricOk, Track, TrackAttributes, TrackStatus, FEATURE_LANES_SIZE,
 };
 use crate::Errors;
 use anyhow::Result;
-use crossbeam::channel::{Receiver, Sender};
+use crossbeam::channel::{Receiver, SendError, Sender};
+use descriptor::{SchemaId, StoreDescriptor};
+use distance_pool::DistanceBufferPool;
+use id_registry::TrackIdRegistry;
 use log::{error, warn};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex, MutexGuard};
+use nan_distances::NanDistancePolicy;
+use recovery::{
+    panic_message, ShardPoisonPolicy, ShardRecovery, EXECUTOR_RESTART_ATTEMPTS,
+    EXECUTOR_RESTART_DELAY,
+};
+use shard_stats::{ShardDistanceCounters, ShardDistanceStats, SlowShardDetector};
+use sharding::ShardAssignment;
+use snapshot::{SnapshotCache, StoreGenerations, TrackStoreSnapshot};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::{Deref, Range};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
 use std::thread::JoinHandle;
+use std::time::Instant;
 use std::{mem, thread};
 use track_distance::{TrackDistanceErr, TrackDistanceOk};
 
@@ -32,10 +68,14 @@ where
         Arc<Track<TA, M, OA, N>>,
         u64,
         bool,
+        Arc<DistanceBufferPool<OA>>,
         Sender<Results<OA>>,
         Sender<Results<OA>>,
     ),
-    Lookup(TA::Lookup, Sender<Results<OA>>),
+    Lookup(DynLookup<TA, OA>, Sender<Results<OA>>),
+    GroupedLookup(Vec<DynLookup<TA, OA>>, Sender<Results<OA>>),
+    UpdateAttributes(TA::Update, DynLookup<TA, OA>, Sender<Results<OA>>),
+    Epoch(usize, u64, DynLookup<TA, OA>, Sender<Results<OA>>),
     Merge(
         u64,
         Track<TA, M, OA, N>,
@@ -49,11 +89,48 @@ where
 ///
 pub type StoreMutexGuard<'a, TA, M, FA, N> = MutexGuard<'a, HashMap<u64, Track<TA, M, FA, N>>>;
 
+/// The type that provides lock-ed read-only access to certain shard store, the shard is not
+/// considered changed by the snapshots
+///
+pub struct StoreReadGuard<'a, TA, M, FA, N>(StoreMutexGuard<'a, TA, M, FA, N>)
+where
+    TA: TrackAttributes<TA, FA>,
+    M: ObservationMetric<TA, FA>,
+    FA: ObservationAttributes,
+    N: ChangeNotifier;
+
+impl<TA, M, FA, N> Deref for StoreReadGuard<'_, TA, M, FA, N>
+where
+    TA: TrackAttributes<TA, FA>,
+    M: ObservationMetric<TA, FA>,
+    FA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    type Target = HashMap<u64, Track<TA, M, FA, N>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 /// The type that provides the initial track that was in the store before it was merged into
 /// target track
 ///
 pub type OwnedMergeResult<TA, M, FA, N> = Result<Option<Track<TA, M, FA, N>>>;
 
+/// The outcome of the merge simulated with [merge_dry_run](TrackStore::merge_dry_run)
+///
+#[derive(Debug)]
+pub struct MergeDryRun<TA> {
+    /// The attributes the destination track would have after the merge. When the merge fails,
+    /// they are the current attributes of the destination track.
+    pub attributes: TA,
+    /// The number of observations per feature class the destination track would have after the merge
+    pub observation_counts: HashMap<u64, usize>,
+    /// The error the merge would fail with
+    pub error: Option<anyhow::Error>,
+}
+
 #[derive(Debug)]
 pub enum Results<OA>
 where
@@ -62,8 +139,12 @@ where
     DistanceOk(Vec<ObservationMetricOk<OA>>),
     DistanceErr(Vec<ObservationMetricErr<OA>>),
     BakedStatus(Vec<(u64, Result<TrackStatus>)>),
+    /// The statuses of the tracks with the indices of the lookups they match
+    GroupedStatus(Vec<(usize, u64, Result<TrackStatus>)>),
     Dropped,
     MergeResult(Result<()>),
+    UpdateResults(Vec<(u64, Result<()>)>),
+    EpochNotified,
 }
 
 /// Merge future result
@@ -92,11 +173,34 @@ where
     pub fn is_ready(&self) -> bool {
         !self.receiver.is_empty()
     }
+
+    pub(crate) fn receiver(&self) -> &Receiver<Results<OA>> {
+        &self.receiver
+    }
 }
 
 /// Auxiliary type to express distance calculation errors
 pub type ObservationMetricErr<OA> = Result<Vec<ObservationMetricOk<OA>>>;
 
+/// The command channel and the thread of the shard executor
+///
+type Executor<TA, M, OA, N> = (Sender<Commands<TA, M, OA, N>>, JoinHandle<()>);
+
+/// The status of the track, the panic of the attributes is reported as the error
+///
+fn baked_status<TA, M, OA, N>(track: &Track<TA, M, OA, N>) -> Result<TrackStatus>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    catch_unwind(AssertUnwindSafe(|| {
+        track.get_attributes().baked(&track.observations)
+    }))
+    .unwrap_or_else(|p| Err(Errors::AttributesPanic(track.track_id, panic_message(p)).into()))
+}
+
 /// Track store container with accelerated similarity operations.
 ///
 /// TrackStore is implemented for certain attributes (A), attribute update (U), and metric (M), so
@@ -121,11 +225,22 @@ where
     metric: M,
     notifier: N,
     num_shards: usize,
+    shard_assignment: ShardAssignment,
+    id_registry: TrackIdRegistry,
+    generations: Arc<StoreGenerations>,
+    snapshot_cache: Mutex<SnapshotCache<TA, M, OA, N>>,
+    feature_projections: FeatureProjections,
+    feature_dimensions: BTreeMap<u64, usize>,
+    distance_stats: Arc<Vec<ShardDistanceCounters>>,
+    slow_shard_detector: Option<SlowShardDetector>,
+    poison_recovery: Arc<ShardRecovery>,
+    nan_policy: Arc<RwLock<NanDistancePolicy>>,
+    merge_history_options: Option<MergeHistoryOptions>,
+    distance_pool: Arc<DistanceBufferPool<OA>>,
     #[allow(clippy::type_complexity)]
     stores: Arc<Vec<Mutex<HashMap<u64, Track<TA, M, OA, N>>>>>,
     // receiver: Receiver<Results<FA>>,
-    #[allow(clippy::type_complexity)]
-    executors: Vec<(Sender<Commands<TA, M, OA, N>>, JoinHandle<()>)>,
+    executors: Vec<Mutex<Executor<TA, M, OA, N>>>,
 }
 
 impl<TA, M, OA, N> Drop for TrackStore<TA, M, OA, N>
@@ -136,21 +251,7 @@ where
     N: ChangeNotifier,
 {
     fn drop(&mut self) {
-        let executors = mem::take(&mut self.executors);
-        let (results_sender, results_receiver) = crossbeam::channel::unbounded();
-        for (s, j) in executors {
-            s.send(Commands::Drop(results_sender.clone())).unwrap();
-            let res = results_receiver.recv().unwrap();
-            match res {
-                Results::Dropped => {
-                    j.join().unwrap();
-                    drop(s);
-                }
-                _ => {
-                    unreachable!();
-                }
-            }
-        }
+        self.shutdown();
     }
 }
 
@@ -168,8 +269,14 @@ where
         stores: Arc<Vec<Mutex<HashMap<u64, Track<TA, M, OA, N>>>>>,
         store_id: usize,
         commands_receiver: Receiver<Commands<TA, M, OA, N>>,
+        distance_stats: Arc<Vec<ShardDistanceCounters>>,
+        poison_recovery: Arc<ShardRecovery>,
+        generations: Arc<StoreGenerations>,
+        nan_policy: Arc<RwLock<NanDistancePolicy>>,
     ) {
-        let store = stores.get(store_id).unwrap();
+        let distance_stats = distance_stats.get(store_id).unwrap();
+        // the distances of one pair of the tracks, kept across the commands to reuse the memory
+        let mut pair_distances = Vec::new();
         while let Ok(c) = commands_receiver.recv() {
             match c {
                 Commands::Drop(channel) => {
@@ -177,18 +284,15 @@ where
                     return;
                 }
                 Commands::FindBaked(channel) => {
-                    let baked = store
-                        .lock()
-                        .unwrap()
+                    let baked = poison_recovery
+                        .lock(&stores, store_id)
                         .iter()
-                        .flat_map(|(track_id, track)| {
-                            match track.get_attributes().baked(&track.observations) {
-                                Ok(status) => match status {
-                                    TrackStatus::Pending => None,
-                                    other => Some((*track_id, Ok(other))),
-                                },
-                                Err(e) => Some((*track_id, Err(e))),
-                            }
+                        .flat_map(|(track_id, track)| match baked_status(track) {
+                            Ok(status) => match status {
+                                TrackStatus::Pending => None,
+                                other => Some((*track_id, Ok(other))),
+                            },
+                            Err(e) => Some((*track_id, Err(e))),
                         })
                         .collect();
                     let r = channel.send(Results::BakedStatus(baked));
@@ -196,62 +300,77 @@ where
                         return;
                     }
                 }
-                Commands::Distances(track, feature_class, only_baked, channel_ok, channel_err) => {
-                    let mut capacity = 0;
-                    let res = store
-                        .lock()
-                        .unwrap()
-                        .iter()
-                        .flat_map(|(_, other)| {
-                            if track.track_id == other.track_id {
-                                return None;
-                            }
-
-                            if !only_baked {
-                                let dists = track.distances(other, feature_class);
-                                match dists {
-                                    Ok(dists) => {
-                                        capacity += dists.len();
-                                        Some(Ok(track.metric.postprocess_distances(dists)))
-                                    }
-                                    Err(e) => match e.downcast_ref::<Errors>() {
-                                        Some(Errors::IncompatibleAttributes) => None,
-                                        _ => Some(Err(e)),
-                                    },
-                                }
-                            } else {
-                                match other.get_attributes().baked(&other.observations) {
-                                    Ok(TrackStatus::Ready) => {
-                                        let dists = track.distances(other, feature_class);
-                                        match dists {
-                                            Ok(dists) => {
-                                                capacity += dists.len();
-                                                Some(Ok(track.metric.postprocess_distances(dists)))
-                                            }
-                                            Err(e) => match e.downcast_ref::<Errors>() {
-                                                Some(Errors::IncompatibleAttributes) => None,
-                                                _ => Some(Err(e)),
-                                            },
-                                        }
+                Commands::Distances(
+                    track,
+                    feature_class,
+                    only_baked,
+                    pool,
+                    channel_ok,
+                    channel_err,
+                ) => {
+                    let started = Instant::now();
+                    let nan_policy = *nan_policy.read().unwrap();
+                    let mut pairs = 0;
+                    let mut nans = 0;
+                    let mut distances = pool.acquire();
+                    let mut errors = Vec::new();
+                    let mut compare = |other: &Track<TA, M, OA, N>| {
+                        pairs += 1;
+                        pair_distances.clear();
+                        let res = catch_unwind(AssertUnwindSafe(|| {
+                            track
+                                .distances_into(other, feature_class, &mut pair_distances)
+                                .and_then(|()| {
+                                    let mut dists = track
+                                        .metric
+                                        .postprocess_distances(mem::take(&mut pair_distances));
+                                    let found = nan_policy.apply(&mut dists);
+                                    nans += found;
+                                    distances.append(&mut dists);
+                                    pair_distances = dists;
+                                    if found > 0 && nan_policy == NanDistancePolicy::Error {
+                                        Err(Errors::NanDistance(track.track_id, other.track_id)
+                                            .into())
+                                    } else {
+                                        Ok(())
                                     }
-                                    _ => None,
-                                }
+                                })
+                        }))
+                        .unwrap_or_else(|p| {
+                            Err(Errors::MetricPanic(
+                                track.track_id,
+                                other.track_id,
+                                panic_message(p),
+                            )
+                            .into())
+                        });
+                        if let Err(e) = res {
+                            if !matches!(
+                                e.downcast_ref::<Errors>(),
+                                Some(Errors::IncompatibleAttributes)
+                            ) {
+                                errors.push(Err(e));
                             }
-                        })
-                        .collect::<Vec<_>>();
-
-                    let mut distances = Vec::with_capacity(capacity);
-                    let mut errors = Vec::new();
+                        }
+                    };
+                    for other in poison_recovery.lock(&stores, store_id).values() {
+                        if track.track_id == other.track_id {
+                            continue;
+                        }
 
-                    for r in res {
-                        match r {
-                            Ok(dists) => {
-                                distances.extend_from_slice(&dists);
-                            }
-                            e => errors.push(e),
+                        if !only_baked || matches!(baked_status(other), Ok(TrackStatus::Ready)) {
+                            compare(other);
                         }
                     }
 
+                    distance_stats.record(
+                        pairs,
+                        distances.len(),
+                        nans,
+                        errors.len(),
+                        started.elapsed(),
+                    );
+
                     let r = channel_ok.send(Results::DistanceOk(distances));
                     if let Err(e) = r {
                         warn!("Unable to send data back to caller. Channel error: {:?}", e);
@@ -263,17 +382,26 @@ where
                     }
                 }
                 Commands::Merge(dest_id, src, classes, merge_history, channel_opt) => {
-                    let mut store = store.lock().unwrap();
+                    let mut store = poison_recovery.lock(&stores, store_id);
+                    generations.touch(store_id);
                     let dest = store.get_mut(&dest_id);
 
                     let res = match dest {
                         Some(dest) => {
                             if dest_id == src.track_id {
                                 Err(Errors::SameTrackCalculation(dest_id).into())
-                            } else if !classes.is_empty() {
-                                dest.merge(&src, &classes, merge_history)
                             } else {
-                                dest.merge(&src, &src.get_feature_classes(), merge_history)
+                                let classes = if !classes.is_empty() {
+                                    classes
+                                } else {
+                                    src.get_feature_classes()
+                                };
+                                catch_unwind(AssertUnwindSafe(|| {
+                                    dest.merge(&src, &classes, merge_history)
+                                }))
+                                .unwrap_or_else(|p| {
+                                    Err(Errors::MergePanic(dest_id, panic_message(p)).into())
+                                })
                             }
                         }
 
@@ -287,12 +415,19 @@ where
                     }
                 }
                 Commands::Lookup(q, channel) => {
-                    let store = store.lock().unwrap();
+                    let store = poison_recovery.lock(&stores, store_id);
                     let res = channel.send(Results::BakedStatus(
                         store
                             .values()
-                            .filter(|x| x.lookup(&q))
-                            .map(|x| (x.track_id, x.get_attributes().baked(&x.observations)))
+                            .filter_map(|x| match catch_unwind(AssertUnwindSafe(|| x.lookup(&q))) {
+                                Ok(true) => Some((x.track_id, baked_status(x))),
+                                Ok(false) => None,
+                                Err(p) => Some((
+                                    x.track_id,
+                                    Err(Errors::AttributesPanic(x.track_id, panic_message(p))
+                                        .into()),
+                                )),
+                            })
                             .collect(),
                     ));
 
@@ -300,10 +435,175 @@ where
                         warn!("Receiver channel was dropped before the data sent into it. Error is: {:?}", send_res);
                     }
                 }
+                Commands::GroupedLookup(lookups, channel) => {
+                    let store = poison_recovery.lock(&stores, store_id);
+                    let res = channel.send(Results::GroupedStatus(
+                        store
+                            .values()
+                            .flat_map(|x| {
+                                match catch_unwind(AssertUnwindSafe(|| {
+                                    lookups.iter().position(|q| x.lookup(q))
+                                })) {
+                                    Ok(Some(group)) => vec![(group, x.track_id, baked_status(x))],
+                                    Ok(None) => vec![],
+                                    // the group of the track is unknown, it is reported to all
+                                    Err(p) => {
+                                        let message = panic_message(p);
+                                        (0..lookups.len())
+                                            .map(|group| {
+                                                (
+                                                    group,
+                                                    x.track_id,
+                                                    Err(Errors::AttributesPanic(
+                                                        x.track_id,
+                                                        message.clone(),
+                                                    )
+                                                    .into()),
+                                                )
+                                            })
+                                            .collect()
+                                    }
+                                }
+                            })
+                            .collect(),
+                    ));
+
+                    if let Err(send_res) = res {
+                        warn!("Receiver channel was dropped before the data sent into it. Error is: {:?}", send_res);
+                    }
+                }
+                Commands::UpdateAttributes(update, q, channel) => {
+                    let mut store = poison_recovery.lock(&stores, store_id);
+                    let updated = store
+                        .values_mut()
+                        .filter_map(|x| {
+                            match catch_unwind(AssertUnwindSafe(|| {
+                                if x.lookup(&q) {
+                                    Some(x.update_attributes(&update))
+                                } else {
+                                    None
+                                }
+                            })) {
+                                Ok(res) => res.map(|r| (x.track_id, r)),
+                                Err(p) => Some((
+                                    x.track_id,
+                                    Err(Errors::AttributesPanic(x.track_id, panic_message(p))
+                                        .into()),
+                                )),
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    if !updated.is_empty() {
+                        generations.touch(store_id);
+                    }
+                    drop(store);
+
+                    if let Err(send_res) = channel.send(Results::UpdateResults(updated)) {
+                        warn!("Receiver channel was dropped before the data sent into it. Error is: {:?}", send_res);
+                    }
+                }
+                Commands::Epoch(epoch, scene_id, q, channel) => {
+                    let mut store = poison_recovery.lock(&stores, store_id);
+                    let mut touched = false;
+                    for x in store.values_mut() {
+                        let res = catch_unwind(AssertUnwindSafe(|| {
+                            let matched = x.lookup(&q);
+                            if matched {
+                                x.on_epoch(epoch, scene_id);
+                            }
+                            matched
+                        }));
+                        match res {
+                            Ok(matched) => touched |= matched,
+                            Err(p) => {
+                                touched = true;
+                                error!("{}", Errors::AttributesPanic(x.track_id, panic_message(p)));
+                            }
+                        }
+                    }
+                    if touched {
+                        generations.touch(store_id);
+                    }
+                    drop(store);
+
+                    if let Err(send_res) = channel.send(Results::EpochNotified) {
+                        warn!("Receiver channel was dropped before the data sent into it. Error is: {:?}", send_res);
+                    }
+                }
             }
         }
     }
 
+    #[allow(clippy::type_complexity)]
+    fn spawn_executor(
+        stores: Arc<Vec<Mutex<HashMap<u64, Track<TA, M, OA, N>>>>>,
+        store_id: usize,
+        distance_stats: Arc<Vec<ShardDistanceCounters>>,
+        poison_recovery: Arc<ShardRecovery>,
+        generations: Arc<StoreGenerations>,
+        nan_policy: Arc<RwLock<NanDistancePolicy>>,
+    ) -> std::io::Result<Executor<TA, M, OA, N>> {
+        let (commands_sender, commands_receiver) = crossbeam::channel::unbounded();
+        let thread = thread::Builder::new().spawn(move || {
+            Self::handle_store_ops(
+                stores,
+                store_id,
+                commands_receiver,
+                distance_stats,
+                poison_recovery,
+                generations,
+                nan_policy,
+            );
+        })?;
+        Ok((commands_sender, thread))
+    }
+
+    /// Sends the command to the executor of the shard.
+    ///
+    /// When the executor thread is found dead, it is restarted and the command is sent to the new
+    /// thread. If the thread can't be spawned, the restart is retried with the exponential back-off,
+    /// the command fails with [ExecutorUnavailable](Errors::ExecutorUnavailable) when all the
+    /// attempts fail.
+    ///
+    fn send(&self, executor_id: usize, command: Commands<TA, M, OA, N>) -> Result<()> {
+        let mut executor = self.executors[executor_id].lock().unwrap();
+        let mut command = match executor.0.send(command) {
+            Ok(()) => return Ok(()),
+            Err(SendError(command)) => command,
+        };
+
+        let mut delay = EXECUTOR_RESTART_DELAY;
+        for attempt in 1..=EXECUTOR_RESTART_ATTEMPTS {
+            warn!(
+                "Executor {} is dead, restarting it, attempt {}",
+                executor_id, attempt
+            );
+            match Self::spawn_executor(
+                self.stores.clone(),
+                executor_id,
+                self.distance_stats.clone(),
+                self.poison_recovery.clone(),
+                self.generations.clone(),
+                self.nan_policy.clone(),
+            ) {
+                Ok(restarted) => {
+                    let (_, dead) = mem::replace(&mut *executor, restarted);
+                    if let Err(p) = dead.join() {
+                        error!("Executor {} panicked: {}", executor_id, panic_message(p));
+                    }
+                    match executor.0.send(command) {
+                        Ok(()) => return Ok(()),
+                        Err(SendError(c)) => command = c,
+                    }
+                }
+                Err(e) => error!("Unable to restart executor {}: {:?}", executor_id, e),
+            }
+            thread::sleep(delay);
+            delay *= 2;
+        }
+        Err(Errors::ExecutorUnavailable(executor_id).into())
+    }
+
     /// Constructor method
     ///
     /// When you construct track store you may pass two initializer objects:
@@ -322,10 +622,30 @@ where
                 .collect::<Vec<_>>(),
         );
         let my_stores = stores.clone();
+        let poison_recovery = Arc::new(ShardRecovery::new(shards));
+        let generations = Arc::new(StoreGenerations::new(shards));
+        let nan_policy = Arc::new(RwLock::new(NanDistancePolicy::default()));
+        let distance_stats = Arc::new(
+            (0..shards)
+                .map(|_| ShardDistanceCounters::default())
+                .collect::<Vec<_>>(),
+        );
 
         Self {
             //receiver: results_receiver,
             num_shards: shards,
+            shard_assignment: ShardAssignment::default(),
+            id_registry: TrackIdRegistry::default(),
+            generations: generations.clone(),
+            snapshot_cache: Mutex::new(SnapshotCache::default()),
+            feature_projections: FeatureProjections::default(),
+            feature_dimensions: BTreeMap::default(),
+            distance_stats: distance_stats.clone(),
+            slow_shard_detector: None,
+            poison_recovery: poison_recovery.clone(),
+            nan_policy: nan_policy.clone(),
+            merge_history_options: None,
+            distance_pool: Arc::new(DistanceBufferPool::default()),
             notifier,
             default_attributes,
             metric,
@@ -333,12 +653,17 @@ where
             executors: {
                 (0..shards)
                     .map(|s| {
-                        let (commands_sender, commands_receiver) = crossbeam::channel::unbounded();
-                        let stores = stores.clone();
-                        let thread = thread::spawn(move || {
-                            Self::handle_store_ops(stores, s, commands_receiver);
-                        });
-                        (commands_sender, thread)
+                        Mutex::new(
+                            Self::spawn_executor(
+                                stores.clone(),
+                                s,
+                                distance_stats.clone(),
+                                poison_recovery.clone(),
+                                generations.clone(),
+                                nan_policy.clone(),
+                            )
+                            .expect("Unable to start the store executor"),
+                        )
                     })
                     .collect()
             },
@@ -355,11 +680,11 @@ where
     pub fn find_usable(&mut self) -> Vec<(u64, Result<TrackStatus>)> {
         let mut results = Vec::with_capacity(self.shard_stats().iter().sum());
         let (results_sender, results_receiver) = crossbeam::channel::unbounded();
-        for (cmd, _) in &mut self.executors {
-            cmd.send(Commands::FindBaked(results_sender.clone()))
+        for executor_id in 0..self.executors.len() {
+            self.send(executor_id, Commands::FindBaked(results_sender.clone()))
                 .unwrap();
         }
-        for (_, _) in &mut self.executors {
+        for _ in &self.executors {
             let res = results_receiver.recv().unwrap();
             match res {
                 Results::BakedStatus(r) => {
@@ -373,22 +698,299 @@ where
         results
     }
 
+    /// The metric the new tracks are created with
+    ///
+    pub fn metric(&self) -> &M {
+        &self.metric
+    }
+
+    /// Replaces the metric the new tracks are created with, the tracks already in the store keep
+    /// their metric objects
+    ///
+    pub fn set_metric(&mut self, metric: M) {
+        self.metric = metric;
+    }
+
+    /// Stops and joins the executor threads of the shards. The tracks are kept and can be dumped,
+    /// but the store can't calculate the distances or merge the tracks anymore.
+    ///
+    pub fn shutdown(&mut self) {
+        let executors = mem::take(&mut self.executors);
+        let (results_sender, results_receiver) = crossbeam::channel::unbounded();
+        for (executor_id, executor) in executors.into_iter().enumerate() {
+            let (s, j) = executor.into_inner().unwrap();
+            if s.send(Commands::Drop(results_sender.clone())).is_ok() {
+                let res = results_receiver.recv().unwrap();
+                match res {
+                    Results::Dropped => {}
+                    _ => {
+                        unreachable!();
+                    }
+                }
+            }
+            drop(s);
+            if let Err(p) = j.join() {
+                error!("Executor {} panicked: {}", executor_id, panic_message(p));
+            }
+        }
+    }
+
+    /// Sets what is done with the tracks of the shard which lock was poisoned by a panic
+    ///
+    pub fn set_poison_policy(&mut self, policy: ShardPoisonPolicy) {
+        self.poison_recovery.set_policy(policy);
+    }
+
+    /// Sets what is done with the NaN distances returned by the metric
+    ///
+    pub fn set_nan_distance_policy(&mut self, policy: NanDistancePolicy) {
+        *self.nan_policy.write().unwrap() = policy;
+    }
+
+    /// Returns what is done with the NaN distances returned by the metric
+    ///
+    pub fn nan_distance_policy(&self) -> NanDistancePolicy {
+        *self.nan_policy.read().unwrap()
+    }
+
+    /// Returns how the tracks are assigned to the shards
+    ///
+    pub fn shard_assignment(&self) -> &ShardAssignment {
+        &self.shard_assignment
+    }
+
+    /// Changes how the tracks are assigned to the shards and moves the tracks of the store to
+    /// their new shards
+    ///
+    /// # Returns
+    /// the number of the tracks moved
+    ///
+    pub fn rebalance(&mut self, assignment: ShardAssignment) -> usize {
+        self.shard_assignment = assignment;
+        let mut moved = Vec::new();
+        for s in 0..self.stores.len() {
+            let mut shard = self.poison_recovery.lock(&self.stores, s);
+            self.generations.touch(s);
+            let ids = shard
+                .keys()
+                .filter(|id| self.shard_assignment.shard(**id, self.num_shards) != s)
+                .copied()
+                .collect::<Vec<_>>();
+            moved.extend(ids.into_iter().flat_map(|id| shard.remove(&id)));
+        }
+        let count = moved.len();
+        for track in moved {
+            self.get_store_mut(track.track_id as usize)
+                .insert(track.track_id, track);
+        }
+        count
+    }
+
+    /// Bounds the merge history of the tracks of the store and of the tracks added later, `None`
+    /// keeps the whole history of the tracks added later
+    ///
+    pub fn set_merge_history_options(&mut self, options: Option<MergeHistoryOptions>) {
+        self.merge_history_options = options;
+        for s in 0..self.stores.len() {
+            let mut shard = self.poison_recovery.lock(&self.stores, s);
+            self.generations.touch(s);
+            shard
+                .values_mut()
+                .for_each(|t| t.set_merge_history_options(options));
+        }
+    }
+
+    /// Returns the bounds of the merge history of the tracks
+    ///
+    pub fn merge_history_options(&self) -> Option<MergeHistoryOptions> {
+        self.merge_history_options
+    }
+
+    /// The description of the store to check its compatibility with the other stores before the
+    /// tracks are exchanged with them
+    ///
+    pub fn descriptor(&self) -> StoreDescriptor {
+        let feature_classes = self
+            .feature_projections
+            .iter()
+            .map(|(class, projection)| (*class, projection.canonical_dim()))
+            .collect::<BTreeMap<_, _>>();
+        let mut feature_dimensions = BTreeMap::<u64, usize>::new();
+        for s in 0..self.stores.len() {
+            for track in self.poison_recovery.lock(&self.stores, s).values() {
+                for (class, observations) in &track.observations {
+                    for f in observations.iter().flat_map(|o| o.feature().as_ref()) {
+                        let dim = feature_dimensions.entry(*class).or_default();
+                        *dim = (*dim).max(f.len() * FEATURE_LANES_SIZE);
+                    }
+                }
+            }
+        }
+        StoreDescriptor::new(
+            SchemaId::new(TA::schema_name(), TA::schema_version()),
+            SchemaId::new(OA::schema_name(), OA::schema_version()),
+            SchemaId::new(M::schema_name(), M::schema_version()),
+            feature_classes,
+            feature_dimensions,
+            &[
+                ("shards", &self.num_shards),
+                ("shard_assignment", &self.shard_assignment),
+                ("nan_distance_policy", &self.nan_distance_policy()),
+                ("poison_policy", &self.poison_recovery.policy()),
+                ("merge_history", &self.merge_history_options),
+            ],
+        )
+    }
+
+    /// The serializable states of all the tracks of the store ordered by the track ids
+    ///
+    pub fn dump(&self) -> Vec<TrackState<TA, OA>> {
+        let mut states = (0..self.stores.len())
+            .flat_map(|s| {
+                self.poison_recovery
+                    .lock(&self.stores, s)
+                    .values()
+                    .map(TrackState::from)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        states.sort_by_key(|s| s.track_id);
+        states
+    }
+
+    /// Adds the track restored from the state to the store. The track gets the metric and the
+    /// notifier of the store, the feature projections are not applied as the features of the
+    /// state are already projected.
+    ///
+    pub fn restore(&mut self, state: TrackState<TA, OA>) -> Result<u64> {
+        let mut track = state.into_track(self.metric.clone(), self.notifier.clone());
+        if self.merge_history_options.is_some() {
+            track.set_merge_history_options(self.merge_history_options);
+        }
+        let track_id = track.track_id;
+        let mut store = self.get_store_mut(track_id as usize);
+        if store.get(&track_id).is_none() {
+            store.insert(track_id, track);
+            Ok(track_id)
+        } else {
+            Err(Errors::DuplicateTrackId(track_id).into())
+        }
+    }
+
     /// Counts of objects per every store shard
     ///
     pub fn shard_stats(&self) -> Vec<usize> {
         let mut result = Vec::new();
-        for s in self.stores.iter() {
-            result.push(s.lock().unwrap().len());
+        for s in 0..self.stores.len() {
+            result.push(self.poison_recovery.lock(&self.stores, s).len());
         }
         result
     }
 
+    /// The pool of the buffers the distances are returned in, the vectors returned by
+    /// [TrackDistanceOk::all](TrackDistanceOk::all) can be released to it when they are not needed
+    ///
+    pub fn distance_pool(&self) -> &DistanceBufferPool<OA> {
+        &self.distance_pool
+    }
+
+    /// Distance computation counters per every store shard accumulated since the store creation or
+    /// the last [reset_distance_stats](Self::reset_distance_stats) call
+    ///
+    pub fn distance_stats(&self) -> Vec<ShardDistanceStats> {
+        self.distance_stats.iter().map(|s| s.stats()).collect()
+    }
+
+    /// Resets the distance computation counters of all the shards
+    ///
+    pub fn reset_distance_stats(&self) {
+        for s in self.distance_stats.iter() {
+            s.reset();
+        }
+    }
+
+    /// Sets the detector that checks the distance computation counters for the slow shards before
+    /// every distance computation
+    ///
+    pub fn set_slow_shard_detector(&mut self, detector: Option<SlowShardDetector>) {
+        self.slow_shard_detector = detector;
+    }
+
+    /// Registers the projection that maps the features of the class to the canonical dimension when
+    /// they are ingested into the store
+    ///
+    pub fn set_feature_projection(
+        &mut self,
+        feature_class: impl FeatureClass,
+        projection: FeatureProjection,
+    ) {
+        self.feature_projections
+            .insert(feature_class.class_id(), projection);
+    }
+
+    /// Sets the true dimension of the features of the class. The features are kept padded to the
+    /// multiple of 8 values, the dimension is used to strip the padding when the features leave
+    /// the store, e.g. they are [exported](Self::export_arrow).
+    ///
+    pub fn set_feature_dimension(&mut self, feature_class: impl FeatureClass, dim: usize) {
+        self.feature_dimensions
+            .insert(feature_class.class_id(), dim);
+    }
+
+    /// The true dimensions of the feature classes: the ones set with
+    /// [set_feature_dimension](Self::set_feature_dimension) and the canonical dimensions of the
+    /// registered projections for the other classes
+    ///
+    pub fn feature_dimensions(&self) -> BTreeMap<u64, usize> {
+        let mut dimensions = self
+            .feature_projections
+            .iter()
+            .map(|(class, projection)| (*class, projection.canonical_dim()))
+            .collect::<BTreeMap<_, _>>();
+        dimensions.extend(self.feature_dimensions.iter().map(|(c, d)| (*c, *d)));
+        dimensions
+    }
+
+    fn project(&self, track: &mut Track<TA, M, OA, N>) -> Result<()> {
+        if self.feature_projections.is_empty() {
+            return Ok(());
+        }
+        track.project_features(&self.feature_projections)
+    }
+
+    /// Takes the read-only snapshot of the store.
+    ///
+    /// The shards are locked and copied one by one, so every shard of the snapshot is consistent
+    /// while the shards may be copied at different moments. Only the shards changed since the
+    /// previous snapshot are copied, the others are shared with the previous snapshot if it is
+    /// still alive, the tracking is not stalled while the snapshot is being analyzed. Every
+    /// snapshot is stamped with the generation of the latest change it contains.
+    ///
+    pub fn snapshot(&self) -> TrackStoreSnapshot<TA, M, OA, N> {
+        let mut cache = self.snapshot_cache.lock().unwrap();
+        let mut generation = 0;
+        let shards = (0..self.stores.len())
+            .map(|s| {
+                let shard = self.poison_recovery.lock(&self.stores, s);
+                let shard_generation = self.generations.shard(s);
+                generation = generation.max(shard_generation);
+                cache.reuse(s, shard_generation).unwrap_or_else(|| {
+                    let copy = Arc::new((*shard).clone());
+                    drop(shard);
+                    cache.update(s, shard_generation, &copy);
+                    copy
+                })
+            })
+            .collect();
+        TrackStoreSnapshot::new(generation, shards, self.shard_assignment.clone())
+    }
+
     /// Pulls (and removes) requested tracks from the store.
     ///
     pub fn fetch_tracks(&mut self, tracks: &[u64]) -> Vec<Track<TA, M, OA, N>> {
         let mut res = Vec::default();
         for track_id in tracks {
-            let mut tracks_shard = self.get_store(*track_id as usize);
+            let mut tracks_shard = self.get_store_mut(*track_id as usize);
             if let Some(t) = tracks_shard.remove(track_id) {
                 res.push(t);
             }
@@ -418,6 +1020,50 @@ where
             .notifier(self.notifier.clone())
     }
 
+    /// Reserves `count` sequential track ids, the ids reserved with
+    /// [reserve_track_id](Self::reserve_track_id) and the ids of the tracks in the store are
+    /// skipped
+    ///
+    pub fn reserve_track_ids(&self, count: usize) -> Range<u64> {
+        loop {
+            let ids = self.id_registry.reserve_range(count);
+            if ids
+                .clone()
+                .all(|id| !self.get_store(id as usize).contains_key(&id))
+            {
+                return ids;
+            }
+        }
+    }
+
+    /// Reserves the track id supplied by the user, so it is never generated by the trackers
+    ///
+    /// # Returns
+    /// * `Ok(track_id)` if reserved
+    /// * `Err(Errors::DuplicateTrackId(track_id))` if the id is already reserved or the track
+    ///   with the id is in the store
+    ///
+    pub fn reserve_track_id(&self, track_id: u64) -> Result<u64> {
+        if self.get_store(track_id as usize).contains_key(&track_id)
+            || !self.id_registry.reserve(track_id)
+        {
+            return Err(Errors::DuplicateTrackId(track_id).into());
+        }
+        Ok(track_id)
+    }
+
+    /// Releases the track id reserved with [reserve_track_id](Self::reserve_track_id)
+    ///
+    pub fn release_track_id(&self, track_id: u64) -> bool {
+        self.id_registry.release(track_id)
+    }
+
+    /// The registry of the track ids of the store
+    ///
+    pub fn id_registry(&self) -> &TrackIdRegistry {
+        &self.id_registry
+    }
+
     /// Calculates distances for external track (not in track store) to all tracks in DB which are
     /// allowed.
     ///
@@ -429,24 +1075,46 @@ where
     pub fn foreign_track_distances(
         &mut self,
         tracks: Vec<Track<TA, M, OA, N>>,
-        feature_class: u64,
+        feature_class: impl FeatureClass,
         only_baked: bool,
     ) -> (TrackDistanceOk<OA>, TrackDistanceErr<OA>) {
+        if let Some(detector) = &self.slow_shard_detector {
+            detector.check(&self.distance_stats());
+        }
+        let feature_class = feature_class.class_id();
+        let tracks = tracks
+            .into_iter()
+            .filter_map(|mut t| match self.project(&mut t) {
+                Ok(()) => Some(t),
+                Err(e) => {
+                    error!(
+                        "Unable to project features of the track {}, the track is skipped. Error is: {:?}",
+                        t.track_id, e
+                    );
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
         let tracks_count = tracks.len();
+        assert!(!self.executors.is_empty(), "The store is shut down");
 
         let (results_ok_sender, results_ok_receiver) = crossbeam::channel::unbounded();
         let (results_err_sender, results_err_receiver) = crossbeam::channel::unbounded();
 
         for track in tracks {
             let track = Arc::new(track);
-            for (cmd, _) in &mut self.executors {
-                cmd.send(Commands::Distances(
-                    track.clone(),
-                    feature_class,
-                    only_baked,
-                    results_ok_sender.clone(),
-                    results_err_sender.clone(),
-                ))
+            for executor_id in 0..self.executors.len() {
+                self.send(
+                    executor_id,
+                    Commands::Distances(
+                        track.clone(),
+                        feature_class,
+                        only_baked,
+                        self.distance_pool.clone(),
+                        results_ok_sender.clone(),
+                        results_err_sender.clone(),
+                    ),
+                )
                 .unwrap();
             }
         }
@@ -454,7 +1122,7 @@ where
         let count = self.executors.len() * tracks_count;
 
         (
-            TrackDistanceOk::new(count, results_ok_receiver),
+            TrackDistanceOk::new(count, results_ok_receiver, self.distance_pool.clone()),
             TrackDistanceErr::new(count, results_err_receiver),
         )
     }
@@ -471,7 +1139,7 @@ where
     pub fn owned_track_distances(
         &mut self,
         tracks: &[u64],
-        feature_class: u64,
+        feature_class: impl FeatureClass,
         only_baked: bool,
     ) -> (TrackDistanceOk<OA>, TrackDistanceErr<OA>) {
         let tracks_vec = self.fetch_tracks(tracks);
@@ -485,17 +1153,27 @@ where
         res
     }
 
-    /// returns the store shard for id
+    /// returns the read-only store shard for id
     ///
-    pub fn get_store(&self, id: usize) -> StoreMutexGuard<'_, TA, M, OA, N> {
-        let store_id = id % self.num_shards;
-        self.stores.as_ref().get(store_id).unwrap().lock().unwrap()
+    pub fn get_store(&self, id: usize) -> StoreReadGuard<'_, TA, M, OA, N> {
+        let store_id = self.get_executor(id);
+        StoreReadGuard(self.poison_recovery.lock(&self.stores, store_id))
+    }
+
+    /// returns the store shard for id, the shard is considered changed by the snapshots because
+    /// the tracks may be modified through the guard
+    ///
+    pub fn get_store_mut(&self, id: usize) -> StoreMutexGuard<'_, TA, M, OA, N> {
+        let store_id = self.get_executor(id);
+        let shard = self.poison_recovery.lock(&self.stores, store_id);
+        self.generations.touch(store_id);
+        shard
     }
 
     /// returns the store shard for id
     ///
     pub fn get_executor(&self, id: usize) -> usize {
-        id % self.num_shards
+        self.shard_assignment.shard(id as u64, self.num_shards)
     }
 
     /// Adds external track into storage
@@ -507,9 +1185,13 @@ where
     /// * `Ok(track_id)` if added
     /// * `Err(Errors::DuplicateTrackId(track_id))` if failed to add
     ///
-    pub fn add_track(&mut self, track: Track<TA, M, OA, N>) -> Result<u64> {
+    pub fn add_track(&mut self, mut track: Track<TA, M, OA, N>) -> Result<u64> {
+        self.project(&mut track)?;
+        if self.merge_history_options.is_some() {
+            track.set_merge_history_options(self.merge_history_options);
+        }
         let track_id = track.track_id;
-        let mut store = self.get_store(track_id as usize);
+        let mut store = self.get_store_mut(track_id as usize);
         if store.get(&track_id).is_none() {
             store.insert(track_id, track);
             Ok(track_id)
@@ -530,12 +1212,17 @@ where
     pub fn add(
         &mut self,
         track_id: u64,
-        feature_class: u64,
+        feature_class: impl FeatureClass,
         feature_attribute: Option<OA>,
         feature: Option<Feature>,
         attributes_update: Option<TA::Update>,
     ) -> Result<()> {
-        let mut tracks = self.get_store(track_id as usize);
+        let feature_class = feature_class.class_id();
+        let feature = match (feature, self.feature_projections.get(&feature_class)) {
+            (Some(f), Some(p)) => Some(p.project(&f)?),
+            (f, _) => f,
+        };
+        let mut tracks = self.get_store_mut(track_id as usize);
         #[allow(clippy::significant_drop_in_scrutinee)]
         match tracks.get_mut(&track_id) {
             None => {
@@ -548,8 +1235,9 @@ where
                         vec![Observation(feature_attribute, feature)],
                     )]),
                     metric: self.metric.clone(),
-                    merge_history: vec![track_id],
+                    merge_history: MergeHistory::new(track_id),
                 };
+                t.set_merge_history_options(self.merge_history_options);
                 if let Some(attributes_update) = &attributes_update {
                     t.update_attributes(attributes_update)?;
                 }
@@ -610,6 +1298,58 @@ where
         }
     }
 
+    /// Simulates the merge of the owned tracks without modification of the store
+    ///
+    /// The copy of the destination track is merged with the source track, so attributes merge and
+    /// metric optimization are executed as for the real merge, the change notifications are not sent.
+    ///
+    /// # Arguments
+    /// * `dest_id` - identifier of destination track
+    /// * `src_id` - identifier of source track
+    /// * `classes` - optional list of classes to merge (otherwise all defined in src are merged into dest)
+    /// * `merge_history` - configures whether merge history is built upon track merging.
+    ///
+    /// # Return
+    /// * `Ok(MergeDryRun)` - the outcome of the merge
+    /// * `Err(e)` - one of the tracks is not found or the tracks are the same
+    ///
+    pub fn merge_dry_run(
+        &self,
+        dest_id: u64,
+        src_id: u64,
+        classes: Option<&[u64]>,
+        merge_history: bool,
+    ) -> Result<MergeDryRun<TA>> {
+        if dest_id == src_id {
+            return Err(Errors::SameTrackCalculation(dest_id).into());
+        }
+        let mut dest = self
+            .get_store(dest_id as usize)
+            .get(&dest_id)
+            .cloned()
+            .ok_or(Errors::TrackNotFound(dest_id))?;
+        let src = self
+            .get_store(src_id as usize)
+            .get(&src_id)
+            .cloned()
+            .ok_or(Errors::TrackNotFound(src_id))?;
+
+        let classes = match classes {
+            Some(c) => c.to_vec(),
+            None => src.get_feature_classes(),
+        };
+        let error = dest.merge_silent(&src, &classes, merge_history).err();
+        Ok(MergeDryRun {
+            observation_counts: dest
+                .observations
+                .iter()
+                .map(|(cls, o)| (*cls, o.len()))
+                .collect(),
+            attributes: dest.attributes,
+            error,
+        })
+    }
+
     /// Merge external track with destination stored in store without blocking
     ///
     /// # Arguments
@@ -625,13 +1365,13 @@ where
     pub fn merge_external_noblock(
         &mut self,
         dest_id: u64,
-        src: Track<TA, M, OA, N>,
+        mut src: Track<TA, M, OA, N>,
         classes: Option<&[u64]>,
         merge_history: bool,
     ) -> Result<FutureMergeResponse<OA>> {
+        self.project(&mut src)?;
         let (results_sender, results_receiver) = crossbeam::channel::bounded(1);
         let executor_id = self.get_executor(dest_id as usize);
-        let (cmd, _) = self.executors.get_mut(executor_id).unwrap();
 
         let command = Commands::Merge(
             dest_id,
@@ -645,7 +1385,7 @@ where
             Some(results_sender.clone()),
         );
 
-        let res = cmd.send(command);
+        let res = self.send(executor_id, command);
 
         if res.is_err() {
             error!(
@@ -681,27 +1421,51 @@ where
         classes: Option<&[u64]>,
         merge_history: bool,
     ) -> Result<()> {
-        let res = self.merge_external_noblock(dest_id, src.clone(), classes, merge_history);
-        if let Ok(res) = res {
-            res.get()
-        } else {
-            res?;
-            unreachable!();
-        }
+        self.merge_external_owned(dest_id, src.clone(), classes, merge_history)
     }
 
-    /// Method is used to find tracks that match lookup query.
+    /// Merge external track with destination stored in store. Unlike [merge_external](Self::merge_external)
+    /// the source track is moved to the executor, so its observations and features are not copied.
+    ///
+    /// # Arguments
+    /// * `dest_id` - identifier of destination track
+    /// * `src` - source track
+    /// * `classes` - optional list of classes to merge (otherwise all defined in src are merged into dest)
+    /// * `merge_history` - configures whether merge history is built upon track merging.
+    ///
+    /// # Return
+    /// * `Ok(())` - merge was successful
+    /// * `Err(e)` - merge met problems
+    ///
+    pub fn merge_external_owned(
+        &mut self,
+        dest_id: u64,
+        src: Track<TA, M, OA, N>,
+        classes: Option<&[u64]>,
+        merge_history: bool,
+    ) -> Result<()> {
+        self.merge_external_noblock(dest_id, src, classes, merge_history)?
+            .get()
+    }
+
+    /// Method is used to find tracks that match lookup query. Besides the lookup of the attributes,
+    /// the query may be the closure or the combination of the lookups, look at
+    /// [lookup](crate::track::lookup).
     ///
     /// The search is parallelized with Rayon. The results returned for tracks with their statuses.
     ///
-    pub fn lookup(&self, q: TA::Lookup) -> Vec<(u64, Result<TrackStatus>)> {
+    pub fn lookup<L: LookupRequest<TA, OA>>(&self, q: L) -> Vec<(u64, Result<TrackStatus>)> {
+        let q = q.boxed();
         let mut results = Vec::with_capacity(self.shard_stats().iter().sum());
         let (results_sender, results_receiver) = crossbeam::channel::unbounded();
-        for (cmd, _) in &self.executors {
-            cmd.send(Commands::Lookup(q.clone(), results_sender.clone()))
-                .unwrap();
+        for executor_id in 0..self.executors.len() {
+            self.send(
+                executor_id,
+                Commands::Lookup(q.clone(), results_sender.clone()),
+            )
+            .unwrap();
         }
-        for (_, _) in &self.executors {
+        for _ in &self.executors {
             let res = results_receiver.recv().unwrap();
             match res {
                 Results::BakedStatus(r) => {
@@ -715,11 +1479,108 @@ where
         results
     }
 
+    /// Finds the tracks matching any of the lookups within a single pass over the shards. Every
+    /// track is assigned to the first lookup it matches, so its status is evaluated once.
+    ///
+    /// # Returns
+    /// the ids and the statuses of the tracks matching every lookup, in the order of the lookups
+    ///
+    pub fn lookup_grouped<L: LookupRequest<TA, OA>>(
+        &self,
+        lookups: Vec<L>,
+    ) -> Vec<Vec<(u64, Result<TrackStatus>)>> {
+        let lookups = lookups.into_iter().map(|l| l.boxed()).collect::<Vec<_>>();
+        let mut results = lookups.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+        if lookups.is_empty() {
+            return results;
+        }
+        let (results_sender, results_receiver) = crossbeam::channel::unbounded();
+        for executor_id in 0..self.executors.len() {
+            self.send(
+                executor_id,
+                Commands::GroupedLookup(lookups.clone(), results_sender.clone()),
+            )
+            .unwrap();
+        }
+        for _ in &self.executors {
+            match results_receiver.recv().unwrap() {
+                Results::GroupedStatus(r) => {
+                    for (group, track_id, status) in r {
+                        results[group].push((track_id, status));
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+        results
+    }
+
+    /// Applies the attribute update to all the tracks matching the filter within the shard
+    /// executors, e.g. to rebase the epochs after the clock change or to change the flag of the
+    /// scene, without fetching the tracks from the store. Use
+    /// [NoopLookup<TA, OA, true>](crate::track::NoopLookup) to update all the tracks.
+    ///
+    /// # Returns
+    /// the results of the update of the matched tracks
+    ///
+    pub fn update_all<L: LookupRequest<TA, OA>>(
+        &self,
+        update: TA::Update,
+        filter: L,
+    ) -> Vec<(u64, Result<()>)> {
+        let filter = filter.boxed();
+        let mut results = Vec::new();
+        let (results_sender, results_receiver) = crossbeam::channel::unbounded();
+        for executor_id in 0..self.executors.len() {
+            self.send(
+                executor_id,
+                Commands::UpdateAttributes(update.clone(), filter.clone(), results_sender.clone()),
+            )
+            .unwrap();
+        }
+        for _ in &self.executors {
+            match results_receiver.recv().unwrap() {
+                Results::UpdateResults(r) => results.extend(r),
+                _ => unreachable!(),
+            }
+        }
+        results
+    }
+
+    /// Notifies all the tracks of the store that the epoch of the scene advanced, look at
+    /// [TrackAttributes::on_epoch](TrackAttributes::on_epoch)
+    ///
+    pub fn on_epoch(&self, epoch: usize, scene_id: u64) {
+        self.on_epoch_with(epoch, scene_id, NoopLookup::<TA, OA, true>::default())
+    }
+
+    /// Notifies the tracks matching the filter that the epoch of the scene advanced, the tracks
+    /// are notified within the shard executors
+    ///
+    pub fn on_epoch_with<L: LookupRequest<TA, OA>>(&self, epoch: usize, scene_id: u64, filter: L) {
+        let filter = filter.boxed();
+        let (results_sender, results_receiver) = crossbeam::channel::unbounded();
+        for executor_id in 0..self.executors.len() {
+            self.send(
+                executor_id,
+                Commands::Epoch(epoch, scene_id, filter.clone(), results_sender.clone()),
+            )
+            .unwrap();
+        }
+        for _ in &self.executors {
+            match results_receiver.recv().unwrap() {
+                Results::EpochNotified => {}
+                _ => unreachable!(),
+            }
+        }
+    }
+
     /// clears all the tracks from the store
     ///
     pub fn clear(&self) {
-        for s in self.stores.as_ref() {
-            let mut lock = s.lock().unwrap();
+        for s in 0..self.stores.len() {
+            let mut lock = self.poison_recovery.lock(&self.stores, s);
+            self.generations.touch(s);
             lock.clear();
         }
     }
@@ -0,0 +1,494 @@
+use crate::track::notify::ChangeNotifier;
+use crate::track::state::{ObservationState, TrackState};
+use crate::track::store::TrackStore;
+use crate::track::{ObservationAttributes, ObservationMetric, TrackAttributes};
+use crate::utils::arrow::{read_stream, write_stream, ArrowArray, ArrowTable};
+use crate::Errors;
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+
+fn malformed(message: impl Into<String>) -> anyhow::Error {
+    Errors::ArrowStream(message.into()).into()
+}
+
+/// Writes the summaries of the tracks as the Arrow IPC stream.
+///
+/// Columns:
+/// * `track_id` - `uint64`;
+/// * `attributes` - the track attributes, the type is inferred from their serialized form, e.g.
+///   the attributes serialized as the JSON object are written as `struct` with a field per key,
+///   see the types below;
+/// * `merge_history` - `list<uint64>`, the kept ids of the merge history, the bounds of the
+///   history are not exported;
+/// * `observations` - `uint64`, the number of the observations of the track (all the classes).
+///
+/// The attributes are converted to the columns with their JSON representation: the booleans
+/// become `bool`, the integers `int64` (`uint64` above `i64::MAX`), the other numbers `float64`,
+/// the strings `utf8`, the objects `struct`, the arrays `list`. The values of the mixed kinds are
+/// kept in the `utf8` column with the `arrow.json` extension type. The optional values which are
+/// absent in all the rows are written as the null `utf8` column.
+///
+pub fn write_track_summaries<W, TA, OA>(w: W, states: &[TrackState<TA, OA>]) -> Result<()>
+where
+    W: Write,
+    TA: Serialize,
+{
+    let attributes = states
+        .iter()
+        .map(|s| serde_json::to_value(&s.attributes))
+        .collect::<serde_json::Result<Vec<_>>>()?;
+    write_stream(
+        w,
+        &[
+            (
+                "track_id",
+                ArrowArray::UInt64(states.iter().map(|s| Some(s.track_id)).collect()),
+            ),
+            ("attributes", ArrowArray::from_json(attributes)),
+            (
+                "merge_history",
+                ArrowArray::list(
+                    states
+                        .iter()
+                        .map(|s| Some(s.merge_history.ids().clone()))
+                        .collect(),
+                    ArrowArray::UInt64,
+                ),
+            ),
+            (
+                "observations",
+                ArrowArray::UInt64(
+                    states
+                        .iter()
+                        .map(|s| Some(s.observations.iter().map(|(_, o)| o.len() as u64).sum()))
+                        .collect(),
+                ),
+            ),
+        ],
+    )
+}
+
+/// Writes the observations of the tracks as the Arrow IPC stream, one row per observation in the
+/// order they are kept in the tracks.
+///
+/// Columns:
+/// * `track_id` - `uint64`;
+/// * `feature_class` - `uint64`;
+/// * `attributes` - the observation attributes, null if absent, the type is inferred like for
+///   the [track attributes](write_track_summaries);
+/// * `feature_<class>` - `fixed_size_list<float32, dim>` per feature class with the features,
+///   null in the rows of the other classes and in the rows without the feature. The dimension of
+///   the class is taken from `dimensions`, the features are kept in the tracks padded with zeros
+///   to the multiple of 8 values and the padding is stripped. The classes absent in `dimensions`
+///   are written with the padded dimension.
+///
+/// # Errors
+/// [ArrowStream](Errors::ArrowStream) if the feature has non-zero values beyond the dimension of
+/// its class
+///
+pub fn write_observations<W, TA, OA>(
+    w: W,
+    states: &[TrackState<TA, OA>],
+    dimensions: &BTreeMap<u64, usize>,
+) -> Result<()>
+where
+    W: Write,
+    OA: Serialize,
+{
+    let rows = states
+        .iter()
+        .flat_map(|s| {
+            s.observations.iter().flat_map(move |(feature_class, o)| {
+                o.iter().map(move |o| (s.track_id, *feature_class, o))
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let attributes = rows
+        .iter()
+        .map(|(_, _, o)| match &o.attributes {
+            Some(a) => serde_json::to_value(a),
+            None => Ok(serde_json::Value::Null),
+        })
+        .collect::<serde_json::Result<Vec<_>>>()?;
+    let mut padded = BTreeMap::<u64, usize>::new();
+    for (_, feature_class, o) in &rows {
+        if let Some(f) = &o.feature {
+            let dim = padded.entry(*feature_class).or_default();
+            *dim = (*dim).max(f.len());
+        }
+    }
+
+    let mut columns = vec![
+        (
+            "track_id".to_string(),
+            ArrowArray::UInt64(rows.iter().map(|(id, _, _)| Some(*id)).collect()),
+        ),
+        (
+            "feature_class".to_string(),
+            ArrowArray::UInt64(rows.iter().map(|(_, fc, _)| Some(*fc)).collect()),
+        ),
+        ("attributes".to_string(), ArrowArray::from_json(attributes)),
+    ];
+    for (class, padded_dim) in padded {
+        let dim = dimensions.get(&class).copied().unwrap_or(padded_dim);
+        let features = rows
+            .iter()
+            .map(|(track_id, feature_class, o)| match &o.feature {
+                Some(f) if *feature_class == class => {
+                    if f.iter().skip(dim).any(|x| *x != 0.0) {
+                        return Err(malformed(format!(
+                            "feature of track={track_id} has more than {dim} values of class={class}"
+                        )));
+                    }
+                    let mut f = f.clone();
+                    f.resize(dim, 0.0);
+                    Ok(Some(f))
+                }
+                _ => Ok(None),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        columns.push((
+            format!("feature_{class}"),
+            ArrowArray::fixed_size_list(dim, features, ArrowArray::Float32),
+        ));
+    }
+    write_stream(w, &columns)
+}
+
+/// The values of the required column over all the record batches
+///
+fn column<T>(
+    table: &ArrowTable,
+    name: &str,
+    values: impl Fn(&ArrowArray) -> Result<Vec<T>>,
+) -> Result<Vec<T>> {
+    let index = table
+        .column(name)
+        .ok_or_else(|| malformed(format!("missing column {name}")))?;
+    let mut res = Vec::new();
+    for batch in &table.batches {
+        res.extend(values(&batch[index])?);
+    }
+    Ok(res)
+}
+
+fn ids(table: &ArrowTable, name: &str) -> Result<Vec<u64>> {
+    column(table, name, ArrowArray::u64_values)?
+        .into_iter()
+        .map(|id| id.ok_or_else(|| malformed(format!("null value in column {name}"))))
+        .collect()
+}
+
+/// Reads the track states from the streams written with [write_track_summaries] and
+/// [write_observations].
+///
+/// The streams are accepted if they have the same columns, the integer columns may be of any
+/// width, the floating point columns may be of the double precision and the lists may be of any
+/// kind, so the tables edited with pandas or polars can be loaded back. The feature of the
+/// observation is read from the `feature_<class>` column of its class, the features are padded
+/// back to the multiple of 8 values when the tracks are restored. The attributes are
+/// deserialized from the JSON representation of the columns, the null fields of the structs are
+/// treated as absent. The `merge_history` and
+/// `observations` columns of the summaries are optional, the absent merge history is the track
+/// itself, the observation counters are ignored.
+///
+/// The states are returned in the order of the summaries, the observations of every feature
+/// class keep the order of the rows.
+///
+pub fn read_track_states<R1, R2, TA, OA>(
+    summaries: R1,
+    observations: R2,
+) -> Result<Vec<TrackState<TA, OA>>>
+where
+    R1: Read,
+    R2: Read,
+    TA: DeserializeOwned,
+    OA: DeserializeOwned,
+{
+    let summaries = read_stream(summaries)?;
+    let track_ids = ids(&summaries, "track_id")?;
+    let attributes = column(&summaries, "attributes", ArrowArray::json_values)?;
+    let merge_history = match summaries.column("merge_history") {
+        Some(_) => column(&summaries, "merge_history", |a| {
+            a.lists(ArrowArray::u64_values)
+        })?,
+        None => vec![None; track_ids.len()],
+    };
+
+    let mut states = Vec::with_capacity(track_ids.len());
+    let mut positions = HashMap::with_capacity(track_ids.len());
+    for ((track_id, attributes), merge_history) in
+        track_ids.into_iter().zip(attributes).zip(merge_history)
+    {
+        if attributes.is_null() {
+            return Err(malformed(format!("track={track_id} has null attributes")));
+        }
+        if positions.insert(track_id, states.len()).is_some() {
+            return Err(Errors::DuplicateTrackId(track_id).into());
+        }
+        states.push(TrackState {
+            track_id,
+            attributes: serde_json::from_value(attributes)?,
+            observations: Vec::new(),
+            merge_history: merge_history.unwrap_or_else(|| vec![track_id]).into(),
+        });
+    }
+
+    let observations = read_stream(observations)?;
+    let track_ids = ids(&observations, "track_id")?;
+    let feature_classes = ids(&observations, "feature_class")?;
+    let attributes = column(&observations, "attributes", ArrowArray::json_values)?;
+    let mut features = HashMap::new();
+    for name in &observations.names {
+        if let Some(class) = name
+            .strip_prefix("feature_")
+            .and_then(|class| class.parse::<u64>().ok())
+        {
+            let lists = column(&observations, name, |a| a.lists(ArrowArray::f32_values))?;
+            features.insert(class, lists);
+        }
+    }
+    for (row, ((track_id, feature_class), attributes)) in track_ids
+        .into_iter()
+        .zip(feature_classes)
+        .zip(attributes)
+        .enumerate()
+    {
+        let feature = features
+            .get_mut(&feature_class)
+            .and_then(|lists| lists.get_mut(row))
+            .and_then(Option::take);
+        let position = positions.get(&track_id).ok_or_else(|| {
+            malformed(format!(
+                "observation of track={track_id} missing in the summaries"
+            ))
+        })?;
+        let observation = ObservationState {
+            attributes: match attributes {
+                serde_json::Value::Null => None,
+                a => Some(serde_json::from_value(a)?),
+            },
+            feature,
+        };
+        let classes = &mut states[*position].observations;
+        match classes.iter_mut().find(|(fc, _)| *fc == feature_class) {
+            Some((_, o)) => o.push(observation),
+            None => classes.push((feature_class, vec![observation])),
+        }
+    }
+    for s in states.iter_mut() {
+        s.observations
+            .sort_by_key(|(feature_class, _)| *feature_class);
+    }
+    Ok(states)
+}
+
+impl<TA, M, OA, N> TrackStore<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA> + Serialize + DeserializeOwned,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes + Serialize + DeserializeOwned,
+    N: ChangeNotifier,
+{
+    /// Exports the tracks of the store as two Arrow IPC streams: the track summaries and the
+    /// observations, see [write_track_summaries] and [write_observations] for the columns. The
+    /// features are written with the [dimensions](Self::feature_dimensions) of their classes.
+    ///
+    pub fn export_arrow<W1: Write, W2: Write>(
+        &self,
+        summaries: W1,
+        observations: W2,
+    ) -> Result<()> {
+        let states = self.dump();
+        write_track_summaries(summaries, &states)?;
+        write_observations(observations, &states, &self.feature_dimensions())
+    }
+
+    /// Bulk-loads the tracks from the Arrow IPC streams, see [read_track_states] for the
+    /// accepted streams. The tracks are added like [restore](Self::restore) does, the tracks with
+    /// the ids already present in the store are not added.
+    ///
+    /// # Returns
+    /// the results of the tracks addition in the order of the summaries
+    ///
+    pub fn import_arrow<R1: Read, R2: Read>(
+        &mut self,
+        summaries: R1,
+        observations: R2,
+    ) -> Result<Vec<Result<u64>>> {
+        Ok(read_track_states(summaries, observations)?
+            .into_iter()
+            .map(|s| self.restore(s))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, SimpleAttrs, SimpleMetric};
+    use crate::track::projection::FeatureProjection;
+    use crate::track::store::arrow::{read_track_states, write_observations};
+    use crate::track::store::TrackStore;
+    use crate::track::utils::FromVec;
+    use crate::track::Feature;
+    use crate::track::NoopNotifier;
+    use crate::utils::arrow::{read_stream, write_stream, ArrowArray};
+    use crate::Errors;
+    use anyhow::Result;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn export_import() -> Result<()> {
+        let mut store = TrackStore::new(SimpleMetric, SimpleAttrs::default(), NoopNotifier, 2);
+        store.add(1, 0, Some(0.9), Some(vec2(0.0, 1.0)), None)?;
+        store.add(1, 0, None, Some(vec2(1.0, 1.0)), None)?;
+        store.add(1, 1, Some(0.5), None, None)?;
+        store.add(2, 0, Some(0.7), Some(vec2(0.5, 0.5)), None)?;
+
+        let (mut summaries, mut observations) = (Vec::new(), Vec::new());
+        store.export_arrow(&mut summaries, &mut observations)?;
+
+        let mut gallery = TrackStore::new(SimpleMetric, SimpleAttrs::default(), NoopNotifier, 3);
+        let added = gallery.import_arrow(summaries.as_slice(), observations.as_slice())?;
+        assert_eq!(added.into_iter().collect::<Result<Vec<_>>>()?, vec![1, 2]);
+        assert_eq!(gallery.shard_stats().iter().sum::<usize>(), 2);
+
+        let original = store.dump();
+        let imported = gallery.dump();
+        for (o, i) in original.iter().zip(imported.iter()) {
+            assert_eq!(o.track_id, i.track_id);
+            assert_eq!(o.merge_history, i.merge_history);
+            assert_eq!(
+                serde_json::to_string(&o.observations)?,
+                serde_json::to_string(&i.observations)?
+            );
+        }
+
+        let added = gallery.import_arrow(summaries.as_slice(), observations.as_slice())?;
+        assert!(added.iter().all(Result::is_err));
+        Ok(())
+    }
+
+    #[test]
+    fn variable_dimensions() -> Result<()> {
+        let mut store = TrackStore::new(SimpleMetric, SimpleAttrs::default(), NoopNotifier, 1);
+        store.add(1, 0, None, Some(vec2(0.0, 1.0)), None)?;
+        store.add(1, 1, None, Some(Feature::from_vec(vec![1.0; 9])), None)?;
+        let states = store.dump();
+
+        let mut observations = Vec::new();
+        write_observations(&mut observations, &states, &BTreeMap::from([(1, 9)]))?;
+        let table = read_stream(observations.as_slice())?;
+        let sizes = ["feature_0", "feature_1"].map(|name| {
+            match &table.batches[0][table.column(name).unwrap()] {
+                ArrowArray::FixedSizeList { validity, size, .. } => (validity.clone(), *size),
+                _ => unreachable!(),
+            }
+        });
+        // the class without the dimension keeps the padding
+        assert_eq!(sizes, [(vec![true, false], 8), (vec![false, true], 9)]);
+        let mut summaries = Vec::new();
+        // the summaries edited outside: no merge history, the ids are signed
+        write_stream(
+            &mut summaries,
+            &[
+                ("track_id", ArrowArray::Int64(vec![Some(1)])),
+                (
+                    "attributes",
+                    ArrowArray::Struct {
+                        validity: vec![true],
+                        fields: vec![("set".into(), ArrowArray::Boolean(vec![Some(true)]))],
+                    },
+                ),
+            ],
+        )?;
+        let restored = read_track_states::<_, _, SimpleAttrs, f32>(
+            summaries.as_slice(),
+            observations.as_slice(),
+        )?;
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].merge_history.ids(), &vec![1]);
+        assert_eq!(restored[0].observations[1].1[0].feature, Some(vec![1.0; 9]));
+        assert_eq!(
+            restored[0].observations[0].1[0].feature,
+            Some(vec![0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0])
+        );
+
+        // the dimension cuts the values of the feature
+        let err =
+            write_observations(&mut Vec::new(), &states, &BTreeMap::from([(1, 8)])).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Errors>(),
+            Some(Errors::ArrowStream(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn store_feature_dimensions() -> Result<()> {
+        let mut store = TrackStore::new(SimpleMetric, SimpleAttrs::default(), NoopNotifier, 1);
+        store.set_feature_projection(0, FeatureProjection::new(2));
+        store.set_feature_dimension(1, 3);
+        assert_eq!(store.feature_dimensions(), BTreeMap::from([(0, 2), (1, 3)]));
+        store.add(1, 0, None, Some(vec2(0.0, 1.0)), None)?;
+        store.add(
+            1,
+            1,
+            None,
+            Some(Feature::from_vec(vec![1.0, 2.0, 3.0])),
+            None,
+        )?;
+
+        let (mut summaries, mut observations) = (Vec::new(), Vec::new());
+        store.export_arrow(&mut summaries, &mut observations)?;
+        let restored = read_track_states::<_, _, SimpleAttrs, f32>(
+            summaries.as_slice(),
+            observations.as_slice(),
+        )?;
+        let features = restored[0]
+            .observations
+            .iter()
+            .map(|(_, o)| o[0].feature.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(features, vec![vec![0.0, 1.0], vec![1.0, 2.0, 3.0]]);
+
+        // the imported features are padded back
+        let mut gallery = TrackStore::new(SimpleMetric, SimpleAttrs::default(), NoopNotifier, 1);
+        gallery.import_arrow(summaries.as_slice(), observations.as_slice())?;
+        assert_eq!(
+            serde_json::to_string(&gallery.dump())?,
+            serde_json::to_string(&store.dump())?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn missing_track() -> Result<()> {
+        let mut store = TrackStore::new(SimpleMetric, SimpleAttrs::default(), NoopNotifier, 1);
+        store.add(5, 0, None, Some(vec2(0.0, 1.0)), None)?;
+        let mut observations = Vec::new();
+        write_observations(&mut observations, &store.dump(), &BTreeMap::new())?;
+        let mut summaries = Vec::new();
+        write_stream(
+            &mut summaries,
+            &[
+                ("track_id", ArrowArray::UInt64(vec![])),
+                ("attributes", ArrowArray::Utf8(vec![])),
+            ],
+        )?;
+        let err = read_track_states::<_, _, SimpleAttrs, f32>(
+            summaries.as_slice(),
+            observations.as_slice(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Errors>(),
+            Some(Errors::ArrowStream(_))
+        ));
+        Ok(())
+    }
+}
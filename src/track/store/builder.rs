@@ -1,6 +1,13 @@
 use crate::store::TrackStore;
+use crate::track::feature_class::FeatureClass;
+use crate::track::merge_history::MergeHistoryOptions;
 use crate::track::notify::{ChangeNotifier, NoopNotifier};
+use crate::track::projection::{FeatureProjection, FeatureProjections};
+use crate::track::store::nan_distances::NanDistancePolicy;
+use crate::track::store::recovery::ShardPoisonPolicy;
+use crate::track::store::sharding::ShardAssignment;
 use crate::track::{ObservationAttributes, ObservationMetric, TrackAttributes};
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
 
 /// Builder for TrackStore
@@ -16,6 +23,12 @@ where
     default_attributes: Option<TA>,
     notifier: Option<N>,
     shards: usize,
+    feature_projections: FeatureProjections,
+    feature_dimensions: BTreeMap<u64, usize>,
+    poison_policy: ShardPoisonPolicy,
+    nan_policy: NanDistancePolicy,
+    merge_history_options: Option<MergeHistoryOptions>,
+    shard_assignment: ShardAssignment,
     _phantom_oa: PhantomData<OA>,
 }
 
@@ -52,6 +65,12 @@ where
             metric: None,
             default_attributes: None,
             notifier: None,
+            feature_projections: FeatureProjections::default(),
+            feature_dimensions: BTreeMap::default(),
+            poison_policy: ShardPoisonPolicy::default(),
+            nan_policy: NanDistancePolicy::default(),
+            merge_history_options: None,
+            shard_assignment: ShardAssignment::default(),
             _phantom_oa: PhantomData,
         }
     }
@@ -89,14 +108,74 @@ where
         self
     }
 
+    /// Registers the projection that maps the features of the class to the canonical dimension
+    ///
+    pub fn feature_projection(
+        mut self,
+        feature_class: impl FeatureClass,
+        projection: FeatureProjection,
+    ) -> Self {
+        self.feature_projections
+            .insert(feature_class.class_id(), projection);
+        self
+    }
+
+    /// Sets the true dimension of the features of the class, see
+    /// [set_feature_dimension](TrackStore::set_feature_dimension)
+    ///
+    pub fn feature_dimension(mut self, feature_class: impl FeatureClass, dim: usize) -> Self {
+        self.feature_dimensions
+            .insert(feature_class.class_id(), dim);
+        self
+    }
+
+    /// Sets what is done with the tracks of the shard which lock was poisoned by a panic
+    ///
+    pub fn poison_policy(mut self, policy: ShardPoisonPolicy) -> Self {
+        self.poison_policy = policy;
+        self
+    }
+
+    /// Sets what is done with the NaN distances returned by the metric
+    ///
+    pub fn nan_distance_policy(mut self, policy: NanDistancePolicy) -> Self {
+        self.nan_policy = policy;
+        self
+    }
+
+    /// Bounds the merge history of the tracks
+    ///
+    pub fn merge_history(mut self, options: MergeHistoryOptions) -> Self {
+        self.merge_history_options = Some(options);
+        self
+    }
+
+    /// Sets how the tracks are assigned to the shards
+    ///
+    pub fn shard_assignment(mut self, assignment: ShardAssignment) -> Self {
+        self.shard_assignment = assignment;
+        self
+    }
+
     /// Builds the TrackStore
     ///
     pub fn build(self) -> TrackStore<TA, M, OA, N> {
-        TrackStore::new(
+        let mut store = TrackStore::new(
             self.metric.unwrap(),
             self.default_attributes.unwrap(),
             self.notifier.unwrap(),
             self.shards,
-        )
+        );
+        for (feature_class, projection) in self.feature_projections {
+            store.set_feature_projection(feature_class, projection);
+        }
+        for (feature_class, dim) in self.feature_dimensions {
+            store.set_feature_dimension(feature_class, dim);
+        }
+        store.set_poison_policy(self.poison_policy);
+        store.set_nan_distance_policy(self.nan_policy);
+        store.set_merge_history_options(self.merge_history_options);
+        store.rebalance(self.shard_assignment);
+        store
     }
 }
@@ -0,0 +1,133 @@
+use crate::Errors;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+/// The version of the descriptor layout, changed when the descriptors become incomparable with
+/// the descriptors of the previous versions
+///
+pub const STORE_DESCRIPTOR_VERSION: u32 = 2;
+
+/// The stable name and version of the type, declared by the type itself
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaId {
+    pub name: String,
+    pub version: u32,
+}
+
+impl SchemaId {
+    pub fn new(name: &str, version: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            version,
+        }
+    }
+}
+
+impl std::fmt::Display for SchemaId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.name, self.version)
+    }
+}
+
+/// Self-description of the track store.
+///
+/// The descriptor is exchanged between the processes before the tracks are moved between the
+/// stores, e.g. the snapshot is restored, the store is replicated or the distances are computed
+/// against the foreign tracks, so the incompatible stores are detected before the data is touched.
+/// The types are identified by the names and the versions they declare with `schema_name` and
+/// `schema_version` of [TrackAttributes](crate::track::TrackAttributes),
+/// [ObservationAttributes](crate::track::ObservationAttributes) and
+/// [ObservationMetric](crate::track::ObservationMetric), so the descriptors don't depend on the
+/// compiler. The compatibility is decided by [check_compatible](Self::check_compatible), the
+/// options are kept for the diagnostics only.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreDescriptor {
+    /// The version of the descriptor layout
+    pub version: u32,
+    /// The track attributes
+    pub attributes: SchemaId,
+    /// The observation attributes
+    pub observation_attributes: SchemaId,
+    /// The metric
+    pub metric: SchemaId,
+    /// The canonical dimensions of the feature classes with the registered projections
+    pub feature_classes: BTreeMap<u64, usize>,
+    /// The dimensions of the features kept by the tracks, padded to the multiple of 8 values as
+    /// the features are stored; the largest one if the class keeps the features of the different
+    /// dimensions
+    pub feature_dimensions: BTreeMap<u64, usize>,
+    /// The options of the store
+    pub options: BTreeMap<String, String>,
+}
+
+impl StoreDescriptor {
+    pub(crate) fn new(
+        attributes: SchemaId,
+        observation_attributes: SchemaId,
+        metric: SchemaId,
+        feature_classes: BTreeMap<u64, usize>,
+        feature_dimensions: BTreeMap<u64, usize>,
+        options: &[(&str, &dyn Debug)],
+    ) -> Self {
+        Self {
+            version: STORE_DESCRIPTOR_VERSION,
+            attributes,
+            observation_attributes,
+            metric,
+            feature_classes,
+            feature_dimensions,
+            options: options
+                .iter()
+                .map(|(name, value)| (name.to_string(), format!("{value:?}")))
+                .collect(),
+        }
+    }
+
+    /// Checks that the tracks of the store described by `other` can be used with the store
+    /// described by `self`. The feature classes are compared when both stores register the
+    /// projection for the class, the feature dimensions are compared when both stores keep the
+    /// features of the class.
+    ///
+    /// # Returns
+    /// * `Ok(())` if the stores are compatible
+    /// * `Err(e)` with [StoreDescriptorMismatch](Errors::StoreDescriptorMismatch) for the first
+    ///   mismatched property
+    ///
+    pub fn check_compatible(&self, other: &StoreDescriptor) -> Result<()> {
+        fn check<T: PartialEq + ToString>(property: &str, expected: &T, found: &T) -> Result<()> {
+            if expected != found {
+                return Err(Errors::StoreDescriptorMismatch(
+                    property.to_string(),
+                    expected.to_string(),
+                    found.to_string(),
+                )
+                .into());
+            }
+            Ok(())
+        }
+
+        check("version", &self.version, &other.version)?;
+        check("attributes", &self.attributes, &other.attributes)?;
+        check(
+            "observation_attributes",
+            &self.observation_attributes,
+            &other.observation_attributes,
+        )?;
+        check("metric", &self.metric, &other.metric)?;
+        for (class, dim) in &self.feature_classes {
+            if let Some(other_dim) = other.feature_classes.get(class) {
+                check(&format!("feature_classes[{class}]"), dim, other_dim)?;
+            }
+        }
+        for (class, dim) in &self.feature_dimensions {
+            if let Some(other_dim) = other.feature_dimensions.get(class) {
+                check(&format!("feature_dimensions[{class}]"), dim, other_dim)?;
+            }
+        }
+        Ok(())
+    }
+}
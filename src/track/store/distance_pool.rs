@@ -0,0 +1,167 @@
+use crate::track::{ObservationAttributes, ObservationMetricOk};
+use std::sync::Mutex;
+
+/// The pool of the buffers the store shards return the computed distances in.
+///
+/// Every distance request takes the buffer from the pool, the buffer is returned to the pool when
+/// the distances are consumed with the iterator of [TrackDistanceOk](crate::track::store::track_distance::TrackDistanceOk)
+/// or merged by [TrackDistanceOk::all](crate::track::store::track_distance::TrackDistanceOk::all).
+/// When the number of the tracks and the candidates stays stable, the buffers grow to the sizes
+/// they need during the first calls and the next calls don't allocate the memory for the results.
+///
+/// The vector returned by `all` can be given back with [release](DistanceBufferPool::release) to
+/// be reused too.
+///
+#[derive(Debug)]
+pub struct DistanceBufferPool<OA>
+where
+    OA: ObservationAttributes,
+{
+    buffers: Mutex<Vec<Vec<ObservationMetricOk<OA>>>>,
+}
+
+impl<OA> Default for DistanceBufferPool<OA>
+where
+    OA: ObservationAttributes,
+{
+    fn default() -> Self {
+        Self {
+            buffers: Mutex::new(Vec::default()),
+        }
+    }
+}
+
+impl<OA> DistanceBufferPool<OA>
+where
+    OA: ObservationAttributes,
+{
+    /// Takes the buffer from the pool, the empty buffer is created when the pool is exhausted
+    ///
+    pub fn acquire(&self) -> Vec<ObservationMetricOk<OA>> {
+        self.buffers.lock().unwrap().pop().unwrap_or_default()
+    }
+
+    /// Clears the buffer and returns it to the pool
+    ///
+    pub fn release(&self, mut buffer: Vec<ObservationMetricOk<OA>>) {
+        if buffer.capacity() == 0 {
+            return;
+        }
+        buffer.clear();
+        self.buffers.lock().unwrap().push(buffer);
+    }
+
+    /// The number of the buffers kept in the pool
+    ///
+    pub fn len(&self) -> usize {
+        self.buffers.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Frees the memory of the buffers kept in the pool
+    ///
+    pub fn clear(&self) {
+        self.buffers.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::examples::{vec2, UnboundAttrs, UnboundMetric};
+    use crate::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
+    use crate::track::store::distance_pool::DistanceBufferPool;
+    use crate::track::store::TrackStore;
+    use crate::track::{ObservationMetricOk, Track};
+
+    #[test]
+    fn reuse() {
+        let pool = DistanceBufferPool::<f32>::default();
+        assert!(pool.acquire().capacity() == 0);
+        pool.release(Vec::new());
+        assert!(pool.is_empty());
+
+        let mut buffer = pool.acquire();
+        buffer.push(ObservationMetricOk::new(1, 2, None, Some(0.5)));
+        let capacity = buffer.capacity();
+        let ptr = buffer.as_ptr();
+        pool.release(buffer);
+        assert_eq!(pool.len(), 1);
+
+        let buffer = pool.acquire();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.capacity(), capacity);
+        assert_eq!(buffer.as_ptr(), ptr);
+        assert!(pool.is_empty());
+
+        pool.release(buffer);
+        pool.clear();
+        assert!(pool.is_empty());
+    }
+
+    type Store = TrackStore<UnboundAttrs, UnboundMetric, f32, NoopNotifier>;
+
+    fn track(store: &Store, x: f32) -> Track<UnboundAttrs, UnboundMetric, f32> {
+        store
+            .new_track_random_id()
+            .observation(ObservationBuilder::new(0).observation(vec2(x, 0.0)).build())
+            .build()
+            .unwrap()
+    }
+
+    /// The addresses and the capacities of the buffers kept in the pool
+    ///
+    fn buffers(pool: &DistanceBufferPool<f32>) -> Vec<(usize, usize)> {
+        let buffers = (0..pool.len()).map(|_| pool.acquire()).collect::<Vec<_>>();
+        let mut layout = buffers
+            .iter()
+            .map(|b| (b.as_ptr() as usize, b.capacity()))
+            .collect::<Vec<_>>();
+        layout.sort_unstable();
+        for buffer in buffers {
+            pool.release(buffer);
+        }
+        layout
+    }
+
+    #[test]
+    fn steady_state_distances_reuse_buffers() {
+        // the single shard fills the buffers of the same size for all the candidates
+        let mut store: Store = TrackStoreBuilder::new(1)
+            .default_attributes(UnboundAttrs)
+            .metric(UnboundMetric)
+            .notifier(NoopNotifier)
+            .build();
+        for i in 0..1000 {
+            let t = track(&store, i as f32);
+            store.add_track(t).unwrap();
+        }
+        let candidates = (0..4)
+            .map(|i| track(&store, -(i as f32)))
+            .collect::<Vec<_>>();
+
+        let compute = |store: &mut Store| {
+            let (dists, errs) = store.foreign_track_distances(candidates.clone(), 0, false);
+            assert_eq!(dists.into_iter().count(), 4000);
+            assert!(errs.all().is_empty());
+        };
+
+        // the buffers grow to the sizes they need
+        for _ in 0..10 {
+            compute(&mut store);
+        }
+        let warm = buffers(store.distance_pool());
+        assert!(!warm.is_empty());
+
+        // the results are received in the same buffers, they are not grown and no more buffers
+        // are created than the requests can hold at once
+        for _ in 0..100 {
+            compute(&mut store);
+        }
+        let steady = buffers(store.distance_pool());
+        assert!(steady.len() <= candidates.len());
+        assert!(warm.iter().all(|b| steady.contains(b)));
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::sync::Mutex;
+
+#[derive(Debug)]
+struct Reservations {
+    next: u64,
+    reserved: BTreeSet<u64>,
+}
+
+/// The registry of the track ids shared by all the producers of the ids of the store.
+///
+/// The ids generated by the trackers are reserved sequentially starting from 1, the ids supplied
+/// by the user (e.g. the gallery import) are reserved one by one with [reserve](Self::reserve).
+/// The sequential reservation skips the ids reserved by the user, the reservation of the id fails
+/// when the id was already reserved either way, so the concurrent producers never get the same id.
+/// The reservations are not kept in the tracker state.
+///
+#[derive(Debug)]
+pub struct TrackIdRegistry(Mutex<Reservations>);
+
+impl Default for TrackIdRegistry {
+    fn default() -> Self {
+        Self(Mutex::new(Reservations {
+            next: 1,
+            reserved: BTreeSet::new(),
+        }))
+    }
+}
+
+impl TrackIdRegistry {
+    /// Reserves `count` sequential ids which are not reserved yet
+    ///
+    pub fn reserve_range(&self, count: usize) -> Range<u64> {
+        let mut reservations = self.0.lock().unwrap();
+        let mut start = reservations.next;
+        while let Some(taken) = reservations
+            .reserved
+            .range(start..start + count as u64)
+            .next_back()
+        {
+            start = taken + 1;
+        }
+        reservations.next = start + count as u64;
+        start..reservations.next
+    }
+
+    /// Reserves the id if it is not reserved yet
+    ///
+    /// # Returns
+    /// `true` if the id is reserved by the call
+    ///
+    pub fn reserve(&self, track_id: u64) -> bool {
+        let mut reservations = self.0.lock().unwrap();
+        track_id >= reservations.next && reservations.reserved.insert(track_id)
+    }
+
+    /// Releases the id reserved with [reserve](Self::reserve), the sequentially reserved ids are
+    /// never released
+    ///
+    pub fn release(&self, track_id: u64) -> bool {
+        self.0.lock().unwrap().reserved.remove(&track_id)
+    }
+
+    /// Checks if the id is reserved
+    ///
+    pub fn is_reserved(&self, track_id: u64) -> bool {
+        let reservations = self.0.lock().unwrap();
+        track_id < reservations.next || reservations.reserved.contains(&track_id)
+    }
+
+    /// Moves the sequential reservation forward, so the ids below `next` are treated as reserved,
+    /// it is used when the tracker is restored from the state
+    ///
+    pub fn advance(&self, next: u64) {
+        let mut reservations = self.0.lock().unwrap();
+        reservations.next = reservations.next.max(next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::store::id_registry::TrackIdRegistry;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn reservations() {
+        let registry = TrackIdRegistry::default();
+        assert_eq!(registry.reserve_range(2), 1..3);
+        assert!(!registry.reserve(2));
+        assert!(registry.reserve(4));
+        assert!(!registry.reserve(4));
+        assert_eq!(registry.reserve_range(2), 5..7);
+        assert!(registry.is_reserved(3));
+
+        assert!(registry.reserve(100));
+        assert!(registry.release(100));
+        assert!(!registry.is_reserved(100));
+        registry.advance(50);
+        assert!(!registry.reserve(49));
+        assert_eq!(registry.reserve_range(1), 50..51);
+    }
+
+    #[test]
+    fn concurrent() {
+        let registry = Arc::new(TrackIdRegistry::default());
+        let producers = (0..4)
+            .map(|p| {
+                let registry = registry.clone();
+                thread::spawn(move || {
+                    (0..100)
+                        .flat_map(|i| {
+                            if p % 2 == 0 {
+                                registry.reserve_range(1).collect::<Vec<_>>()
+                            } else {
+                                (i * 3..i * 3 + 3)
+                                    .filter(|id| registry.reserve(*id))
+                                    .collect()
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        let ids = producers
+            .into_iter()
+            .flat_map(|p| p.join().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+    }
+}
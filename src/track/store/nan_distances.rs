@@ -0,0 +1,143 @@
+use crate::track::{ObservationAttributes, ObservationMetricOk};
+use serde::{Deserialize, Serialize};
+
+/// What is done with the distances the metric returns as NaN, e.g. the cosine distances of the
+/// zero-norm features.
+///
+/// The policy is applied after [postprocess_distances](crate::track::ObservationMetric::postprocess_distances),
+/// the distance is NaN when its feature distance is NaN or its attribute metric object is NaN
+/// according to [metric_object_is_nan](ObservationAttributes::metric_object_is_nan). The NaN
+/// distances are counted in [ShardDistanceStats](crate::track::store::shard_stats::ShardDistanceStats).
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NanDistancePolicy {
+    /// Removes the NaN distances
+    #[default]
+    Drop,
+    /// Replaces the NaN feature distances with `f32::MAX` and removes the NaN attribute metric
+    /// objects, so the pair is the worst candidate
+    Worst,
+    /// Removes all the distances of the track pair and reports the pair with
+    /// [NanDistance](crate::Errors::NanDistance) error
+    Error,
+}
+
+impl NanDistancePolicy {
+    /// Applies the policy to the distances of the track pair
+    ///
+    /// # Returns
+    /// the number of the NaN distances found
+    ///
+    pub(crate) fn apply<OA: ObservationAttributes>(
+        &self,
+        distances: &mut Vec<ObservationMetricOk<OA>>,
+    ) -> usize {
+        let is_nan = |d: &ObservationMetricOk<OA>| {
+            d.feature_distance.map(f32::is_nan).unwrap_or(false)
+                || d.attribute_metric
+                    .as_ref()
+                    .map(OA::metric_object_is_nan)
+                    .unwrap_or(false)
+        };
+        let nans = distances.iter().filter(|d| is_nan(d)).count();
+        if nans == 0 {
+            return 0;
+        }
+        match self {
+            NanDistancePolicy::Drop => distances.retain(|d| !is_nan(d)),
+            NanDistancePolicy::Worst => {
+                for d in distances.iter_mut() {
+                    if d.feature_distance.map(f32::is_nan).unwrap_or(false) {
+                        d.feature_distance = Some(f32::MAX);
+                    }
+                    if d.attribute_metric
+                        .as_ref()
+                        .map(OA::metric_object_is_nan)
+                        .unwrap_or(false)
+                    {
+                        d.attribute_metric = None;
+                    }
+                }
+            }
+            NanDistancePolicy::Error => distances.clear(),
+        }
+        nans
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::NanDistancePolicy;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "NanDistancePolicy")]
+    #[derive(Clone, Copy, Debug)]
+    pub struct PyNanDistancePolicy(pub NanDistancePolicy);
+
+    #[pymethods]
+    impl PyNanDistancePolicy {
+        #[staticmethod]
+        pub fn drop() -> Self {
+            PyNanDistancePolicy(NanDistancePolicy::Drop)
+        }
+
+        #[staticmethod]
+        pub fn worst() -> Self {
+            PyNanDistancePolicy(NanDistancePolicy::Worst)
+        }
+
+        #[staticmethod]
+        pub fn error() -> Self {
+            PyNanDistancePolicy(NanDistancePolicy::Error)
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{self:?}")
+        }
+
+        fn __str__(&self) -> String {
+            format!("{self:#?}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::store::nan_distances::NanDistancePolicy;
+    use crate::track::ObservationMetricOk;
+
+    fn distances() -> Vec<ObservationMetricOk<f32>> {
+        vec![
+            ObservationMetricOk::new(1, 2, Some(0.5), Some(f32::NAN)),
+            ObservationMetricOk::new(1, 2, Some(f32::NAN), Some(0.1)),
+            ObservationMetricOk::new(1, 2, Some(0.5), Some(0.2)),
+        ]
+    }
+
+    #[test]
+    fn apply() {
+        let mut d = distances();
+        assert_eq!(NanDistancePolicy::Drop.apply(&mut d), 2);
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].feature_distance, Some(0.2));
+
+        let mut d = distances();
+        assert_eq!(NanDistancePolicy::Worst.apply(&mut d), 2);
+        assert_eq!(d.len(), 3);
+        assert_eq!(d[0].feature_distance, Some(f32::MAX));
+        assert_eq!(d[1].attribute_metric, None);
+        assert_eq!(d[1].feature_distance, Some(0.1));
+
+        let mut d = distances();
+        assert_eq!(NanDistancePolicy::Error.apply(&mut d), 2);
+        assert!(d.is_empty());
+
+        let mut d = distances().split_off(2);
+        assert_eq!(NanDistancePolicy::Error.apply(&mut d), 0);
+        assert_eq!(d.len(), 1);
+    }
+}
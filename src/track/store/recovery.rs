@@ -0,0 +1,138 @@
+use log::error;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, RwLock};
+use std::time::Duration;
+
+/// The delay before the first restart attempt of the executor whose thread can't be spawned,
+/// every next attempt waits twice longer
+///
+pub const EXECUTOR_RESTART_DELAY: Duration = Duration::from_millis(1);
+
+/// The number of the attempts to restart the executor before the command fails
+///
+pub const EXECUTOR_RESTART_ATTEMPTS: usize = 5;
+
+/// What is done with the tracks of the shard which lock was poisoned by a panic
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShardPoisonPolicy {
+    /// Keep the tracks of the shard, the track being changed when the panic happened may be
+    /// partially updated
+    #[default]
+    Keep,
+    /// Remove all the tracks of the shard
+    Clear,
+}
+
+/// Recovery state of the store shards.
+///
+/// The poisoned lock can't be cleared with the supported Rust version, so the shard stays poisoned
+/// and the policy is applied once, when the poisoned lock is met for the first time.
+///
+#[derive(Debug)]
+pub(crate) struct ShardRecovery {
+    policy: RwLock<ShardPoisonPolicy>,
+    recovered: Vec<AtomicBool>,
+}
+
+impl ShardRecovery {
+    pub(crate) fn new(shards: usize) -> Self {
+        Self {
+            policy: RwLock::new(ShardPoisonPolicy::default()),
+            recovered: (0..shards).map(|_| AtomicBool::new(false)).collect(),
+        }
+    }
+
+    pub(crate) fn set_policy(&self, policy: ShardPoisonPolicy) {
+        *self.policy.write().unwrap() = policy;
+    }
+
+    pub(crate) fn policy(&self) -> ShardPoisonPolicy {
+        *self.policy.read().unwrap()
+    }
+
+    /// Locks the shard, the poisoned lock is recovered according to the policy
+    ///
+    pub(crate) fn lock<'a, T>(
+        &self,
+        shards: &'a [Mutex<HashMap<u64, T>>],
+        shard_id: usize,
+    ) -> MutexGuard<'a, HashMap<u64, T>> {
+        shards[shard_id].lock().unwrap_or_else(|poisoned| {
+            let mut guard = poisoned.into_inner();
+            if !self.recovered[shard_id].swap(true, Ordering::SeqCst) {
+                let policy = *self.policy.read().unwrap();
+                error!(
+                    "The lock of the shard {} was poisoned by a panic, the shard with {} tracks is recovered with {:?} policy",
+                    shard_id,
+                    guard.len(),
+                    policy
+                );
+                if policy == ShardPoisonPolicy::Clear {
+                    guard.clear();
+                }
+            }
+            guard
+        })
+    }
+}
+
+/// The message of the panic caught with `catch_unwind`
+///
+pub(crate) fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::store::recovery::{panic_message, ShardPoisonPolicy, ShardRecovery};
+    use std::collections::HashMap;
+    use std::panic::catch_unwind;
+    use std::sync::Mutex;
+
+    #[test]
+    fn poisoned_shard() {
+        let shards = [
+            Mutex::new(HashMap::from([(1u64, 1)])),
+            Mutex::new(HashMap::from([(2u64, 2)])),
+        ];
+        let poison = |shard_id: usize| {
+            let _ = catch_unwind(|| {
+                let _guard = shards[shard_id].lock().unwrap();
+                panic!("poison");
+            });
+            assert!(shards[shard_id].is_poisoned());
+        };
+        let recovery = ShardRecovery::new(2);
+
+        poison(0);
+        assert_eq!(recovery.lock(&shards, 0).len(), 1);
+
+        recovery.set_policy(ShardPoisonPolicy::Clear);
+        poison(1);
+        assert!(recovery.lock(&shards, 1).is_empty());
+        recovery.lock(&shards, 1).insert(3, 3);
+        assert_eq!(recovery.lock(&shards, 1).len(), 1);
+        assert_eq!(recovery.lock(&shards, 0).len(), 1);
+    }
+
+    #[test]
+    fn message() {
+        assert_eq!(
+            panic_message(catch_unwind(|| panic!("static")).unwrap_err()),
+            "static"
+        );
+        assert_eq!(
+            panic_message(catch_unwind(|| panic!("formatted {}", 1)).unwrap_err()),
+            "formatted 1"
+        );
+    }
+}
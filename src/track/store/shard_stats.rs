@@ -0,0 +1,319 @@
+use log::warn;
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Distance computation counters of the store shard
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ShardDistanceStats {
+    /// The number of distance requests (one per foreign track) processed by the shard
+    pub requests: u64,
+    /// The number of track pairs compared
+    pub pairs: u64,
+    /// The number of distances produced
+    pub results: u64,
+    /// The number of NaN distances met, they are handled according to the
+    /// [NanDistancePolicy](crate::track::store::nan_distances::NanDistancePolicy) of the store
+    pub nan_distances: u64,
+    /// The number of track pairs which distances failed, e.g. because of a metric panic or the
+    /// [NanDistancePolicy::Error](crate::track::store::nan_distances::NanDistancePolicy::Error)
+    /// policy, the pairs are not present in the results
+    pub errors: u64,
+    /// The time spent on the distance computations
+    pub elapsed: Duration,
+}
+
+impl ShardDistanceStats {
+    /// The mean time spent on one distance request
+    ///
+    pub fn mean_request_time(&self) -> Option<Duration> {
+        if self.requests == 0 {
+            None
+        } else {
+            Some(Duration::from_nanos(
+                (self.elapsed.as_nanos() / self.requests as u128) as u64,
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ShardDistanceCounters {
+    requests: AtomicU64,
+    pairs: AtomicU64,
+    results: AtomicU64,
+    nan_distances: AtomicU64,
+    errors: AtomicU64,
+    elapsed_ns: AtomicU64,
+}
+
+impl ShardDistanceCounters {
+    pub(crate) fn record(
+        &self,
+        pairs: usize,
+        results: usize,
+        nan_distances: usize,
+        errors: usize,
+        elapsed: Duration,
+    ) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.pairs.fetch_add(pairs as u64, Ordering::Relaxed);
+        self.results.fetch_add(results as u64, Ordering::Relaxed);
+        self.nan_distances
+            .fetch_add(nan_distances as u64, Ordering::Relaxed);
+        self.errors.fetch_add(errors as u64, Ordering::Relaxed);
+        self.elapsed_ns
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn stats(&self) -> ShardDistanceStats {
+        ShardDistanceStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            pairs: self.pairs.load(Ordering::Relaxed),
+            results: self.results.load(Ordering::Relaxed),
+            nan_distances: self.nan_distances.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            elapsed: Duration::from_nanos(self.elapsed_ns.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        self.requests.store(0, Ordering::Relaxed);
+        self.pairs.store(0, Ordering::Relaxed);
+        self.results.store(0, Ordering::Relaxed);
+        self.nan_distances.store(0, Ordering::Relaxed);
+        self.errors.store(0, Ordering::Relaxed);
+        self.elapsed_ns.store(0, Ordering::Relaxed);
+    }
+}
+
+/// The function that is called with the shard id, its stats, and the ratio of its mean request time
+/// to the mean request time of the other shards
+///
+pub type SlowShardHook = Arc<dyn Fn(usize, &ShardDistanceStats, f64) + Send + Sync>;
+
+/// Detects the shards which distance computations are consistently slower than the ones of the
+/// other shards, that usually means the skewed distribution of the track ids.
+///
+/// The mean request times of the shards accumulated since the last stats reset are compared before
+/// every distance computation. The slow shard is reported to the log (and to the hook if it is set)
+/// once, until it stops being slow.
+///
+pub struct SlowShardDetector {
+    ratio: f64,
+    min_requests: u64,
+    hook: Option<SlowShardHook>,
+    reported: Mutex<HashSet<usize>>,
+}
+
+impl fmt::Debug for SlowShardDetector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlowShardDetector")
+            .field("ratio", &self.ratio)
+            .field("min_requests", &self.min_requests)
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
+
+impl SlowShardDetector {
+    /// Creates the detector
+    ///
+    /// # Parameters
+    /// * `ratio` - how many times the mean request time of the shard must exceed the mean over the
+    ///   other shards for the shard to be considered slow
+    /// * `min_requests` - the number of requests the shard must process before it is checked
+    ///
+    pub fn new(ratio: f64, min_requests: u64) -> Self {
+        assert!(ratio > 1.0, "Ratio must be greater than 1.0");
+        Self {
+            ratio,
+            min_requests,
+            hook: None,
+            reported: Mutex::default(),
+        }
+    }
+
+    /// Sets the function that is called when the slow shard is found
+    ///
+    pub fn with_hook(mut self, hook: SlowShardHook) -> Self {
+        self.hook = Some(hook);
+        self
+    }
+
+    /// Finds the slow shards
+    ///
+    /// # Returns
+    /// the shard ids with the ratios of their mean request times to the mean over the other
+    /// checked shards
+    ///
+    pub fn slow_shards(&self, stats: &[ShardDistanceStats]) -> Vec<(usize, f64)> {
+        let means = stats
+            .iter()
+            .map(|s| {
+                s.mean_request_time()
+                    .filter(|_| s.requests >= self.min_requests)
+                    .map(|t| t.as_secs_f64())
+            })
+            .collect::<Vec<_>>();
+        let checked = means.iter().flatten().collect::<Vec<_>>();
+        if checked.len() < 2 {
+            return vec![];
+        }
+        let total = checked.iter().copied().sum::<f64>();
+        let others = (checked.len() - 1) as f64;
+        means
+            .iter()
+            .enumerate()
+            .filter_map(|(shard, mean)| {
+                mean.and_then(|m| {
+                    let other_mean = (total - m) / others;
+                    (other_mean > 0.0).then(|| (shard, m / other_mean))
+                })
+            })
+            .filter(|(_, ratio)| *ratio > self.ratio)
+            .collect()
+    }
+
+    pub(crate) fn check(&self, stats: &[ShardDistanceStats]) {
+        let slow = self.slow_shards(stats);
+        let mut reported = self.reported.lock().unwrap();
+        reported.retain(|shard| slow.iter().any(|(s, _)| s == shard));
+        for (shard, ratio) in slow {
+            if !reported.insert(shard) {
+                continue;
+            }
+            warn!(
+                "Shard {} computes distances {:.2} times slower than the other shards: {:?}",
+                shard, ratio, stats[shard]
+            );
+            if let Some(hook) = &self.hook {
+                hook(shard, &stats[shard], ratio);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::ShardDistanceStats;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "ShardDistanceStats")]
+    #[derive(Clone, Copy, Debug)]
+    pub struct PyShardDistanceStats(pub ShardDistanceStats);
+
+    #[pymethods]
+    impl PyShardDistanceStats {
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+
+        #[getter]
+        fn requests(&self) -> u64 {
+            self.0.requests
+        }
+
+        #[getter]
+        fn pairs(&self) -> u64 {
+            self.0.pairs
+        }
+
+        #[getter]
+        fn results(&self) -> u64 {
+            self.0.results
+        }
+
+        #[getter]
+        fn nan_distances(&self) -> u64 {
+            self.0.nan_distances
+        }
+
+        #[getter]
+        fn errors(&self) -> u64 {
+            self.0.errors
+        }
+
+        /// The time spent on the distance computations, in seconds
+        ///
+        #[getter]
+        fn elapsed(&self) -> f64 {
+            self.0.elapsed.as_secs_f64()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::store::shard_stats::{ShardDistanceStats, SlowShardDetector};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn stats(requests: u64, elapsed_ms: u64) -> ShardDistanceStats {
+        ShardDistanceStats {
+            requests,
+            pairs: requests * 10,
+            results: requests * 10,
+            nan_distances: 0,
+            errors: 0,
+            elapsed: Duration::from_millis(elapsed_ms),
+        }
+    }
+
+    #[test]
+    fn slow_shards() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let hook_calls = calls.clone();
+        let detector = SlowShardDetector::new(2.0, 10).with_hook(Arc::new(move |shard, _, _| {
+            assert_eq!(shard, 2);
+            hook_calls.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        let balanced = [stats(10, 10), stats(10, 12), stats(10, 11)];
+        assert!(detector.slow_shards(&balanced).is_empty());
+
+        let skewed = [stats(10, 10), stats(10, 10), stats(10, 100), stats(1, 1000)];
+        let slow = detector.slow_shards(&skewed);
+        assert_eq!(slow.len(), 1);
+        assert_eq!(slow[0].0, 2);
+
+        detector.check(&skewed);
+        detector.check(&skewed);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        detector.check(&balanced);
+        detector.check(&skewed);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // the shard isn't compared with the mean including itself
+        let detector = SlowShardDetector::new(2.0, 10);
+        let slow = detector.slow_shards(&[stats(10, 10), stats(10, 30)]);
+        assert_eq!(slow.len(), 1);
+        assert_eq!(slow[0].0, 1);
+        assert!((slow[0].1 - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn mean_request_time() {
+        assert_eq!(stats(0, 10).mean_request_time(), None);
+        assert_eq!(
+            stats(4, 10).mean_request_time(),
+            Some(Duration::from_micros(2500))
+        );
+        assert_eq!(
+            stats(1 << 32, 1 << 32).mean_request_time(),
+            Some(Duration::from_millis(1))
+        );
+    }
+}
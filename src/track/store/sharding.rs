@@ -0,0 +1,90 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+const FX_SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+/// The custom assignment of the track ids to the shards, the result is taken modulo the number of
+/// the shards
+///
+#[derive(Clone)]
+pub struct ShardAssigner(Arc<dyn Fn(u64, usize) -> usize + Send + Sync>);
+
+impl ShardAssigner {
+    pub fn new(assigner: impl Fn(u64, usize) -> usize + Send + Sync + 'static) -> Self {
+        Self(Arc::new(assigner))
+    }
+}
+
+impl Debug for ShardAssigner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ShardAssigner")
+    }
+}
+
+/// How the tracks are assigned to the shards of the store.
+///
+/// The assignment must be deterministic, the shard of the track is computed every time the track
+/// is accessed by its id. The sequential ids are evenly distributed by [Modulo](ShardAssignment::Modulo),
+/// however the ids imported from the external systems are often clustered (e.g. the multiples of
+/// some step), so they skew the load of the shards, [FxHash](ShardAssignment::FxHash) scatters them.
+/// The assignment of the existing store is changed with [rebalance](crate::store::TrackStore::rebalance).
+///
+#[derive(Debug, Clone, Default)]
+pub enum ShardAssignment {
+    /// `track_id % shards`
+    #[default]
+    Modulo,
+    /// The FxHash of the track id mapped to the shards by its high bits
+    FxHash,
+    /// The user-defined assignment
+    Custom(ShardAssigner),
+}
+
+impl ShardAssignment {
+    pub fn custom(assigner: impl Fn(u64, usize) -> usize + Send + Sync + 'static) -> Self {
+        ShardAssignment::Custom(ShardAssigner::new(assigner))
+    }
+
+    /// The shard of the track
+    ///
+    /// # Parameters
+    /// * `track_id` - the id of the track
+    /// * `shards` - the number of the shards, must be positive
+    ///
+    pub fn shard(&self, track_id: u64, shards: usize) -> usize {
+        match self {
+            ShardAssignment::Modulo => (track_id % shards as u64) as usize,
+            ShardAssignment::FxHash => {
+                let hash = track_id.wrapping_mul(FX_SEED);
+                ((hash as u128 * shards as u128) >> 64) as usize
+            }
+            ShardAssignment::Custom(assigner) => (assigner.0)(track_id, shards) % shards,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::store::sharding::ShardAssignment;
+
+    #[test]
+    fn assignment() {
+        let load = |assignment: &ShardAssignment| {
+            let mut load = vec![0; 4];
+            (0..1000).for_each(|i| load[assignment.shard(i * 4, 4)] += 1);
+            load
+        };
+        assert_eq!(load(&ShardAssignment::Modulo), vec![1000, 0, 0, 0]);
+
+        let hashed = load(&ShardAssignment::FxHash);
+        assert!(hashed.iter().all(|l| (200..300).contains(l)), "{hashed:?}");
+        assert_eq!(
+            ShardAssignment::FxHash.shard(12345, 7),
+            ShardAssignment::FxHash.shard(12345, 7)
+        );
+
+        let custom = ShardAssignment::custom(|id, _| id as usize / 4 + 1);
+        assert_eq!(custom.shard(8, 4), 3);
+        assert_eq!(custom.shard(12, 4), 0);
+    }
+}
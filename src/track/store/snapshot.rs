@@ -0,0 +1,205 @@
+use crate::track::notify::{ChangeNotifier, NoopNotifier};
+use crate::track::store::sharding::ShardAssignment;
+use crate::track::{ObservationAttributes, ObservationMetric, Track, TrackAttributes};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+
+/// Read-only view of the track store taken at a certain moment.
+///
+/// The snapshot is taken with all the shard locks held, so it is a consistent cut of the store,
+/// the locks are released before the snapshot is returned, so the tracking continues while the
+/// snapshot is iterated. The shards not changed since the previous snapshot are shared with it
+/// while it is alive, and the snapshot is cheap to clone because the shard copies are shared
+/// between the clones.
+///
+pub struct TrackStoreSnapshot<TA, M, OA, N = NoopNotifier>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    generation: u64,
+    #[allow(clippy::type_complexity)]
+    shards: Vec<Arc<HashMap<u64, Track<TA, M, OA, N>>>>,
+    shard_assignment: ShardAssignment,
+}
+
+impl<TA, M, OA, N> Clone for TrackStoreSnapshot<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    fn clone(&self) -> Self {
+        Self {
+            generation: self.generation,
+            shards: self.shards.clone(),
+            shard_assignment: self.shard_assignment.clone(),
+        }
+    }
+}
+
+impl<TA, M, OA, N> TrackStoreSnapshot<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn new(
+        generation: u64,
+        shards: Vec<Arc<HashMap<u64, Track<TA, M, OA, N>>>>,
+        shard_assignment: ShardAssignment,
+    ) -> Self {
+        Self {
+            generation,
+            shards,
+            shard_assignment,
+        }
+    }
+
+    /// The generation of the store the snapshot was taken at, the generation is increased by
+    /// every change of the store, so the snapshots with the same generation hold the same tracks
+    ///
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Number of tracks in the snapshot
+    ///
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Counts of tracks per every shard
+    ///
+    pub fn shard_stats(&self) -> Vec<usize> {
+        self.shards.iter().map(|s| s.len()).collect()
+    }
+
+    /// Tracks of the shard `id`
+    ///
+    #[allow(clippy::type_complexity)]
+    pub fn shard(&self, id: usize) -> Option<&HashMap<u64, Track<TA, M, OA, N>>> {
+        self.shards.get(id).map(|s| s.as_ref())
+    }
+
+    /// Gets the track by its id
+    ///
+    pub fn get(&self, track_id: u64) -> Option<&Track<TA, M, OA, N>> {
+        if self.shards.is_empty() {
+            return None;
+        }
+        self.shards[self.shard_assignment.shard(track_id, self.shards.len())].get(&track_id)
+    }
+
+    /// Iterates over all the tracks of the snapshot
+    ///
+    pub fn iter(&self) -> impl Iterator<Item = &Track<TA, M, OA, N>> {
+        self.shards.iter().flat_map(|s| s.values())
+    }
+}
+
+/// The generations of the store and its shards. Every change of a shard increases the
+/// generation of the store and marks the shard with it, so the snapshot may reuse the shard
+/// copies of the previous snapshot if the shards are not changed since then.
+///
+#[derive(Debug)]
+pub(crate) struct StoreGenerations {
+    store: AtomicU64,
+    shards: Vec<AtomicU64>,
+}
+
+impl StoreGenerations {
+    pub(crate) fn new(shards: usize) -> Self {
+        Self {
+            store: AtomicU64::new(0),
+            shards: (0..shards).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    /// Marks the shard as changed, must be called while the shard lock is held
+    ///
+    pub(crate) fn touch(&self, shard: usize) {
+        let generation = self.store.fetch_add(1, Ordering::SeqCst) + 1;
+        self.shards[shard].store(generation, Ordering::SeqCst);
+    }
+
+    /// The generation the shard was lastly changed at
+    ///
+    pub(crate) fn shard(&self, shard: usize) -> u64 {
+        self.shards[shard].load(Ordering::SeqCst)
+    }
+}
+
+/// The shard copies of the last snapshots with the generations they were copied at, they are
+/// referenced weakly so the cache never keeps the copies the caller has dropped
+///
+pub(crate) struct SnapshotCache<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    #[allow(clippy::type_complexity)]
+    shards: HashMap<usize, (u64, Weak<HashMap<u64, Track<TA, M, OA, N>>>)>,
+}
+
+impl<TA, M, OA, N> Default for SnapshotCache<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    fn default() -> Self {
+        Self {
+            shards: HashMap::default(),
+        }
+    }
+}
+
+impl<TA, M, OA, N> SnapshotCache<TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// The copy of the shard from the last snapshots if it is alive and the shard is not changed
+    /// since then
+    ///
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn reuse(
+        &self,
+        shard: usize,
+        shard_generation: u64,
+    ) -> Option<Arc<HashMap<u64, Track<TA, M, OA, N>>>> {
+        self.shards
+            .get(&shard)
+            .filter(|(generation, _)| *generation == shard_generation)
+            .and_then(|(_, copy)| copy.upgrade())
+    }
+
+    /// Remembers the copy of the shard taken at the generation
+    ///
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn update(
+        &mut self,
+        shard: usize,
+        shard_generation: u64,
+        copy: &Arc<HashMap<u64, Track<TA, M, OA, N>>>,
+    ) {
+        self.shards
+            .insert(shard, (shard_generation, Arc::downgrade(copy)));
+    }
+}
@@ -1,16 +1,25 @@
 #[cfg(test)]
 mod tests {
     use crate::distance::euclidean;
-    use crate::examples::{current_time_ms, vec2};
+    use crate::examples::{current_time_ms, vec2, SimpleAttrs, SimpleMetric};
     use crate::prelude::TrackStoreBuilder;
+    use crate::track::lookup::lookup_fn;
+    use crate::track::merge_history::MergeHistoryOptions;
+    use crate::track::projection::FeatureProjection;
+    use crate::track::store::descriptor::SchemaId;
+    use crate::track::store::nan_distances::NanDistancePolicy;
+    use crate::track::store::shard_stats::ShardDistanceStats;
+    use crate::track::store::sharding::ShardAssignment;
     use crate::track::store::TrackStore;
-    use crate::track::utils::feature_attributes_sort_dec;
+    use crate::track::utils::{feature_attributes_sort_dec, FromVec};
     use crate::track::{
-        LookupRequest, MetricOutput, MetricQuery, NoopLookup, NoopNotifier, Observation,
+        Feature, LookupRequest, MetricOutput, MetricQuery, NoopLookup, NoopNotifier, Observation,
         ObservationAttributes, ObservationMetric, ObservationsDb, Track, TrackAttributes,
         TrackAttributesUpdate, TrackStatus,
     };
-    use crate::EPS;
+    use crate::trackers::sort::metric::SortMetric;
+    use crate::trackers::sort::SortAttributes;
+    use crate::{Errors, EPS};
     use anyhow::Result;
     use std::thread;
     use std::time::Duration;
@@ -20,6 +29,7 @@ mod tests {
         start_time: u128,
         end_time: u128,
         baked_period: u128,
+        epochs: Vec<(usize, u64)>,
     }
 
     #[derive(Default, Clone)]
@@ -58,6 +68,10 @@ mod tests {
                 Ok(TrackStatus::Pending)
             }
         }
+
+        fn on_epoch(&mut self, epoch: usize, scene_id: u64) {
+            self.epochs.push((epoch, scene_id));
+        }
     }
 
     #[derive(Default, Clone)]
@@ -178,6 +192,217 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn feature_projection() -> Result<()> {
+        let projection = FeatureProjection::new(2).with_matrix(
+            16,
+            &[
+                [vec![0.5], vec![0.0; 15]].concat(),
+                [vec![0.0, 0.5], vec![0.0; 14]].concat(),
+            ],
+        )?;
+        let mut store = TrackStoreBuilder::new(1)
+            .metric(TimeMetric { max_length: 20 })
+            .default_attributes(TimeAttrs::default())
+            .notifier(NoopNotifier)
+            .feature_projection(0, projection)
+            .build();
+
+        store.add(0, 0, Some(0.9), Some(vec2(1.0, 1.0)), None)?;
+        let mut old = [2.0, 2.0].to_vec();
+        old.extend([0.0; 14]);
+        store.add(0, 0, Some(0.9), Some(Feature::from_vec(old)), None)?;
+        assert!(store
+            .add(
+                0,
+                0,
+                Some(0.9),
+                Some(Feature::from_vec(vec![1.0; 24])),
+                None
+            )
+            .is_err());
+
+        let track = store.fetch_tracks(&[0]).pop().unwrap();
+        let observations = track.get_observations(0).unwrap();
+        assert_eq!(observations.len(), 2);
+        for o in observations {
+            let f = o.feature().as_ref().unwrap();
+            assert_eq!(f.len(), 1);
+            assert_eq!(Vec::from_vec(f)[..2], [1.0, 1.0]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn lookup_grouped() -> Result<()> {
+        let mut store = TrackStore::new(SimpleMetric, SimpleAttrs::default(), NoopNotifier, 2);
+        for id in 0..5 {
+            store.add(id, 0, None, Some(vec2(0.0, 1.0)), None)?;
+        }
+        let lookup = |modulo: u64| {
+            lookup_fn(
+                move |_: &SimpleAttrs, _: &ObservationsDb<f32>, history: &[u64]| {
+                    history[0] % modulo == 0
+                },
+            )
+        };
+        // the tracks are assigned to the first matching lookup
+        let groups = store.lookup_grouped(vec![lookup(2), lookup(1), lookup(3)]);
+        let mut ids = groups
+            .iter()
+            .map(|g| {
+                let mut ids = g.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+                ids.sort();
+                ids
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![vec![0, 2, 4], vec![1, 3], vec![]]);
+        ids = store
+            .lookup_grouped::<NoopLookup<SimpleAttrs, f32>>(vec![])
+            .into_iter()
+            .map(|g| g.into_iter().map(|(id, _)| id).collect())
+            .collect();
+        assert!(ids.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn descriptor() -> Result<()> {
+        let store = |dim| {
+            TrackStoreBuilder::new(1)
+                .metric(TimeMetric { max_length: 20 })
+                .default_attributes(TimeAttrs::default())
+                .notifier(NoopNotifier)
+                .feature_projection(0, FeatureProjection::new(dim))
+                .build()
+        };
+        let descriptor = store(2).descriptor();
+        assert_eq!(descriptor.feature_classes.get(&0), Some(&2));
+        assert_eq!(descriptor.options.get("shards").unwrap(), "1");
+        let restored = serde_json::from_str(&serde_json::to_string(&descriptor)?)?;
+        assert_eq!(descriptor, restored);
+        descriptor.check_compatible(&restored)?;
+
+        let mismatch = descriptor
+            .check_compatible(&store(4).descriptor())
+            .unwrap_err();
+        assert!(matches!(
+            mismatch.downcast_ref::<Errors>(),
+            Some(Errors::StoreDescriptorMismatch(property, expected, found))
+                if property == "feature_classes[0]" && expected == "2" && found == "4"
+        ));
+
+        let mut other = descriptor.clone();
+        other.metric = SchemaId::new("OtherMetric", 0);
+        assert!(descriptor.check_compatible(&other).is_err());
+
+        let sort_store = TrackStore::new(
+            SortMetric::default(),
+            SortAttributes::default(),
+            NoopNotifier,
+            1,
+        );
+        let descriptor = sort_store.descriptor();
+        assert_eq!(descriptor.attributes, SchemaId::new("SortAttributes", 0));
+        assert_eq!(
+            descriptor.observation_attributes,
+            SchemaId::new("Universal2DBox", 0)
+        );
+        assert_eq!(descriptor.metric, SchemaId::new("SortMetric", 0));
+        assert!(descriptor.feature_dimensions.is_empty());
+
+        let mut store = TrackStore::new(SimpleMetric, SimpleAttrs::default(), NoopNotifier, 1);
+        store.add(1, 0, None, Some(vec2(0.0, 1.0)), None)?;
+        let mut other = TrackStore::new(SimpleMetric, SimpleAttrs::default(), NoopNotifier, 1);
+        other.add(1, 0, None, Some(Feature::from_vec(vec![1.0; 9])), None)?;
+        assert_eq!(store.descriptor().feature_dimensions.get(&0), Some(&8));
+        let mismatch = store
+            .descriptor()
+            .check_compatible(&other.descriptor())
+            .unwrap_err();
+        assert!(matches!(
+            mismatch.downcast_ref::<Errors>(),
+            Some(Errors::StoreDescriptorMismatch(property, expected, found))
+                if property == "feature_dimensions[0]" && expected == "8" && found == "16"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot() -> Result<()> {
+        let mut store = TrackStore::new(
+            TimeMetric { max_length: 20 },
+            TimeAttrs {
+                baked_period: 10,
+                ..Default::default()
+            },
+            NoopNotifier,
+            2,
+        );
+
+        for id in 0..3 {
+            store.add(
+                id,
+                0,
+                Some(0.9),
+                Some(vec2(0.0, 1.0)),
+                time_attrs_current_ts(),
+            )?;
+        }
+
+        let snapshot = store.snapshot();
+        assert!(snapshot.generation() > 0);
+        assert_eq!(snapshot.len(), 3);
+        assert_eq!(snapshot.shard_stats(), vec![2, 1]);
+
+        // nothing is changed, the shards are shared with the previous snapshot
+        assert_eq!(store.get_store(0).len(), 2);
+        let same = store.snapshot();
+        assert_eq!(same.generation(), snapshot.generation());
+        for s in 0..2 {
+            assert!(std::ptr::eq(
+                same.shard(s).unwrap(),
+                snapshot.shard(s).unwrap()
+            ));
+        }
+        drop(same);
+
+        // only the shard of the track 1 is changed
+        store.fetch_tracks(&[1]);
+        let partial = store.snapshot();
+        assert!(partial.generation() > snapshot.generation());
+        assert!(std::ptr::eq(
+            partial.shard(0).unwrap(),
+            snapshot.shard(0).unwrap()
+        ));
+        assert_eq!(partial.shard_stats(), vec![2, 0]);
+        assert_eq!(snapshot.shard_stats(), vec![2, 1]);
+
+        store.fetch_tracks(&[0, 1]);
+        store.add(
+            2,
+            0,
+            Some(0.9),
+            Some(vec2(0.0, 1.0)),
+            time_attrs_current_ts(),
+        )?;
+
+        assert_eq!(snapshot.len(), 3);
+        assert!(snapshot.get(1).is_some());
+        assert_eq!(
+            snapshot.get(2).unwrap().get_observations(0).unwrap().len(),
+            1
+        );
+        assert_eq!(snapshot.iter().count(), 3);
+
+        let next = store.snapshot();
+        assert!(next.generation() > partial.generation());
+        assert_eq!(next.len(), 1);
+        assert!(next.get(1).is_none());
+
+        Ok(())
+    }
+
     #[test]
     fn general_ops() -> Result<()> {
         let mut store = TrackStore::new(
@@ -421,6 +646,16 @@ mod tests {
         assert_eq!(dists.len(), 1);
         assert!(errs.is_empty());
 
+        let stats = store.distance_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats.iter().map(|s| s.requests).sum::<u64>(), 4);
+        assert_eq!(stats[0].pairs, 0);
+        assert_eq!(stats[1].pairs, 2);
+        assert_eq!(stats.iter().map(|s| s.results).sum::<u64>(), 2);
+
+        store.reset_distance_stats();
+        assert_eq!(store.distance_stats()[1], ShardDistanceStats::default());
+
         Ok(())
     }
 
@@ -565,6 +800,45 @@ mod tests {
         classes.sort();
         assert_eq!(classes, vec![0, 1]);
 
+        let res = store.merge_external_owned(0, ext_track, Some(&[1]), true);
+        assert!(res.is_ok());
+        let observations = store
+            .get_store(0)
+            .get(&0)
+            .unwrap()
+            .get_observations(1)
+            .unwrap()
+            .len();
+        assert_eq!(observations, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bounded_merge_history() -> Result<()> {
+        let mut store = TrackStore::new(
+            TimeMetric { max_length: 20 },
+            TimeAttrs::default(),
+            NoopNotifier,
+            1,
+        );
+        store.add(0, 0, Some(0.9), Some(vec2(0.0, 1.0)), None)?;
+        store.set_merge_history_options(Some(MergeHistoryOptions::new(2).bloom_filter(256, 2)));
+        for track_id in 1..=5 {
+            let mut t = Track::new(
+                track_id,
+                TimeMetric { max_length: 20 },
+                TimeAttrs::default(),
+                NoopNotifier,
+            );
+            t.add_observation(0, Some(0.8), Some(vec2(0.66, 0.33)), None)?;
+            store.merge_external_owned(0, t, Some(&[0]), true)?;
+        }
+        let shard = store.get_store(0);
+        let history = shard.get(&0).unwrap().get_merge_history_summary();
+        assert_eq!(history.ids(), &vec![4, 5]);
+        assert_eq!(history.count(), 6);
+        assert!((0..=5).all(|id| history.contains(id)));
         Ok(())
     }
 
@@ -610,6 +884,308 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn merge_dry_run() -> Result<()> {
+        let mut store = TrackStore::new(
+            TimeMetric { max_length: 2 },
+            TimeAttrs::default(),
+            NoopNotifier,
+            2,
+        );
+        store.add(
+            0,
+            0,
+            Some(0.9),
+            Some(vec2(0.0, 1.0)),
+            Some(TimeAttrUpdates { time: 10 }),
+        )?;
+        store.add(0, 0, Some(0.8), Some(vec2(0.0, 1.0)), None)?;
+        store.add(
+            1,
+            0,
+            Some(0.7),
+            Some(vec2(0.0, 1.0)),
+            Some(TimeAttrUpdates { time: 20 }),
+        )?;
+        store.add(1, 1, Some(0.7), Some(vec2(0.0, 1.0)), None)?;
+
+        let res = store.merge_dry_run(0, 1, None, false)?;
+        assert!(res.error.is_none());
+        assert_eq!(res.attributes.end_time, 20);
+        assert_eq!(res.observation_counts.get(&0), Some(&2));
+        assert_eq!(res.observation_counts.get(&1), Some(&1));
+
+        let res = store.merge_dry_run(0, 1, Some(&[1]), false)?;
+        assert_eq!(res.observation_counts.get(&0), Some(&2));
+        assert_eq!(res.observation_counts.get(&1), Some(&1));
+
+        {
+            let shard = store.get_store(0);
+            let dest = shard.get(&0).unwrap();
+            assert_eq!(dest.get_attributes().end_time, 10);
+            assert!(dest.get_observations(1).is_none());
+        }
+        assert_eq!(store.shard_stats(), vec![1, 1]);
+
+        assert!(store.merge_dry_run(0, 0, None, false).is_err());
+        assert!(store.merge_dry_run(0, 5, None, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn on_epoch() -> Result<()> {
+        let mut store = TrackStore::new(
+            TimeMetric { max_length: 20 },
+            TimeAttrs::default(),
+            NoopNotifier,
+            2,
+        );
+        store.add(0, 0, Some(0.9), Some(vec2(0.0, 1.0)), None)?;
+        store.add(1, 0, Some(0.9), Some(vec2(0.0, 1.0)), None)?;
+
+        store.on_epoch(1, 0);
+        store.on_epoch(1, 7);
+        for track_id in [0, 1] {
+            let shard = store.get_store(track_id as usize);
+            let track = shard.get(&track_id).unwrap();
+            assert_eq!(track.get_attributes().epochs, vec![(1, 0), (1, 7)]);
+        }
+
+        store.on_epoch_with(
+            2,
+            0,
+            lookup_fn(|_: &TimeAttrs, _: &ObservationsDb<f32>, history: &[u64]| {
+                history.contains(&1)
+            }),
+        );
+        let epochs = |track_id: u64| {
+            let shard = store.get_store(track_id as usize);
+            shard.get(&track_id).unwrap().get_attributes().epochs.len()
+        };
+        assert_eq!((epochs(0), epochs(1)), (2, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn rebalance() -> Result<()> {
+        let mut store = TrackStore::new(
+            TimeMetric { max_length: 20 },
+            TimeAttrs::default(),
+            NoopNotifier,
+            4,
+        );
+        for track_id in (0..32).step_by(4) {
+            store.add(track_id, 0, Some(0.9), Some(vec2(0.0, 1.0)), None)?;
+        }
+        assert_eq!(store.shard_stats(), vec![8, 0, 0, 0]);
+
+        let moved = store.rebalance(ShardAssignment::FxHash);
+        let stats = store.shard_stats();
+        assert_eq!(stats.iter().sum::<usize>(), 8);
+        assert_eq!(moved, 8 - stats[0]);
+        assert!(stats.iter().all(|s| *s < 8), "{stats:?}");
+
+        let ext_track = store.new_track(100).build()?;
+        for track_id in (0..32).step_by(4) {
+            assert!(store.get_store(track_id as usize).get(&track_id).is_some());
+            assert!(store.snapshot().get(track_id).is_some());
+            store.merge_external(track_id, &ext_track, None, false)?;
+        }
+
+        let store: TrackStore<TimeAttrs, TimeMetric, f32> = TrackStoreBuilder::new(3)
+            .metric(TimeMetric { max_length: 20 })
+            .default_attributes(TimeAttrs::default())
+            .notifier(NoopNotifier)
+            .shard_assignment(ShardAssignment::custom(|_, _| 2))
+            .build();
+        assert_eq!(store.get_executor(7), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn reserve_track_ids() -> Result<()> {
+        let mut store = TrackStore::new(
+            TimeMetric { max_length: 20 },
+            TimeAttrs::default(),
+            NoopNotifier,
+            2,
+        );
+        store.add(5, 0, Some(0.9), Some(vec2(0.0, 1.0)), None)?;
+        assert!(store.reserve_track_id(5).is_err());
+        assert_eq!(store.reserve_track_id(3)?, 3);
+        assert!(store.reserve_track_id(3).is_err());
+        assert_eq!(store.reserve_track_ids(3), 7..10);
+        assert!(store.reserve_track_id(6).is_err());
+        assert!(store.release_track_id(3));
+        assert_eq!(store.reserve_track_ids(1), 10..11);
+        Ok(())
+    }
+
+    #[test]
+    fn update_all() -> Result<()> {
+        let mut store = TrackStore::new(
+            TimeMetric { max_length: 20 },
+            TimeAttrs::default(),
+            NoopNotifier,
+            2,
+        );
+        for track_id in 0..4 {
+            store.add(
+                track_id,
+                0,
+                Some(0.9),
+                Some(vec2(0.0, 1.0)),
+                Some(TimeAttrUpdates {
+                    time: track_id as u128 + 1,
+                }),
+            )?;
+        }
+
+        let filter =
+            lookup_fn(|attrs: &TimeAttrs, _: &ObservationsDb<f32>, _: &[u64]| attrs.end_time > 2);
+        let mut updated = store
+            .update_all(TimeAttrUpdates { time: 10 }, filter)
+            .into_iter()
+            .map(|(track_id, res)| res.map(|_| track_id))
+            .collect::<Result<Vec<_>>>()?;
+        updated.sort_unstable();
+        assert_eq!(updated, vec![2, 3]);
+
+        let updated = store.update_all(
+            TimeAttrUpdates { time: 20 },
+            NoopLookup::<TimeAttrs, f32, true>::default(),
+        );
+        assert_eq!(updated.len(), 4);
+        for (track_id, start_time) in [(0, 1), (1, 2), (2, 3), (3, 4)] {
+            let shard = store.get_store(track_id as usize);
+            let attrs = shard.get(&track_id).unwrap().get_attributes();
+            assert_eq!((attrs.start_time, attrs.end_time), (start_time, 20));
+        }
+        Ok(())
+    }
+
+    #[derive(Default, Clone)]
+    struct PanicMetric;
+
+    impl ObservationMetric<TimeAttrs, f32> for PanicMetric {
+        fn metric(&self, mq: &MetricQuery<TimeAttrs, f32>) -> MetricOutput<f32> {
+            if matches!(mq.candidate_observation.attr(), Some(q) if *q < 0.0) {
+                panic!("negative quality");
+            }
+            Some((None, None))
+        }
+
+        fn optimize(
+            &mut self,
+            _feature_class: u64,
+            _merge_history: &[u64],
+            _attrs: &mut TimeAttrs,
+            _features: &mut Vec<Observation<f32>>,
+            _prev_length: usize,
+            _is_merge: bool,
+        ) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn metric_panic() -> Result<()> {
+        let mut store = TrackStore::new(PanicMetric, TimeAttrs::default(), NoopNotifier, 2);
+        store.add(1, 0, Some(0.9), Some(vec2(0.0, 1.0)), None)?;
+        store.add(2, 0, Some(0.9), Some(vec2(0.0, 1.0)), None)?;
+
+        let mut ext_track = Track::new(3, PanicMetric, TimeAttrs::default(), NoopNotifier);
+        ext_track.add_observation(0, Some(-1.0), Some(vec2(0.0, 1.0)), None)?;
+        let (dists, errs) = store.foreign_track_distances(vec![ext_track], 0, false);
+        assert!(dists.all().is_empty());
+        let errs = errs.all();
+        assert_eq!(errs.len(), 2);
+        for e in errs {
+            let e = e.unwrap_err();
+            assert!(matches!(
+                e.downcast_ref::<Errors>(),
+                Some(Errors::MetricPanic(3, _, m)) if m == "negative quality"
+            ));
+        }
+
+        let mut ext_track = Track::new(4, PanicMetric, TimeAttrs::default(), NoopNotifier);
+        ext_track.add_observation(0, Some(1.0), Some(vec2(0.0, 1.0)), None)?;
+        let (dists, errs) = store.foreign_track_distances(vec![ext_track], 0, false);
+        assert_eq!(dists.all().len(), 2);
+        assert!(errs.all().is_empty());
+        Ok(())
+    }
+
+    #[derive(Default, Clone)]
+    struct NanMetric;
+
+    impl ObservationMetric<TimeAttrs, f32> for NanMetric {
+        fn metric(&self, mq: &MetricQuery<TimeAttrs, f32>) -> MetricOutput<f32> {
+            match mq.candidate_observation.attr() {
+                Some(q) if *q < 0.0 => Some((None, Some(f32::NAN))),
+                _ => Some((None, Some(0.5))),
+            }
+        }
+
+        fn optimize(
+            &mut self,
+            _feature_class: u64,
+            _merge_history: &[u64],
+            _attrs: &mut TimeAttrs,
+            _features: &mut Vec<Observation<f32>>,
+            _prev_length: usize,
+            _is_merge: bool,
+        ) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn nan_distances() -> Result<()> {
+        let mut store = TrackStore::new(NanMetric, TimeAttrs::default(), NoopNotifier, 1);
+        store.add(1, 0, Some(0.9), Some(vec2(0.0, 1.0)), None)?;
+        store.add(2, 0, Some(0.9), Some(vec2(0.0, 1.0)), None)?;
+        let nan_track = || {
+            let mut t = Track::new(3, NanMetric, TimeAttrs::default(), NoopNotifier);
+            t.add_observation(0, Some(-1.0), Some(vec2(0.0, 1.0)), None)
+                .unwrap();
+            t
+        };
+
+        let (dists, errs) = store.foreign_track_distances(vec![nan_track()], 0, false);
+        assert!(dists.all().is_empty());
+        assert!(errs.all().is_empty());
+        assert_eq!(store.distance_stats()[0].nan_distances, 2);
+
+        store.set_nan_distance_policy(NanDistancePolicy::Worst);
+        let (dists, _) = store.foreign_track_distances(vec![nan_track()], 0, false);
+        let dists = dists.all();
+        assert_eq!(dists.len(), 2);
+        assert!(dists.iter().all(|d| d.feature_distance == Some(f32::MAX)));
+
+        store.set_nan_distance_policy(NanDistancePolicy::Error);
+        let (dists, errs) = store.foreign_track_distances(vec![nan_track()], 0, false);
+        assert!(dists.all().is_empty());
+        let errs = errs.all();
+        assert_eq!(errs.len(), 2);
+        for e in errs {
+            let e = e.unwrap_err();
+            assert!(matches!(
+                e.downcast_ref::<Errors>(),
+                Some(Errors::NanDistance(3, _))
+            ));
+        }
+        assert_eq!(store.distance_stats()[0].nan_distances, 6);
+        assert_eq!(store.distance_stats()[0].errors, 2);
+
+        // the trackers log the failed pairs and skip them
+        let (dists, errs) = store.foreign_track_distances(vec![nan_track()], 0, false);
+        assert!(dists.all().is_empty());
+        assert_eq!(errs.log(), 2);
+        assert_eq!(store.distance_stats()[0].errors, 4);
+        Ok(())
+    }
+
     #[test]
     fn lookup() {
         #[derive(Default, Clone)]
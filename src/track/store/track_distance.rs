@@ -1,6 +1,10 @@
+use crate::store::distance_pool::DistanceBufferPool;
 use crate::store::{ObservationMetricErr, Results};
 use crate::track::{ObservationAttributes, ObservationMetricOk};
 use crossbeam::channel::Receiver;
+use log::warn;
+use std::mem;
+use std::sync::Arc;
 use std::vec::IntoIter;
 
 /// Represents the response from the track distance computation.
@@ -14,25 +18,29 @@ where
         let mut results = Vec::new();
         for _ in 0..self.count() {
             let res = self.channel().recv().unwrap();
-            Self::extend(&mut results, self.elt(res));
+            self.extend(&mut results, self.elt(res));
         }
         results
     }
 
     fn count(&self) -> usize;
     fn elt(&self, res: Results<OA>) -> Vec<Self::Output>;
-    fn extend(output: &mut Vec<Self::Output>, elt: Vec<Self::Output>);
+    fn extend(&self, output: &mut Vec<Self::Output>, elt: Vec<Self::Output>);
     fn channel(&self) -> &Receiver<Results<OA>>;
 }
 
 /// Represents the ok response from the track distance computation.
 ///
+/// The distances are received in the buffers of the store [distance pool](DistanceBufferPool),
+/// the buffers are returned to the pool when they are consumed.
+///
 pub struct TrackDistanceOk<OA>
 where
     OA: ObservationAttributes,
 {
     count: usize,
     channel: Receiver<Results<OA>>,
+    pool: Arc<DistanceBufferPool<OA>>,
 }
 
 pub struct TrackDistanceOkIterator<OA>
@@ -41,7 +49,9 @@ where
 {
     iterator_count: usize,
     channel: Receiver<Results<OA>>,
-    current_chunk: IntoIter<ObservationMetricOk<OA>>,
+    pool: Arc<DistanceBufferPool<OA>>,
+    /// The received chunk in the reversed order, so the elements are popped without the reallocation
+    current_chunk: Vec<ObservationMetricOk<OA>>,
 }
 
 pub struct TrackDistanceErrIterator<OA>
@@ -57,12 +67,27 @@ impl<OA> TrackDistanceOk<OA>
 where
     OA: ObservationAttributes,
 {
+    /// Collects all the distances, the vector is taken from the distance pool of the store
+    ///
     pub fn all(self) -> Vec<ObservationMetricOk<OA>> {
-        self.get_all()
+        let mut results = self.pool.acquire();
+        for _ in 0..self.count {
+            let res = self.channel.recv().unwrap();
+            self.extend(&mut results, self.elt(res));
+        }
+        results
     }
 
-    pub(crate) fn new(count: usize, channel: Receiver<Results<OA>>) -> Self {
-        Self { count, channel }
+    pub(crate) fn new(
+        count: usize,
+        channel: Receiver<Results<OA>>,
+        pool: Arc<DistanceBufferPool<OA>>,
+    ) -> Self {
+        Self {
+            count,
+            channel,
+            pool,
+        }
     }
 }
 
@@ -87,6 +112,22 @@ where
     pub(crate) fn new(count: usize, channel: Receiver<Results<OA>>) -> Self {
         Self { count, channel }
     }
+
+    /// Reports the errors to the log, the track pairs which distances failed are absent from
+    /// the ok response, so they are skipped by the caller. The errors are also counted in the
+    /// [distance stats](crate::store::TrackStore::distance_stats) of the store.
+    ///
+    /// # Returns
+    /// the number of errors
+    ///
+    pub(crate) fn log(self) -> usize {
+        let mut count = 0;
+        for e in self.into_iter().filter_map(|r| r.err()) {
+            warn!("Track distance is skipped: {:?}", e);
+            count += 1;
+        }
+        count
+    }
 }
 
 impl<OA> Iterator for TrackDistanceOkIterator<OA>
@@ -97,17 +138,20 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
-            let elt = self.current_chunk.next();
+            let elt = self.current_chunk.pop();
             if elt.is_some() {
                 return elt;
-            } else if self.iterator_count == 0 {
+            }
+            self.pool.release(mem::take(&mut self.current_chunk));
+            if self.iterator_count == 0 {
                 return None;
             } else {
                 self.iterator_count -= 1;
                 let elt = self.channel.recv().unwrap();
                 match elt {
-                    Results::DistanceOk(elt) => {
-                        self.current_chunk = elt.into_iter();
+                    Results::DistanceOk(mut elt) => {
+                        elt.reverse();
+                        self.current_chunk = elt;
                     }
                     _ => unreachable!(),
                 }
@@ -154,7 +198,8 @@ where
         TrackDistanceOkIterator {
             iterator_count: self.count,
             channel: self.channel,
-            current_chunk: Vec::default().into_iter(),
+            pool: self.pool,
+            current_chunk: Vec::default(),
         }
     }
 }
@@ -194,8 +239,9 @@ where
         }
     }
 
-    fn extend(output: &mut Vec<Self::Output>, elt: Vec<Self::Output>) {
-        output.extend_from_slice(&elt);
+    fn extend(&self, output: &mut Vec<Self::Output>, mut elt: Vec<Self::Output>) {
+        output.append(&mut elt);
+        self.pool.release(elt);
     }
 
     fn channel(&self) -> &Receiver<Results<OA>> {
@@ -221,7 +267,7 @@ where
             }
         }
     }
-    fn extend(output: &mut Vec<Self::Output>, elt: Vec<Self::Output>) {
+    fn extend(&self, output: &mut Vec<Self::Output>, elt: Vec<Self::Output>) {
         output.extend(elt);
     }
 
@@ -1,4 +1,4 @@
-use crate::track::{Feature, Observation, ObservationAttributes, FEATURE_LANES_SIZE};
+use crate::track::{Feature, FeatureMask, Observation, ObservationAttributes, FEATURE_LANES_SIZE};
 use std::cmp::Ordering;
 use ultraviolet::f32x8;
 
@@ -70,6 +70,145 @@ impl FromVec<&Vec<f32>, Feature> for Feature {
     }
 }
 
+/// Feature from the slice, the tail of the last SIMD block is filled with `padding` instead of
+/// zeros
+///
+pub fn feature_with_padding(vec: &[f32], padding: f32) -> Feature {
+    vec.chunks(FEATURE_LANES_SIZE)
+        .map(|chunk| {
+            let mut acc = [padding; FEATURE_LANES_SIZE];
+            acc[..chunk.len()].copy_from_slice(chunk);
+            f32x8::new(acc)
+        })
+        .collect()
+}
+
+/// Validity mask from the per-lane flags, the lanes padding the last SIMD block are masked
+///
+pub fn feature_mask(valid: &[bool]) -> FeatureMask {
+    valid
+        .chunks(FEATURE_LANES_SIZE)
+        .map(|chunk| {
+            let mut acc = [0.0; FEATURE_LANES_SIZE];
+            for (lane, v) in acc.iter_mut().zip(chunk) {
+                *lane = if *v { 1.0 } else { 0.0 };
+            }
+            f32x8::new(acc)
+        })
+        .collect()
+}
+
+/// Validity mask of the descriptor which first `valid_length` lanes out of `length` are valid,
+/// e.g. the truncated embedding
+///
+pub fn prefix_feature_mask(valid_length: usize, length: usize) -> FeatureMask {
+    feature_mask(
+        &(0..length.max(valid_length))
+            .map(|i| i < valid_length)
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Element-wise mean of the features, e.g. the centroid of the track template
+///
+/// The features must have the same number of SIMD blocks, the zero tail lanes stay zero.
+///
+/// # Returns
+/// the mean, `None` if there are no features
+///
+pub fn feature_mean<'a>(features: impl IntoIterator<Item = &'a Feature>) -> Option<Feature> {
+    feature_weighted_mean(features.into_iter().map(|f| (f, 1.0)))
+}
+
+/// Element-wise weighted mean of the features, e.g. the quality-weighted centroid
+///
+/// # Parameters
+/// * `features` - the features with their non-negative weights, the features must have the same
+///   number of SIMD blocks
+///
+/// # Returns
+/// the mean, `None` if there are no features or the weights sum up to zero
+///
+pub fn feature_weighted_mean<'a>(
+    features: impl IntoIterator<Item = (&'a Feature, f32)>,
+) -> Option<Feature> {
+    let mut acc: Option<Feature> = None;
+    let mut total = 0.0;
+    for (feature, weight) in features {
+        assert!(weight >= 0.0, "The weights must be non-negative");
+        let weight_block = f32x8::splat(weight);
+        match &mut acc {
+            None => acc = Some(feature.iter().map(|b| *b * weight_block).collect()),
+            Some(acc) => {
+                assert_eq!(
+                    acc.len(),
+                    feature.len(),
+                    "The features must have the same dimension"
+                );
+                for (a, b) in acc.iter_mut().zip(feature) {
+                    *a += *b * weight_block;
+                }
+            }
+        }
+        total += weight;
+    }
+    if total <= 0.0 {
+        return None;
+    }
+    let scale = f32x8::splat(1.0 / total);
+    acc.map(|acc| acc.into_iter().map(|b| b * scale).collect())
+}
+
+/// Exponential moving average of the template and the new feature, `alpha` is the weight of the
+/// new feature
+///
+pub fn feature_ema(template: &Feature, feature: &Feature, alpha: f32) -> Feature {
+    assert!(
+        (0.0..=1.0).contains(&alpha),
+        "Alpha must be contained in [0.0..=1.0]"
+    );
+    assert_eq!(
+        template.len(),
+        feature.len(),
+        "The features must have the same dimension"
+    );
+    let (keep, add) = (f32x8::splat(1.0 - alpha), f32x8::splat(alpha));
+    template
+        .iter()
+        .zip(feature)
+        .map(|(t, f)| *t * keep + *f * add)
+        .collect()
+}
+
+/// Feature scaled to the unit L2 norm over the lanes valid in the mask, the masked lanes are
+/// zeroed, so the padding of the last SIMD block doesn't affect the norm. The zero feature is
+/// returned as is.
+///
+/// # Parameters
+/// * `feature` - the feature
+/// * `mask` - the validity mask of the feature, `None` if all the lanes are valid
+///
+pub fn feature_normalize(feature: &Feature, mask: Option<&FeatureMask>) -> Feature {
+    let masked = feature
+        .iter()
+        .enumerate()
+        .map(|(i, b)| match mask {
+            Some(m) => *b * m.get(i).copied().unwrap_or(f32x8::ZERO),
+            None => *b,
+        })
+        .collect::<Feature>();
+    let norm = masked
+        .iter()
+        .map(|b| (*b * *b).reduce_add())
+        .sum::<f32>()
+        .sqrt();
+    if norm == 0.0 {
+        return masked;
+    }
+    let scale = f32x8::splat(1.0 / norm);
+    masked.into_iter().map(|b| b * scale).collect()
+}
+
 /// Utility trait to get conversion between feature vector representations
 ///
 pub trait FromVec<V, R> {
@@ -78,8 +217,12 @@ pub trait FromVec<V, R> {
 
 #[cfg(test)]
 mod tests {
-    use crate::track::utils::FromVec;
+    use crate::track::utils::{
+        feature_ema, feature_mask, feature_mean, feature_normalize, feature_weighted_mean,
+        feature_with_padding, prefix_feature_mask, FromVec,
+    };
     use crate::track::Feature;
+    use crate::EPS;
 
     #[test]
     fn conv_tests() {
@@ -88,4 +231,56 @@ mod tests {
         let v2 = Vec::from_vec(&o);
         assert_eq!(v2, vec![0.0, 0.2, 0.3, 0.0, 0.0, 0.0, 0.0, 0.0]);
     }
+
+    #[test]
+    fn padding() {
+        let o = feature_with_padding(&[0.0, 0.2, 0.3], -1.0);
+        assert_eq!(
+            Vec::from_vec(&o),
+            vec![0.0, 0.2, 0.3, -1.0, -1.0, -1.0, -1.0, -1.0]
+        );
+        assert_eq!(feature_with_padding(&[1.0; 8], -1.0).len(), 1);
+    }
+
+    #[test]
+    fn masks() {
+        let m = feature_mask(&[true, false, true]);
+        assert_eq!(
+            Vec::from_vec(&m),
+            vec![1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+        let m = prefix_feature_mask(9, 12);
+        assert_eq!(m.len(), 2);
+        assert_eq!(Vec::from_vec(&m)[7..10].to_vec(), vec![1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let f1 = Feature::from_vec(vec![1.0; 9]);
+        let f2 = Feature::from_vec(vec![3.0; 9]);
+
+        let mean = Vec::from_vec(&feature_mean([&f1, &f2]).unwrap());
+        assert_eq!(mean[..9].to_vec(), vec![2.0; 9]);
+        assert!(mean[9..].iter().all(|v| *v == 0.0));
+        assert!(feature_mean([]).is_none());
+
+        let mean = Vec::from_vec(&feature_weighted_mean([(&f1, 3.0), (&f2, 1.0)]).unwrap());
+        assert!((mean[0] - 1.5).abs() < EPS);
+        assert!(feature_weighted_mean([(&f1, 0.0)]).is_none());
+
+        let ema = Vec::from_vec(&feature_ema(&f1, &f2, 0.25));
+        assert!((ema[8] - 1.5).abs() < EPS);
+
+        // the padding doesn't affect the norm
+        let padded = feature_with_padding(&[3.0, 4.0], 100.0);
+        let normalized = Vec::from_vec(&feature_normalize(
+            &padded,
+            Some(&prefix_feature_mask(2, 2)),
+        ));
+        assert!((normalized[0] - 0.6).abs() < EPS);
+        assert!((normalized[1] - 0.8).abs() < EPS);
+        assert_eq!(normalized[2], 0.0);
+        let zero = Feature::from_vec(vec![0.0; 3]);
+        assert_eq!(Vec::from_vec(&feature_normalize(&zero, None)), vec![0.0; 8]);
+    }
 }
@@ -3,6 +3,14 @@ pub mod topn;
 
 use crate::track::{ObservationAttributes, ObservationMetricOk};
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// User-defined gating predicate that is applied to the distances before the assignment is made.
+///
+/// When the predicate returns `false` for the pair (candidate, track), the pair is excluded from the voting,
+/// which allows injecting business rules without re-implementing the metric.
+///
+pub type VotingGate<OA> = Arc<dyn Fn(&ObservationMetricOk<OA>) -> bool + Send + Sync>;
 
 /// Trait to implement distance voting engines.
 ///
@@ -21,6 +21,7 @@ where
 {
     max_distance: f32,
     min_votes: usize,
+    track_min_votes: HashMap<u64, usize>,
     _phony: PhantomData<OA>,
 }
 
@@ -38,9 +39,24 @@ where
         Self {
             max_distance,
             min_votes,
+            track_min_votes: HashMap::default(),
             _phony: PhantomData,
         }
     }
+
+    /// Overrides the minimal amount of votes for the destination tracks
+    ///
+    pub fn with_track_min_votes(mut self, track_min_votes: HashMap<u64, usize>) -> Self {
+        self.track_min_votes = track_min_votes;
+        self
+    }
+
+    fn min_votes(&self, track_id: u64) -> usize {
+        self.track_min_votes
+            .get(&track_id)
+            .copied()
+            .unwrap_or(self.min_votes)
+    }
 }
 
 impl<OA> Voting<OA> for BestFitVoting<OA>
@@ -88,7 +104,7 @@ where
             )
             .into_group_map()
             .into_iter()
-            .filter(|(_, count)| count.len() >= self.min_votes)
+            .filter(|((_, dest_track), count)| count.len() >= self.min_votes(*dest_track))
             .map(|((src_track, dest_track), dists)| {
                 debug!(
                     "Group | Src: {:#?}, Dst: {:#?}, Dist: {:#?}",
@@ -103,7 +119,7 @@ where
             })
             .collect::<Vec<_>>();
 
-        candidates.sort_by(|e1, e2| e2.weight.partial_cmp(&e1.weight).unwrap());
+        candidates.sort_by(|e1, e2| e2.weight.total_cmp(&e1.weight));
 
         debug!("Candidates: {:#?}", &candidates);
 
@@ -128,7 +128,7 @@ where
         }
 
         for counts in results.values_mut() {
-            counts.sort_by(|l, r| r.weight.partial_cmp(&l.weight).unwrap());
+            counts.sort_by(|l, r| r.weight.total_cmp(&l.weight));
             counts.truncate(self.topn);
         }
 
@@ -6,6 +6,7 @@ pub mod sort;
 pub mod epoch_db;
 
 /// Visual tracker implementations
+#[cfg(feature = "visual")]
 pub mod visual_sort;
 
 /// Trait that implements kalman_2d_box prediction for attributes
@@ -18,7 +19,109 @@ pub mod spatio_temporal_constraints;
 
 /// Prediction batch request implementation
 ///
+#[cfg(feature = "batch")]
 pub mod batch;
 
 /// Trait to implement tracker API
 pub mod tracker_api;
+
+/// Filter that defines which tracks are reported to the caller
+///
+pub mod output_filter;
+
+/// Online estimation of the detection noise from the Kalman filter residuals
+///
+pub mod noise_model;
+
+/// Online estimation of the object density with the congestion-aware association thresholds
+///
+pub mod density;
+
+/// Detection of the observations passed with the same custom object id within the epoch
+///
+pub mod duplicate_ids;
+
+/// Majority voting for the class labels of the track observations
+///
+pub mod class_voting;
+
+/// User metadata carried by the observations and the tracks
+///
+pub mod metadata;
+
+/// Rolling percentiles of the prediction latency per scene
+///
+pub mod latency;
+
+/// Latency budget that skips the visual matching of the overloaded scenes
+///
+pub mod latency_budget;
+
+/// Clustering of the near-duplicate observations with the box fusion before the association
+///
+pub mod candidate_clustering;
+
+/// Iterator that yields the tracks of the epoch as soon as their observations are merged
+///
+pub mod predict_iter;
+
+/// Mapping of the observations passed to the prediction to the tracks they ended up in
+///
+pub mod candidate_assignment;
+
+/// Entry and exit zones of the scenes that drive track creation and early waste
+///
+#[cfg(feature = "clipping")]
+pub mod scene_zones;
+
+/// Per-scene overrides of the metric and voting options applied without rebuilding the tracker
+///
+pub mod predict_overrides;
+
+/// Composable transformers (clamping, scaling, confidence calibration, ROI filtering) applied to
+/// the observations at ingestion
+///
+pub mod observation_pipeline;
+
+/// Preparation of the observations owned by the tracker: the transformers, the duplicate ids
+/// policy and the clustering of the near-duplicates
+///
+pub mod observation_intake;
+
+/// Clamping of the reported track boxes and the Kalman state to the frame of the scene
+///
+pub mod frame_clamping;
+
+/// Custom conditions (leaving the region of interest, low confidence, user predicates) that waste
+/// the tracks per scene
+///
+pub mod waste_triggers;
+
+/// Fusion of the outputs of two trackers running in parallel into the track set with the stable ids
+///
+pub mod track_fusion;
+
+/// Export of the distances of the matched and the unmatched observations for the active learning
+///
+pub mod distance_export;
+
+/// The boxes reported for the tracks which box history is exhausted
+///
+pub mod history_fallback;
+
+/// Detection of the batch tracker threads that made no progress for too long
+///
+pub mod watchdog;
+
+/// The motion model predicting the boxes of the tracks
+///
+pub mod motion_model;
+
+/// Online estimation of the id switch and fragmentation rates without the ground truth
+///
+pub mod quality;
+
+/// Reference ReID pipeline for retail analytics - tracking, zone visits, best shots and JSONL export
+///
+#[cfg(feature = "retail")]
+pub mod retail;
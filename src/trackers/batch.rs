@@ -1,40 +1,262 @@
 use crate::prelude::SortTrack;
-use crossbeam::channel::{Receiver, Sender};
+use crate::trackers::candidate_assignment::CandidateAssignment;
+use crate::trackers::watchdog::Watchdog;
+use crate::Errors;
+use anyhow::Result;
+use crossbeam::channel::{Receiver, RecvTimeoutError, Sender};
 use log::debug;
 
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
 
 pub type BatchRecords<T> = HashMap<u64, Vec<T>>;
 pub type SceneTracks = (u64, Vec<SortTrack>);
+/// The tracks of the scene with the assignments of the scene observations to the tracks
+pub type SceneAssignedTracks = (u64, Vec<SortTrack>, Vec<CandidateAssignment>);
 
 #[derive(Debug, Clone)]
 pub struct PredictionBatchRequest<T> {
     batch: BatchRecords<T>,
-    sender: Sender<SceneTracks>,
+    sender: Sender<SceneAssignedTracks>,
     batch_size: Arc<Mutex<usize>>,
+    results: Arc<(Mutex<SceneResults>, Condvar)>,
 }
 
+/// The results of the batch routed to the scenes
+///
+#[derive(Debug, Default)]
+struct SceneResults {
+    /// The scenes of the batch which results are not delivered yet
+    scenes: HashSet<u64>,
+    /// The results received from the channel but not requested yet
+    pending: HashMap<u64, SceneAssignedTracks>,
+    /// One of the consumers waits on the channel
+    receiving: bool,
+}
+
+impl SceneResults {
+    fn take(&mut self, scene_id: u64) -> Option<SceneAssignedTracks> {
+        let res = self.pending.remove(&scene_id)?;
+        self.scenes.remove(&scene_id);
+        Some(res)
+    }
+}
+
+/// The results of the batch. The results are received either in the order of completion with
+/// [get](Self::get) or for the certain scene with [get_for](Self::get_for), so the independent
+/// per-scene consumers sharing the clones of the object wait only for their own results.
+///
 #[derive(Clone, Debug)]
 pub struct PredictionBatchResult {
-    receiver: Receiver<SceneTracks>,
+    receiver: Receiver<SceneAssignedTracks>,
     batch_size: Arc<Mutex<usize>>,
+    results: Arc<(Mutex<SceneResults>, Condvar)>,
 }
 
 impl PredictionBatchResult {
     pub fn ready(&self) -> bool {
-        !self.receiver.is_empty()
+        !self.receiver.is_empty() || !self.results.0.lock().unwrap().pending.is_empty()
+    }
+
+    /// Checks if the result of the scene can be received without blocking
+    ///
+    pub fn ready_for(&self, scene_id: u64) -> bool {
+        let mut results = self.results.0.lock().unwrap();
+        if !results.receiving {
+            while let Ok(res) = self.receiver.try_recv() {
+                results.pending.insert(res.0, res);
+            }
+        }
+        results.pending.contains_key(&scene_id)
     }
 
     pub fn get(&self) -> SceneTracks {
-        self.receiver
-            .recv()
+        let (scene_id, tracks, _) = self.get_with_assignments();
+        (scene_id, tracks)
+    }
+
+    /// Receives the tracks of the scene with the assignments of the scene observations to the
+    /// tracks
+    ///
+    pub fn get_with_assignments(&self) -> SceneAssignedTracks {
+        self.receive(None)
             .expect("Receiver must always receive batch computation result")
     }
 
+    /// Receives the tracks of the scene, the results of the other scenes received meanwhile are
+    /// kept for their consumers
+    ///
+    /// # Returns
+    /// `None` if the scene is not in the batch or its result is already received
+    ///
+    pub fn get_for(&self, scene_id: u64) -> Option<Vec<SortTrack>> {
+        self.get_with_assignments_for(scene_id)
+            .map(|(_, tracks, _)| tracks)
+    }
+
+    /// Receives the tracks of the scene with the assignments of the scene observations to the
+    /// tracks, see [get_for](Self::get_for)
+    ///
+    pub fn get_with_assignments_for(&self, scene_id: u64) -> Option<SceneAssignedTracks> {
+        self.receive(Some(scene_id))
+    }
+
+    /// The scenes of the batch which results are not received yet
+    ///
+    pub fn scenes(&self) -> Vec<u64> {
+        self.results
+            .0
+            .lock()
+            .unwrap()
+            .scenes
+            .iter()
+            .copied()
+            .collect()
+    }
+
     pub fn batch_size(&self) -> usize {
         *self.batch_size.lock().unwrap()
     }
+
+    /// Waits until the result of the scene (or of any scene when `scene_id` is `None`) is
+    /// received, only one consumer waits on the channel at once, the others wait for the results
+    /// it routes
+    ///
+    fn receive(&self, scene_id: Option<u64>) -> Option<SceneAssignedTracks> {
+        let (lock, cvar) = &*self.results;
+        let mut results = lock.lock().unwrap();
+        loop {
+            let ready = match scene_id {
+                Some(scene_id) => results.pending.contains_key(&scene_id).then_some(scene_id),
+                None => results.pending.keys().next().copied(),
+            };
+            if let Some(ready) = ready {
+                return results.take(ready);
+            }
+            if matches!(scene_id, Some(scene_id) if !results.scenes.contains(&scene_id)) {
+                return None;
+            }
+            if results.receiving {
+                results = cvar.wait(results).unwrap();
+                continue;
+            }
+            results.receiving = true;
+            drop(results);
+            let res = self.receiver.recv();
+            results = lock.lock().unwrap();
+            results.receiving = false;
+            cvar.notify_all();
+            let res = res.ok()?;
+            results.pending.insert(res.0, res);
+        }
+    }
+}
+
+/// The result of the batch tracker shutdown
+///
+#[derive(Debug)]
+pub struct BatchShutdownReport<T> {
+    /// The scenes that were not processed before the deadline with the observations submitted for
+    /// them, so they can be resubmitted to another tracker. Processing results for them are never
+    /// sent.
+    pub unprocessed: Vec<(u64, Vec<T>)>,
+    /// The number of voting threads that didn't stop before the deadline and were detached
+    pub detached_threads: usize,
+}
+
+/// Waits until the in-flight batch is processed
+///
+/// # Returns
+/// `false` if the batch is still processed when the deadline is reached
+///
+pub(crate) fn wait_batch_processed(
+    monitor: Option<&Arc<(Mutex<usize>, Condvar)>>,
+    deadline: Instant,
+) -> bool {
+    if let Some(m) = monitor {
+        let (lock, cvar) = &**m;
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let (_guard, res) = cvar
+            .wait_timeout_while(lock.lock().unwrap(), timeout, |v| *v > 0)
+            .unwrap();
+        !res.timed_out()
+    } else {
+        true
+    }
+}
+
+/// Waits until the previous batch is processed
+///
+/// When the watchdog is enabled, the waiting is abandoned with
+/// [WorkerStalled](Errors::WorkerStalled) error once any of the workers is stalled, otherwise
+/// the waiting is unbounded.
+///
+pub(crate) fn wait_batch_idle(
+    monitor: Option<&Arc<(Mutex<usize>, Condvar)>>,
+    watchdog: Option<&Watchdog>,
+) -> Result<()> {
+    let Some(m) = monitor else {
+        return Ok(());
+    };
+    let (lock, cvar) = &**m;
+    let Some(watchdog) = watchdog else {
+        let _guard = cvar.wait_while(lock.lock().unwrap(), |v| *v > 0).unwrap();
+        return Ok(());
+    };
+    let mut guard = lock.lock().unwrap();
+    while *guard > 0 {
+        if let Some(stall) = watchdog.stalled().first() {
+            return Err(Errors::WorkerStalled(stall.worker.to_string(), stall.busy_for).into());
+        }
+        guard = cvar
+            .wait_timeout(guard, watchdog.options().check_period())
+            .unwrap()
+            .0;
+    }
+    Ok(())
+}
+
+/// Sends the exit command to the voting threads and joins them until the deadline. The voting
+/// threads hold the senders of the `finished` channel, so it is disconnected once all of them exit.
+///
+/// # Returns
+/// the number of threads that were not stopped before the deadline, such threads are detached
+///
+pub(crate) fn join_voting_threads<C>(
+    threads: Vec<(Sender<C>, JoinHandle<()>)>,
+    exit: fn() -> C,
+    finished: &Receiver<()>,
+    deadline: Instant,
+) -> usize {
+    let mut handles = Vec::with_capacity(threads.len());
+    for (tx, t) in threads {
+        if tx.send(exit()).is_err() {
+            debug!("Voting thread is already stopped");
+        }
+        handles.push(t);
+    }
+
+    let all_finished = loop {
+        match finished.recv_deadline(deadline) {
+            Ok(()) => continue,
+            Err(RecvTimeoutError::Disconnected) => break true,
+            Err(RecvTimeoutError::Timeout) => break false,
+        }
+    };
+
+    let mut detached = 0;
+    for t in handles {
+        if all_finished || t.is_finished() {
+            if t.join().is_err() {
+                debug!("Voting thread finished with panic");
+            }
+        } else {
+            detached += 1;
+        }
+    }
+    detached
 }
 
 #[cfg(feature = "python")]
@@ -45,6 +267,7 @@ pub mod python {
     use pyo3::prelude::*;
 
     pub type PySceneTracks = (u64, Vec<PySortTrack>);
+    pub type PySceneAssignedTracks = (u64, Vec<PySortTrack>, Vec<(usize, u64, bool)>);
 
     #[pyclass]
     #[derive(Clone, Debug)]
@@ -62,6 +285,60 @@ pub mod python {
             Python::with_gil(|py| py.allow_threads(|| unsafe { std::mem::transmute(self.0.get()) }))
         }
 
+        #[pyo3(signature = ())]
+        fn get_with_assignments(&self) -> PySceneAssignedTracks {
+            let (scene_id, tracks, assignments) =
+                Python::with_gil(|py| py.allow_threads(|| self.0.get_with_assignments()));
+            (
+                scene_id,
+                unsafe {
+                    std::mem::transmute::<Vec<crate::prelude::SortTrack>, Vec<PySortTrack>>(tracks)
+                },
+                assignments
+                    .into_iter()
+                    .map(|a| (a.index, a.track_id, a.new_track))
+                    .collect(),
+            )
+        }
+
+        /// Receives the tracks of the scene, `None` if the scene is not in the batch or its
+        /// result is already received
+        ///
+        #[pyo3(signature = (scene_id))]
+        fn get_for(&self, scene_id: u64) -> Option<Vec<PySortTrack>> {
+            Python::with_gil(|py| py.allow_threads(|| self.0.get_for(scene_id))).map(
+                |tracks| unsafe {
+                    std::mem::transmute::<Vec<crate::prelude::SortTrack>, Vec<PySortTrack>>(tracks)
+                },
+            )
+        }
+
+        #[pyo3(signature = (scene_id))]
+        fn get_with_assignments_for(&self, scene_id: u64) -> Option<PySceneAssignedTracks> {
+            let (scene_id, tracks, assignments) = Python::with_gil(|py| {
+                py.allow_threads(|| self.0.get_with_assignments_for(scene_id))
+            })?;
+            Some((
+                scene_id,
+                unsafe {
+                    std::mem::transmute::<Vec<crate::prelude::SortTrack>, Vec<PySortTrack>>(tracks)
+                },
+                assignments
+                    .into_iter()
+                    .map(|a| (a.index, a.track_id, a.new_track))
+                    .collect(),
+            ))
+        }
+
+        #[pyo3(signature = (scene_id))]
+        fn ready_for(&self, scene_id: u64) -> bool {
+            self.0.ready_for(scene_id)
+        }
+
+        fn scenes(&self) -> Vec<u64> {
+            self.0.scenes()
+        }
+
         pub fn batch_size(&self) -> usize {
             self.0.batch_size()
         }
@@ -69,12 +346,12 @@ pub mod python {
 }
 
 impl<T> PredictionBatchRequest<T> {
-    pub fn get_sender(&self) -> Sender<SceneTracks> {
+    pub fn get_sender(&self) -> Sender<SceneAssignedTracks> {
         self.sender.clone()
     }
 
     #[allow(dead_code)]
-    pub(crate) fn send(&self, res: SceneTracks) -> bool {
+    pub(crate) fn send(&self, res: SceneAssignedTracks) -> bool {
         let res = self.sender.send(res);
         if let Err(e) = res {
             debug!(
@@ -100,20 +377,24 @@ impl<T> PredictionBatchRequest<T> {
         }
         let mut batch_size = self.batch_size.lock().unwrap();
         *batch_size = self.batch.len();
+        self.results.0.lock().unwrap().scenes.insert(scene_id);
     }
 
     pub fn new() -> (Self, PredictionBatchResult) {
         let (sender, receiver) = crossbeam::channel::bounded(1);
         let batch_size = Arc::new(Mutex::new(0));
+        let results = Arc::new((Mutex::new(SceneResults::default()), Condvar::new()));
         (
             Self {
                 batch: BatchRecords::default(),
                 sender,
                 batch_size: batch_size.clone(),
+                results: results.clone(),
             },
             PredictionBatchResult {
                 receiver,
                 batch_size,
+                results,
             },
         )
     }
@@ -127,6 +408,7 @@ impl<T> PredictionBatchRequest<T> {
 mod tests {
     use crate::prelude::Universal2DBox;
     use crate::trackers::batch::PredictionBatchRequest;
+    use std::thread::spawn;
 
     #[test]
     fn test() {
@@ -137,12 +419,39 @@ mod tests {
         let _batch = request.get_batch();
         assert_eq!(result.batch_size(), 2);
 
-        assert!(request.send((0, vec![])));
+        assert!(request.send((0, vec![], vec![])));
         assert_eq!(result.ready(), true);
         let res = result.get();
         assert_eq!(res.0, 0);
         assert!(res.1.is_empty());
         drop(result);
-        assert!(!request.send((0, vec![])));
+        assert!(!request.send((0, vec![], vec![])));
+    }
+
+    #[test]
+    fn scene_routing() {
+        let (mut request, result) = PredictionBatchRequest::<Universal2DBox>::new();
+        for scene_id in 0..3 {
+            request.add(scene_id, Universal2DBox::new(0.0, 0.0, None, 1.0, 5.0));
+        }
+        let consumers = (0..2)
+            .map(|scene_id| {
+                let result = result.clone();
+                spawn(move || result.get_for(scene_id))
+            })
+            .collect::<Vec<_>>();
+
+        for scene_id in [2, 1, 0] {
+            assert!(request.send((scene_id, vec![], vec![])));
+        }
+        for consumer in consumers {
+            assert!(consumer.join().unwrap().is_some());
+        }
+        assert_eq!(result.scenes(), vec![2]);
+        assert!(result.ready_for(2));
+        assert!(!result.ready_for(0));
+        assert_eq!(result.get().0, 2);
+        assert!(result.get_for(0).is_none());
+        assert!(result.get_for(5).is_none());
     }
 }
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+/// The track the observation passed to the prediction ended up in.
+///
+/// The observations dropped by the observation pipeline or by the duplicate custom object id
+/// policy, the observations fused into the other ones by the candidate clustering and the
+/// ignored observations have no assignment.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CandidateAssignment {
+    /// The index of the observation in the slice passed to the prediction
+    pub index: usize,
+    /// The id of the track the observation is merged into or the id of the created track
+    pub track_id: u64,
+    /// `true` if the observation started the new track
+    pub new_track: bool,
+}
+
+impl CandidateAssignment {
+    pub fn new(index: usize, track_id: u64, new_track: bool) -> Self {
+        Self {
+            index,
+            track_id,
+            new_track,
+        }
+    }
+
+    /// Builds the assignments of the candidates voted by the tracker
+    ///
+    /// # Parameters
+    /// * `indices` - the indices of the observations the candidates are built from
+    /// * `candidates` - the candidate track ids with the ids of the tracks they are merged into
+    ///
+    pub(crate) fn from_candidates(
+        indices: &[usize],
+        candidates: impl IntoIterator<Item = (u64, Option<u64>)>,
+    ) -> Vec<Self> {
+        indices
+            .iter()
+            .zip(candidates)
+            .map(|(index, (track_id, dest))| match dest {
+                Some(dest) => Self::new(*index, dest, false),
+                None => Self::new(*index, track_id, true),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::candidate_assignment::CandidateAssignment;
+
+    #[test]
+    fn from_candidates() {
+        let assignments =
+            CandidateAssignment::from_candidates(&[0, 2], [(10, Some(3)), (11, None)]);
+        assert_eq!(
+            assignments,
+            vec![
+                CandidateAssignment::new(0, 3, false),
+                CandidateAssignment::new(2, 11, true)
+            ]
+        );
+    }
+}
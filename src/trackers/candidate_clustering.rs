@@ -0,0 +1,227 @@
+use crate::track::ObservationAttributes;
+use crate::utils::bbox::Universal2DBox;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// Clustering of the observations of the epoch that is applied before they are associated with the
+/// tracks.
+///
+/// Detectors without NMS and ensembles of detectors produce several near-duplicate boxes for the
+/// same object, which create competing candidate tracks. The observations are grouped greedily: the
+/// most confident unclustered observation seeds the cluster and collects all the unclustered
+/// observations that overlap with it with IoU not less than `iou_threshold`. The cluster is
+/// replaced by its seed observation with the box fused from the boxes of the cluster: the centers,
+/// the widths, the heights and the angles are averaged with the confidences used as weights, the
+/// confidence of the fused box is the best confidence of the cluster.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CandidateClustering {
+    iou_threshold: f32,
+}
+
+impl CandidateClustering {
+    pub fn new(iou_threshold: f32) -> Self {
+        assert!(
+            iou_threshold > 0.0 && iou_threshold <= 1.0,
+            "IoU threshold must lay between (0.0 and 1.0]"
+        );
+        Self { iou_threshold }
+    }
+
+    pub fn iou_threshold(&self) -> f32 {
+        self.iou_threshold
+    }
+
+    /// Groups the boxes into clusters
+    ///
+    /// # Returns
+    /// the clusters of the box indices, the first index of every cluster is its seed
+    ///
+    pub fn clusters(&self, boxes: &[&Universal2DBox]) -> Vec<Vec<usize>> {
+        let mut order = (0..boxes.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| boxes[*b].confidence.total_cmp(&boxes[*a].confidence));
+
+        let mut clustered = vec![false; boxes.len()];
+        let mut clusters = Vec::default();
+        for (pos, seed) in order.iter().enumerate() {
+            if clustered[*seed] {
+                continue;
+            }
+            clustered[*seed] = true;
+            let mut cluster = vec![*seed];
+            for other in &order[pos + 1..] {
+                if clustered[*other] {
+                    continue;
+                }
+                let iou = Universal2DBox::calculate_metric_object(
+                    &Some(boxes[*seed]),
+                    &Some(boxes[*other]),
+                )
+                .unwrap_or(0.0);
+                if iou >= self.iou_threshold {
+                    clustered[*other] = true;
+                    cluster.push(*other);
+                }
+            }
+            clusters.push(cluster);
+        }
+        clusters
+    }
+
+    /// Applies the clustering to the observations
+    ///
+    /// # Parameters
+    /// * `observations` - the observations of one scene and epoch
+    /// * `bbox` - extracts the box from the observation
+    /// * `with_bbox` - makes the copy of the observation with the box replaced
+    ///
+    /// # Returns
+    /// the observations of the original order with every cluster replaced by its fused observation
+    ///
+    pub fn apply<'a, T: Clone>(
+        &self,
+        observations: &'a [T],
+        bbox: impl Fn(&T) -> &Universal2DBox,
+        with_bbox: impl Fn(&T, Universal2DBox) -> T,
+    ) -> Cow<'a, [T]> {
+        let boxes = observations.iter().map(&bbox).collect::<Vec<_>>();
+        let mut clusters = self.clusters(&boxes);
+        if clusters.len() == observations.len() {
+            return Cow::Borrowed(observations);
+        }
+        clusters.sort_by_key(|c| c[0]);
+        Cow::Owned(
+            clusters
+                .into_iter()
+                .map(|c| {
+                    let seed = &observations[c[0]];
+                    if c.len() == 1 {
+                        seed.clone()
+                    } else {
+                        with_bbox(seed, fuse(c.iter().map(|i| boxes[*i])))
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Fuses the boxes with their confidences used as weights
+///
+pub fn fuse<'a>(boxes: impl Iterator<Item = &'a Universal2DBox>) -> Universal2DBox {
+    let (mut xc, mut yc, mut width, mut height, mut angle) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    let (mut weights, mut confidence, mut oriented, mut count) = (0.0, 0.0_f32, true, 0);
+    for b in boxes {
+        let w = b.confidence.max(f32::EPSILON);
+        xc += w * b.xc;
+        yc += w * b.yc;
+        width += w * b.aspect * b.height;
+        height += w * b.height;
+        match b.angle {
+            Some(a) => angle += w * a,
+            None => oriented = false,
+        }
+        weights += w;
+        confidence = confidence.max(b.confidence);
+        count += 1;
+    }
+    assert!(count > 0, "At least one box must be fused");
+    let (width, height) = (width / weights, height / weights);
+    Universal2DBox::new_with_confidence(
+        xc / weights,
+        yc / weights,
+        oriented.then_some(angle / weights),
+        width / height,
+        height,
+        confidence,
+    )
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::CandidateClustering;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "CandidateClustering")]
+    #[derive(Debug, Clone, Copy)]
+    pub struct PyCandidateClustering(pub(crate) CandidateClustering);
+
+    #[pymethods]
+    impl PyCandidateClustering {
+        #[new]
+        #[pyo3(signature = (iou_threshold))]
+        pub(crate) fn new(iou_threshold: f32) -> Self {
+            Self(CandidateClustering::new(iou_threshold))
+        }
+
+        #[getter]
+        fn iou_threshold(&self) -> f32 {
+            self.0.iou_threshold()
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::candidate_clustering::{fuse, CandidateClustering};
+    use crate::utils::bbox::{BoundingBox, Universal2DBox};
+    use crate::EPS;
+
+    #[test]
+    fn fusion() {
+        let l = BoundingBox::new_with_confidence(0.0, 0.0, 10.0, 20.0, 0.75).as_xyaah();
+        let r = BoundingBox::new_with_confidence(4.0, 0.0, 14.0, 20.0, 0.25).as_xyaah();
+        let f = fuse([&l, &r].into_iter());
+        assert!((f.xc - 6.5).abs() < EPS);
+        assert!((f.aspect * f.height - 11.0).abs() < EPS);
+        assert!((f.height - 20.0).abs() < EPS);
+        assert!(f.angle.is_none());
+        assert_eq!(f.confidence, 0.75);
+    }
+
+    #[test]
+    fn clustering() {
+        let observations = vec![
+            (
+                BoundingBox::new_with_confidence(0.0, 0.0, 10.0, 20.0, 0.5).as_xyaah(),
+                1,
+            ),
+            (
+                BoundingBox::new_with_confidence(100.0, 0.0, 10.0, 20.0, 0.9).as_xyaah(),
+                2,
+            ),
+            (
+                BoundingBox::new_with_confidence(1.0, 0.0, 10.0, 20.0, 0.9).as_xyaah(),
+                3,
+            ),
+        ];
+        let clustering = CandidateClustering::new(0.5);
+        fn bbox(o: &(Universal2DBox, i32)) -> &Universal2DBox {
+            &o.0
+        }
+        fn with_bbox(o: &(Universal2DBox, i32), b: Universal2DBox) -> (Universal2DBox, i32) {
+            (b, o.1)
+        }
+        let res = clustering.apply(&observations, bbox, with_bbox);
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].1, 2);
+        assert_eq!(res[1].1, 3);
+        assert_eq!(res[1].0.confidence, 0.9);
+        assert!(res[1].0.xc > 5.5 && res[1].0.xc < 6.0);
+
+        let res = CandidateClustering::new(0.95).apply(&observations, bbox, with_bbox);
+        assert_eq!(res.len(), 3);
+    }
+}
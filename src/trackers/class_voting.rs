@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+/// The class of the track chosen by the majority of its observations
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackClass {
+    /// The class label
+    pub class_id: i64,
+    /// The share of the observations of the track labeled with the class
+    pub stability: f32,
+    /// The class does not change anymore
+    pub frozen: bool,
+}
+
+/// Histogram of the class labels of the track observations.
+///
+/// The majority class changes only when another class collects strictly more votes, so the class
+/// of the track does not flip when the votes are balanced. When the freezing is enabled, the
+/// majority class is fixed after the track collects the required number of votes; the votes are
+/// still counted, so the stability keeps reflecting the detector output.
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClassVotes {
+    votes: Vec<(i64, usize)>,
+    total: usize,
+    leader: Option<(i64, usize)>,
+    frozen: bool,
+}
+
+impl ClassVotes {
+    /// Accounts the class label of the observation
+    ///
+    /// # Parameters
+    /// * `class_id` - the class label
+    /// * `freeze_after` - the number of votes after which the majority class is fixed
+    ///
+    pub fn vote(&mut self, class_id: i64, freeze_after: Option<usize>) {
+        self.add(class_id, 1, freeze_after);
+    }
+
+    /// Accounts the class labels collected by the other track
+    ///
+    pub fn merge(&mut self, other: &ClassVotes, freeze_after: Option<usize>) {
+        for (class_id, count) in &other.votes {
+            self.add(*class_id, *count, freeze_after);
+        }
+    }
+
+    fn add(&mut self, class_id: i64, count: usize, freeze_after: Option<usize>) {
+        let votes = match self.votes.iter_mut().find(|(c, _)| *c == class_id) {
+            Some((_, votes)) => {
+                *votes += count;
+                *votes
+            }
+            None => {
+                self.votes.push((class_id, count));
+                count
+            }
+        };
+        self.total += count;
+
+        match &mut self.leader {
+            Some((leader, leader_votes)) if *leader == class_id => *leader_votes = votes,
+            Some((_, leader_votes)) if self.frozen || votes <= *leader_votes => {}
+            _ => self.leader = Some((class_id, votes)),
+        }
+        if matches!(freeze_after, Some(n) if self.total >= n) {
+            self.frozen = true;
+        }
+    }
+
+    /// The votes collected per class
+    ///
+    pub fn votes(&self) -> &[(i64, usize)] {
+        &self.votes
+    }
+
+    /// The majority class, `None` if no labeled observations were collected
+    ///
+    pub fn majority(&self) -> Option<TrackClass> {
+        self.leader.map(|(class_id, votes)| TrackClass {
+            class_id,
+            stability: votes as f32 / self.total as f32,
+            frozen: self.frozen,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::class_voting::ClassVotes;
+
+    #[test]
+    fn majority() {
+        let mut votes = ClassVotes::default();
+        assert!(votes.majority().is_none());
+
+        votes.vote(1, None);
+        votes.vote(2, None);
+        assert_eq!(votes.majority().unwrap().class_id, 1);
+        votes.vote(2, None);
+        let class = votes.majority().unwrap();
+        assert_eq!(class.class_id, 2);
+        assert!((class.stability - 2.0 / 3.0).abs() < 1e-6);
+        assert!(!class.frozen);
+
+        let mut other = ClassVotes::default();
+        other.vote(1, None);
+        other.vote(1, None);
+        votes.merge(&other, None);
+        assert_eq!(votes.majority().unwrap().class_id, 1);
+        assert_eq!(votes.votes(), &[(1, 3), (2, 2)]);
+    }
+
+    #[test]
+    fn freeze() {
+        let mut votes = ClassVotes::default();
+        votes.vote(1, Some(2));
+        votes.vote(2, Some(2));
+        assert!(votes.majority().unwrap().frozen);
+        for _ in 0..5 {
+            votes.vote(2, Some(2));
+        }
+        let class = votes.majority().unwrap();
+        assert_eq!(class.class_id, 1);
+        assert!((class.stability - 1.0 / 7.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::trackers::sort::PositionalMetricType;
+use crate::utils::bbox::Universal2DBox;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Configuration of the track density estimation
+///
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DensityOptions {
+    /// The side of the square grid cell the density is estimated for
+    pub cell_size: f32,
+    /// The weight of the last epoch in the smoothed counts of the cells, `1.0` keeps the last epoch only
+    pub smoothing: f32,
+    /// Adapt the positional association thresholds to the density of the cells
+    pub adapt: bool,
+    /// The density (objects per unit area) the configured thresholds are kept for
+    pub reference_density: f32,
+    /// The lowest factor the association is loosened with in the sparse cells
+    pub min_factor: f32,
+    /// The highest factor the association is tightened with in the congested cells
+    pub max_factor: f32,
+}
+
+impl Default for DensityOptions {
+    fn default() -> Self {
+        Self {
+            cell_size: 100.0,
+            smoothing: 0.3,
+            adapt: false,
+            reference_density: 1.0 / 10000.0,
+            min_factor: 0.5,
+            max_factor: 2.0,
+        }
+    }
+}
+
+/// Density of the objects of the scene
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneDensity {
+    /// The smoothed number of the objects
+    pub objects: f32,
+    /// The number of the grid cells occupied by the objects
+    pub occupied_cells: usize,
+    /// The objects per unit area of the occupied cells
+    pub density: f32,
+}
+
+/// The smoothed numbers of the objects per grid cell
+///
+type CellCounts = HashMap<(i64, i64), f32>;
+
+/// Online estimator of the object density.
+///
+/// The scene is split into the square cells, the observations of every epoch are counted for the
+/// cells of their box centers. The counts are smoothed over the epochs with the exponential moving
+/// average, so a single missed or duplicated detection does not change the estimate much.
+///
+/// When the adaptation is enabled, the positional metric of the candidate is scaled with the factor
+/// `cell density / reference density` limited by `[min_factor, max_factor]`: the Mahalanobis cost
+/// is multiplied by the factor and IoU is divided by it, so the association requires closer
+/// matches in the congested cells and accepts farther ones in the sparse cells.
+///
+#[derive(Debug, Default)]
+pub struct DensityEstimator {
+    opts: DensityOptions,
+    scenes: Mutex<HashMap<u64, CellCounts>>,
+}
+
+impl DensityEstimator {
+    pub fn new(opts: DensityOptions) -> Self {
+        assert!(opts.cell_size > 0.0, "Cell size must be a positive number");
+        assert!(
+            opts.smoothing > 0.0 && opts.smoothing <= 1.0,
+            "Smoothing must lay between (0.0 and 1.0]"
+        );
+        assert!(
+            opts.reference_density > 0.0,
+            "Reference density must be a positive number"
+        );
+        assert!(
+            opts.min_factor > 0.0 && opts.min_factor <= 1.0 && opts.max_factor >= 1.0,
+            "Factors must satisfy 0.0 < min_factor <= 1.0 <= max_factor"
+        );
+        Self {
+            opts,
+            scenes: Mutex::default(),
+        }
+    }
+
+    pub fn options(&self) -> DensityOptions {
+        self.opts
+    }
+
+    fn cell(&self, bbox: &Universal2DBox) -> (i64, i64) {
+        (
+            (bbox.xc / self.opts.cell_size).floor() as i64,
+            (bbox.yc / self.opts.cell_size).floor() as i64,
+        )
+    }
+
+    /// Accounts the observations of the epoch
+    ///
+    pub fn record<'a>(&self, scene_id: u64, boxes: impl Iterator<Item = &'a Universal2DBox>) {
+        let mut counts = CellCounts::default();
+        for bbox in boxes {
+            *counts.entry(self.cell(bbox)).or_default() += 1.0;
+        }
+
+        let alpha = self.opts.smoothing;
+        let mut scenes = self.scenes.lock().unwrap();
+        let cells = scenes.entry(scene_id).or_default();
+        for (cell, value) in cells.iter_mut() {
+            *value -= alpha * *value;
+            if let Some(count) = counts.remove(cell) {
+                *value += alpha * count;
+            }
+        }
+        for (cell, count) in counts {
+            cells.insert(cell, alpha * count);
+        }
+        cells.retain(|_, value| *value >= 0.01);
+    }
+
+    /// The density of the scene, `None` if no observations of the scene were accounted
+    ///
+    pub fn estimate(&self, scene_id: u64) -> Option<SceneDensity> {
+        let scenes = self.scenes.lock().unwrap();
+        let cells = scenes.get(&scene_id).filter(|c| !c.is_empty())?;
+        let objects = cells.values().sum::<f32>();
+        Some(SceneDensity {
+            objects,
+            occupied_cells: cells.len(),
+            density: objects / (cells.len() as f32 * self.opts.cell_size.powi(2)),
+        })
+    }
+
+    /// The density (objects per unit area) of the cell containing the center of the box
+    ///
+    pub fn cell_density(&self, scene_id: u64, bbox: &Universal2DBox) -> f32 {
+        let scenes = self.scenes.lock().unwrap();
+        scenes
+            .get(&scene_id)
+            .and_then(|cells| cells.get(&self.cell(bbox)))
+            .map(|count| count / self.opts.cell_size.powi(2))
+            .unwrap_or(0.0)
+    }
+
+    /// The factor the association is tightened with at the box, `1.0` when the adaptation is disabled
+    ///
+    pub fn factor(&self, scene_id: u64, bbox: &Universal2DBox) -> f32 {
+        if !self.opts.adapt {
+            return 1.0;
+        }
+        (self.cell_density(scene_id, bbox) / self.opts.reference_density)
+            .clamp(self.opts.min_factor, self.opts.max_factor)
+    }
+
+    /// Forgets the density of the scene
+    ///
+    pub fn reset(&self, scene_id: u64) {
+        self.scenes.lock().unwrap().remove(&scene_id);
+    }
+}
+
+/// Scales the positional metric with the congestion factor
+///
+pub fn adapt_positional_metric(kind: PositionalMetricType, metric: f32, factor: f32) -> f32 {
+    match kind {
+        PositionalMetricType::Mahalanobis => metric * factor,
+        PositionalMetricType::IoU(_) => metric / factor,
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::DensityOptions;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "DensityOptions")]
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct PyDensityOptions(pub(crate) DensityOptions);
+
+    #[pymethods]
+    impl PyDensityOptions {
+        #[new]
+        #[pyo3(signature = (cell_size = 100.0, smoothing = 0.3, adapt = false, reference_density = 0.0001, min_factor = 0.5, max_factor = 2.0))]
+        pub(crate) fn new(
+            cell_size: f32,
+            smoothing: f32,
+            adapt: bool,
+            reference_density: f32,
+            min_factor: f32,
+            max_factor: f32,
+        ) -> Self {
+            Self(DensityOptions {
+                cell_size,
+                smoothing,
+                adapt,
+                reference_density,
+                min_factor,
+                max_factor,
+            })
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::density::{DensityEstimator, DensityOptions};
+    use crate::utils::bbox::{BoundingBox, Universal2DBox};
+
+    #[test]
+    fn estimate() {
+        let estimator = DensityEstimator::new(DensityOptions {
+            cell_size: 100.0,
+            smoothing: 1.0,
+            adapt: true,
+            reference_density: 2.0 / 10000.0,
+            min_factor: 0.5,
+            max_factor: 2.0,
+        });
+        assert!(estimator.estimate(1).is_none());
+
+        let crowd = (0..6)
+            .map(|i| BoundingBox::new(10.0 * i as f32, 0.0, 5.0, 10.0).into())
+            .chain([BoundingBox::new(500.0, 500.0, 5.0, 10.0).into()])
+            .collect::<Vec<Universal2DBox>>();
+        estimator.record(1, crowd.iter());
+
+        let density = estimator.estimate(1).unwrap();
+        assert_eq!(density.occupied_cells, 2);
+        assert!((density.objects - 7.0).abs() < 1e-6);
+        assert!((density.density - 7.0 / 20000.0).abs() < 1e-9);
+
+        assert_eq!(estimator.factor(1, &crowd[0]), 2.0);
+        assert_eq!(estimator.factor(1, &crowd[6]), 0.5);
+        assert_eq!(estimator.factor(2, &crowd[0]), 0.5);
+
+        estimator.record(1, crowd[6..].iter());
+        assert_eq!(estimator.estimate(1).unwrap().occupied_cells, 1);
+
+        estimator.reset(1);
+        assert!(estimator.estimate(1).is_none());
+    }
+
+    #[test]
+    fn smoothing() {
+        let estimator = DensityEstimator::new(DensityOptions {
+            smoothing: 0.5,
+            ..Default::default()
+        });
+        let bbox: Universal2DBox = BoundingBox::new(10.0, 10.0, 5.0, 10.0).into();
+        estimator.record(1, [&bbox, &bbox].into_iter());
+        estimator.record(1, [&bbox, &bbox].into_iter());
+        assert!((estimator.estimate(1).unwrap().objects - 1.5).abs() < 1e-6);
+        estimator.record(1, std::iter::empty());
+        assert!((estimator.estimate(1).unwrap().objects - 0.75).abs() < 1e-6);
+        assert_eq!(estimator.factor(1, &bbox), 1.0);
+    }
+}
@@ -0,0 +1,288 @@
+use crate::track::{ObservationAttributes, ObservationMetricOk};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The distances between the observation and the track
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TrackDistance {
+    pub track_id: u64,
+    /// The best positional score of the pair reported by the positional metric, the higher is
+    /// the closer
+    pub positional: Option<f32>,
+    /// The smallest distance between the features of the pair, the lower is the closer
+    pub visual: Option<f32>,
+}
+
+impl TrackDistance {
+    /// The visual distance is preferred when known
+    ///
+    fn closer_than(&self, other: &TrackDistance) -> bool {
+        match (self.visual, other.visual) {
+            (Some(d), Some(other)) => d < other,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => {
+                self.positional.unwrap_or(f32::MIN) > other.positional.unwrap_or(f32::MIN)
+            }
+        }
+    }
+}
+
+/// The observation merged into the existing track
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MatchedPair {
+    /// The index of the observation in the slice passed to the prediction
+    pub index: usize,
+    /// The distances to the track the observation is merged into, the scores are `None` when
+    /// the track was reactivated or promoted from the cold store
+    pub distance: TrackDistance,
+}
+
+/// The observation which started the new track
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct UnmatchedDetection {
+    /// The index of the observation in the slice passed to the prediction
+    pub index: usize,
+    /// The distances to the nearest track, `None` when no track was compared with the observation
+    pub nearest: Option<TrackDistance>,
+}
+
+/// The outcome of the association of the scene epoch: the matched pairs with their distances and
+/// the unmatched detections with the distances to the nearest tracks. The hard examples for the
+/// active learning are the matched pairs with the large distances and the unmatched detections
+/// close to the tracks.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EpochDistances {
+    pub scene_id: u64,
+    pub epoch: usize,
+    pub matched: Vec<MatchedPair>,
+    pub unmatched: Vec<UnmatchedDetection>,
+}
+
+impl EpochDistances {
+    /// Builds the export from the raw distances of the candidates and the results of the voting
+    ///
+    /// # Parameters
+    /// * `candidates` - the indices of the observations, the ids of the candidate tracks built from
+    ///   them and the ids of the tracks the candidates are merged into
+    /// * `distances` - the distances the voting was run on
+    ///
+    pub(crate) fn new<'a, OA>(
+        scene_id: u64,
+        epoch: usize,
+        candidates: impl IntoIterator<Item = (usize, u64, Option<u64>)>,
+        distances: impl IntoIterator<Item = &'a ObservationMetricOk<OA>>,
+    ) -> Self
+    where
+        OA: ObservationAttributes<MetricObject = f32>,
+    {
+        let mut pairs = HashMap::<u64, HashMap<u64, TrackDistance>>::new();
+        for d in distances {
+            let pair = pairs
+                .entry(d.from)
+                .or_default()
+                .entry(d.to)
+                .or_insert(TrackDistance {
+                    track_id: d.to,
+                    positional: None,
+                    visual: None,
+                });
+            if let Some(p) = d.attribute_metric {
+                pair.positional = Some(pair.positional.map_or(p, |e| e.max(p)));
+            }
+            if let Some(v) = d.feature_distance {
+                pair.visual = Some(pair.visual.map_or(v, |e| e.min(v)));
+            }
+        }
+
+        let mut matched = Vec::default();
+        let mut unmatched = Vec::default();
+        for (index, candidate, dest) in candidates {
+            let tracks = pairs.get(&candidate);
+            match dest {
+                Some(track_id) => matched.push(MatchedPair {
+                    index,
+                    distance: tracks.and_then(|t| t.get(&track_id)).copied().unwrap_or(
+                        TrackDistance {
+                            track_id,
+                            positional: None,
+                            visual: None,
+                        },
+                    ),
+                }),
+                None => unmatched.push(UnmatchedDetection {
+                    index,
+                    nearest: tracks.and_then(|t| {
+                        t.values()
+                            .fold(None, |nearest: Option<TrackDistance>, d| match nearest {
+                                Some(n) if !d.closer_than(&n) => Some(n),
+                                _ => Some(*d),
+                            })
+                    }),
+                }),
+            }
+        }
+        Self {
+            scene_id,
+            epoch,
+            matched,
+            unmatched,
+        }
+    }
+}
+
+/// The receiver of the distances exported after every association of the tracker
+///
+pub trait DistanceSink: Send + Sync {
+    fn export(&self, distances: EpochDistances);
+}
+
+impl<F> DistanceSink for F
+where
+    F: Fn(EpochDistances) + Send + Sync,
+{
+    fn export(&self, distances: EpochDistances) {
+        self(distances)
+    }
+}
+
+/// The sink that keeps the exported distances until they are [drained](Self::drain)
+///
+#[derive(Debug, Clone, Default)]
+pub struct DistanceCollector(Arc<Mutex<Vec<EpochDistances>>>);
+
+impl DistanceCollector {
+    /// Takes the distances collected since the previous call
+    ///
+    pub fn drain(&self) -> Vec<EpochDistances> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl DistanceSink for DistanceCollector {
+    fn export(&self, distances: EpochDistances) {
+        self.0.lock().unwrap().push(distances);
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::DistanceCollector;
+    use pyo3::prelude::*;
+
+    type PyTrackDistance = (u64, Option<f32>, Option<f32>);
+
+    #[pyclass]
+    #[pyo3(name = "DistanceCollector")]
+    #[derive(Debug, Clone, Default)]
+    pub struct PyDistanceCollector(pub(crate) DistanceCollector);
+
+    #[pymethods]
+    impl PyDistanceCollector {
+        #[new]
+        fn new() -> Self {
+            Self::default()
+        }
+
+        /// Takes the distances collected since the previous call as the
+        /// `(scene_id, epoch, matched, unmatched)` tuples, where `matched` are the
+        /// `(index, (track_id, positional, visual))` tuples and `unmatched` are the
+        /// `(index, (track_id, positional, visual) | None)` tuples
+        ///
+        #[pyo3(text_signature = "($self)")]
+        #[allow(clippy::type_complexity)]
+        fn drain(
+            &self,
+        ) -> Vec<(
+            u64,
+            usize,
+            Vec<(usize, PyTrackDistance)>,
+            Vec<(usize, Option<PyTrackDistance>)>,
+        )> {
+            self.0
+                .drain()
+                .into_iter()
+                .map(|e| {
+                    (
+                        e.scene_id,
+                        e.epoch,
+                        e.matched
+                            .iter()
+                            .map(|m| {
+                                let d = m.distance;
+                                (m.index, (d.track_id, d.positional, d.visual))
+                            })
+                            .collect(),
+                        e.unmatched
+                            .iter()
+                            .map(|u| {
+                                (
+                                    u.index,
+                                    u.nearest.map(|d| (d.track_id, d.positional, d.visual)),
+                                )
+                            })
+                            .collect(),
+                    )
+                })
+                .collect()
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::ObservationMetricOk;
+    use crate::trackers::distance_export::{EpochDistances, TrackDistance};
+    use crate::utils::bbox::Universal2DBox;
+
+    #[test]
+    fn export() {
+        let dist = |from, to, positional: f32| {
+            ObservationMetricOk::<Universal2DBox>::new(from, to, Some(positional), None)
+        };
+        let distances = vec![
+            dist(10, 1, 0.8),
+            dist(10, 2, 0.1),
+            dist(11, 1, 0.3),
+            dist(11, 2, 0.2),
+        ];
+        let export = EpochDistances::new(
+            3,
+            5,
+            vec![(0, 10, Some(1)), (1, 11, None), (2, 12, None)],
+            &distances,
+        );
+        assert_eq!(export.scene_id, 3);
+        assert_eq!(export.epoch, 5);
+        assert_eq!(export.matched.len(), 1);
+        assert_eq!(export.matched[0].index, 0);
+        assert_eq!(
+            export.matched[0].distance,
+            TrackDistance {
+                track_id: 1,
+                positional: Some(0.8),
+                visual: None
+            }
+        );
+        assert_eq!(export.unmatched.len(), 2);
+        assert_eq!(export.unmatched[0].nearest.unwrap().track_id, 1);
+        assert_eq!(export.unmatched[1].index, 2);
+        assert!(export.unmatched[1].nearest.is_none());
+    }
+}
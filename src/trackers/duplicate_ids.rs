@@ -0,0 +1,163 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Defines how the tracker handles the observations of a scene that are passed within one
+/// epoch with the same `custom_object_id`.
+///
+/// Such observations usually come from the upstream bugs (e.g. the detector results are merged twice)
+/// and create competing candidate tracks that steal votes from each other.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DuplicateObjectIdPolicy {
+    /// Observations are not checked
+    #[default]
+    Allow,
+    /// Duplicates are reported to the log and counted, all the observations are used
+    Warn,
+    /// Duplicates are reported to the log and counted, only the observation with the best
+    /// bounding box confidence is used
+    KeepBestConfidence,
+}
+
+impl DuplicateObjectIdPolicy {
+    /// Applies the policy to the observations of one scene and epoch
+    ///
+    /// # Parameters
+    /// * `scene_id` - the scene of the observations
+    /// * `epoch` - the epoch of the observations
+    /// * `observations` - the observations to check
+    /// * `key` - extracts `custom_object_id` and confidence from the observation
+    ///
+    /// # Returns
+    /// the observations to use and the number of found duplicates
+    ///
+    pub fn apply<'a, T: Clone>(
+        &self,
+        scene_id: u64,
+        epoch: usize,
+        observations: &'a [T],
+        key: impl Fn(&T) -> (Option<i64>, f32),
+    ) -> (Cow<'a, [T]>, usize) {
+        if *self == DuplicateObjectIdPolicy::Allow {
+            return (Cow::Borrowed(observations), 0);
+        }
+
+        // custom_object_id -> index of the best observation
+        let mut best = HashMap::<i64, usize>::default();
+        let mut duplicates = 0;
+        for (i, o) in observations.iter().enumerate() {
+            let (id, confidence) = key(o);
+            if let Some(id) = id {
+                match best.get_mut(&id) {
+                    None => {
+                        best.insert(id, i);
+                    }
+                    Some(best_idx) => {
+                        duplicates += 1;
+                        warn!(
+                            "Duplicate custom_object_id={} is passed for scene_id={} at epoch={}",
+                            id, scene_id, epoch
+                        );
+                        if confidence > key(&observations[*best_idx]).1 {
+                            *best_idx = i;
+                        }
+                    }
+                }
+            }
+        }
+
+        if duplicates == 0 || *self == DuplicateObjectIdPolicy::Warn {
+            return (Cow::Borrowed(observations), duplicates);
+        }
+
+        let kept = observations
+            .iter()
+            .enumerate()
+            .filter(|(i, o)| match key(o).0 {
+                Some(id) => best[&id] == *i,
+                None => true,
+            })
+            .map(|(_, o)| o.clone())
+            .collect::<Vec<_>>();
+        (Cow::Owned(kept), duplicates)
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::DuplicateObjectIdPolicy;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "DuplicateObjectIdPolicy")]
+    #[derive(Clone, Copy, Debug)]
+    pub struct PyDuplicateObjectIdPolicy(pub DuplicateObjectIdPolicy);
+
+    #[pymethods]
+    impl PyDuplicateObjectIdPolicy {
+        #[staticmethod]
+        pub fn allow() -> Self {
+            PyDuplicateObjectIdPolicy(DuplicateObjectIdPolicy::Allow)
+        }
+
+        #[staticmethod]
+        pub fn warn() -> Self {
+            PyDuplicateObjectIdPolicy(DuplicateObjectIdPolicy::Warn)
+        }
+
+        #[staticmethod]
+        pub fn keep_best_confidence() -> Self {
+            PyDuplicateObjectIdPolicy(DuplicateObjectIdPolicy::KeepBestConfidence)
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{self:?}")
+        }
+
+        fn __str__(&self) -> String {
+            format!("{self:#?}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::duplicate_ids::DuplicateObjectIdPolicy;
+
+    fn key(o: &(Option<i64>, f32)) -> (Option<i64>, f32) {
+        *o
+    }
+
+    #[test]
+    fn policies() {
+        let observations = vec![
+            (Some(1), 0.5),
+            (None, 0.1),
+            (Some(1), 0.9),
+            (None, 0.1),
+            (Some(2), 0.3),
+            (Some(1), 0.7),
+        ];
+
+        let (res, duplicates) = DuplicateObjectIdPolicy::Allow.apply(0, 1, &observations, key);
+        assert_eq!(res.len(), 6);
+        assert_eq!(duplicates, 0);
+
+        let (res, duplicates) = DuplicateObjectIdPolicy::Warn.apply(0, 1, &observations, key);
+        assert_eq!(res.len(), 6);
+        assert_eq!(duplicates, 2);
+
+        let (res, duplicates) =
+            DuplicateObjectIdPolicy::KeepBestConfidence.apply(0, 1, &observations, key);
+        assert_eq!(duplicates, 2);
+        assert_eq!(
+            res.as_ref(),
+            &[(None, 0.1), (Some(1), 0.9), (None, 0.1), (Some(2), 0.3)]
+        );
+    }
+}
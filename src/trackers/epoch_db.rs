@@ -3,10 +3,54 @@ use anyhow::Result;
 use std::collections::HashMap;
 use std::sync::RwLock;
 
+/// Keeps the last epochs when the scenes received the observations. The idleness is measured in
+/// the epochs of the scene itself, so the scene which epochs advance with the empty predictions
+/// or the skipped epochs for `n` epochs after its last observations is considered idle for `n`
+/// epochs.
+///
+#[derive(Debug, Default)]
+pub struct SceneUpdates {
+    last_active: HashMap<u64, usize>,
+}
+
+impl SceneUpdates {
+    fn activate(&mut self, scene_id: u64, epoch: usize) {
+        self.last_active.insert(scene_id, epoch);
+    }
+
+    fn idle_epochs(&self, scene_id: u64, epoch: usize) -> usize {
+        epoch.saturating_sub(self.last_active.get(&scene_id).copied().unwrap_or_default())
+    }
+}
+
 pub trait EpochDb {
     fn epoch_db(&self) -> &Option<RwLock<HashMap<u64, usize>>>;
     fn max_idle_epochs(&self) -> usize;
 
+    /// The storage of the last scene updates, the idle scenes cannot be found without it
+    ///
+    fn scene_updates(&self) -> Option<&RwLock<SceneUpdates>> {
+        None
+    }
+
+    /// Whether the tracks are notified when the epochs of their scenes advance, look at
+    /// [TrackAttributes::on_epoch](crate::track::TrackAttributes::on_epoch)
+    ///
+    fn epoch_notifications(&self) -> bool {
+        true
+    }
+
+    /// Marks the current epoch of the scene as the epoch the scene received the observations
+    ///
+    fn mark_scene_active(&self, scene_id: u64) {
+        if let (Some(updates), Some(epoch)) = (
+            self.scene_updates(),
+            self.current_epoch_with_scene(scene_id),
+        ) {
+            updates.write().unwrap().activate(scene_id, epoch);
+        }
+    }
+
     fn skip_epochs_for_scene(&self, scene_id: u64, n: usize) {
         if let Some(epoch_store) = self.epoch_db() {
             let mut epoch_store = epoch_store.write().unwrap();
@@ -18,6 +62,61 @@ pub trait EpochDb {
         }
     }
 
+    /// The scenes known to the epoch storage in ascending order
+    ///
+    fn scenes(&self) -> Vec<u64> {
+        let mut scenes = self
+            .epoch_db()
+            .as_ref()
+            .map(|epoch_store| {
+                epoch_store
+                    .read()
+                    .unwrap()
+                    .keys()
+                    .copied()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        scenes.sort_unstable();
+        scenes
+    }
+
+    /// Removes the scene from the epoch storage, so its epochs start from the beginning. The tracks
+    /// of the scene are considered wasted until the scene is used again.
+    ///
+    /// # Returns
+    /// the epoch of the removed scene
+    ///
+    fn reset_scene(&self, scene_id: u64) -> Option<usize> {
+        let epoch_store = self.epoch_db().as_ref()?;
+        let epoch = epoch_store.write().unwrap().remove(&scene_id);
+        if let Some(updates) = self.scene_updates() {
+            updates.write().unwrap().last_active.remove(&scene_id);
+        }
+        epoch
+    }
+
+    /// The scenes which passed more than `n` of their epochs since they lastly received the
+    /// observations, in ascending order
+    ///
+    fn idle_scenes(&self, n: usize) -> Vec<u64> {
+        match (self.scene_updates(), self.epoch_db()) {
+            (Some(updates), Some(epoch_store)) => {
+                let updates = updates.read().unwrap();
+                let mut scenes = epoch_store
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .filter(|(scene_id, epoch)| updates.idle_epochs(**scene_id, **epoch) > n)
+                    .map(|(scene_id, _)| *scene_id)
+                    .collect::<Vec<_>>();
+                scenes.sort_unstable();
+                scenes
+            }
+            _ => vec![],
+        }
+    }
+
     fn current_epoch_with_scene(&self, scene_id: u64) -> Option<usize> {
         if let Some(epoch_store) = self.epoch_db() {
             let mut epoch_store = epoch_store.write().unwrap();
@@ -36,13 +135,14 @@ pub trait EpochDb {
         if let Some(epoch_store) = self.epoch_db() {
             let mut epoch_store = epoch_store.write().unwrap();
             let epoch = epoch_store.get_mut(&scene_id);
-            if let Some(epoch) = epoch {
+            let epoch = if let Some(epoch) = epoch {
                 *epoch += 1;
-                Some(*epoch)
+                *epoch
             } else {
                 epoch_store.insert(scene_id, 1);
-                Some(1)
-            }
+                1
+            };
+            Some(epoch)
         } else {
             None
         }
@@ -51,10 +151,14 @@ pub trait EpochDb {
     fn baked(&self, scene_id: u64, last_updated: usize) -> Result<TrackStatus> {
         if let Some(current_epoch) = &self.epoch_db() {
             let current_epoch = current_epoch.read().unwrap();
-            if last_updated + self.max_idle_epochs() < *current_epoch.get(&scene_id).unwrap_or(&0) {
-                Ok(TrackStatus::Wasted)
-            } else {
-                Ok(TrackStatus::Pending)
+            match current_epoch.get(&scene_id) {
+                // The tracks outlive the scene when the scene is reset
+                None if last_updated > 0 => Ok(TrackStatus::Wasted),
+                None => Ok(TrackStatus::Pending),
+                Some(epoch) if last_updated + self.max_idle_epochs() < *epoch => {
+                    Ok(TrackStatus::Wasted)
+                }
+                Some(_) => Ok(TrackStatus::Pending),
             }
         } else {
             // If epoch expiration is not set the tracks are always ready.
@@ -68,7 +172,7 @@ pub trait EpochDb {
 #[cfg(test)]
 mod tests {
     use crate::track::TrackStatus;
-    use crate::trackers::epoch_db::EpochDb;
+    use crate::trackers::epoch_db::{EpochDb, SceneUpdates};
     use std::collections::HashMap;
     use std::sync::RwLock;
 
@@ -115,5 +219,64 @@ mod tests {
 
         db.skip_epochs_for_scene(1, 1);
         assert!(matches!(db.baked(1, 2), Ok(TrackStatus::Wasted)));
+
+        assert_eq!(db.scenes(), vec![0, 1]);
+        assert!(db.idle_scenes(0).is_empty());
+        assert_eq!(db.reset_scene(0), Some(12));
+        assert_eq!(db.reset_scene(0), None);
+        assert_eq!(db.scenes(), vec![1]);
+        assert_eq!(db.current_epoch_with_scene(0), Some(0));
+    }
+
+    #[test]
+    fn idle_scenes() {
+        #[derive(Debug, Default)]
+        pub struct DbOptions {
+            epoch_db: Option<RwLock<HashMap<u64, usize>>>,
+            scene_updates: RwLock<SceneUpdates>,
+        }
+
+        impl EpochDb for DbOptions {
+            fn epoch_db(&self) -> &Option<RwLock<HashMap<u64, usize>>> {
+                &self.epoch_db
+            }
+
+            fn max_idle_epochs(&self) -> usize {
+                0
+            }
+
+            fn scene_updates(&self) -> Option<&RwLock<SceneUpdates>> {
+                Some(&self.scene_updates)
+            }
+        }
+
+        let db = DbOptions {
+            epoch_db: Some(RwLock::new(HashMap::default())),
+            ..Default::default()
+        };
+
+        for scene_id in 0..3 {
+            db.next_epoch(scene_id);
+            db.mark_scene_active(scene_id);
+        }
+        // the epochs of the other scenes don't make the scene idle
+        for _ in 0..10 {
+            db.next_epoch(0);
+            db.mark_scene_active(0);
+        }
+        assert!(db.idle_scenes(0).is_empty());
+
+        for _ in 0..3 {
+            db.next_epoch(1);
+        }
+        db.skip_epochs_for_scene(2, 5);
+
+        assert_eq!(db.idle_scenes(0), vec![1, 2]);
+        assert_eq!(db.idle_scenes(3), vec![2]);
+        assert!(db.idle_scenes(5).is_empty());
+
+        db.reset_scene(2);
+        assert_eq!(db.idle_scenes(0), vec![1]);
+        assert_eq!(db.scenes(), vec![0, 1]);
     }
 }
@@ -0,0 +1,169 @@
+use crate::utils::bbox::Universal2DBox;
+use serde::{Deserialize, Serialize};
+
+/// Clamping of the track boxes to the frame of the scene.
+///
+/// The frame size is taken from the [calibration](crate::track::calibration::SceneCalibration) of
+/// the scene, the scenes without the frame size are never clamped. The clamped tracks are reported
+/// with the `clipped` flag set.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameClamping {
+    /// The boxes are reported as they are
+    #[default]
+    Disabled,
+    /// The predicted and the observed boxes of the reported tracks are clipped to the frame, the
+    /// boxes kept by the tracks are not changed
+    Output,
+    /// The boxes are clipped in the output, also the center of the Kalman state is kept within
+    /// the frame and the velocity along the clamped axis is reset, so the prediction doesn't run
+    /// away when the object leaves the frame
+    OutputAndState,
+}
+
+impl FrameClamping {
+    pub fn output(&self) -> bool {
+        !matches!(self, FrameClamping::Disabled)
+    }
+
+    pub fn state(&self) -> bool {
+        matches!(self, FrameClamping::OutputAndState)
+    }
+}
+
+/// Clips the box to the frame `[0, width] x [0, height]`.
+///
+/// The center of the rotated box is clamped only. The box lying outside of the frame is moved
+/// inside of it keeping the size (limited by the frame).
+///
+/// # Returns
+/// the clipped box and `true` if the box was changed
+///
+pub fn clamp_to_frame(bbox: &Universal2DBox, width: f32, height: f32) -> (Universal2DBox, bool) {
+    if bbox.angle.is_some() {
+        let (xc, yc) = (bbox.xc.clamp(0.0, width), bbox.yc.clamp(0.0, height));
+        let clipped = xc != bbox.xc || yc != bbox.yc;
+        let mut res = bbox.clone();
+        if clipped {
+            res = Universal2DBox::new_with_confidence(
+                xc,
+                yc,
+                bbox.angle,
+                bbox.aspect,
+                bbox.height,
+                bbox.confidence,
+            );
+        }
+        return (res, clipped);
+    }
+
+    let box_width = bbox.aspect * bbox.height;
+    let (left, top) = (bbox.xc - box_width / 2.0, bbox.yc - bbox.height / 2.0);
+    let (right, bottom) = (left + box_width, top + bbox.height);
+    if left >= 0.0 && top >= 0.0 && right <= width && bottom <= height {
+        return (bbox.clone(), false);
+    }
+
+    let (mut new_left, mut new_right) = (left.max(0.0), right.min(width));
+    if new_right <= new_left {
+        let w = box_width.min(width);
+        new_left = (left.clamp(0.0, width - w)).max(0.0);
+        new_right = new_left + w;
+    }
+    let (mut new_top, mut new_bottom) = (top.max(0.0), bottom.min(height));
+    if new_bottom <= new_top {
+        let h = bbox.height.min(height);
+        new_top = (top.clamp(0.0, height - h)).max(0.0);
+        new_bottom = new_top + h;
+    }
+    (
+        Universal2DBox::ltwh_with_confidence(
+            new_left,
+            new_top,
+            new_right - new_left,
+            new_bottom - new_top,
+            bbox.confidence,
+        ),
+        true,
+    )
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::FrameClamping;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "FrameClamping")]
+    #[derive(Clone, Copy, Debug)]
+    pub struct PyFrameClamping(pub FrameClamping);
+
+    #[pymethods]
+    impl PyFrameClamping {
+        #[staticmethod]
+        pub fn disabled() -> Self {
+            PyFrameClamping(FrameClamping::Disabled)
+        }
+
+        #[staticmethod]
+        pub fn output() -> Self {
+            PyFrameClamping(FrameClamping::Output)
+        }
+
+        #[staticmethod]
+        pub fn output_and_state() -> Self {
+            PyFrameClamping(FrameClamping::OutputAndState)
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{self:?}")
+        }
+
+        fn __str__(&self) -> String {
+            format!("{self:#?}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::frame_clamping::clamp_to_frame;
+    use crate::utils::bbox::{BoundingBox, Universal2DBox};
+    use crate::EPS;
+
+    #[test]
+    fn clamp() {
+        let inside: Universal2DBox = BoundingBox::new(10.0, 10.0, 20.0, 20.0).into();
+        let (res, clipped) = clamp_to_frame(&inside, 100.0, 50.0);
+        assert!(!clipped);
+        assert_eq!(res, inside);
+
+        let overshoot: Universal2DBox =
+            BoundingBox::new_with_confidence(-10.0, 40.0, 30.0, 20.0, 0.7).into();
+        let (res, clipped) = clamp_to_frame(&overshoot, 100.0, 50.0);
+        assert!(clipped);
+        let res = BoundingBox::try_from(res).unwrap();
+        assert!(res.left.abs() < EPS);
+        assert!((res.top - 40.0).abs() < EPS);
+        assert!((res.width - 20.0).abs() < EPS);
+        assert!((res.height - 10.0).abs() < EPS);
+        assert!((res.confidence - 0.7).abs() < EPS);
+
+        let outside: Universal2DBox = BoundingBox::new(120.0, 10.0, 10.0, 20.0).into();
+        let (res, clipped) = clamp_to_frame(&outside, 100.0, 50.0);
+        assert!(clipped);
+        let res = BoundingBox::try_from(res).unwrap();
+        assert!((res.left - 90.0).abs() < EPS);
+        assert!((res.width - 10.0).abs() < EPS);
+        assert!((res.top - 10.0).abs() < EPS);
+
+        let rotated = Universal2DBox::new(-5.0, 10.0, Some(0.5), 1.0, 10.0);
+        let (res, clipped) = clamp_to_frame(&rotated, 100.0, 50.0);
+        assert!(clipped);
+        assert_eq!(res.xc, 0.0);
+        assert_eq!(res.angle, Some(0.5));
+    }
+}
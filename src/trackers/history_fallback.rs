@@ -0,0 +1,115 @@
+use crate::utils::bbox::Universal2DBox;
+use crate::utils::kalman::kalman_2d_box::DIM_2D_BOX_X2;
+use crate::utils::kalman::KalmanState;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// The boxes reported for the track which box history is empty.
+///
+/// The history of the track is momentarily empty when the track is converted before its first
+/// observation is applied (e.g. the candidate track of the merge or the track restored with the
+/// truncated history), the conversion of such track to the output never panics. The synthesized
+/// boxes have zero confidence.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HistoryFallback {
+    /// The box is built from the Kalman state of the track, the empty box is used when the track
+    /// has no state
+    #[default]
+    KalmanState,
+    /// The empty box centered at the origin is used
+    EmptyBox,
+}
+
+impl HistoryFallback {
+    /// The box synthesized for the track without the history
+    ///
+    pub fn synthesize(&self, state: Option<KalmanState<{ DIM_2D_BOX_X2 }>>) -> Universal2DBox {
+        let empty = || Universal2DBox::new_with_confidence(0.0, 0.0, None, 0.0, 0.0, 0.0);
+        match self {
+            HistoryFallback::KalmanState => state
+                .and_then(|s| Universal2DBox::try_from(s).ok())
+                .map(|b| {
+                    Universal2DBox::new_with_confidence(
+                        b.xc, b.yc, b.angle, b.aspect, b.height, 0.0,
+                    )
+                })
+                .unwrap_or_else(empty),
+            HistoryFallback::EmptyBox => empty(),
+        }
+    }
+
+    /// The latest box of the history or the synthesized one when the history is empty
+    ///
+    pub fn last_box(
+        &self,
+        history: &VecDeque<Universal2DBox>,
+        state: Option<KalmanState<{ DIM_2D_BOX_X2 }>>,
+    ) -> Universal2DBox {
+        history
+            .back()
+            .cloned()
+            .unwrap_or_else(|| self.synthesize(state))
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::HistoryFallback;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "HistoryFallback")]
+    #[derive(Clone, Copy, Debug)]
+    pub struct PyHistoryFallback(pub HistoryFallback);
+
+    #[pymethods]
+    impl PyHistoryFallback {
+        #[staticmethod]
+        pub fn kalman_state() -> Self {
+            PyHistoryFallback(HistoryFallback::KalmanState)
+        }
+
+        #[staticmethod]
+        pub fn empty_box() -> Self {
+            PyHistoryFallback(HistoryFallback::EmptyBox)
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{self:?}")
+        }
+
+        fn __str__(&self) -> String {
+            format!("{self:#?}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::history_fallback::HistoryFallback;
+    use crate::utils::bbox::{BoundingBox, Universal2DBox};
+    use crate::utils::kalman::kalman_2d_box::Universal2DBoxKalmanFilter;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn fallback() {
+        let bb: Universal2DBox = BoundingBox::new(10.0, 20.0, 4.0, 8.0).into();
+        let state = Universal2DBoxKalmanFilter::default().initiate(&bb);
+
+        let synthesized = HistoryFallback::KalmanState.synthesize(Some(state));
+        assert_eq!((synthesized.xc, synthesized.yc), (bb.xc, bb.yc));
+        assert_eq!(synthesized.height, bb.height);
+        assert_eq!(synthesized.confidence, 0.0);
+
+        let empty = HistoryFallback::KalmanState.synthesize(None);
+        assert_eq!(empty, HistoryFallback::EmptyBox.synthesize(Some(state)));
+        assert_eq!(empty.height, 0.0);
+
+        let history = VecDeque::from(vec![bb.clone()]);
+        assert_eq!(HistoryFallback::EmptyBox.last_box(&history, None), bb);
+    }
+}
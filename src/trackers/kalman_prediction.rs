@@ -1,3 +1,4 @@
+use crate::trackers::motion_model::MotionModel;
 use crate::utils::bbox::Universal2DBox;
 use crate::utils::kalman::kalman_2d_box::{Universal2DBoxKalmanFilter, DIM_2D_BOX_X2};
 use crate::utils::kalman::KalmanState;
@@ -10,11 +11,61 @@ pub trait TrackAttributesKalmanPrediction {
 
     fn get_velocity_weight(&self) -> f32;
 
+    /// Called when the observation is applied to the predicted state, used to estimate the noise model
+    ///
+    fn report_innovation(
+        &self,
+        _prediction: &KalmanState<{ DIM_2D_BOX_X2 }>,
+        _update: &KalmanState<{ DIM_2D_BOX_X2 }>,
+        _observation_bbox: &Universal2DBox,
+    ) {
+    }
+
+    /// The frame the center of the Kalman state is kept within, `None` if the state is not clamped
+    ///
+    fn state_frame(&self) -> Option<(f32, f32)> {
+        None
+    }
+
+    /// The motion model of the track, the observed boxes are used as the predictions when the
+    /// model is [MotionModel::None](MotionModel::None)
+    ///
+    fn motion_model(&self) -> MotionModel {
+        MotionModel::Kalman
+    }
+
+    /// Predicts the state over the epochs without the observations, the uncertainty of the state
+    /// grows with every epoch
+    ///
+    fn propagate_state(&mut self, epochs: usize) {
+        if !self.motion_model().is_kalman() {
+            return;
+        }
+        if let Some(mut state) = self.get_state() {
+            let f = Universal2DBoxKalmanFilter::new(
+                self.get_position_weight(),
+                self.get_velocity_weight(),
+            );
+            for _ in 0..epochs {
+                state = f.predict(&state);
+            }
+            if let Some((width, height)) = self.state_frame() {
+                state.clamp_center(width, height);
+            }
+            self.set_state(state);
+        }
+    }
+
     fn make_prediction(&mut self, observation_bbox: &Universal2DBox) -> Universal2DBox {
+        if !self.motion_model().is_kalman() {
+            return observation_bbox.clone();
+        }
+
         let f =
             Universal2DBoxKalmanFilter::new(self.get_position_weight(), self.get_velocity_weight());
 
-        let current_state = if let Some(state) = self.get_state() {
+        let last_state = self.get_state();
+        let current_state = if let Some(state) = last_state {
             state
         } else {
             f.initiate(observation_bbox)
@@ -22,7 +73,13 @@ pub trait TrackAttributesKalmanPrediction {
 
         let prediction = f.predict(&current_state);
 
-        let new_state = f.update(&prediction, observation_bbox);
+        let mut new_state = f.update(&prediction, observation_bbox);
+        if last_state.is_some() {
+            self.report_innovation(&prediction, &new_state, observation_bbox);
+        }
+        if let Some((width, height)) = self.state_frame() {
+            new_state.clamp_center(width, height);
+        }
         self.set_state(new_state);
 
         let mut res = Universal2DBox::try_from(new_state).unwrap();
@@ -0,0 +1,121 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The default number of the last predictions the percentiles are computed for
+///
+pub const DEFAULT_LATENCY_WINDOW: usize = 1000;
+
+/// Percentiles of the prediction latency of the scene
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    /// The number of predictions the percentiles are computed for
+    pub samples: usize,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+/// Rolling latency of the predictions per scene.
+///
+/// The latency is measured from the moment the tracker receives the observations of the scene till
+/// the moment the results are ready (for the batch trackers - sent to the batch result channel).
+/// The last `window` latencies of every scene are kept, the percentiles are computed with the
+/// nearest-rank method.
+///
+#[derive(Debug)]
+pub struct LatencyStats {
+    window: usize,
+    scenes: Mutex<HashMap<u64, VecDeque<Duration>>>,
+}
+
+impl Default for LatencyStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_LATENCY_WINDOW)
+    }
+}
+
+impl LatencyStats {
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "Latency window must be greater than 0");
+        Self {
+            window,
+            scenes: Mutex::default(),
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Accounts the latency of the prediction for the scene
+    ///
+    pub fn record(&self, scene_id: u64, latency: Duration) {
+        let mut scenes = self.scenes.lock().unwrap();
+        let samples = scenes.entry(scene_id).or_default();
+        if samples.len() == self.window {
+            samples.pop_front();
+        }
+        samples.push_back(latency);
+    }
+
+    /// The latency percentiles of the scene, `None` if the scene has no predictions accounted
+    ///
+    pub fn percentiles(&self, scene_id: u64) -> Option<LatencyPercentiles> {
+        let mut samples = {
+            let scenes = self.scenes.lock().unwrap();
+            scenes
+                .get(&scene_id)
+                .filter(|s| !s.is_empty())?
+                .iter()
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        samples.sort_unstable();
+        let rank = |p: f64| samples[((p * samples.len() as f64).ceil() as usize).max(1) - 1];
+        Some(LatencyPercentiles {
+            samples: samples.len(),
+            p50: rank(0.50),
+            p95: rank(0.95),
+            p99: rank(0.99),
+        })
+    }
+
+    /// Forgets the latencies of the scene
+    ///
+    pub fn reset(&self, scene_id: u64) {
+        self.scenes.lock().unwrap().remove(&scene_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::latency::LatencyStats;
+    use std::time::Duration;
+
+    #[test]
+    fn percentiles() {
+        let stats = LatencyStats::new(100);
+        assert!(stats.percentiles(1).is_none());
+
+        for i in (1..=200).rev() {
+            stats.record(1, Duration::from_millis(i));
+        }
+        stats.record(2, Duration::from_millis(7));
+
+        let p = stats.percentiles(1).unwrap();
+        assert_eq!(p.samples, 100);
+        assert_eq!(p.p50, Duration::from_millis(50));
+        assert_eq!(p.p95, Duration::from_millis(95));
+        assert_eq!(p.p99, Duration::from_millis(99));
+
+        let p = stats.percentiles(2).unwrap();
+        assert_eq!(p.samples, 1);
+        assert_eq!(p.p50, Duration::from_millis(7));
+        assert_eq!(p.p99, Duration::from_millis(7));
+
+        stats.reset(1);
+        assert!(stats.percentiles(1).is_none());
+    }
+}
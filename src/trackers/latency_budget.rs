@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The latency budget of the predictions of the scene.
+///
+/// When the prediction of the scene takes longer than `max_latency` or the queue of the pending
+/// predictions is longer than `max_queue_depth`, the scene is degraded: the visual distances are
+/// not computed and the observations are associated by their positions only, the same way
+/// [disable_visual](crate::trackers::predict_overrides::PredictOverrides::disable_visual) does.
+/// The scene is restored when it stayed degraded for at least `min_degraded_epochs` predictions
+/// and the prediction takes not longer than `recovery_latency` while the queue is within its limit.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LatencyBudget {
+    max_latency: Duration,
+    recovery_latency: Duration,
+    max_queue_depth: Option<usize>,
+    min_degraded_epochs: usize,
+}
+
+impl LatencyBudget {
+    pub fn new(max_latency: Duration, recovery_latency: Duration) -> Self {
+        assert!(
+            recovery_latency <= max_latency,
+            "The recovery latency must not exceed the maximal latency"
+        );
+        Self {
+            max_latency,
+            recovery_latency,
+            max_queue_depth: None,
+            min_degraded_epochs: 1,
+        }
+    }
+
+    /// Degrades the scene when more than `depth` predictions are pending, only the batch trackers
+    /// have the queue
+    ///
+    pub fn with_max_queue_depth(mut self, depth: usize) -> Self {
+        self.max_queue_depth = Some(depth);
+        self
+    }
+
+    /// Keeps the scene degraded for at least `n` predictions, so it doesn't flap when the
+    /// predictions without the visual distances are fast
+    ///
+    pub fn with_min_degraded_epochs(mut self, n: usize) -> Self {
+        assert!(n > 0, "The number of epochs must be a positive number");
+        self.min_degraded_epochs = n;
+        self
+    }
+
+    pub fn max_latency(&self) -> Duration {
+        self.max_latency
+    }
+
+    pub fn recovery_latency(&self) -> Duration {
+        self.recovery_latency
+    }
+
+    pub fn max_queue_depth(&self) -> Option<usize> {
+        self.max_queue_depth
+    }
+
+    pub fn min_degraded_epochs(&self) -> usize {
+        self.min_degraded_epochs
+    }
+
+    fn queue_is_ok(&self, queue_depth: usize) -> bool {
+        self.max_queue_depth.map_or(true, |max| queue_depth <= max)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct SceneLoad {
+    /// The number of the predictions since the scene was degraded
+    degraded: Option<usize>,
+    switches: usize,
+}
+
+/// Tracks the load of the scenes against the [LatencyBudget]
+///
+#[derive(Debug)]
+pub struct LatencyBudgetController {
+    budget: LatencyBudget,
+    scenes: Mutex<HashMap<u64, SceneLoad>>,
+}
+
+impl LatencyBudgetController {
+    pub fn new(budget: LatencyBudget) -> Self {
+        Self {
+            budget,
+            scenes: Mutex::default(),
+        }
+    }
+
+    pub fn budget(&self) -> LatencyBudget {
+        self.budget
+    }
+
+    /// Accounts the prediction of the scene
+    ///
+    /// # Parameters
+    /// * `scene_id` - the scene of the prediction
+    /// * `latency` - the time the prediction took
+    /// * `queue_depth` - the number of the predictions pending when the prediction finished
+    ///
+    pub fn record(&self, scene_id: u64, latency: Duration, queue_depth: usize) {
+        let budget = &self.budget;
+        let mut scenes = self.scenes.lock().unwrap();
+        let load = scenes.entry(scene_id).or_default();
+        load.degraded = match load.degraded {
+            None if latency > budget.max_latency || !budget.queue_is_ok(queue_depth) => {
+                load.switches += 1;
+                Some(0)
+            }
+            Some(epochs)
+                if epochs + 1 >= budget.min_degraded_epochs
+                    && latency <= budget.recovery_latency
+                    && budget.queue_is_ok(queue_depth) =>
+            {
+                load.switches += 1;
+                None
+            }
+            Some(epochs) => Some(epochs + 1),
+            None => None,
+        };
+    }
+
+    /// Checks if the visual distances are skipped for the scene
+    ///
+    pub fn degraded(&self, scene_id: u64) -> bool {
+        self.scenes
+            .lock()
+            .unwrap()
+            .get(&scene_id)
+            .map_or(false, |l| l.degraded.is_some())
+    }
+
+    /// The number of the times the scene was degraded or restored
+    ///
+    pub fn switches(&self, scene_id: u64) -> usize {
+        self.scenes
+            .lock()
+            .unwrap()
+            .get(&scene_id)
+            .map_or(0, |l| l.switches)
+    }
+
+    /// Forgets the load of the scene
+    ///
+    pub fn reset(&self, scene_id: u64) {
+        self.scenes.lock().unwrap().remove(&scene_id);
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::LatencyBudget;
+    use pyo3::prelude::*;
+    use std::time::Duration;
+
+    #[pyclass]
+    #[pyo3(name = "LatencyBudget")]
+    #[derive(Debug, Clone, Copy)]
+    pub struct PyLatencyBudget(pub(crate) LatencyBudget);
+
+    #[pymethods]
+    impl PyLatencyBudget {
+        /// The latencies are in seconds
+        ///
+        #[new]
+        #[pyo3(signature = (max_latency, recovery_latency, max_queue_depth = None, min_degraded_epochs = 1))]
+        pub(crate) fn new(
+            max_latency: f64,
+            recovery_latency: f64,
+            max_queue_depth: Option<usize>,
+            min_degraded_epochs: usize,
+        ) -> Self {
+            let mut budget = LatencyBudget::new(
+                Duration::from_secs_f64(max_latency),
+                Duration::from_secs_f64(recovery_latency),
+            )
+            .with_min_degraded_epochs(min_degraded_epochs);
+            if let Some(depth) = max_queue_depth {
+                budget = budget.with_max_queue_depth(depth);
+            }
+            Self(budget)
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::latency_budget::{LatencyBudget, LatencyBudgetController};
+    use std::time::Duration;
+
+    #[test]
+    fn degradation() {
+        let ms = Duration::from_millis;
+        let controller = LatencyBudgetController::new(
+            LatencyBudget::new(ms(20), ms(10))
+                .with_max_queue_depth(2)
+                .with_min_degraded_epochs(2),
+        );
+        controller.record(1, ms(15), 0);
+        assert!(!controller.degraded(1));
+        controller.record(1, ms(25), 0);
+        assert!(controller.degraded(1));
+        assert!(!controller.degraded(2));
+
+        // kept for the minimal number of the epochs
+        controller.record(1, ms(5), 0);
+        assert!(controller.degraded(1));
+        // the latency is above the recovery one
+        controller.record(1, ms(15), 0);
+        assert!(controller.degraded(1));
+        controller.record(1, ms(5), 0);
+        assert!(!controller.degraded(1));
+        assert_eq!(controller.switches(1), 2);
+
+        controller.record(2, ms(1), 3);
+        assert!(controller.degraded(2));
+        controller.reset(2);
+        assert!(!controller.degraded(2));
+    }
+}
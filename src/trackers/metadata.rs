@@ -0,0 +1,65 @@
+use serde_json::Value;
+
+/// Accounts the metadata of the observation merged into the track.
+///
+/// When both the track and the observation metadata are JSON objects, the keys of the observation
+/// replace the keys of the track, so the context attached once stays with the track until it is
+/// overwritten. Otherwise the metadata of the observation replaces the metadata of the track; the
+/// observations without metadata don't change it.
+///
+pub fn merge_metadata(dest: &mut Option<Value>, src: &Option<Value>) {
+    match (dest.as_mut(), src) {
+        (_, None) => {}
+        (Some(Value::Object(dest)), Some(Value::Object(src))) => {
+            for (k, v) in src {
+                dest.insert(k.clone(), v.clone());
+            }
+        }
+        (_, Some(src)) => *dest = Some(src.clone()),
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use pyo3::exceptions::PyValueError;
+    use pyo3::prelude::*;
+    use serde_json::Value;
+
+    /// Converts the JSON-serializable Python object to the metadata value
+    ///
+    pub fn from_py(py: Python, object: &PyAny) -> PyResult<Value> {
+        let json = py.import("json")?.call_method1("dumps", (object,))?;
+        serde_json::from_str(json.extract::<&str>()?)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Converts the metadata value to the Python object, `None` is returned for the missing metadata
+    ///
+    pub fn to_py(py: Python, value: &Option<Value>) -> PyResult<PyObject> {
+        match value {
+            None => Ok(py.None()),
+            Some(value) => Ok(py
+                .import("json")?
+                .call_method1("loads", (value.to_string(),))?
+                .into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::metadata::merge_metadata;
+    use serde_json::json;
+
+    #[test]
+    fn merge() {
+        let mut m = None;
+        merge_metadata(&mut m, &Some(json!({"camera": "a", "zone": 1})));
+        merge_metadata(&mut m, &None);
+        merge_metadata(&mut m, &Some(json!({"zone": 2})));
+        assert_eq!(m, Some(json!({"camera": "a", "zone": 2})));
+
+        merge_metadata(&mut m, &Some(json!([1, 2])));
+        assert_eq!(m, Some(json!([1, 2])));
+    }
+}
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// The motion model used to predict the boxes of the tracks.
+///
+/// Without the motion model the predicted box is the observed one, so the association is done
+/// on the raw observed boxes, the Kalman state is never allocated and the prediction cost is
+/// saved. The Mahalanobis metric is unavailable without the Kalman state, the tracks are never
+/// associated with it.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MotionModel {
+    /// The boxes are predicted and smoothed with the Kalman filter
+    #[default]
+    Kalman,
+    /// The observed boxes are used as is
+    None,
+}
+
+impl MotionModel {
+    /// Whether the Kalman state of the tracks is maintained
+    ///
+    pub fn is_kalman(&self) -> bool {
+        matches!(self, MotionModel::Kalman)
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::MotionModel;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "MotionModel")]
+    #[derive(Clone, Copy, Debug)]
+    pub struct PyMotionModel(pub MotionModel);
+
+    #[pymethods]
+    impl PyMotionModel {
+        #[staticmethod]
+        pub fn kalman() -> Self {
+            PyMotionModel(MotionModel::Kalman)
+        }
+
+        #[staticmethod]
+        pub fn none() -> Self {
+            PyMotionModel(MotionModel::None)
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{self:?}")
+        }
+
+        fn __str__(&self) -> String {
+            format!("{self:#?}")
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use crate::utils::bbox::Universal2DBox;
+use crate::utils::kalman::kalman_2d_box::DIM_2D_BOX_X2;
+use crate::utils::kalman::KalmanState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Configuration of the detection noise model estimation
+///
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct NoiseModelOptions {
+    /// Use the estimated Kalman weights for the scene instead of the configured ones
+    pub adapt: bool,
+    /// The number of residuals collected for the scene before the estimate is reported
+    pub min_samples: usize,
+    /// The number of the last residuals the estimate is mostly based on
+    pub window: usize,
+}
+
+impl Default for NoiseModelOptions {
+    fn default() -> Self {
+        Self {
+            adapt: false,
+            min_samples: 100,
+            window: 1000,
+        }
+    }
+}
+
+/// Kalman weights recommended for the scene
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseEstimate {
+    /// The number of residuals the estimate is based on
+    pub samples: usize,
+    pub position_weight: f32,
+    pub velocity_weight: f32,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct ResidualStats {
+    samples: usize,
+    position: f64,
+    velocity: f64,
+}
+
+/// Online estimator of the detection noise.
+///
+/// The Kalman filter innovations (the differences between the observed and the predicted boxes) and
+/// the corrections of the velocities are collected for all the tracks of the scene. Both are
+/// normalized by the box height as the Kalman weights are, so the boxes of different sizes
+/// contribute equally. The mean squares are kept as the moving averages over the `window` of the
+/// last residuals.
+///
+/// The position weight is estimated under the assumption that the prediction and the measurement
+/// uncertainties contribute to the innovation equally, the velocity weight is the RMS of the
+/// velocity corrections.
+///
+#[derive(Debug, Default)]
+pub struct NoiseModelEstimator {
+    opts: NoiseModelOptions,
+    scenes: Mutex<HashMap<u64, ResidualStats>>,
+}
+
+impl NoiseModelEstimator {
+    pub fn new(opts: NoiseModelOptions) -> Self {
+        Self {
+            opts,
+            scenes: Mutex::default(),
+        }
+    }
+
+    pub fn options(&self) -> NoiseModelOptions {
+        self.opts
+    }
+
+    /// Accounts the Kalman filter update of the track
+    ///
+    /// # Parameters
+    /// * `scene_id` - the scene of the track
+    /// * `prediction` - the state predicted before the observation is applied
+    /// * `update` - the state after the observation is applied
+    /// * `observation` - the observed box
+    ///
+    pub fn record(
+        &self,
+        scene_id: u64,
+        prediction: &KalmanState<DIM_2D_BOX_X2>,
+        update: &KalmanState<DIM_2D_BOX_X2>,
+        observation: &Universal2DBox,
+    ) {
+        let (pm, um) = (prediction.mean(), update.mean());
+        let height = pm[4] as f64;
+        if height <= 0.0 {
+            return;
+        }
+        let sq = |v: f32| (v as f64 / height).powi(2);
+        let position = (sq(observation.xc - pm[0])
+            + sq(observation.yc - pm[1])
+            + sq(observation.height - pm[4]))
+            / 3.0;
+        let velocity = (sq(um[5] - pm[5]) + sq(um[6] - pm[6]) + sq(um[9] - pm[9])) / 3.0;
+
+        let mut scenes = self.scenes.lock().unwrap();
+        let stats = scenes.entry(scene_id).or_default();
+        stats.samples += 1;
+        let alpha = 1.0 / stats.samples.min(self.opts.window.max(1)) as f64;
+        stats.position += alpha * (position - stats.position);
+        stats.velocity += alpha * (velocity - stats.velocity);
+    }
+
+    /// The recommended Kalman weights for the scene, `None` until `min_samples` residuals are collected
+    ///
+    pub fn estimate(&self, scene_id: u64) -> Option<NoiseEstimate> {
+        let scenes = self.scenes.lock().unwrap();
+        scenes
+            .get(&scene_id)
+            .filter(|s| s.samples >= self.opts.min_samples && s.position > 0.0)
+            .map(|s| NoiseEstimate {
+                samples: s.samples,
+                position_weight: (s.position / 2.0).sqrt() as f32,
+                velocity_weight: s.velocity.sqrt() as f32,
+            })
+    }
+
+    /// The Kalman weights to use for the scene
+    ///
+    /// # Parameters
+    /// * `scene_id` - the scene
+    /// * `configured` - the position and the velocity weights used when the adaptation is disabled
+    ///   or the estimate is not ready
+    ///
+    pub fn weights(&self, scene_id: u64, configured: (f32, f32)) -> (f32, f32) {
+        if !self.opts.adapt {
+            return configured;
+        }
+        match self.estimate(scene_id) {
+            Some(e) if e.velocity_weight > 0.0 => (e.position_weight, e.velocity_weight),
+            _ => configured,
+        }
+    }
+
+    /// Forgets the residuals collected for the scene
+    ///
+    pub fn reset(&self, scene_id: u64) {
+        self.scenes.lock().unwrap().remove(&scene_id);
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::NoiseModelOptions;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "NoiseModelOptions")]
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct PyNoiseModelOptions(pub(crate) NoiseModelOptions);
+
+    #[pymethods]
+    impl PyNoiseModelOptions {
+        #[new]
+        #[pyo3(signature = (adapt = false, min_samples = 100, window = 1000))]
+        pub(crate) fn new(adapt: bool, min_samples: usize, window: usize) -> Self {
+            Self(NoiseModelOptions {
+                adapt,
+                min_samples,
+                window,
+            })
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::noise_model::{NoiseModelEstimator, NoiseModelOptions};
+    use crate::utils::bbox::BoundingBox;
+    use crate::utils::kalman::kalman_2d_box::Universal2DBoxKalmanFilter;
+
+    #[test]
+    fn estimate() {
+        let estimator = NoiseModelEstimator::new(NoiseModelOptions {
+            adapt: true,
+            min_samples: 5,
+            window: 100,
+        });
+        let f = Universal2DBoxKalmanFilter::default();
+        let mut state = f.initiate(&BoundingBox::new(0.0, 0.0, 10.0, 20.0).into());
+        for i in 1..10 {
+            let jitter = if i % 2 == 0 { 1.0 } else { -1.0 };
+            let bbox = BoundingBox::new(i as f32 + jitter, 0.0, 10.0, 20.0).into();
+            let prediction = f.predict(&state);
+            state = f.update(&prediction, &bbox);
+            estimator.record(1, &prediction, &state, &bbox);
+            if i < 5 {
+                assert!(estimator.estimate(1).is_none());
+            }
+        }
+
+        let estimate = estimator.estimate(1).unwrap();
+        assert_eq!(estimate.samples, 9);
+        assert!(estimate.position_weight > 0.0);
+        assert!(estimate.velocity_weight > 0.0);
+        assert!(estimator.estimate(2).is_none());
+
+        assert_eq!(
+            estimator.weights(1, (0.1, 0.2)),
+            (estimate.position_weight, estimate.velocity_weight)
+        );
+        assert_eq!(estimator.weights(2, (0.1, 0.2)), (0.1, 0.2));
+
+        estimator.reset(1);
+        assert!(estimator.estimate(1).is_none());
+    }
+}
@@ -0,0 +1,115 @@
+use crate::trackers::candidate_clustering::CandidateClustering;
+use crate::trackers::duplicate_ids::DuplicateObjectIdPolicy;
+use crate::trackers::observation_pipeline::ObservationPipeline;
+use crate::utils::bbox::Universal2DBox;
+
+/// Preparation of the observations passed to the tracker before they are associated with the
+/// tracks: the transformers, the duplicate custom_object_id policy and the clustering of the
+/// near-duplicates.
+///
+/// The intake is owned by the tracker and runs on the thread calling `predict`, the tracks never
+/// access it.
+///
+#[derive(Debug, Clone, Default)]
+pub struct ObservationIntake {
+    pipeline: ObservationPipeline,
+    duplicate_object_id_policy: DuplicateObjectIdPolicy,
+    candidate_clustering: Option<CandidateClustering>,
+    duplicate_object_ids: usize,
+}
+
+impl ObservationIntake {
+    pub fn new(
+        pipeline: ObservationPipeline,
+        duplicate_object_id_policy: DuplicateObjectIdPolicy,
+        candidate_clustering: Option<CandidateClustering>,
+    ) -> Self {
+        Self {
+            pipeline,
+            duplicate_object_id_policy,
+            candidate_clustering,
+            duplicate_object_ids: 0,
+        }
+    }
+
+    /// Replaces the transformers applied to the incoming observations
+    ///
+    pub fn set_observation_pipeline(&mut self, pipeline: ObservationPipeline) {
+        self.pipeline = pipeline;
+    }
+
+    /// Returns the transformers applied to the incoming observations
+    ///
+    pub fn observation_pipeline(&self) -> &ObservationPipeline {
+        &self.pipeline
+    }
+
+    /// Replaces the handling of the observations with the same custom_object_id within the epoch
+    ///
+    pub fn set_duplicate_object_id_policy(&mut self, policy: DuplicateObjectIdPolicy) {
+        self.duplicate_object_id_policy = policy;
+    }
+
+    /// Returns the handling of the observations with the same custom_object_id within the epoch
+    ///
+    pub fn duplicate_object_id_policy(&self) -> DuplicateObjectIdPolicy {
+        self.duplicate_object_id_policy
+    }
+
+    /// The number of duplicate custom_object_id found since the tracker creation
+    ///
+    pub fn duplicate_object_ids(&self) -> usize {
+        self.duplicate_object_ids
+    }
+
+    /// Enables or disables the clustering of the near-duplicate observations within the epoch
+    ///
+    pub fn set_candidate_clustering(&mut self, clustering: Option<CandidateClustering>) {
+        self.candidate_clustering = clustering;
+    }
+
+    /// Returns the clustering of the near-duplicate observations within the epoch
+    ///
+    pub fn candidate_clustering(&self) -> Option<CandidateClustering> {
+        self.candidate_clustering
+    }
+
+    /// Prepares the observations of the scene for the association: runs them through the
+    /// transformers, applies the duplicate custom_object_id policy and clusters the
+    /// near-duplicates
+    ///
+    /// # Returns
+    /// the prepared observations with the indices of the source observations, the dropped and
+    /// the fused away observations are absent
+    ///
+    pub(crate) fn prepare<T: Clone>(
+        &mut self,
+        scene_id: u64,
+        epoch: usize,
+        observations: &[T],
+        bbox: impl Fn(&T) -> &Universal2DBox,
+        with_bbox: impl Fn(&T, Universal2DBox) -> T,
+        key: impl Fn(&T) -> (Option<i64>, f32),
+    ) -> Vec<(usize, T)> {
+        let observations = observations.iter().cloned().enumerate().collect::<Vec<_>>();
+        let observations = self.pipeline.apply(
+            &observations,
+            |(_, o)| bbox(o),
+            |(i, o), bb| (*i, with_bbox(o, bb)),
+        );
+        let (observations, duplicates) =
+            self.duplicate_object_id_policy
+                .apply(scene_id, epoch, &observations, |(_, o)| key(o));
+        self.duplicate_object_ids += duplicates;
+        match &self.candidate_clustering {
+            Some(c) => c
+                .apply(
+                    &observations,
+                    |(_, o)| bbox(o),
+                    |(i, o), bb| (*i, with_bbox(o, bb)),
+                )
+                .into_owned(),
+            None => observations.into_owned(),
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use crate::utils::bbox::coordinates::CoordinateSystem;
+use crate::utils::bbox::Universal2DBox;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// The transformation of the observation box applied before the candidate track is created
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ObservationTransformer {
+    /// Clips the box to the frame `[0, width] x [0, height]`. The center of the rotated box is
+    /// clamped only. The boxes that lay outside of the frame are dropped.
+    Clamp { width: f32, height: f32 },
+    /// Multiplies the horizontal and the vertical coordinates and sizes of the box, e.g. to bring
+    /// the boxes detected on the resized frame to the coordinates of the original frame
+    Scale { x: f32, y: f32 },
+    /// Calibrates the confidence of the detector with `scale * confidence + shift` limited by
+    /// `[0.0, 1.0]`
+    CalibrateConfidence { scale: f32, shift: f32 },
+    /// Drops the boxes which centers lay outside of the region `[left, right] x [top, bottom]`
+    Roi {
+        left: f32,
+        top: f32,
+        right: f32,
+        bottom: f32,
+    },
+    /// Validates the box defined in the coordinate system and converts it to the pixel coordinates
+    /// with the vertical axis pointing down. The boxes which are not valid in the system, e.g. the
+    /// pixel boxes passed as normalized, are dropped.
+    Coordinates(CoordinateSystem),
+}
+
+impl ObservationTransformer {
+    /// Transforms the box
+    ///
+    /// # Returns
+    /// the transformed box, `None` if the observation must be dropped
+    ///
+    pub fn transform(&self, bbox: Universal2DBox) -> Option<Universal2DBox> {
+        match *self {
+            ObservationTransformer::Clamp { width, height } => {
+                if bbox.angle.is_some() {
+                    return Some(Universal2DBox::new_with_confidence(
+                        bbox.xc.clamp(0.0, width),
+                        bbox.yc.clamp(0.0, height),
+                        bbox.angle,
+                        bbox.aspect,
+                        bbox.height,
+                        bbox.confidence,
+                    ));
+                }
+                let half_width = bbox.aspect * bbox.height / 2.0;
+                let half_height = bbox.height / 2.0;
+                let left = (bbox.xc - half_width).max(0.0);
+                let right = (bbox.xc + half_width).min(width);
+                let top = (bbox.yc - half_height).max(0.0);
+                let bottom = (bbox.yc + half_height).min(height);
+                if right <= left || bottom <= top {
+                    return None;
+                }
+                Some(Universal2DBox::ltwh_with_confidence(
+                    left,
+                    top,
+                    right - left,
+                    bottom - top,
+                    bbox.confidence,
+                ))
+            }
+            ObservationTransformer::Scale { x, y } => Some(Universal2DBox::new_with_confidence(
+                bbox.xc * x,
+                bbox.yc * y,
+                bbox.angle,
+                bbox.aspect * x / y,
+                bbox.height * y,
+                bbox.confidence,
+            )),
+            ObservationTransformer::CalibrateConfidence { scale, shift } => {
+                let mut bbox = bbox;
+                bbox.set_confidence((scale * bbox.confidence + shift).clamp(0.0, 1.0));
+                Some(bbox)
+            }
+            ObservationTransformer::Roi {
+                left,
+                top,
+                right,
+                bottom,
+            } => {
+                if (left..=right).contains(&bbox.xc) && (top..=bottom).contains(&bbox.yc) {
+                    Some(bbox)
+                } else {
+                    None
+                }
+            }
+            ObservationTransformer::Coordinates(system) => {
+                if system.is_valid(&bbox) {
+                    Some(system.box_to_pixels(&bbox))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// The list of the transformers applied in order to every incoming observation of the tracker.
+///
+/// The pipeline runs before the duplicate handling and the candidate clustering, so the rest of
+/// the tracker sees the transformed boxes only. The observation dropped by any transformer is not
+/// tracked and is not reported.
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ObservationPipeline {
+    transformers: Vec<ObservationTransformer>,
+}
+
+impl ObservationPipeline {
+    pub fn new(transformers: Vec<ObservationTransformer>) -> Self {
+        for t in &transformers {
+            match t {
+                ObservationTransformer::Clamp { width, height } => assert!(
+                    *width > 0.0 && *height > 0.0,
+                    "The frame sizes must be positive numbers"
+                ),
+                ObservationTransformer::Scale { x, y } => assert!(
+                    *x > 0.0 && *y > 0.0,
+                    "The scale factors must be positive numbers"
+                ),
+                ObservationTransformer::CalibrateConfidence { .. } => {}
+                ObservationTransformer::Roi {
+                    left,
+                    top,
+                    right,
+                    bottom,
+                } => assert!(
+                    left <= right && top <= bottom,
+                    "The region must satisfy left <= right and top <= bottom"
+                ),
+                ObservationTransformer::Coordinates(_) => {}
+            }
+        }
+        Self { transformers }
+    }
+
+    /// Appends the transformer to the pipeline
+    ///
+    pub fn with(self, transformer: ObservationTransformer) -> Self {
+        let mut transformers = self.transformers;
+        transformers.push(transformer);
+        Self::new(transformers)
+    }
+
+    pub fn transformers(&self) -> &[ObservationTransformer] {
+        &self.transformers
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transformers.is_empty()
+    }
+
+    /// Runs the box through the transformers
+    ///
+    /// # Returns
+    /// the transformed box, `None` if the observation is dropped
+    ///
+    pub fn transform(&self, bbox: &Universal2DBox) -> Option<Universal2DBox> {
+        self.transformers
+            .iter()
+            .try_fold(bbox.clone(), |bbox, t| t.transform(bbox))
+    }
+
+    /// Applies the pipeline to the observations
+    ///
+    /// # Parameters
+    /// * `observations` - the observations of one scene and epoch
+    /// * `bbox` - extracts the box from the observation
+    /// * `with_bbox` - makes the copy of the observation with the box replaced
+    ///
+    /// # Returns
+    /// the transformed observations of the original order without the dropped ones
+    ///
+    pub fn apply<'a, T: Clone>(
+        &self,
+        observations: &'a [T],
+        bbox: impl Fn(&T) -> &Universal2DBox,
+        with_bbox: impl Fn(&T, Universal2DBox) -> T,
+    ) -> Cow<'a, [T]> {
+        if self.is_empty() {
+            return Cow::Borrowed(observations);
+        }
+        Cow::Owned(
+            observations
+                .iter()
+                .filter_map(|o| self.transform(bbox(o)).map(|bb| with_bbox(o, bb)))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::{ObservationPipeline, ObservationTransformer};
+    use crate::utils::bbox::coordinates::python::PyCoordinateSystem;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "ObservationTransformer")]
+    #[derive(Debug, Clone, Copy)]
+    pub struct PyObservationTransformer(pub(crate) ObservationTransformer);
+
+    #[pymethods]
+    impl PyObservationTransformer {
+        #[staticmethod]
+        pub fn clamp(width: f32, height: f32) -> Self {
+            Self(ObservationTransformer::Clamp { width, height })
+        }
+
+        #[staticmethod]
+        pub fn scale(x: f32, y: f32) -> Self {
+            Self(ObservationTransformer::Scale { x, y })
+        }
+
+        #[staticmethod]
+        #[pyo3(signature = (scale = 1.0, shift = 0.0))]
+        pub fn calibrate_confidence(scale: f32, shift: f32) -> Self {
+            Self(ObservationTransformer::CalibrateConfidence { scale, shift })
+        }
+
+        #[staticmethod]
+        pub fn roi(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+            Self(ObservationTransformer::Roi {
+                left,
+                top,
+                right,
+                bottom,
+            })
+        }
+
+        #[staticmethod]
+        pub fn coordinates(system: PyCoordinateSystem) -> Self {
+            Self(ObservationTransformer::Coordinates(system.0))
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+
+    #[pyclass]
+    #[pyo3(name = "ObservationPipeline")]
+    #[derive(Debug, Clone, Default)]
+    pub struct PyObservationPipeline(pub(crate) ObservationPipeline);
+
+    #[pymethods]
+    impl PyObservationPipeline {
+        #[new]
+        #[pyo3(signature = (transformers = vec![]))]
+        pub(crate) fn new(transformers: Vec<PyObservationTransformer>) -> Self {
+            Self(ObservationPipeline::new(
+                transformers.into_iter().map(|t| t.0).collect(),
+            ))
+        }
+
+        #[getter]
+        fn transformers(&self) -> Vec<PyObservationTransformer> {
+            self.0
+                .transformers()
+                .iter()
+                .map(|t| PyObservationTransformer(*t))
+                .collect()
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::observation_pipeline::{ObservationPipeline, ObservationTransformer};
+    use crate::utils::bbox::coordinates::CoordinateSystem;
+    use crate::utils::bbox::{BoundingBox, Universal2DBox};
+    use crate::EPS;
+
+    #[test]
+    fn transform() {
+        let bbox: Universal2DBox =
+            BoundingBox::new_with_confidence(-10.0, 10.0, 30.0, 20.0, 0.5).into();
+
+        let clamped = ObservationTransformer::Clamp {
+            width: 100.0,
+            height: 100.0,
+        }
+        .transform(bbox.clone())
+        .unwrap();
+        let clamped = BoundingBox::try_from(clamped).unwrap();
+        assert!((clamped.left - 0.0).abs() < EPS);
+        assert!((clamped.width - 20.0).abs() < EPS);
+        assert!((clamped.height - 20.0).abs() < EPS);
+
+        assert!(ObservationTransformer::Clamp {
+            width: 100.0,
+            height: 100.0,
+        }
+        .transform(BoundingBox::new(200.0, 10.0, 30.0, 20.0).into())
+        .is_none());
+
+        let scaled = ObservationTransformer::Scale { x: 2.0, y: 0.5 }
+            .transform(bbox.clone())
+            .unwrap();
+        let scaled = BoundingBox::try_from(scaled).unwrap();
+        assert!((scaled.left + 20.0).abs() < EPS);
+        assert!((scaled.top - 5.0).abs() < EPS);
+        assert!((scaled.width - 60.0).abs() < EPS);
+        assert!((scaled.height - 10.0).abs() < EPS);
+
+        let calibrated = ObservationTransformer::CalibrateConfidence {
+            scale: 2.0,
+            shift: 0.2,
+        }
+        .transform(bbox.clone())
+        .unwrap();
+        assert_eq!(calibrated.confidence, 1.0);
+
+        let roi = ObservationTransformer::Roi {
+            left: 0.0,
+            top: 0.0,
+            right: 50.0,
+            bottom: 50.0,
+        };
+        assert!(roi.transform(bbox.clone()).is_some());
+        assert!(roi
+            .transform(BoundingBox::new(60.0, 10.0, 30.0, 20.0).into())
+            .is_none());
+
+        let coordinates =
+            ObservationTransformer::Coordinates(CoordinateSystem::normalized(200.0, 100.0));
+        let converted = coordinates
+            .transform(BoundingBox::new(0.1, 0.2, 0.5, 0.4).into())
+            .unwrap();
+        let converted = BoundingBox::try_from(converted).unwrap();
+        assert!((converted.left - 20.0).abs() < EPS);
+        assert!((converted.width - 100.0).abs() < EPS);
+        assert!(coordinates.transform(bbox.clone()).is_none());
+    }
+
+    #[test]
+    fn pipeline() {
+        let observations: Vec<(Universal2DBox, Option<i64>)> = vec![
+            (BoundingBox::new(10.0, 10.0, 10.0, 10.0).into(), Some(1)),
+            (BoundingBox::new(90.0, 10.0, 10.0, 10.0).into(), Some(2)),
+        ];
+        let empty = ObservationPipeline::default();
+        assert_eq!(
+            empty
+                .apply(&observations, |(bb, _)| bb, |(_, id), bb| (bb, *id))
+                .len(),
+            2
+        );
+
+        let pipeline = ObservationPipeline::default()
+            .with(ObservationTransformer::Scale { x: 0.5, y: 0.5 })
+            .with(ObservationTransformer::Roi {
+                left: 0.0,
+                top: 0.0,
+                right: 40.0,
+                bottom: 40.0,
+            });
+        let res = pipeline.apply(&observations, |(bb, _)| bb, |(_, id), bb| (bb, *id));
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].1, Some(1));
+        assert!((res[0].0.xc - 7.5).abs() < EPS);
+    }
+}
@@ -0,0 +1,211 @@
+use crate::trackers::sort::SortTrack;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Filter that is applied to the tracks before they are returned to the caller.
+///
+/// The tracks that don't pass the filter are still kept and updated inside the tracker,
+/// the filter only decides which of them are reported.
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrackOutputFilter {
+    min_track_length: usize,
+    max_coasted_epochs: Option<usize>,
+    excluded_scenes: HashSet<u64>,
+    #[serde(default)]
+    excluded_classes: HashSet<i64>,
+}
+
+impl TrackOutputFilter {
+    /// The minimal length of the track to be reported
+    ///
+    pub fn min_track_length(mut self, length: usize) -> Self {
+        self.min_track_length = length;
+        self
+    }
+
+    /// The maximal number of epochs passed since the last track update while it is still reported
+    ///
+    pub fn max_coasted_epochs(mut self, epochs: usize) -> Self {
+        self.max_coasted_epochs = Some(epochs);
+        self
+    }
+
+    /// Tracks of the scene `scene_id` are not reported
+    ///
+    pub fn exclude_scene(mut self, scene_id: u64) -> Self {
+        self.excluded_scenes.insert(scene_id);
+        self
+    }
+
+    /// Tracks of the scene `scene_id` are reported again
+    ///
+    pub fn include_scene(mut self, scene_id: u64) -> Self {
+        self.excluded_scenes.remove(&scene_id);
+        self
+    }
+
+    /// Tracks which majority [class](SortTrack::class) is `class_id` are not reported, the tracks
+    /// without the labeled observations are always reported
+    ///
+    pub fn exclude_class(mut self, class_id: i64) -> Self {
+        self.excluded_classes.insert(class_id);
+        self
+    }
+
+    /// Tracks which majority class is `class_id` are reported again
+    ///
+    pub fn include_class(mut self, class_id: i64) -> Self {
+        self.excluded_classes.remove(&class_id);
+        self
+    }
+
+    /// Checks if the track must be reported
+    ///
+    /// # Parameters
+    /// * `track` - the track to check
+    /// * `current_epoch` - the current epoch of the track's scene
+    ///
+    pub fn allows(&self, track: &SortTrack, current_epoch: usize) -> bool {
+        track.length >= self.min_track_length
+            && self
+                .max_coasted_epochs
+                .map(|max| current_epoch.saturating_sub(track.epoch) <= max)
+                .unwrap_or(true)
+            && !self.excluded_scenes.contains(&track.scene_id)
+            && !track
+                .class
+                .as_ref()
+                .map(|c| self.excluded_classes.contains(&c.class_id))
+                .unwrap_or(false)
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::TrackOutputFilter;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "TrackOutputFilter")]
+    #[derive(Debug, Clone, Default)]
+    pub struct PyTrackOutputFilter(pub(crate) TrackOutputFilter);
+
+    #[pymethods]
+    impl PyTrackOutputFilter {
+        #[new]
+        pub(crate) fn new() -> Self {
+            Self::default()
+        }
+
+        #[pyo3(text_signature = "($self, length)")]
+        pub(crate) fn min_track_length(&mut self, length: i64) {
+            self.0 = self.0.clone().min_track_length(
+                length
+                    .try_into()
+                    .expect("Parameter must be a positive number"),
+            );
+        }
+
+        #[pyo3(text_signature = "($self, epochs)")]
+        pub(crate) fn max_coasted_epochs(&mut self, epochs: i64) {
+            self.0 = self.0.clone().max_coasted_epochs(
+                epochs
+                    .try_into()
+                    .expect("Parameter must be a positive number"),
+            );
+        }
+
+        #[pyo3(text_signature = "($self, scene_id)")]
+        pub(crate) fn exclude_scene(&mut self, scene_id: u64) {
+            self.0 = self.0.clone().exclude_scene(scene_id);
+        }
+
+        #[pyo3(text_signature = "($self, scene_id)")]
+        pub(crate) fn include_scene(&mut self, scene_id: u64) {
+            self.0 = self.0.clone().include_scene(scene_id);
+        }
+
+        #[pyo3(text_signature = "($self, class_id)")]
+        pub(crate) fn exclude_class(&mut self, class_id: i64) {
+            self.0 = self.0.clone().exclude_class(class_id);
+        }
+
+        #[pyo3(text_signature = "($self, class_id)")]
+        pub(crate) fn include_class(&mut self, class_id: i64) {
+            self.0 = self.0.clone().include_class(class_id);
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::class_voting::TrackClass;
+    use crate::trackers::output_filter::TrackOutputFilter;
+    use crate::trackers::sort::{SortTrack, VotingType};
+    use crate::utils::bbox::Universal2DBox;
+
+    fn track(epoch: usize, scene_id: u64, length: usize) -> SortTrack {
+        SortTrack {
+            id: 1,
+            epoch,
+            predicted_bbox: Universal2DBox::new(0.0, 0.0, None, 1.0, 5.0),
+            observed_bbox: Universal2DBox::new(0.0, 0.0, None, 1.0, 5.0),
+            scene_id,
+            length,
+            voting_type: VotingType::Positional,
+            custom_object_id: None,
+            tentative: false,
+            class: None,
+            metadata: None,
+            created_epoch: epoch,
+            created_timestamp: 0,
+            clipped: false,
+        }
+    }
+
+    #[test]
+    fn filter() {
+        let f = TrackOutputFilter::default();
+        assert!(f.allows(&track(1, 0, 1), 10));
+
+        let f = TrackOutputFilter::default()
+            .min_track_length(3)
+            .max_coasted_epochs(2)
+            .exclude_scene(5);
+        assert!(!f.allows(&track(10, 0, 2), 10));
+        assert!(f.allows(&track(10, 0, 3), 10));
+        assert!(f.allows(&track(8, 0, 3), 10));
+        assert!(!f.allows(&track(7, 0, 3), 10));
+        assert!(!f.allows(&track(10, 5, 3), 10));
+        assert!(f.include_scene(5).allows(&track(10, 5, 3), 10));
+    }
+
+    #[test]
+    fn class_filter() {
+        let labeled = |class_id| SortTrack {
+            class: Some(TrackClass {
+                class_id,
+                stability: 1.0,
+                frozen: false,
+            }),
+            ..track(10, 0, 3)
+        };
+        let f = TrackOutputFilter::default().exclude_class(2);
+        assert!(!f.allows(&labeled(2), 10));
+        assert!(f.allows(&labeled(1), 10));
+        assert!(f.allows(&track(10, 2, 3), 10));
+        assert!(f.include_class(2).allows(&labeled(2), 10));
+    }
+}
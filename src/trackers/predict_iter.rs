@@ -0,0 +1,153 @@
+use crate::store::{FutureMergeResponse, TrackStore};
+use crate::track::notify::ChangeNotifier;
+use crate::track::{ObservationAttributes, ObservationMetric, Track, TrackAttributes};
+use crate::trackers::sort::{SortAttributesOptions, SortTrack};
+use crossbeam::channel::Select;
+use std::collections::VecDeque;
+use std::sync::RwLock;
+use std::time::Instant;
+
+/// Iterator over the tracks updated with the observations of the epoch.
+///
+/// The merges of the observations into the existing tracks are sent to the store shards at once,
+/// the tracks are yielded as soon as their merges complete, so the order of the results differs
+/// from the order of the observations. The tracks created for the new objects are yielded first.
+/// The tracks that must not be reported according to the output filter are skipped.
+///
+/// When the iterator is dropped before it is exhausted, it waits for the sent merges to complete
+/// and accounts the tracks that were not yielded in the tracking quality, as `predict` does. The
+/// prediction latency of the scene is accounted when the iterator is dropped.
+///
+pub struct PredictIter<'a, TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    store: &'a RwLock<TrackStore<TA, M, OA, N>>,
+    opts: &'a SortAttributesOptions,
+    scene_id: u64,
+    started: Instant,
+    convert: fn(&Track<TA, M, OA, N>) -> SortTrack,
+    ready: VecDeque<u64>,
+    pending: Vec<(u64, FutureMergeResponse<OA>)>,
+}
+
+impl<'a, TA, M, OA, N> PredictIter<'a, TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    /// Adds the new tracks to the store and sends the merges into the existing tracks
+    ///
+    /// # Parameters
+    /// * `scene_id` - the scene of the observations
+    /// * `started` - when the prediction started
+    /// * `assignments` - the tracks built for the observations with the destination track ids,
+    ///   the tracks without the destination are added to the store as is
+    ///
+    #[allow(clippy::type_complexity)]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        store: &'a RwLock<TrackStore<TA, M, OA, N>>,
+        opts: &'a SortAttributesOptions,
+        scene_id: u64,
+        started: Instant,
+        convert: fn(&Track<TA, M, OA, N>) -> SortTrack,
+        assignments: Vec<(Track<TA, M, OA, N>, Option<u64>)>,
+    ) -> Self {
+        let mut ready = VecDeque::default();
+        let mut pending = Vec::default();
+        {
+            let mut store = store.write().unwrap();
+            for (track, dest) in assignments {
+                match dest {
+                    Some(dest) => pending.push((
+                        dest,
+                        store
+                            .merge_external_noblock(dest, track, Some(&[0]), false)
+                            .unwrap(),
+                    )),
+                    None => ready.push_back(store.add_track(track).unwrap()),
+                }
+            }
+        }
+        Self {
+            store,
+            opts,
+            scene_id,
+            started,
+            convert,
+            ready,
+            pending,
+        }
+    }
+
+    fn next_track_id(&mut self) -> Option<u64> {
+        if let Some(track_id) = self.ready.pop_front() {
+            return Some(track_id);
+        }
+        if self.pending.is_empty() {
+            return None;
+        }
+        let index = {
+            let mut select = Select::new();
+            for (_, merge) in &self.pending {
+                select.recv(merge.receiver());
+            }
+            select.ready()
+        };
+        let (track_id, merge) = self.pending.swap_remove(index);
+        merge.get().unwrap();
+        Some(track_id)
+    }
+
+    fn track(&self, track_id: u64) -> SortTrack {
+        let store = self.store.read().unwrap();
+        let shard = store.get_store(track_id as usize);
+        (self.convert)(shard.get(&track_id).unwrap())
+    }
+}
+
+impl<'a, TA, M, OA, N> Iterator for PredictIter<'a, TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    type Item = SortTrack;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(track_id) = self.next_track_id() {
+            let track = self.track(track_id);
+            self.opts
+                .record_quality(self.scene_id, std::slice::from_ref(&track));
+            if self.opts.output_allowed(&track) {
+                return Some(track);
+            }
+        }
+        None
+    }
+}
+
+impl<'a, TA, M, OA, N> Drop for PredictIter<'a, TA, M, OA, N>
+where
+    TA: TrackAttributes<TA, OA>,
+    M: ObservationMetric<TA, OA>,
+    OA: ObservationAttributes,
+    N: ChangeNotifier,
+{
+    fn drop(&mut self) {
+        let mut remaining = Vec::with_capacity(self.ready.len() + self.pending.len());
+        while let Some(track_id) = self.next_track_id() {
+            remaining.push(self.track(track_id));
+        }
+        self.opts.record_quality(self.scene_id, &remaining);
+        self.opts
+            .record_predict_latency(self.scene_id, self.started.elapsed(), 0);
+    }
+}
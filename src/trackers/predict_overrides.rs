@@ -0,0 +1,158 @@
+use crate::trackers::sort::{PositionalMetricType, MAHALANOBIS_NEW_TRACK_THRESHOLD};
+use serde::{Deserialize, Serialize};
+
+/// Overrides of the tracker configuration applied to the predictions of the scene.
+///
+/// The overrides are layered over the configured metric and voting options when the distances of
+/// the scene are computed and voted, so the tracker can be made stricter or looser for a few
+/// frames without rebuilding its metric. The fields left to `None` keep the configured values.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PredictOverrides {
+    /// The IoU threshold used instead of the configured one, ignored for the Mahalanobis metric
+    pub iou_threshold: Option<f32>,
+    /// Don't compute the visual distances, the observations are associated by their positions only
+    pub disable_visual: bool,
+    /// The number of the visual votes used instead of the configured `visual_min_votes`
+    pub visual_min_votes: Option<usize>,
+}
+
+impl PredictOverrides {
+    /// The positional metric with the overridden threshold
+    ///
+    pub fn positional_kind(&self, kind: PositionalMetricType) -> PositionalMetricType {
+        match (kind, self.iou_threshold) {
+            (PositionalMetricType::IoU(_), Some(threshold)) => {
+                assert!(
+                    threshold > 0.0 && threshold < 1.0,
+                    "Threshold must lay between (0.0 and 1.0)"
+                );
+                PositionalMetricType::IoU(threshold)
+            }
+            _ => kind,
+        }
+    }
+
+    /// The positional threshold of the voting, the candidates farther than it start new tracks
+    ///
+    pub fn positional_threshold(&self, kind: PositionalMetricType) -> f32 {
+        match self.positional_kind(kind) {
+            PositionalMetricType::Mahalanobis => MAHALANOBIS_NEW_TRACK_THRESHOLD,
+            PositionalMetricType::IoU(t) => t,
+        }
+    }
+
+    /// The minimal number of the visual votes required to associate the observation by its feature
+    ///
+    pub fn visual_min_votes(&self, visual_min_votes: usize) -> usize {
+        self.visual_min_votes.unwrap_or(visual_min_votes)
+    }
+
+    /// The overrides with `top` layered over them: the fields set in `top` take precedence, the
+    /// visual distances are disabled when either of the overrides disables them
+    ///
+    pub fn layered(&self, top: Option<PredictOverrides>) -> PredictOverrides {
+        match top {
+            Some(top) => PredictOverrides {
+                iou_threshold: top.iou_threshold.or(self.iou_threshold),
+                disable_visual: self.disable_visual || top.disable_visual,
+                visual_min_votes: top.visual_min_votes.or(self.visual_min_votes),
+            },
+            None => *self,
+        }
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::PredictOverrides;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "PredictOverrides")]
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct PyPredictOverrides(pub(crate) PredictOverrides);
+
+    #[pymethods]
+    impl PyPredictOverrides {
+        #[new]
+        #[pyo3(signature = (iou_threshold = None, disable_visual = false, visual_min_votes = None))]
+        pub(crate) fn new(
+            iou_threshold: Option<f32>,
+            disable_visual: bool,
+            visual_min_votes: Option<usize>,
+        ) -> Self {
+            Self(PredictOverrides {
+                iou_threshold,
+                disable_visual,
+                visual_min_votes,
+            })
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::predict_overrides::PredictOverrides;
+    use crate::trackers::sort::{PositionalMetricType, MAHALANOBIS_NEW_TRACK_THRESHOLD};
+
+    #[test]
+    fn resolve() {
+        let overrides = PredictOverrides::default();
+        assert_eq!(
+            overrides.positional_threshold(PositionalMetricType::IoU(0.3)),
+            0.3
+        );
+        assert_eq!(overrides.visual_min_votes(2), 2);
+
+        let overrides = PredictOverrides {
+            iou_threshold: Some(0.6),
+            visual_min_votes: Some(4),
+            ..Default::default()
+        };
+        assert!(matches!(
+            overrides.positional_kind(PositionalMetricType::IoU(0.3)),
+            PositionalMetricType::IoU(t) if t == 0.6
+        ));
+        assert_eq!(
+            overrides.positional_threshold(PositionalMetricType::Mahalanobis),
+            MAHALANOBIS_NEW_TRACK_THRESHOLD
+        );
+        assert_eq!(overrides.visual_min_votes(2), 4);
+    }
+
+    #[test]
+    fn layered() {
+        let sticky = PredictOverrides {
+            iou_threshold: Some(0.6),
+            disable_visual: true,
+            visual_min_votes: Some(4),
+        };
+        assert_eq!(sticky.layered(None), sticky);
+
+        let call = PredictOverrides {
+            iou_threshold: Some(0.4),
+            ..Default::default()
+        };
+        assert_eq!(
+            sticky.layered(Some(call)),
+            PredictOverrides {
+                iou_threshold: Some(0.4),
+                disable_visual: true,
+                visual_min_votes: Some(4),
+            }
+        );
+        assert_eq!(PredictOverrides::default().layered(Some(call)), call);
+    }
+}
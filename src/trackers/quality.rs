@@ -0,0 +1,272 @@
+use crate::trackers::sort::SortTrack;
+use crate::utils::bbox::Universal2DBox;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Configuration of the online tracking quality estimation
+///
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct QualityOptions {
+    /// The number of epochs the lost track can be re-created within
+    pub window: usize,
+    /// The largest distance (in the sums of the box radii) between the last box of the lost track
+    /// and the first box of the new track that re-creates it
+    pub max_distance: f32,
+    /// The largest displacement per epoch (in the sums of the box radii) of the track between its
+    /// consecutive observations, the larger jumps are considered the id switches
+    pub jump_distance: f32,
+    /// The weight of the last sample in the smoothed rates
+    pub smoothing: f32,
+}
+
+impl Default for QualityOptions {
+    fn default() -> Self {
+        Self {
+            window: 30,
+            max_distance: 1.0,
+            jump_distance: 1.0,
+            smoothing: 0.01,
+        }
+    }
+}
+
+/// Tracking quality of the scene estimated without the ground truth
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TrackingQuality {
+    /// The number of the tracks started
+    pub new_tracks: usize,
+    /// The number of the track updates
+    pub updates: usize,
+    /// The number of the track updates that jumped farther than the jump distance
+    pub id_switches: usize,
+    /// The number of the new tracks started near the recently lost tracks
+    pub fragmentations: usize,
+    /// The smoothed share of the track updates that are the id switches
+    pub id_switch_rate: f32,
+    /// The smoothed share of the new tracks that re-create the lost tracks
+    pub fragmentation_rate: f32,
+}
+
+#[derive(Debug, Default)]
+struct SceneQuality {
+    quality: TrackingQuality,
+    /// The last epoch and the last observed box of the tracks seen within the window
+    last_seen: HashMap<u64, (usize, Universal2DBox)>,
+}
+
+/// Online estimator of the tracking quality.
+///
+/// The estimator watches the tracks reported by the predictions and detects the typical tracking
+/// failures with the heuristics:
+/// * the id switch is the update that moves the track farther than it can move between the
+///   epochs, i.e. the track is associated with the other object;
+/// * the fragmentation is the new track started close to the last box of the track that was not
+///   seen for at most the window epochs, i.e. the lost object is re-acquired under the new id.
+///
+/// The lost track explains at most one new track. The rates are smoothed with the exponential
+/// moving average, so the drift of the quality is visible on the long-running scenes.
+///
+#[derive(Debug, Default)]
+pub struct QualityEstimator {
+    opts: QualityOptions,
+    scenes: Mutex<HashMap<u64, SceneQuality>>,
+}
+
+impl QualityEstimator {
+    pub fn new(opts: QualityOptions) -> Self {
+        assert!(opts.window > 0, "Window must be a positive number");
+        assert!(
+            opts.max_distance > 0.0 && opts.jump_distance > 0.0,
+            "Distances must be positive numbers"
+        );
+        assert!(
+            opts.smoothing > 0.0 && opts.smoothing <= 1.0,
+            "Smoothing must lay between (0.0 and 1.0]"
+        );
+        Self {
+            opts,
+            scenes: Mutex::default(),
+        }
+    }
+
+    pub fn options(&self) -> QualityOptions {
+        self.opts
+    }
+
+    /// Accounts the tracks reported by the prediction of the scene
+    ///
+    pub fn record(&self, scene_id: u64, tracks: &[SortTrack]) {
+        let alpha = self.opts.smoothing;
+        let mut scenes = self.scenes.lock().unwrap();
+        let scene = scenes.entry(scene_id).or_default();
+        if let Some(epoch) = tracks.iter().map(|t| t.epoch).max() {
+            let window = self.opts.window;
+            scene
+                .last_seen
+                .retain(|_, (seen, _)| seen.saturating_add(window) >= epoch);
+        }
+        for track in tracks {
+            let bbox = &track.observed_bbox;
+            if track.length == 1 {
+                let lost = scene
+                    .last_seen
+                    .iter()
+                    .filter(|(_, (epoch, _))| *epoch < track.epoch)
+                    .map(|(id, (_, last))| (*id, distance(last, bbox)))
+                    .filter(|(_, dist)| *dist <= self.opts.max_distance)
+                    .min_by(|(_, l), (_, r)| l.total_cmp(r))
+                    .map(|(id, _)| id);
+                let q = &mut scene.quality;
+                q.new_tracks += 1;
+                let sample = if let Some(id) = lost {
+                    scene.last_seen.remove(&id);
+                    q.fragmentations += 1;
+                    1.0
+                } else {
+                    0.0
+                };
+                q.fragmentation_rate += alpha * (sample - q.fragmentation_rate);
+            } else if let Some((epoch, last)) = scene.last_seen.get(&track.id) {
+                let epochs = track.epoch.saturating_sub(*epoch).max(1);
+                let jump = distance(last, bbox) / epochs as f32;
+                let q = &mut scene.quality;
+                q.updates += 1;
+                let sample = if jump > self.opts.jump_distance {
+                    q.id_switches += 1;
+                    1.0
+                } else {
+                    0.0
+                };
+                q.id_switch_rate += alpha * (sample - q.id_switch_rate);
+            }
+            scene
+                .last_seen
+                .insert(track.id, (track.epoch, bbox.clone()));
+        }
+    }
+
+    /// The quality of the scene, `None` if no tracks of the scene were accounted
+    ///
+    pub fn estimate(&self, scene_id: u64) -> Option<TrackingQuality> {
+        self.scenes
+            .lock()
+            .unwrap()
+            .get(&scene_id)
+            .map(|s| s.quality)
+    }
+
+    /// Forgets the quality of the scene
+    ///
+    pub fn reset(&self, scene_id: u64) {
+        self.scenes.lock().unwrap().remove(&scene_id);
+    }
+}
+
+fn distance(l: &Universal2DBox, r: &Universal2DBox) -> f32 {
+    if l.aspect > 0.0 && l.height > 0.0 && r.aspect > 0.0 && r.height > 0.0 {
+        Universal2DBox::dist_in_2r(l, r)
+    } else {
+        f32::MAX
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::QualityOptions;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "QualityOptions")]
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct PyQualityOptions(pub(crate) QualityOptions);
+
+    #[pymethods]
+    impl PyQualityOptions {
+        #[new]
+        #[pyo3(signature = (window = 30, max_distance = 1.0, jump_distance = 1.0, smoothing = 0.01))]
+        pub(crate) fn new(
+            window: usize,
+            max_distance: f32,
+            jump_distance: f32,
+            smoothing: f32,
+        ) -> Self {
+            Self(QualityOptions {
+                window,
+                max_distance,
+                jump_distance,
+                smoothing,
+            })
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::quality::{QualityEstimator, QualityOptions};
+    use crate::trackers::sort::{SortTrack, VotingType};
+    use crate::utils::bbox::BoundingBox;
+
+    fn track(id: u64, epoch: usize, length: usize, left: f32) -> SortTrack {
+        let bbox = BoundingBox::new(left, 0.0, 10.0, 20.0).as_xyaah();
+        SortTrack {
+            id,
+            epoch,
+            predicted_bbox: bbox.clone(),
+            observed_bbox: bbox,
+            scene_id: 1,
+            length,
+            voting_type: VotingType::Positional,
+            custom_object_id: None,
+            tentative: false,
+            class: None,
+            metadata: None,
+            created_epoch: epoch + 1 - length,
+            created_timestamp: 0,
+            clipped: false,
+        }
+    }
+
+    #[test]
+    fn estimate() {
+        let estimator = QualityEstimator::new(QualityOptions {
+            window: 5,
+            smoothing: 0.5,
+            ..Default::default()
+        });
+        assert!(estimator.estimate(1).is_none());
+
+        estimator.record(1, &[track(1, 1, 1, 0.0), track(2, 1, 1, 500.0)]);
+        estimator.record(1, &[track(1, 2, 2, 2.0), track(2, 2, 2, 900.0)]);
+        let q = estimator.estimate(1).unwrap();
+        assert_eq!((q.new_tracks, q.updates, q.id_switches), (2, 2, 1));
+        assert!((q.id_switch_rate - 0.5).abs() < 1e-6);
+
+        // the tracks 1 and 2 are lost for longer than the window
+        estimator.record(1, &[track(2, 3, 3, 900.0)]);
+        estimator.record(1, &[track(3, 10, 1, 4.0), track(4, 10, 1, 904.0)]);
+        let q = estimator.estimate(1).unwrap();
+        assert_eq!((q.new_tracks, q.fragmentations), (4, 0));
+
+        // the track 3 is lost and re-created as the track 5
+        estimator.record(1, &[track(5, 11, 1, 6.0)]);
+        let q = estimator.estimate(1).unwrap();
+        assert_eq!(q.fragmentations, 1);
+        assert!((q.fragmentation_rate - 0.5).abs() < 1e-6);
+
+        estimator.reset(1);
+        assert!(estimator.estimate(1).is_none());
+    }
+}
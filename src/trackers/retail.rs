@@ -0,0 +1,404 @@
+use crate::trackers::tracker_api::TrackerAPI;
+use crate::trackers::visual_sort::options::VisualSortOptions;
+use crate::trackers::visual_sort::simple_api::VisualSort;
+use crate::trackers::visual_sort::VisualSortObservation;
+use crate::utils::bbox::Universal2DBox;
+use geo::{Contains, LineString, Point, Polygon};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+
+/// The area of the scene (entrance, shelf, cashier desk) which visits are reported
+///
+#[derive(Debug, Clone)]
+pub struct Zone {
+    pub id: u64,
+    pub polygon: Polygon<f64>,
+}
+
+impl Zone {
+    /// Creates the zone from the polygon vertices
+    ///
+    pub fn new(id: u64, vertices: &[(f32, f32)]) -> Self {
+        let line = LineString::from(
+            vertices
+                .iter()
+                .map(|(x, y)| (*x as f64, *y as f64))
+                .collect::<Vec<_>>(),
+        );
+        Self {
+            id,
+            polygon: Polygon::new(line, vec![]),
+        }
+    }
+
+    /// The object is in the zone when the bottom center of its box (the point where a person
+    /// stands) is inside the zone
+    ///
+    pub fn contains(&self, bbox: &Universal2DBox) -> bool {
+        let anchor = Point::new(bbox.xc as f64, (bbox.yc + bbox.height / 2.0) as f64);
+        self.polygon.contains(&anchor)
+    }
+}
+
+/// Configuration of the retail analytics pipeline
+///
+#[derive(Debug, Clone)]
+pub struct RetailPipelineConfig {
+    /// ReID tracker configuration
+    ///
+    pub tracker: VisualSortOptions,
+    /// Number of tracker shards
+    ///
+    pub shards: usize,
+    /// Zones which visits are reported
+    ///
+    pub zones: Vec<Zone>,
+    /// The minimal feature quality of the observation to become a best shot
+    ///
+    pub best_shot_min_quality: f32,
+}
+
+impl Default for RetailPipelineConfig {
+    fn default() -> Self {
+        Self {
+            tracker: VisualSortOptions::default(),
+            shards: 1,
+            zones: Vec::default(),
+            best_shot_min_quality: 0.0,
+        }
+    }
+}
+
+/// Detected person passed to the pipeline
+///
+#[derive(Debug, Clone)]
+pub struct RetailDetection {
+    pub bbox: Universal2DBox,
+    pub feature: Option<Vec<f32>>,
+    pub feature_quality: f32,
+}
+
+/// The most valuable observation of the track
+///
+#[derive(Debug, Clone, Serialize)]
+#[serde(into = "BestShotJson")]
+pub struct BestShot {
+    pub epoch: usize,
+    pub bbox: Universal2DBox,
+    pub feature_quality: f32,
+}
+
+/// JSON layout of [BestShot](BestShot) with the box fields inlined
+///
+#[derive(Serialize)]
+struct BestShotJson {
+    epoch: usize,
+    xc: f32,
+    yc: f32,
+    aspect: f32,
+    height: f32,
+    feature_quality: f32,
+}
+
+impl From<BestShot> for BestShotJson {
+    fn from(s: BestShot) -> Self {
+        Self {
+            epoch: s.epoch,
+            xc: s.bbox.xc,
+            yc: s.bbox.yc,
+            aspect: s.bbox.aspect,
+            height: s.bbox.height,
+            feature_quality: s.feature_quality,
+        }
+    }
+}
+
+/// Events produced by the pipeline
+///
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RetailEvent {
+    ZoneEnter {
+        track_id: u64,
+        zone_id: u64,
+        epoch: usize,
+    },
+    ZoneExit {
+        track_id: u64,
+        zone_id: u64,
+        epoch: usize,
+    },
+    TrackFinished {
+        track_id: u64,
+        length: usize,
+        zones_visited: Vec<u64>,
+        best_shot: Option<BestShot>,
+    },
+}
+
+impl RetailEvent {
+    /// JSON representation of the event, the non-finite values are written as `null`
+    ///
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("The event is always serializable")
+    }
+}
+
+/// Writes the events as JSON lines
+///
+pub struct JsonlExporter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonlExporter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn export(&mut self, events: &[RetailEvent]) -> std::io::Result<()> {
+        for e in events {
+            writeln!(self.writer, "{}", e.to_json())?;
+        }
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[derive(Default)]
+struct TrackState {
+    length: usize,
+    zones: BTreeSet<u64>,
+    zones_visited: BTreeSet<u64>,
+    best_shot: Option<BestShot>,
+}
+
+/// Reference pipeline for ReID-based retail analytics.
+///
+/// Persons are tracked with [VisualSort](VisualSort), zone visits are reported when the tracks
+/// enter and leave the zones, the observation with the best feature quality is kept for every
+/// track and reported when the track is finished. The events can be written with [JsonlExporter](JsonlExporter).
+///
+pub struct RetailPipeline {
+    tracker: VisualSort,
+    zones: Vec<Zone>,
+    best_shot_min_quality: f32,
+    states: HashMap<u64, TrackState>,
+}
+
+impl RetailPipeline {
+    pub fn new(config: &RetailPipelineConfig) -> Self {
+        Self {
+            tracker: VisualSort::new(config.shards, &config.tracker),
+            zones: config.zones.clone(),
+            best_shot_min_quality: config.best_shot_min_quality,
+            states: HashMap::default(),
+        }
+    }
+
+    /// Processes the detections of the next frame
+    ///
+    pub fn process(&mut self, detections: &[RetailDetection]) -> Vec<RetailEvent> {
+        let observations = detections
+            .iter()
+            .enumerate()
+            .map(|(i, d)| {
+                VisualSortObservation::new(
+                    d.feature.as_deref(),
+                    Some(d.feature_quality),
+                    d.bbox.clone(),
+                    Some(i as i64),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut events = Vec::default();
+        for t in self.tracker.predict(&observations) {
+            let state = self.states.entry(t.id).or_default();
+            state.length = t.length;
+
+            let detection = t.custom_object_id.and_then(|i| detections.get(i as usize));
+            if let Some(d) = detection {
+                let better = state
+                    .best_shot
+                    .as_ref()
+                    .map(|s| d.feature_quality > s.feature_quality)
+                    .unwrap_or(true);
+                if better && d.feature_quality >= self.best_shot_min_quality {
+                    state.best_shot = Some(BestShot {
+                        epoch: t.epoch,
+                        bbox: d.bbox.clone(),
+                        feature_quality: d.feature_quality,
+                    });
+                }
+            }
+
+            let zones = self
+                .zones
+                .iter()
+                .filter(|z| z.contains(&t.observed_bbox))
+                .map(|z| z.id)
+                .collect::<BTreeSet<_>>();
+            for zone_id in state.zones.difference(&zones) {
+                events.push(RetailEvent::ZoneExit {
+                    track_id: t.id,
+                    zone_id: *zone_id,
+                    epoch: t.epoch,
+                });
+            }
+            for zone_id in zones.difference(&state.zones) {
+                events.push(RetailEvent::ZoneEnter {
+                    track_id: t.id,
+                    zone_id: *zone_id,
+                    epoch: t.epoch,
+                });
+            }
+            state.zones_visited.extend(zones.iter());
+            state.zones = zones;
+        }
+
+        let wasted = self
+            .tracker
+            .wasted()
+            .into_iter()
+            .map(|t| (t.get_track_id(), t.get_attributes().last_updated_epoch))
+            .collect::<Vec<_>>();
+        for (track_id, epoch) in wasted {
+            self.finish_track(track_id, epoch, &mut events);
+        }
+        events
+    }
+
+    /// Finishes all the tracks, e.g. when the stream is over
+    ///
+    pub fn finish(&mut self) -> Vec<RetailEvent> {
+        let epoch = self.tracker.current_epoch();
+        let mut events = Vec::default();
+        let mut track_ids = self.states.keys().copied().collect::<Vec<_>>();
+        track_ids.sort();
+        for track_id in track_ids {
+            self.finish_track(track_id, epoch, &mut events);
+        }
+        self.tracker.get_main_store_mut().clear();
+        self.tracker.clear_wasted();
+        events
+    }
+
+    fn finish_track(&mut self, track_id: u64, epoch: usize, events: &mut Vec<RetailEvent>) {
+        if let Some(state) = self.states.remove(&track_id) {
+            for zone_id in &state.zones {
+                events.push(RetailEvent::ZoneExit {
+                    track_id,
+                    zone_id: *zone_id,
+                    epoch,
+                });
+            }
+            events.push(RetailEvent::TrackFinished {
+                track_id,
+                length: state.length,
+                zones_visited: state.zones_visited.into_iter().collect(),
+                best_shot: state.best_shot,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::retail::{
+        BestShot, JsonlExporter, RetailDetection, RetailEvent, RetailPipeline,
+        RetailPipelineConfig, Zone,
+    };
+    use crate::trackers::visual_sort::options::VisualSortOptions;
+    use crate::utils::bbox::BoundingBox;
+
+    #[test]
+    fn pipeline() {
+        let config = RetailPipelineConfig {
+            tracker: VisualSortOptions::default().max_idle_epochs(2),
+            zones: vec![Zone::new(
+                7,
+                &[(0.0, 0.0), (6.5, 0.0), (6.5, 20.0), (0.0, 20.0)],
+            )],
+            best_shot_min_quality: 0.5,
+            ..Default::default()
+        };
+        let mut pipeline = RetailPipeline::new(&config);
+        let mut exporter = JsonlExporter::new(Vec::new());
+
+        let mut events = Vec::default();
+        for (i, q) in [0.6, 0.9, 0.7, 0.4].into_iter().enumerate() {
+            let detection = RetailDetection {
+                bbox: BoundingBox::new(2.0 + i as f32, 5.0, 4.0, 5.0).as_xyaah(),
+                feature: Some(vec![1.0, 0.0]),
+                feature_quality: q,
+            };
+            events.extend(pipeline.process(&[detection]));
+        }
+        events.extend(pipeline.finish());
+        exporter.export(&events).unwrap();
+
+        assert!(matches!(
+            events[0],
+            RetailEvent::ZoneEnter {
+                zone_id: 7,
+                epoch: 1,
+                ..
+            }
+        ));
+        assert!(matches!(
+            events[1],
+            RetailEvent::ZoneExit {
+                zone_id: 7,
+                epoch: 4,
+                ..
+            }
+        ));
+        match &events[2] {
+            RetailEvent::TrackFinished {
+                length,
+                zones_visited,
+                best_shot,
+                ..
+            } => {
+                assert_eq!(*length, 4);
+                assert_eq!(zones_visited, &vec![7]);
+                let best_shot = best_shot.as_ref().unwrap();
+                assert_eq!(best_shot.epoch, 2);
+                assert_eq!(best_shot.feature_quality, 0.9);
+            }
+            _ => unreachable!(),
+        }
+        assert_eq!(events.len(), 3);
+
+        let out = String::from_utf8(exporter.into_inner()).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with(r#"{"event":"zone_enter""#));
+        assert!(lines[2].contains(r#""zones_visited":[7]"#));
+    }
+
+    #[test]
+    fn non_finite_json() {
+        let event = RetailEvent::TrackFinished {
+            track_id: 1,
+            length: 2,
+            zones_visited: vec![3, 4],
+            best_shot: Some(BestShot {
+                epoch: 5,
+                bbox: BoundingBox::new(f32::NAN, 5.0, 4.0, 5.0).as_xyaah(),
+                feature_quality: f32::INFINITY,
+            }),
+        };
+        let value: serde_json::Value = serde_json::from_str(&event.to_json()).unwrap();
+        assert_eq!(value["event"], "track_finished");
+        assert_eq!(value["zones_visited"], serde_json::json!([3, 4]));
+        assert_eq!(value["best_shot"]["epoch"], 5);
+        assert!(value["best_shot"]["xc"].is_null());
+        assert!(value["best_shot"]["feature_quality"].is_null());
+        assert_eq!(value["best_shot"]["height"], 5.0);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::utils::bbox::Universal2DBox;
+use geo::{Intersects, LineString, Polygon};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Entry and exit zones of the scenes (doors, frame borders, etc.).
+///
+/// The tracks that appear outside of the entry zones of their scene are unlikely new objects, so
+/// they are kept tentative (not reported) until they collect `creation_penalty` additional
+/// observations. The tracks that disappear inside the exit zones are wasted immediately instead
+/// of idling for `max_idle_epochs`.
+///
+/// The scenes without entry zones accept new tracks everywhere, the scenes without exit zones
+/// never waste tracks early.
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SceneZones {
+    #[serde(with = "zones")]
+    entry: HashMap<u64, Vec<Polygon<f64>>>,
+    #[serde(with = "zones")]
+    exit: HashMap<u64, Vec<Polygon<f64>>>,
+    creation_penalty: usize,
+}
+
+/// Serde helpers that keep the zones as the lists of their exterior vertices
+///
+mod zones {
+    use geo::{LineString, Polygon};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    type Vertices = Vec<Vec<(f64, f64)>>;
+
+    pub fn serialize<S: Serializer>(
+        zones: &HashMap<u64, Vec<Polygon<f64>>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        zones
+            .iter()
+            .map(|(scene_id, polygons)| {
+                (
+                    *scene_id,
+                    polygons
+                        .iter()
+                        .map(|p| p.exterior().coords().map(|c| (c.x, c.y)).collect())
+                        .collect::<Vertices>(),
+                )
+            })
+            .collect::<HashMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<u64, Vec<Polygon<f64>>>, D::Error> {
+        Ok(HashMap::<u64, Vertices>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(scene_id, polygons)| {
+                (
+                    scene_id,
+                    polygons
+                        .into_iter()
+                        .map(|v| Polygon::new(LineString::from(v), vec![]))
+                        .collect(),
+                )
+            })
+            .collect())
+    }
+}
+
+fn make_polygon(vertices: &[(f32, f32)]) -> Polygon<f64> {
+    assert!(
+        vertices.len() >= 3,
+        "The zone must be defined by at least 3 vertices"
+    );
+    Polygon::new(
+        LineString::from(
+            vertices
+                .iter()
+                .map(|(x, y)| (*x as f64, *y as f64))
+                .collect::<Vec<_>>(),
+        ),
+        vec![],
+    )
+}
+
+fn intersects(zones: Option<&Vec<Polygon<f64>>>, bbox: &Universal2DBox) -> bool {
+    let bbox = Polygon::from(bbox);
+    zones
+        .map(|zones| zones.iter().any(|z| z.intersects(&bbox)))
+        .unwrap_or(false)
+}
+
+impl SceneZones {
+    /// Adds the entry zone for the scene
+    ///
+    pub fn entry_zone(mut self, scene_id: u64, vertices: &[(f32, f32)]) -> Self {
+        self.entry
+            .entry(scene_id)
+            .or_default()
+            .push(make_polygon(vertices));
+        self
+    }
+
+    /// Adds the exit zone for the scene
+    ///
+    pub fn exit_zone(mut self, scene_id: u64, vertices: &[(f32, f32)]) -> Self {
+        self.exit
+            .entry(scene_id)
+            .or_default()
+            .push(make_polygon(vertices));
+        self
+    }
+
+    /// Replaces the entry and exit zones of the scene
+    ///
+    pub(crate) fn set_scene_zones(
+        &mut self,
+        scene_id: u64,
+        entry: &[Vec<(f32, f32)>],
+        exit: &[Vec<(f32, f32)>],
+    ) {
+        self.entry
+            .insert(scene_id, entry.iter().map(|v| make_polygon(v)).collect());
+        self.exit
+            .insert(scene_id, exit.iter().map(|v| make_polygon(v)).collect());
+        self.entry.retain(|_, zones| !zones.is_empty());
+        self.exit.retain(|_, zones| !zones.is_empty());
+    }
+
+    /// The number of additional observations the track that appeared outside of the entry zones
+    /// must collect before it is reported
+    ///
+    pub fn creation_penalty(mut self, observations: usize) -> Self {
+        self.creation_penalty = observations;
+        self
+    }
+
+    /// The number of observations the track that appeared at `bbox` must collect before it is reported
+    ///
+    pub fn required_track_length(&self, scene_id: u64, bbox: &Universal2DBox) -> usize {
+        let entry = self.entry.get(&scene_id);
+        if entry.is_none() || intersects(entry, bbox) {
+            0
+        } else {
+            self.creation_penalty + 1
+        }
+    }
+
+    /// Checks if the box lies within one of the exit zones of the scene
+    ///
+    pub fn is_exit(&self, scene_id: u64, bbox: &Universal2DBox) -> bool {
+        intersects(self.exit.get(&scene_id), bbox)
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::SceneZones;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "SceneZones")]
+    #[derive(Debug, Clone, Default)]
+    pub struct PySceneZones(pub(crate) SceneZones);
+
+    #[pymethods]
+    impl PySceneZones {
+        #[new]
+        pub(crate) fn new() -> Self {
+            Self::default()
+        }
+
+        #[pyo3(text_signature = "($self, scene_id, vertices)")]
+        pub(crate) fn entry_zone(&mut self, scene_id: u64, vertices: Vec<(f32, f32)>) {
+            self.0 = self.0.clone().entry_zone(scene_id, &vertices);
+        }
+
+        #[pyo3(text_signature = "($self, scene_id, vertices)")]
+        pub(crate) fn exit_zone(&mut self, scene_id: u64, vertices: Vec<(f32, f32)>) {
+            self.0 = self.0.clone().exit_zone(scene_id, &vertices);
+        }
+
+        #[pyo3(text_signature = "($self, observations)")]
+        pub(crate) fn creation_penalty(&mut self, observations: i64) {
+            self.0 = self.0.clone().creation_penalty(
+                observations
+                    .try_into()
+                    .expect("Parameter must be a positive number"),
+            );
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::scene_zones::SceneZones;
+    use crate::utils::bbox::BoundingBox;
+
+    #[test]
+    fn zones() {
+        let zones = SceneZones::default()
+            .entry_zone(1, &[(0.0, 0.0), (10.0, 0.0), (10.0, 100.0), (0.0, 100.0)])
+            .exit_zone(
+                1,
+                &[(90.0, 0.0), (100.0, 0.0), (100.0, 100.0), (90.0, 100.0)],
+            )
+            .creation_penalty(2);
+
+        let at_entry = BoundingBox::new(5.0, 10.0, 10.0, 20.0).as_xyaah();
+        let at_center = BoundingBox::new(40.0, 10.0, 10.0, 20.0).as_xyaah();
+        let at_exit = BoundingBox::new(85.0, 10.0, 10.0, 20.0).as_xyaah();
+
+        assert_eq!(zones.required_track_length(1, &at_entry), 0);
+        assert_eq!(zones.required_track_length(1, &at_center), 3);
+        assert_eq!(zones.required_track_length(0, &at_center), 0);
+
+        assert!(zones.is_exit(1, &at_exit));
+        assert!(!zones.is_exit(1, &at_center));
+        assert!(!zones.is_exit(0, &at_exit));
+    }
+}
@@ -1,18 +1,47 @@
+use crate::track::calibration::{CalibrationRegistry, SceneCalibration};
+use crate::track::notify::ChangeNotifier;
 use crate::track::{
     LookupRequest, ObservationsDb, Track, TrackAttributes, TrackAttributesUpdate, TrackStatus,
 };
-use crate::trackers::epoch_db::EpochDb;
+use crate::trackers::class_voting::{ClassVotes, TrackClass};
+use crate::trackers::density::{
+    adapt_positional_metric, DensityEstimator, DensityOptions, SceneDensity,
+};
+use crate::trackers::epoch_db::{EpochDb, SceneUpdates};
+use crate::trackers::frame_clamping::{clamp_to_frame, FrameClamping};
+use crate::trackers::history_fallback::HistoryFallback;
 use crate::trackers::kalman_prediction::TrackAttributesKalmanPrediction;
+use crate::trackers::latency::{LatencyPercentiles, LatencyStats};
+use crate::trackers::latency_budget::{LatencyBudget, LatencyBudgetController};
+use crate::trackers::metadata::merge_metadata;
+use crate::trackers::motion_model::MotionModel;
+use crate::trackers::noise_model::{NoiseEstimate, NoiseModelEstimator, NoiseModelOptions};
+use crate::trackers::output_filter::TrackOutputFilter;
+use crate::trackers::predict_overrides::PredictOverrides;
+use crate::trackers::quality::{QualityEstimator, QualityOptions, TrackingQuality};
+#[cfg(feature = "clipping")]
+use crate::trackers::scene_zones::SceneZones;
 use crate::trackers::spatio_temporal_constraints::SpatioTemporalConstraints;
+use crate::trackers::waste_triggers::{WasteContext, WasteTriggers};
+#[cfg(feature = "f16")]
+use crate::utils::bbox::compact::{compact_history, CompactUniversal2DBox};
 use crate::utils::bbox::Universal2DBox;
-use crate::utils::kalman::kalman_2d_box::DIM_2D_BOX_X2;
+use crate::utils::kalman::gating::MahalanobisGating;
+use crate::utils::kalman::kalman_2d_box::{Universal2DBoxKalmanFilter, DIM_2D_BOX, DIM_2D_BOX_X2};
 use crate::utils::kalman::KalmanState;
 use anyhow::Result;
+use serde_json::Value;
 
 use std::collections::{HashMap, VecDeque};
+#[cfg(feature = "f16")]
+use std::sync::atomic::AtomicBool;
+#[cfg(feature = "f16")]
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use self::metric::SortMetric;
+use serde::{Deserialize, Serialize};
 
 /// SORT metric implementation with IoU and Mahalanobis distances
 pub mod metric;
@@ -25,15 +54,29 @@ pub mod simple_api;
 pub mod voting;
 
 /// SORT tracker with Batch API
+#[cfg(feature = "batch")]
 pub mod batch_api;
 
 /// Default IoU threshold that is defined by SORT author in the original repo
 pub const DEFAULT_SORT_IOU_THRESHOLD: f32 = 0.3;
 
+/// The configuration of the scenes, kept behind one lock so the related settings change together,
+/// e.g. the calibration and the zones it defines
+///
+#[derive(Debug, Default)]
+struct SceneSettings {
+    #[cfg(feature = "clipping")]
+    zones: SceneZones,
+    calibration: CalibrationRegistry,
+    predict_overrides: HashMap<u64, PredictOverrides>,
+}
+
 #[derive(Debug)]
 pub struct SortAttributesOptions {
     /// The map that stores current epochs for the scene_id
     epoch_db: Option<RwLock<HashMap<u64, usize>>>,
+    /// When the epochs of the scenes were lastly changed
+    scene_updates: RwLock<SceneUpdates>,
     /// The maximum number of epochs without update while the track is alive
     max_idle_epochs: usize,
     /// The maximum length of collected objects for the track
@@ -41,70 +84,961 @@ pub struct SortAttributesOptions {
     pub spatio_temporal_constraints: SpatioTemporalConstraints,
     pub position_weight: f32,
     pub velocity_weight: f32,
+    /// The filter that defines which tracks are reported
+    output_filter: RwLock<TrackOutputFilter>,
+    /// The zones, the calibrations and the overrides of the scenes
+    scenes: RwLock<SceneSettings>,
+    /// The estimator of the detection noise
+    noise_model: RwLock<Option<NoiseModelEstimator>>,
+    /// The estimator of the object density
+    density: RwLock<Option<DensityEstimator>>,
+    /// The estimator of the tracking quality
+    quality: RwLock<Option<QualityEstimator>>,
+    /// The rolling latency of the predictions per scene
+    predict_latency: RwLock<LatencyStats>,
+    /// The latency budget that skips the visual distances of the overloaded scenes
+    latency_budget: RwLock<Option<LatencyBudgetController>>,
+    /// The number of class votes after which the majority class of the track is fixed
+    class_freeze: RwLock<Option<usize>>,
+    /// Propagate the Kalman state of the idle tracks over the skipped epochs
+    skipped_epochs_prediction: RwLock<bool>,
+    /// Record the wall-clock time of every observation
+    observation_timestamps: RwLock<bool>,
+    /// The number of epochs after which the track is wasted even if it is still matched
+    max_track_lifetime: RwLock<Option<usize>>,
+    /// The custom conditions that waste the tracks
+    waste_triggers: RwLock<WasteTriggers>,
+    /// The clamping of the track boxes to the frame of the scene
+    frame_clamping: RwLock<FrameClamping>,
+    /// The boxes reported for the tracks without the box history
+    history_fallback: RwLock<HistoryFallback>,
+    /// The motion model predicting the boxes of the tracks
+    motion_model: RwLock<MotionModel>,
+    /// The chi-square gating of the Mahalanobis distances, the legacy cost is used when not set
+    mahalanobis_gating: RwLock<Option<MahalanobisGating>>,
+    /// Keep the box history but the last boxes with half precision
+    #[cfg(feature = "f16")]
+    compact_box_history: AtomicBool,
 }
 
 impl Default for SortAttributesOptions {
     fn default() -> Self {
         Self {
             epoch_db: None,
+            scene_updates: RwLock::new(SceneUpdates::default()),
             max_idle_epochs: 0,
             history_length: 0,
             spatio_temporal_constraints: SpatioTemporalConstraints::default(),
             position_weight: 1.0 / 20.0,
             velocity_weight: 1.0 / 160.0,
+            output_filter: RwLock::new(TrackOutputFilter::default()),
+            scenes: RwLock::new(SceneSettings::default()),
+            noise_model: RwLock::new(None),
+            density: RwLock::new(None),
+            quality: RwLock::new(None),
+            predict_latency: RwLock::new(LatencyStats::default()),
+            latency_budget: RwLock::new(None),
+            class_freeze: RwLock::new(None),
+            skipped_epochs_prediction: RwLock::new(false),
+            observation_timestamps: RwLock::new(false),
+            max_track_lifetime: RwLock::new(None),
+            waste_triggers: RwLock::new(WasteTriggers::default()),
+            frame_clamping: RwLock::new(FrameClamping::default()),
+            history_fallback: RwLock::new(HistoryFallback::default()),
+            motion_model: RwLock::new(MotionModel::default()),
+            mahalanobis_gating: RwLock::new(None),
+            #[cfg(feature = "f16")]
+            compact_box_history: AtomicBool::new(false),
         }
     }
 }
 
-impl EpochDb for SortAttributesOptions {
-    fn epoch_db(&self) -> &Option<RwLock<HashMap<u64, usize>>> {
-        &self.epoch_db
+impl EpochDb for SortAttributesOptions {
+    fn epoch_db(&self) -> &Option<RwLock<HashMap<u64, usize>>> {
+        &self.epoch_db
+    }
+
+    fn max_idle_epochs(&self) -> usize {
+        self.max_idle_epochs
+    }
+
+    fn scene_updates(&self) -> Option<&RwLock<SceneUpdates>> {
+        Some(&self.scene_updates)
+    }
+
+    /// The SORT attributes use the notifications only to propagate the Kalman state
+    ///
+    fn epoch_notifications(&self) -> bool {
+        self.skipped_epochs_prediction()
+    }
+}
+
+impl SortAttributesOptions {
+    pub fn new(
+        epoch_db: Option<RwLock<HashMap<u64, usize>>>,
+        max_idle_epochs: usize,
+        history_length: usize,
+        spatio_temporal_constraints: SpatioTemporalConstraints,
+        position_weight: f32,
+        velocity_weight: f32,
+    ) -> Self {
+        Self {
+            epoch_db,
+            scene_updates: RwLock::new(SceneUpdates::default()),
+            max_idle_epochs,
+            history_length,
+            spatio_temporal_constraints,
+            position_weight,
+            velocity_weight,
+            output_filter: RwLock::new(TrackOutputFilter::default()),
+            scenes: RwLock::new(SceneSettings::default()),
+            noise_model: RwLock::new(None),
+            density: RwLock::new(None),
+            quality: RwLock::new(None),
+            predict_latency: RwLock::new(LatencyStats::default()),
+            latency_budget: RwLock::new(None),
+            class_freeze: RwLock::new(None),
+            skipped_epochs_prediction: RwLock::new(false),
+            observation_timestamps: RwLock::new(false),
+            max_track_lifetime: RwLock::new(None),
+            waste_triggers: RwLock::new(WasteTriggers::default()),
+            frame_clamping: RwLock::new(FrameClamping::default()),
+            history_fallback: RwLock::new(HistoryFallback::default()),
+            motion_model: RwLock::new(MotionModel::default()),
+            mahalanobis_gating: RwLock::new(None),
+            #[cfg(feature = "f16")]
+            compact_box_history: AtomicBool::new(false),
+        }
+    }
+
+    /// Sets the output filter
+    ///
+    pub fn with_output_filter(self, filter: TrackOutputFilter) -> Self {
+        self.set_output_filter(filter);
+        self
+    }
+
+    /// Replaces the output filter
+    ///
+    pub fn set_output_filter(&self, filter: TrackOutputFilter) {
+        *self.output_filter.write().unwrap() = filter;
+    }
+
+    /// Returns the output filter
+    ///
+    pub fn output_filter(&self) -> TrackOutputFilter {
+        self.output_filter.read().unwrap().clone()
+    }
+
+    /// Removes the tracks that must not be reported according to the output filter. Tentative
+    /// tracks are never reported.
+    ///
+    pub fn filter_output(&self, mut tracks: Vec<SortTrack>) -> Vec<SortTrack> {
+        let filter = self.output_filter.read().unwrap();
+        tracks.retain(|t| self.allows(&filter, t));
+        tracks
+    }
+
+    /// Checks if the track must be reported according to the output filter
+    ///
+    pub fn output_allowed(&self, track: &SortTrack) -> bool {
+        self.allows(&self.output_filter.read().unwrap(), track)
+    }
+
+    fn allows(&self, filter: &TrackOutputFilter, track: &SortTrack) -> bool {
+        !track.tentative
+            && filter.allows(
+                track,
+                self.current_epoch_with_scene(track.scene_id)
+                    .unwrap_or_default(),
+            )
+    }
+
+    /// Sets the entry and exit zones of the scenes
+    ///
+    #[cfg(feature = "clipping")]
+    pub fn with_scene_zones(self, zones: SceneZones) -> Self {
+        self.set_scene_zones(zones);
+        self
+    }
+
+    /// Replaces the entry and exit zones of the scenes
+    ///
+    #[cfg(feature = "clipping")]
+    pub fn set_scene_zones(&self, zones: SceneZones) {
+        self.scenes.write().unwrap().zones = zones;
+    }
+
+    /// Returns the entry and exit zones of the scenes
+    ///
+    #[cfg(feature = "clipping")]
+    pub fn scene_zones(&self) -> SceneZones {
+        self.scenes.read().unwrap().zones.clone()
+    }
+
+    /// Registers the calibration of the scene
+    ///
+    pub fn with_scene_calibration(self, scene_id: u64, calibration: SceneCalibration) -> Self {
+        self.set_scene_calibration(scene_id, Some(calibration));
+        self
+    }
+
+    /// Registers or removes the calibration of the scene. The entry and exit zones of the
+    /// calibration replace the zones of the scene, the calibration without zones keeps them. The
+    /// zones are ignored without the `clipping` feature.
+    ///
+    pub fn set_scene_calibration(&self, scene_id: u64, calibration: Option<SceneCalibration>) {
+        let mut scenes = self.scenes.write().unwrap();
+        match calibration {
+            Some(calibration) => {
+                #[cfg(feature = "clipping")]
+                if !calibration.get_entry_zones().is_empty()
+                    || !calibration.get_exit_zones().is_empty()
+                {
+                    scenes.zones.set_scene_zones(
+                        scene_id,
+                        calibration.get_entry_zones(),
+                        calibration.get_exit_zones(),
+                    );
+                }
+                scenes.calibration.set(scene_id, calibration);
+            }
+            None => {
+                scenes.calibration.remove(scene_id);
+            }
+        }
+    }
+
+    /// Returns the calibration of the scene
+    ///
+    pub fn scene_calibration(&self, scene_id: u64) -> Option<Arc<SceneCalibration>> {
+        self.scenes.read().unwrap().calibration.get(scene_id)
+    }
+
+    /// Returns the calibrations of all the scenes
+    ///
+    pub fn calibration_registry(&self) -> CalibrationRegistry {
+        self.scenes.read().unwrap().calibration.clone()
+    }
+
+    /// Replaces the calibrations of all the scenes, the scene zones are not changed
+    ///
+    pub fn set_calibration_registry(&self, registry: CalibrationRegistry) {
+        self.scenes.write().unwrap().calibration = registry;
+    }
+
+    /// The number of observations the track that appeared at `bbox` must collect before it is reported
+    ///
+    #[cfg(feature = "clipping")]
+    pub fn required_track_length(&self, scene_id: u64, bbox: &Universal2DBox) -> usize {
+        self.scenes
+            .read()
+            .unwrap()
+            .zones
+            .required_track_length(scene_id, bbox)
+    }
+
+    /// The number of observations the track that appeared at `bbox` must collect before it is
+    /// reported, the scenes have no entry zones without the `clipping` feature
+    ///
+    #[cfg(not(feature = "clipping"))]
+    pub fn required_track_length(&self, _scene_id: u64, _bbox: &Universal2DBox) -> usize {
+        0
+    }
+
+    /// Enables the detection noise model estimation
+    ///
+    pub fn with_noise_model(self, opts: NoiseModelOptions) -> Self {
+        self.set_noise_model(Some(opts));
+        self
+    }
+
+    /// Enables (replacing collected residuals) or disables the detection noise model estimation
+    ///
+    pub fn set_noise_model(&self, opts: Option<NoiseModelOptions>) {
+        *self.noise_model.write().unwrap() = opts.map(NoiseModelEstimator::new);
+    }
+
+    /// Returns the configuration of the detection noise model estimation
+    ///
+    pub fn noise_model(&self) -> Option<NoiseModelOptions> {
+        self.noise_model
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|m| m.options())
+    }
+
+    /// The Kalman weights recommended for the scene by the noise model estimation
+    ///
+    pub fn noise_estimate(&self, scene_id: u64) -> Option<NoiseEstimate> {
+        self.noise_model
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|m| m.estimate(scene_id))
+    }
+
+    /// The position and the velocity Kalman weights used for the scene
+    ///
+    pub fn kalman_weights(&self, scene_id: u64) -> (f32, f32) {
+        let configured = (self.position_weight, self.velocity_weight);
+        match self.noise_model.read().unwrap().as_ref() {
+            Some(m) => m.weights(scene_id, configured),
+            None => configured,
+        }
+    }
+
+    pub(crate) fn record_innovation(
+        &self,
+        scene_id: u64,
+        prediction: &KalmanState<DIM_2D_BOX_X2>,
+        update: &KalmanState<DIM_2D_BOX_X2>,
+        observation: &Universal2DBox,
+    ) {
+        if let Some(m) = self.noise_model.read().unwrap().as_ref() {
+            m.record(scene_id, prediction, update, observation);
+        }
+    }
+
+    /// Enables the object density estimation
+    ///
+    pub fn with_density_estimation(self, opts: DensityOptions) -> Self {
+        self.set_density_estimation(Some(opts));
+        self
+    }
+
+    /// Enables (replacing collected counts) or disables the object density estimation
+    ///
+    pub fn set_density_estimation(&self, opts: Option<DensityOptions>) {
+        *self.density.write().unwrap() = opts.map(DensityEstimator::new);
+    }
+
+    /// Returns the configuration of the object density estimation
+    ///
+    pub fn density_estimation(&self) -> Option<DensityOptions> {
+        self.density.read().unwrap().as_ref().map(|d| d.options())
+    }
+
+    /// The object density of the scene estimated from the observations of the last epochs
+    ///
+    pub fn scene_density(&self, scene_id: u64) -> Option<SceneDensity> {
+        self.density
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|d| d.estimate(scene_id))
+    }
+
+    /// The factor the positional metric of the candidate observed at `bbox` is scaled with,
+    /// `1.0` unless the density adaptation is enabled
+    ///
+    pub fn congestion_factor(&self, scene_id: u64, bbox: &Universal2DBox) -> f32 {
+        match self.density.read().unwrap().as_ref() {
+            Some(d) => d.factor(scene_id, bbox),
+            None => 1.0,
+        }
+    }
+
+    pub(crate) fn record_density<'a>(
+        &self,
+        scene_id: u64,
+        boxes: impl Iterator<Item = &'a Universal2DBox>,
+    ) {
+        if let Some(d) = self.density.read().unwrap().as_ref() {
+            d.record(scene_id, boxes);
+        }
+    }
+
+    /// Enables the tracking quality estimation
+    ///
+    pub fn with_quality_estimation(self, opts: QualityOptions) -> Self {
+        self.set_quality_estimation(Some(opts));
+        self
+    }
+
+    /// Enables (discarding the collected estimates) or disables the tracking quality estimation
+    ///
+    pub fn set_quality_estimation(&self, opts: Option<QualityOptions>) {
+        *self.quality.write().unwrap() = opts.map(QualityEstimator::new);
+    }
+
+    /// Returns the configuration of the tracking quality estimation
+    ///
+    pub fn quality_estimation(&self) -> Option<QualityOptions> {
+        self.quality.read().unwrap().as_ref().map(|q| q.options())
+    }
+
+    /// The id switch and fragmentation rates of the scene estimated from the reported tracks
+    ///
+    pub fn tracking_quality(&self, scene_id: u64) -> Option<TrackingQuality> {
+        self.quality
+            .read()
+            .unwrap()
+            .as_ref()
+            .and_then(|q| q.estimate(scene_id))
+    }
+
+    /// Accounts the tracks reported by the prediction of the scene, the tracks must be accounted
+    /// before the output filter is applied
+    ///
+    pub(crate) fn record_quality(&self, scene_id: u64, tracks: &[SortTrack]) {
+        if let Some(q) = self.quality.read().unwrap().as_ref() {
+            q.record(scene_id, tracks);
+        }
+    }
+
+    /// Sets the number of the last predictions of every scene the latency percentiles are computed for
+    ///
+    pub fn with_latency_window(self, window: usize) -> Self {
+        self.set_latency_window(window);
+        self
+    }
+
+    /// Replaces the latency window, the collected latencies are discarded
+    ///
+    pub fn set_latency_window(&self, window: usize) {
+        *self.predict_latency.write().unwrap() = LatencyStats::new(window);
+    }
+
+    /// The prediction latency percentiles of the scene over the latency window
+    ///
+    pub fn predict_latency(&self, scene_id: u64) -> Option<LatencyPercentiles> {
+        self.predict_latency.read().unwrap().percentiles(scene_id)
+    }
+
+    /// Accounts the latency of the prediction of the scene
+    ///
+    /// # Parameters
+    /// * `scene_id` - the scene of the prediction
+    /// * `latency` - the time the prediction took
+    /// * `queue_depth` - the number of the predictions pending, `0` for the trackers without the queue
+    ///
+    pub(crate) fn record_predict_latency(
+        &self,
+        scene_id: u64,
+        latency: Duration,
+        queue_depth: usize,
+    ) {
+        self.predict_latency
+            .read()
+            .unwrap()
+            .record(scene_id, latency);
+        if let Some(budget) = self.latency_budget.read().unwrap().as_ref() {
+            budget.record(scene_id, latency, queue_depth);
+        }
+    }
+
+    /// Sets the latency budget of the scenes
+    ///
+    pub fn with_latency_budget(self, budget: LatencyBudget) -> Self {
+        self.set_latency_budget(Some(budget));
+        self
+    }
+
+    /// Enables (forgetting the load of the scenes) or disables the latency budget
+    ///
+    pub fn set_latency_budget(&self, budget: Option<LatencyBudget>) {
+        *self.latency_budget.write().unwrap() = budget.map(LatencyBudgetController::new);
+    }
+
+    /// Returns the latency budget of the scenes
+    ///
+    pub fn latency_budget(&self) -> Option<LatencyBudget> {
+        self.latency_budget
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|b| b.budget())
+    }
+
+    /// Checks if the scene exceeded the latency budget, so its visual distances are skipped
+    ///
+    pub fn latency_degraded(&self, scene_id: u64) -> bool {
+        self.latency_budget
+            .read()
+            .unwrap()
+            .as_ref()
+            .map_or(false, |b| b.degraded(scene_id))
+    }
+
+    /// Fixes the majority class of the track after it collects `n` class votes
+    ///
+    pub fn with_class_freeze(self, n: usize) -> Self {
+        self.set_class_freeze(Some(n));
+        self
+    }
+
+    /// Enables or disables the fixing of the majority class of the tracks, the tracks already
+    /// fixed are not affected
+    ///
+    pub fn set_class_freeze(&self, n: Option<usize>) {
+        *self.class_freeze.write().unwrap() = n;
+    }
+
+    /// Returns the number of class votes after which the majority class of the track is fixed
+    ///
+    pub fn class_freeze(&self) -> Option<usize> {
+        *self.class_freeze.read().unwrap()
+    }
+
+    /// Propagates the Kalman state of the alive tracks over the epochs skipped with
+    /// [skip_epochs_for_scene](crate::trackers::tracker_api::TrackerAPI::skip_epochs_for_scene),
+    /// so the predictions of the tracks matched or reactivated after the gap follow the objects
+    ///
+    pub fn with_skipped_epochs_prediction(self) -> Self {
+        self.set_skipped_epochs_prediction(true);
+        self
+    }
+
+    /// Enables or disables the propagation of the Kalman state over the skipped epochs
+    ///
+    pub fn set_skipped_epochs_prediction(&self, enabled: bool) {
+        *self.skipped_epochs_prediction.write().unwrap() = enabled;
+    }
+
+    /// Returns if the Kalman state is propagated over the skipped epochs
+    ///
+    pub fn skipped_epochs_prediction(&self) -> bool {
+        *self.skipped_epochs_prediction.read().unwrap()
+    }
+
+    /// Records the wall-clock time of every observation of the tracks, so the exported
+    /// trajectories can be aligned with the video timestamps. Disabled by default, the clock is
+    /// then read only when the track is created and wasted.
+    ///
+    pub fn with_observation_timestamps(self) -> Self {
+        self.set_observation_timestamps(true);
+        self
+    }
+
+    /// Enables or disables the recording of the wall-clock time of every observation
+    ///
+    pub fn set_observation_timestamps(&self, enabled: bool) {
+        *self.observation_timestamps.write().unwrap() = enabled;
+    }
+
+    /// Returns if the wall-clock time of every observation is recorded
+    ///
+    pub fn observation_timestamps(&self) -> bool {
+        *self.observation_timestamps.read().unwrap()
+    }
+
+    /// Limits the lifetime of the tracks: the track is wasted after `epochs` epochs since its
+    /// creation even if it is continuously matched, the next observations of the object start a
+    /// new track with a fresh id
+    ///
+    pub fn with_max_track_lifetime(self, epochs: usize) -> Self {
+        self.set_max_track_lifetime(Some(epochs));
+        self
+    }
+
+    /// Sets or removes the limit of the track lifetime in epochs
+    ///
+    pub fn set_max_track_lifetime(&self, epochs: Option<usize>) {
+        if let Some(epochs) = epochs {
+            assert!(epochs > 0, "The lifetime must be a positive number");
+        }
+        *self.max_track_lifetime.write().unwrap() = epochs;
+    }
+
+    /// Returns the limit of the track lifetime in epochs
+    ///
+    pub fn max_track_lifetime(&self) -> Option<usize> {
+        *self.max_track_lifetime.read().unwrap()
+    }
+
+    /// Checks if the track created at `created_epoch` reached the lifetime limit at `epoch`
+    ///
+    pub fn lifetime_expired(&self, created_epoch: usize, epoch: usize) -> bool {
+        self.max_track_lifetime()
+            .map(|max| epoch.saturating_sub(created_epoch) >= max)
+            .unwrap_or(false)
+    }
+
+    /// Checks if the track of the scene created at `created_epoch` reached the lifetime limit at
+    /// the current epoch of the scene
+    ///
+    pub fn track_lifetime_expired(&self, scene_id: u64, created_epoch: usize) -> bool {
+        self.current_epoch_with_scene(scene_id)
+            .map(|epoch| self.lifetime_expired(created_epoch, epoch))
+            .unwrap_or(false)
+    }
+
+    /// Sets the custom conditions that waste the tracks in addition to the idle epochs expiry
+    ///
+    pub fn with_waste_triggers(self, triggers: WasteTriggers) -> Self {
+        self.set_waste_triggers(triggers);
+        self
+    }
+
+    /// Replaces the custom conditions that waste the tracks
+    ///
+    pub fn set_waste_triggers(&self, triggers: WasteTriggers) {
+        *self.waste_triggers.write().unwrap() = triggers;
+    }
+
+    /// Returns the custom conditions that waste the tracks
+    ///
+    pub fn waste_triggers(&self) -> WasteTriggers {
+        self.waste_triggers.read().unwrap().clone()
+    }
+
+    /// Checks if any of the waste triggers of the track scene fires
+    ///
+    pub fn waste_triggered(&self, context: &WasteContext) -> bool {
+        let triggers = self.waste_triggers.read().unwrap();
+        !triggers.is_empty() && triggers.triggered(context)
+    }
+
+    /// Checks if any waste trigger is set, so the context of the track must be built
+    ///
+    pub(crate) fn has_waste_triggers(&self) -> bool {
+        !self.waste_triggers.read().unwrap().is_empty()
+    }
+
+    /// Sets the clamping of the track boxes to the frame, the frame size is taken from the
+    /// calibration of the scene
+    ///
+    pub fn with_frame_clamping(self, clamping: FrameClamping) -> Self {
+        self.set_frame_clamping(clamping);
+        self
+    }
+
+    /// Replaces the clamping of the track boxes to the frame
+    ///
+    pub fn set_frame_clamping(&self, clamping: FrameClamping) {
+        *self.frame_clamping.write().unwrap() = clamping;
+    }
+
+    /// Returns the clamping of the track boxes to the frame
+    ///
+    pub fn frame_clamping(&self) -> FrameClamping {
+        *self.frame_clamping.read().unwrap()
+    }
+
+    /// Sets the boxes reported for the tracks which box history is empty
+    ///
+    pub fn with_history_fallback(self, fallback: HistoryFallback) -> Self {
+        self.set_history_fallback(fallback);
+        self
+    }
+
+    /// Replaces the boxes reported for the tracks which box history is empty
+    ///
+    pub fn set_history_fallback(&self, fallback: HistoryFallback) {
+        *self.history_fallback.write().unwrap() = fallback;
+    }
+
+    /// Returns the boxes reported for the tracks which box history is empty
+    ///
+    pub fn history_fallback(&self) -> HistoryFallback {
+        *self.history_fallback.read().unwrap()
+    }
+
+    /// Sets the motion model predicting the boxes of the tracks
+    ///
+    pub fn with_motion_model(self, model: MotionModel) -> Self {
+        self.set_motion_model(model);
+        self
+    }
+
+    /// Replaces the motion model predicting the boxes of the tracks, the tracks created under
+    /// the other model keep their Kalman state
+    ///
+    /// # Panics
+    /// When the motion model is [MotionModel::None](MotionModel::None) and the
+    /// [Mahalanobis gating](Self::set_mahalanobis_gating) is set, the gating requires the Kalman
+    /// state
+    ///
+    pub fn set_motion_model(&self, model: MotionModel) {
+        assert!(
+            model.is_kalman() || self.mahalanobis_gating().is_none(),
+            "The Mahalanobis gating requires the Kalman motion model"
+        );
+        *self.motion_model.write().unwrap() = model;
+    }
+
+    /// Returns the motion model predicting the boxes of the tracks
+    ///
+    pub fn motion_model(&self) -> MotionModel {
+        *self.motion_model.read().unwrap()
+    }
+
+    fn frame_size(&self, scene_id: u64) -> Option<(f32, f32)> {
+        self.scene_calibration(scene_id)
+            .and_then(|c| c.get_frame_size())
+    }
+
+    /// Clips the observed and the predicted boxes of the reported track to the frame of the scene
+    ///
+    /// # Returns
+    /// the observed and the predicted boxes and `true` if any of them was clipped
+    ///
+    pub fn clamp_output(
+        &self,
+        scene_id: u64,
+        observed: &Universal2DBox,
+        predicted: &Universal2DBox,
+    ) -> (Universal2DBox, Universal2DBox, bool) {
+        match self.frame_size(scene_id) {
+            Some((width, height)) if self.frame_clamping().output() => {
+                let (observed, observed_clipped) = clamp_to_frame(observed, width, height);
+                let (predicted, predicted_clipped) = clamp_to_frame(predicted, width, height);
+                (observed, predicted, observed_clipped || predicted_clipped)
+            }
+            _ => (observed.clone(), predicted.clone(), false),
+        }
+    }
+
+    /// The frame the Kalman state of the tracks of the scene is kept within
+    ///
+    pub(crate) fn state_frame(&self, scene_id: u64) -> Option<(f32, f32)> {
+        if self.frame_clamping().state() {
+            self.frame_size(scene_id)
+        } else {
+            None
+        }
+    }
+
+    /// Replaces the legacy Mahalanobis cost with the chi-square gated score, see
+    /// [MahalanobisGating](MahalanobisGating)
+    ///
+    pub fn with_mahalanobis_gating(self, gating: MahalanobisGating) -> Self {
+        self.set_mahalanobis_gating(Some(gating));
+        self
+    }
+
+    /// Sets or removes the chi-square gating of the Mahalanobis distances
+    ///
+    /// # Panics
+    /// When the gating is set with the [MotionModel::None](MotionModel::None) motion model, the
+    /// gating requires the Kalman state
+    ///
+    pub fn set_mahalanobis_gating(&self, gating: Option<MahalanobisGating>) {
+        assert!(
+            gating.is_none() || self.motion_model().is_kalman(),
+            "The Mahalanobis gating requires the Kalman motion model"
+        );
+        *self.mahalanobis_gating.write().unwrap() = gating;
+    }
+
+    /// Returns the chi-square gating of the Mahalanobis distances
+    ///
+    pub fn mahalanobis_gating(&self) -> Option<MahalanobisGating> {
+        *self.mahalanobis_gating.read().unwrap()
+    }
+
+    /// The positional metric of the pair from the squared Mahalanobis distance.
+    ///
+    /// With the gating the metric is the gated score multiplied with the detection confidence and
+    /// divided by the congestion factor (the same way as IoU), without the gating it is the legacy
+    /// cost `(CHI2_UPPER_BOUND - distance) / confidence`, which is `0.0` outside of the 0.95 gate.
+    ///
+    /// # Returns
+    /// the metric, `None` if the pair is outside of the gate
+    ///
+    pub fn mahalanobis_metric(&self, distance: f32, confidence: f32, factor: f32) -> Option<f32> {
+        match self.mahalanobis_gating() {
+            Some(gating) => gating
+                .score(distance, DIM_2D_BOX)
+                .map(|score| score * confidence / factor),
+            None => Some(adapt_positional_metric(
+                PositionalMetricType::Mahalanobis,
+                Universal2DBoxKalmanFilter::calculate_cost(distance, true) / confidence,
+                factor,
+            )),
+        }
+    }
+
+    /// The positional threshold of the voting for the scene, the candidates which metric doesn't
+    /// exceed it start new tracks
+    ///
+    pub fn positional_threshold(&self, scene_id: u64, kind: PositionalMetricType) -> f32 {
+        self.positional_threshold_with(&self.predict_overrides(scene_id), kind)
+    }
+
+    /// The positional threshold of the voting with the overrides of the prediction
+    ///
+    pub(crate) fn positional_threshold_with(
+        &self,
+        overrides: &PredictOverrides,
+        kind: PositionalMetricType,
+    ) -> f32 {
+        match (overrides.positional_kind(kind), self.mahalanobis_gating()) {
+            (PositionalMetricType::Mahalanobis, Some(gating)) => gating.min_score,
+            _ => overrides.positional_threshold(kind),
+        }
+    }
+
+    /// Sets the overrides of the configuration for the predictions of the scene
+    ///
+    /// # Parameters
+    /// * `scene_id` - the scene the overrides are applied to
+    /// * `overrides` - the overrides, `None` restores the configured behavior
+    ///
+    /// # Returns
+    /// the overrides that were set for the scene before
+    ///
+    pub fn set_predict_overrides(
+        &self,
+        scene_id: u64,
+        overrides: Option<PredictOverrides>,
+    ) -> Option<PredictOverrides> {
+        let overrides_by_scene = &mut self.scenes.write().unwrap().predict_overrides;
+        match overrides {
+            Some(overrides) => overrides_by_scene.insert(scene_id, overrides),
+            None => overrides_by_scene.remove(&scene_id),
+        }
+    }
+
+    /// Returns the overrides set for the scenes
+    ///
+    pub fn predict_overrides_by_scene(&self) -> HashMap<u64, PredictOverrides> {
+        self.scenes.read().unwrap().predict_overrides.clone()
+    }
+
+    /// Returns the overrides of the configuration for the predictions of the scene, the visual
+    /// distances are disabled while the scene exceeds the [latency budget](Self::latency_budget)
+    ///
+    pub fn predict_overrides(&self, scene_id: u64) -> PredictOverrides {
+        let mut overrides = self
+            .scenes
+            .read()
+            .unwrap()
+            .predict_overrides
+            .get(&scene_id)
+            .copied()
+            .unwrap_or_default();
+        overrides.disable_visual |= self.latency_degraded(scene_id);
+        overrides
+    }
+
+    /// Enables the storage of the observed and the predicted boxes history with half precision.
+    /// The last boxes of the tracks are always kept with full precision.
+    ///
+    /// The preceding boxes are moved from `observed_boxes` and `predicted_boxes` of the track
+    /// attributes to their compact counterparts, so the full history must be read with
+    /// `observed_boxes_history` and `predicted_boxes_history`; the wasted tracks report the full
+    /// history. Look at [CompactUniversal2DBox](crate::utils::bbox::compact::CompactUniversal2DBox)
+    /// for the precision and the range of the stored values.
+    ///
+    #[cfg(feature = "f16")]
+    pub fn with_compact_box_history(self, enabled: bool) -> Self {
+        self.set_compact_box_history(enabled);
+        self
+    }
+
+    /// Enables or disables the storage of the box history with half precision for the
+    /// boxes collected from now on
+    ///
+    #[cfg(feature = "f16")]
+    pub fn set_compact_box_history(&self, enabled: bool) {
+        self.compact_box_history.store(enabled, Ordering::Relaxed);
     }
 
-    fn max_idle_epochs(&self) -> usize {
-        self.max_idle_epochs
+    #[cfg(feature = "f16")]
+    pub fn compact_box_history(&self) -> bool {
+        self.compact_box_history.load(Ordering::Relaxed)
     }
-}
 
-impl SortAttributesOptions {
-    pub fn new(
-        epoch_db: Option<RwLock<HashMap<u64, usize>>>,
-        max_idle_epochs: usize,
-        history_length: usize,
-        spatio_temporal_constraints: SpatioTemporalConstraints,
-        position_weight: f32,
-        velocity_weight: f32,
-    ) -> Self {
-        Self {
-            epoch_db,
-            max_idle_epochs,
-            history_length,
-            spatio_temporal_constraints,
-            position_weight,
-            velocity_weight,
+    /// Checks if the track wasn't updated during the last epoch and was lastly seen in the exit zone
+    ///
+    #[cfg(feature = "clipping")]
+    pub fn vanished_at_exit(
+        &self,
+        scene_id: u64,
+        last_updated: usize,
+        last_bbox: Option<&Universal2DBox>,
+    ) -> bool {
+        match (self.current_epoch_with_scene(scene_id), last_bbox) {
+            (Some(current_epoch), Some(bbox)) if last_updated < current_epoch => {
+                self.scenes.read().unwrap().zones.is_exit(scene_id, bbox)
+            }
+            _ => false,
         }
     }
+
+    /// Checks if the track wasn't updated during the last epoch and was lastly seen in the exit
+    /// zone, the scenes have no exit zones without the `clipping` feature
+    ///
+    #[cfg(not(feature = "clipping"))]
+    pub fn vanished_at_exit(
+        &self,
+        _scene_id: u64,
+        _last_updated: usize,
+        _last_bbox: Option<&Universal2DBox>,
+    ) -> bool {
+        false
+    }
 }
 
 /// Attributes associated with SORT track
 ///
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SortAttributes {
     /// The lastly predicted boxes
+    ///
+    /// With the compact box history only the latest box is kept here, the preceding ones are
+    /// kept in `compact_predicted_boxes`, the full history is returned by
+    /// [predicted_boxes_history](Self::predicted_boxes_history)
+    ///
     pub predicted_boxes: VecDeque<Universal2DBox>,
     /// The lastly observed boxes
+    ///
+    /// With the compact box history only the latest box is kept here, the preceding ones are
+    /// kept in `compact_observed_boxes`, the full history is returned by
+    /// [observed_boxes_history](Self::observed_boxes_history)
+    ///
     pub observed_boxes: VecDeque<Universal2DBox>,
+    /// The predicted boxes preceding `predicted_boxes`, kept with half precision
+    #[cfg(feature = "f16")]
+    pub compact_predicted_boxes: VecDeque<CompactUniversal2DBox>,
+    /// The observed boxes preceding `observed_boxes`, kept with half precision
+    #[cfg(feature = "f16")]
+    pub compact_observed_boxes: VecDeque<CompactUniversal2DBox>,
+    /// The epochs when the lastly observed boxes were collected
+    pub observed_epochs: VecDeque<usize>,
+    /// The wall-clock times (milliseconds since UNIX epoch) when the lastly observed boxes were
+    /// collected, recorded only when [observation_timestamps](SortAttributesOptions::observation_timestamps)
+    /// is enabled, so they are aligned with the latest `observed_epochs`
+    pub observed_timestamps: VecDeque<u64>,
     /// The epoch when the track was lastly updated
     pub last_updated_epoch: usize,
+    /// The epoch when the track was created
+    pub created_epoch: usize,
+    /// The wall-clock time (milliseconds since UNIX epoch) when the track was created
+    pub created_timestamp: u64,
+    /// The epoch when the track was wasted, `None` while the track is alive
+    pub wasted_epoch: Option<usize>,
+    /// The wall-clock time (milliseconds since UNIX epoch) when the track was wasted
+    pub wasted_timestamp: Option<u64>,
     /// The length of the track
     pub track_length: usize,
     /// Customer-specific scene identifier that splits the objects by classes, realms, etc.
     pub scene_id: u64,
     /// Custom object id
     pub custom_object_id: Option<i64>,
+    /// The number of observations the track must collect before it is reported
+    pub required_track_length: usize,
+    /// The class labels of the observations
+    pub class_votes: ClassVotes,
+    /// The user metadata attached to the observations
+    pub metadata: Option<Value>,
+    /// The epoch the Kalman state was propagated to over the skipped epochs
+    #[serde(default)]
+    pub propagated_epoch: usize,
+    /// The last known distance from the observer to the object
+    #[serde(default)]
+    pub depth: Option<f32>,
+    /// The overrides of the prediction the candidate is built for, layered over the overrides of
+    /// the scene when the candidate is compared with the tracks
+    #[serde(skip)]
+    pub(crate) call_overrides: Option<PredictOverrides>,
 
     /// Kalman filter predicted state
     state: Option<KalmanState<{ DIM_2D_BOX_X2 }>>,
+    #[serde(skip)]
     opts: Arc<SortAttributesOptions>,
 }
 
@@ -118,11 +1052,29 @@ impl TrackAttributesKalmanPrediction for SortAttributes {
     }
 
     fn get_position_weight(&self) -> f32 {
-        self.opts.position_weight
+        self.opts.kalman_weights(self.scene_id).0
     }
 
     fn get_velocity_weight(&self) -> f32 {
-        self.opts.velocity_weight
+        self.opts.kalman_weights(self.scene_id).1
+    }
+
+    fn report_innovation(
+        &self,
+        prediction: &KalmanState<{ DIM_2D_BOX_X2 }>,
+        update: &KalmanState<{ DIM_2D_BOX_X2 }>,
+        observation_bbox: &Universal2DBox,
+    ) {
+        self.opts
+            .record_innovation(self.scene_id, prediction, update, observation_bbox);
+    }
+
+    fn state_frame(&self) -> Option<(f32, f32)> {
+        self.opts.state_frame(self.scene_id)
+    }
+
+    fn motion_model(&self) -> MotionModel {
+        self.opts.motion_model()
     }
 }
 
@@ -131,11 +1083,27 @@ impl Default for SortAttributes {
         Self {
             predicted_boxes: VecDeque::default(),
             observed_boxes: VecDeque::default(),
+            #[cfg(feature = "f16")]
+            compact_predicted_boxes: VecDeque::default(),
+            #[cfg(feature = "f16")]
+            compact_observed_boxes: VecDeque::default(),
+            observed_epochs: VecDeque::default(),
+            observed_timestamps: VecDeque::default(),
             last_updated_epoch: 0,
+            created_epoch: 0,
+            created_timestamp: 0,
+            wasted_epoch: None,
+            wasted_timestamp: None,
             track_length: 0,
             scene_id: 0,
             state: None,
             custom_object_id: None,
+            required_track_length: 0,
+            class_votes: ClassVotes::default(),
+            metadata: None,
+            propagated_epoch: 0,
+            depth: None,
+            call_overrides: None,
             opts: Arc::new(SortAttributesOptions::default()),
         }
     }
@@ -154,21 +1122,130 @@ impl SortAttributes {
         }
     }
 
+    /// Attaches the attributes restored from the state to the options of the tracker
+    ///
+    pub(crate) fn set_options(&mut self, opts: Arc<SortAttributesOptions>) {
+        self.opts = opts;
+    }
+
     fn update_history(
         &mut self,
         observation_bbox: &Universal2DBox,
         predicted_bbox: &Universal2DBox,
     ) {
+        let timestamp = self.opts.observation_timestamps().then(wall_clock_ms);
+        if self.track_length == 0 {
+            self.created_epoch = self.last_updated_epoch;
+            self.created_timestamp = timestamp.unwrap_or_else(wall_clock_ms);
+            self.required_track_length = self
+                .opts
+                .required_track_length(self.scene_id, observation_bbox);
+        }
         self.track_length += 1;
 
         self.observed_boxes.push_back(observation_bbox.clone());
         self.predicted_boxes.push_back(predicted_bbox.clone());
+        #[cfg(feature = "f16")]
+        if self.opts.compact_box_history() {
+            compact_history(&mut self.observed_boxes, &mut self.compact_observed_boxes);
+            compact_history(&mut self.predicted_boxes, &mut self.compact_predicted_boxes);
+        }
+        self.observed_epochs.push_back(self.last_updated_epoch);
+        self.observed_timestamps.extend(timestamp);
+
+        if self.opts.history_length > 0 && self.observed_epochs.len() > self.opts.history_length {
+            self.pop_oldest_boxes();
+            self.observed_epochs.pop_front();
+        }
+        if self.observed_timestamps.len() > self.observed_epochs.len() {
+            self.observed_timestamps.pop_front();
+        }
+    }
+
+    fn pop_oldest_boxes(&mut self) {
+        #[cfg(feature = "f16")]
+        if self.compact_observed_boxes.pop_front().is_some() {
+            self.compact_predicted_boxes.pop_front();
+            return;
+        }
+        self.observed_boxes.pop_front();
+        self.predicted_boxes.pop_front();
+    }
+
+    /// The observed boxes from the oldest to the latest, including the ones kept with half precision
+    ///
+    pub fn observed_boxes_history(&self) -> Vec<Universal2DBox> {
+        let history = self.observed_boxes.iter().cloned();
+        #[cfg(feature = "f16")]
+        let history = self
+            .compact_observed_boxes
+            .iter()
+            .map(Universal2DBox::from)
+            .chain(history);
+        history.collect()
+    }
+
+    /// The predicted boxes from the oldest to the latest, including the ones kept with half precision
+    ///
+    pub fn predicted_boxes_history(&self) -> Vec<Universal2DBox> {
+        let history = self.predicted_boxes.iter().cloned();
+        #[cfg(feature = "f16")]
+        let history = self
+            .compact_predicted_boxes
+            .iter()
+            .map(Universal2DBox::from)
+            .chain(history);
+        history.collect()
+    }
 
-        if self.opts.history_length > 0 && self.observed_boxes.len() > self.opts.history_length {
-            self.observed_boxes.pop_front();
-            self.predicted_boxes.pop_front();
+    /// Evaluates the waste triggers over the full box history of the track
+    ///
+    fn waste_triggered(&self) -> bool {
+        if !self.opts.has_waste_triggers() {
+            return false;
         }
+        let observed_boxes = self.observed_boxes_history();
+        let predicted_boxes = self.predicted_boxes_history();
+        self.opts.waste_triggered(&WasteContext {
+            scene_id: self.scene_id,
+            current_epoch: self.opts.current_epoch_with_scene(self.scene_id),
+            created_epoch: self.created_epoch,
+            last_updated_epoch: self.last_updated_epoch,
+            track_length: self.track_length,
+            custom_object_id: self.custom_object_id,
+            observed_boxes: &observed_boxes,
+            predicted_boxes: &predicted_boxes,
+            metadata: self.metadata.as_ref(),
+        })
+    }
+
+    /// The latest observed and predicted boxes reported for the track, clipped to the frame when
+    /// the frame clamping is enabled
+    ///
+    pub(crate) fn output_boxes(&self) -> (Universal2DBox, Universal2DBox, bool) {
+        let (observed, predicted) = self.last_boxes();
+        self.opts.clamp_output(self.scene_id, &observed, &predicted)
     }
+
+    /// The latest observed and predicted boxes, synthesized with the
+    /// [history fallback](SortAttributesOptions::history_fallback) when the history is empty
+    ///
+    pub(crate) fn last_boxes(&self) -> (Universal2DBox, Universal2DBox) {
+        let fallback = self.opts.history_fallback();
+        (
+            fallback.last_box(&self.observed_boxes, self.state),
+            fallback.last_box(&self.predicted_boxes, self.state),
+        )
+    }
+}
+
+/// Current wall-clock time in milliseconds since UNIX epoch
+///
+pub(crate) fn wall_clock_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
 }
 
 /// Update object for SortAttributes
@@ -178,6 +1255,10 @@ pub struct SortAttributesUpdate {
     epoch: usize,
     scene_id: u64,
     custom_object_id: Option<i64>,
+    class_id: Option<i64>,
+    metadata: Option<Value>,
+    depth: Option<f32>,
+    overrides: Option<PredictOverrides>,
 }
 
 /// Lookup object for SortAttributes
@@ -185,6 +1266,10 @@ pub struct SortAttributesUpdate {
 #[derive(Clone, Debug)]
 pub enum SortLookup {
     IdleLookup(u64),
+    /// All the tracks of the scene
+    SceneLookup(u64),
+    /// The tracks of the scene reached the lifetime limit
+    ExpiredLookup(u64),
 }
 
 impl LookupRequest<SortAttributes, Universal2DBox> for SortLookup {
@@ -203,6 +1288,13 @@ impl LookupRequest<SortAttributes, Universal2DBox> for SortLookup {
                             .current_epoch_with_scene(attributes.scene_id)
                             .unwrap()
             }
+            SortLookup::SceneLookup(scene_id) => *scene_id == attributes.scene_id,
+            SortLookup::ExpiredLookup(scene_id) => {
+                *scene_id == attributes.scene_id
+                    && attributes
+                        .opts
+                        .track_lifetime_expired(*scene_id, attributes.created_epoch)
+            }
         }
     }
 }
@@ -218,6 +1310,10 @@ impl SortAttributesUpdate {
             epoch,
             scene_id: 0,
             custom_object_id,
+            class_id: None,
+            metadata: None,
+            depth: None,
+            overrides: None,
         }
     }
     /// update epoch for a specific scene_id
@@ -230,8 +1326,40 @@ impl SortAttributesUpdate {
             epoch,
             scene_id,
             custom_object_id,
+            class_id: None,
+            metadata: None,
+            depth: None,
+            overrides: None,
         }
     }
+
+    /// Sets the class label of the observation
+    ///
+    pub fn with_class_id(mut self, class_id: Option<i64>) -> Self {
+        self.class_id = class_id;
+        self
+    }
+
+    /// Attaches the user metadata to the observation
+    ///
+    pub fn with_metadata(mut self, metadata: Option<Value>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Sets the distance from the observer to the object
+    ///
+    pub fn with_depth(mut self, depth: Option<f32>) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Attaches the overrides of the prediction the observation is passed to
+    ///
+    pub fn with_overrides(mut self, overrides: Option<PredictOverrides>) -> Self {
+        self.overrides = overrides;
+        self
+    }
 }
 
 impl TrackAttributesUpdate<SortAttributes> for SortAttributesUpdate {
@@ -239,6 +1367,14 @@ impl TrackAttributesUpdate<SortAttributes> for SortAttributesUpdate {
         attrs.last_updated_epoch = self.epoch;
         attrs.scene_id = self.scene_id;
         attrs.custom_object_id = self.custom_object_id;
+        if let Some(class_id) = self.class_id {
+            attrs.class_votes.vote(class_id, attrs.opts.class_freeze());
+        }
+        merge_metadata(&mut attrs.metadata, &self.metadata);
+        if self.depth.is_some() {
+            attrs.depth = self.depth;
+        }
+        attrs.call_overrides = self.overrides;
         Ok(())
     }
 }
@@ -249,17 +1385,20 @@ impl TrackAttributes<SortAttributes, Universal2DBox> for SortAttributes {
 
     fn compatible(&self, other: &SortAttributes) -> bool {
         if self.scene_id == other.scene_id {
-            let o1 = self.predicted_boxes.back().unwrap();
-            let o2 = other.predicted_boxes.back().unwrap();
+            let (_, o1) = self.last_boxes();
+            let (_, o2) = other.last_boxes();
 
             let epoch_delta = (self.last_updated_epoch as i128 - other.last_updated_epoch as i128)
                 .abs()
                 .try_into()
                 .unwrap();
 
-            let center_dist = Universal2DBox::dist_in_2r(o1, o2);
+            let center_dist = Universal2DBox::dist_in_2r(&o1, &o2);
 
             self.opts.max_idle_epochs() >= epoch_delta
+                && !self
+                    .opts
+                    .lifetime_expired(other.created_epoch, self.last_updated_epoch)
                 && self
                     .opts
                     .spatio_temporal_constraints
@@ -272,12 +1411,125 @@ impl TrackAttributes<SortAttributes, Universal2DBox> for SortAttributes {
     fn merge(&mut self, other: &SortAttributes) -> Result<()> {
         self.last_updated_epoch = other.last_updated_epoch;
         self.custom_object_id = other.custom_object_id;
+        self.created_epoch = self.created_epoch.min(other.created_epoch);
+        self.created_timestamp = self.created_timestamp.min(other.created_timestamp);
+        self.class_votes
+            .merge(&other.class_votes, self.opts.class_freeze());
+        merge_metadata(&mut self.metadata, &other.metadata);
+        if other.depth.is_some() {
+            self.depth = other.depth;
+        }
         Ok(())
     }
 
     fn baked(&self, _observations: &ObservationsDb<Universal2DBox>) -> Result<TrackStatus> {
+        if self.opts.vanished_at_exit(
+            self.scene_id,
+            self.last_updated_epoch,
+            self.observed_boxes.back(),
+        ) || self
+            .opts
+            .track_lifetime_expired(self.scene_id, self.created_epoch)
+            || self.waste_triggered()
+        {
+            return Ok(TrackStatus::Wasted);
+        }
         self.opts.baked(self.scene_id, self.last_updated_epoch)
     }
+
+    /// The state is propagated to the epoch preceding `epoch` only: the prediction of `epoch`
+    /// itself is made when the track is matched, so the continuous epochs propagate nothing
+    ///
+    fn on_epoch(&mut self, epoch: usize, scene_id: u64) {
+        let last_epoch = self.last_updated_epoch.max(self.propagated_epoch);
+        if scene_id == self.scene_id
+            && epoch > last_epoch + 1
+            && self.opts.skipped_epochs_prediction()
+        {
+            self.propagate_state(epoch - 1 - last_epoch);
+            self.propagated_epoch = epoch - 1;
+        }
+    }
+
+    fn on_wasted(&mut self) {
+        self.wasted_epoch = self.opts.current_epoch_with_scene(self.scene_id);
+        self.wasted_timestamp = Some(wall_clock_ms());
+    }
+
+    fn scene_calibration(&self) -> Option<Arc<SceneCalibration>> {
+        self.opts.scene_calibration(self.scene_id)
+    }
+
+    /// Both tracks inherit the box history, the counters, the class votes, the metadata and the
+    /// Kalman state, so they continue from the same past. The custom object id stays with the
+    /// original track.
+    ///
+    fn split(&mut self) -> Result<Self> {
+        let mut forked = self.clone();
+        forked.custom_object_id = None;
+        forked.wasted_epoch = None;
+        forked.wasted_timestamp = None;
+        Ok(forked)
+    }
+
+    fn schema_name() -> &'static str {
+        "SortAttributes"
+    }
+}
+
+/// Observation passed to the SORT tracker
+///
+#[derive(Debug, Clone)]
+pub struct SortObservation {
+    bounding_box: Universal2DBox,
+    custom_object_id: Option<i64>,
+    class_id: Option<i64>,
+    metadata: Option<Value>,
+    depth: Option<f32>,
+    ignored: bool,
+}
+
+impl SortObservation {
+    pub fn new(bounding_box: Universal2DBox, custom_object_id: Option<i64>) -> Self {
+        Self {
+            bounding_box,
+            custom_object_id,
+            class_id: None,
+            metadata: None,
+            depth: None,
+            ignored: false,
+        }
+    }
+
+    /// Sets the distance from the observer to the object (from the stereo or monodepth model)
+    ///
+    pub fn with_depth(mut self, depth: f32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Sets the class label of the object, the majority class of the track is reported
+    ///
+    pub fn with_class_id(mut self, class_id: i64) -> Self {
+        self.class_id = Some(class_id);
+        self
+    }
+
+    /// Attaches the user metadata to the observation, it is reported with the track
+    ///
+    pub fn with_metadata(mut self, metadata: Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Marks the observation as ignored, e.g. the detection inside the ignore zone of the scene.
+    /// The ignored observation neither creates the track nor updates the existing one, however
+    /// it is accounted by the scene density and the occlusion estimation.
+    ///
+    pub fn with_ignored(mut self, ignored: bool) -> Self {
+        self.ignored = ignored;
+        self
+    }
 }
 
 /// Online track structure that contains tracking information for the last tracker epoch
@@ -308,6 +1560,25 @@ pub struct SortTrack {
     /// custom object id passed by the user to find the track easily
     ///
     pub custom_object_id: Option<i64>,
+    /// the track appeared outside of the scene entry zones and has not collected enough
+    /// observations yet, such tracks are not reported
+    ///
+    pub tentative: bool,
+    /// the majority class of the observations, `None` if the observations are not labeled
+    ///
+    pub class: Option<TrackClass>,
+    /// the user metadata attached to the observations of the track
+    ///
+    pub metadata: Option<Value>,
+    /// the epoch when the track was created
+    ///
+    pub created_epoch: usize,
+    /// the wall-clock time (milliseconds since UNIX epoch) when the track was created
+    ///
+    pub created_timestamp: u64,
+    /// the observed or the predicted box was clipped to the frame of the scene
+    ///
+    pub clipped: bool,
 }
 
 /// Online track structure that contains tracking information for the last tracker epoch
@@ -338,53 +1609,159 @@ pub struct WastedSortTrack {
     /// history of observed boxes
     ///
     pub observed_boxes: Vec<Universal2DBox>,
+    /// epochs when the observed boxes were collected
+    ///
+    pub observed_epochs: Vec<usize>,
+    /// wall-clock times (milliseconds since UNIX epoch) when the latest observed boxes were
+    /// collected, empty unless the observation timestamps are enabled
+    ///
+    pub observed_timestamps: Vec<u64>,
+    /// the user metadata attached to the observations of the track
+    ///
+    pub metadata: Option<Value>,
+    /// the epoch when the track was created
+    ///
+    pub created_epoch: usize,
+    /// the wall-clock time (milliseconds since UNIX epoch) when the track was created
+    ///
+    pub created_timestamp: u64,
+    /// the epoch when the track was wasted
+    ///
+    pub wasted_epoch: Option<usize>,
+    /// the wall-clock time (milliseconds since UNIX epoch) when the track was wasted
+    ///
+    pub wasted_timestamp: Option<u64>,
 }
 
-impl From<Track<SortAttributes, SortMetric, Universal2DBox>> for WastedSortTrack {
-    fn from(track: Track<SortAttributes, SortMetric, Universal2DBox>) -> Self {
+impl<N: ChangeNotifier> From<Track<SortAttributes, SortMetric, Universal2DBox, N>>
+    for WastedSortTrack
+{
+    fn from(track: Track<SortAttributes, SortMetric, Universal2DBox, N>) -> Self {
         let attrs = track.get_attributes();
+        let (observed_bbox, predicted_bbox) = attrs.last_boxes();
         WastedSortTrack {
             id: track.get_track_id(),
             epoch: attrs.last_updated_epoch,
             scene_id: attrs.scene_id,
             length: attrs.track_length,
-            observed_bbox: attrs.observed_boxes.back().unwrap().clone(),
-            predicted_bbox: attrs.predicted_boxes.back().unwrap().clone(),
-            predicted_boxes: attrs.predicted_boxes.clone().into_iter().collect(),
-            observed_boxes: attrs.observed_boxes.clone().into_iter().collect(),
+            observed_bbox,
+            predicted_bbox,
+            predicted_boxes: attrs.predicted_boxes_history(),
+            observed_boxes: attrs.observed_boxes_history(),
+            observed_epochs: attrs.observed_epochs.clone().into_iter().collect(),
+            observed_timestamps: attrs.observed_timestamps.clone().into_iter().collect(),
+            metadata: attrs.metadata.clone(),
+            created_epoch: attrs.created_epoch,
+            created_timestamp: attrs.created_timestamp,
+            wasted_epoch: attrs.wasted_epoch,
+            wasted_timestamp: attrs.wasted_timestamp,
         }
     }
 }
 
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum VotingType {
     #[default]
     Visual,
     Positional,
 }
 
-#[derive(Clone, Default, Copy, Debug)]
+#[derive(Clone, Default, Copy, Debug, Serialize, Deserialize)]
 pub enum PositionalMetricType {
     #[default]
     Mahalanobis,
     IoU(f32),
 }
 
+impl PositionalMetricType {
+    /// Converts the positional metric value to the distance: Mahalanobis cost is the distance
+    /// itself, IoU is converted to `1 - IoU`
+    ///
+    pub fn metric_to_distance(&self, metric: f32) -> f32 {
+        match self {
+            PositionalMetricType::Mahalanobis => metric,
+            PositionalMetricType::IoU(_) => 1.0 - metric,
+        }
+    }
+}
+
+/// The periodic job moving the wasted tracks to the wasted store.
+///
+/// Every scene counts its own predictions, so the sweep of the scene happens every `periodicity`
+/// predictions of the scene and checks the tracks of the scene only. The scenes due within the
+/// same batch are swept within a single pass over the store.
+///
 pub struct AutoWaste {
     pub periodicity: usize,
+    /// The countdown of the default scene (`scene_id == 0`), kept for the readers only, the
+    /// changes are ignored
+    #[deprecated(note = "the countdowns are kept per scene, use `countdown(scene_id)`")]
     pub counter: usize,
+    /// The countdowns of the scenes
+    pub counters: HashMap<u64, usize>,
+}
+
+#[allow(deprecated)]
+impl AutoWaste {
+    pub fn new(periodicity: usize) -> Self {
+        Self {
+            periodicity,
+            counter: periodicity,
+            counters: HashMap::default(),
+        }
+    }
+
+    /// The number of the predictions of the scene left before its sweep
+    ///
+    pub fn countdown(&self, scene_id: u64) -> usize {
+        self.counters
+            .get(&scene_id)
+            .copied()
+            .unwrap_or(self.periodicity)
+    }
+
+    /// Changes the periodicity, the scenes already seen are swept on their next predictions
+    ///
+    pub fn restart(&mut self, periodicity: usize) {
+        self.periodicity = periodicity;
+        self.counters.values_mut().for_each(|c| *c = 0);
+        self.counter = self.countdown(0);
+    }
+
+    /// Counts the prediction of the scene
+    ///
+    /// # Returns
+    /// `true` when the sweep of the scene is due
+    ///
+    pub fn tick(&mut self, scene_id: u64) -> bool {
+        let counter = self.counters.entry(scene_id).or_insert(self.periodicity);
+        let due = if *counter == 0 {
+            *counter = self.periodicity;
+            true
+        } else {
+            *counter -= 1;
+            false
+        };
+        self.counter = self.countdown(0);
+        due
+    }
 }
 
 pub(crate) const DEFAULT_AUTO_WASTE_PERIODICITY: usize = 100;
+/// The voting threshold of the legacy Mahalanobis cost: any pair within the 0.95 chi-square gate
+/// is associated, since its cost `(CHI2_UPPER_BOUND - distance) / confidence` exceeds it
+///
 pub(crate) const MAHALANOBIS_NEW_TRACK_THRESHOLD: f32 = 1.0;
 
 #[cfg(feature = "python")]
 pub mod python {
     use pyo3::prelude::*;
 
+    use crate::trackers::metadata::python::to_py;
     use crate::utils::bbox::python::PyUniversal2DBox;
 
-    use super::{PositionalMetricType, SortTrack, VotingType, WastedSortTrack};
+    use super::{PositionalMetricType, SortObservation, SortTrack, VotingType, WastedSortTrack};
+    use crate::trackers::metadata::python::from_py;
 
     #[pyclass]
     #[pyo3(name = "PositionalMetricType")]
@@ -420,6 +1797,46 @@ pub mod python {
         }
     }
 
+    #[pyclass]
+    #[pyo3(name = "SortObservation")]
+    #[derive(Debug, Clone)]
+    pub struct PySortObservation(pub(crate) SortObservation);
+
+    #[pymethods]
+    impl PySortObservation {
+        #[new]
+        #[pyo3(signature = (bounding_box, custom_object_id = None, class_id = None, metadata = None, ignored = false, depth = None))]
+        pub fn new(
+            py: Python,
+            bounding_box: PyUniversal2DBox,
+            custom_object_id: Option<i64>,
+            class_id: Option<i64>,
+            metadata: Option<&PyAny>,
+            ignored: bool,
+            depth: Option<f32>,
+        ) -> PyResult<Self> {
+            Ok(Self(SortObservation {
+                bounding_box: bounding_box.0,
+                custom_object_id,
+                class_id,
+                metadata: metadata.map(|m| from_py(py, m)).transpose()?,
+                depth,
+                ignored,
+            }))
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{self:?}")
+        }
+
+        fn __str__(&self) -> String {
+            format!("{self:#?}")
+        }
+    }
+
     #[pyclass]
     #[pyo3(name = "SortTrack")]
     #[derive(Debug, Clone)]
@@ -478,6 +1895,41 @@ pub mod python {
         fn get_custom_object_id(&self) -> Option<i64> {
             self.0.custom_object_id
         }
+
+        #[getter]
+        fn get_class_id(&self) -> Option<i64> {
+            self.0.class.map(|c| c.class_id)
+        }
+
+        #[getter]
+        fn get_class_stability(&self) -> Option<f32> {
+            self.0.class.map(|c| c.stability)
+        }
+
+        #[getter]
+        fn get_class_frozen(&self) -> bool {
+            self.0.class.map(|c| c.frozen).unwrap_or(false)
+        }
+
+        #[getter]
+        fn get_metadata(&self, py: Python) -> PyResult<PyObject> {
+            to_py(py, &self.0.metadata)
+        }
+
+        #[getter]
+        fn get_created_epoch(&self) -> usize {
+            self.0.created_epoch
+        }
+
+        #[getter]
+        fn get_created_timestamp(&self) -> u64 {
+            self.0.created_timestamp
+        }
+
+        #[getter]
+        fn get_clipped(&self) -> bool {
+            self.0.clipped
+        }
     }
 
     #[pyclass]
@@ -538,6 +1990,41 @@ pub mod python {
         fn observed_boxes(&self) -> Vec<PyUniversal2DBox> {
             unsafe { std::mem::transmute(self.0.observed_boxes.clone()) }
         }
+
+        #[getter]
+        fn observed_epochs(&self) -> Vec<usize> {
+            self.0.observed_epochs.clone()
+        }
+
+        #[getter]
+        fn observed_timestamps(&self) -> Vec<u64> {
+            self.0.observed_timestamps.clone()
+        }
+
+        #[getter]
+        fn metadata(&self, py: Python) -> PyResult<PyObject> {
+            to_py(py, &self.0.metadata)
+        }
+
+        #[getter]
+        fn created_epoch(&self) -> usize {
+            self.0.created_epoch
+        }
+
+        #[getter]
+        fn created_timestamp(&self) -> u64 {
+            self.0.created_timestamp
+        }
+
+        #[getter]
+        fn wasted_epoch(&self) -> Option<usize> {
+            self.0.wasted_epoch
+        }
+
+        #[getter]
+        fn wasted_timestamp(&self) -> Option<u64> {
+            self.0.wasted_timestamp
+        }
     }
 
     #[pyclass]
@@ -563,11 +2050,16 @@ pub mod python {
 #[cfg(test)]
 mod track_tests {
     use crate::prelude::{NoopNotifier, ObservationBuilder, TrackBuilder};
+    use crate::track::TrackAttributes;
+    use crate::trackers::history_fallback::HistoryFallback;
     use crate::trackers::sort::metric::{SortMetric, DEFAULT_MINIMAL_SORT_CONFIDENCE};
     use crate::trackers::sort::PositionalMetricType::IoU;
-    use crate::trackers::sort::{SortAttributes, DEFAULT_SORT_IOU_THRESHOLD};
-    use crate::utils::bbox::BoundingBox;
+    use crate::trackers::sort::{
+        SortAttributes, SortTrack, WastedSortTrack, DEFAULT_SORT_IOU_THRESHOLD,
+    };
+    use crate::utils::bbox::{BoundingBox, Universal2DBox};
     use crate::utils::kalman::kalman_2d_box::Universal2DBoxKalmanFilter;
+    use serde_json::json;
 
     #[test]
     fn construct() {
@@ -627,5 +2119,80 @@ mod track_tests {
         assert!(t1.get_attributes().state.is_some());
         assert_eq!(t1.get_attributes().predicted_boxes.len(), 2);
         assert_eq!(t1.get_attributes().observed_boxes.len(), 2);
+        assert_eq!(t1.get_attributes().observed_epochs.len(), 2);
+        // the observation timestamps are not recorded by default
+        assert!(t1.get_attributes().observed_timestamps.is_empty());
+    }
+
+    #[test]
+    fn split() {
+        let track = TrackBuilder::new(1)
+            .attributes(SortAttributes::default())
+            .metric(SortMetric::new(
+                IoU(DEFAULT_SORT_IOU_THRESHOLD),
+                DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            ))
+            .notifier(NoopNotifier)
+            .observation(
+                ObservationBuilder::new(0)
+                    .observation_attributes(BoundingBox::new(1.0, 1.0, 10.0, 15.0).into())
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        let mut attributes = track.get_attributes().clone();
+        attributes.custom_object_id = Some(7);
+        attributes
+            .class_votes
+            .vote(3, attributes.opts.class_freeze());
+        attributes.metadata = Some(json!({"zone": "entry"}));
+        attributes.wasted_epoch = Some(4);
+
+        let forked = attributes.split().unwrap();
+        assert_eq!(forked.predicted_boxes, attributes.predicted_boxes);
+        assert_eq!(forked.observed_boxes, attributes.observed_boxes);
+        assert_eq!(forked.observed_epochs, attributes.observed_epochs);
+        assert_eq!(forked.track_length, attributes.track_length);
+        assert_eq!(
+            forked.state.unwrap().mean(),
+            attributes.state.unwrap().mean()
+        );
+        assert_eq!(forked.class_votes.majority().map(|c| c.class_id), Some(3));
+        assert_eq!(forked.metadata, attributes.metadata);
+        assert_eq!(forked.custom_object_id, None);
+        assert_eq!(forked.wasted_epoch, None);
+        assert_eq!(attributes.custom_object_id, Some(7));
+        assert_eq!(attributes.wasted_epoch, Some(4));
+    }
+
+    #[test]
+    fn exhausted_history() {
+        let bb: Universal2DBox = BoundingBox::new(1.0, 1.0, 10.0, 15.0).into();
+        let mut attrs = SortAttributes::default();
+        attrs.state = Some(Universal2DBoxKalmanFilter::default().initiate(&bb));
+
+        let track = TrackBuilder::new(1)
+            .attributes(attrs)
+            .metric(SortMetric::new(
+                IoU(DEFAULT_SORT_IOU_THRESHOLD),
+                DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            ))
+            .notifier(NoopNotifier)
+            .build()
+            .unwrap();
+        assert!(track.get_attributes().observed_boxes.is_empty());
+
+        let sort_track = SortTrack::from(&track);
+        assert_eq!(sort_track.observed_bbox.xc, bb.xc);
+        assert_eq!(sort_track.predicted_bbox.height, bb.height);
+        assert_eq!(sort_track.predicted_bbox.confidence, 0.0);
+
+        track
+            .get_attributes()
+            .opts
+            .set_history_fallback(HistoryFallback::EmptyBox);
+        let wasted = WastedSortTrack::from(track);
+        assert_eq!(wasted.observed_bbox.height, 0.0);
+        assert_eq!(wasted.predicted_bbox.xc, 0.0);
     }
 }
@@ -4,19 +4,43 @@ use crate::prelude::{
 };
 use crate::store::track_distance::TrackDistanceOkIterator;
 use crate::store::TrackStore;
+use crate::track::calibration::SceneCalibration;
 use crate::track::Track;
-use crate::trackers::batch::{PredictionBatchRequest, PredictionBatchResult, SceneTracks};
+use crate::trackers::batch::{
+    join_voting_threads, wait_batch_idle, wait_batch_processed, BatchShutdownReport,
+    PredictionBatchRequest, PredictionBatchResult, SceneAssignedTracks,
+};
+use crate::trackers::candidate_assignment::CandidateAssignment;
+use crate::trackers::candidate_clustering::CandidateClustering;
+use crate::trackers::density::DensityOptions;
+use crate::trackers::duplicate_ids::DuplicateObjectIdPolicy;
 use crate::trackers::epoch_db::EpochDb;
+use crate::trackers::frame_clamping::FrameClamping;
+use crate::trackers::history_fallback::HistoryFallback;
+use crate::trackers::motion_model::MotionModel;
+use crate::trackers::noise_model::NoiseModelOptions;
+use crate::trackers::observation_intake::ObservationIntake;
+use crate::trackers::observation_pipeline::ObservationPipeline;
+use crate::trackers::output_filter::TrackOutputFilter;
+use crate::trackers::predict_overrides::PredictOverrides;
+use crate::trackers::quality::QualityOptions;
+#[cfg(feature = "clipping")]
+use crate::trackers::scene_zones::SceneZones;
 use crate::trackers::sort::metric::SortMetric;
 use crate::trackers::sort::voting::SortVoting;
 use crate::trackers::sort::{
     AutoWaste, SortAttributes, SortAttributesOptions, SortAttributesUpdate, SortLookup,
-    DEFAULT_AUTO_WASTE_PERIODICITY, MAHALANOBIS_NEW_TRACK_THRESHOLD,
+    DEFAULT_AUTO_WASTE_PERIODICITY,
 };
 
 use crate::trackers::spatio_temporal_constraints::SpatioTemporalConstraints;
 use crate::trackers::tracker_api::TrackerAPI;
+use crate::trackers::waste_triggers::WasteTriggers;
+use crate::trackers::watchdog::{SharedWatchdog, Stall, Watchdog, WatchdogOptions, Worker};
+use crate::utils::kalman::gating::MahalanobisGating;
 use crate::voting::Voting;
+use crate::Errors;
+use anyhow::Result;
 use crossbeam::channel::{Receiver, Sender};
 use log::warn;
 use rand::Rng;
@@ -24,6 +48,7 @@ use std::collections::HashMap;
 use std::mem;
 use std::sync::{Arc, Condvar, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::thread::{spawn, JoinHandle};
+use std::time::{Duration, Instant};
 
 type VotingSenderChannel = Sender<VotingCommands>;
 type VotingReceiverChannel = Receiver<VotingCommands>;
@@ -36,9 +61,12 @@ enum VotingCommands {
     Distances {
         scene_id: u64,
         distances: TrackDistanceOkIterator<Universal2DBox>,
-        channel: Sender<SceneTracks>,
+        channel: Sender<SceneAssignedTracks>,
         tracks: Vec<MiddlewareSortTrack>,
+        indices: Vec<usize>,
+        observations: Vec<(Universal2DBox, Option<i64>)>,
         monitor: BatchBusyMonitor,
+        started: Instant,
     },
     Exit,
 }
@@ -48,8 +76,13 @@ pub struct BatchSort {
     store: Arc<RwLock<MiddlewareSortTrackStore>>,
     wasted_store: RwLock<MiddlewareSortTrackStore>,
     opts: Arc<SortAttributesOptions>,
+    intake: ObservationIntake,
     voting_threads: Vec<(VotingSenderChannel, JoinHandle<()>)>,
+    voting_queues: Vec<VotingReceiverChannel>,
+    voting_finished: Receiver<()>,
     auto_waste: AutoWaste,
+    watchdog: SharedWatchdog,
+    shut_down: bool,
 }
 
 impl Drop for BatchSort {
@@ -66,10 +99,12 @@ impl Drop for BatchSort {
 }
 
 fn voting_thread(
+    thread_id: usize,
     store: Arc<RwLock<MiddlewareSortTrackStore>>,
     rx: VotingReceiverChannel,
     method: PositionalMetricType,
-    track_id: Arc<RwLock<u64>>,
+    opts: Arc<SortAttributesOptions>,
+    watchdog: SharedWatchdog,
 ) {
     while let Ok(command) = rx.recv() {
         match command {
@@ -78,8 +113,12 @@ fn voting_thread(
                 distances,
                 channel,
                 tracks,
+                indices,
                 monitor,
+                started,
+                ..
             } => {
+                let _watch = watchdog.watch(Worker::Voting(thread_id));
                 let candidates_num = tracks.len();
                 let tracks_num = {
                     let store = store.read().expect("Access to store must always succeed");
@@ -87,24 +126,22 @@ fn voting_thread(
                 };
 
                 let voting = SortVoting::new(
-                    match method {
-                        PositionalMetricType::Mahalanobis => MAHALANOBIS_NEW_TRACK_THRESHOLD,
-                        PositionalMetricType::IoU(t) => t,
-                    },
+                    opts.positional_threshold(scene_id, method),
                     candidates_num,
                     tracks_num,
                 );
 
                 let winners = voting.winners(distances);
                 let mut res = Vec::default();
-                for mut t in tracks {
+                let mut assignments = Vec::default();
+                for (index, mut t) in indices.into_iter().zip(tracks) {
                     let source = t.get_track_id();
-                    let tid = {
-                        let mut track_id = track_id.write().unwrap();
-                        *track_id += 1;
-                        *track_id
-                    };
-                    let track_id: u64 = if let Some(dest) = winners.get(&source) {
+                    let tid = store
+                        .read()
+                        .expect("Access to store must always succeed")
+                        .reserve_track_ids(1)
+                        .start;
+                    let (track_id, new_track) = if let Some(dest) = winners.get(&source) {
                         let dest = dest[0];
                         if dest == source {
                             t.set_track_id(tid);
@@ -113,14 +150,14 @@ fn voting_thread(
                                 .expect("Access to store must always succeed")
                                 .add_track(t)
                                 .unwrap();
-                            tid
+                            (tid, true)
                         } else {
                             store
                                 .write()
                                 .expect("Access to store must always succeed")
-                                .merge_external(dest, &t, Some(&[0]), false)
+                                .merge_external_owned(dest, t, Some(&[0]), false)
                                 .unwrap();
-                            dest
+                            (dest, false)
                         }
                     } else {
                         t.set_track_id(tid);
@@ -129,8 +166,9 @@ fn voting_thread(
                             .expect("Access to store must always succeed")
                             .add_track(t)
                             .unwrap();
-                        tid
+                        (tid, true)
                     };
+                    assignments.push(CandidateAssignment::new(index, track_id, new_track));
 
                     let store = store.read().expect("Access to store must always succeed");
                     let shard = store.get_store(track_id as usize);
@@ -138,7 +176,10 @@ fn voting_thread(
 
                     res.push(SortTrack::from(track))
                 }
-                let res = channel.send((scene_id, res));
+                opts.record_quality(scene_id, &res);
+                let res = opts.filter_output(res);
+                opts.record_predict_latency(scene_id, started.elapsed(), rx.len());
+                let res = channel.send((scene_id, res, assignments));
                 if let Err(e) = res {
                     warn!("Unable to send results to a caller, likely the caller already closed the channel. Error is: {:?}", e);
                 }
@@ -192,16 +233,30 @@ impl BatchSort {
                 .build(),
         );
 
-        let track_id = Arc::new(RwLock::new(0));
-
+        let watchdog = SharedWatchdog::default();
+        let (finished, voting_finished) = crossbeam::channel::bounded(0);
+        let mut voting_queues = Vec::with_capacity(voting_shards);
         let voting_threads = (0..voting_shards)
-            .map(|_e| {
+            .map(|thread_id| {
                 let (tx, rx) = crossbeam::channel::unbounded();
+                voting_queues.push(rx.clone());
                 let thread_store = store.clone();
-                let thread_track_id = track_id.clone();
+                let thread_opts = opts.clone();
+                let thread_watchdog = watchdog.clone();
+                let thread_finished: Sender<()> = finished.clone();
                 (
                     tx,
-                    spawn(move || voting_thread(thread_store, rx, method, thread_track_id)),
+                    spawn(move || {
+                        let _finished = thread_finished;
+                        voting_thread(
+                            thread_id,
+                            thread_store,
+                            rx,
+                            method,
+                            thread_opts,
+                            thread_watchdog,
+                        )
+                    }),
                 )
             })
             .collect::<Vec<_>>();
@@ -211,29 +266,121 @@ impl BatchSort {
             store,
             wasted_store,
             opts,
+            intake: ObservationIntake::default(),
             voting_threads,
-            auto_waste: AutoWaste {
-                periodicity: DEFAULT_AUTO_WASTE_PERIODICITY,
-                counter: DEFAULT_AUTO_WASTE_PERIODICITY,
-            },
+            voting_queues,
+            voting_finished,
+            auto_waste: AutoWaste::new(DEFAULT_AUTO_WASTE_PERIODICITY),
+            watchdog,
+            shut_down: false,
         }
     }
 
+    /// Stops the tracker gracefully
+    ///
+    /// New batches are not accepted after the call. The in-flight batch is processed and its
+    /// results are sent until the deadline is reached, the scenes which processing is not started
+    /// by the deadline are returned, the voting threads are joined until the deadline and detached
+    /// if they don't stop in time.
+    ///
+    /// # Parameters
+    /// * `timeout` - the time given for the draining and the threads shutdown
+    ///
+    pub fn shutdown(
+        &mut self,
+        timeout: Duration,
+    ) -> BatchShutdownReport<(Universal2DBox, Option<i64>)> {
+        let deadline = Instant::now() + timeout;
+        self.shut_down = true;
+
+        if !wait_batch_processed(self.monitor.as_ref(), deadline) {
+            warn!("The batch is not processed before the shutdown deadline");
+        }
+
+        let mut unprocessed = Vec::default();
+        for rx in mem::take(&mut self.voting_queues) {
+            while let Ok(command) = rx.try_recv() {
+                if let VotingCommands::Distances {
+                    scene_id,
+                    observations,
+                    ..
+                } = command
+                {
+                    unprocessed.push((scene_id, observations));
+                }
+            }
+        }
+
+        let detached_threads = join_voting_threads(
+            mem::take(&mut self.voting_threads),
+            || VotingCommands::Exit,
+            &self.voting_finished,
+            deadline,
+        );
+        if detached_threads > 0 {
+            warn!(
+                "{} voting threads didn't stop before the shutdown deadline and were detached",
+                detached_threads
+            );
+        }
+
+        BatchShutdownReport {
+            unprocessed,
+            detached_threads,
+        }
+    }
+
+    /// Runs the prediction of the batch, waiting until the previous batch is processed
+    ///
+    /// The waiting is unbounded, the stalls detected by the [watchdog](Self::set_watchdog) are
+    /// only reported, use [try_predict](Self::try_predict) to abandon the batch on the stall
+    ///
+    /// # Panics
+    /// When the tracker is [shut down](Self::shutdown)
+    ///
     pub fn predict(
         &mut self,
         batch_request: PredictionBatchRequest<(Universal2DBox, Option<i64>)>,
     ) {
-        if self.auto_waste.counter == 0 {
-            self.auto_waste();
-            self.auto_waste.counter = self.auto_waste.periodicity;
-        } else {
-            self.auto_waste.counter -= 1;
-        }
+        self.predict_watched(batch_request, None)
+            .expect("The batch must be accepted by the running tracker");
+    }
+
+    /// Runs the prediction of the batch
+    ///
+    /// # Returns
+    /// * [WorkerStalled](crate::Errors::WorkerStalled) error when the [watchdog](Self::set_watchdog)
+    ///   detects the stalled thread while the previous batch is processed, the batch is not
+    ///   started then
+    /// * [TrackerShutDown](crate::Errors::TrackerShutDown) error when the tracker is
+    ///   [shut down](Self::shutdown)
+    ///
+    pub fn try_predict(
+        &mut self,
+        batch_request: PredictionBatchRequest<(Universal2DBox, Option<i64>)>,
+    ) -> Result<()> {
+        let watchdog = self.watchdog.get();
+        self.predict_watched(batch_request, watchdog.as_deref())
+    }
 
-        if let Some(m) = &self.monitor {
-            let (lock, cvar) = &**m;
-            let _guard = cvar.wait_while(lock.lock().unwrap(), |v| *v > 0).unwrap();
+    fn predict_watched(
+        &mut self,
+        batch_request: PredictionBatchRequest<(Universal2DBox, Option<i64>)>,
+        watchdog: Option<&Watchdog>,
+    ) -> Result<()> {
+        if self.shut_down {
+            return Err(Errors::TrackerShutDown.into());
         }
+        let started = Instant::now();
+        let due = batch_request
+            .get_batch()
+            .keys()
+            .filter(|scene_id| self.auto_waste.tick(**scene_id))
+            .copied()
+            .collect::<Vec<_>>();
+        self.auto_waste_scenes(&due);
+
+        wait_batch_idle(self.monitor.as_ref(), watchdog)?;
 
         self.monitor = Some(Arc::new((
             Mutex::new(batch_request.batch_size()),
@@ -243,6 +390,25 @@ impl BatchSort {
         for (i, (scene_id, bboxes)) in batch_request.get_batch().iter().enumerate() {
             let mut rng = rand::thread_rng();
             let epoch = self.opts.next_epoch(*scene_id).unwrap();
+            self.notify_epoch(*scene_id, epoch);
+            self.waste_expired_scene(*scene_id);
+            if !bboxes.is_empty() {
+                self.opts.mark_scene_active(*scene_id);
+            }
+            let (indices, bboxes): (Vec<_>, Vec<_>) = self
+                .intake
+                .prepare(
+                    *scene_id,
+                    epoch,
+                    bboxes,
+                    |(bb, _)| bb,
+                    |(_, id), bb| (bb, *id),
+                    |(bb, id)| (*id, bb.confidence),
+                )
+                .into_iter()
+                .unzip();
+            self.opts
+                .record_density(*scene_id, bboxes.iter().map(|(bb, _)| bb));
 
             let tracks = bboxes
                 .iter()
@@ -267,6 +433,7 @@ impl BatchSort {
                 .collect::<Vec<_>>();
 
             let (dists, errs) = {
+                let _watch = self.watchdog.watch(Worker::Distances);
                 let mut store = self
                     .store
                     .write()
@@ -274,19 +441,178 @@ impl BatchSort {
                 store.foreign_track_distances(tracks.clone(), 0, false)
             };
 
-            assert!(errs.all().is_empty());
+            errs.log();
             let thread_id = i % self.voting_threads.len();
             self.voting_threads[thread_id]
                 .0
                 .send(VotingCommands::Distances {
                     monitor: self.monitor.as_ref().unwrap().clone(),
+                    started,
                     scene_id: *scene_id,
                     distances: dists.into_iter(),
                     channel: batch_request.get_sender(),
                     tracks,
+                    indices,
+                    observations: batch_request.get_batch()[scene_id].clone(),
                 })
                 .expect("Sending voting request to voting thread must not fail");
         }
+        Ok(())
+    }
+
+    /// Sets the filter that defines which tracks are returned by `predict` and `idle_tracks`
+    ///
+    pub fn set_output_filter(&mut self, filter: TrackOutputFilter) {
+        self.opts.set_output_filter(filter);
+    }
+
+    /// Sets the entry and exit zones of the scenes
+    ///
+    #[cfg(feature = "clipping")]
+    pub fn set_scene_zones(&mut self, zones: SceneZones) {
+        self.opts.set_scene_zones(zones);
+    }
+
+    /// Registers or removes the calibration of the scene, the calibration is available to the
+    /// metrics with `MetricQuery::scene_calibration`
+    ///
+    pub fn set_scene_calibration(&mut self, scene_id: u64, calibration: Option<SceneCalibration>) {
+        self.opts.set_scene_calibration(scene_id, calibration);
+    }
+
+    /// Sets the handling of the observations passed with the same custom_object_id within the epoch
+    ///
+    pub fn set_duplicate_object_id_policy(&mut self, policy: DuplicateObjectIdPolicy) {
+        self.intake.set_duplicate_object_id_policy(policy);
+    }
+
+    /// Enables or disables the clustering of the near-duplicate observations within the epoch
+    ///
+    pub fn set_candidate_clustering(&mut self, clustering: Option<CandidateClustering>) {
+        self.intake.set_candidate_clustering(clustering);
+    }
+
+    /// Sets the custom conditions (leaving the region of interest, low confidence, user
+    /// predicates) that waste the tracks of the scenes
+    ///
+    pub fn set_waste_triggers(&mut self, triggers: WasteTriggers) {
+        self.opts.set_waste_triggers(triggers);
+    }
+
+    /// Limits the lifetime of the tracks in epochs, the track is wasted when it reaches the limit
+    /// even if it is still matched, `None` removes the limit
+    ///
+    pub fn set_max_track_lifetime(&mut self, epochs: Option<usize>) {
+        self.opts.set_max_track_lifetime(epochs);
+    }
+
+    /// Sets the clamping of the reported boxes (and optionally of the Kalman state) to the frame,
+    /// the frame size is taken from the calibration of the scene
+    ///
+    pub fn set_frame_clamping(&mut self, clamping: FrameClamping) {
+        self.opts.set_frame_clamping(clamping);
+    }
+
+    /// Sets the boxes reported for the tracks which box history is empty, the boxes are built
+    /// from the Kalman state by default
+    ///
+    pub fn set_history_fallback(&mut self, fallback: HistoryFallback) {
+        self.opts.set_history_fallback(fallback);
+    }
+
+    /// Sets the motion model, with [MotionModel::None](MotionModel::None) the tracks are associated
+    /// on the observed boxes without the Kalman prediction and the Mahalanobis metric never matches
+    ///
+    pub fn set_motion_model(&mut self, model: MotionModel) {
+        self.opts.set_motion_model(model);
+    }
+
+    /// Enables or disables the watchdog that reports the distance computation and the voting
+    /// threads that made no progress for the stall timeout
+    ///
+    pub fn set_watchdog(&mut self, opts: Option<WatchdogOptions>) {
+        self.watchdog.set(opts);
+    }
+
+    /// Returns the options of the watchdog, `None` when the watchdog is disabled
+    ///
+    pub fn watchdog_options(&self) -> Option<WatchdogOptions> {
+        self.watchdog.options()
+    }
+
+    /// The number of the stalled threads detected by the watchdog
+    ///
+    pub fn watchdog_stalls(&self) -> usize {
+        self.watchdog.stalls()
+    }
+
+    /// The channel the stalls detected by the watchdog are sent to, `None` when the watchdog is
+    /// disabled
+    ///
+    pub fn subscribe_stalls(&self) -> Option<Receiver<Stall>> {
+        self.watchdog.subscribe()
+    }
+
+    /// Replaces the legacy Mahalanobis cost with the chi-square gated score, the pairs outside of
+    /// the gate are never associated, `None` restores the legacy cost
+    ///
+    pub fn set_mahalanobis_gating(&mut self, gating: Option<MahalanobisGating>) {
+        self.opts.set_mahalanobis_gating(gating);
+    }
+
+    /// Sets the transformers applied to the incoming observations before the candidate tracks
+    /// are created
+    ///
+    pub fn set_observation_pipeline(&mut self, pipeline: ObservationPipeline) {
+        self.intake.set_observation_pipeline(pipeline);
+    }
+
+    /// The number of duplicate custom_object_id found since the tracker creation
+    ///
+    pub fn duplicate_object_ids(&self) -> usize {
+        self.intake.duplicate_object_ids()
+    }
+
+    /// Sets the hard cut-off for the positional distance (the Mahalanobis cost or `1 - IoU`),
+    /// the pairs that are farther are never associated
+    ///
+    pub fn set_max_distance(&mut self, max_distance: Option<f32>) {
+        let mut store = self
+            .store
+            .write()
+            .expect("Access to store must always succeed");
+        let metric = store.metric().clone().with_max_distance(max_distance);
+        store.set_metric(metric);
+    }
+
+    /// Enables or disables the object density estimation and the congestion-aware association
+    ///
+    pub fn set_density_estimation(&mut self, opts: Option<DensityOptions>) {
+        self.opts.set_density_estimation(opts);
+    }
+
+    /// Enables or disables the estimation of the id switch and fragmentation rates
+    ///
+    pub fn set_quality_estimation(&mut self, opts: Option<QualityOptions>) {
+        self.opts.set_quality_estimation(opts);
+    }
+
+    /// Enables or disables the detection noise model estimation
+    ///
+    pub fn set_noise_model(&mut self, opts: Option<NoiseModelOptions>) {
+        self.opts.set_noise_model(opts);
+    }
+
+    /// Sets the overrides of the configuration applied to the predictions of the scene until
+    /// they are removed. The overrides must be kept until the results of the predictions they are
+    /// set for are received.
+    ///
+    /// # Parameters
+    /// * `scene_id` - the scene the overrides are applied to
+    /// * `overrides` - the overrides, `None` restores the configured behavior
+    ///
+    pub fn set_predict_overrides(&mut self, scene_id: u64, overrides: Option<PredictOverrides>) {
+        self.opts.set_predict_overrides(scene_id, overrides);
     }
 
     pub fn idle_tracks(&mut self) -> Vec<SortTrack> {
@@ -296,7 +622,7 @@ impl BatchSort {
     pub fn idle_tracks_with_scene(&mut self, scene_id: u64) -> Vec<SortTrack> {
         let store = self.store.read().unwrap();
 
-        store
+        let tracks = store
             .lookup(SortLookup::IdleLookup(scene_id))
             .iter()
             .map(|(track_id, _status)| {
@@ -304,7 +630,8 @@ impl BatchSort {
                 let track = shard.get(track_id).unwrap();
                 SortTrack::from(track)
             })
-            .collect()
+            .collect();
+        self.opts.filter_output(tracks)
     }
 }
 
@@ -315,6 +642,16 @@ impl TrackerAPI<SortAttributes, SortMetric, Universal2DBox, SortAttributesOption
         &mut self.auto_waste
     }
 
+    fn scene_lookup(&self, scene_id: u64) -> Option<SortLookup> {
+        Some(SortLookup::SceneLookup(scene_id))
+    }
+
+    fn expired_lookup(&self, scene_id: u64) -> Option<SortLookup> {
+        self.opts
+            .max_track_lifetime()
+            .map(|_| SortLookup::ExpiredLookup(scene_id))
+    }
+
     fn get_opts(&self) -> &SortAttributesOptions {
         &self.opts
     }
@@ -366,20 +703,41 @@ impl Default for SortPredictionBatchRequest {
 #[cfg(feature = "python")]
 pub mod python {
     use crate::{
+        store::{
+            nan_distances::python::PyNanDistancePolicy, shard_stats::python::PyShardDistanceStats,
+        },
+        track::calibration::python::PySceneCalibration,
         trackers::{
             batch::python::PyPredictionBatchResult,
+            candidate_clustering::python::PyCandidateClustering,
+            density::python::PyDensityOptions,
+            duplicate_ids::python::PyDuplicateObjectIdPolicy,
+            frame_clamping::python::PyFrameClamping,
+            history_fallback::python::PyHistoryFallback,
+            motion_model::python::PyMotionModel,
+            noise_model::python::PyNoiseModelOptions,
+            observation_pipeline::python::PyObservationPipeline,
+            output_filter::python::PyTrackOutputFilter,
+            predict_overrides::python::PyPredictOverrides,
+            quality::python::PyQualityOptions,
+            scene_zones::python::PySceneZones,
             sort::{
                 python::{PyPositionalMetricType, PySortTrack, PyWastedSortTrack},
                 WastedSortTrack,
             },
             spatio_temporal_constraints::python::PySpatioTemporalConstraints,
             tracker_api::TrackerAPI,
+            waste_triggers::python::PyWasteTriggers,
+            watchdog::python::PyWatchdogOptions,
         },
         utils::bbox::python::PyUniversal2DBox,
+        utils::kalman::gating::python::PyMahalanobisGating,
     };
 
     use super::{BatchSort, SortPredictionBatchRequest};
+    use pyo3::exceptions::PyRuntimeError;
     use pyo3::prelude::*;
+    use std::time::Duration;
 
     #[pyclass]
     #[pyo3(name = "BatchSort")]
@@ -428,6 +786,137 @@ pub mod python {
             ))
         }
 
+        #[pyo3(signature = (filter))]
+        fn set_output_filter(&mut self, filter: PyTrackOutputFilter) {
+            self.0.set_output_filter(filter.0)
+        }
+
+        #[pyo3(signature = (zones))]
+        fn set_scene_zones(&mut self, zones: PySceneZones) {
+            self.0.set_scene_zones(zones.0)
+        }
+
+        #[pyo3(signature = (policy))]
+        fn set_duplicate_object_id_policy(&mut self, policy: PyDuplicateObjectIdPolicy) {
+            self.0.set_duplicate_object_id_policy(policy.0)
+        }
+
+        #[pyo3(signature = (policy))]
+        fn set_nan_distance_policy(&mut self, policy: PyNanDistancePolicy) {
+            self.0.set_nan_distance_policy(policy.0)
+        }
+
+        #[pyo3(signature = (max_distance))]
+        fn set_max_distance(&mut self, max_distance: Option<f32>) {
+            self.0.set_max_distance(max_distance)
+        }
+
+        #[pyo3(signature = (clustering))]
+        fn set_candidate_clustering(&mut self, clustering: Option<PyCandidateClustering>) {
+            self.0.set_candidate_clustering(clustering.map(|c| c.0))
+        }
+
+        #[pyo3(signature = (scene_id, calibration))]
+        fn set_scene_calibration(
+            &mut self,
+            scene_id: u64,
+            calibration: Option<PySceneCalibration>,
+        ) {
+            self.0
+                .set_scene_calibration(scene_id, calibration.map(|c| c.0))
+        }
+
+        #[pyo3(signature = (triggers))]
+        fn set_waste_triggers(&mut self, triggers: PyWasteTriggers) {
+            self.0.set_waste_triggers(triggers.0)
+        }
+
+        #[pyo3(signature = (epochs))]
+        fn set_max_track_lifetime(&mut self, epochs: Option<usize>) {
+            self.0.set_max_track_lifetime(epochs)
+        }
+
+        #[pyo3(signature = (clamping))]
+        fn set_frame_clamping(&mut self, clamping: PyFrameClamping) {
+            self.0.set_frame_clamping(clamping.0)
+        }
+
+        #[pyo3(signature = (fallback))]
+        fn set_history_fallback(&mut self, fallback: PyHistoryFallback) {
+            self.0.set_history_fallback(fallback.0)
+        }
+
+        #[pyo3(signature = (model))]
+        fn set_motion_model(&mut self, model: PyMotionModel) {
+            self.0.set_motion_model(model.0)
+        }
+
+        /// Enables or disables (with `None`) the watchdog that logs and counts the distance
+        /// computation and the voting threads that made no progress for the stall timeout
+        ///
+        #[pyo3(signature = (opts))]
+        fn set_watchdog(&mut self, opts: Option<PyWatchdogOptions>) {
+            self.0.set_watchdog(opts.map(|o| o.0))
+        }
+
+        /// The number of the stalled threads detected by the watchdog
+        ///
+        #[pyo3(signature = ())]
+        fn watchdog_stalls(&self) -> usize {
+            self.0.watchdog_stalls()
+        }
+
+        #[pyo3(signature = (gating))]
+        fn set_mahalanobis_gating(&mut self, gating: Option<PyMahalanobisGating>) {
+            self.0.set_mahalanobis_gating(gating.map(|g| g.0))
+        }
+
+        #[pyo3(signature = (pipeline))]
+        fn set_observation_pipeline(&mut self, pipeline: PyObservationPipeline) {
+            self.0.set_observation_pipeline(pipeline.0)
+        }
+
+        /// The number of duplicate custom_object_id found since the tracker creation
+        ///
+        #[pyo3(signature = ())]
+        fn duplicate_object_ids(&self) -> usize {
+            self.0.duplicate_object_ids()
+        }
+
+        #[pyo3(signature = (opts))]
+        fn set_density_estimation(&mut self, opts: Option<PyDensityOptions>) {
+            self.0.set_density_estimation(opts.map(|o| o.0))
+        }
+
+        #[pyo3(signature = (opts))]
+        fn set_quality_estimation(&mut self, opts: Option<PyQualityOptions>) {
+            self.0.set_quality_estimation(opts.map(|o| o.0))
+        }
+
+        #[pyo3(signature = (opts))]
+        fn set_noise_model(&mut self, opts: Option<PyNoiseModelOptions>) {
+            self.0.set_noise_model(opts.map(|o| o.0))
+        }
+
+        #[pyo3(signature = (scene_id, overrides))]
+        fn set_predict_overrides(&mut self, scene_id: u64, overrides: Option<PyPredictOverrides>) {
+            self.0
+                .set_predict_overrides(scene_id, overrides.map(|o| o.0))
+        }
+
+        /// The Kalman weights recommended for the scene by the noise model estimation
+        ///
+        /// # Returns
+        /// `(position_weight, velocity_weight, samples)` or `None` if the estimate is not ready
+        ///
+        #[pyo3(signature = (scene_id))]
+        fn noise_estimate(&self, scene_id: u64) -> Option<(f32, f32, usize)> {
+            self.0
+                .get_opts()
+                .noise_estimate(scene_id)
+                .map(|e| (e.position_weight, e.velocity_weight, e.samples))
+        }
+
         #[pyo3(signature = (n))]
         fn skip_epochs(&mut self, n: i64) {
             assert!(n > 0);
@@ -441,6 +930,97 @@ pub mod python {
                 .skip_epochs_for_scene(scene_id.try_into().unwrap(), n.try_into().unwrap())
         }
 
+        /// The object density of the scene estimated from the observations of the last epochs
+        ///
+        /// # Returns
+        /// `(objects, occupied_cells, density)` or `None` if the estimation is disabled or the scene has no observations accounted
+        ///
+        #[pyo3(signature = (scene_id))]
+        fn scene_density(&self, scene_id: u64) -> Option<(f32, usize, f32)> {
+            self.0
+                .get_opts()
+                .scene_density(scene_id)
+                .map(|d| (d.objects, d.occupied_cells, d.density))
+        }
+
+        /// The tracking quality of the scene estimated without the ground truth
+        ///
+        /// # Returns
+        /// `(new_tracks, updates, id_switches, fragmentations, id_switch_rate, fragmentation_rate)` or `None` if the estimation is disabled or the scene has no tracks accounted
+        ///
+        #[pyo3(signature = (scene_id))]
+        fn tracking_quality(
+            &self,
+            scene_id: u64,
+        ) -> Option<(usize, usize, usize, usize, f32, f32)> {
+            self.0.get_opts().tracking_quality(scene_id).map(|q| {
+                (
+                    q.new_tracks,
+                    q.updates,
+                    q.id_switches,
+                    q.fragmentations,
+                    q.id_switch_rate,
+                    q.fragmentation_rate,
+                )
+            })
+        }
+
+        /// The prediction latency percentiles of the scene in seconds
+        ///
+        /// # Returns
+        /// `(p50, p95, p99, samples)` or `None` if the scene has no predictions accounted
+        ///
+        #[pyo3(signature = (scene_id))]
+        fn predict_latency(&self, scene_id: u64) -> Option<(f64, f64, f64, usize)> {
+            self.0.get_opts().predict_latency(scene_id).map(|p| {
+                (
+                    p.p50.as_secs_f64(),
+                    p.p95.as_secs_f64(),
+                    p.p99.as_secs_f64(),
+                    p.samples,
+                )
+            })
+        }
+
+        /// The scenes which epochs are tracked
+        ///
+        #[pyo3(signature = ())]
+        fn scenes(&self) -> Vec<u64> {
+            TrackerAPI::scenes(&self.0)
+        }
+
+        /// Moves the active tracks of the scene to the wasted store and restarts its epochs
+        ///
+        #[pyo3(signature = (scene_id))]
+        fn reset_scene(&mut self, scene_id: u64) {
+            self.0.reset_scene(scene_id)
+        }
+
+        /// Resets the scenes which passed more than `n` of their epochs without the observations
+        ///
+        #[pyo3(signature = (n))]
+        fn clear_idle_scenes(&mut self, n: usize) -> Vec<u64> {
+            self.0.clear_idle_scenes(n)
+        }
+
+        /// Get the distance computation counters of the active tracks store per shard
+        ///
+        #[pyo3(signature = ())]
+        fn distance_stats(&self) -> Vec<PyShardDistanceStats> {
+            self.0
+                .distance_stats()
+                .into_iter()
+                .map(PyShardDistanceStats)
+                .collect()
+        }
+
+        /// Resets the distance computation counters
+        ///
+        #[pyo3(signature = ())]
+        fn reset_distance_stats(&self) {
+            self.0.reset_distance_stats()
+        }
+
         /// Get the amount of stored tracks per shard
         ///
         #[pyo3(signature = ())]
@@ -487,10 +1067,19 @@ pub mod python {
         /// # Parameters
         /// * `bboxes` - bounding boxes received from a detector
         ///
+        /// # Raises
+        /// `RuntimeError` when the watchdog detects the stalled thread while the previous batch is
+        /// processed or the tracker is shut down
+        ///
         #[pyo3(signature = (batch))]
-        fn predict(&mut self, mut batch: PySortPredictionBatchRequest) -> PyPredictionBatchResult {
-            self.0.predict(batch.0.batch);
-            PyPredictionBatchResult(batch.0.result.take().unwrap())
+        fn predict(
+            &mut self,
+            mut batch: PySortPredictionBatchRequest,
+        ) -> PyResult<PyPredictionBatchResult> {
+            self.0
+                .try_predict(batch.0.batch)
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+            Ok(PyPredictionBatchResult(batch.0.result.take().unwrap()))
         }
 
         /// Remove all the tracks with expired life
@@ -528,6 +1117,32 @@ pub mod python {
                 })
             })
         }
+
+        /// Stop the tracker gracefully
+        ///
+        /// # Parameters
+        /// * `timeout_ms` - the time given for the draining and the threads shutdown
+        ///
+        /// # Returns
+        /// the ids of the scenes that were not processed and the number of detached threads
+        ///
+        #[pyo3(signature = (timeout_ms))]
+        pub fn shutdown(&mut self, timeout_ms: i64) -> (Vec<u64>, usize) {
+            let timeout = Duration::from_millis(
+                timeout_ms
+                    .try_into()
+                    .expect("Parameter must be a positive number"),
+            );
+            Python::with_gil(|py| {
+                py.allow_threads(|| {
+                    let report = self.0.shutdown(timeout);
+                    (
+                        report.unprocessed.into_iter().map(|(s, _)| s).collect(),
+                        report.detached_threads,
+                    )
+                })
+            })
+        }
     }
 
     #[pyclass]
@@ -555,6 +1170,11 @@ mod tests {
     use crate::trackers::batch::PredictionBatchRequest;
     use crate::trackers::sort::batch_api::BatchSort;
     use crate::trackers::sort::metric::DEFAULT_MINIMAL_SORT_CONFIDENCE;
+    use crate::trackers::watchdog::{WatchdogOptions, Worker};
+    use crate::Errors;
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::thread::{sleep, spawn};
+    use std::time::Duration;
 
     #[test]
     fn new_drop() {
@@ -580,4 +1200,51 @@ mod tests {
             dbg!(data);
         }
     }
+
+    #[test]
+    fn watchdog() {
+        let ms = Duration::from_millis;
+        let mut bs = BatchSort::new(
+            1,
+            1,
+            1,
+            1,
+            Mahalanobis,
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        bs.set_watchdog(Some(WatchdogOptions::new(ms(20), ms(5))));
+        let stalls = bs.subscribe_stalls().unwrap();
+
+        // the voting thread is stuck with the previous batch
+        let monitor = Arc::new((Mutex::new(1), Condvar::new()));
+        bs.monitor = Some(monitor.clone());
+        let stuck = bs.watchdog.watch(Worker::Voting(0));
+        sleep(ms(50));
+
+        let (mut batch, res) = PredictionBatchRequest::new();
+        batch.add(0, (BoundingBox::new(0.0, 0.0, 5.0, 10.0).into(), Some(1)));
+        let err = bs.try_predict(batch.clone()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Errors>(),
+            Some(Errors::WorkerStalled(_, _))
+        ));
+        assert_eq!(bs.watchdog_stalls(), 1);
+        assert_eq!(stalls.try_recv().unwrap().worker, Worker::Voting(0));
+
+        // the plain prediction keeps waiting for the stalled thread
+        let release = spawn(move || {
+            sleep(ms(50));
+            drop(stuck);
+            let (lock, cvar) = &*monitor;
+            *lock.lock().unwrap() = 0;
+            cvar.notify_all();
+        });
+        bs.predict(batch);
+        release.join().unwrap();
+        assert_eq!(res.get().1.len(), 1);
+        assert_eq!(bs.watchdog_stalls(), 1);
+    }
 }
@@ -2,18 +2,27 @@ use crate::track::{
     MetricOutput, MetricQuery, Observation, ObservationAttributes, ObservationMetric,
     ObservationMetricOk,
 };
+use crate::trackers::density::adapt_positional_metric;
 use crate::trackers::kalman_prediction::TrackAttributesKalmanPrediction;
 use crate::trackers::sort::PositionalMetricType;
 use crate::trackers::sort::{SortAttributes, DEFAULT_SORT_IOU_THRESHOLD};
 use crate::utils::bbox::Universal2DBox;
 use crate::utils::kalman::kalman_2d_box::Universal2DBoxKalmanFilter;
+use crate::Errors;
+use log::warn;
+use serde::{Deserialize, Serialize};
 
 pub const DEFAULT_MINIMAL_SORT_CONFIDENCE: f32 = 0.05;
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SortMetric {
     method: PositionalMetricType,
     min_confidence: f32,
+    history_depth: usize,
+    history_decay: f32,
+    max_distance: Option<f32>,
+    #[serde(default)]
+    max_depth_delta: Option<f32>,
 }
 
 impl Default for SortMetric {
@@ -30,45 +39,187 @@ impl SortMetric {
         Self {
             method,
             min_confidence,
+            history_depth: 1,
+            history_decay: 1.0,
+            max_distance: None,
+            max_depth_delta: None,
         }
     }
+
+    /// Enables the comparison of the candidate not only with the last predicted box of the track, but
+    /// with up to `depth` last predicted boxes kept in the track history. The IoU values are averaged
+    /// with the weights `decay^age`, so the newest prediction has the highest weight.
+    ///
+    /// Only IoU metric uses the history, Mahalanobis metric is calculated against the Kalman state.
+    /// The history is limited by the track's `history_length`.
+    ///
+    /// # Parameters
+    /// * `depth` - the number of last predicted boxes to compare with, `1` means only the last one;
+    /// * `decay` - the weight multiplier for every next older prediction, must lay in `(0.0..=1.0)`.
+    ///
+    pub fn with_history(mut self, depth: usize, decay: f32) -> Self {
+        assert!(depth > 0, "History depth must be a positive number");
+        assert!(
+            decay > 0.0 && decay <= 1.0,
+            "Decay must lay between (0.0 and 1.0]"
+        );
+        self.history_depth = depth;
+        self.history_decay = decay;
+        self
+    }
+
+    /// Sets the hard cut-off for the positional distance, the pairs that are farther are never
+    /// associated regardless of the metric threshold. The distance is the Mahalanobis cost or
+    /// `1 - IoU` (weighted with the confidence).
+    ///
+    pub fn with_max_distance(mut self, max_distance: Option<f32>) -> Self {
+        assert!(
+            max_distance.map(|d| d >= 0.0).unwrap_or(true),
+            "Maximum distance must be a non-negative number"
+        );
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn max_distance(&self) -> Option<f32> {
+        self.max_distance
+    }
+
+    /// Sets the maximal allowed difference between the depths (the distances from the observer)
+    /// of the candidate and the track, the objects overlapping on the image but situated at the
+    /// different distances are never associated. The depth is only checked when both the
+    /// candidate and the track have it.
+    ///
+    pub fn with_max_depth_delta(mut self, max_depth_delta: Option<f32>) -> Self {
+        assert!(
+            max_depth_delta.map(|d| d > 0.0).unwrap_or(true),
+            "Depth delta must be a positive number"
+        );
+        self.max_depth_delta = max_depth_delta;
+        self
+    }
+
+    pub fn max_depth_delta(&self) -> Option<f32> {
+        self.max_depth_delta
+    }
+
+    fn depth_is_compatible(
+        &self,
+        candidate_attrs: &SortAttributes,
+        track_attrs: &SortAttributes,
+    ) -> bool {
+        match (
+            self.max_depth_delta,
+            candidate_attrs.depth,
+            track_attrs.depth,
+        ) {
+            (Some(max_delta), Some(candidate_depth), Some(track_depth)) => {
+                (candidate_depth - track_depth).abs() <= max_delta
+            }
+            _ => true,
+        }
+    }
+
+    pub fn min_confidence(&self) -> f32 {
+        self.min_confidence
+    }
+
+    fn history_iou(
+        &self,
+        candidate_bbox: &Universal2DBox,
+        track_bbox: &Universal2DBox,
+        track_attrs: &SortAttributes,
+    ) -> Option<f32> {
+        let history = track_attrs.predicted_boxes.iter().rev().skip(1).cloned();
+        #[cfg(feature = "f16")]
+        let history = history.chain(
+            track_attrs
+                .compact_predicted_boxes
+                .iter()
+                .rev()
+                .map(Universal2DBox::from),
+        );
+        let (mut weighted, mut weights, mut weight) = (0.0, 0.0, 1.0);
+        for bbox in std::iter::once(track_bbox.clone())
+            .chain(history)
+            .take(self.history_depth)
+        {
+            let iou = Universal2DBox::calculate_metric_object(&Some(candidate_bbox), &Some(&bbox));
+            weighted += weight * iou.unwrap_or(0.0);
+            weights += weight;
+            weight *= self.history_decay;
+        }
+        Some(weighted / weights).filter(|e| *e > 0.0)
+    }
 }
 
 impl ObservationMetric<SortAttributes, Universal2DBox> for SortMetric {
     fn metric(&self, mq: &MetricQuery<SortAttributes, Universal2DBox>) -> MetricOutput<f32> {
-        let (candidate_bbox, track_bbox) = (
-            mq.candidate_observation.attr().as_ref().unwrap(),
-            mq.track_observation.attr().as_ref().unwrap(),
-        );
+        // the malformed observations (built without the boxes) are not compared
+        let (Some(candidate_bbox), Some(track_bbox)) = (
+            mq.candidate_observation.attr().as_ref(),
+            mq.track_observation.attr().as_ref(),
+        ) else {
+            warn!(
+                "The observation without the bounding box is not compared, scene={}",
+                mq.track_attrs.scene_id
+            );
+            return None;
+        };
+        if !self.depth_is_compatible(mq.candidate_attrs, mq.track_attrs) {
+            return None;
+        }
         let conf = if candidate_bbox.confidence < self.min_confidence {
             self.min_confidence
         } else {
             candidate_bbox.confidence
         };
 
-        if Universal2DBox::too_far(candidate_bbox, track_bbox) {
+        let use_history =
+            matches!(self.method, PositionalMetricType::IoU(_)) && self.history_depth > 1;
+
+        if !use_history && Universal2DBox::too_far(candidate_bbox, track_bbox) {
             None
         } else {
-            Some(match self.method {
-                PositionalMetricType::Mahalanobis => {
-                    let state = mq.track_attrs.get_state().unwrap();
-                    let f = Universal2DBoxKalmanFilter::new(
-                        mq.track_attrs.get_position_weight(),
-                        mq.track_attrs.get_velocity_weight(),
-                    );
-                    let dist = f.distance(state, candidate_bbox);
-                    (
-                        Some(Universal2DBoxKalmanFilter::calculate_cost(dist, true) / conf),
-                        None,
-                    )
-                }
+            let factor = mq
+                .track_attrs
+                .opts
+                .congestion_factor(mq.track_attrs.scene_id, candidate_bbox);
+            let method = mq
+                .track_attrs
+                .opts
+                .predict_overrides(mq.track_attrs.scene_id)
+                .layered(mq.candidate_attrs.call_overrides)
+                .positional_kind(self.method);
+            Some(match method {
+                PositionalMetricType::Mahalanobis => match mq.track_attrs.get_state() {
+                    Some(state) => {
+                        let f = Universal2DBoxKalmanFilter::new(
+                            mq.track_attrs.get_position_weight(),
+                            mq.track_attrs.get_velocity_weight(),
+                        );
+                        let dist = f.distance(state, candidate_bbox);
+                        (
+                            mq.track_attrs.opts.mahalanobis_metric(dist, conf, factor),
+                            None,
+                        )
+                    }
+                    // the track has no Kalman state without the motion model
+                    None => (None, None),
+                },
                 PositionalMetricType::IoU(threshold) => {
-                    let box_m_opt = Universal2DBox::calculate_metric_object(
-                        &Some(candidate_bbox),
-                        &Some(track_bbox),
-                    );
+                    let box_m_opt = if use_history {
+                        self.history_iou(candidate_bbox, track_bbox, mq.track_attrs)
+                    } else {
+                        Universal2DBox::calculate_metric_object(
+                            &Some(candidate_bbox),
+                            &Some(track_bbox),
+                        )
+                    };
                     (
-                        box_m_opt.map(|e| e * conf).filter(|e| *e >= threshold),
+                        box_m_opt
+                            .map(|e| adapt_positional_metric(self.method, e * conf, factor))
+                            .filter(|e| *e >= threshold),
                         None,
                     )
                 }
@@ -85,8 +236,14 @@ impl ObservationMetric<SortAttributes, Universal2DBox> for SortMetric {
         _prev_length: usize,
         _is_merge: bool,
     ) -> anyhow::Result<()> {
-        let mut observation = features.pop().unwrap();
-        let observation_bbox = observation.attr().as_ref().unwrap();
+        let mut observation = features.pop().ok_or_else(|| {
+            Errors::MalformedObservation(
+                "at least one observation must present in the track".into(),
+            )
+        })?;
+        let observation_bbox = observation.attr().as_ref().ok_or_else(|| {
+            Errors::MalformedObservation("the observation bounding box is missing".into())
+        })?;
         features.clear();
 
         let mut predicted_bbox = attrs.make_prediction(observation_bbox);
@@ -110,9 +267,17 @@ impl ObservationMetric<SortAttributes, Universal2DBox> for SortMetric {
     ) -> Vec<ObservationMetricOk<Universal2DBox>> {
         unfiltered
             .into_iter()
-            .filter(|res| res.attribute_metric.is_some())
+            .filter(|res| match (res.attribute_metric, self.max_distance) {
+                (None, _) => false,
+                (Some(m), Some(max_distance)) => self.method.metric_to_distance(m) <= max_distance,
+                (Some(_), None) => true,
+            })
             .collect()
     }
+
+    fn schema_name() -> &'static str {
+        "SortMetric"
+    }
 }
 
 #[cfg(test)]
@@ -216,4 +381,44 @@ mod tests {
             "Confidence in track box must NOT be used."
         );
     }
+
+    #[test]
+    fn history_used_in_distance_calculation() {
+        let attr_opts = Arc::new(SortAttributesOptions::new(
+            None,
+            0,
+            5,
+            SpatioTemporalConstraints::default(),
+            1.0 / 20.0,
+            1.0 / 160.0,
+        ));
+
+        let candidate_attrs = SortAttributes::new(attr_opts.clone());
+        let mut track_attrs = SortAttributes::new(attr_opts);
+        let old_bbox = BoundingBox::new(0.0, 0.0, 8.0, 10.0).as_xyaah();
+        let last_bbox = BoundingBox::new(100.0, 100.0, 8.0, 10.0).as_xyaah();
+        track_attrs.update_history(&old_bbox, &old_bbox);
+        track_attrs.update_history(&last_bbox, &last_bbox);
+
+        let candidate_obs = Observation::new(Some(old_bbox.clone()), None);
+        let track_obs = Observation::new(Some(last_bbox), None);
+
+        let mq = MetricQuery {
+            feature_class: 0,
+            candidate_attrs: &candidate_attrs,
+            candidate_observation: &candidate_obs,
+            track_attrs: &track_attrs,
+            track_observation: &track_obs,
+        };
+
+        let metric = SortMetric::new(PositionalMetricType::IoU(0.3), 1.0);
+        assert!(metric.metric(&mq).is_none());
+
+        let metric = SortMetric::new(PositionalMetricType::IoU(0.3), 1.0).with_history(2, 1.0);
+        let res = metric.metric(&mq).unwrap().0.unwrap();
+        assert!((res - 0.5).abs() < EPS);
+
+        let metric = SortMetric::new(PositionalMetricType::IoU(0.3), 1.0).with_history(2, 0.25);
+        assert!(metric.metric(&mq).unwrap().0.is_none());
+    }
 }
@@ -1,31 +1,133 @@
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Instant;
 
+use anyhow::Result;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
+use crate::store::nan_distances::NanDistancePolicy;
 use crate::store::TrackStore;
+use crate::track::calibration::{CalibrationRegistry, SceneCalibration};
+use crate::track::notify::ChangeNotifier;
+use crate::track::state::TrackState;
 use crate::track::Track;
+use crate::trackers::candidate_assignment::CandidateAssignment;
+use crate::trackers::candidate_clustering::CandidateClustering;
+use crate::trackers::density::DensityOptions;
+use crate::trackers::distance_export::{DistanceSink, EpochDistances};
+use crate::trackers::duplicate_ids::DuplicateObjectIdPolicy;
 use crate::trackers::epoch_db::EpochDb;
+use crate::trackers::frame_clamping::FrameClamping;
+use crate::trackers::history_fallback::HistoryFallback;
+use crate::trackers::motion_model::MotionModel;
+use crate::trackers::noise_model::NoiseModelOptions;
+use crate::trackers::observation_intake::ObservationIntake;
+use crate::trackers::observation_pipeline::ObservationPipeline;
+use crate::trackers::output_filter::TrackOutputFilter;
+use crate::trackers::predict_iter::PredictIter;
+use crate::trackers::predict_overrides::PredictOverrides;
+use crate::trackers::quality::QualityOptions;
+#[cfg(feature = "clipping")]
+use crate::trackers::scene_zones::SceneZones;
 use crate::trackers::sort::{
     metric::SortMetric, voting::SortVoting, AutoWaste, PositionalMetricType, SortAttributes,
-    SortAttributesOptions, SortAttributesUpdate, SortLookup, SortTrack, VotingType,
-    DEFAULT_AUTO_WASTE_PERIODICITY, MAHALANOBIS_NEW_TRACK_THRESHOLD,
+    SortAttributesOptions, SortAttributesUpdate, SortLookup, SortObservation, SortTrack,
+    VotingType, DEFAULT_AUTO_WASTE_PERIODICITY,
 };
 use crate::trackers::spatio_temporal_constraints::SpatioTemporalConstraints;
 use crate::trackers::tracker_api::TrackerAPI;
+use crate::trackers::waste_triggers::WasteTriggers;
 use crate::utils::bbox::Universal2DBox;
-use crate::voting::Voting;
+use crate::utils::kalman::gating::MahalanobisGating;
+use crate::voting::{Voting, VotingGate};
 
 /// Easy to use SORT tracker implementation
 ///
-pub struct Sort {
-    store: RwLock<TrackStore<SortAttributes, SortMetric, Universal2DBox>>,
-    wasted_store: RwLock<TrackStore<SortAttributes, SortMetric, Universal2DBox>>,
+pub struct Sort<N: ChangeNotifier = NoopNotifier> {
+    store: RwLock<TrackStore<SortAttributes, SortMetric, Universal2DBox, N>>,
+    wasted_store: RwLock<TrackStore<SortAttributes, SortMetric, Universal2DBox, N>>,
     method: PositionalMetricType,
     opts: Arc<SortAttributesOptions>,
+    intake: ObservationIntake,
     auto_waste: AutoWaste,
     track_id: u64,
+    gate: Option<VotingGate<Universal2DBox>>,
+    distance_sink: Option<Arc<dyn DistanceSink>>,
+}
+
+/// Serializable state of [Sort](Sort) that is enough to continue the tracking after the restore.
+///
+/// The state keeps the tracker configuration, the epochs of the scenes and the active and the
+/// wasted tracks. The gating callback and the collected statistics (latency, noise and density
+/// estimates, duplicate counters) are not kept.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SortState {
+    pub shards: usize,
+    pub bbox_history: usize,
+    pub max_idle_epochs: usize,
+    pub method: PositionalMetricType,
+    pub metric: SortMetric,
+    pub spatio_temporal_constraints: SpatioTemporalConstraints,
+    pub kalman_position_weight: f32,
+    pub kalman_velocity_weight: f32,
+    #[serde(default)]
+    pub output_filter: TrackOutputFilter,
+    #[cfg(feature = "clipping")]
+    #[serde(default)]
+    pub scene_zones: SceneZones,
+    #[serde(default)]
+    pub calibration: CalibrationRegistry,
+    #[serde(default)]
+    pub duplicate_object_id_policy: DuplicateObjectIdPolicy,
+    #[serde(default)]
+    pub nan_distance_policy: NanDistancePolicy,
+    #[serde(default)]
+    pub candidate_clustering: Option<CandidateClustering>,
+    #[serde(default)]
+    pub noise_model: Option<NoiseModelOptions>,
+    #[serde(default)]
+    pub density: Option<DensityOptions>,
+    #[serde(default)]
+    pub quality: Option<QualityOptions>,
+    #[serde(default)]
+    pub class_freeze: Option<usize>,
+    #[serde(default)]
+    pub skipped_epochs_prediction: bool,
+    #[serde(default)]
+    pub observation_timestamps: bool,
+    #[serde(default)]
+    pub max_track_lifetime: Option<usize>,
+    #[serde(default)]
+    pub waste_triggers: WasteTriggers,
+    #[serde(default)]
+    pub frame_clamping: FrameClamping,
+    #[serde(default)]
+    pub history_fallback: HistoryFallback,
+    #[serde(default)]
+    pub motion_model: MotionModel,
+    #[serde(default)]
+    pub mahalanobis_gating: Option<MahalanobisGating>,
+    #[serde(default)]
+    pub observation_pipeline: ObservationPipeline,
+    /// The overrides of the configuration set for the scenes
+    #[serde(default)]
+    pub predict_overrides: HashMap<u64, PredictOverrides>,
+    #[cfg(feature = "f16")]
+    #[serde(default)]
+    pub compact_box_history: bool,
+    #[serde(default = "default_auto_waste_periodicity")]
+    pub auto_waste_periodicity: usize,
+    pub track_id: u64,
+    pub epochs: HashMap<u64, usize>,
+    pub tracks: Vec<TrackState<SortAttributes, Universal2DBox>>,
+    pub wasted: Vec<TrackState<SortAttributes, Universal2DBox>>,
+}
+
+fn default_auto_waste_periodicity() -> usize {
+    DEFAULT_AUTO_WASTE_PERIODICITY
 }
 
 impl Sort {
@@ -47,6 +149,42 @@ impl Sort {
         spatio_temporal_constraints: Option<SpatioTemporalConstraints>,
         kalman_position_weight: f32,
         kalman_velocity_weight: f32,
+    ) -> Self {
+        Self::new_with_notifier(
+            NoopNotifier,
+            shards,
+            bbox_history,
+            max_idle_epochs,
+            method,
+            min_confidence,
+            spatio_temporal_constraints,
+            kalman_position_weight,
+            kalman_velocity_weight,
+        )
+    }
+
+    /// Creates the tracker from the previously taken state
+    ///
+    pub fn from_state(state: SortState) -> Result<Self> {
+        Self::from_state_with_notifier(state, NoopNotifier)
+    }
+}
+
+impl<N: ChangeNotifier> Sort<N> {
+    /// Creates new tracker which sends the ids of the changed tracks of both the active and the
+    /// wasted stores to the notifier, the parameters are the same as for [new](Sort::new)
+    ///
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_notifier(
+        notifier: N,
+        shards: usize,
+        bbox_history: usize,
+        max_idle_epochs: usize,
+        method: PositionalMetricType,
+        min_confidence: f32,
+        spatio_temporal_constraints: Option<SpatioTemporalConstraints>,
+        kalman_position_weight: f32,
+        kalman_velocity_weight: f32,
     ) -> Self {
         assert!(bbox_history > 0);
         let epoch_db = RwLock::new(HashMap::default());
@@ -62,7 +200,7 @@ impl Sort {
             TrackStoreBuilder::new(shards)
                 .default_attributes(SortAttributes::new(opts.clone()))
                 .metric(SortMetric::new(method, min_confidence))
-                .notifier(NoopNotifier)
+                .notifier(notifier.clone())
                 .build(),
         );
 
@@ -70,7 +208,7 @@ impl Sort {
             TrackStoreBuilder::new(shards)
                 .default_attributes(SortAttributes::new(opts.clone()))
                 .metric(SortMetric::new(method, min_confidence))
-                .notifier(NoopNotifier)
+                .notifier(notifier.clone())
                 .build(),
         );
 
@@ -80,13 +218,197 @@ impl Sort {
             wasted_store,
             method,
             opts,
-            auto_waste: AutoWaste {
-                periodicity: DEFAULT_AUTO_WASTE_PERIODICITY,
-                counter: DEFAULT_AUTO_WASTE_PERIODICITY,
-            },
+            intake: ObservationIntake::default(),
+            auto_waste: AutoWaste::new(DEFAULT_AUTO_WASTE_PERIODICITY),
+            gate: None,
+            distance_sink: None,
         }
     }
 
+    /// Sets the gating callback invoked for every (candidate, track) distance before the assignment;
+    /// the pairs rejected by the callback are never associated
+    ///
+    /// # Parameters
+    /// * `gate` - the callback, `None` removes the previously set one
+    ///
+    pub fn set_gate(&mut self, gate: Option<VotingGate<Universal2DBox>>) {
+        self.gate = gate;
+    }
+
+    /// Sets the sink receiving the distances of the matched and the unmatched observations after
+    /// every association, `None` removes the previously set one
+    ///
+    pub fn set_distance_sink(&mut self, sink: Option<Arc<dyn DistanceSink>>) {
+        self.distance_sink = sink;
+    }
+
+    /// Sets the filter that defines which tracks are returned by `predict` and `idle_tracks`
+    ///
+    pub fn set_output_filter(&mut self, filter: TrackOutputFilter) {
+        self.opts.set_output_filter(filter);
+    }
+
+    /// Sets the entry and exit zones of the scenes
+    ///
+    #[cfg(feature = "clipping")]
+    pub fn set_scene_zones(&mut self, zones: SceneZones) {
+        self.opts.set_scene_zones(zones);
+    }
+
+    /// Registers or removes the calibration of the scene, the calibration is available to the
+    /// metrics with `MetricQuery::scene_calibration`
+    ///
+    pub fn set_scene_calibration(&mut self, scene_id: u64, calibration: Option<SceneCalibration>) {
+        self.opts.set_scene_calibration(scene_id, calibration);
+    }
+
+    /// Sets the handling of the observations passed with the same custom_object_id within the epoch
+    ///
+    pub fn set_duplicate_object_id_policy(&mut self, policy: DuplicateObjectIdPolicy) {
+        self.intake.set_duplicate_object_id_policy(policy);
+    }
+
+    /// Enables or disables the clustering of the near-duplicate observations within the epoch
+    ///
+    pub fn set_candidate_clustering(&mut self, clustering: Option<CandidateClustering>) {
+        self.intake.set_candidate_clustering(clustering);
+    }
+
+    /// Enables or disables the fixing of the majority class of the tracks after `n` class votes
+    ///
+    pub fn set_class_freeze(&mut self, n: Option<usize>) {
+        self.opts.set_class_freeze(n);
+    }
+
+    /// Enables or disables the propagation of the Kalman state of the tracks over the epochs
+    /// skipped with [skip_epochs_for_scene](TrackerAPI::skip_epochs_for_scene)
+    ///
+    pub fn set_skipped_epochs_prediction(&mut self, enabled: bool) {
+        self.opts.set_skipped_epochs_prediction(enabled);
+    }
+
+    /// Enables or disables the recording of the wall-clock time of every observation, the times
+    /// are exported with the wasted tracks
+    ///
+    pub fn set_observation_timestamps(&mut self, enabled: bool) {
+        self.opts.set_observation_timestamps(enabled);
+    }
+
+    /// Sets the custom conditions (leaving the region of interest, low confidence, user
+    /// predicates) that waste the tracks of the scenes
+    ///
+    pub fn set_waste_triggers(&mut self, triggers: WasteTriggers) {
+        self.opts.set_waste_triggers(triggers);
+    }
+
+    /// Limits the lifetime of the tracks in epochs, the track is wasted when it reaches the limit
+    /// even if it is still matched, `None` removes the limit
+    ///
+    pub fn set_max_track_lifetime(&mut self, epochs: Option<usize>) {
+        self.opts.set_max_track_lifetime(epochs);
+    }
+
+    /// Sets the clamping of the reported boxes (and optionally of the Kalman state) to the frame,
+    /// the frame size is taken from the calibration of the scene
+    ///
+    pub fn set_frame_clamping(&mut self, clamping: FrameClamping) {
+        self.opts.set_frame_clamping(clamping);
+    }
+
+    /// Sets the boxes reported for the tracks which box history is empty, the boxes are built
+    /// from the Kalman state by default
+    ///
+    pub fn set_history_fallback(&mut self, fallback: HistoryFallback) {
+        self.opts.set_history_fallback(fallback);
+    }
+
+    /// Sets the motion model, with [MotionModel::None](MotionModel::None) the tracks are associated
+    /// on the observed boxes without the Kalman prediction and the Mahalanobis metric never matches
+    ///
+    /// # Panics
+    /// When [MotionModel::None](MotionModel::None) is set with the Mahalanobis gating
+    ///
+    pub fn set_motion_model(&mut self, model: MotionModel) {
+        self.opts.set_motion_model(model);
+    }
+
+    /// Replaces the legacy Mahalanobis cost with the chi-square gated score, the pairs outside of
+    /// the gate are never associated, `None` restores the legacy cost
+    ///
+    /// # Panics
+    /// When the gating is set with the [MotionModel::None](MotionModel::None) motion model
+    ///
+    pub fn set_mahalanobis_gating(&mut self, gating: Option<MahalanobisGating>) {
+        self.opts.set_mahalanobis_gating(gating);
+    }
+
+    /// Sets the hard cut-off for the positional distance (the Mahalanobis cost or `1 - IoU`),
+    /// the pairs that are farther are never associated
+    ///
+    pub fn set_max_distance(&mut self, max_distance: Option<f32>) {
+        let mut store = self.store.write().unwrap();
+        let metric = store.metric().clone().with_max_distance(max_distance);
+        store.set_metric(metric);
+    }
+
+    /// Sets the maximal allowed difference between the depths of the observation and the track,
+    /// look at [SortObservation::with_depth](SortObservation::with_depth)
+    ///
+    pub fn set_max_depth_delta(&mut self, max_depth_delta: Option<f32>) {
+        let mut store = self.store.write().unwrap();
+        let metric = store.metric().clone().with_max_depth_delta(max_depth_delta);
+        store.set_metric(metric);
+    }
+
+    /// Enables or disables the object density estimation and the congestion-aware association
+    ///
+    pub fn set_density_estimation(&mut self, opts: Option<DensityOptions>) {
+        self.opts.set_density_estimation(opts);
+    }
+
+    /// Enables or disables the estimation of the id switch and fragmentation rates
+    ///
+    pub fn set_quality_estimation(&mut self, opts: Option<QualityOptions>) {
+        self.opts.set_quality_estimation(opts);
+    }
+
+    /// Enables or disables the detection noise model estimation
+    ///
+    pub fn set_noise_model(&mut self, opts: Option<NoiseModelOptions>) {
+        self.opts.set_noise_model(opts);
+    }
+
+    /// Sets the overrides of the configuration applied to the predictions of the scene until
+    /// they are removed
+    ///
+    /// # Parameters
+    /// * `scene_id` - the scene the overrides are applied to
+    /// * `overrides` - the overrides, `None` restores the configured behavior
+    ///
+    pub fn set_predict_overrides(&mut self, scene_id: u64, overrides: Option<PredictOverrides>) {
+        self.opts.set_predict_overrides(scene_id, overrides);
+    }
+
+    /// Sets the transformers applied to the incoming observations before the candidate tracks
+    /// are created
+    ///
+    pub fn set_observation_pipeline(&mut self, pipeline: ObservationPipeline) {
+        self.intake.set_observation_pipeline(pipeline);
+    }
+
+    /// The number of duplicate custom_object_id found since the tracker creation
+    ///
+    pub fn duplicate_object_ids(&self) -> usize {
+        self.intake.duplicate_object_ids()
+    }
+
+    /// Enables or disables the storage of the box history with half precision
+    ///
+    #[cfg(feature = "f16")]
+    pub fn set_compact_box_history(&mut self, enabled: bool) {
+        self.opts.set_compact_box_history(enabled);
+    }
+
     /// Receive tracking information for observed bboxes of `scene_id` == 0
     ///
     /// # Parameters
@@ -97,7 +419,7 @@ impl Sort {
     }
 
     fn gen_track_id(&mut self) -> u64 {
-        self.track_id += 1;
+        self.track_id = self.store.read().unwrap().reserve_track_ids(1).start;
         self.track_id
     }
 
@@ -112,31 +434,230 @@ impl Sort {
         scene_id: u64,
         bboxes: &[(Universal2DBox, Option<i64>)],
     ) -> Vec<SortTrack> {
-        if self.auto_waste.counter == 0 {
-            self.auto_waste();
-            self.auto_waste.counter = self.auto_waste.periodicity;
-        } else {
-            self.auto_waste.counter -= 1;
+        self.predict_observations(scene_id, &observations(bboxes))
+    }
+
+    /// Receive tracking information for observed bboxes of `scene_id` with the configuration
+    /// overridden for this prediction only
+    ///
+    /// # Parameters
+    /// * `scene_id` - scene id provided by a user (class, camera id, etc...)
+    /// * `bboxes` - bounding boxes received from a detector
+    /// * `overrides` - the overrides layered over the configuration of the tracker
+    ///
+    pub fn predict_with_overrides(
+        &mut self,
+        scene_id: u64,
+        bboxes: &[(Universal2DBox, Option<i64>)],
+        overrides: PredictOverrides,
+    ) -> Vec<SortTrack> {
+        self.predict_overridden(scene_id, &observations(bboxes), Some(overrides))
+            .0
+    }
+
+    /// Receive tracking information for observed bboxes of `scene_id` labeled with the classes.
+    /// The majority class of the labels collected by the track is reported in
+    /// [SortTrack::class](SortTrack::class).
+    ///
+    /// # Parameters
+    /// * `scene_id` - scene id provided by a user (class, camera id, etc...)
+    /// * `bboxes` - bounding boxes received from a detector with custom object ids and class labels
+    ///
+    pub fn predict_with_classes(
+        &mut self,
+        scene_id: u64,
+        bboxes: &[(Universal2DBox, Option<i64>, Option<i64>)],
+    ) -> Vec<SortTrack> {
+        let observations = bboxes
+            .iter()
+            .map(|(bb, custom_object_id, class_id)| SortObservation {
+                class_id: *class_id,
+                ..SortObservation::new(bb.clone(), *custom_object_id)
+            })
+            .collect::<Vec<_>>();
+        self.predict_observations(scene_id, &observations)
+    }
+
+    /// Receive tracking information for the observations of `scene_id` with the optional class
+    /// labels and user metadata
+    ///
+    /// # Parameters
+    /// * `scene_id` - scene id provided by a user (class, camera id, etc...)
+    /// * `observations` - observations received from a detector
+    ///
+    pub fn predict_observations(
+        &mut self,
+        scene_id: u64,
+        observations: &[SortObservation],
+    ) -> Vec<SortTrack> {
+        self.predict_with_assignments(scene_id, observations).0
+    }
+
+    /// Receive tracking information for the observations of `scene_id` with the tracks the
+    /// observations ended up in
+    ///
+    /// # Parameters
+    /// * `scene_id` - scene id provided by a user (class, camera id, etc...)
+    /// * `observations` - observations received from a detector
+    ///
+    /// # Returns
+    /// the tracks and the assignments of the observations, the assignments are reported for the
+    /// tracks removed by the output filter as well
+    ///
+    pub fn predict_with_assignments(
+        &mut self,
+        scene_id: u64,
+        observations: &[SortObservation],
+    ) -> (Vec<SortTrack>, Vec<CandidateAssignment>) {
+        self.predict_overridden(scene_id, observations, None)
+    }
+
+    fn predict_overridden(
+        &mut self,
+        scene_id: u64,
+        observations: &[SortObservation],
+        overrides: Option<PredictOverrides>,
+    ) -> (Vec<SortTrack>, Vec<CandidateAssignment>) {
+        let started = Instant::now();
+        let mut res = Vec::default();
+        let (candidates, assignments) = self.assign(scene_id, observations, overrides);
+        for (t, dest) in candidates {
+            let track_id = match dest {
+                Some(dest) => {
+                    self.store
+                        .write()
+                        .unwrap()
+                        .merge_external_owned(dest, t, Some(&[0]), false)
+                        .unwrap();
+                    dest
+                }
+                None => self.store.write().unwrap().add_track(t).unwrap(),
+            };
+
+            let lock = self.store.read().unwrap();
+            let store = lock.get_store(track_id as usize);
+            let track = store.get(&track_id).unwrap();
+            res.push(SortTrack::from(track));
+        }
+
+        self.opts.record_quality(scene_id, &res);
+        let res = self.opts.filter_output(res);
+        self.opts
+            .record_predict_latency(scene_id, started.elapsed(), 0);
+        (res, assignments)
+    }
+
+    /// Receive tracking information for observed bboxes of `scene_id` == 0 as they are ready
+    ///
+    /// # Parameters
+    /// * `bboxes` - bounding boxes received from a detector
+    ///
+    pub fn predict_iter(
+        &mut self,
+        bboxes: &[(Universal2DBox, Option<i64>)],
+    ) -> PredictIter<'_, SortAttributes, SortMetric, Universal2DBox, N> {
+        self.predict_iter_with_scene(0, bboxes)
+    }
+
+    /// Receive tracking information for observed bboxes of `scene_id` as they are ready. Unlike
+    /// [predict_with_scene](Self::predict_with_scene) the tracks are yielded in the order their
+    /// observations are merged.
+    ///
+    /// # Parameters
+    /// * `scene_id` - scene id provided by a user (class, camera id, etc...)
+    /// * `bboxes` - bounding boxes received from a detector
+    ///
+    pub fn predict_iter_with_scene(
+        &mut self,
+        scene_id: u64,
+        bboxes: &[(Universal2DBox, Option<i64>)],
+    ) -> PredictIter<'_, SortAttributes, SortMetric, Universal2DBox, N> {
+        let started = Instant::now();
+        let (candidates, _) = self.assign(scene_id, &observations(bboxes), None);
+        PredictIter::new(
+            &self.store,
+            &self.opts,
+            scene_id,
+            started,
+            |t| SortTrack::from(t),
+            candidates,
+        )
+    }
+
+    /// Builds the candidate tracks for the observations and votes for their destinations
+    ///
+    /// # Returns
+    /// the candidate tracks with the ids of the tracks they must be merged into, the candidates
+    /// without destinations get new ids; the assignments of the observations to the tracks
+    ///
+    #[allow(clippy::type_complexity)]
+    fn assign(
+        &mut self,
+        scene_id: u64,
+        observations: &[SortObservation],
+        overrides: Option<PredictOverrides>,
+    ) -> (
+        Vec<(
+            Track<SortAttributes, SortMetric, Universal2DBox, N>,
+            Option<u64>,
+        )>,
+        Vec<CandidateAssignment>,
+    ) {
+        if self.auto_waste.tick(scene_id) {
+            self.auto_waste_scene(scene_id);
         }
 
         let mut rng = rand::thread_rng();
         let epoch = self.opts.next_epoch(scene_id).unwrap();
+        self.notify_epoch(scene_id, epoch);
+        self.waste_expired_scene(scene_id);
+        if !observations.is_empty() {
+            self.opts.mark_scene_active(scene_id);
+        }
+        let (indices, observations): (Vec<_>, Vec<_>) = self
+            .intake
+            .prepare(
+                scene_id,
+                epoch,
+                observations,
+                |o| &o.bounding_box,
+                |o, bounding_box| SortObservation {
+                    bounding_box,
+                    ..o.clone()
+                },
+                |o| (o.custom_object_id, o.bounding_box.confidence),
+            )
+            .into_iter()
+            .unzip();
+        self.opts
+            .record_density(scene_id, observations.iter().map(|o| &o.bounding_box));
+        let (indices, observations): (Vec<_>, Vec<_>) = indices
+            .into_iter()
+            .zip(observations)
+            .filter(|(_, o)| !o.ignored)
+            .unzip();
 
-        let tracks = bboxes
+        let tracks = observations
             .iter()
-            .map(|(bb, custom_object_id)| {
+            .map(|o| {
                 self.store
                     .read()
                     .unwrap()
                     .new_track(rng.gen())
                     .observation(
                         ObservationBuilder::new(0)
-                            .observation_attributes(bb.clone())
-                            .track_attributes_update(SortAttributesUpdate::new_with_scene(
-                                epoch,
-                                scene_id,
-                                *custom_object_id,
-                            ))
+                            .observation_attributes(o.bounding_box.clone())
+                            .track_attributes_update(
+                                SortAttributesUpdate::new_with_scene(
+                                    epoch,
+                                    scene_id,
+                                    o.custom_object_id,
+                                )
+                                .with_class_id(o.class_id)
+                                .with_metadata(o.metadata.clone())
+                                .with_depth(o.depth)
+                                .with_overrides(overrides),
+                            )
                             .build(),
                     )
                     .build()
@@ -149,50 +670,206 @@ impl Sort {
                 .write()
                 .unwrap()
                 .foreign_track_distances(tracks.clone(), 0, false);
-        assert!(errs.all().is_empty());
-        let dists = dists.all();
+        errs.log();
+        let dists = dists.into_iter().collect::<Vec<_>>();
         let voting = SortVoting::new(
-            match self.method {
-                PositionalMetricType::Mahalanobis => MAHALANOBIS_NEW_TRACK_THRESHOLD,
-                PositionalMetricType::IoU(t) => t,
-            },
+            self.opts.positional_threshold_with(
+                &self.opts.predict_overrides(scene_id).layered(overrides),
+                self.method,
+            ),
             num_candidates,
             self.store.read().unwrap().shard_stats().iter().sum(),
-        );
-        let winners = voting.winners(dists);
-        let mut res = Vec::default();
+        )
+        .with_gate(self.gate.clone());
+        let winners = voting.winners(dists.iter().cloned());
+        if let Some(sink) = &self.distance_sink {
+            sink.export(EpochDistances::new(
+                scene_id,
+                epoch,
+                indices.iter().zip(&tracks).map(|(index, t)| {
+                    let source = t.get_track_id();
+                    let dest = winners
+                        .get(&source)
+                        .map(|dest| dest[0])
+                        .filter(|dest| *dest != source);
+                    (*index, source, dest)
+                }),
+                &dists,
+            ));
+        }
 
-        for mut t in tracks {
-            let source = t.get_track_id();
-            let track_id: u64 = if let Some(dest) = winners.get(&source) {
-                let dest = dest[0];
-                if dest == source {
-                    let track_id = self.gen_track_id();
-                    t.set_track_id(track_id);
-                    self.store.write().unwrap().add_track(t).unwrap();
-                    track_id
-                } else {
-                    self.store
-                        .write()
-                        .unwrap()
-                        .merge_external(dest, &t, Some(&[0]), false)
-                        .unwrap();
-                    dest
+        let candidates = tracks
+            .into_iter()
+            .map(|mut t| {
+                let source = t.get_track_id();
+                match winners.get(&source) {
+                    Some(dest) if dest[0] != source => (t, Some(dest[0])),
+                    _ => {
+                        t.set_track_id(self.gen_track_id());
+                        (t, None)
+                    }
                 }
-            } else {
-                let track_id = self.gen_track_id();
-                t.set_track_id(track_id);
-                self.store.write().unwrap().add_track(t).unwrap();
-                track_id
-            };
+            })
+            .collect::<Vec<_>>();
+        let assignments = CandidateAssignment::from_candidates(
+            &indices,
+            candidates.iter().map(|(t, dest)| (t.get_track_id(), *dest)),
+        );
+        (candidates, assignments)
+    }
 
-            let lock = self.store.read().unwrap();
-            let store = lock.get_store(track_id as usize);
-            let track = store.get(&track_id).unwrap();
-            res.push(SortTrack::from(track));
+    /// Takes the serializable state of the tracker
+    ///
+    pub fn state(&self) -> SortState {
+        let store = self.store.read().unwrap();
+        SortState {
+            shards: store.shard_stats().len(),
+            bbox_history: self.opts.history_length,
+            max_idle_epochs: self.opts.max_idle_epochs(),
+            method: self.method,
+            metric: store.metric().clone(),
+            spatio_temporal_constraints: self.opts.spatio_temporal_constraints.clone(),
+            kalman_position_weight: self.opts.position_weight,
+            kalman_velocity_weight: self.opts.velocity_weight,
+            output_filter: self.opts.output_filter(),
+            #[cfg(feature = "clipping")]
+            scene_zones: self.opts.scene_zones(),
+            calibration: self.opts.calibration_registry(),
+            duplicate_object_id_policy: self.intake.duplicate_object_id_policy(),
+            nan_distance_policy: store.nan_distance_policy(),
+            candidate_clustering: self.intake.candidate_clustering(),
+            noise_model: self.opts.noise_model(),
+            density: self.opts.density_estimation(),
+            quality: self.opts.quality_estimation(),
+            class_freeze: self.opts.class_freeze(),
+            skipped_epochs_prediction: self.opts.skipped_epochs_prediction(),
+            observation_timestamps: self.opts.observation_timestamps(),
+            max_track_lifetime: self.opts.max_track_lifetime(),
+            waste_triggers: self.opts.waste_triggers(),
+            frame_clamping: self.opts.frame_clamping(),
+            history_fallback: self.opts.history_fallback(),
+            motion_model: self.opts.motion_model(),
+            mahalanobis_gating: self.opts.mahalanobis_gating(),
+            observation_pipeline: self.intake.observation_pipeline().clone(),
+            predict_overrides: self.opts.predict_overrides_by_scene(),
+            #[cfg(feature = "f16")]
+            compact_box_history: self.opts.compact_box_history(),
+            auto_waste_periodicity: self.auto_waste.periodicity,
+            track_id: self.track_id,
+            epochs: self
+                .opts
+                .epoch_db()
+                .as_ref()
+                .unwrap()
+                .read()
+                .unwrap()
+                .clone(),
+            tracks: store.dump(),
+            wasted: self.wasted_store.read().unwrap().dump(),
+        }
+    }
+
+    /// Exports the active tracks as the Arrow IPC streams of the track summaries and the
+    /// observations, see [export_arrow](TrackStore::export_arrow) for the columns
+    ///
+    pub fn export_arrow<W1: std::io::Write, W2: std::io::Write>(
+        &self,
+        summaries: W1,
+        observations: W2,
+    ) -> Result<()> {
+        self.store
+            .read()
+            .unwrap()
+            .export_arrow(summaries, observations)
+    }
+
+    /// Creates the tracker from the previously taken state, the ids of the changed tracks are sent
+    /// to the notifier
+    ///
+    pub fn from_state_with_notifier(state: SortState, notifier: N) -> Result<Self> {
+        let mut tracker = Self::new_with_notifier(
+            notifier,
+            state.shards,
+            state.bbox_history,
+            state.max_idle_epochs,
+            state.method,
+            state.metric.min_confidence(),
+            Some(state.spatio_temporal_constraints),
+            state.kalman_position_weight,
+            state.kalman_velocity_weight,
+        );
+        tracker.opts.set_output_filter(state.output_filter);
+        #[cfg(feature = "clipping")]
+        tracker.opts.set_scene_zones(state.scene_zones);
+        tracker.opts.set_calibration_registry(state.calibration);
+        tracker
+            .intake
+            .set_duplicate_object_id_policy(state.duplicate_object_id_policy);
+        tracker.set_nan_distance_policy(state.nan_distance_policy);
+        tracker
+            .intake
+            .set_candidate_clustering(state.candidate_clustering);
+        tracker.opts.set_noise_model(state.noise_model);
+        tracker.opts.set_density_estimation(state.density);
+        tracker.opts.set_quality_estimation(state.quality);
+        tracker.opts.set_class_freeze(state.class_freeze);
+        tracker
+            .opts
+            .set_skipped_epochs_prediction(state.skipped_epochs_prediction);
+        tracker
+            .opts
+            .set_observation_timestamps(state.observation_timestamps);
+        tracker
+            .opts
+            .set_max_track_lifetime(state.max_track_lifetime);
+        tracker.opts.set_waste_triggers(state.waste_triggers);
+        tracker.opts.set_frame_clamping(state.frame_clamping);
+        tracker.opts.set_history_fallback(state.history_fallback);
+        tracker.opts.set_motion_model(state.motion_model);
+        tracker
+            .opts
+            .set_mahalanobis_gating(state.mahalanobis_gating);
+        tracker
+            .intake
+            .set_observation_pipeline(state.observation_pipeline);
+        for (scene_id, overrides) in state.predict_overrides {
+            tracker
+                .opts
+                .set_predict_overrides(scene_id, Some(overrides));
+        }
+        #[cfg(feature = "f16")]
+        tracker
+            .opts
+            .set_compact_box_history(state.compact_box_history);
+        tracker.set_auto_waste(state.auto_waste_periodicity);
+        tracker.track_id = state.track_id;
+        tracker
+            .store
+            .read()
+            .unwrap()
+            .id_registry()
+            .advance(state.track_id + 1);
+        *tracker.opts.epoch_db().as_ref().unwrap().write().unwrap() = state.epochs;
+
+        for (store, tracks) in [
+            (&tracker.store, state.tracks),
+            (&tracker.wasted_store, state.wasted),
+        ] {
+            let mut store = store.write().unwrap();
+            store.set_metric(state.metric.clone());
+            for mut track in tracks {
+                track.attributes.set_options(tracker.opts.clone());
+                store.restore(track)?;
+            }
         }
+        Ok(tracker)
+    }
 
-        res
+    /// Stops the executor threads of the tracker, the tracker can't be used afterwards
+    ///
+    pub fn shutdown(&mut self) {
+        self.store.write().unwrap().shutdown();
+        self.wasted_store.write().unwrap().shutdown();
     }
 
     pub fn idle_tracks(&mut self) -> Vec<SortTrack> {
@@ -202,7 +879,7 @@ impl Sort {
     pub fn idle_tracks_with_scene(&mut self, scene_id: u64) -> Vec<SortTrack> {
         let store = self.store.read().unwrap();
 
-        store
+        let tracks = store
             .lookup(SortLookup::IdleLookup(scene_id))
             .iter()
             .map(|(track_id, _status)| {
@@ -210,72 +887,123 @@ impl Sort {
                 let track = shard.get(track_id).unwrap();
                 SortTrack::from(track)
             })
-            .collect()
+            .collect();
+        self.opts.filter_output(tracks)
     }
 }
 
-impl TrackerAPI<SortAttributes, SortMetric, Universal2DBox, SortAttributesOptions, NoopNotifier>
-    for Sort
+impl<N: ChangeNotifier>
+    TrackerAPI<SortAttributes, SortMetric, Universal2DBox, SortAttributesOptions, N> for Sort<N>
 {
     fn get_auto_waste_obj_mut(&mut self) -> &mut AutoWaste {
         &mut self.auto_waste
     }
 
+    fn scene_lookup(&self, scene_id: u64) -> Option<SortLookup> {
+        Some(SortLookup::SceneLookup(scene_id))
+    }
+
+    fn expired_lookup(&self, scene_id: u64) -> Option<SortLookup> {
+        self.opts
+            .max_track_lifetime()
+            .map(|_| SortLookup::ExpiredLookup(scene_id))
+    }
+
     fn get_opts(&self) -> &SortAttributesOptions {
         &self.opts
     }
 
     fn get_main_store_mut(
         &mut self,
-    ) -> RwLockWriteGuard<TrackStore<SortAttributes, SortMetric, Universal2DBox, NoopNotifier>>
-    {
+    ) -> RwLockWriteGuard<TrackStore<SortAttributes, SortMetric, Universal2DBox, N>> {
         self.store.write().unwrap()
     }
 
     fn get_wasted_store_mut(
         &mut self,
-    ) -> RwLockWriteGuard<TrackStore<SortAttributes, SortMetric, Universal2DBox, NoopNotifier>>
-    {
+    ) -> RwLockWriteGuard<TrackStore<SortAttributes, SortMetric, Universal2DBox, N>> {
         self.wasted_store.write().unwrap()
     }
 
     fn get_main_store(
         &self,
-    ) -> RwLockReadGuard<TrackStore<SortAttributes, SortMetric, Universal2DBox, NoopNotifier>> {
+    ) -> RwLockReadGuard<TrackStore<SortAttributes, SortMetric, Universal2DBox, N>> {
         self.store.read().unwrap()
     }
 
     fn get_wasted_store(
         &self,
-    ) -> RwLockReadGuard<TrackStore<SortAttributes, SortMetric, Universal2DBox, NoopNotifier>> {
+    ) -> RwLockReadGuard<TrackStore<SortAttributes, SortMetric, Universal2DBox, N>> {
         self.wasted_store.read().unwrap()
     }
 }
 
-impl From<&Track<SortAttributes, SortMetric, Universal2DBox>> for SortTrack {
-    fn from(track: &Track<SortAttributes, SortMetric, Universal2DBox>) -> Self {
+impl<N: ChangeNotifier> From<&Track<SortAttributes, SortMetric, Universal2DBox, N>> for SortTrack {
+    fn from(track: &Track<SortAttributes, SortMetric, Universal2DBox, N>) -> Self {
         let attrs = track.get_attributes();
+        let (observed_bbox, predicted_bbox, clipped) = attrs.output_boxes();
         SortTrack {
             id: track.get_track_id(),
             custom_object_id: attrs.custom_object_id,
             voting_type: VotingType::Positional,
             epoch: attrs.last_updated_epoch,
             scene_id: attrs.scene_id,
-            observed_bbox: attrs.observed_boxes.back().unwrap().clone(),
-            predicted_bbox: attrs.predicted_boxes.back().unwrap().clone(),
+            observed_bbox,
+            predicted_bbox,
             length: attrs.track_length,
+            tentative: attrs.track_length < attrs.required_track_length,
+            class: attrs.class_votes.majority(),
+            metadata: attrs.metadata.clone(),
+            created_epoch: attrs.created_epoch,
+            created_timestamp: attrs.created_timestamp,
+            clipped,
         }
     }
 }
 
+fn observations(bboxes: &[(Universal2DBox, Option<i64>)]) -> Vec<SortObservation> {
+    bboxes
+        .iter()
+        .map(|(bb, custom_object_id)| SortObservation::new(bb.clone(), *custom_object_id))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::store::nan_distances::NanDistancePolicy;
+    use crate::track::calibration::SceneCalibration;
+    use crate::track::notify::ChangeNotifier;
+    use crate::track::store::arrow::read_track_states;
+    use crate::track::TrackAttributes;
+    use crate::trackers::candidate_assignment::CandidateAssignment;
+    use crate::trackers::candidate_clustering::CandidateClustering;
+    use crate::trackers::density::DensityOptions;
+    use crate::trackers::distance_export::DistanceCollector;
+    use crate::trackers::duplicate_ids::DuplicateObjectIdPolicy;
+    use crate::trackers::frame_clamping::FrameClamping;
+    use crate::trackers::motion_model::MotionModel;
+    use crate::trackers::noise_model::NoiseModelOptions;
+    use crate::trackers::observation_pipeline::{ObservationPipeline, ObservationTransformer};
+    use crate::trackers::output_filter::TrackOutputFilter;
+    use crate::trackers::predict_overrides::PredictOverrides;
+    use crate::trackers::quality::QualityOptions;
+    #[cfg(feature = "clipping")]
+    use crate::trackers::scene_zones::SceneZones;
     use crate::trackers::sort::metric::DEFAULT_MINIMAL_SORT_CONFIDENCE;
-    use crate::trackers::sort::simple_api::Sort;
-    use crate::trackers::sort::PositionalMetricType::IoU;
-    use crate::trackers::sort::DEFAULT_SORT_IOU_THRESHOLD;
+    use crate::trackers::sort::simple_api::{Sort, SortState};
+    use crate::trackers::sort::PositionalMetricType::{IoU, Mahalanobis};
+    use crate::trackers::sort::{
+        SortAttributes, SortObservation, WastedSortTrack, DEFAULT_AUTO_WASTE_PERIODICITY,
+        DEFAULT_SORT_IOU_THRESHOLD,
+    };
     use crate::trackers::tracker_api::TrackerAPI;
-    use crate::utils::bbox::BoundingBox;
+    use crate::trackers::waste_triggers::{WasteTrigger, WasteTriggers};
+    use crate::utils::arrow::{read_stream, ArrowArray};
+    use crate::utils::bbox::{BoundingBox, Universal2DBox};
+    use crate::utils::kalman::gating::{GatingQuantile, MahalanobisGating};
+    use crate::EPS;
+    use serde_json::json;
+    use std::sync::Arc;
 
     #[test]
     fn sort() {
@@ -289,6 +1017,7 @@ mod tests {
             1.0 / 20.0,
             1.0 / 160.0,
         );
+        t.set_observation_timestamps(true);
         assert_eq!(t.current_epoch(), 0);
         let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
         let v = t.predict(&[(bb.into(), None)]);
@@ -339,6 +1068,11 @@ mod tests {
         assert_eq!(wasted.len(), 1);
         assert_eq!(wasted[0].get_track_id(), track_id);
         assert_eq!(t.current_epoch(), 5);
+
+        let wasted = WastedSortTrack::from(wasted[0].clone());
+        assert_eq!(wasted.observed_epochs, vec![1, 2]);
+        assert_eq!(wasted.observed_timestamps.len(), 2);
+        assert!(wasted.observed_timestamps[0] <= wasted.observed_timestamps[1]);
     }
 
     #[test]
@@ -370,7 +1104,7 @@ mod tests {
     }
 
     #[test]
-    fn idle_tracks() {
+    fn sort_output_filter() {
         let mut t = Sort::new(
             1,
             10,
@@ -381,32 +1115,1345 @@ mod tests {
             1.0 / 20.0,
             1.0 / 160.0,
         );
+        t.set_output_filter(TrackOutputFilter::default().min_track_length(2));
         let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+        let v = t.predict(&[(bb.into(), None)]);
+        assert!(v.is_empty());
+        let v = t.predict(&[(bb.into(), None)]);
+        assert_eq!(v.len(), 1);
+        assert_eq!(v[0].length, 2);
 
-        let _v = t.predict_with_scene(1, &[(bb.into(), Some(4))]);
-        let idle = t.idle_tracks_with_scene(1);
-        assert!(idle.is_empty());
-
-        let _v = t.predict_with_scene(1, &[]);
-
-        let idle = t.idle_tracks_with_scene(1);
-        assert_eq!(idle.len(), 1);
-        assert_eq!(idle[0].id, 1);
+        t.set_output_filter(TrackOutputFilter::default().max_coasted_epochs(0));
+        t.skip_epochs(1);
+        assert!(t.idle_tracks().is_empty());
     }
 
+    #[cfg(feature = "clipping")]
     #[test]
-    fn clear_wasted_tracks() {
+    fn sort_scene_zones() {
         let mut t = Sort::new(
             1,
             10,
-            2,
+            5,
             IoU(DEFAULT_SORT_IOU_THRESHOLD),
             DEFAULT_MINIMAL_SORT_CONFIDENCE,
             None,
             1.0 / 20.0,
             1.0 / 160.0,
         );
-        let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+        t.set_scene_zones(
+            SceneZones::default()
+                .entry_zone(0, &[(0.0, 0.0), (20.0, 0.0), (20.0, 100.0), (0.0, 100.0)])
+                .exit_zone(
+                    0,
+                    &[(80.0, 0.0), (100.0, 0.0), (100.0, 100.0), (80.0, 100.0)],
+                )
+                .creation_penalty(1),
+        );
+
+        let inside = BoundingBox::new(40.0, 10.0, 10.0, 20.0);
+        let v = t.predict(&[(inside.into(), None)]);
+        assert!(v.is_empty());
+        let v = t.predict(&[(inside.into(), None)]);
+        assert_eq!(v.len(), 1);
+        assert_eq!(v[0].length, 2);
+
+        let exit = BoundingBox::new(85.0, 10.0, 10.0, 20.0);
+        let v = t.predict(&[(exit.into(), None)]);
+        assert!(v.is_empty());
+        let v = t.predict(&[(exit.into(), None)]);
+        assert_eq!(v.len(), 1);
+        let exit_track = v[0].id;
+        assert!(t.wasted().is_empty());
+
+        t.skip_epochs(1);
+        let wasted = t.wasted();
+        assert_eq!(wasted.len(), 1);
+        assert_eq!(wasted[0].get_track_id(), exit_track);
+    }
+
+    #[test]
+    fn sort_duplicate_object_ids() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let bb1 = BoundingBox::new_with_confidence(0.0, 0.0, 10.0, 20.0, 0.6);
+        let bb2 = BoundingBox::new_with_confidence(50.0, 0.0, 10.0, 20.0, 0.9);
+        let v = t.predict(&[(bb1.into(), Some(1)), (bb2.into(), Some(1))]);
+        assert_eq!(v.len(), 2);
+        assert_eq!(t.duplicate_object_ids(), 0);
+
+        t.set_duplicate_object_id_policy(DuplicateObjectIdPolicy::Warn);
+        let v = t.predict(&[(bb1.into(), Some(1)), (bb2.into(), Some(1))]);
+        assert_eq!(v.len(), 2);
+        assert_eq!(t.duplicate_object_ids(), 1);
+
+        t.set_duplicate_object_id_policy(DuplicateObjectIdPolicy::KeepBestConfidence);
+        let v = t.predict(&[(bb1.into(), Some(1)), (bb2.into(), Some(1))]);
+        assert_eq!(v.len(), 1);
+        assert_eq!(v[0].observed_bbox.confidence, 0.9);
+        assert_eq!(t.duplicate_object_ids(), 2);
+    }
+
+    #[test]
+    fn sort_candidate_clustering() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let bb1 = BoundingBox::new_with_confidence(0.0, 0.0, 10.0, 20.0, 0.6);
+        let bb2 = BoundingBox::new_with_confidence(1.0, 0.0, 10.0, 20.0, 0.9);
+        let v = t.predict(&[(bb1.into(), None), (bb2.into(), Some(2))]);
+        assert_eq!(v.len(), 2);
+
+        t.set_candidate_clustering(Some(CandidateClustering::new(0.5)));
+        let v = t.predict(&[(bb1.into(), None), (bb2.into(), Some(2))]);
+        assert_eq!(v.len(), 1);
+        assert_eq!(v[0].custom_object_id, Some(2));
+        assert_eq!(v[0].observed_bbox.confidence, 0.9);
+        assert!((v[0].observed_bbox.xc - 5.6).abs() < 0.01);
+    }
+
+    #[test]
+    fn sort_predict_iter() {
+        let mut t = Sort::new(
+            2,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let bb1 = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+        let bb2 = BoundingBox::new(100.0, 0.0, 10.0, 20.0);
+        let v1 = t.predict(&[(bb1.into(), Some(1)), (bb2.into(), Some(2))]);
+
+        let mut v2 = t
+            .predict_iter(&[(bb1.into(), Some(1)), (bb2.into(), Some(2))])
+            .collect::<Vec<_>>();
+        v2.sort_by_key(|t| t.custom_object_id);
+        assert_eq!(v2.len(), 2);
+        for (l, r) in v1.iter().zip(v2.iter()) {
+            assert_eq!(l.id, r.id);
+            assert_eq!(r.length, 2);
+        }
+
+        let first = t
+            .predict_iter(&[(bb1.into(), Some(1)), (bb2.into(), Some(2))])
+            .next()
+            .unwrap();
+        assert_eq!(first.length, 3);
+        let v3 = t.predict(&[(bb1.into(), Some(1)), (bb2.into(), Some(2))]);
+        assert!(v3.iter().all(|t| t.length == 4));
+    }
+
+    #[test]
+    fn sort_predict_iter_dropped_quality() {
+        let mut t = Sort::new(
+            2,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_quality_estimation(Some(QualityOptions::default()));
+        let bb1 = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+        let bb2 = BoundingBox::new(100.0, 0.0, 10.0, 20.0);
+        let observations = [(bb1.into(), Some(1)), (bb2.into(), Some(2))];
+
+        // the iterators are dropped after the first track, the rest are accounted on drop
+        assert!(t.predict_iter(&observations).next().is_some());
+        assert!(t.predict_iter(&observations).next().is_some());
+        let quality = t.get_opts().tracking_quality(0).unwrap();
+        assert_eq!(quality.new_tracks, 2);
+        assert_eq!(quality.updates, 2);
+    }
+
+    #[test]
+    fn sort_predict_latency() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+        for _ in 0..3 {
+            t.predict_with_scene(1, &[(bb.into(), None)]);
+        }
+        t.predict_iter_with_scene(1, &[(bb.into(), None)]).count();
+
+        let p = t.get_opts().predict_latency(1).unwrap();
+        assert_eq!(p.samples, 4);
+        assert!(p.p50 <= p.p95 && p.p95 <= p.p99);
+        assert!(t.get_opts().predict_latency(2).is_none());
+
+        t.get_opts().set_latency_window(2);
+        t.predict_with_scene(1, &[(bb.into(), None)]);
+        assert_eq!(t.get_opts().predict_latency(1).unwrap().samples, 1);
+    }
+
+    #[test]
+    fn sort_class_voting() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+        let v = t.predict(&[(bb.into(), None)]);
+        assert!(v[0].class.is_none());
+
+        t.set_class_freeze(Some(4));
+        for class_id in [3, 3, 5, 5, 5] {
+            t.predict_with_classes(0, &[(bb.into(), None, Some(class_id))]);
+        }
+        let v = t.predict_with_classes(0, &[(bb.into(), None, Some(5))]);
+        assert_eq!(v.len(), 1);
+        let class = v[0].class.unwrap();
+        assert_eq!(class.class_id, 3);
+        assert!(class.frozen);
+        assert!((class.stability - 2.0 / 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn sort_max_track_lifetime() {
+        let mut t = Sort::new(
+            1,
+            10,
+            5,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_max_track_lifetime(Some(3));
+        let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+        let ids = (0..4)
+            .map(|_| t.predict(&[(bb.into(), None)])[0].id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids[0], ids[1]);
+        assert_eq!(ids[0], ids[2]);
+        assert_ne!(ids[0], ids[3]);
+        // the expired track is wasted by the prediction, not by the periodic auto-waste
+        assert_eq!(t.active_shard_stats().iter().sum::<usize>(), 1);
+        assert_eq!(t.get_wasted_store().shard_stats().iter().sum::<usize>(), 1);
+
+        let wasted = t.wasted();
+        assert_eq!(wasted.len(), 1);
+        assert_eq!(wasted[0].get_track_id(), ids[0]);
+        assert_eq!(wasted[0].get_attributes().track_length, 3);
+        assert_eq!(t.active_shard_stats().iter().sum::<usize>(), 1);
+        assert_eq!(t.state().max_track_lifetime, Some(3));
+    }
+
+    #[test]
+    fn sort_waste_triggers() {
+        let mut t = Sort::new(
+            1,
+            10,
+            5,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_auto_waste(0);
+        t.set_waste_triggers(
+            WasteTriggers::default()
+                .trigger(WasteTrigger::low_confidence(0.5, 2))
+                .scene_predicate(1, |c| c.track_length > 1),
+        );
+        let bb = |confidence| BoundingBox::new_with_confidence(0.0, 0.0, 10.0, 20.0, confidence);
+        let ids = [0.9, 0.4, 0.4, 0.9]
+            .into_iter()
+            .map(|c| t.predict(&[(bb(c).into(), None)])[0].id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids[0], ids[1]);
+        assert_eq!(ids[0], ids[2]);
+        assert_ne!(ids[0], ids[3]);
+        assert_eq!(t.wasted().len(), 1);
+
+        let ids = (0..3)
+            .map(|_| t.predict_with_scene(1, &[(bb(0.9).into(), None)])[0].id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids[0], ids[1]);
+        assert_ne!(ids[0], ids[2]);
+
+        let state = t.state();
+        assert!(!state.waste_triggers.is_empty());
+        let restored: SortState =
+            serde_json::from_str(&serde_json::to_string(&state).unwrap()).unwrap();
+        assert!(!restored.waste_triggers.is_empty());
+    }
+
+    #[test]
+    fn sort_frame_clamping() {
+        let mut t = Sort::new(
+            1,
+            10,
+            5,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_scene_calibration(
+            0,
+            Some(SceneCalibration::default().frame_size(100.0, 100.0)),
+        );
+        let bb = BoundingBox::new(-15.0, 10.0, 20.0, 20.0);
+
+        let v = t.predict(&[(bb.into(), None)]);
+        assert!(!v[0].clipped);
+        assert!(v[0].observed_bbox.xc < 0.0);
+
+        t.set_frame_clamping(FrameClamping::Output);
+        let v = t.predict(&[(bb.into(), None)]);
+        assert!(v[0].clipped);
+        let observed = BoundingBox::try_from(&v[0].observed_bbox).unwrap();
+        assert!(observed.left.abs() < EPS);
+        assert!((observed.width - 5.0).abs() < EPS);
+        let predicted = BoundingBox::try_from(&v[0].predicted_bbox).unwrap();
+        assert!(predicted.left >= 0.0);
+        let kept = |t: &Sort, id: u64| {
+            t.store
+                .read()
+                .unwrap()
+                .get_store(0)
+                .get(&id)
+                .unwrap()
+                .get_attributes()
+                .clone()
+        };
+        assert!(kept(&t, v[0].id).predicted_boxes.back().unwrap().xc < 0.0);
+
+        t.set_frame_clamping(FrameClamping::OutputAndState);
+        let v = t.predict(&[(bb.into(), None)]);
+        assert!(v[0].clipped);
+        let state = Universal2DBox::try_from(kept(&t, v[0].id).state.unwrap()).unwrap();
+        assert!(state.xc.abs() < EPS);
+        assert_eq!(t.state().frame_clamping, FrameClamping::OutputAndState);
+
+        let v = t.predict(&[(BoundingBox::new(10.0, 10.0, 20.0, 20.0).into(), None)]);
+        assert!(!v[0].clipped);
+    }
+
+    #[test]
+    fn no_motion_model() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_motion_model(MotionModel::None);
+        let v = t.predict(&[(BoundingBox::new(10.0, 10.0, 20.0, 20.0).into(), None)]);
+        let bb = BoundingBox::new(12.0, 10.0, 20.0, 20.0);
+        let v2 = t.predict(&[(bb.into(), None)]);
+        assert_eq!(v[0].id, v2[0].id);
+        assert_eq!(v2[0].predicted_bbox, Universal2DBox::from(bb));
+        let attrs = t
+            .store
+            .read()
+            .unwrap()
+            .get_store(0)
+            .get(&v2[0].id)
+            .unwrap()
+            .get_attributes()
+            .clone();
+        assert!(attrs.state.is_none());
+
+        let restored = Sort::from_state(t.state()).unwrap();
+        assert_eq!(restored.state().motion_model, MotionModel::None);
+
+        // the Mahalanobis metric is unavailable without the Kalman state
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            Mahalanobis,
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_motion_model(MotionModel::None);
+        let v = t.predict(&[(bb.into(), None)]);
+        let v2 = t.predict(&[(bb.into(), None)]);
+        assert_ne!(v[0].id, v2[0].id);
+    }
+
+    #[test]
+    #[should_panic(expected = "The Mahalanobis gating requires the Kalman motion model")]
+    fn no_motion_model_gating() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            Mahalanobis,
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_motion_model(MotionModel::None);
+        t.set_mahalanobis_gating(Some(MahalanobisGating::new(GatingQuantile::Q99, 0.1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "The Mahalanobis gating requires the Kalman motion model")]
+    fn gating_no_motion_model() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            Mahalanobis,
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_mahalanobis_gating(Some(MahalanobisGating::new(GatingQuantile::Q99, 0.1)));
+        t.set_motion_model(MotionModel::None);
+    }
+
+    #[test]
+    fn sort_metadata() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+        let v =
+            t.predict_observations(
+                0,
+                &[SortObservation::new(bb.into(), None)
+                    .with_metadata(json!({"frame": 1, "cam": "a"}))],
+            );
+        assert_eq!(v[0].metadata, Some(json!({"frame": 1, "cam": "a"})));
+
+        t.predict(&[(bb.into(), None)]);
+        let v = t.predict_observations(
+            0,
+            &[SortObservation::new(bb.into(), None).with_metadata(json!({"frame": 3}))],
+        );
+        assert_eq!(v[0].length, 3);
+        assert_eq!(v[0].metadata, Some(json!({"frame": 3, "cam": "a"})));
+
+        t.skip_epochs(3);
+        let wasted = t.wasted();
+        assert_eq!(wasted.len(), 1);
+        assert_eq!(
+            WastedSortTrack::from(wasted[0].clone()).metadata,
+            Some(json!({"frame": 3, "cam": "a"}))
+        );
+    }
+
+    #[test]
+    fn sort_max_distance() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let v = t.predict(&[(BoundingBox::new(0.0, 0.0, 10.0, 20.0).into(), None)]);
+        let id = v[0].id;
+        let v = t.predict(&[(BoundingBox::new(3.0, 0.0, 10.0, 20.0).into(), None)]);
+        assert_eq!(v[0].id, id);
+
+        t.set_max_distance(Some(0.2));
+        let v = t.predict(&[(BoundingBox::new(9.0, 0.0, 10.0, 20.0).into(), None)]);
+        assert_ne!(v[0].id, id);
+    }
+
+    #[test]
+    fn sort_skipped_epochs_prediction() {
+        let tracker = |enabled: bool| {
+            let mut t = Sort::new(
+                1,
+                10,
+                10,
+                IoU(DEFAULT_SORT_IOU_THRESHOLD),
+                DEFAULT_MINIMAL_SORT_CONFIDENCE,
+                None,
+                1.0 / 20.0,
+                1.0 / 160.0,
+            );
+            t.set_skipped_epochs_prediction(enabled);
+            t
+        };
+        let bb = |x: f32| BoundingBox::new(x, 0.0, 40.0, 20.0).into();
+        let (mut off, mut on) = (tracker(false), tracker(true));
+
+        // the continuous epochs are predicted the same way
+        for i in 0..5 {
+            let x = 2.0 * i as f32;
+            let expected = off.predict(&[(bb(x), None)]);
+            let tracks = on.predict(&[(bb(x), None)]);
+            assert_eq!(tracks[0].predicted_bbox, expected[0].predicted_bbox);
+            assert_eq!(tracks[0].observed_bbox, expected[0].observed_bbox);
+        }
+
+        // the object moved over the skipped epochs, the propagated track follows it
+        off.skip_epochs_for_scene(0, 3);
+        on.skip_epochs_for_scene(0, 3);
+        let x = 2.0 * 8.0;
+        let expected = off.predict(&[(bb(x), None)]);
+        let tracks = on.predict(&[(bb(x), None)]);
+        assert_eq!(tracks[0].id, expected[0].id);
+        let observed = bb(x).xc;
+        assert!(
+            (tracks[0].predicted_bbox.xc - observed).abs()
+                < (expected[0].predicted_bbox.xc - observed).abs()
+        );
+    }
+
+    #[test]
+    fn sort_max_depth_delta() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_max_depth_delta(Some(1.0));
+        let observe = |depth: f32| {
+            SortObservation::new(BoundingBox::new(0.0, 0.0, 10.0, 20.0).into(), None)
+                .with_depth(depth)
+        };
+        let id = t.predict_observations(0, &[observe(5.0)])[0].id;
+        assert_eq!(t.predict_observations(0, &[observe(5.5)])[0].id, id);
+        // the depth of the track is the depth of the last observation
+        assert_eq!(t.predict_observations(0, &[observe(6.4)])[0].id, id);
+        assert_ne!(t.predict_observations(0, &[observe(8.0)])[0].id, id);
+
+        // the observations without the depth are not gated
+        let v = t.predict(&[(BoundingBox::new(0.0, 0.0, 10.0, 20.0).into(), None)]);
+        assert_eq!(v.len(), 1);
+        assert_eq!(
+            t.store.read().unwrap().metric().max_depth_delta(),
+            Some(1.0)
+        );
+    }
+
+    #[test]
+    fn sort_scene_auto_waste() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_auto_waste(3);
+        t.predict(&[(BoundingBox::new(0.0, 0.0, 10.0, 20.0).into(), None)]);
+        for _ in 0..6 {
+            t.predict(&[]);
+        }
+        // the predictions of the other scene don't sweep the tracks of the scene
+        for _ in 0..10 {
+            t.predict_with_scene(1, &[]);
+        }
+        assert_eq!(t.active_shard_stats().iter().sum::<usize>(), 1);
+
+        t.predict(&[]);
+        assert_eq!(t.active_shard_stats().iter().sum::<usize>(), 0);
+        assert_eq!(t.wasted().len(), 1);
+
+        // the legacy counter follows the countdown of the default scene
+        assert_eq!(t.auto_waste.countdown(0), 3);
+        #[allow(deprecated)]
+        let counter = t.auto_waste.counter;
+        assert_eq!(counter, 3);
+    }
+
+    #[test]
+    fn sort_mahalanobis_gating() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            Mahalanobis,
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_mahalanobis_gating(Some(MahalanobisGating::new(GatingQuantile::Q99, 0.1)));
+        let v = t.predict(&[(BoundingBox::new(0.0, 0.0, 10.0, 20.0).into(), None)]);
+        let id = v[0].id;
+        let v = t.predict(&[(BoundingBox::new(0.2, 0.0, 10.0, 20.0).into(), None)]);
+        assert_eq!(v[0].id, id);
+
+        let v = t.predict(&[(BoundingBox::new(50.0, 50.0, 10.0, 20.0).into(), None)]);
+        assert_ne!(v[0].id, id);
+
+        let state = t.state();
+        assert_eq!(
+            state.mahalanobis_gating,
+            Some(MahalanobisGating::new(GatingQuantile::Q99, 0.1))
+        );
+        let restored = Sort::from_state(state).unwrap();
+        assert_eq!(
+            restored.opts.mahalanobis_gating(),
+            Some(MahalanobisGating::new(GatingQuantile::Q99, 0.1))
+        );
+    }
+
+    #[test]
+    fn sort_candidate_assignments() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let (v, assignments) = t.predict_with_assignments(
+            0,
+            &[SortObservation::new(
+                BoundingBox::new(0.0, 0.0, 10.0, 20.0).into(),
+                None,
+            )],
+        );
+        assert_eq!(
+            assignments,
+            vec![CandidateAssignment::new(0, v[0].id, true)]
+        );
+        let id = v[0].id;
+
+        let (v, assignments) = t.predict_with_assignments(
+            0,
+            &[
+                SortObservation::new(BoundingBox::new(50.0, 50.0, 10.0, 20.0).into(), None),
+                SortObservation::new(BoundingBox::new(0.2, 0.0, 10.0, 20.0).into(), None),
+            ],
+        );
+        assert_eq!(v.len(), 2);
+        assert_eq!(assignments.len(), 2);
+        assert_eq!(assignments[0].index, 0);
+        assert!(assignments[0].new_track);
+        assert_ne!(assignments[0].track_id, id);
+        assert_eq!(assignments[1], CandidateAssignment::new(1, id, false));
+    }
+
+    #[test]
+    fn sort_ignored_observations() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+        let ignored = SortObservation::new(BoundingBox::new(50.0, 50.0, 10.0, 20.0).into(), None)
+            .with_ignored(true);
+        let (v, assignments) = t
+            .predict_with_assignments(0, &[ignored.clone(), SortObservation::new(bb.into(), None)]);
+        assert_eq!(v.len(), 1);
+        assert_eq!(
+            assignments,
+            vec![CandidateAssignment::new(1, v[0].id, true)]
+        );
+        let id = v[0].id;
+
+        // the ignored observation over the track doesn't update it
+        let (v, assignments) = t.predict_with_assignments(
+            0,
+            &[SortObservation::new(bb.into(), None).with_ignored(true)],
+        );
+        assert!(v.is_empty());
+        assert!(assignments.is_empty());
+        let idle = t.idle_tracks();
+        assert_eq!(idle.len(), 1);
+        assert_eq!(idle[0].id, id);
+        assert_eq!(idle[0].length, 1);
+
+        let v = t.predict_observations(0, &[ignored, SortObservation::new(bb.into(), None)]);
+        assert_eq!(v.len(), 1);
+        assert_eq!(v[0].id, id);
+        assert_eq!(v[0].length, 2);
+        assert_eq!(t.active_shard_stats().iter().sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn sort_distance_export() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let collector = DistanceCollector::default();
+        t.set_distance_sink(Some(Arc::new(collector.clone())));
+        let id = t.predict(&[(BoundingBox::new(0.0, 0.0, 10.0, 20.0).into(), None)])[0].id;
+        let exported = collector.drain();
+        assert_eq!(exported.len(), 1);
+        assert!(exported[0].matched.is_empty());
+        assert_eq!(exported[0].unmatched[0].nearest, None);
+
+        t.predict(&[
+            (BoundingBox::new(50.0, 50.0, 10.0, 20.0).into(), None),
+            (BoundingBox::new(1.0, 0.0, 10.0, 20.0).into(), None),
+        ]);
+        let exported = collector.drain();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].epoch, 2);
+        assert_eq!(exported[0].matched.len(), 1);
+        let matched = exported[0].matched[0];
+        assert_eq!(matched.index, 1);
+        assert_eq!(matched.distance.track_id, id);
+        assert!(matched.distance.positional.unwrap() > DEFAULT_SORT_IOU_THRESHOLD);
+        assert_eq!(exported[0].unmatched.len(), 1);
+        assert_eq!(exported[0].unmatched[0].index, 0);
+
+        t.set_distance_sink(None);
+        t.predict(&[(BoundingBox::new(2.0, 0.0, 10.0, 20.0).into(), None)]);
+        assert!(collector.drain().is_empty());
+    }
+
+    #[test]
+    fn sort_predict_overrides() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let overrides = PredictOverrides {
+            iou_threshold: Some(0.6),
+            ..Default::default()
+        };
+        let v = t.predict(&[(BoundingBox::new(0.0, 0.0, 10.0, 20.0).into(), None)]);
+        let id = v[0].id;
+        let v = t.predict_with_overrides(
+            0,
+            &[(BoundingBox::new(3.0, 0.0, 10.0, 20.0).into(), None)],
+            overrides,
+        );
+        assert_ne!(v[0].id, id);
+        let id = v[0].id;
+        assert_eq!(
+            t.get_opts().predict_overrides(0),
+            PredictOverrides::default()
+        );
+
+        // the overrides of the other scene don't change the association
+        t.set_predict_overrides(1, Some(overrides));
+        let v = t.predict(&[(BoundingBox::new(6.0, 0.0, 10.0, 20.0).into(), None)]);
+        assert_eq!(v[0].id, id);
+
+        // the overrides of the prediction are layered over the overrides of the scene
+        t.set_predict_overrides(0, Some(overrides));
+        let v = t.predict_with_overrides(
+            0,
+            &[(BoundingBox::new(9.0, 0.0, 10.0, 20.0).into(), None)],
+            PredictOverrides::default(),
+        );
+        assert_ne!(v[0].id, id);
+        let id = v[0].id;
+        let v = t.predict_with_overrides(
+            0,
+            &[(BoundingBox::new(12.0, 0.0, 10.0, 20.0).into(), None)],
+            PredictOverrides {
+                iou_threshold: Some(0.3),
+                ..Default::default()
+            },
+        );
+        assert_eq!(v[0].id, id);
+        assert_eq!(t.get_opts().predict_overrides(0), overrides);
+    }
+
+    #[test]
+    fn sort_lifetime() {
+        let mut t = Sort::new(
+            1,
+            1,
+            1,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+        let created = t.predict(&[(bb.into(), None)])[0].clone();
+        assert_eq!(created.created_epoch, 1);
+        for _ in 0..2 {
+            let v = t.predict(&[(bb.into(), None)]);
+            assert_eq!(v[0].created_epoch, 1);
+            assert_eq!(v[0].created_timestamp, created.created_timestamp);
+        }
+
+        let mut wasted = Vec::default();
+        while wasted.is_empty() {
+            t.predict(&[]);
+            wasted = t.wasted();
+        }
+        let wasted = WastedSortTrack::from(wasted[0].clone());
+        assert_eq!(wasted.observed_epochs, vec![3]);
+        assert_eq!(wasted.created_epoch, 1);
+        assert_eq!(wasted.wasted_epoch, Some(t.current_epoch()));
+        assert!(wasted.wasted_timestamp.unwrap() >= wasted.created_timestamp);
+    }
+
+    #[test]
+    fn sort_density() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let opts = DensityOptions {
+            smoothing: 1.0,
+            reference_density: 0.5 / 10000.0,
+            ..Default::default()
+        };
+        t.set_density_estimation(Some(opts));
+        let v = t.predict(&[(BoundingBox::new(0.0, 0.0, 10.0, 20.0).into(), None)]);
+        let id = v[0].id;
+        let v = t.predict(&[(BoundingBox::new(3.0, 0.0, 10.0, 20.0).into(), None)]);
+        assert_eq!(v[0].id, id);
+        let density = t.get_opts().scene_density(0).unwrap();
+        assert_eq!(density.occupied_cells, 1);
+        assert!((density.objects - 1.0).abs() < 1e-6);
+
+        t.set_density_estimation(Some(DensityOptions {
+            adapt: true,
+            ..opts
+        }));
+        let v = t.predict(&[(BoundingBox::new(6.0, 0.0, 10.0, 20.0).into(), None)]);
+        assert_ne!(v[0].id, id);
+    }
+
+    #[test]
+    fn sort_quality() {
+        let mut t = Sort::new(
+            1,
+            10,
+            5,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        assert!(t.get_opts().tracking_quality(0).is_none());
+        t.set_quality_estimation(Some(QualityOptions::default()));
+        let v = t.predict(&[(BoundingBox::new(0.0, 0.0, 10.0, 20.0).into(), None)]);
+        let id = v[0].id;
+        t.predict(&[(BoundingBox::new(1.0, 0.0, 10.0, 20.0).into(), None)]);
+        // the track is lost and the object is re-acquired under the new id
+        let v = t.predict(&[(BoundingBox::new(9.0, 0.0, 10.0, 20.0).into(), None)]);
+        assert_ne!(v[0].id, id);
+
+        let quality = t.get_opts().tracking_quality(0).unwrap();
+        assert_eq!(quality.new_tracks, 2);
+        assert_eq!(quality.updates, 1);
+        assert_eq!(quality.id_switches, 0);
+        assert_eq!(quality.fragmentations, 1);
+        assert!(quality.fragmentation_rate > 0.0);
+        assert!(t.state().quality.is_some());
+    }
+
+    #[derive(Clone)]
+    struct ChannelNotifier(crossbeam::channel::Sender<u64>);
+
+    impl ChangeNotifier for ChannelNotifier {
+        fn send(&mut self, id: u64) {
+            self.0.send(id).unwrap();
+        }
+    }
+
+    #[test]
+    fn sort_notifier() {
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let mut t = Sort::new_with_notifier(
+            ChannelNotifier(tx),
+            1,
+            10,
+            5,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let v = t.predict(&[(BoundingBox::new(0.0, 0.0, 10.0, 20.0).into(), None)]);
+        let id = v[0].id;
+        rx.try_iter().for_each(drop);
+
+        t.predict(&[(BoundingBox::new(1.0, 0.0, 10.0, 20.0).into(), None)]);
+        let changed = rx.try_iter().collect::<Vec<_>>();
+        assert!(changed.contains(&id));
+
+        let state = t.state();
+        let (tx, rx) = crossbeam::channel::unbounded();
+        let mut t = Sort::from_state_with_notifier(state, ChannelNotifier(tx)).unwrap();
+        t.predict(&[(BoundingBox::new(2.0, 0.0, 10.0, 20.0).into(), None)]);
+        assert!(rx.try_iter().any(|changed| changed == id));
+    }
+
+    #[test]
+    fn sort_scene_calibration() {
+        let mut t = Sort::new(
+            1,
+            10,
+            5,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_scene_calibration(
+            1,
+            Some(
+                SceneCalibration::default()
+                    .pixels_per_meter(20.0)
+                    .exit_zone(&[(80.0, 0.0), (100.0, 0.0), (100.0, 100.0), (80.0, 100.0)]),
+            ),
+        );
+        #[cfg(feature = "clipping")]
+        {
+            let exit = BoundingBox::new(85.0, 10.0, 10.0, 20.0).as_xyaah();
+            assert!(t.opts.scene_zones().is_exit(1, &exit));
+            assert!(!t.opts.scene_zones().is_exit(0, &exit));
+        }
+
+        let mut attrs = SortAttributes::new(t.opts.clone());
+        attrs.scene_id = 1;
+        assert_eq!(
+            attrs.scene_calibration().unwrap().to_meters(10.0),
+            Some(0.5)
+        );
+        attrs.scene_id = 0;
+        assert!(attrs.scene_calibration().is_none());
+
+        let restored = Sort::from_state(t.state()).unwrap();
+        assert_eq!(
+            restored.opts.scene_calibration(1),
+            t.opts.scene_calibration(1)
+        );
+        t.set_scene_calibration(1, None);
+        assert!(t.opts.scene_calibration(1).is_none());
+    }
+
+    #[test]
+    fn sort_observation_pipeline() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_observation_pipeline(ObservationPipeline::new(vec![
+            ObservationTransformer::Scale { x: 2.0, y: 2.0 },
+            ObservationTransformer::Roi {
+                left: 0.0,
+                top: 0.0,
+                right: 100.0,
+                bottom: 100.0,
+            },
+        ]));
+        let res = t.predict(&[
+            (BoundingBox::new(10.0, 10.0, 10.0, 10.0).into(), Some(1)),
+            (BoundingBox::new(60.0, 10.0, 10.0, 10.0).into(), Some(2)),
+        ]);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].custom_object_id, Some(1));
+        assert!((res[0].observed_bbox.xc - 30.0).abs() < 1e-6);
+        assert!((res[0].observed_bbox.height - 20.0).abs() < 1e-6);
+
+        let state = t.state();
+        assert_eq!(state.observation_pipeline.transformers().len(), 2);
+    }
+
+    #[test]
+    fn sort_export_arrow() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        for i in 0..3 {
+            let bb = BoundingBox::new(i as f32, 0.0, 10.0, 20.0);
+            t.predict(&[(bb.into(), Some(i))]);
+        }
+        let (mut summaries, mut observations) = (Vec::new(), Vec::new());
+        t.export_arrow(&mut summaries, &mut observations).unwrap();
+
+        let table = read_stream(summaries.as_slice()).unwrap();
+        let attributes = &table.batches[0][table.column("attributes").unwrap()];
+        assert!(matches!(attributes, ArrowArray::Struct { .. }));
+        let table = read_stream(observations.as_slice()).unwrap();
+        // the tracker keeps no features
+        assert_eq!(table.names, vec!["track_id", "feature_class", "attributes"]);
+
+        let restored = read_track_states::<_, _, SortAttributes, Universal2DBox>(
+            summaries.as_slice(),
+            observations.as_slice(),
+        )
+        .unwrap();
+        let original = t.store.read().unwrap().dump();
+        assert_eq!(
+            serde_json::to_value(&restored).unwrap(),
+            serde_json::to_value(&original).unwrap()
+        );
+    }
+
+    #[test]
+    fn sort_state() {
+        let mut t = Sort::new(
+            2,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            0.2,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_class_freeze(Some(3));
+        t.set_skipped_epochs_prediction(true);
+        t.set_nan_distance_policy(NanDistancePolicy::Worst);
+        let overrides = PredictOverrides {
+            iou_threshold: Some(0.5),
+            ..Default::default()
+        };
+        t.set_predict_overrides(5, Some(overrides));
+        for i in 0..3 {
+            let bb = BoundingBox::new(i as f32, 0.0, 10.0, 20.0);
+            t.predict_observations(
+                0,
+                &[SortObservation::new(bb.into(), Some(7)).with_metadata(json!({"frame": i}))],
+            );
+        }
+        t.predict_with_scene(1, &[(BoundingBox::new(50.0, 0.0, 10.0, 20.0).into(), None)]);
+        t.skip_epochs_for_scene(1, 3);
+        assert_eq!(t.wasted().len(), 1);
+        t.predict_with_scene(1, &[(BoundingBox::new(90.0, 0.0, 10.0, 20.0).into(), None)]);
+
+        let state = serde_json::to_string(&t.state()).unwrap();
+        let mut r = Sort::from_state(serde_json::from_str(&state).unwrap()).unwrap();
+        assert_eq!(r.get_opts().class_freeze(), Some(3));
+        assert!(r.get_opts().skipped_epochs_prediction());
+        assert_eq!(r.nan_distance_policy(), NanDistancePolicy::Worst);
+        assert_eq!(r.get_opts().predict_overrides(5), overrides);
+        assert_eq!(r.store.read().unwrap().metric().min_confidence(), 0.2);
+        assert_eq!(r.current_epoch_with_scene(1), t.current_epoch_with_scene(1));
+
+        let bb = BoundingBox::new(3.0, 0.0, 10.0, 20.0);
+        let expected = t.predict(&[(bb.into(), None)]);
+        let restored = r.predict(&[(bb.into(), None)]);
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].id, expected[0].id);
+        assert_eq!(restored[0].length, 4);
+        assert_eq!(restored[0].metadata, Some(json!({"frame": 2})));
+        assert!((restored[0].predicted_bbox.xc - expected[0].predicted_bbox.xc).abs() < 1e-6);
+
+        let new = r.predict_with_scene(2, &[(bb.into(), None)]);
+        assert_eq!(
+            new[0].id,
+            t.predict_with_scene(2, &[(bb.into(), None)])[0].id
+        );
+    }
+
+    #[test]
+    fn sort_state_defaults() {
+        let t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let mut state = serde_json::to_value(t.state()).unwrap();
+        // the state taken before the options were introduced
+        for option in [
+            "calibration",
+            "max_track_lifetime",
+            "frame_clamping",
+            "mahalanobis_gating",
+            "observation_pipeline",
+            "predict_overrides",
+            "auto_waste_periodicity",
+            "nan_distance_policy",
+        ] {
+            state.as_object_mut().unwrap().remove(option).unwrap();
+        }
+        let state: SortState = serde_json::from_value(state).unwrap();
+        assert_eq!(state.auto_waste_periodicity, DEFAULT_AUTO_WASTE_PERIODICITY);
+        assert!(state.predict_overrides.is_empty());
+        Sort::from_state(state).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "The store is shut down")]
+    fn sort_shutdown() {
+        let mut t = Sort::new(
+            2,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.predict(&[(BoundingBox::new(0.0, 0.0, 10.0, 20.0).into(), None)]);
+        t.shutdown();
+        assert_eq!(t.state().tracks.len(), 1);
+        t.predict(&[(BoundingBox::new(0.0, 0.0, 10.0, 20.0).into(), None)]);
+    }
+
+    #[test]
+    fn sort_noise_model() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_noise_model(Some(NoiseModelOptions {
+            adapt: true,
+            min_samples: 3,
+            window: 100,
+        }));
+        for i in 0..5 {
+            let jitter = if i % 2 == 0 { 0.5 } else { -0.5 };
+            let bb = BoundingBox::new(i as f32 + jitter, 0.0, 10.0, 20.0);
+            let v = t.predict(&[(bb.into(), None)]);
+            assert_eq!(v.len(), 1);
+            if i < 3 {
+                assert!(t.get_opts().noise_estimate(0).is_none());
+            }
+        }
+        let estimate = t.get_opts().noise_estimate(0).unwrap();
+        assert_eq!(estimate.samples, 4);
+        assert_eq!(
+            t.get_opts().kalman_weights(0),
+            (estimate.position_weight, estimate.velocity_weight)
+        );
+        assert_eq!(t.get_opts().kalman_weights(1), (1.0 / 20.0, 1.0 / 160.0));
+    }
+
+    #[cfg(feature = "f16")]
+    #[test]
+    fn sort_compact_box_history() {
+        let mut t = Sort::new(
+            1,
+            3,
+            1,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_compact_box_history(true);
+        for i in 0..5 {
+            let bb = BoundingBox::new(1000.3 + i as f32, 0.0, 10.0, 20.0);
+            t.predict(&[(bb.into(), None)]);
+        }
+        t.skip_epochs(2);
+        let wasted = t.wasted();
+        assert_eq!(wasted.len(), 1);
+        let attrs = wasted[0].get_attributes();
+        assert_eq!(attrs.observed_boxes.len(), 1);
+        assert_eq!(attrs.compact_observed_boxes.len(), 2);
+        assert_eq!(attrs.compact_predicted_boxes.len(), 2);
+        assert_eq!(attrs.observed_boxes[0].xc, 1004.3 + 5.0);
+
+        let history = attrs.observed_boxes_history();
+        assert_eq!(history.len(), 3);
+        assert!((history[0].xc - (1002.3 + 5.0)).abs() <= 0.5);
+        assert_eq!(attrs.predicted_boxes_history().len(), 3);
+        let track = WastedSortTrack::from(wasted[0].clone());
+        assert_eq!(track.observed_boxes.len(), 3);
+
+        let restored = Sort::from_state(t.state()).unwrap();
+        assert!(restored.get_opts().compact_box_history());
+    }
+
+    #[cfg(feature = "f16")]
+    #[test]
+    fn sort_compact_box_history_waste_triggers() {
+        let mut t = Sort::new(
+            1,
+            3,
+            5,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        t.set_compact_box_history(true);
+        t.set_auto_waste(0);
+        t.set_waste_triggers(
+            WasteTriggers::default().trigger(WasteTrigger::low_confidence(0.5, 3)),
+        );
+        let bb = |confidence| BoundingBox::new_with_confidence(0.0, 0.0, 10.0, 20.0, confidence);
+        // the low confidence boxes are kept with half precision but the trigger sees them
+        let ids = [0.9, 0.4, 0.4, 0.4, 0.9]
+            .into_iter()
+            .map(|c| t.predict(&[(bb(c).into(), None)])[0].id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids[0], ids[3]);
+        assert_ne!(ids[0], ids[4]);
+    }
+
+    #[test]
+    fn sort_gate() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+        let v1 = t.predict(&[(bb.into(), None)]);
+        t.set_gate(Some(Arc::new(|_| false)));
+        let v2 = t.predict(&[(bb.into(), None)]);
+        assert_ne!(v1[0].id, v2[0].id);
+
+        t.set_gate(None);
+        let v3 = t.predict(&[(bb.into(), None)]);
+        assert!(v3[0].id == v1[0].id || v3[0].id == v2[0].id);
+    }
+
+    #[test]
+    fn idle_tracks() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+
+        let _v = t.predict_with_scene(1, &[(bb.into(), Some(4))]);
+        let idle = t.idle_tracks_with_scene(1);
+        assert!(idle.is_empty());
+
+        let _v = t.predict_with_scene(1, &[]);
+
+        let idle = t.idle_tracks_with_scene(1);
+        assert_eq!(idle.len(), 1);
+        assert_eq!(idle[0].id, 1);
+    }
+
+    #[test]
+    fn clear_wasted_tracks() {
+        let mut t = Sort::new(
+            1,
+            10,
+            2,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
 
         let _v = t.predict_with_scene(1, &[(bb.into(), Some(4))]);
         t.skip_epochs_for_scene(1, 3);
@@ -430,23 +2477,87 @@ mod tests {
             0
         );
     }
+
+    #[test]
+    fn clear_idle_scenes() {
+        let mut t = Sort::new(
+            1,
+            10,
+            20,
+            IoU(DEFAULT_SORT_IOU_THRESHOLD),
+            DEFAULT_MINIMAL_SORT_CONFIDENCE,
+            None,
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let bb = BoundingBox::new(0.0, 0.0, 10.0, 20.0);
+
+        t.predict_with_scene(1, &[(bb.into(), None)]);
+        t.predict_with_scene(2, &[(bb.into(), None)]);
+        // the scene isn't idle while the other scenes pass their epochs
+        for _ in 0..10 {
+            t.predict_with_scene(2, &[(bb.into(), None)]);
+        }
+        assert!(t.clear_idle_scenes(0).is_empty());
+        for _ in 0..5 {
+            t.predict_with_scene(1, &[]);
+            t.predict_with_scene(2, &[(bb.into(), None)]);
+        }
+        assert_eq!(t.scenes(), vec![1, 2]);
+        assert!(t.clear_idle_scenes(5).is_empty());
+        assert_eq!(t.clear_idle_scenes(4), vec![1]);
+        assert_eq!(t.scenes(), vec![2]);
+        assert_eq!(t.current_epoch_with_scene(1), 0);
+
+        let wasted = t.wasted();
+        assert_eq!(wasted.len(), 1);
+        assert_eq!(wasted[0].get_attributes().scene_id, 1);
+        assert_eq!(t.active_shard_stats().iter().sum::<usize>(), 1);
+
+        t.reset_scene(2);
+        assert!(t.scenes().is_empty());
+        assert_eq!(t.wasted().len(), 1);
+    }
 }
 
 #[cfg(feature = "python")]
 pub mod python {
+    use pyo3::exceptions::PyValueError;
     use pyo3::prelude::*;
+    use pyo3::types::PyBytes;
+    use std::sync::Arc;
 
     use crate::{
         prelude::Universal2DBox,
+        store::{
+            nan_distances::python::PyNanDistancePolicy, shard_stats::python::PyShardDistanceStats,
+        },
+        track::calibration::python::PySceneCalibration,
         trackers::{
+            candidate_clustering::python::PyCandidateClustering,
+            density::python::PyDensityOptions,
+            distance_export::{python::PyDistanceCollector, DistanceSink},
+            duplicate_ids::python::PyDuplicateObjectIdPolicy,
+            frame_clamping::python::PyFrameClamping,
+            history_fallback::python::PyHistoryFallback,
+            motion_model::python::PyMotionModel,
+            noise_model::python::PyNoiseModelOptions,
+            observation_pipeline::python::PyObservationPipeline,
+            output_filter::python::PyTrackOutputFilter,
+            predict_overrides::python::PyPredictOverrides,
+            quality::python::PyQualityOptions,
+            scene_zones::python::PySceneZones,
             sort::{
-                python::{PyPositionalMetricType, PySortTrack, PyWastedSortTrack},
+                python::{
+                    PyPositionalMetricType, PySortObservation, PySortTrack, PyWastedSortTrack,
+                },
                 WastedSortTrack,
             },
             spatio_temporal_constraints::python::PySpatioTemporalConstraints,
             tracker_api::TrackerAPI,
+            waste_triggers::python::PyWasteTriggers,
         },
-        utils::bbox::python::PyUniversal2DBox,
+        utils::{bbox::python::PyUniversal2DBox, kalman::gating::python::PyMahalanobisGating},
     };
 
     use super::Sort;
@@ -493,6 +2604,214 @@ pub mod python {
             ))
         }
 
+        /// Serializes the state of the tracker (the options, the epochs and the tracks) to JSON
+        ///
+        #[pyo3(signature = ())]
+        pub fn state(&self, py: Python) -> PyResult<PyObject> {
+            let state = serde_json::to_vec(&self.0.state())
+                .map_err(|e| PyValueError::new_err(e.to_string()))?;
+            Ok(PyBytes::new(py, &state).into())
+        }
+
+        /// Exports the active tracks as two Arrow IPC streams: the track summaries and the
+        /// observations, both can be read with `pyarrow.ipc.open_stream`
+        ///
+        #[pyo3(signature = ())]
+        pub fn export_arrow(&self, py: Python) -> PyResult<(PyObject, PyObject)> {
+            let (mut summaries, mut observations) = (Vec::new(), Vec::new());
+            self.0
+                .export_arrow(&mut summaries, &mut observations)
+                .map_err(|e| PyValueError::new_err(e.to_string()))?;
+            Ok((
+                PyBytes::new(py, &summaries).into(),
+                PyBytes::new(py, &observations).into(),
+            ))
+        }
+
+        /// Creates the tracker from the state serialized with `state`
+        ///
+        #[staticmethod]
+        #[pyo3(signature = (state))]
+        pub fn from_state(state: &[u8]) -> PyResult<Self> {
+            let state =
+                serde_json::from_slice(state).map_err(|e| PyValueError::new_err(e.to_string()))?;
+            Ok(Self(
+                Sort::from_state(state).map_err(|e| PyValueError::new_err(e.to_string()))?,
+            ))
+        }
+
+        fn __reduce__(&self, py: Python) -> PyResult<(PyObject, (PyObject,))> {
+            Ok((
+                py.get_type::<Self>().getattr("from_state")?.into(),
+                (self.state(py)?,),
+            ))
+        }
+
+        /// Stops the executor threads of the tracker, the tracker can't be used afterwards
+        ///
+        #[pyo3(signature = ())]
+        pub fn shutdown(&mut self) {
+            self.0.shutdown()
+        }
+
+        fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+            slf
+        }
+
+        fn __exit__(&mut self, _exc_type: &PyAny, _exc_value: &PyAny, _traceback: &PyAny) -> bool {
+            self.0.shutdown();
+            false
+        }
+
+        #[pyo3(signature = (filter))]
+        pub fn set_output_filter(&mut self, filter: PyTrackOutputFilter) {
+            self.0.set_output_filter(filter.0)
+        }
+
+        #[pyo3(signature = (zones))]
+        pub fn set_scene_zones(&mut self, zones: PySceneZones) {
+            self.0.set_scene_zones(zones.0)
+        }
+
+        #[pyo3(signature = (policy))]
+        pub fn set_duplicate_object_id_policy(&mut self, policy: PyDuplicateObjectIdPolicy) {
+            self.0.set_duplicate_object_id_policy(policy.0)
+        }
+
+        #[pyo3(signature = (policy))]
+        pub fn set_nan_distance_policy(&mut self, policy: PyNanDistancePolicy) {
+            self.0.set_nan_distance_policy(policy.0)
+        }
+
+        #[pyo3(signature = (max_distance))]
+        pub fn set_max_distance(&mut self, max_distance: Option<f32>) {
+            self.0.set_max_distance(max_distance)
+        }
+
+        #[pyo3(signature = (max_depth_delta))]
+        pub fn set_max_depth_delta(&mut self, max_depth_delta: Option<f32>) {
+            self.0.set_max_depth_delta(max_depth_delta)
+        }
+
+        /// Sets the collector receiving the distances of the matched and the unmatched observations
+        /// after every association, `None` stops the export
+        ///
+        #[pyo3(signature = (collector))]
+        pub fn set_distance_collector(&mut self, collector: Option<PyDistanceCollector>) {
+            self.0
+                .set_distance_sink(collector.map(|c| Arc::new(c.0) as Arc<dyn DistanceSink>))
+        }
+
+        #[pyo3(signature = (clustering))]
+        pub fn set_candidate_clustering(&mut self, clustering: Option<PyCandidateClustering>) {
+            self.0.set_candidate_clustering(clustering.map(|c| c.0))
+        }
+
+        #[pyo3(signature = (n))]
+        pub fn set_class_freeze(&mut self, n: Option<usize>) {
+            self.0.set_class_freeze(n)
+        }
+
+        #[pyo3(signature = (enabled))]
+        pub fn set_skipped_epochs_prediction(&mut self, enabled: bool) {
+            self.0.set_skipped_epochs_prediction(enabled)
+        }
+
+        #[pyo3(signature = (enabled))]
+        pub fn set_observation_timestamps(&mut self, enabled: bool) {
+            self.0.set_observation_timestamps(enabled)
+        }
+
+        #[pyo3(signature = (triggers))]
+        pub fn set_waste_triggers(&mut self, triggers: PyWasteTriggers) {
+            self.0.set_waste_triggers(triggers.0)
+        }
+
+        #[pyo3(signature = (epochs))]
+        pub fn set_max_track_lifetime(&mut self, epochs: Option<usize>) {
+            self.0.set_max_track_lifetime(epochs)
+        }
+
+        #[pyo3(signature = (clamping))]
+        pub fn set_frame_clamping(&mut self, clamping: PyFrameClamping) {
+            self.0.set_frame_clamping(clamping.0)
+        }
+
+        #[pyo3(signature = (fallback))]
+        pub fn set_history_fallback(&mut self, fallback: PyHistoryFallback) {
+            self.0.set_history_fallback(fallback.0)
+        }
+
+        #[pyo3(signature = (model))]
+        pub fn set_motion_model(&mut self, model: PyMotionModel) {
+            self.0.set_motion_model(model.0)
+        }
+
+        #[pyo3(signature = (gating))]
+        pub fn set_mahalanobis_gating(&mut self, gating: Option<PyMahalanobisGating>) {
+            self.0.set_mahalanobis_gating(gating.map(|g| g.0))
+        }
+
+        #[pyo3(signature = (scene_id, calibration))]
+        pub fn set_scene_calibration(
+            &mut self,
+            scene_id: u64,
+            calibration: Option<PySceneCalibration>,
+        ) {
+            self.0
+                .set_scene_calibration(scene_id, calibration.map(|c| c.0))
+        }
+
+        #[pyo3(signature = (pipeline))]
+        pub fn set_observation_pipeline(&mut self, pipeline: PyObservationPipeline) {
+            self.0.set_observation_pipeline(pipeline.0)
+        }
+
+        /// The number of duplicate custom_object_id found since the tracker creation
+        ///
+        #[pyo3(signature = ())]
+        pub fn duplicate_object_ids(&self) -> usize {
+            self.0.duplicate_object_ids()
+        }
+
+        #[pyo3(signature = (opts))]
+        pub fn set_density_estimation(&mut self, opts: Option<PyDensityOptions>) {
+            self.0.set_density_estimation(opts.map(|o| o.0))
+        }
+
+        #[pyo3(signature = (opts))]
+        pub fn set_quality_estimation(&mut self, opts: Option<PyQualityOptions>) {
+            self.0.set_quality_estimation(opts.map(|o| o.0))
+        }
+
+        #[pyo3(signature = (opts))]
+        pub fn set_noise_model(&mut self, opts: Option<PyNoiseModelOptions>) {
+            self.0.set_noise_model(opts.map(|o| o.0))
+        }
+
+        #[pyo3(signature = (scene_id, overrides))]
+        pub fn set_predict_overrides(
+            &mut self,
+            scene_id: u64,
+            overrides: Option<PyPredictOverrides>,
+        ) {
+            self.0
+                .set_predict_overrides(scene_id, overrides.map(|o| o.0))
+        }
+
+        /// The Kalman weights recommended for the scene by the noise model estimation
+        ///
+        /// # Returns
+        /// `(position_weight, velocity_weight, samples)` or `None` if the estimate is not ready
+        ///
+        #[pyo3(signature = (scene_id))]
+        pub fn noise_estimate(&self, scene_id: u64) -> Option<(f32, f32, usize)> {
+            self.0
+                .get_opts()
+                .noise_estimate(scene_id)
+                .map(|e| (e.position_weight, e.velocity_weight, e.samples))
+        }
+
         #[pyo3(signature = (n))]
         pub fn skip_epochs(&mut self, n: i64) {
             assert!(n > 0);
@@ -506,6 +2825,97 @@ pub mod python {
                 .skip_epochs_for_scene(scene_id.try_into().unwrap(), n.try_into().unwrap())
         }
 
+        /// The object density of the scene estimated from the observations of the last epochs
+        ///
+        /// # Returns
+        /// `(objects, occupied_cells, density)` or `None` if the estimation is disabled or the scene has no observations accounted
+        ///
+        #[pyo3(signature = (scene_id))]
+        pub fn scene_density(&self, scene_id: u64) -> Option<(f32, usize, f32)> {
+            self.0
+                .get_opts()
+                .scene_density(scene_id)
+                .map(|d| (d.objects, d.occupied_cells, d.density))
+        }
+
+        /// The tracking quality of the scene estimated without the ground truth
+        ///
+        /// # Returns
+        /// `(new_tracks, updates, id_switches, fragmentations, id_switch_rate, fragmentation_rate)` or `None` if the estimation is disabled or the scene has no tracks accounted
+        ///
+        #[pyo3(signature = (scene_id))]
+        pub fn tracking_quality(
+            &self,
+            scene_id: u64,
+        ) -> Option<(usize, usize, usize, usize, f32, f32)> {
+            self.0.get_opts().tracking_quality(scene_id).map(|q| {
+                (
+                    q.new_tracks,
+                    q.updates,
+                    q.id_switches,
+                    q.fragmentations,
+                    q.id_switch_rate,
+                    q.fragmentation_rate,
+                )
+            })
+        }
+
+        /// The prediction latency percentiles of the scene in seconds
+        ///
+        /// # Returns
+        /// `(p50, p95, p99, samples)` or `None` if the scene has no predictions accounted
+        ///
+        #[pyo3(signature = (scene_id))]
+        pub fn predict_latency(&self, scene_id: u64) -> Option<(f64, f64, f64, usize)> {
+            self.0.get_opts().predict_latency(scene_id).map(|p| {
+                (
+                    p.p50.as_secs_f64(),
+                    p.p95.as_secs_f64(),
+                    p.p99.as_secs_f64(),
+                    p.samples,
+                )
+            })
+        }
+
+        /// The scenes which epochs are tracked
+        ///
+        #[pyo3(signature = ())]
+        pub fn scenes(&self) -> Vec<u64> {
+            TrackerAPI::scenes(&self.0)
+        }
+
+        /// Moves the active tracks of the scene to the wasted store and restarts its epochs
+        ///
+        #[pyo3(signature = (scene_id))]
+        pub fn reset_scene(&mut self, scene_id: u64) {
+            self.0.reset_scene(scene_id)
+        }
+
+        /// Resets the scenes which passed more than `n` of their epochs without the observations
+        ///
+        #[pyo3(signature = (n))]
+        pub fn clear_idle_scenes(&mut self, n: usize) -> Vec<u64> {
+            self.0.clear_idle_scenes(n)
+        }
+
+        /// Get the distance computation counters of the active tracks store per shard
+        ///
+        #[pyo3(signature = ())]
+        pub fn distance_stats(&self) -> Vec<PyShardDistanceStats> {
+            self.0
+                .distance_stats()
+                .into_iter()
+                .map(PyShardDistanceStats)
+                .collect()
+        }
+
+        /// Resets the distance computation counters
+        ///
+        #[pyo3(signature = ())]
+        pub fn reset_distance_stats(&self) {
+            self.0.reset_distance_stats()
+        }
+
         /// Get the amount of stored tracks per shard
         ///
         #[pyo3(signature = ())]
@@ -583,6 +2993,125 @@ pub mod python {
             })
         }
 
+        /// Receive tracking information for observed bboxes of `scene_id` with the configuration
+        /// overridden for this prediction only
+        ///
+        /// # Parameters
+        /// * `scene_id` - scene id provided by a user (class, camera id, etc...)
+        /// * `bboxes` - bounding boxes received from a detector
+        /// * `overrides` - the overrides layered over the configuration of the tracker
+        ///
+        #[pyo3(signature = (scene_id, bboxes, overrides))]
+        pub fn predict_with_overrides(
+            &mut self,
+            scene_id: i64,
+            bboxes: Vec<(PyUniversal2DBox, Option<i64>)>,
+            overrides: PyPredictOverrides,
+        ) -> Vec<PySortTrack> {
+            assert!(scene_id >= 0);
+            let bboxes: Vec<(Universal2DBox, Option<i64>)> = unsafe { std::mem::transmute(bboxes) };
+
+            Python::with_gil(|py| {
+                py.allow_threads(|| unsafe {
+                    std::mem::transmute(self.0.predict_with_overrides(
+                        scene_id.try_into().unwrap(),
+                        &bboxes,
+                        overrides.0,
+                    ))
+                })
+            })
+        }
+
+        /// Receive tracking information for observed bboxes of `scene_id` labeled with the classes
+        ///
+        /// # Parameters
+        /// * `scene_id` - scene id provided by a user (class, camera id, etc...)
+        /// * `bboxes` - bounding boxes received from a detector with custom object ids and class labels
+        ///
+        #[pyo3(signature = (scene_id, bboxes))]
+        pub fn predict_with_classes(
+            &mut self,
+            scene_id: i64,
+            bboxes: Vec<(PyUniversal2DBox, Option<i64>, Option<i64>)>,
+        ) -> Vec<PySortTrack> {
+            assert!(scene_id >= 0);
+            let bboxes = bboxes
+                .into_iter()
+                .map(|(bb, custom_object_id, class_id)| (bb.0, custom_object_id, class_id))
+                .collect::<Vec<_>>();
+
+            Python::with_gil(|py| {
+                py.allow_threads(|| unsafe {
+                    std::mem::transmute(
+                        self.0
+                            .predict_with_classes(scene_id.try_into().unwrap(), &bboxes),
+                    )
+                })
+            })
+        }
+
+        /// Receive tracking information for the observations of `scene_id` with the optional
+        /// class labels and metadata
+        ///
+        /// # Parameters
+        /// * `scene_id` - scene id provided by a user (class, camera id, etc...)
+        /// * `observations` - observations received from a detector
+        ///
+        #[pyo3(signature = (scene_id, observations))]
+        pub fn predict_observations(
+            &mut self,
+            scene_id: i64,
+            observations: Vec<PySortObservation>,
+        ) -> Vec<PySortTrack> {
+            assert!(scene_id >= 0);
+            let observations = observations.into_iter().map(|o| o.0).collect::<Vec<_>>();
+
+            Python::with_gil(|py| {
+                py.allow_threads(|| unsafe {
+                    std::mem::transmute(
+                        self.0
+                            .predict_observations(scene_id.try_into().unwrap(), &observations),
+                    )
+                })
+            })
+        }
+
+        /// Receive tracking information for the observations of `scene_id` with the tracks the
+        /// observations ended up in
+        ///
+        /// # Parameters
+        /// * `scene_id` - scene id provided by a user (class, camera id, etc...)
+        /// * `observations` - observations received from a detector
+        ///
+        /// # Returns
+        /// the tracks and the `(index, track_id, new_track)` assignments of the observations
+        ///
+        #[pyo3(signature = (scene_id, observations))]
+        pub fn predict_with_assignments(
+            &mut self,
+            scene_id: i64,
+            observations: Vec<PySortObservation>,
+        ) -> (Vec<PySortTrack>, Vec<(usize, u64, bool)>) {
+            assert!(scene_id >= 0);
+            let observations = observations.into_iter().map(|o| o.0).collect::<Vec<_>>();
+
+            let (tracks, assignments) = Python::with_gil(|py| {
+                py.allow_threads(|| {
+                    self.0
+                        .predict_with_assignments(scene_id.try_into().unwrap(), &observations)
+                })
+            });
+            (
+                unsafe {
+                    std::mem::transmute::<Vec<crate::prelude::SortTrack>, Vec<PySortTrack>>(tracks)
+                },
+                assignments
+                    .into_iter()
+                    .map(|a| (a.index, a.track_id, a.new_track))
+                    .collect(),
+            )
+        }
+
         /// Fetch and remove all the tracks with expired life
         ///
         #[pyo3(signature = ())]
@@ -1,6 +1,6 @@
 use crate::track::ObservationMetricOk;
 use crate::utils::bbox::Universal2DBox;
-use crate::voting::Voting;
+use crate::voting::{Voting, VotingGate};
 use core::option::Option::{None, Some};
 use pathfinding::kuhn_munkres::kuhn_munkres;
 use pathfinding::matrix::Matrix;
@@ -12,6 +12,7 @@ pub struct SortVoting {
     threshold: i64,
     candidate_num: usize,
     track_num: usize,
+    gate: Option<VotingGate<Universal2DBox>>,
 }
 
 impl SortVoting {
@@ -20,8 +21,16 @@ impl SortVoting {
             threshold: (threshold * F32_U64_MULT) as i64,
             candidate_num: candidates_num,
             track_num: tracks_num,
+            gate: None,
         }
     }
+
+    /// Sets the gating predicate, the distances rejected by the predicate don't participate in the assignment
+    ///
+    pub fn with_gate(mut self, gate: Option<VotingGate<Universal2DBox>>) -> Self {
+        self.gate = gate;
+        self
+    }
 }
 
 impl Voting<Universal2DBox> for SortVoting {
@@ -53,6 +62,8 @@ impl Voting<Universal2DBox> for SortVoting {
             attribute_metric,
             feature_distance: _,
         } in distances
+            .into_iter()
+            .filter(|e| self.gate.as_ref().map(|gate| gate(e)).unwrap_or(true))
         {
             assert!(from > 0 && to > 0);
 
@@ -106,6 +117,7 @@ mod voting_tests {
     use crate::trackers::sort::voting::SortVoting;
     use crate::voting::Voting;
     use std::collections::HashMap;
+    use std::sync::Arc;
 
     #[test]
     fn test_voting() {
@@ -172,4 +184,16 @@ mod voting_tests {
             HashMap::from([(10, vec![20]), (11, vec![25]), (12, vec![12])])
         );
     }
+
+    #[test]
+    fn test_voting_gate() {
+        let v = SortVoting::new(0.3, 2, 2).with_gate(Some(Arc::new(|e| e.to != 20)));
+        let winners = v.winners([
+            ObservationMetricOk::new(10, 20, Some(0.6), None),
+            ObservationMetricOk::new(10, 25, Some(0.4), None),
+            ObservationMetricOk::new(11, 20, Some(0.5), None),
+        ]);
+
+        assert_eq!(winners, HashMap::from([(10, vec![25])]));
+    }
 }
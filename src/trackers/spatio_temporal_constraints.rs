@@ -1,3 +1,8 @@
+use crate::Errors;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
 /// The struct allows defining the constraints for objects comprared across different epochs.
 ///
 /// When the new objects batch is passed to the tracker it has a newer epoch that the tracks that are kept
@@ -11,7 +16,7 @@
 /// * `R_Track` - radius of the circle surrounding the last bounding box of the track.
 ///
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct SpatioTemporalConstraints {
     constraints: Vec<(usize, f32)>,
 }
@@ -45,22 +50,64 @@ impl SpatioTemporalConstraints {
         self.constraints.dedup_by(|(e1, _), (e2, _)| *e1 == *e2);
     }
 
+    /// Adds the constraints when all of them are valid, otherwise reports the first invalid one
+    /// with [InvalidConstraint](Errors::InvalidConstraint) and keeps the constraints unchanged
+    ///
+    pub fn try_add_constraints(&mut self, constraints: Vec<(usize, f32)>) -> Result<()> {
+        if let Some((delta, max_distance)) =
+            constraints.iter().find(|(_, d)| d.is_nan() || *d <= 0.0)
+        {
+            return Err(Errors::InvalidConstraint(*delta, *max_distance).into());
+        }
+        self.add_constraints(constraints);
+        Ok(())
+    }
+
+    /// The ladder of the constraints ordered by the epoch delta, for every epoch delta the first
+    /// constraint with the same or the larger delta applies
+    ///
+    pub fn get_constraints(&self) -> &[(usize, f32)] {
+        &self.constraints
+    }
+
+    /// The maximal distance allowed for the epoch delta, `None` when the distance is unconstrained
+    ///
+    pub fn max_distance(&self, epoch_delta: usize) -> Option<f32> {
+        self.constraints
+            .iter()
+            .find(|(d, _)| *d >= epoch_delta)
+            .map(|(_, max_dist)| *max_dist)
+    }
+
     pub fn validate(&self, epoch_delta: usize, dist: f32) -> bool {
         assert!(
             dist >= 0.0,
             "The distance is expected to be a positive float"
         );
-        let constraint = self.constraints.iter().find(|(d, _)| *d >= epoch_delta);
-
-        match constraint {
+        match self.max_distance(epoch_delta) {
             None => true,
-            Some((_, max_dist)) => dist <= *max_dist,
+            Some(max_dist) => dist <= max_dist,
         }
     }
 }
 
+impl fmt::Display for SpatioTemporalConstraints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lower = 0;
+        for (delta, max_distance) in &self.constraints {
+            writeln!(
+                f,
+                "epoch delta {lower}..={delta}: distance <= {max_distance} x (R_obj + R_track)"
+            )?;
+            lower = delta + 1;
+        }
+        write!(f, "epoch delta >= {lower}: unconstrained")
+    }
+}
+
 #[cfg(feature = "python")]
 pub mod python {
+    use pyo3::exceptions::PyValueError;
     use pyo3::prelude::*;
 
     use super::SpatioTemporalConstraints;
@@ -77,21 +124,62 @@ pub mod python {
             Self(SpatioTemporalConstraints::default())
         }
 
-        /// Allows adding new constraints to the constraints engine
+        /// Allows adding new constraints to the constraints engine, `ValueError` is raised when
+        /// any of the distances is not a positive float
         ///
         /// # Parameters
         /// * `constraints` - Vec of tuples (epoch_delta, max_allowed_distance)
         ///
         #[pyo3(text_signature = "($self, l: [(epoch_delta, max_allowed_distance)]")]
-        pub fn add_constraints(&mut self, constraints: Vec<(usize, f32)>) {
-            self.0.add_constraints(constraints)
+        pub fn add_constraints(&mut self, constraints: Vec<(usize, f32)>) -> PyResult<()> {
+            self.0
+                .try_add_constraints(constraints)
+                .map_err(|e| PyValueError::new_err(e.to_string()))
         }
 
-        /// Validates the distance for specified epoch delta
+        /// Validates the distance for specified epoch delta, `ValueError` is raised when the
+        /// distance is negative
         ///
         #[pyo3(text_signature = "($self, epoch_delta, dist)")]
-        pub fn validate(&self, epoch_delta: usize, dist: f32) -> bool {
-            self.0.validate(epoch_delta, dist)
+        pub fn validate(&self, epoch_delta: usize, dist: f32) -> PyResult<bool> {
+            if dist.is_nan() || dist < 0.0 {
+                return Err(PyValueError::new_err(format!(
+                    "The distance is expected to be a positive float, got {dist}"
+                )));
+            }
+            Ok(self.0.validate(epoch_delta, dist))
+        }
+
+        /// The constraints as the list of tuples (epoch_delta, max_allowed_distance) ordered by
+        /// the epoch delta
+        ///
+        #[getter]
+        pub fn get_constraints(&self) -> Vec<(usize, f32)> {
+            self.0.get_constraints().to_vec()
+        }
+
+        /// The maximal distance allowed for the epoch delta, `None` when unconstrained
+        ///
+        #[pyo3(text_signature = "($self, epoch_delta)")]
+        pub fn max_distance(&self, epoch_delta: usize) -> Option<f32> {
+            self.0.max_distance(epoch_delta)
+        }
+
+        fn __len__(&self) -> usize {
+            self.0.get_constraints().len()
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        /// The human-readable ladder of the constraints
+        ///
+        fn __str__(&self) -> String {
+            self.0.to_string()
         }
     }
 }
@@ -119,4 +207,23 @@ mod tests {
         assert!(spc.validate(9, 8.7));
         assert!(spc.validate(9, 100.0));
     }
+
+    #[test]
+    fn introspection() {
+        let mut spc = SpatioTemporalConstraints::default();
+        assert!(spc.try_add_constraints(vec![(1, 0.5), (3, -1.0)]).is_err());
+        assert!(spc.try_add_constraints(vec![(1, f32::NAN)]).is_err());
+        assert!(spc.get_constraints().is_empty());
+
+        spc.try_add_constraints(vec![(3, 2.0), (1, 0.5)]).unwrap();
+        assert_eq!(spc.get_constraints(), &[(1, 0.5), (3, 2.0)]);
+        assert_eq!(spc.max_distance(2), Some(2.0));
+        assert_eq!(spc.max_distance(4), None);
+        assert_eq!(
+            spc.to_string(),
+            "epoch delta 0..=1: distance <= 0.5 x (R_obj + R_track)\n\
+             epoch delta 2..=3: distance <= 2 x (R_obj + R_track)\n\
+             epoch delta >= 4: unconstrained"
+        );
+    }
 }
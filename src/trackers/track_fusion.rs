@@ -0,0 +1,428 @@
+use crate::track::ObservationAttributes;
+use crate::trackers::sort::SortTrack;
+use crate::utils::bbox::Universal2DBox;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// The options of the fusion of the outputs of two trackers running over the same observations.
+///
+/// The tracks of the primary and the secondary tracker reported for the epoch are paired by the
+/// IoU of their observed boxes, the pairs matched in the previous epochs (co-occurred) are
+/// preferred over the pairs with the better IoU, so the pairing is stable while the boxes overlap
+/// with IoU not less than `iou_threshold`. The fused track keeps its id while any of its source
+/// tracks is reported, the pair history and the id mappings not used for `max_idle_epochs`
+/// epochs of the scene are forgotten.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TrackFusionOptions {
+    iou_threshold: f32,
+    max_idle_epochs: usize,
+}
+
+impl Default for TrackFusionOptions {
+    fn default() -> Self {
+        Self {
+            iou_threshold: 0.3,
+            max_idle_epochs: 10,
+        }
+    }
+}
+
+impl TrackFusionOptions {
+    pub fn new(iou_threshold: f32, max_idle_epochs: usize) -> Self {
+        assert!(
+            iou_threshold > 0.0 && iou_threshold <= 1.0,
+            "IoU threshold must lay between (0.0 and 1.0]"
+        );
+        Self {
+            iou_threshold,
+            max_idle_epochs,
+        }
+    }
+
+    pub fn iou_threshold(&self) -> f32 {
+        self.iou_threshold
+    }
+
+    pub fn max_idle_epochs(&self) -> usize {
+        self.max_idle_epochs
+    }
+}
+
+/// The track reconciled from the outputs of the trackers
+///
+#[derive(Debug, Clone)]
+pub struct FusedTrack {
+    /// the stable id of the fused track
+    pub id: u64,
+    pub scene_id: u64,
+    /// the id of the primary tracker track
+    pub primary_id: Option<u64>,
+    /// the id of the secondary tracker track
+    pub secondary_id: Option<u64>,
+    /// the number of the epochs the source tracks were paired, including the current one
+    pub co_occurrences: usize,
+    /// the observed box of the primary track if it is reported, otherwise of the secondary one
+    pub bbox: Universal2DBox,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PairHistory {
+    co_occurrences: usize,
+    last_epoch: usize,
+}
+
+#[derive(Debug, Default)]
+struct SceneFusion {
+    epoch: usize,
+    pairs: HashMap<(u64, u64), PairHistory>,
+    primary: HashMap<u64, (u64, usize)>,
+    secondary: HashMap<u64, (u64, usize)>,
+}
+
+impl SceneFusion {
+    fn forget(&mut self, max_idle_epochs: usize) {
+        let epoch = self.epoch;
+        let alive = |last: usize| epoch - last <= max_idle_epochs;
+        self.pairs.retain(|_, h| alive(h.last_epoch));
+        self.primary.retain(|_, (_, last)| alive(*last));
+        self.secondary.retain(|_, (_, last)| alive(*last));
+    }
+}
+
+/// Fusion of the outputs of two trackers into the track set with the stable ids, look at
+/// [TrackFusionOptions] for the details
+///
+#[derive(Debug, Default)]
+pub struct TrackFusion {
+    opts: TrackFusionOptions,
+    scenes: HashMap<u64, SceneFusion>,
+    next_id: u64,
+}
+
+impl TrackFusion {
+    pub fn new(opts: TrackFusionOptions) -> Self {
+        Self {
+            opts,
+            scenes: HashMap::default(),
+            next_id: 0,
+        }
+    }
+
+    pub fn options(&self) -> TrackFusionOptions {
+        self.opts
+    }
+
+    /// Fuses the tracks the trackers reported for the epoch of the scene
+    ///
+    /// # Parameters
+    /// * `scene_id` - the scene of the tracks
+    /// * `primary` - the tracks of the primary tracker, its boxes are preferred
+    /// * `secondary` - the tracks of the secondary tracker
+    ///
+    /// # Returns
+    /// the fused tracks, the paired ones first, then the unpaired primary and secondary tracks
+    ///
+    pub fn fuse(
+        &mut self,
+        scene_id: u64,
+        primary: &[SortTrack],
+        secondary: &[SortTrack],
+    ) -> Vec<FusedTrack> {
+        let opts = self.opts;
+        let next_id = &mut self.next_id;
+        let scene = self.scenes.entry(scene_id).or_default();
+        scene.epoch += 1;
+        let epoch = scene.epoch;
+
+        let mut candidates = Vec::default();
+        for (pi, p) in primary.iter().enumerate() {
+            for (si, s) in secondary.iter().enumerate() {
+                let iou = Universal2DBox::calculate_metric_object(
+                    &Some(&p.observed_bbox),
+                    &Some(&s.observed_bbox),
+                )
+                .unwrap_or(0.0);
+                if iou >= opts.iou_threshold {
+                    let history = scene
+                        .pairs
+                        .get(&(p.id, s.id))
+                        .map(|h| h.co_occurrences)
+                        .unwrap_or(0);
+                    candidates.push((pi, si, history, iou));
+                }
+            }
+        }
+        candidates.sort_by(|(_, _, lh, l), (_, _, rh, r)| rh.cmp(lh).then(r.total_cmp(l)));
+
+        let mut primary_paired = vec![false; primary.len()];
+        let mut secondary_paired = vec![false; secondary.len()];
+        let mut used = HashSet::new();
+        let mut fused = Vec::default();
+        let mut allocate = |preferred: &[Option<u64>], used: &mut HashSet<u64>| {
+            let id = preferred
+                .iter()
+                .flatten()
+                .find(|id| !used.contains(*id))
+                .copied()
+                .unwrap_or_else(|| {
+                    *next_id += 1;
+                    *next_id
+                });
+            used.insert(id);
+            id
+        };
+
+        for (pi, si, _, _) in candidates {
+            if primary_paired[pi] || secondary_paired[si] {
+                continue;
+            }
+            primary_paired[pi] = true;
+            secondary_paired[si] = true;
+            let (p, s) = (&primary[pi], &secondary[si]);
+            let history = scene.pairs.entry((p.id, s.id)).or_insert(PairHistory {
+                co_occurrences: 0,
+                last_epoch: epoch,
+            });
+            history.co_occurrences += 1;
+            history.last_epoch = epoch;
+            let co_occurrences = history.co_occurrences;
+            let id = allocate(
+                &[
+                    scene.primary.get(&p.id).map(|(id, _)| *id),
+                    scene.secondary.get(&s.id).map(|(id, _)| *id),
+                ],
+                &mut used,
+            );
+            scene.primary.insert(p.id, (id, epoch));
+            scene.secondary.insert(s.id, (id, epoch));
+            fused.push(FusedTrack {
+                id,
+                scene_id,
+                primary_id: Some(p.id),
+                secondary_id: Some(s.id),
+                co_occurrences,
+                bbox: p.observed_bbox.clone(),
+            });
+        }
+
+        for (tracks, paired, is_primary) in [
+            (primary, &primary_paired, true),
+            (secondary, &secondary_paired, false),
+        ] {
+            for (t, _) in tracks.iter().zip(paired).filter(|(_, paired)| !**paired) {
+                let mapping = if is_primary {
+                    &mut scene.primary
+                } else {
+                    &mut scene.secondary
+                };
+                let id = allocate(&[mapping.get(&t.id).map(|(id, _)| *id)], &mut used);
+                mapping.insert(t.id, (id, epoch));
+                fused.push(FusedTrack {
+                    id,
+                    scene_id,
+                    primary_id: is_primary.then_some(t.id),
+                    secondary_id: (!is_primary).then_some(t.id),
+                    co_occurrences: 0,
+                    bbox: t.observed_bbox.clone(),
+                });
+            }
+        }
+
+        scene.forget(opts.max_idle_epochs);
+        fused
+    }
+
+    /// Forgets the history of the scene
+    ///
+    pub fn reset_scene(&mut self, scene_id: u64) {
+        self.scenes.remove(&scene_id);
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::{FusedTrack, TrackFusion, TrackFusionOptions};
+    use crate::trackers::sort::python::PySortTrack;
+    use crate::utils::bbox::python::PyUniversal2DBox;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "FusedTrack")]
+    #[derive(Debug, Clone)]
+    pub struct PyFusedTrack(pub(crate) FusedTrack);
+
+    #[pymethods]
+    impl PyFusedTrack {
+        #[getter]
+        fn id(&self) -> u64 {
+            self.0.id
+        }
+
+        #[getter]
+        fn scene_id(&self) -> u64 {
+            self.0.scene_id
+        }
+
+        #[getter]
+        fn primary_id(&self) -> Option<u64> {
+            self.0.primary_id
+        }
+
+        #[getter]
+        fn secondary_id(&self) -> Option<u64> {
+            self.0.secondary_id
+        }
+
+        #[getter]
+        fn co_occurrences(&self) -> usize {
+            self.0.co_occurrences
+        }
+
+        #[getter]
+        fn bbox(&self) -> PyUniversal2DBox {
+            PyUniversal2DBox(self.0.bbox.clone())
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+
+    #[pyclass]
+    #[pyo3(name = "TrackFusion")]
+    #[derive(Debug)]
+    pub struct PyTrackFusion(pub(crate) TrackFusion);
+
+    #[pymethods]
+    impl PyTrackFusion {
+        #[new]
+        #[pyo3(signature = (iou_threshold = 0.3, max_idle_epochs = 10))]
+        pub(crate) fn new(iou_threshold: f32, max_idle_epochs: usize) -> Self {
+            Self(TrackFusion::new(TrackFusionOptions::new(
+                iou_threshold,
+                max_idle_epochs,
+            )))
+        }
+
+        /// Fuses the tracks the trackers reported for the epoch of the scene
+        ///
+        #[pyo3(signature = (primary, secondary, scene_id = 0))]
+        pub fn fuse(
+            &mut self,
+            primary: Vec<PySortTrack>,
+            secondary: Vec<PySortTrack>,
+            scene_id: u64,
+        ) -> Vec<PyFusedTrack> {
+            let primary = primary.into_iter().map(|t| t.0).collect::<Vec<_>>();
+            let secondary = secondary.into_iter().map(|t| t.0).collect::<Vec<_>>();
+            self.0
+                .fuse(scene_id, &primary, &secondary)
+                .into_iter()
+                .map(PyFusedTrack)
+                .collect()
+        }
+
+        #[pyo3(signature = (scene_id))]
+        pub fn reset_scene(&mut self, scene_id: u64) {
+            self.0.reset_scene(scene_id)
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0.options())
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0.options())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::sort::{SortTrack, VotingType};
+    use crate::trackers::track_fusion::{FusedTrack, TrackFusion, TrackFusionOptions};
+    use crate::utils::bbox::BoundingBox;
+
+    fn track(id: u64, x: f32) -> SortTrack {
+        let bbox = BoundingBox::new(x, 0.0, 10.0, 20.0).as_xyaah();
+        SortTrack {
+            id,
+            epoch: 1,
+            predicted_bbox: bbox.clone(),
+            observed_bbox: bbox,
+            scene_id: 0,
+            length: 1,
+            voting_type: VotingType::Positional,
+            custom_object_id: None,
+            tentative: false,
+            class: None,
+            metadata: None,
+            created_epoch: 1,
+            created_timestamp: 0,
+            clipped: false,
+        }
+    }
+
+    fn ids(tracks: &[FusedTrack]) -> Vec<(u64, Option<u64>, Option<u64>)> {
+        let mut ids = tracks
+            .iter()
+            .map(|t| (t.id, t.primary_id, t.secondary_id))
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn fusion() {
+        let mut fusion = TrackFusion::new(TrackFusionOptions::new(0.3, 2));
+        let fused = fusion.fuse(0, &[track(1, 0.0), track(2, 100.0)], &[track(7, 1.0)]);
+        assert_eq!(ids(&fused), vec![(1, Some(1), Some(7)), (2, Some(2), None)]);
+
+        // the secondary tracker switched the id, the fused id is kept by the primary track
+        let fused = fusion.fuse(0, &[track(1, 0.0), track(2, 100.0)], &[track(8, 1.0)]);
+        assert_eq!(ids(&fused), vec![(1, Some(1), Some(8)), (2, Some(2), None)]);
+
+        // the primary tracker lost the object, the fused id is kept by the secondary track
+        let fused = fusion.fuse(0, &[track(2, 100.0)], &[track(8, 3.0)]);
+        assert_eq!(ids(&fused), vec![(1, None, Some(8)), (2, Some(2), None)]);
+
+        // the established pair is preferred over the pair with the better IoU
+        let fused = fusion.fuse(
+            0,
+            &[track(1, 2.0), track(3, 4.0), track(2, 100.0)],
+            &[track(8, 4.0)],
+        );
+        let pair = fused.iter().find(|t| t.secondary_id == Some(8)).unwrap();
+        assert_eq!(
+            (pair.id, pair.primary_id, pair.co_occurrences),
+            (1, Some(1), 2)
+        );
+        assert_eq!(fused.iter().filter(|t| t.id == 1).count(), 1);
+
+        // the other scenes are fused independently, the ids are unique across the scenes
+        let fused = fusion.fuse(1, &[track(1, 0.0)], &[]);
+        assert_eq!(ids(&fused), vec![(4, Some(1), None)]);
+    }
+
+    #[test]
+    fn forget() {
+        let mut fusion = TrackFusion::new(TrackFusionOptions::new(0.3, 1));
+        let first = fusion.fuse(0, &[track(1, 0.0)], &[])[0].id;
+        fusion.fuse(0, &[], &[]);
+        assert_eq!(fusion.fuse(0, &[track(1, 0.0)], &[])[0].id, first);
+        fusion.fuse(0, &[], &[]);
+        fusion.fuse(0, &[], &[]);
+        assert_ne!(fusion.fuse(0, &[track(1, 0.0)], &[])[0].id, first);
+    }
+}
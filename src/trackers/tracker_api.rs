@@ -1,3 +1,5 @@
+use crate::store::nan_distances::NanDistancePolicy;
+use crate::store::shard_stats::ShardDistanceStats;
 use crate::store::TrackStore;
 use crate::track::notify::ChangeNotifier;
 use crate::track::TrackStatus;
@@ -22,12 +24,26 @@ where
     fn get_main_store(&self) -> RwLockReadGuard<TrackStore<TA, M, OA, N>>;
     fn get_wasted_store(&self) -> RwLockReadGuard<TrackStore<TA, M, OA, N>>;
 
-    /// change auto waste job periodicity
+    /// The lookup matching all the tracks of the scene, `None` if the tracker can't select the
+    /// tracks of the scene: the epochs are announced to all the tracks and the sweeps of the
+    /// scenes check all the tracks
+    ///
+    fn scene_lookup(&self, _scene_id: u64) -> Option<TA::Lookup> {
+        None
+    }
+
+    /// The lookup matching the tracks of the scene which reached the lifetime limit, `None` if
+    /// the lifetime isn't limited
+    ///
+    fn expired_lookup(&self, _scene_id: u64) -> Option<TA::Lookup> {
+        None
+    }
+
+    /// change auto waste job periodicity, the scenes already seen are swept on their next
+    /// predictions
     ///
     fn set_auto_waste(&mut self, periodicity: usize) {
-        let obj = self.get_auto_waste_obj_mut();
-        obj.periodicity = periodicity;
-        obj.counter = 0;
+        self.get_auto_waste_obj_mut().restart(periodicity);
     }
 
     /// Skip number of epochs to force tracks to turn to terminal state
@@ -47,9 +63,68 @@ where
     ///
     fn skip_epochs_for_scene(&mut self, scene_id: u64, n: usize) {
         self.get_opts().skip_epochs_for_scene(scene_id, n);
+        if let Some(epoch) = self.get_opts().current_epoch_with_scene(scene_id) {
+            self.notify_epoch(scene_id, epoch);
+        }
         self.auto_waste();
     }
 
+    /// Notifies the tracks of the scene that the epoch of the scene advanced, the skipped epochs
+    /// are reported with the single notification of the last epoch
+    ///
+    fn notify_epoch(&self, scene_id: u64, epoch: usize) {
+        if self.get_opts().epoch_notifications() {
+            let store = self.get_main_store();
+            match self.scene_lookup(scene_id) {
+                Some(lookup) => store.on_epoch_with(epoch, scene_id, lookup),
+                None => store.on_epoch(epoch, scene_id),
+            }
+        }
+    }
+
+    /// The scenes which epochs are tracked, in ascending order
+    ///
+    fn scenes(&self) -> Vec<u64> {
+        self.get_opts().scenes()
+    }
+
+    /// Forgets the scene: its active tracks are moved to the wasted store and its epochs start
+    /// from the beginning. The wasted tracks of the scene must be fetched before the scene is used
+    /// again, otherwise they are not reported as wasted until the scene reaches their epochs.
+    ///
+    /// # Parameters
+    /// * `scene_id` - scene to reset
+    ///
+    fn reset_scene(&mut self, scene_id: u64) {
+        self.get_opts()
+            .skip_epochs_for_scene(scene_id, self.get_opts().max_idle_epochs() + 1);
+        self.auto_waste_scene(scene_id);
+        self.get_opts().reset_scene(scene_id);
+    }
+
+    /// Resets the scenes which passed more than `n` of their epochs since they lastly received the
+    /// observations. The active tracks of the scenes are moved to the wasted store within a single
+    /// pass over the main store.
+    ///
+    /// # Parameters
+    /// * `n` - the number of epochs the scene may stay idle
+    ///
+    /// # Returns
+    /// the reset scenes
+    ///
+    fn clear_idle_scenes(&mut self, n: usize) -> Vec<u64> {
+        let scenes = self.get_opts().idle_scenes(n);
+        let opts = self.get_opts();
+        for scene_id in &scenes {
+            opts.skip_epochs_for_scene(*scene_id, opts.max_idle_epochs() + 1);
+        }
+        self.auto_waste_scenes(&scenes);
+        for scene_id in &scenes {
+            self.get_opts().reset_scene(*scene_id);
+        }
+        scenes
+    }
+
     /// Get the current epoch for `scene_id` == 0
     ///
     fn current_epoch(&self) -> usize {
@@ -78,8 +153,106 @@ where
         self.get_main_store_mut().fetch_tracks(&wasted)
     }
 
+    /// Receive the tracks of the scene with expired life from the main store, only the tracks of
+    /// the scene are checked
+    ///
+    /// # Parameters
+    /// * `scene_id` - scene id
+    ///
+    fn get_main_store_wasted_with_scene(&mut self, scene_id: u64) -> Vec<Track<TA, M, OA, N>> {
+        self.get_main_store_wasted_with_scenes(&[scene_id])
+    }
+
+    /// Receive the tracks of the scenes with expired life from the main store, the tracks are
+    /// grouped by the scenes within a single pass over the store and only the tracks of the scenes
+    /// are checked. All the tracks are checked if the tracker has no [scene_lookup](Self::scene_lookup).
+    ///
+    /// # Parameters
+    /// * `scenes` - scene ids
+    ///
+    fn get_main_store_wasted_with_scenes(&mut self, scenes: &[u64]) -> Vec<Track<TA, M, OA, N>> {
+        let Some(lookups) = scenes
+            .iter()
+            .map(|scene_id| self.scene_lookup(*scene_id))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return self.get_main_store_wasted();
+        };
+        let wasted = self
+            .get_main_store()
+            .lookup_grouped(lookups)
+            .into_iter()
+            .flatten()
+            .filter(|(_, status)| matches!(status, Ok(TrackStatus::Wasted)))
+            .map(|(track, _)| track)
+            .collect::<Vec<_>>();
+
+        self.get_main_store_mut().fetch_tracks(&wasted)
+    }
+
+    /// Called with the tracks moved from the main store to the wasted store
+    ///
+    fn on_tracks_wasted(&mut self, _tracks: &[Track<TA, M, OA, N>]) {}
+
     fn auto_waste(&mut self) {
         let tracks = self.get_main_store_wasted();
+        self.move_to_wasted(tracks);
+    }
+
+    /// Moves the wasted tracks of the scene to the wasted store
+    ///
+    /// # Parameters
+    /// * `scene_id` - scene id
+    ///
+    fn auto_waste_scene(&mut self, scene_id: u64) {
+        self.auto_waste_scenes(&[scene_id]);
+    }
+
+    /// Moves the wasted tracks of the scenes to the wasted store within a single pass over the
+    /// main store
+    ///
+    /// # Parameters
+    /// * `scenes` - scene ids
+    ///
+    fn auto_waste_scenes(&mut self, scenes: &[u64]) {
+        if scenes.is_empty() {
+            return;
+        }
+        let tracks = self.get_main_store_wasted_with_scenes(scenes);
+        self.move_to_wasted(tracks);
+    }
+
+    /// Moves the tracks of the scene which reached the lifetime limit to the wasted store. Unlike
+    /// the periodic auto-waste the check runs on every prediction of the scene, so the expired
+    /// tracks are never kept in the main store.
+    ///
+    /// # Parameters
+    /// * `scene_id` - scene id
+    ///
+    fn waste_expired_scene(&mut self, scene_id: u64) {
+        let Some(lookup) = self.expired_lookup(scene_id) else {
+            return;
+        };
+        let expired = self
+            .get_main_store()
+            .lookup(lookup)
+            .into_iter()
+            .map(|(track, _)| track)
+            .collect::<Vec<_>>();
+        if expired.is_empty() {
+            return;
+        }
+        let tracks = self.get_main_store_mut().fetch_tracks(&expired);
+        self.move_to_wasted(tracks);
+    }
+
+    /// Moves the tracks fetched from the main store to the wasted store
+    ///
+    fn move_to_wasted(&mut self, mut tracks: Vec<Track<TA, M, OA, N>>) {
+        for t in &mut tracks {
+            t.on_wasted();
+        }
+        self.on_tracks_wasted(&tracks);
         for t in tracks {
             self.get_wasted_store_mut()
                 .add_track(t)
@@ -105,6 +278,30 @@ where
         self.get_main_store().shard_stats()
     }
 
+    /// Get the distance computation counters of the main store per shard
+    ///
+    fn distance_stats(&self) -> Vec<ShardDistanceStats> {
+        self.get_main_store().distance_stats()
+    }
+
+    /// Resets the distance computation counters of the main store
+    ///
+    fn reset_distance_stats(&self) {
+        self.get_main_store().reset_distance_stats()
+    }
+
+    /// Sets the handling of the NaN distances returned by the metric
+    ///
+    fn set_nan_distance_policy(&mut self, policy: NanDistancePolicy) {
+        self.get_main_store_mut().set_nan_distance_policy(policy)
+    }
+
+    /// The handling of the NaN distances returned by the metric
+    ///
+    fn nan_distance_policy(&self) -> NanDistancePolicy {
+        self.get_main_store().nan_distance_policy()
+    }
+
     /// Get the amount of tracks kept in wasted store per shard
     ///
     fn wasted_shard_stats(&self) -> Vec<usize> {
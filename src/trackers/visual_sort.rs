@@ -1,7 +1,9 @@
 use std::borrow::Cow;
 
+use serde_json::Value;
+
 use crate::{
-    track::{utils::FromVec, Track},
+    track::{notify::ChangeNotifier, utils::FromVec, Track},
     utils::bbox::Universal2DBox,
 };
 
@@ -27,16 +29,36 @@ pub mod observation_attributes;
 pub mod simple_api;
 
 /// Batched API that accepts the batch with multiple scenes at once
+#[cfg(feature = "batch")]
 pub mod batch_api;
 /// Options object to configure the tracker
 pub mod options;
 
+/// Appearance-only reactivation of the tracks idle after the long occlusions
+pub mod reactivation;
+
+/// Visual threshold adapted to the distances between the features of the track
+pub mod adaptive_threshold;
+
+/// Export of the aggregated track embeddings to the external vector databases
+pub mod vector_store;
+
+/// Compressed tier of the long idle tracks kept for the re-identification
+pub mod cold_store;
+
+/// Per-scene estimation of the feature covariance with the optional whitening of the features
+pub mod whitening;
+
 #[derive(Debug, Clone)]
 pub struct VisualSortObservation<'a> {
     feature: Option<Cow<'a, [f32]>>,
     feature_quality: Option<f32>,
     bounding_box: Universal2DBox,
     custom_object_id: Option<i64>,
+    depth: Option<f32>,
+    class_id: Option<i64>,
+    metadata: Option<Value>,
+    ignored: bool,
 }
 
 impl<'a> VisualSortObservation<'a> {
@@ -51,6 +73,55 @@ impl<'a> VisualSortObservation<'a> {
             feature_quality,
             bounding_box,
             custom_object_id,
+            depth: None,
+            class_id: None,
+            metadata: None,
+            ignored: false,
+        }
+    }
+
+    /// Sets the distance from the observer to the object (from the stereo or monodepth model)
+    ///
+    pub fn with_depth(mut self, depth: f32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Sets the class label of the object, the majority class of the track is reported
+    ///
+    pub fn with_class_id(mut self, class_id: i64) -> Self {
+        self.class_id = Some(class_id);
+        self
+    }
+
+    /// Attaches the user metadata to the observation, it is reported with the track
+    ///
+    pub fn with_metadata(mut self, metadata: Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Marks the observation as ignored, e.g. the detection inside the ignore zone of the scene.
+    /// The ignored observation neither creates the track nor updates the existing one, however
+    /// it is accounted by the scene density and the occlusion estimation.
+    ///
+    pub fn with_ignored(mut self, ignored: bool) -> Self {
+        self.ignored = ignored;
+        self
+    }
+
+    /// Converts the observation to the one owning the feature
+    ///
+    pub fn into_owned(self) -> VisualSortObservation<'static> {
+        VisualSortObservation {
+            feature: self.feature.map(|f| Cow::Owned(f.into_owned())),
+            feature_quality: self.feature_quality,
+            bounding_box: self.bounding_box,
+            custom_object_id: self.custom_object_id,
+            depth: self.depth,
+            class_id: self.class_id,
+            metadata: self.metadata,
+            ignored: self.ignored,
         }
     }
 }
@@ -117,28 +188,65 @@ pub struct WastedVisualSortTrack {
     /// history of features
     ///
     pub observed_features: Vec<Option<Vec<f32>>>,
+
+    /// epochs when the observed boxes were collected
+    ///
+    pub observed_epochs: Vec<usize>,
+
+    /// wall-clock times (milliseconds since UNIX epoch) when the latest observed boxes were
+    /// collected, empty unless the observation timestamps are enabled
+    ///
+    pub observed_timestamps: Vec<u64>,
+
+    /// the user metadata attached to the observations of the track
+    ///
+    pub metadata: Option<Value>,
+
+    /// the epoch when the track was created
+    ///
+    pub created_epoch: usize,
+
+    /// the wall-clock time (milliseconds since UNIX epoch) when the track was created
+    ///
+    pub created_timestamp: u64,
+
+    /// the epoch when the track was wasted
+    ///
+    pub wasted_epoch: Option<usize>,
+
+    /// the wall-clock time (milliseconds since UNIX epoch) when the track was wasted
+    ///
+    pub wasted_timestamp: Option<u64>,
 }
 
-impl From<Track<VisualAttributes, VisualMetric, VisualObservationAttributes>>
+impl<N: ChangeNotifier> From<Track<VisualAttributes, VisualMetric, VisualObservationAttributes, N>>
     for WastedVisualSortTrack
 {
-    fn from(track: Track<VisualAttributes, VisualMetric, VisualObservationAttributes>) -> Self {
+    fn from(track: Track<VisualAttributes, VisualMetric, VisualObservationAttributes, N>) -> Self {
         let attrs = track.get_attributes();
+        let (observed_bbox, predicted_bbox) = attrs.last_boxes();
         WastedVisualSortTrack {
             id: track.get_track_id(),
             epoch: attrs.last_updated_epoch,
             scene_id: attrs.scene_id,
             length: attrs.track_length,
-            observed_bbox: attrs.observed_boxes.back().unwrap().clone(),
-            predicted_bbox: attrs.predicted_boxes.back().unwrap().clone(),
-            predicted_boxes: attrs.predicted_boxes.clone().into_iter().collect(),
-            observed_boxes: attrs.observed_boxes.clone().into_iter().collect(),
+            observed_bbox,
+            predicted_bbox,
+            predicted_boxes: attrs.predicted_boxes_history(),
+            observed_boxes: attrs.observed_boxes_history(),
             observed_features: attrs
                 .observed_features
                 .clone()
                 .iter()
                 .map(|f_opt| f_opt.as_ref().map(Vec::from_vec))
                 .collect(),
+            observed_epochs: attrs.observed_epochs.clone().into_iter().collect(),
+            observed_timestamps: attrs.observed_timestamps.clone().into_iter().collect(),
+            metadata: attrs.metadata.clone(),
+            created_epoch: attrs.created_epoch,
+            created_timestamp: attrs.created_timestamp,
+            wasted_epoch: attrs.wasted_epoch,
+            wasted_timestamp: attrs.wasted_timestamp,
         }
     }
 }
@@ -146,6 +254,7 @@ impl From<Track<VisualAttributes, VisualMetric, VisualObservationAttributes>>
 #[cfg(feature = "python")]
 pub mod python {
     use super::{VisualSortObservation, VisualSortObservationSet, WastedVisualSortTrack};
+    use crate::trackers::metadata::python::{from_py, to_py};
     use crate::utils::bbox::python::PyUniversal2DBox;
     use pyo3::prelude::*;
     use std::borrow::Cow;
@@ -211,6 +320,41 @@ pub mod python {
         fn observed_features(&self) -> Vec<Option<Vec<f32>>> {
             self.0.observed_features.clone()
         }
+
+        #[getter]
+        fn observed_epochs(&self) -> Vec<usize> {
+            self.0.observed_epochs.clone()
+        }
+
+        #[getter]
+        fn observed_timestamps(&self) -> Vec<u64> {
+            self.0.observed_timestamps.clone()
+        }
+
+        #[getter]
+        fn metadata(&self, py: Python) -> PyResult<PyObject> {
+            to_py(py, &self.0.metadata)
+        }
+
+        #[getter]
+        fn created_epoch(&self) -> usize {
+            self.0.created_epoch
+        }
+
+        #[getter]
+        fn created_timestamp(&self) -> u64 {
+            self.0.created_timestamp
+        }
+
+        #[getter]
+        fn wasted_epoch(&self) -> Option<usize> {
+            self.0.wasted_epoch
+        }
+
+        #[getter]
+        fn wasted_timestamp(&self) -> Option<u64> {
+            self.0.wasted_timestamp
+        }
     }
 
     #[pyclass]
@@ -221,19 +365,29 @@ pub mod python {
     #[pymethods]
     impl PyVisualSortObservation {
         #[new]
-        #[pyo3(signature = (feature, feature_quality, bounding_box, custom_object_id))]
+        #[pyo3(signature = (feature, feature_quality, bounding_box, custom_object_id, depth = None, class_id = None, metadata = None, ignored = false))]
+        #[allow(clippy::too_many_arguments)]
         pub fn new(
+            py: Python,
             feature: Option<Vec<f32>>,
             feature_quality: Option<f32>,
             bounding_box: PyUniversal2DBox,
             custom_object_id: Option<i64>,
-        ) -> Self {
-            Self(VisualSortObservation {
+            depth: Option<f32>,
+            class_id: Option<i64>,
+            metadata: Option<&PyAny>,
+            ignored: bool,
+        ) -> PyResult<Self> {
+            Ok(Self(VisualSortObservation {
                 feature: feature.map(Cow::Owned),
                 feature_quality,
                 bounding_box: bounding_box.0,
                 custom_object_id,
-            })
+                depth,
+                class_id,
+                metadata: metadata.map(|m| from_py(py, m)).transpose()?,
+                ignored,
+            }))
         }
 
         #[classattr]
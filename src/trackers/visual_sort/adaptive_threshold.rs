@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// The visual threshold adapted to the tightness of the track embeddings.
+///
+/// Every feature collected by the track records its distance to the closest feature the track
+/// already kept. When the track has at least `min_samples` distances recorded, the candidates are
+/// accepted visually if their distance is within `mean + k * std` of the last `window` distances,
+/// otherwise the global visual threshold is used.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AdaptiveVisualThreshold {
+    pub k: f32,
+    pub window: usize,
+    pub min_samples: usize,
+}
+
+impl AdaptiveVisualThreshold {
+    pub fn new(k: f32, window: usize, min_samples: usize) -> Self {
+        assert!(k >= 0.0, "K must be a non-negative number");
+        assert!(
+            min_samples > 1 && window >= min_samples,
+            "Min samples must be greater than 1 and window must be not less than min samples"
+        );
+        Self {
+            k,
+            window,
+            min_samples,
+        }
+    }
+
+    /// The distance threshold of the track, `None` until the track collects enough distances
+    ///
+    pub fn threshold(&self, stats: &VisualDistanceStats) -> Option<f32> {
+        if stats.len() < self.min_samples {
+            return None;
+        }
+        Some(stats.mean()? + self.k * stats.std()?)
+    }
+}
+
+/// Rolling statistics of the distances between the features collected by the track
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VisualDistanceStats {
+    distances: VecDeque<f32>,
+}
+
+impl VisualDistanceStats {
+    /// Records the distance, keeping at most `window` latest distances
+    ///
+    pub fn record(&mut self, distance: f32, window: usize) {
+        self.distances.push_back(distance);
+        while self.distances.len() > window {
+            self.distances.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.distances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.distances.is_empty()
+    }
+
+    pub fn mean(&self) -> Option<f32> {
+        if self.distances.is_empty() {
+            return None;
+        }
+        Some(self.distances.iter().sum::<f32>() / self.distances.len() as f32)
+    }
+
+    /// The standard deviation of the distances
+    ///
+    pub fn std(&self) -> Option<f32> {
+        let mean = self.mean()?;
+        let var = self
+            .distances
+            .iter()
+            .map(|d| (d - mean) * (d - mean))
+            .sum::<f32>()
+            / self.distances.len() as f32;
+        Some(var.sqrt())
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::AdaptiveVisualThreshold;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "AdaptiveVisualThreshold")]
+    #[derive(Clone, Copy, Debug)]
+    pub struct PyAdaptiveVisualThreshold(pub AdaptiveVisualThreshold);
+
+    #[pymethods]
+    impl PyAdaptiveVisualThreshold {
+        #[new]
+        #[pyo3(signature = (k, window, min_samples))]
+        fn new(k: f32, window: usize, min_samples: usize) -> Self {
+            PyAdaptiveVisualThreshold(AdaptiveVisualThreshold::new(k, window, min_samples))
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{self:?}")
+        }
+
+        fn __str__(&self) -> String {
+            format!("{self:#?}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::visual_sort::adaptive_threshold::{
+        AdaptiveVisualThreshold, VisualDistanceStats,
+    };
+    use crate::EPS;
+
+    #[test]
+    fn threshold() {
+        let adaptive = AdaptiveVisualThreshold::new(2.0, 4, 3);
+        let mut stats = VisualDistanceStats::default();
+        stats.record(1.0, adaptive.window);
+        stats.record(3.0, adaptive.window);
+        assert!(adaptive.threshold(&stats).is_none());
+
+        stats.record(2.0, adaptive.window);
+        stats.record(2.0, adaptive.window);
+        stats.record(2.0, adaptive.window);
+        assert_eq!(stats.len(), 4);
+        let std = (0.75f32 / 4.0).sqrt();
+        assert!((stats.mean().unwrap() - 2.25).abs() < EPS);
+        assert!((stats.std().unwrap() - std).abs() < EPS);
+        assert!((adaptive.threshold(&stats).unwrap() - (2.25 + 2.0 * std)).abs() < EPS);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_window() {
+        AdaptiveVisualThreshold::new(1.0, 2, 3);
+    }
+}
@@ -1,34 +1,42 @@
 use crate::prelude::{
-    NoopNotifier, ObservationBuilder, PositionalMetricType, SortTrack, TrackStoreBuilder,
-    VisualSortObservation, VisualSortOptions,
+    NoopNotifier, ObservationBuilder, SortTrack, TrackStoreBuilder, VisualSortObservation,
+    VisualSortOptions,
 };
 use crate::store::track_distance::TrackDistanceOkIterator;
 use crate::store::TrackStore;
 use crate::track::utils::FromVec;
 use crate::track::{Feature, Track};
-use crate::trackers::batch::{PredictionBatchRequest, PredictionBatchResult, SceneTracks};
-use crate::trackers::epoch_db::EpochDb;
-use crate::trackers::sort::{
-    AutoWaste, SortAttributesOptions, DEFAULT_AUTO_WASTE_PERIODICITY,
-    MAHALANOBIS_NEW_TRACK_THRESHOLD,
+use crate::trackers::batch::{
+    join_voting_threads, wait_batch_idle, wait_batch_processed, BatchShutdownReport,
+    PredictionBatchRequest, PredictionBatchResult, SceneAssignedTracks,
 };
+use crate::trackers::candidate_assignment::CandidateAssignment;
+use crate::trackers::epoch_db::EpochDb;
+use crate::trackers::observation_intake::ObservationIntake;
+use crate::trackers::predict_overrides::PredictOverrides;
+use crate::trackers::sort::{AutoWaste, SortAttributesOptions, DEFAULT_AUTO_WASTE_PERIODICITY};
 use crate::trackers::tracker_api::TrackerAPI;
 use crate::trackers::visual_sort::metric::{VisualMetric, VisualMetricOptions};
 use crate::trackers::visual_sort::observation_attributes::VisualObservationAttributes;
+use crate::trackers::visual_sort::reactivation::{reactivate, ReactivationOptions};
 use crate::trackers::visual_sort::track_attributes::{
     VisualAttributes, VisualAttributesUpdate, VisualSortLookup,
 };
-use crate::trackers::visual_sort::voting::VisualVoting;
+use crate::trackers::visual_sort::voting::{effective_min_votes, track_min_votes, VisualVoting};
+use crate::trackers::watchdog::{SharedWatchdog, Stall, Watchdog, WatchdogOptions, Worker};
 use crate::utils::clipping::bbox_own_areas::{
     exclusively_owned_areas, exclusively_owned_areas_normalized_shares,
 };
 use crate::voting::Voting;
+use crate::Errors;
+use anyhow::Result;
 use crossbeam::channel::{Receiver, Sender};
 use log::warn;
 use rand::Rng;
 use std::mem;
 use std::sync::{Arc, Condvar, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::thread::{spawn, JoinHandle};
+use std::time::{Duration, Instant};
 
 type VotingSenderChannel = Sender<VotingCommands>;
 type VotingReceiverChannel = Receiver<VotingCommands>;
@@ -42,9 +50,12 @@ enum VotingCommands {
     Distances {
         scene_id: u64,
         distances: TrackDistanceOkIterator<VisualObservationAttributes>,
-        channel: Sender<SceneTracks>,
+        channel: Sender<SceneAssignedTracks>,
         tracks: Vec<MiddlewareSortTrack>,
+        indices: Vec<usize>,
+        observations: Vec<VisualSortObservation<'static>>,
         monitor: BatchBusyMonitor,
+        started: Instant,
     },
     Exit,
 }
@@ -57,8 +68,13 @@ pub struct BatchVisualSort {
     wasted_store: RwLock<MiddlewareVisualSortTrackStore>,
     metric_opts: Arc<VisualMetricOptions>,
     track_opts: Arc<SortAttributesOptions>,
+    intake: ObservationIntake,
     voting_threads: Vec<(VotingSenderChannel, JoinHandle<()>)>,
+    voting_queues: Vec<VotingReceiverChannel>,
+    voting_finished: Receiver<()>,
     auto_waste: AutoWaste,
+    watchdog: SharedWatchdog,
+    shut_down: bool,
 }
 
 impl Drop for BatchVisualSort {
@@ -75,10 +91,13 @@ impl Drop for BatchVisualSort {
 }
 
 fn voting_thread(
+    thread_id: usize,
     store: Arc<RwLock<MiddlewareVisualSortTrackStore>>,
     rx: VotingReceiverChannel,
     metric_opts: Arc<VisualMetricOptions>,
-    track_id: Arc<RwLock<u64>>,
+    track_opts: Arc<SortAttributesOptions>,
+    reactivation: Option<ReactivationOptions>,
+    watchdog: SharedWatchdog,
 ) {
     while let Ok(command) = rx.recv() {
         match command {
@@ -87,33 +106,54 @@ fn voting_thread(
                 distances,
                 channel,
                 tracks,
+                indices,
                 monitor,
+                started,
+                ..
             } => {
+                let _watch = watchdog.watch(Worker::Voting(thread_id));
+                let distances = distances.collect::<Vec<_>>();
+                let overrides = track_opts.predict_overrides(scene_id);
                 let voting = VisualVoting::new(
-                    match metric_opts.positional_kind {
-                        PositionalMetricType::Mahalanobis => MAHALANOBIS_NEW_TRACK_THRESHOLD,
-                        PositionalMetricType::IoU(t) => t,
-                    },
+                    track_opts.positional_threshold(scene_id, metric_opts.positional_kind),
                     f32::MAX,
-                    metric_opts.visual_min_votes,
-                );
-                let winners = voting.winners(distances);
+                    overrides.visual_min_votes(metric_opts.visual_min_votes),
+                )
+                .with_track_min_votes(track_min_votes(
+                    &store.read().unwrap(),
+                    &distances,
+                    &metric_opts,
+                    &track_opts,
+                    None,
+                ))
+                .with_fusion(metric_opts.visual_fusion);
+                let mut winners = voting.winners(distances);
+                if let Some(reactivation) = &reactivation {
+                    reactivate(
+                        &mut store.write().unwrap(),
+                        &tracks,
+                        &mut winners,
+                        &voting,
+                        reactivation,
+                    );
+                }
                 let mut res = Vec::default();
-                for mut t in tracks {
+                let mut assignments = Vec::default();
+                for (index, mut t) in indices.into_iter().zip(tracks) {
                     let source = t.get_track_id();
 
-                    let tid = {
-                        let mut track_id = track_id.write().unwrap();
-                        *track_id += 1;
-                        *track_id
-                    };
+                    let tid = store
+                        .read()
+                        .expect("Access to store must always succeed")
+                        .reserve_track_ids(1)
+                        .start;
 
-                    let track_id: u64 = if let Some(dest) = winners.get(&source) {
+                    let (track_id, new_track) = if let Some(dest) = winners.get(&source) {
                         let (dest, vt) = dest[0];
                         if dest == source {
                             t.set_track_id(tid);
                             store.write().unwrap().add_track(t).unwrap();
-                            tid
+                            (tid, true)
                         } else {
                             t.add_observation(
                                 0,
@@ -125,15 +165,16 @@ fn voting_thread(
                             store
                                 .write()
                                 .unwrap()
-                                .merge_external(dest, &t, Some(&[0]), false)
+                                .merge_external_owned(dest, t, Some(&[0]), false)
                                 .unwrap();
-                            dest
+                            (dest, false)
                         }
                     } else {
                         t.set_track_id(tid);
                         store.write().unwrap().add_track(t).unwrap();
-                        tid
+                        (tid, true)
                     };
+                    assignments.push(CandidateAssignment::new(index, track_id, new_track));
 
                     let lock = store.read().unwrap();
                     let store = lock.get_store(track_id as usize);
@@ -142,7 +183,10 @@ fn voting_thread(
                     res.push(SortTrack::from(track))
                 }
 
-                let res = channel.send((scene_id, res));
+                track_opts.record_quality(scene_id, &res);
+                let res = track_opts.filter_output(res);
+                track_opts.record_predict_latency(scene_id, started.elapsed(), rx.len());
+                let res = channel.send((scene_id, res, assignments));
                 if let Err(e) = res {
                     warn!("Unable to send results to a caller, likely the caller already closed the channel. Error is: {:?}", e);
                 }
@@ -167,6 +211,7 @@ impl BatchVisualSort {
                 .default_attributes(VisualAttributes::new(track_opts.clone()))
                 .metric(metric.clone())
                 .notifier(NoopNotifier)
+                .nan_distance_policy(opts.get_nan_distance_policy())
                 .build(),
         ));
 
@@ -178,19 +223,33 @@ impl BatchVisualSort {
                 .build(),
         );
 
-        let track_id = Arc::new(RwLock::new(0));
-
+        let watchdog = SharedWatchdog::default();
+        let (finished, voting_finished) = crossbeam::channel::bounded(0);
+        let mut voting_queues = Vec::with_capacity(voting_shards);
         let voting_threads = (0..voting_shards)
-            .map(|_e| {
+            .map(|thread_id| {
                 let (tx, rx) = crossbeam::channel::unbounded();
+                voting_queues.push(rx.clone());
                 let thread_store = store.clone();
-                let thread_track_id = track_id.clone();
                 let thread_metric_opts = metric_opts.clone();
+                let thread_track_opts = track_opts.clone();
+                let thread_reactivation = opts.get_reactivation();
+                let thread_watchdog = watchdog.clone();
+                let thread_finished: Sender<()> = finished.clone();
 
                 (
                     tx,
                     spawn(move || {
-                        voting_thread(thread_store, rx, thread_metric_opts, thread_track_id)
+                        let _finished = thread_finished;
+                        voting_thread(
+                            thread_id,
+                            thread_store,
+                            rx,
+                            thread_metric_opts,
+                            thread_track_opts,
+                            thread_reactivation,
+                            thread_watchdog,
+                        )
                     }),
                 )
             })
@@ -202,26 +261,162 @@ impl BatchVisualSort {
             wasted_store,
             track_opts,
             metric_opts,
+            intake: opts.observation_intake(),
             voting_threads,
-            auto_waste: AutoWaste {
-                periodicity: DEFAULT_AUTO_WASTE_PERIODICITY,
-                counter: DEFAULT_AUTO_WASTE_PERIODICITY,
-            },
+            voting_queues,
+            voting_finished,
+            auto_waste: AutoWaste::new(DEFAULT_AUTO_WASTE_PERIODICITY),
+            watchdog,
+            shut_down: false,
         }
     }
 
-    pub fn predict(&mut self, batch_request: PredictionBatchRequest<VisualSortObservation>) {
-        if self.auto_waste.counter == 0 {
-            self.auto_waste();
-            self.auto_waste.counter = self.auto_waste.periodicity;
-        } else {
-            self.auto_waste.counter -= 1;
+    /// Stops the tracker gracefully
+    ///
+    /// New batches are not accepted after the call. The in-flight batch is processed and its
+    /// results are sent until the deadline is reached, the scenes which processing is not started
+    /// by the deadline are returned, the voting threads are joined until the deadline and detached
+    /// if they don't stop in time.
+    ///
+    /// # Parameters
+    /// * `timeout` - the time given for the draining and the threads shutdown
+    ///
+    pub fn shutdown(
+        &mut self,
+        timeout: Duration,
+    ) -> BatchShutdownReport<VisualSortObservation<'static>> {
+        let deadline = Instant::now() + timeout;
+        self.shut_down = true;
+
+        if !wait_batch_processed(self.monitor.as_ref(), deadline) {
+            warn!("The batch is not processed before the shutdown deadline");
+        }
+
+        let mut unprocessed = Vec::default();
+        for rx in mem::take(&mut self.voting_queues) {
+            while let Ok(command) = rx.try_recv() {
+                if let VotingCommands::Distances {
+                    scene_id,
+                    observations,
+                    ..
+                } = command
+                {
+                    unprocessed.push((scene_id, observations));
+                }
+            }
+        }
+
+        let detached_threads = join_voting_threads(
+            mem::take(&mut self.voting_threads),
+            || VotingCommands::Exit,
+            &self.voting_finished,
+            deadline,
+        );
+        if detached_threads > 0 {
+            warn!(
+                "{} voting threads didn't stop before the shutdown deadline and were detached",
+                detached_threads
+            );
         }
 
-        if let Some(m) = &self.monitor {
-            let (lock, cvar) = &**m;
-            let _guard = cvar.wait_while(lock.lock().unwrap(), |v| *v > 0).unwrap();
+        BatchShutdownReport {
+            unprocessed,
+            detached_threads,
         }
+    }
+
+    /// The number of duplicate custom_object_id found since the tracker creation
+    ///
+    pub fn duplicate_object_ids(&self) -> usize {
+        self.intake.duplicate_object_ids()
+    }
+
+    /// Sets the overrides of the configuration applied to the predictions of the scene until
+    /// they are removed. The overrides must be kept until the results of the predictions they are
+    /// set for are received
+    ///
+    /// # Parameters
+    /// * `scene_id` - the scene the overrides are applied to
+    /// * `overrides` - the overrides, `None` restores the configured behavior
+    ///
+    pub fn set_predict_overrides(&mut self, scene_id: u64, overrides: Option<PredictOverrides>) {
+        self.track_opts.set_predict_overrides(scene_id, overrides);
+    }
+
+    /// Enables or disables the watchdog that reports the distance computation and the voting
+    /// threads that made no progress for the stall timeout
+    ///
+    pub fn set_watchdog(&mut self, opts: Option<WatchdogOptions>) {
+        self.watchdog.set(opts);
+    }
+
+    /// Returns the options of the watchdog, `None` when the watchdog is disabled
+    ///
+    pub fn watchdog_options(&self) -> Option<WatchdogOptions> {
+        self.watchdog.options()
+    }
+
+    /// The number of the stalled threads detected by the watchdog
+    ///
+    pub fn watchdog_stalls(&self) -> usize {
+        self.watchdog.stalls()
+    }
+
+    /// The channel the stalls detected by the watchdog are sent to, `None` when the watchdog is
+    /// disabled
+    ///
+    pub fn subscribe_stalls(&self) -> Option<Receiver<Stall>> {
+        self.watchdog.subscribe()
+    }
+
+    /// Runs the prediction of the batch, waiting until the previous batch is processed
+    ///
+    /// The waiting is unbounded, the stalls detected by the [watchdog](Self::set_watchdog) are
+    /// only reported, use [try_predict](Self::try_predict) to abandon the batch on the stall
+    ///
+    /// # Panics
+    /// When the tracker is [shut down](Self::shutdown)
+    ///
+    pub fn predict(&mut self, batch_request: PredictionBatchRequest<VisualSortObservation>) {
+        self.predict_watched(batch_request, None)
+            .expect("The batch must be accepted by the running tracker");
+    }
+
+    /// Runs the prediction of the batch
+    ///
+    /// # Returns
+    /// * [WorkerStalled](crate::Errors::WorkerStalled) error when the [watchdog](Self::set_watchdog)
+    ///   detects the stalled thread while the previous batch is processed, the batch is not
+    ///   started then
+    /// * [TrackerShutDown](crate::Errors::TrackerShutDown) error when the tracker is
+    ///   [shut down](Self::shutdown)
+    ///
+    pub fn try_predict(
+        &mut self,
+        batch_request: PredictionBatchRequest<VisualSortObservation>,
+    ) -> Result<()> {
+        let watchdog = self.watchdog.get();
+        self.predict_watched(batch_request, watchdog.as_deref())
+    }
+
+    fn predict_watched(
+        &mut self,
+        batch_request: PredictionBatchRequest<VisualSortObservation>,
+        watchdog: Option<&Watchdog>,
+    ) -> Result<()> {
+        if self.shut_down {
+            return Err(Errors::TrackerShutDown.into());
+        }
+        let started = Instant::now();
+        let due = batch_request
+            .get_batch()
+            .keys()
+            .filter(|scene_id| self.auto_waste.tick(**scene_id))
+            .copied()
+            .collect::<Vec<_>>();
+        self.auto_waste_scenes(&due);
+
+        wait_batch_idle(self.monitor.as_ref(), watchdog)?;
 
         self.monitor = Some(Arc::new((
             Mutex::new(batch_request.batch_size()),
@@ -229,6 +424,30 @@ impl BatchVisualSort {
         )));
 
         for (i, (scene_id, observations)) in batch_request.get_batch().iter().enumerate() {
+            let epoch = self.track_opts.next_epoch(*scene_id).unwrap();
+            self.notify_epoch(*scene_id, epoch);
+            self.waste_expired_scene(*scene_id);
+            if !observations.is_empty() {
+                self.track_opts.mark_scene_active(*scene_id);
+            }
+            let (indices, observations): (Vec<_>, Vec<_>) = self
+                .intake
+                .prepare(
+                    *scene_id,
+                    epoch,
+                    observations,
+                    |o| &o.bounding_box,
+                    |o, bounding_box| VisualSortObservation {
+                        bounding_box,
+                        ..o.clone()
+                    },
+                    |o| (o.custom_object_id, o.bounding_box.confidence),
+                )
+                .into_iter()
+                .unzip();
+            self.track_opts
+                .record_density(*scene_id, observations.iter().map(|o| &o.bounding_box));
+
             let mut percentages = Vec::default();
             let use_own_area_percentage =
                 self.metric_opts.visual_minimal_own_area_percentage_collect
@@ -249,11 +468,18 @@ impl BatchVisualSort {
             }
 
             let mut rng = rand::thread_rng();
-            let epoch = self.track_opts.next_epoch(*scene_id).unwrap();
 
+            // the ignored observations are accounted by the own areas, but get no candidates
+            let indices = indices
+                .into_iter()
+                .zip(&observations)
+                .filter(|(_, o)| !o.ignored)
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
             let tracks = observations
                 .iter()
                 .enumerate()
+                .filter(|(_, o)| !o.ignored)
                 .map(|(i, o)| {
                     self.store
                         .read()
@@ -272,11 +498,14 @@ impl BatchVisualSort {
                                         o.feature_quality.unwrap_or(1.0),
                                         o.bounding_box.clone(),
                                     )
-                                },
+                                }
+                                .with_depth(o.depth),
                             );
 
                             if let Some(feature) = &o.feature {
-                                obs = obs.observation(Feature::from_vec(feature.to_vec()));
+                                obs = obs.observation(Feature::from_vec(
+                                    self.metric_opts.ingest_feature(*scene_id, feature),
+                                ));
                             }
 
                             obs.track_attributes_update(
@@ -284,7 +513,9 @@ impl BatchVisualSort {
                                     epoch,
                                     *scene_id,
                                     o.custom_object_id,
-                                ),
+                                )
+                                .with_class_id(o.class_id)
+                                .with_metadata(o.metadata.clone()),
                             )
                             .build()
                         })
@@ -294,6 +525,7 @@ impl BatchVisualSort {
                 .collect::<Vec<_>>();
 
             let (dists, errs) = {
+                let _watch = self.watchdog.watch(Worker::Distances);
                 let mut store = self
                     .store
                     .write()
@@ -301,19 +533,26 @@ impl BatchVisualSort {
                 store.foreign_track_distances(tracks.clone(), 0, false)
             };
 
-            assert!(errs.all().is_empty());
+            errs.log();
             let thread_id = i % self.voting_threads.len();
             self.voting_threads[thread_id]
                 .0
                 .send(VotingCommands::Distances {
                     monitor: self.monitor.as_ref().unwrap().clone(),
+                    started,
                     scene_id: *scene_id,
                     distances: dists.into_iter(),
                     channel: batch_request.get_sender(),
                     tracks,
+                    indices,
+                    observations: batch_request.get_batch()[scene_id]
+                        .iter()
+                        .map(|o| o.clone().into_owned())
+                        .collect(),
                 })
                 .expect("Sending voting request to voting thread must not fail");
         }
+        Ok(())
     }
 
     pub fn idle_tracks(&mut self) -> Vec<SortTrack> {
@@ -323,7 +562,7 @@ impl BatchVisualSort {
     pub fn idle_tracks_with_scene(&mut self, scene_id: u64) -> Vec<SortTrack> {
         let store = self.store.read().unwrap();
 
-        store
+        let tracks = store
             .lookup(VisualSortLookup::IdleLookup(scene_id))
             .iter()
             .map(|(track_id, _status)| {
@@ -331,7 +570,25 @@ impl BatchVisualSort {
                 let track = shard.get(track_id).unwrap();
                 SortTrack::from(track)
             })
-            .collect()
+            .collect();
+        self.track_opts.filter_output(tracks)
+    }
+
+    /// The visual votes the active track must collect to be associated by the features, it is
+    /// lower than `visual_min_votes` when the votes are scaled with
+    /// [visual_min_votes_fraction](VisualSortOptions::visual_min_votes_fraction)
+    ///
+    pub fn effective_min_votes(&self, track_id: u64) -> Option<usize> {
+        let store = self.store.read().unwrap();
+        let shard = store.get_store(track_id as usize);
+        shard.get(&track_id).map(|t| {
+            effective_min_votes(
+                t.get_attributes(),
+                &self.metric_opts,
+                &self.track_opts,
+                None,
+            )
+        })
     }
 }
 
@@ -348,6 +605,16 @@ impl
         &mut self.auto_waste
     }
 
+    fn scene_lookup(&self, scene_id: u64) -> Option<VisualSortLookup> {
+        Some(VisualSortLookup::SceneLookup(scene_id))
+    }
+
+    fn expired_lookup(&self, scene_id: u64) -> Option<VisualSortLookup> {
+        self.track_opts
+            .max_track_lifetime()
+            .map(|_| VisualSortLookup::ExpiredLookup(scene_id))
+    }
+
     fn get_opts(&self) -> &SortAttributesOptions {
         &self.track_opts
     }
@@ -377,6 +644,93 @@ mod tests {
     };
     use crate::trackers::batch::PredictionBatchRequest;
     use crate::trackers::visual_sort::batch_api::BatchVisualSort;
+    use crate::Errors;
+    use std::thread::spawn;
+    use std::time::Duration;
+
+    #[test]
+    fn shutdown() {
+        let mut tracker = BatchVisualSort::new(1, 2, &VisualSortOptions::default());
+        let (mut batch, predictions) = PredictionBatchRequest::<VisualSortObservation>::new();
+        for scene_id in 0..4 {
+            batch.add(
+                scene_id,
+                VisualSortObservation::new(
+                    None,
+                    None,
+                    BoundingBox::new(1.0, 1.0, 3.0, 5.0).as_xyaah(),
+                    None,
+                ),
+            );
+        }
+        tracker.predict(batch);
+
+        let consumer = spawn(move || {
+            for _ in 0..predictions.batch_size() {
+                let (_, tracks) = predictions.get();
+                assert_eq!(tracks.len(), 1);
+            }
+        });
+        let report = tracker.shutdown(Duration::from_secs(5));
+        assert!(report.unprocessed.is_empty());
+        assert_eq!(report.detached_threads, 0);
+        consumer.join().unwrap();
+
+        let report = tracker.shutdown(Duration::from_secs(1));
+        assert!(report.unprocessed.is_empty());
+        assert_eq!(report.detached_threads, 0);
+    }
+
+    #[test]
+    fn shutdown_stuck() {
+        let mut tracker = BatchVisualSort::new(1, 1, &VisualSortOptions::default());
+        let (mut batch, predictions) = PredictionBatchRequest::<VisualSortObservation>::new();
+        for scene_id in 0..3 {
+            batch.add(
+                scene_id,
+                VisualSortObservation::new(
+                    None,
+                    None,
+                    BoundingBox::new(1.0, 1.0, 3.0, 5.0).as_xyaah(),
+                    None,
+                ),
+            );
+        }
+        tracker.predict(batch);
+
+        // results are not consumed, so the voting thread is blocked on sending the second scene
+        let report = tracker.shutdown(Duration::from_millis(500));
+        assert!(!report.unprocessed.is_empty());
+        assert_eq!(report.detached_threads, 1);
+        assert_eq!(report.unprocessed[0].1.len(), 1);
+        // the submitted observations are returned to be resubmitted
+        assert_eq!(
+            report.unprocessed[0].1[0].bounding_box,
+            BoundingBox::new(1.0, 1.0, 3.0, 5.0).as_xyaah()
+        );
+        drop(predictions);
+    }
+
+    #[test]
+    #[should_panic]
+    fn predict_after_shutdown() {
+        let mut tracker = BatchVisualSort::new(1, 1, &VisualSortOptions::default());
+        tracker.shutdown(Duration::from_secs(1));
+        let (batch, _) = PredictionBatchRequest::<VisualSortObservation>::new();
+        tracker.predict(batch);
+    }
+
+    #[test]
+    fn try_predict_after_shutdown() {
+        let mut tracker = BatchVisualSort::new(1, 1, &VisualSortOptions::default());
+        tracker.shutdown(Duration::from_secs(1));
+        let (batch, _) = PredictionBatchRequest::<VisualSortObservation>::new();
+        let err = tracker.try_predict(batch).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Errors>(),
+            Some(Errors::TrackerShutDown)
+        ));
+    }
 
     #[test]
     fn test() {
@@ -485,12 +839,16 @@ impl<'a> Default for VisualSortPredictionBatchRequest<'a> {
 
 #[cfg(feature = "python")]
 pub mod python {
+    use pyo3::exceptions::PyRuntimeError;
     use pyo3::prelude::*;
+    use std::time::Duration;
 
     use crate::{
         prelude::VisualSortObservation,
+        store::shard_stats::python::PyShardDistanceStats,
         trackers::{
             batch::{python::PyPredictionBatchResult, PredictionBatchRequest},
+            predict_overrides::python::PyPredictOverrides,
             sort::python::PySortTrack,
             tracker_api::TrackerAPI,
             visual_sort::{
@@ -498,6 +856,7 @@ pub mod python {
                 python::{PyVisualSortObservation, PyWastedVisualSortTrack},
                 WastedVisualSortTrack,
             },
+            watchdog::python::PyWatchdogOptions,
         },
     };
 
@@ -534,6 +893,125 @@ pub mod python {
                 .skip_epochs_for_scene(scene_id.try_into().unwrap(), n.try_into().unwrap())
         }
 
+        #[pyo3(signature = (scene_id, overrides))]
+        fn set_predict_overrides(&mut self, scene_id: u64, overrides: Option<PyPredictOverrides>) {
+            self.0
+                .set_predict_overrides(scene_id, overrides.map(|o| o.0))
+        }
+
+        /// Enables or disables (with `None`) the watchdog that logs and counts the distance
+        /// computation and the voting threads that made no progress for the stall timeout
+        ///
+        #[pyo3(signature = (opts))]
+        fn set_watchdog(&mut self, opts: Option<PyWatchdogOptions>) {
+            self.0.set_watchdog(opts.map(|o| o.0))
+        }
+
+        /// The number of the stalled threads detected by the watchdog
+        ///
+        #[pyo3(signature = ())]
+        fn watchdog_stalls(&self) -> usize {
+            self.0.watchdog_stalls()
+        }
+
+        /// The object density of the scene estimated from the observations of the last epochs
+        ///
+        /// # Returns
+        /// `(objects, occupied_cells, density)` or `None` if the estimation is disabled or the scene has no observations accounted
+        ///
+        #[pyo3(signature = (scene_id))]
+        fn scene_density(&self, scene_id: u64) -> Option<(f32, usize, f32)> {
+            self.0
+                .get_opts()
+                .scene_density(scene_id)
+                .map(|d| (d.objects, d.occupied_cells, d.density))
+        }
+
+        /// The tracking quality of the scene estimated without the ground truth
+        ///
+        /// # Returns
+        /// `(new_tracks, updates, id_switches, fragmentations, id_switch_rate, fragmentation_rate)` or `None` if the estimation is disabled or the scene has no tracks accounted
+        ///
+        #[pyo3(signature = (scene_id))]
+        fn tracking_quality(
+            &self,
+            scene_id: u64,
+        ) -> Option<(usize, usize, usize, usize, f32, f32)> {
+            self.0.get_opts().tracking_quality(scene_id).map(|q| {
+                (
+                    q.new_tracks,
+                    q.updates,
+                    q.id_switches,
+                    q.fragmentations,
+                    q.id_switch_rate,
+                    q.fragmentation_rate,
+                )
+            })
+        }
+
+        /// Checks if the scene exceeded the latency budget, so its visual distances are skipped
+        ///
+        #[pyo3(signature = (scene_id))]
+        fn latency_degraded(&self, scene_id: u64) -> bool {
+            self.0.get_opts().latency_degraded(scene_id)
+        }
+
+        /// The prediction latency percentiles of the scene in seconds
+        ///
+        /// # Returns
+        /// `(p50, p95, p99, samples)` or `None` if the scene has no predictions accounted
+        ///
+        #[pyo3(signature = (scene_id))]
+        fn predict_latency(&self, scene_id: u64) -> Option<(f64, f64, f64, usize)> {
+            self.0.get_opts().predict_latency(scene_id).map(|p| {
+                (
+                    p.p50.as_secs_f64(),
+                    p.p95.as_secs_f64(),
+                    p.p99.as_secs_f64(),
+                    p.samples,
+                )
+            })
+        }
+
+        /// The scenes which epochs are tracked
+        ///
+        #[pyo3(signature = ())]
+        fn scenes(&self) -> Vec<u64> {
+            TrackerAPI::scenes(&self.0)
+        }
+
+        /// Moves the active tracks of the scene to the wasted store and restarts its epochs
+        ///
+        #[pyo3(signature = (scene_id))]
+        fn reset_scene(&mut self, scene_id: u64) {
+            self.0.reset_scene(scene_id)
+        }
+
+        /// Resets the scenes which passed more than `n` of their epochs without the observations
+        ///
+        #[pyo3(signature = (n))]
+        fn clear_idle_scenes(&mut self, n: usize) -> Vec<u64> {
+            self.0.clear_idle_scenes(n)
+        }
+
+        /// Get the distance computation counters of the active tracks store per shard
+        ///
+        #[pyo3(signature = ())]
+        fn distance_stats(&self) -> Vec<PyShardDistanceStats> {
+            self.0
+                .distance_stats()
+                .into_iter()
+                .map(PyShardDistanceStats)
+                .collect()
+        }
+
+        /// Resets the distance computation counters
+        ///
+        #[pyo3(signature = ())]
+        fn reset_distance_stats(&self) {
+            self.0.reset_distance_stats()
+        }
+
         /// Get the amount of stored tracks per shard
         ///
         #[pyo3(signature = ())]
@@ -580,11 +1058,15 @@ pub mod python {
         /// # Parameters
         /// * `bboxes` - bounding boxes received from a detector
         ///
+        /// # Raises
+        /// `RuntimeError` when the watchdog detects the stalled thread while the previous batch is
+        /// processed or the tracker is shut down
+        ///
         #[pyo3(signature = (py_batch))]
         fn predict(
             &mut self,
             py_batch: PyVisualSortPredictionBatchRequest,
-        ) -> PyPredictionBatchResult {
+        ) -> PyResult<PyPredictionBatchResult> {
             let (mut batch, res) = PredictionBatchRequest::<VisualSortObservation>::new();
             for (scene_id, observations) in py_batch.0.batch.get_batch() {
                 for o in observations {
@@ -600,9 +1082,11 @@ pub mod python {
                     );
                 }
             }
-            self.0.predict(batch);
+            self.0
+                .try_predict(batch)
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
 
-            PyPredictionBatchResult(res)
+            Ok(PyPredictionBatchResult(res))
         }
 
         /// Remove all the tracks with expired life
@@ -638,6 +1122,39 @@ pub mod python {
                 })
             })
         }
+
+        /// Get the visual votes the active track must collect to be associated by the features
+        ///
+        #[pyo3(signature = (track_id))]
+        pub fn effective_min_votes(&self, track_id: u64) -> Option<usize> {
+            self.0.effective_min_votes(track_id)
+        }
+
+        /// Stop the tracker gracefully
+        ///
+        /// # Parameters
+        /// * `timeout_ms` - the time given for the draining and the threads shutdown
+        ///
+        /// # Returns
+        /// the ids of the scenes that were not processed and the number of detached threads
+        ///
+        #[pyo3(signature = (timeout_ms))]
+        pub fn shutdown(&mut self, timeout_ms: i64) -> (Vec<u64>, usize) {
+            let timeout = Duration::from_millis(
+                timeout_ms
+                    .try_into()
+                    .expect("Parameter must be a positive number"),
+            );
+            Python::with_gil(|py| {
+                py.allow_threads(|| {
+                    let report = self.0.shutdown(timeout);
+                    (
+                        report.unprocessed.into_iter().map(|(s, _)| s).collect(),
+                        report.detached_threads,
+                    )
+                })
+            })
+        }
     }
 
     #[derive(Debug, Clone)]
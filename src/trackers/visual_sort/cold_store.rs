@@ -0,0 +1,412 @@
+use crate::distance::{cosine, euclidean};
+use crate::store::TrackStore;
+use crate::track::notify::ChangeNotifier;
+use crate::track::state::{ObservationState, TrackState};
+use crate::track::utils::FromVec;
+use crate::track::{Feature, Track};
+use crate::trackers::sort::{SortAttributesOptions, VotingType};
+use crate::trackers::visual_sort::metric::{VisualMetric, VisualSortMetricType};
+use crate::trackers::visual_sort::observation_attributes::VisualObservationAttributes;
+use crate::trackers::visual_sort::track_attributes::VisualAttributes;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Configuration of the cold tier of the tracks.
+///
+/// The tracks of the scene idle for more than `demote_after` epochs leave the main store and are
+/// kept compressed: the history of the attributes is trimmed to the latest entry and only
+/// `kept_features` best-quality features are kept, quantized to 8 bits. The cold tracks are not
+/// used by the regular voting, so they don't slow down the distance computation, but the
+/// candidates left without the destination are compared with them by the appearance and the
+/// matching track is promoted back to the main store and continued with the candidate.
+///
+/// The cold tracks idle for more than `retention` epochs are moved to the wasted tracks, without
+/// the retention they are kept until the tracker is dropped.
+///
+/// The demotion happens before the track is wasted, so `demote_after` must be less than the
+/// `max_idle_epochs` of the tracker.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColdStoreOptions {
+    demote_after: usize,
+    kept_features: usize,
+    retention: Option<usize>,
+}
+
+impl ColdStoreOptions {
+    pub fn new(demote_after: usize, kept_features: usize, retention: Option<usize>) -> Self {
+        assert!(
+            kept_features > 0,
+            "The number of kept features must be a positive number"
+        );
+        assert!(
+            retention.map(|r| r > demote_after).unwrap_or(true),
+            "Retention must be greater than the demotion period"
+        );
+        Self {
+            demote_after,
+            kept_features,
+            retention,
+        }
+    }
+
+    pub fn demote_after(&self) -> usize {
+        self.demote_after
+    }
+
+    pub fn kept_features(&self) -> usize {
+        self.kept_features
+    }
+
+    pub fn retention(&self) -> Option<usize> {
+        self.retention
+    }
+
+    /// Checks if the cold track idle for `idle_epochs` must be wasted
+    ///
+    pub fn expired(&self, idle_epochs: usize) -> bool {
+        self.retention.map(|r| idle_epochs > r).unwrap_or(false)
+    }
+}
+
+/// The feature quantized to 8 bits with the symmetric scale of the vector
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuantizedFeature {
+    scale: f32,
+    values: Vec<i8>,
+}
+
+impl QuantizedFeature {
+    pub fn new(feature: &Feature) -> Self {
+        let values = Vec::from_vec(feature);
+        let max = values.iter().fold(0.0f32, |m, v| m.max(v.abs()));
+        let scale = if max > 0.0 { max / i8::MAX as f32 } else { 1.0 };
+        Self {
+            scale,
+            values: values
+                .into_iter()
+                .map(|v| (v / scale).round() as i8)
+                .collect(),
+        }
+    }
+
+    /// The restored feature vector, padded like the original one
+    ///
+    pub fn values(&self) -> Vec<f32> {
+        self.values.iter().map(|v| *v as f32 * self.scale).collect()
+    }
+
+    pub fn feature(&self) -> Feature {
+        Feature::from_vec(self.values())
+    }
+}
+
+/// The track demoted to the cold store
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColdTrack {
+    state: TrackState<VisualAttributes, VisualObservationAttributes>,
+    features: Vec<(Option<VisualObservationAttributes>, QuantizedFeature)>,
+}
+
+impl ColdTrack {
+    /// Compresses the state of the track, keeping at most `kept_features` features of the best
+    /// quality
+    ///
+    pub fn new(
+        mut state: TrackState<VisualAttributes, VisualObservationAttributes>,
+        kept_features: usize,
+    ) -> Self {
+        state.attributes.compact();
+        let mut observations = state
+            .observations
+            .drain(..)
+            .filter(|(feature_class, _)| *feature_class == 0)
+            .flat_map(|(_, observations)| observations)
+            .filter(|o| o.feature.is_some())
+            .collect::<Vec<_>>();
+        let quality = |o: &ObservationState<VisualObservationAttributes>| {
+            o.attributes
+                .as_ref()
+                .map(|a| a.visual_quality())
+                .unwrap_or_default()
+        };
+        observations.sort_by(|l, r| quality(r).total_cmp(&quality(l)));
+        observations.truncate(kept_features);
+        let features = observations
+            .into_iter()
+            .map(|o| {
+                let feature = Feature::from_vec(o.feature.unwrap());
+                (o.attributes, QuantizedFeature::new(&feature))
+            })
+            .collect();
+        Self { state, features }
+    }
+
+    pub fn track_id(&self) -> u64 {
+        self.state.track_id
+    }
+
+    pub fn scene_id(&self) -> u64 {
+        self.state.attributes.scene_id
+    }
+
+    pub fn last_updated_epoch(&self) -> usize {
+        self.state.attributes.last_updated_epoch
+    }
+
+    /// The number of the features kept by the track
+    ///
+    pub fn features_count(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Compares the feature with the features of the track
+    ///
+    /// # Returns
+    /// the weight (lower is closer) of the closest feature within the threshold of the metric,
+    /// `None` if there is no such feature
+    ///
+    pub fn weight(&self, feature: &Feature, kind: &VisualSortMetricType) -> Option<f32> {
+        self.features
+            .iter()
+            .map(|(_, f)| {
+                let f = f.feature();
+                match kind {
+                    VisualSortMetricType::Euclidean(_) => euclidean(feature, &f),
+                    VisualSortMetricType::Cosine(_) => cosine(feature, &f),
+                }
+            })
+            .filter(|d| kind.is_ok(*d))
+            .map(|d| kind.distance_to_weight(d))
+            .min_by(f32::total_cmp)
+    }
+
+    /// The state of the track with the restored features, the options of the attributes must
+    /// be set before the state is used
+    ///
+    pub fn into_state(mut self) -> TrackState<VisualAttributes, VisualObservationAttributes> {
+        self.state.observations = vec![(
+            0,
+            self.features
+                .into_iter()
+                .map(|(attributes, f)| ObservationState {
+                    attributes,
+                    feature: Some(f.values()),
+                })
+                .collect(),
+        )];
+        self.state
+    }
+}
+
+/// The tracks demoted from the main store
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ColdStore {
+    tracks: HashMap<u64, ColdTrack>,
+}
+
+impl ColdStore {
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    pub fn get(&self, track_id: u64) -> Option<&ColdTrack> {
+        self.tracks.get(&track_id)
+    }
+
+    pub fn insert(&mut self, track: ColdTrack) {
+        self.tracks.insert(track.track_id(), track);
+    }
+
+    /// Removes the track from the store
+    ///
+    pub fn take(&mut self, track_id: u64) -> Option<ColdTrack> {
+        self.tracks.remove(&track_id)
+    }
+
+    /// Removes the tracks matching the predicate from the store
+    ///
+    pub fn take_matching(&mut self, predicate: impl Fn(&ColdTrack) -> bool) -> Vec<ColdTrack> {
+        let ids = self
+            .tracks
+            .values()
+            .filter(|t| predicate(t))
+            .map(|t| t.track_id())
+            .collect::<Vec<_>>();
+        ids.into_iter().flat_map(|id| self.take(id)).collect()
+    }
+
+    /// The tracks of the store ordered by their ids
+    ///
+    pub fn tracks(&self) -> Vec<ColdTrack> {
+        let mut tracks = self.tracks.values().cloned().collect::<Vec<_>>();
+        tracks.sort_by_key(|t| t.track_id());
+        tracks
+    }
+}
+
+/// Runs the promotion pass for the candidates left without the destination by the voting and the
+/// reactivation. Every cold track is promoted for the closest candidate of its scene, the promoted
+/// tracks are restored into the main store with the Kalman state extrapolated to the epoch of the
+/// candidate.
+///
+/// # Parameters
+/// * `store` - the store of the active tracks
+/// * `cold` - the store of the cold tracks
+/// * `tracks` - the candidate tracks of the epoch
+/// * `winners` - the results of the voting, extended with the promoted tracks
+/// * `kind` - the visual metric of the tracker
+/// * `opts` - the options the promoted tracks are attached to
+///
+pub(crate) fn promote<N: ChangeNotifier>(
+    store: &mut TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes, N>,
+    cold: &mut ColdStore,
+    tracks: &[Track<VisualAttributes, VisualMetric, VisualObservationAttributes, N>],
+    winners: &mut HashMap<u64, Vec<(u64, VotingType)>>,
+    kind: &VisualSortMetricType,
+    opts: &Arc<SortAttributesOptions>,
+) {
+    if cold.is_empty() {
+        return;
+    }
+    let mut pairs = tracks
+        .iter()
+        .filter(|t| match winners.get(&t.get_track_id()) {
+            Some(dest) => dest[0].0 == t.get_track_id(),
+            None => true,
+        })
+        .flat_map(|t| {
+            let feature = t
+                .get_observations(0)
+                .and_then(|o| o.first())
+                .and_then(|o| o.feature().as_ref());
+            let scene_id = t.get_attributes().scene_id;
+            cold.tracks
+                .values()
+                .filter(|c| c.scene_id() == scene_id)
+                .flat_map(|c| {
+                    feature
+                        .and_then(|f| c.weight(f, kind))
+                        .map(|w| (t.get_track_id(), c.track_id(), w))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    pairs.sort_by(|l, r| l.2.total_cmp(&r.2));
+
+    let epochs = tracks
+        .iter()
+        .map(|t| (t.get_track_id(), t.get_attributes().last_updated_epoch))
+        .collect::<HashMap<_, _>>();
+    let mut assigned = HashSet::new();
+    for (candidate, track_id, _) in pairs {
+        if assigned.contains(&candidate) {
+            continue;
+        }
+        if let Some(track) = cold.take(track_id) {
+            let mut state = track.into_state();
+            state.attributes.set_options(opts.clone());
+            state.attributes.warm_restart(epochs[&candidate]);
+            store.restore(state).unwrap();
+            winners.insert(candidate, vec![(track_id, VotingType::Visual)]);
+            assigned.insert(candidate);
+        }
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::ColdStoreOptions;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "ColdStoreOptions")]
+    #[derive(Debug, Clone, Copy)]
+    pub struct PyColdStoreOptions(pub(crate) ColdStoreOptions);
+
+    #[pymethods]
+    impl PyColdStoreOptions {
+        #[new]
+        #[pyo3(signature = (demote_after, kept_features = 3, retention = None))]
+        pub(crate) fn new(
+            demote_after: usize,
+            kept_features: usize,
+            retention: Option<usize>,
+        ) -> Self {
+            Self(ColdStoreOptions::new(
+                demote_after,
+                kept_features,
+                retention,
+            ))
+        }
+
+        #[getter]
+        fn demote_after(&self) -> usize {
+            self.0.demote_after()
+        }
+
+        #[getter]
+        fn kept_features(&self) -> usize {
+            self.0.kept_features()
+        }
+
+        #[getter]
+        fn retention(&self) -> Option<usize> {
+            self.0.retention()
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::track::utils::FromVec;
+    use crate::track::Feature;
+    use crate::trackers::visual_sort::cold_store::{ColdStoreOptions, QuantizedFeature};
+
+    #[test]
+    fn quantization() {
+        let feature = Feature::from_vec(vec![1.0, -0.5, 0.25, 0.0]);
+        let quantized = QuantizedFeature::new(&feature);
+        let values = quantized.values();
+        assert_eq!(values.len(), 8);
+        for (restored, original) in values.iter().zip([1.0, -0.5, 0.25, 0.0]) {
+            assert!((restored - original).abs() < 1.0 / 127.0);
+        }
+
+        let zero = QuantizedFeature::new(&Feature::from_vec(vec![0.0; 8]));
+        assert_eq!(zero.values(), vec![0.0; 8]);
+    }
+
+    #[test]
+    fn expiration() {
+        let opts = ColdStoreOptions::new(2, 1, Some(10));
+        assert!(!opts.expired(10));
+        assert!(opts.expired(11));
+        assert!(!ColdStoreOptions::new(2, 1, None).expired(usize::MAX));
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_retention() {
+        ColdStoreOptions::new(5, 1, Some(5));
+    }
+}
@@ -1,23 +1,38 @@
 /// Auxiliary class that helps to build a metric object
 pub mod builder;
+/// Per-class overrides of the visual settings
+pub mod class_options;
 
 use crate::distance::{cosine, euclidean};
 use crate::track::{Feature, MetricQuery, ObservationAttributes, ObservationMetricOk};
 use crate::track::{MetricOutput, Observation, ObservationMetric};
+use crate::trackers::density::adapt_positional_metric;
 use crate::trackers::kalman_prediction::TrackAttributesKalmanPrediction;
 use crate::trackers::sort::PositionalMetricType;
+use crate::trackers::visual_sort::adaptive_threshold::AdaptiveVisualThreshold;
 use crate::trackers::visual_sort::metric::builder::VisualMetricBuilder;
+use crate::trackers::visual_sort::metric::class_options::{
+    VisualClassOptions, VisualClassSettings,
+};
 use crate::trackers::visual_sort::metric::VisualSortMetricType::{Cosine, Euclidean};
 use crate::trackers::visual_sort::observation_attributes::VisualObservationAttributes;
 use crate::trackers::visual_sort::track_attributes::VisualAttributes;
+use crate::trackers::visual_sort::voting::VisualFusion;
+use crate::trackers::visual_sort::whitening::{FeatureWhitening, SceneFeatureStatistics};
 use crate::utils::bbox::Universal2DBox;
 use crate::utils::kalman::kalman_2d_box::Universal2DBoxKalmanFilter;
+use crate::Errors;
 use anyhow::Result;
+use log::warn;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::default::Default;
 use std::iter::Iterator;
 use std::sync::Arc;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum VisualSortMetricType {
     Euclidean(f32),
     Cosine(f32),
@@ -99,10 +114,14 @@ pub mod python {
     }
 }
 
+const SAMPLING_HASH_SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
 #[derive(Debug)]
 pub struct VisualMetricOptions {
     pub visual_max_observations: usize,
     pub visual_min_votes: usize,
+    pub visual_min_votes_fraction: Option<f32>,
+    pub visual_fusion: VisualFusion,
     pub visual_kind: VisualSortMetricType,
     pub positional_kind: PositionalMetricType,
     pub visual_minimal_track_length: usize,
@@ -112,6 +131,55 @@ pub struct VisualMetricOptions {
     pub visual_minimal_own_area_percentage_use: f32,
     pub visual_minimal_own_area_percentage_collect: f32,
     pub positional_min_confidence: f32,
+    pub positional_max_depth_delta: Option<f32>,
+    pub positional_max_distance: Option<f32>,
+    pub visual_max_distance: Option<f32>,
+    pub visual_adaptive_threshold: Option<AdaptiveVisualThreshold>,
+    pub visual_collect_rate_limit: Option<(usize, usize)>,
+    pub visual_collect_sampling: f32,
+    pub visual_collect_sampling_seed: u64,
+    pub visual_class_options: HashMap<i64, VisualClassOptions>,
+    pub visual_whitening: Option<FeatureWhitening>,
+    pub feature_statistics: SceneFeatureStatistics,
+}
+
+impl VisualMetricOptions {
+    /// The visual settings of the object class, the defaults are used for the objects without the
+    /// class and for the classes without the options
+    ///
+    pub fn class_settings(&self, class_id: Option<i64>) -> VisualClassSettings {
+        let defaults = VisualClassSettings {
+            visual_kind: self.visual_kind,
+            visual_minimal_track_length: self.visual_minimal_track_length,
+            visual_minimal_area: self.visual_minimal_area,
+            visual_minimal_quality_use: self.visual_minimal_quality_use,
+            visual_minimal_quality_collect: self.visual_minimal_quality_collect,
+            visual_max_observations: self.visual_max_observations,
+        };
+        class_id
+            .and_then(|c| self.visual_class_options.get(&c))
+            .map(|o| o.resolve(defaults))
+            .unwrap_or(defaults)
+    }
+
+    /// Accounts the incoming feature of the scene in the feature statistics and whitens it when
+    /// the whitening is enabled
+    ///
+    pub fn ingest_feature(&self, scene_id: u64, feature: &[f32]) -> Vec<f32> {
+        match &self.visual_whitening {
+            Some(whitening) => self.feature_statistics.ingest(scene_id, feature, whitening),
+            None => feature.to_vec(),
+        }
+    }
+
+    /// Whitens the query feature of the scene without accounting it in the statistics
+    ///
+    pub fn whiten_feature(&self, scene_id: u64, feature: &[f32]) -> Vec<f32> {
+        match &self.visual_whitening {
+            Some(whitening) => self.feature_statistics.whiten(scene_id, feature, whitening),
+            None => feature.to_vec(),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -129,6 +197,7 @@ impl VisualMetric {
     fn optimize_observations(
         &self,
         observations: &mut Vec<Observation<VisualObservationAttributes>>,
+        visual_max_observations: usize,
     ) {
         observations.retain(|e| e.feature().is_some());
 
@@ -139,16 +208,17 @@ impl VisualMetric {
             }
         });
 
-        observations.sort_by(|e1, e2| {
-            e2.attr()
+        // the observations without the attributes or with NaN quality are the first to drop
+        let quality = |o: &Observation<VisualObservationAttributes>| {
+            o.attr()
                 .as_ref()
-                .unwrap()
-                .visual_quality()
-                .partial_cmp(&e1.attr().as_ref().unwrap().visual_quality())
-                .unwrap()
-        });
+                .map(|a| a.visual_quality())
+                .filter(|q| !q.is_nan())
+                .unwrap_or(f32::NEG_INFINITY)
+        };
+        observations.sort_by(|e1, e2| quality(e2).total_cmp(&quality(e1)));
 
-        if observations.len() >= self.opts.visual_max_observations {
+        if observations.len() >= visual_max_observations {
             observations.truncate(observations.len() - 1);
         }
     }
@@ -158,6 +228,7 @@ impl VisualMetric {
         candidate_observation_bbox_opt: &Option<Universal2DBox>,
         track_observation_bbox_opt: &Option<Universal2DBox>,
         track_attributes: &VisualAttributes,
+        positional_kind: PositionalMetricType,
     ) -> Option<f32> {
         if let (Some(candidate_observation_bbox), Some(track_observation_bbox)) =
             (candidate_observation_bbox_opt, track_observation_bbox_opt)
@@ -173,22 +244,26 @@ impl VisualMetric {
                     candidate_observation_bbox.confidence
                 };
 
-                match self.opts.positional_kind {
+                let factor = track_attributes.congestion_factor(candidate_observation_bbox);
+                match positional_kind {
                     PositionalMetricType::Mahalanobis => {
-                        let state = track_attributes.get_state().unwrap();
+                        // the track has no Kalman state without the motion model
+                        let state = track_attributes.get_state()?;
                         let f = Universal2DBoxKalmanFilter::new(
                             track_attributes.get_position_weight(),
                             track_attributes.get_velocity_weight(),
                         );
                         let dist = f.distance(state, candidate_observation_bbox);
-                        Some(Universal2DBoxKalmanFilter::calculate_cost(dist, true) / conf)
+                        track_attributes.mahalanobis_metric(dist, conf, factor)
                     }
                     PositionalMetricType::IoU(threshold) => {
                         let box_m_opt = Universal2DBox::calculate_metric_object(
                             &candidate_observation_bbox_opt.as_ref(),
                             &track_observation_bbox_opt.as_ref(),
                         );
-                        box_m_opt.map(|e| e * conf).filter(|e| *e >= threshold)
+                        box_m_opt
+                            .map(|e| adapt_positional_metric(positional_kind, e * conf, factor))
+                            .filter(|e| *e >= threshold)
                     }
                 }
             }
@@ -197,25 +272,114 @@ impl VisualMetric {
         }
     }
 
+    fn feature_can_be_collected(
+        &self,
+        attrs: &mut VisualAttributes,
+        bbox: &Universal2DBox,
+    ) -> bool {
+        if let Some((max_features, epochs)) = self.opts.visual_collect_rate_limit {
+            let epoch = attrs.last_updated_epoch;
+            let collected = attrs
+                .collected_feature_epochs
+                .iter()
+                .filter(|e| **e + epochs > epoch)
+                .count();
+            if collected >= max_features {
+                return false;
+            }
+        }
+
+        if self.opts.visual_collect_sampling < 1.0
+            && self.sampling_draw(attrs, bbox) >= self.opts.visual_collect_sampling
+        {
+            return false;
+        }
+
+        self.record_collected_feature(attrs);
+        true
+    }
+
+    fn record_collected_feature(&self, attrs: &mut VisualAttributes) {
+        if let Some((max_features, _)) = self.opts.visual_collect_rate_limit {
+            attrs
+                .collected_feature_epochs
+                .push_back(attrs.last_updated_epoch);
+            if attrs.collected_feature_epochs.len() > max_features {
+                attrs.collected_feature_epochs.pop_front();
+            }
+        }
+    }
+
+    /// The sampling decision is derived from the seed, the track and its predicted box, so the
+    /// collected features don't depend on the thread the merge runs in and are reproducible
+    ///
+    fn sampling_draw(&self, attrs: &VisualAttributes, bbox: &Universal2DBox) -> f32 {
+        let seed = [
+            attrs.scene_id,
+            attrs.last_updated_epoch as u64,
+            attrs.track_length as u64,
+            bbox.xc.to_bits() as u64,
+            bbox.yc.to_bits() as u64,
+            bbox.height.to_bits() as u64,
+        ]
+        .into_iter()
+        .fold(self.opts.visual_collect_sampling_seed, |hash, v| {
+            (hash.rotate_left(5) ^ v).wrapping_mul(SAMPLING_HASH_SEED)
+        });
+        StdRng::seed_from_u64(seed).gen()
+    }
+
+    fn depth_is_compatible(
+        &self,
+        candidate_attributes: &VisualAttributes,
+        track_attributes: &VisualAttributes,
+    ) -> bool {
+        match (
+            self.opts.positional_max_depth_delta,
+            candidate_attributes.depth,
+            track_attributes.depth,
+        ) {
+            (Some(max_delta), Some(candidate_depth), Some(track_depth)) => {
+                (candidate_depth - track_depth).abs() <= max_delta
+            }
+            _ => true,
+        }
+    }
+
+    fn feature_distance(&self, kind: VisualSortMetricType, f1: &Feature, f2: &Feature) -> f32 {
+        match kind {
+            VisualSortMetricType::Euclidean(_) => euclidean(f1, f2),
+            VisualSortMetricType::Cosine(_) => cosine(f1, f2),
+        }
+    }
+
     fn visual_metric(
         &self,
+        settings: &VisualClassSettings,
         candidate_observation_feature: &Feature,
         track_observation_feature: &Feature,
         track_attributes: &VisualAttributes,
     ) -> Option<f32> {
-        if track_attributes.visual_features_collected_count >= self.opts.visual_minimal_track_length
+        if track_attributes.visual_features_collected_count >= settings.visual_minimal_track_length
         {
-            let d = match self.opts.visual_kind {
-                VisualSortMetricType::Euclidean(_) => {
-                    euclidean(candidate_observation_feature, track_observation_feature)
-                }
-                VisualSortMetricType::Cosine(_) => {
-                    cosine(candidate_observation_feature, track_observation_feature)
-                }
+            let d = self.feature_distance(
+                settings.visual_kind,
+                candidate_observation_feature,
+                track_observation_feature,
+            );
+            let weight = settings.visual_kind.distance_to_weight(d);
+
+            let adaptive_threshold = self
+                .opts
+                .visual_adaptive_threshold
+                .and_then(|a| a.threshold(&track_attributes.visual_distance_stats));
+            let is_ok = match adaptive_threshold {
+                Some(threshold) => weight <= threshold,
+                None => settings.visual_kind.is_ok(d),
             };
 
-            if self.opts.visual_kind.is_ok(d) {
-                Some(self.opts.visual_kind.distance_to_weight(d))
+            if is_ok {
+                Some(weight)
             } else {
                 None
             }
@@ -224,10 +388,34 @@ impl VisualMetric {
         }
     }
 
+    /// Records the distance of the collected feature to the closest feature kept by the track
+    ///
+    fn record_feature_distance(
+        &self,
+        attrs: &mut VisualAttributes,
+        kind: VisualSortMetricType,
+        feature: &Feature,
+        observations: &[Observation<VisualObservationAttributes>],
+    ) {
+        if let Some(adaptive) = self.opts.visual_adaptive_threshold {
+            let closest = observations
+                .iter()
+                .flat_map(|o| o.feature().as_ref())
+                .map(|f| kind.distance_to_weight(self.feature_distance(kind, feature, f)))
+                .min_by(|d1, d2| d1.total_cmp(d2));
+            if let Some(distance) = closest {
+                attrs
+                    .visual_distance_stats
+                    .record(distance, adaptive.window);
+            }
+        }
+    }
+
     fn feature_can_be_used(
         &self,
         bbox_opt: &Option<&Universal2DBox>,
         feature_quality: f32,
+        visual_minimal_area: f32,
         visual_minimal_quality: f32,
         visual_own_area_percentage: &Option<f32>,
         visual_minimal_area_percentage: f32,
@@ -240,7 +428,7 @@ impl VisualMetric {
 
         let bbox_is_ok = if let Some(bbox) = bbox_opt {
             let area = bbox.area();
-            area >= self.opts.visual_minimal_area
+            area >= visual_minimal_area
         } else {
             unreachable!("The bbox must always present for candidate track");
         };
@@ -254,17 +442,24 @@ impl ObservationMetric<VisualAttributes, VisualObservationAttributes> for Visual
         &self,
         mq: &MetricQuery<VisualAttributes, VisualObservationAttributes>,
     ) -> MetricOutput<f32> {
-        let candidate_obs_attrs = mq
-            .candidate_observation
-            .attr()
-            .as_ref()
-            .expect("Observation attributes must always present.");
-
-        let track_obs_attrs = mq
-            .track_observation
-            .attr()
-            .as_ref()
-            .expect("Observation attributes must always present.");
+        // the malformed observations (built without the attributes) are not compared
+        let (Some(candidate_obs_attrs), Some(track_obs_attrs)) = (
+            mq.candidate_observation.attr().as_ref(),
+            mq.track_observation.attr().as_ref(),
+        ) else {
+            warn!(
+                "The observation without the attributes is not compared, scene={}",
+                mq.track_attrs.scene_id
+            );
+            return None;
+        };
+        if candidate_obs_attrs.bbox_opt().is_none() {
+            warn!(
+                "The candidate observation without the bounding box is not compared, scene={}",
+                mq.track_attrs.scene_id
+            );
+            return None;
+        }
 
         let candidate_bbox_opt = candidate_obs_attrs.bbox_opt();
         let candidate_feature_q = candidate_obs_attrs.visual_quality();
@@ -275,17 +470,44 @@ impl ObservationMetric<VisualAttributes, VisualObservationAttributes> for Visual
         let candidate_feature_opt = mq.candidate_observation.feature().as_ref();
         let track_feature_opt = mq.track_observation.feature().as_ref();
 
+        let overrides = mq
+            .track_attrs
+            .predict_overrides()
+            .layered(mq.candidate_attrs.call_overrides);
+        let settings = self.opts.class_settings(
+            mq.candidate_attrs
+                .class_votes
+                .majority()
+                .map(|c| c.class_id),
+        );
+
         Some((
-            self.positional_metric(candidate_bbox_opt, track_bbox_opt, mq.track_attrs),
-            if self.feature_can_be_used(
-                &candidate_bbox_opt.as_ref(),
-                candidate_feature_q,
-                self.opts.visual_minimal_quality_use,
-                candidate_own_area_percentage_opt,
-                self.opts.visual_minimal_own_area_percentage_use,
-            ) {
+            if mq.candidate_attrs.reactivation.is_none()
+                && !mq.candidate_attrs.search
+                && self.depth_is_compatible(mq.candidate_attrs, mq.track_attrs)
+            {
+                self.positional_metric(
+                    candidate_bbox_opt,
+                    track_bbox_opt,
+                    mq.track_attrs,
+                    overrides.positional_kind(self.opts.positional_kind),
+                )
+            } else {
+                None
+            },
+            if !overrides.disable_visual
+                && (mq.candidate_attrs.search
+                    || self.feature_can_be_used(
+                        &candidate_bbox_opt.as_ref(),
+                        candidate_feature_q,
+                        settings.visual_minimal_area,
+                        settings.visual_minimal_quality_use,
+                        candidate_own_area_percentage_opt,
+                        self.opts.visual_minimal_own_area_percentage_use,
+                    ))
+            {
                 match (candidate_feature_opt, track_feature_opt) {
-                    (Some(c), Some(t)) => self.visual_metric(c, t, mq.track_attrs),
+                    (Some(c), Some(t)) => self.visual_metric(&settings, c, t, mq.track_attrs),
                     _ => None,
                 }
             } else {
@@ -303,18 +525,28 @@ impl ObservationMetric<VisualAttributes, VisualObservationAttributes> for Visual
         _prev_length: usize,
         is_merge: bool,
     ) -> Result<()> {
-        let mut observation = observations
-            .pop()
-            .expect("At least one observation must present in the track.");
-
-        let obs_attrs = observation
-            .attr()
-            .as_ref()
-            .expect("Observation attributes must always present.");
-
-        let observation_bbox = obs_attrs.unchecked_bbox_ref();
+        let mut observation = observations.pop().ok_or_else(|| {
+            Errors::MalformedObservation(
+                "at least one observation must present in the track".into(),
+            )
+        })?;
+
+        let obs_attrs = observation.attr().as_ref().ok_or_else(|| {
+            Errors::MalformedObservation("the observation attributes are missing".into())
+        })?;
+
+        let observation_bbox = obs_attrs.bbox_opt().as_ref().ok_or_else(|| {
+            Errors::MalformedObservation("the observation bounding box is missing".into())
+        })?;
+        let settings = self
+            .opts
+            .class_settings(attrs.class_votes.majority().map(|c| c.class_id));
         let feature_quality = obs_attrs.visual_quality();
         let own_area_percentage_opt = *obs_attrs.own_area_percentage_opt();
+        let depth_opt = *obs_attrs.depth_opt();
+        if depth_opt.is_some() {
+            attrs.depth = depth_opt;
+        }
 
         let mut predicted_bbox = attrs.make_prediction(observation_bbox);
         attrs.update_history(
@@ -327,7 +559,8 @@ impl ObservationMetric<VisualAttributes, VisualObservationAttributes> for Visual
             && !self.feature_can_be_used(
                 &Some(observation_bbox),
                 feature_quality,
-                self.opts.visual_minimal_quality_collect,
+                settings.visual_minimal_area,
+                settings.visual_minimal_quality_collect,
                 &own_area_percentage_opt,
                 self.opts.visual_minimal_own_area_percentage_collect,
             )
@@ -335,6 +568,19 @@ impl ObservationMetric<VisualAttributes, VisualObservationAttributes> for Visual
             *observation.feature_mut() = None;
         }
 
+        if observation.feature().is_some() {
+            if !is_merge {
+                // the first feature of the track is always kept, but counts towards the limit
+                self.record_collected_feature(attrs);
+            } else if !self.feature_can_be_collected(attrs, &predicted_bbox) {
+                *observation.feature_mut() = None;
+            }
+        }
+
+        if let (true, Some(feature)) = (is_merge, observation.feature()) {
+            self.record_feature_distance(attrs, settings.visual_kind, feature, observations);
+        }
+
         *observation.attr_mut() = Some(if let Some(percentage) = own_area_percentage_opt {
             VisualObservationAttributes::with_own_area_percentage(
                 feature_quality,
@@ -347,6 +593,7 @@ impl ObservationMetric<VisualAttributes, VisualObservationAttributes> for Visual
                 },
                 percentage,
             )
+            .with_depth(depth_opt)
         } else {
             VisualObservationAttributes::new(
                 feature_quality,
@@ -358,9 +605,10 @@ impl ObservationMetric<VisualAttributes, VisualObservationAttributes> for Visual
                     }
                 },
             )
+            .with_depth(depth_opt)
         });
 
-        self.optimize_observations(observations);
+        self.optimize_observations(observations, settings.visual_max_observations);
         observations.push(observation);
         let current_len = observations.len();
         observations.swap(0, current_len - 1);
@@ -377,11 +625,32 @@ impl ObservationMetric<VisualAttributes, VisualObservationAttributes> for Visual
         &self,
         unfiltered: Vec<ObservationMetricOk<VisualObservationAttributes>>,
     ) -> Vec<ObservationMetricOk<VisualObservationAttributes>> {
+        let positional_max_distance = self.opts.positional_max_distance;
+        let visual_max_distance = self.opts.visual_max_distance;
         unfiltered
             .into_iter()
+            .map(|mut res| {
+                if let (Some(m), Some(max_distance)) =
+                    (res.attribute_metric, positional_max_distance)
+                {
+                    if self.opts.positional_kind.metric_to_distance(m) > max_distance {
+                        res.attribute_metric = None;
+                    }
+                }
+                if let (Some(d), Some(max_distance)) = (res.feature_distance, visual_max_distance) {
+                    if d > max_distance {
+                        res.feature_distance = None;
+                    }
+                }
+                res
+            })
             .filter(|res| res.feature_distance.is_some() || res.attribute_metric.is_some())
             .collect()
     }
+
+    fn schema_name() -> &'static str {
+        "VisualMetric"
+    }
 }
 
 #[cfg(test)]
@@ -633,22 +902,52 @@ mod optimize {
             "Feature must be removed because the minimum own area percentage is lower than specified in metric options"
         );
     }
+
+    #[test]
+    fn optimize_observations_without_attributes() {
+        let metric = VisualMetricBuilder::default().build();
+        let observation = |quality: Option<f32>| {
+            Observation::new(
+                quality.map(|q| {
+                    VisualObservationAttributes::new(
+                        q,
+                        BoundingBox::new(0.0, 0.0, 5.0, 10.0).as_xyaah(),
+                    )
+                }),
+                Some(vec2(0.0, 1.0)),
+            )
+        };
+        let mut obs = vec![
+            observation(Some(0.5)),
+            observation(None),
+            observation(Some(f32::NAN)),
+            observation(Some(0.9)),
+        ];
+        metric.optimize_observations(&mut obs, 10);
+        let qualities = obs
+            .iter()
+            .map(|o| o.attr().as_ref().map(|a| a.visual_quality()))
+            .collect::<Vec<_>>();
+        assert_eq!(qualities[..2], [Some(0.9), Some(0.5)]);
+        assert_eq!(obs.len(), 4);
+    }
 }
 
 #[cfg(test)]
 mod metric_tests {
     use crate::examples::vec2;
-    use crate::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
+    use crate::prelude::{NoopNotifier, ObservationBuilder, TrackBuilder, TrackStoreBuilder};
     use crate::store::TrackStore;
-    use crate::track::ObservationMetricOk;
+    use crate::track::{ObservationMetric, ObservationMetricOk};
     use crate::trackers::sort::{PositionalMetricType, SortAttributesOptions};
     use crate::trackers::spatio_temporal_constraints::SpatioTemporalConstraints;
     use crate::trackers::visual_sort::metric::builder::VisualMetricBuilder;
+    use crate::trackers::visual_sort::metric::class_options::VisualClassOptions;
     use crate::trackers::visual_sort::metric::{VisualMetric, VisualSortMetricType};
     use crate::trackers::visual_sort::observation_attributes::VisualObservationAttributes;
     use crate::trackers::visual_sort::track_attributes::VisualAttributes;
     use crate::utils::bbox::BoundingBox;
-    use crate::EPS;
+    use crate::{Errors, EPS};
     use std::sync::Arc;
 
     fn default_attrs() -> VisualAttributes {
@@ -672,6 +971,71 @@ mod metric_tests {
             .build()
     }
 
+    #[test]
+    fn malformed_observation() {
+        let store = default_store(VisualMetricBuilder::default().build());
+        let res = store
+            .new_track(1)
+            .observation(
+                ObservationBuilder::new(0)
+                    .observation(vec2(0.1, 1.1))
+                    .build(),
+            )
+            .build();
+        assert!(matches!(
+            res.err().and_then(|e| e.downcast::<Errors>().ok()),
+            Some(Errors::MalformedObservation(_))
+        ));
+    }
+
+    #[test]
+    fn class_options() {
+        let metric = VisualMetricBuilder::default()
+            .visual_minimal_track_length(1)
+            .visual_class_options(
+                2,
+                VisualClassOptions::default()
+                    .visual_metric(VisualSortMetricType::euclidean(0.01))
+                    .visual_max_observations(7),
+            )
+            .build();
+        let settings = metric.opts.class_settings(Some(2));
+        assert_eq!(settings.visual_max_observations, 7);
+        assert_eq!(settings.visual_minimal_track_length, 1);
+        assert_eq!(
+            metric.opts.class_settings(Some(3)).visual_max_observations,
+            5
+        );
+
+        let track = |id: u64, class_id: Option<i64>, feature: f32| {
+            let mut attrs = default_attrs();
+            if let Some(class_id) = class_id {
+                attrs.class_votes.vote(class_id, None);
+            }
+            TrackBuilder::new(id)
+                .metric(metric.clone())
+                .attributes(attrs)
+                .notifier(NoopNotifier)
+                .observation(
+                    ObservationBuilder::new(0)
+                        .observation(vec2(0.1, feature))
+                        .observation_attributes(VisualObservationAttributes::new(
+                            1.0,
+                            BoundingBox::new(0.3, 0.3, 5.1, 10.0).as_xyaah(),
+                        ))
+                        .build(),
+                )
+                .build()
+                .unwrap()
+        };
+
+        let target = track(3, None, 1.0);
+        let dists = track(1, None, 1.1).distances(&target, 0).unwrap();
+        assert!(dists[0].feature_distance.is_some());
+        let dists = track(2, Some(2), 1.1).distances(&target, 0).unwrap();
+        assert!(dists[0].feature_distance.is_none());
+    }
+
     #[test]
     fn pos_metric_far() {
         let metric = VisualMetricBuilder::default()
@@ -1096,4 +1460,31 @@ mod metric_tests {
                 feature_distance: None     // own area percentage is low
             } if (x - 1.0).abs() < EPS));
     }
+
+    #[test]
+    fn max_distance() {
+        let metric = VisualMetricBuilder::default()
+            .positional_metric(PositionalMetricType::IoU(0.3))
+            .positional_max_distance(0.5)
+            .visual_max_distance(0.3)
+            .build();
+        let res = metric.postprocess_distances(vec![
+            ObservationMetricOk::new(1, 2, Some(0.8), Some(0.1)),
+            ObservationMetricOk::new(1, 3, Some(0.4), Some(0.1)),
+            ObservationMetricOk::new(1, 4, Some(0.8), Some(0.5)),
+            ObservationMetricOk::new(1, 5, Some(0.4), Some(0.5)),
+        ]);
+        let res = res
+            .iter()
+            .map(|r| (r.to, r.attribute_metric, r.feature_distance))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            res,
+            vec![
+                (2, Some(0.8), Some(0.1)),
+                (3, None, Some(0.1)),
+                (4, Some(0.8), None)
+            ]
+        );
+    }
 }
@@ -1,11 +1,17 @@
 use crate::trackers::sort::PositionalMetricType;
+use crate::trackers::visual_sort::adaptive_threshold::AdaptiveVisualThreshold;
+use crate::trackers::visual_sort::metric::class_options::VisualClassOptions;
 use crate::trackers::visual_sort::metric::{
     VisualMetric, VisualMetricOptions, VisualSortMetricType,
 };
+use crate::trackers::visual_sort::voting::VisualFusion;
+use crate::trackers::visual_sort::whitening::{FeatureWhitening, SceneFeatureStatistics};
 
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VisualMetricBuilder {
     visual_kind: VisualSortMetricType,
     positional_kind: PositionalMetricType,
@@ -15,9 +21,22 @@ pub struct VisualMetricBuilder {
     visual_minimal_quality_collect: f32,
     visual_max_observations: usize,
     visual_min_votes: usize,
+    visual_min_votes_fraction: Option<f32>,
+    visual_fusion: VisualFusion,
     visual_minimal_own_area_percentage_use: f32,
     visual_minimal_own_area_percentage_collect: f32,
     positional_min_confidence: f32,
+    positional_max_depth_delta: Option<f32>,
+    positional_max_distance: Option<f32>,
+    visual_max_distance: Option<f32>,
+    visual_adaptive_threshold: Option<AdaptiveVisualThreshold>,
+    visual_collect_rate_limit: Option<(usize, usize)>,
+    visual_collect_sampling: f32,
+    visual_collect_sampling_seed: u64,
+    #[serde(default)]
+    visual_class_options: HashMap<i64, VisualClassOptions>,
+    #[serde(default)]
+    visual_whitening: Option<FeatureWhitening>,
 }
 
 /// By default the metric object is constructed with: Euclidean visual_sort metric, IoU(0.3) positional metric
@@ -34,9 +53,20 @@ impl Default for VisualMetricBuilder {
             visual_minimal_quality_collect: 0.0,
             visual_max_observations: 5,
             visual_min_votes: 1,
+            visual_min_votes_fraction: None,
+            visual_fusion: VisualFusion::Sequential,
             visual_minimal_own_area_percentage_use: 0.0,
             visual_minimal_own_area_percentage_collect: 0.0,
             positional_min_confidence: 0.1,
+            positional_max_depth_delta: None,
+            positional_max_distance: None,
+            visual_max_distance: None,
+            visual_adaptive_threshold: None,
+            visual_collect_rate_limit: None,
+            visual_collect_sampling: 1.0,
+            visual_collect_sampling_seed: 0,
+            visual_class_options: HashMap::default(),
+            visual_whitening: None,
         }
     }
 }
@@ -65,6 +95,20 @@ impl VisualMetricBuilder {
         self
     }
 
+    pub fn visual_min_votes_fraction(mut self, fraction: f32) -> Self {
+        assert!(
+            fraction > 0.0 && fraction <= 1.0,
+            "Fraction must lay between (0.0 and 1.0]"
+        );
+        self.visual_min_votes_fraction = Some(fraction);
+        self
+    }
+
+    pub fn visual_fusion(mut self, fusion: VisualFusion) -> Self {
+        self.visual_fusion = fusion;
+        self
+    }
+
     pub fn positional_min_confidence(mut self, conf: f32) -> Self {
         assert!(
             (0.01..=1.0).contains(&conf),
@@ -74,11 +118,66 @@ impl VisualMetricBuilder {
         self
     }
 
+    pub fn positional_max_depth_delta(mut self, delta: f32) -> Self {
+        self.set_positional_max_depth_delta(Some(delta));
+        self
+    }
+
+    pub fn positional_max_distance(mut self, distance: f32) -> Self {
+        assert!(distance >= 0.0, "Distance must be a non-negative number");
+        self.positional_max_distance = Some(distance);
+        self
+    }
+
+    pub fn visual_max_distance(mut self, distance: f32) -> Self {
+        assert!(distance >= 0.0, "Distance must be a non-negative number");
+        self.visual_max_distance = Some(distance);
+        self
+    }
+
+    pub fn visual_adaptive_threshold(mut self, adaptive: AdaptiveVisualThreshold) -> Self {
+        self.visual_adaptive_threshold = Some(adaptive);
+        self
+    }
+
+    pub fn visual_collect_rate_limit(mut self, max_features: usize, epochs: usize) -> Self {
+        assert!(
+            max_features > 0 && epochs > 0,
+            "The number of features and epochs must be greater than 0"
+        );
+        self.visual_collect_rate_limit = Some((max_features, epochs));
+        self
+    }
+
+    pub fn visual_collect_sampling(mut self, probability: f32) -> Self {
+        assert!(
+            probability > 0.0 && probability <= 1.0,
+            "Probability must lay between (0.0 and 1.0]"
+        );
+        self.visual_collect_sampling = probability;
+        self
+    }
+
+    pub fn visual_collect_sampling_seed(mut self, seed: u64) -> Self {
+        self.visual_collect_sampling_seed = seed;
+        self
+    }
+
+    pub fn visual_whitening(mut self, whitening: FeatureWhitening) -> Self {
+        self.visual_whitening = Some(whitening);
+        self
+    }
+
     pub fn visual_metric(mut self, metric: VisualSortMetricType) -> Self {
         self.visual_kind = metric;
         self
     }
 
+    pub fn visual_class_options(mut self, class_id: i64, options: VisualClassOptions) -> Self {
+        self.visual_class_options.insert(class_id, options);
+        self
+    }
+
     pub fn positional_metric(mut self, metric: PositionalMetricType) -> Self {
         if let PositionalMetricType::IoU(t) = metric {
             assert!(
@@ -141,6 +240,16 @@ impl VisualMetricBuilder {
         VisualMetric {
             opts: Arc::new(VisualMetricOptions {
                 positional_min_confidence: self.positional_min_confidence,
+                positional_max_depth_delta: self.positional_max_depth_delta,
+                positional_max_distance: self.positional_max_distance,
+                visual_max_distance: self.visual_max_distance,
+                visual_adaptive_threshold: self.visual_adaptive_threshold,
+                visual_collect_rate_limit: self.visual_collect_rate_limit,
+                visual_collect_sampling: self.visual_collect_sampling,
+                visual_collect_sampling_seed: self.visual_collect_sampling_seed,
+                visual_class_options: self.visual_class_options.clone(),
+                visual_whitening: self.visual_whitening,
+                feature_statistics: SceneFeatureStatistics::default(),
                 visual_kind: self.visual_kind,
                 positional_kind: self.positional_kind,
                 visual_minimal_track_length: self.visual_minimal_track_length,
@@ -149,6 +258,8 @@ impl VisualMetricBuilder {
                 visual_minimal_quality_collect: self.visual_minimal_quality_collect,
                 visual_max_observations: self.visual_max_observations,
                 visual_min_votes: self.visual_min_votes,
+                visual_min_votes_fraction: self.visual_min_votes_fraction,
+                visual_fusion: self.visual_fusion,
                 visual_minimal_own_area_percentage_use: self.visual_minimal_own_area_percentage_use,
                 visual_minimal_own_area_percentage_collect: self
                     .visual_minimal_own_area_percentage_collect,
@@ -191,6 +302,21 @@ impl VisualMetricBuilder {
         self.visual_min_votes = visual_min_votes;
     }
 
+    #[inline]
+    pub fn set_visual_min_votes_fraction(&mut self, visual_min_votes_fraction: Option<f32>) {
+        self.visual_min_votes_fraction = visual_min_votes_fraction;
+    }
+
+    #[inline]
+    pub fn set_visual_class_options(&mut self, class_id: i64, options: VisualClassOptions) {
+        self.visual_class_options.insert(class_id, options);
+    }
+
+    #[inline]
+    pub fn set_visual_fusion(&mut self, visual_fusion: VisualFusion) {
+        self.visual_fusion = visual_fusion;
+    }
+
     #[inline]
     pub fn set_visual_minimal_own_area_percentage_use(
         &mut self,
@@ -212,6 +338,56 @@ impl VisualMetricBuilder {
         self.positional_min_confidence = positional_min_confidence;
     }
 
+    #[inline]
+    pub fn set_positional_max_depth_delta(&mut self, positional_max_depth_delta: Option<f32>) {
+        assert!(
+            positional_max_depth_delta.map(|d| d > 0.0).unwrap_or(true),
+            "Depth delta must be a positive number"
+        );
+        self.positional_max_depth_delta = positional_max_depth_delta;
+    }
+
+    #[inline]
+    pub fn set_positional_max_distance(&mut self, positional_max_distance: Option<f32>) {
+        self.positional_max_distance = positional_max_distance;
+    }
+
+    #[inline]
+    pub fn set_visual_max_distance(&mut self, visual_max_distance: Option<f32>) {
+        self.visual_max_distance = visual_max_distance;
+    }
+
+    #[inline]
+    pub fn set_visual_adaptive_threshold(
+        &mut self,
+        visual_adaptive_threshold: Option<AdaptiveVisualThreshold>,
+    ) {
+        self.visual_adaptive_threshold = visual_adaptive_threshold;
+    }
+
+    #[inline]
+    pub fn set_visual_collect_rate_limit(
+        &mut self,
+        visual_collect_rate_limit: Option<(usize, usize)>,
+    ) {
+        self.visual_collect_rate_limit = visual_collect_rate_limit;
+    }
+
+    #[inline]
+    pub fn set_visual_collect_sampling(&mut self, visual_collect_sampling: f32) {
+        self.visual_collect_sampling = visual_collect_sampling;
+    }
+
+    #[inline]
+    pub fn set_visual_collect_sampling_seed(&mut self, visual_collect_sampling_seed: u64) {
+        self.visual_collect_sampling_seed = visual_collect_sampling_seed;
+    }
+
+    #[inline]
+    pub fn set_visual_whitening(&mut self, visual_whitening: Option<FeatureWhitening>) {
+        self.visual_whitening = visual_whitening;
+    }
+
     pub fn set_visual_kind(&mut self, visual_kind: VisualSortMetricType) {
         self.visual_kind = visual_kind;
     }
@@ -0,0 +1,162 @@
+use crate::trackers::visual_sort::metric::VisualSortMetricType;
+use serde::{Deserialize, Serialize};
+
+/// The visual settings of the object class, the settings which are not set are taken from the
+/// metric options.
+///
+/// The class of the candidate decides the settings used to compare it with the tracks, the
+/// majority class of the track decides the settings used to collect its features.
+///
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct VisualClassOptions {
+    visual_kind: Option<VisualSortMetricType>,
+    visual_minimal_track_length: Option<usize>,
+    visual_minimal_area: Option<f32>,
+    visual_minimal_quality_use: Option<f32>,
+    visual_minimal_quality_collect: Option<f32>,
+    visual_max_observations: Option<usize>,
+}
+
+/// The visual settings resolved for the object class
+///
+#[derive(Debug, Clone, Copy)]
+pub struct VisualClassSettings {
+    pub visual_kind: VisualSortMetricType,
+    pub visual_minimal_track_length: usize,
+    pub visual_minimal_area: f32,
+    pub visual_minimal_quality_use: f32,
+    pub visual_minimal_quality_collect: f32,
+    pub visual_max_observations: usize,
+}
+
+impl VisualClassOptions {
+    pub fn visual_metric(mut self, metric: VisualSortMetricType) -> Self {
+        self.visual_kind = Some(metric);
+        self
+    }
+
+    pub fn visual_minimal_track_length(mut self, length: usize) -> Self {
+        assert!(
+            length > 0,
+            "The minimal track length must be greater than 0"
+        );
+        self.visual_minimal_track_length = Some(length);
+        self
+    }
+
+    pub fn visual_minimal_area(mut self, area: f32) -> Self {
+        assert!(
+            area >= 0.0,
+            "The minimal area must be a non-negative number"
+        );
+        self.visual_minimal_area = Some(area);
+        self
+    }
+
+    pub fn visual_minimal_quality_use(mut self, q: f32) -> Self {
+        assert!(
+            q >= 0.0,
+            "The minimal quality must be a non-negative number"
+        );
+        self.visual_minimal_quality_use = Some(q);
+        self
+    }
+
+    pub fn visual_minimal_quality_collect(mut self, q: f32) -> Self {
+        assert!(
+            q >= 0.0,
+            "The minimal quality must be a non-negative number"
+        );
+        self.visual_minimal_quality_collect = Some(q);
+        self
+    }
+
+    pub fn visual_max_observations(mut self, n: usize) -> Self {
+        self.visual_max_observations = Some(n);
+        self
+    }
+
+    /// Fills the settings which are not set from the defaults
+    ///
+    pub fn resolve(&self, defaults: VisualClassSettings) -> VisualClassSettings {
+        VisualClassSettings {
+            visual_kind: self.visual_kind.unwrap_or(defaults.visual_kind),
+            visual_minimal_track_length: self
+                .visual_minimal_track_length
+                .unwrap_or(defaults.visual_minimal_track_length),
+            visual_minimal_area: self
+                .visual_minimal_area
+                .unwrap_or(defaults.visual_minimal_area),
+            visual_minimal_quality_use: self
+                .visual_minimal_quality_use
+                .unwrap_or(defaults.visual_minimal_quality_use),
+            visual_minimal_quality_collect: self
+                .visual_minimal_quality_collect
+                .unwrap_or(defaults.visual_minimal_quality_collect),
+            visual_max_observations: self
+                .visual_max_observations
+                .unwrap_or(defaults.visual_max_observations),
+        }
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::VisualClassOptions;
+    use crate::trackers::visual_sort::metric::python::PyVisualSortMetricType;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "VisualClassOptions")]
+    #[derive(Debug, Clone, Default)]
+    pub struct PyVisualClassOptions(pub(crate) VisualClassOptions);
+
+    #[pymethods]
+    impl PyVisualClassOptions {
+        #[new]
+        pub(crate) fn new() -> Self {
+            Self::default()
+        }
+
+        #[pyo3(text_signature = "($self, metric)")]
+        pub(crate) fn visual_metric(&mut self, metric: PyVisualSortMetricType) {
+            self.0 = self.0.visual_metric(metric.0);
+        }
+
+        #[pyo3(text_signature = "($self, length)")]
+        pub(crate) fn visual_minimal_track_length(&mut self, length: usize) {
+            self.0 = self.0.visual_minimal_track_length(length);
+        }
+
+        #[pyo3(text_signature = "($self, area)")]
+        pub(crate) fn visual_minimal_area(&mut self, area: f32) {
+            self.0 = self.0.visual_minimal_area(area);
+        }
+
+        #[pyo3(text_signature = "($self, q)")]
+        pub(crate) fn visual_minimal_quality_use(&mut self, q: f32) {
+            self.0 = self.0.visual_minimal_quality_use(q);
+        }
+
+        #[pyo3(text_signature = "($self, q)")]
+        pub(crate) fn visual_minimal_quality_collect(&mut self, q: f32) {
+            self.0 = self.0.visual_minimal_quality_collect(q);
+        }
+
+        #[pyo3(text_signature = "($self, n)")]
+        pub(crate) fn visual_max_observations(&mut self, n: usize) {
+            self.0 = self.0.visual_max_observations(n);
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
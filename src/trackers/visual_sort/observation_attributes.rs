@@ -1,13 +1,15 @@
 use crate::track::{Observation, ObservationAttributes};
 use crate::utils::bbox::Universal2DBox;
 use crate::EPS;
+use serde::{Deserialize, Serialize};
 use std::fmt::Formatter;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct VisualObservationAttributes {
     bbox: Option<Universal2DBox>,
     visual_quality: f32,
     own_area_percentage: Option<f32>,
+    depth: Option<f32>,
 }
 
 impl VisualObservationAttributes {
@@ -16,6 +18,7 @@ impl VisualObservationAttributes {
             visual_quality: q,
             bbox: Some(b),
             own_area_percentage: None,
+            depth: None,
         }
     }
 
@@ -29,9 +32,17 @@ impl VisualObservationAttributes {
             visual_quality: q,
             bbox: Some(b),
             own_area_percentage: Some(own_area_percentage),
+            depth: None,
         }
     }
 
+    /// Sets the distance from the observer to the object (from the stereo or monodepth model)
+    ///
+    pub fn with_depth(mut self, depth: Option<f32>) -> Self {
+        self.depth = depth;
+        self
+    }
+
     pub fn unchecked_bbox_ref(&self) -> &Universal2DBox {
         self.bbox.as_ref().unwrap()
     }
@@ -44,6 +55,10 @@ impl VisualObservationAttributes {
         &self.own_area_percentage
     }
 
+    pub fn depth_opt(&self) -> &Option<f32> {
+        &self.depth
+    }
+
     pub fn drop_bbox(&mut self) {
         self.bbox = None;
     }
@@ -82,6 +97,14 @@ impl ObservationAttributes for VisualObservationAttributes {
             None
         }
     }
+
+    fn metric_object_is_nan(o: &Self::MetricObject) -> bool {
+        o.is_nan()
+    }
+
+    fn schema_name() -> &'static str {
+        "VisualObservationAttributes"
+    }
 }
 
 impl PartialEq<Self> for VisualObservationAttributes {
@@ -1,12 +1,36 @@
+use crate::store::nan_distances::NanDistancePolicy;
+use crate::track::calibration::SceneCalibration;
+use crate::trackers::candidate_clustering::CandidateClustering;
+use crate::trackers::density::DensityOptions;
+use crate::trackers::duplicate_ids::DuplicateObjectIdPolicy;
+use crate::trackers::frame_clamping::FrameClamping;
+use crate::trackers::history_fallback::HistoryFallback;
+use crate::trackers::latency_budget::LatencyBudget;
+use crate::trackers::motion_model::MotionModel;
+use crate::trackers::noise_model::NoiseModelOptions;
+use crate::trackers::observation_intake::ObservationIntake;
+use crate::trackers::observation_pipeline::ObservationPipeline;
+use crate::trackers::output_filter::TrackOutputFilter;
+use crate::trackers::quality::QualityOptions;
+use crate::trackers::scene_zones::SceneZones;
 use crate::trackers::sort::{PositionalMetricType, SortAttributesOptions};
 use crate::trackers::spatio_temporal_constraints::SpatioTemporalConstraints;
+use crate::trackers::visual_sort::adaptive_threshold::AdaptiveVisualThreshold;
+use crate::trackers::visual_sort::cold_store::ColdStoreOptions;
 use crate::trackers::visual_sort::metric::builder::VisualMetricBuilder;
+use crate::trackers::visual_sort::metric::class_options::VisualClassOptions;
 use crate::trackers::visual_sort::metric::{VisualMetric, VisualSortMetricType};
+use crate::trackers::visual_sort::reactivation::ReactivationOptions;
+use crate::trackers::visual_sort::voting::VisualFusion;
+use crate::trackers::visual_sort::whitening::FeatureWhitening;
+use crate::trackers::waste_triggers::WasteTriggers;
+use crate::utils::kalman::gating::MahalanobisGating;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::RwLock;
 
 /// Class that is used to configure the Visual Tracker
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VisualSortOptions {
     max_idle_epochs: usize,
     kept_history_length: usize,
@@ -14,21 +38,106 @@ pub struct VisualSortOptions {
     metric_builder: VisualMetricBuilder,
     kalman_position_weight: f32,
     kalman_velocity_weight: f32,
+    output_filter: TrackOutputFilter,
+    scene_zones: SceneZones,
+    scene_calibrations: HashMap<u64, SceneCalibration>,
+    duplicate_object_id_policy: DuplicateObjectIdPolicy,
+    #[serde(default)]
+    nan_distance_policy: NanDistancePolicy,
+    candidate_clustering: Option<CandidateClustering>,
+    noise_model: Option<NoiseModelOptions>,
+    density: Option<DensityOptions>,
+    #[serde(default)]
+    quality: Option<QualityOptions>,
+    class_freeze: Option<usize>,
+    #[serde(default)]
+    skipped_epochs_prediction: bool,
+    #[serde(default)]
+    observation_timestamps: bool,
+    max_track_lifetime: Option<usize>,
+    #[serde(default)]
+    waste_triggers: WasteTriggers,
+    frame_clamping: FrameClamping,
+    #[serde(default)]
+    history_fallback: HistoryFallback,
+    #[serde(default)]
+    motion_model: MotionModel,
+    mahalanobis_gating: Option<MahalanobisGating>,
+    observation_pipeline: ObservationPipeline,
+    reactivation: Option<ReactivationOptions>,
+    #[serde(default)]
+    cold_store: Option<ColdStoreOptions>,
+    #[serde(default)]
+    latency_budget: Option<LatencyBudget>,
+    #[cfg(feature = "f16")]
+    compact_box_history: bool,
 }
 
 impl VisualSortOptions {
     pub(crate) fn build(self) -> (SortAttributesOptions, VisualMetric) {
-        (
-            SortAttributesOptions::new(
-                Some(RwLock::new(HashMap::default())),
-                self.max_idle_epochs,
-                self.kept_history_length,
-                self.spatio_temporal_constraints,
-                self.kalman_position_weight,
-                self.kalman_velocity_weight,
-            ),
-            self.metric_builder.build(),
+        let track_opts = SortAttributesOptions::new(
+            Some(RwLock::new(HashMap::default())),
+            self.max_idle_epochs,
+            self.kept_history_length,
+            self.spatio_temporal_constraints,
+            self.kalman_position_weight,
+            self.kalman_velocity_weight,
         )
+        .with_output_filter(self.output_filter)
+        .with_scene_zones(self.scene_zones);
+        for (scene_id, calibration) in self.scene_calibrations {
+            track_opts.set_scene_calibration(scene_id, Some(calibration));
+        }
+        track_opts.set_noise_model(self.noise_model);
+        track_opts.set_density_estimation(self.density);
+        track_opts.set_quality_estimation(self.quality);
+        track_opts.set_class_freeze(self.class_freeze);
+        track_opts.set_skipped_epochs_prediction(self.skipped_epochs_prediction);
+        track_opts.set_observation_timestamps(self.observation_timestamps);
+        track_opts.set_max_track_lifetime(self.max_track_lifetime);
+        track_opts.set_waste_triggers(self.waste_triggers);
+        track_opts.set_frame_clamping(self.frame_clamping);
+        track_opts.set_history_fallback(self.history_fallback);
+        track_opts.set_motion_model(self.motion_model);
+        track_opts.set_mahalanobis_gating(self.mahalanobis_gating);
+        if let Some(cold_store) = &self.cold_store {
+            assert!(
+                cold_store.demote_after() < self.max_idle_epochs,
+                "The tracks must be demoted to the cold store before they are wasted"
+            );
+        }
+        track_opts.set_latency_budget(self.latency_budget);
+        #[cfg(feature = "f16")]
+        track_opts.set_compact_box_history(self.compact_box_history);
+        (track_opts, self.metric_builder.build())
+    }
+
+    /// The handling of the NaN distances of the track pairs, applied to the store of the tracker
+    ///
+    pub(crate) fn get_nan_distance_policy(&self) -> NanDistancePolicy {
+        self.nan_distance_policy
+    }
+
+    /// The preparation of the observations passed to the tracker, owned by the tracker
+    ///
+    pub(crate) fn observation_intake(&self) -> ObservationIntake {
+        ObservationIntake::new(
+            self.observation_pipeline.clone(),
+            self.duplicate_object_id_policy,
+            self.candidate_clustering,
+        )
+    }
+
+    /// The appearance-only reactivation of the idle tracks, applied by the tracker
+    ///
+    pub(crate) fn get_reactivation(&self) -> Option<ReactivationOptions> {
+        self.reactivation
+    }
+
+    /// The cold tier of the long idle tracks, kept by the tracker
+    ///
+    pub(crate) fn get_cold_store(&self) -> Option<ColdStoreOptions> {
+        self.cold_store
     }
 
     /// The number of epochs the track remains active.
@@ -88,6 +197,38 @@ impl VisualSortOptions {
         self
     }
 
+    /// Scales the required visual votes with the number of the features kept by the track: the
+    /// track keeping `N` features requires `ceil(fraction * N)` votes, but at least 1 and at most
+    /// `visual_min_votes`. Without the scaling the tracks keeping fewer than `visual_min_votes`
+    /// features are always associated by the positional metric.
+    ///
+    pub fn visual_min_votes_fraction(mut self, fraction: f32) -> Self {
+        self.metric_builder = self.metric_builder.visual_min_votes_fraction(fraction);
+        self
+    }
+
+    /// Overrides the visual settings for the observations of the class, so the objects of
+    /// different classes, e.g. the people and the vehicles, are matched by the appearance tuned
+    /// for them. The candidate is compared with the tracks by the settings of its class, the
+    /// features are collected by the settings of the majority class of the track. The
+    /// observations without the class use the common settings.
+    ///
+    pub fn visual_class_options(mut self, class_id: i64, options: VisualClassOptions) -> Self {
+        self.metric_builder = self.metric_builder.visual_class_options(class_id, options);
+        self
+    }
+
+    /// How the visual and the positional distances are combined when the candidates are
+    /// associated with the tracks. By default the candidates are associated by the features
+    /// first and the remaining ones by the positional metric; with
+    /// [ReciprocalRank](VisualFusion::ReciprocalRank) the tracks are ranked by both distances and
+    /// the ranks are combined.
+    ///
+    pub fn visual_fusion(mut self, fusion: VisualFusion) -> Self {
+        self.metric_builder = self.metric_builder.visual_fusion(fusion);
+        self
+    }
+
     /// The maximum number of visual_sort observations kept in the track for visual_sort estimations. The features
     /// are collected in the track from the candidates, and when the `visual_max_observations` is
     /// reached, the features with lower quality are wiped from the track.
@@ -136,6 +277,9 @@ impl VisualSortOptions {
 
     /// The minimal required area of track candidate's bounding box to use the visual_sort feature in estimation.
     /// This parameter protects from the low-quality features received from the smallish boxes.
+    /// The area is measured in the coordinates of the boxes passed to the tracker, the normalized
+    /// boxes can be brought to pixels with
+    /// [ObservationTransformer::Coordinates](crate::trackers::observation_pipeline::ObservationTransformer::Coordinates).
     ///
     pub fn visual_minimal_area(mut self, area: f32) -> Self {
         self.metric_builder = self.metric_builder.visual_minimal_area(area);
@@ -180,6 +324,76 @@ impl VisualSortOptions {
         self
     }
 
+    /// The maximal allowed difference between the depth (distance from the observer) of the track
+    /// candidate and the track to compare them positionally. It prevents merging of objects that overlap
+    /// on the image but are situated at different distances. The depth is only checked when both
+    /// candidate and track have it defined.
+    ///
+    pub fn positional_max_depth_delta(mut self, delta: f32) -> Self {
+        self.metric_builder = self.metric_builder.positional_max_depth_delta(delta);
+        self
+    }
+
+    /// The hard cut-off for the positional distance (the Mahalanobis cost or `1 - IoU`) applied
+    /// before the voting. The pairs that are farther are never associated positionally, even if
+    /// the positional threshold allows it.
+    ///
+    pub fn positional_max_distance(mut self, distance: f32) -> Self {
+        self.metric_builder = self.metric_builder.positional_max_distance(distance);
+        self
+    }
+
+    /// The hard cut-off for the feature distance (the Euclidean distance or `1 - cosine`) applied
+    /// before the voting. The pairs that are farther are never associated visually, even if the
+    /// visual threshold is disabled with `f32::MAX`.
+    ///
+    pub fn visual_max_distance(mut self, distance: f32) -> Self {
+        self.metric_builder = self.metric_builder.visual_max_distance(distance);
+        self
+    }
+
+    /// Replaces the visual threshold of the track with `mean + k * std` of the distances between
+    /// the features the track collected, once the track collected enough of them
+    ///
+    pub fn visual_adaptive_threshold(mut self, adaptive: AdaptiveVisualThreshold) -> Self {
+        self.metric_builder = self.metric_builder.visual_adaptive_threshold(adaptive);
+        self
+    }
+
+    /// Limits the feature collection rate for the track: at most `max_features` features are collected
+    /// during `epochs` epochs. It keeps the features of objects that stay still for a long time from
+    /// replacing the whole track gallery.
+    ///
+    pub fn visual_collect_rate_limit(mut self, max_features: usize, epochs: usize) -> Self {
+        self.metric_builder = self
+            .metric_builder
+            .visual_collect_rate_limit(max_features, epochs);
+        self
+    }
+
+    /// The probability the feature that satisfies the collection constraints is collected into the track
+    ///
+    pub fn visual_collect_sampling(mut self, probability: f32) -> Self {
+        self.metric_builder = self.metric_builder.visual_collect_sampling(probability);
+        self
+    }
+
+    /// The seed of the feature collection sampling, the trackers with the same seed collect the
+    /// same features from the same observations
+    ///
+    pub fn visual_collect_sampling_seed(mut self, seed: u64) -> Self {
+        self.metric_builder = self.metric_builder.visual_collect_sampling_seed(seed);
+        self
+    }
+
+    /// Estimates the diagonal covariance of the features per scene and whitens the incoming
+    /// features with it, look at [FeatureWhitening]
+    ///
+    pub fn visual_whitening(mut self, whitening: FeatureWhitening) -> Self {
+        self.metric_builder = self.metric_builder.visual_whitening(whitening);
+        self
+    }
+
     pub fn kalman_position_weight(mut self, weight: f32) -> Self {
         self.kalman_position_weight = weight;
         self
@@ -189,6 +403,195 @@ impl VisualSortOptions {
         self.kalman_velocity_weight = weight;
         self
     }
+
+    /// The filter that defines which tracks are reported to the caller. The tracks that don't pass the
+    /// filter (e.g. too short or coasted for too long) are still tracked but not returned.
+    ///
+    pub fn output_filter(mut self, filter: TrackOutputFilter) -> Self {
+        self.output_filter = filter;
+        self
+    }
+
+    /// Entry and exit zones of the scenes. The tracks appearing outside of the entry zones are not
+    /// reported until they pass the creation penalty, the tracks vanishing in the exit zones are
+    /// wasted without idling.
+    ///
+    pub fn scene_zones(mut self, zones: SceneZones) -> Self {
+        self.scene_zones = zones;
+        self
+    }
+
+    /// The handling of the observations passed with the same custom_object_id within the epoch
+    ///
+    pub fn duplicate_object_id_policy(mut self, policy: DuplicateObjectIdPolicy) -> Self {
+        self.duplicate_object_id_policy = policy;
+        self
+    }
+
+    /// The handling of the NaN distances returned by the metric, e.g. for the zero-norm features
+    ///
+    pub fn nan_distance_policy(mut self, policy: NanDistancePolicy) -> Self {
+        self.nan_distance_policy = policy;
+        self
+    }
+
+    /// Enables the clustering of the near-duplicate observations (e.g. produced by the detectors
+    /// without NMS or by the ensembles of detectors) before they are associated with the tracks
+    ///
+    pub fn candidate_clustering(mut self, clustering: CandidateClustering) -> Self {
+        self.candidate_clustering = Some(clustering);
+        self
+    }
+
+    /// Fixes the majority class of the track after it collects `n` class votes, so the reported
+    /// class does not change when the detector relabels the object later
+    ///
+    pub fn class_freeze(mut self, n: usize) -> Self {
+        self.class_freeze = Some(n);
+        self
+    }
+
+    /// Propagates the Kalman state of the alive tracks over the epochs skipped with
+    /// [skip_epochs_for_scene](crate::trackers::tracker_api::TrackerAPI::skip_epochs_for_scene),
+    /// so the tracks matched or reactivated after the gap are predicted where the objects moved
+    ///
+    pub fn skipped_epochs_prediction(mut self) -> Self {
+        self.skipped_epochs_prediction = true;
+        self
+    }
+
+    /// Records the wall-clock time of every observation of the tracks, the times are exported
+    /// with the wasted tracks
+    ///
+    pub fn observation_timestamps(mut self) -> Self {
+        self.observation_timestamps = true;
+        self
+    }
+
+    /// Limits the lifetime of the tracks: the track is wasted after `epochs` epochs since its
+    /// creation even if it is still matched, so the object gets a new track with a fresh id
+    ///
+    pub fn max_track_lifetime(mut self, epochs: usize) -> Self {
+        self.max_track_lifetime = Some(epochs);
+        self
+    }
+
+    /// Wastes the tracks when the custom conditions (leaving the region of interest, low
+    /// confidence, user predicates) fire in addition to the idle epochs expiry
+    ///
+    pub fn waste_triggers(mut self, triggers: WasteTriggers) -> Self {
+        self.waste_triggers = triggers;
+        self
+    }
+
+    /// Clamps the reported boxes (and optionally the Kalman state) to the frame, the frame size
+    /// is taken from the calibration of the scene
+    ///
+    pub fn frame_clamping(mut self, clamping: FrameClamping) -> Self {
+        self.frame_clamping = clamping;
+        self
+    }
+
+    /// The boxes reported for the tracks which box history is empty, the boxes are built from
+    /// the Kalman state by default
+    ///
+    pub fn history_fallback(mut self, fallback: HistoryFallback) -> Self {
+        self.history_fallback = fallback;
+        self
+    }
+
+    /// The motion model predicting the boxes of the tracks, with
+    /// [MotionModel::None](MotionModel::None) the observed boxes are used as is and the
+    /// Mahalanobis positional metric never matches
+    ///
+    pub fn motion_model(mut self, model: MotionModel) -> Self {
+        self.motion_model = model;
+        self
+    }
+
+    /// Replaces the legacy Mahalanobis cost with the chi-square gated score, the pairs outside of
+    /// the gate are never associated positionally
+    ///
+    pub fn mahalanobis_gating(mut self, gating: MahalanobisGating) -> Self {
+        self.mahalanobis_gating = Some(gating);
+        self
+    }
+
+    /// Registers the calibration of the scene (frame size, homography, scale, ROI, zones), the
+    /// entry and exit zones of the calibration replace the zones of the scene
+    ///
+    pub fn scene_calibration(mut self, scene_id: u64, calibration: SceneCalibration) -> Self {
+        self.scene_calibrations.insert(scene_id, calibration);
+        self
+    }
+
+    /// Sets the transformers (clamping, scaling, confidence calibration, ROI filtering) applied to
+    /// the incoming observations before the candidate tracks are created
+    ///
+    pub fn observation_pipeline(mut self, pipeline: ObservationPipeline) -> Self {
+        self.observation_pipeline = pipeline;
+        self
+    }
+
+    /// Enables the appearance-only reactivation of the idle tracks, so the objects are
+    /// re-acquired after the long occlusions when the positional gates reject them
+    ///
+    pub fn reactivation(mut self, opts: ReactivationOptions) -> Self {
+        self.reactivation = Some(opts);
+        self
+    }
+
+    /// Enables the cold tier of the long idle tracks: the tracks idle for the long time leave the
+    /// main store compressed and are promoted back when a candidate matches them by the
+    /// appearance. The cold tier is maintained by
+    /// [VisualSort](crate::trackers::visual_sort::simple_api::VisualSort) only.
+    ///
+    pub fn cold_store(mut self, opts: ColdStoreOptions) -> Self {
+        self.cold_store = Some(opts);
+        self
+    }
+
+    /// Enables the latency budget: the visual distances of the scene are skipped while its
+    /// predictions are too slow or too many of them are pending
+    ///
+    pub fn latency_budget(mut self, budget: LatencyBudget) -> Self {
+        self.latency_budget = Some(budget);
+        self
+    }
+
+    /// Enables the estimation of the detection noise from the Kalman filter residuals, its
+    /// results are reported per scene and optionally used instead of the configured Kalman weights
+    ///
+    pub fn noise_model(mut self, opts: NoiseModelOptions) -> Self {
+        self.noise_model = Some(opts);
+        self
+    }
+
+    /// Enables the estimation of the object density per scene, optionally used to tighten the
+    /// positional association in the congested cells and to loosen it in the sparse ones
+    ///
+    pub fn density_estimation(mut self, opts: DensityOptions) -> Self {
+        self.density = Some(opts);
+        self
+    }
+
+    /// Enables the online estimation of the id switch and fragmentation rates per scene
+    ///
+    pub fn quality_estimation(mut self, opts: QualityOptions) -> Self {
+        self.quality = Some(opts);
+        self
+    }
+
+    /// Keeps the observed and the predicted boxes history with half precision to reduce the memory
+    /// consumed by the long tracks. The last boxes are kept with full precision, so the tracking
+    /// itself is not affected, but the reported history is, look at
+    /// [with_compact_box_history](crate::trackers::sort::SortAttributesOptions::with_compact_box_history).
+    ///
+    #[cfg(feature = "f16")]
+    pub fn compact_box_history(mut self, enabled: bool) -> Self {
+        self.compact_box_history = enabled;
+        self
+    }
 }
 
 impl Default for VisualSortOptions {
@@ -200,15 +603,61 @@ impl Default for VisualSortOptions {
             spatio_temporal_constraints: SpatioTemporalConstraints::default(),
             kalman_position_weight: 1.0 / 20.0,
             kalman_velocity_weight: 1.0 / 160.0,
+            output_filter: TrackOutputFilter::default(),
+            scene_zones: SceneZones::default(),
+            scene_calibrations: HashMap::default(),
+            duplicate_object_id_policy: DuplicateObjectIdPolicy::default(),
+            nan_distance_policy: NanDistancePolicy::default(),
+            candidate_clustering: None,
+            noise_model: None,
+            density: None,
+            quality: None,
+            class_freeze: None,
+            skipped_epochs_prediction: false,
+            observation_timestamps: false,
+            max_track_lifetime: None,
+            waste_triggers: WasteTriggers::default(),
+            frame_clamping: FrameClamping::default(),
+            history_fallback: HistoryFallback::default(),
+            motion_model: MotionModel::default(),
+            mahalanobis_gating: None,
+            observation_pipeline: ObservationPipeline::default(),
+            reactivation: None,
+            cold_store: None,
+            latency_budget: None,
+            #[cfg(feature = "f16")]
+            compact_box_history: false,
         }
     }
 }
 
 #[cfg(feature = "python")]
 pub mod python {
+    use crate::store::nan_distances::python::PyNanDistancePolicy;
+    use crate::track::calibration::python::PySceneCalibration;
+    use crate::trackers::candidate_clustering::python::PyCandidateClustering;
+    use crate::trackers::density::python::PyDensityOptions;
+    use crate::trackers::duplicate_ids::python::PyDuplicateObjectIdPolicy;
+    use crate::trackers::frame_clamping::python::PyFrameClamping;
+    use crate::trackers::history_fallback::python::PyHistoryFallback;
+    use crate::trackers::latency_budget::python::PyLatencyBudget;
+    use crate::trackers::motion_model::python::PyMotionModel;
+    use crate::trackers::noise_model::python::PyNoiseModelOptions;
+    use crate::trackers::observation_pipeline::python::PyObservationPipeline;
+    use crate::trackers::output_filter::python::PyTrackOutputFilter;
+    use crate::trackers::quality::python::PyQualityOptions;
+    use crate::trackers::scene_zones::python::PySceneZones;
     use crate::trackers::sort::python::PyPositionalMetricType;
     use crate::trackers::spatio_temporal_constraints::python::PySpatioTemporalConstraints;
+    use crate::trackers::visual_sort::adaptive_threshold::python::PyAdaptiveVisualThreshold;
+    use crate::trackers::visual_sort::cold_store::python::PyColdStoreOptions;
+    use crate::trackers::visual_sort::metric::class_options::python::PyVisualClassOptions;
     use crate::trackers::visual_sort::metric::python::PyVisualSortMetricType;
+    use crate::trackers::visual_sort::reactivation::python::PyReactivationOptions;
+    use crate::trackers::visual_sort::voting::VisualFusion;
+    use crate::trackers::visual_sort::whitening::python::PyFeatureWhitening;
+    use crate::trackers::waste_triggers::python::PyWasteTriggers;
+    use crate::utils::kalman::gating::python::PyMahalanobisGating;
 
     use super::VisualSortOptions;
     use pyo3::prelude::*;
@@ -239,6 +688,35 @@ pub mod python {
             self.0.metric_builder.set_visual_min_votes(n as _);
         }
 
+        #[pyo3(text_signature = "($self, fraction)")]
+        pub(crate) fn visual_min_votes_fraction(&mut self, fraction: f32) {
+            assert!(
+                fraction > 0.0 && fraction <= 1.0,
+                "Fraction must lay between (0.0 and 1.0]"
+            );
+            self.0
+                .metric_builder
+                .set_visual_min_votes_fraction(Some(fraction));
+        }
+
+        #[pyo3(text_signature = "($self, class_id, options)")]
+        pub(crate) fn visual_class_options(
+            &mut self,
+            class_id: i64,
+            options: PyVisualClassOptions,
+        ) {
+            self.0
+                .metric_builder
+                .set_visual_class_options(class_id, options.0);
+        }
+
+        #[pyo3(text_signature = "($self, k)")]
+        pub(crate) fn visual_rank_fusion(&mut self, k: f32) {
+            self.0
+                .metric_builder
+                .set_visual_fusion(VisualFusion::reciprocal_rank(k));
+        }
+
         #[pyo3(text_signature = "($self, metric)")]
         pub(crate) fn visual_metric(&mut self, metric: PyVisualSortMetricType) {
             self.0.metric_builder.set_visual_kind(metric.0);
@@ -307,6 +785,76 @@ pub mod python {
                 .set_visual_minimal_own_area_percentage_collect(area);
         }
 
+        #[pyo3(text_signature = "($self, delta)")]
+        pub(crate) fn positional_max_depth_delta(&mut self, delta: f32) {
+            self.0
+                .metric_builder
+                .set_positional_max_depth_delta(Some(delta));
+        }
+
+        #[pyo3(text_signature = "($self, distance)")]
+        pub(crate) fn positional_max_distance(&mut self, distance: f32) {
+            assert!(distance >= 0.0, "Distance must be a non-negative number");
+            self.0
+                .metric_builder
+                .set_positional_max_distance(Some(distance));
+        }
+
+        #[pyo3(text_signature = "($self, distance)")]
+        pub(crate) fn visual_max_distance(&mut self, distance: f32) {
+            assert!(distance >= 0.0, "Distance must be a non-negative number");
+            self.0
+                .metric_builder
+                .set_visual_max_distance(Some(distance));
+        }
+
+        #[pyo3(text_signature = "($self, adaptive)")]
+        pub(crate) fn visual_adaptive_threshold(&mut self, adaptive: PyAdaptiveVisualThreshold) {
+            self.0
+                .metric_builder
+                .set_visual_adaptive_threshold(Some(adaptive.0));
+        }
+
+        #[pyo3(text_signature = "($self, max_features, epochs)")]
+        pub(crate) fn visual_collect_rate_limit(&mut self, max_features: i64, epochs: i64) {
+            let max_features: usize = max_features
+                .try_into()
+                .expect("Parameter must be a positive number");
+            let epochs: usize = epochs
+                .try_into()
+                .expect("Parameter must be a positive number");
+            assert!(
+                max_features > 0 && epochs > 0,
+                "The number of features and epochs must be greater than 0"
+            );
+            self.0
+                .metric_builder
+                .set_visual_collect_rate_limit(Some((max_features, epochs)));
+        }
+
+        #[pyo3(text_signature = "($self, probability)")]
+        pub(crate) fn visual_collect_sampling(&mut self, probability: f32) {
+            assert!(
+                probability > 0.0 && probability <= 1.0,
+                "Probability must lay between (0.0 and 1.0]"
+            );
+            self.0
+                .metric_builder
+                .set_visual_collect_sampling(probability);
+        }
+
+        #[pyo3(text_signature = "($self, seed)")]
+        pub(crate) fn visual_collect_sampling_seed(&mut self, seed: u64) {
+            self.0.metric_builder.set_visual_collect_sampling_seed(seed);
+        }
+
+        #[pyo3(text_signature = "($self, whitening)")]
+        pub(crate) fn visual_whitening(&mut self, whitening: PyFeatureWhitening) {
+            self.0
+                .metric_builder
+                .set_visual_whitening(Some(whitening.0));
+        }
+
         #[pyo3(text_signature = "($self, weight)")]
         pub(crate) fn kalman_position_weight(&mut self, weight: f32) {
             self.0.kalman_position_weight = weight;
@@ -317,6 +865,116 @@ pub mod python {
             self.0.kalman_velocity_weight = weight;
         }
 
+        #[pyo3(text_signature = "($self, filter)")]
+        pub(crate) fn output_filter(&mut self, filter: PyTrackOutputFilter) {
+            self.0.output_filter = filter.0;
+        }
+
+        #[pyo3(text_signature = "($self, zones)")]
+        pub(crate) fn scene_zones(&mut self, zones: PySceneZones) {
+            self.0.scene_zones = zones.0;
+        }
+
+        #[pyo3(text_signature = "($self, policy)")]
+        pub(crate) fn duplicate_object_id_policy(&mut self, policy: PyDuplicateObjectIdPolicy) {
+            self.0.duplicate_object_id_policy = policy.0;
+        }
+
+        #[pyo3(text_signature = "($self, policy)")]
+        pub(crate) fn nan_distance_policy(&mut self, policy: PyNanDistancePolicy) {
+            self.0.nan_distance_policy = policy.0;
+        }
+
+        #[pyo3(text_signature = "($self, clustering)")]
+        pub(crate) fn candidate_clustering(&mut self, clustering: PyCandidateClustering) {
+            self.0.candidate_clustering = Some(clustering.0);
+        }
+
+        #[pyo3(text_signature = "($self, n)")]
+        pub(crate) fn class_freeze(&mut self, n: usize) {
+            self.0.class_freeze = Some(n);
+        }
+
+        #[pyo3(text_signature = "($self)")]
+        pub(crate) fn skipped_epochs_prediction(&mut self) {
+            self.0.skipped_epochs_prediction = true;
+        }
+
+        #[pyo3(text_signature = "($self)")]
+        pub(crate) fn observation_timestamps(&mut self) {
+            self.0.observation_timestamps = true;
+        }
+
+        #[pyo3(text_signature = "($self, epochs)")]
+        pub(crate) fn max_track_lifetime(&mut self, epochs: usize) {
+            self.0.max_track_lifetime = Some(epochs);
+        }
+
+        #[pyo3(text_signature = "($self, triggers)")]
+        pub(crate) fn waste_triggers(&mut self, triggers: PyWasteTriggers) {
+            self.0.waste_triggers = triggers.0;
+        }
+
+        #[pyo3(text_signature = "($self, clamping)")]
+        pub(crate) fn frame_clamping(&mut self, clamping: PyFrameClamping) {
+            self.0.frame_clamping = clamping.0;
+        }
+
+        #[pyo3(text_signature = "($self, fallback)")]
+        pub(crate) fn history_fallback(&mut self, fallback: PyHistoryFallback) {
+            self.0.history_fallback = fallback.0;
+        }
+
+        #[pyo3(text_signature = "($self, model)")]
+        pub(crate) fn motion_model(&mut self, model: PyMotionModel) {
+            self.0.motion_model = model.0;
+        }
+
+        #[pyo3(text_signature = "($self, gating)")]
+        pub(crate) fn mahalanobis_gating(&mut self, gating: PyMahalanobisGating) {
+            self.0.mahalanobis_gating = Some(gating.0);
+        }
+
+        #[pyo3(text_signature = "($self, scene_id, calibration)")]
+        pub(crate) fn scene_calibration(&mut self, scene_id: u64, calibration: PySceneCalibration) {
+            self.0.scene_calibrations.insert(scene_id, calibration.0);
+        }
+
+        #[pyo3(text_signature = "($self, pipeline)")]
+        pub(crate) fn observation_pipeline(&mut self, pipeline: PyObservationPipeline) {
+            self.0.observation_pipeline = pipeline.0;
+        }
+
+        #[pyo3(text_signature = "($self, opts)")]
+        pub(crate) fn reactivation(&mut self, opts: PyReactivationOptions) {
+            self.0.reactivation = Some(opts.0);
+        }
+
+        #[pyo3(text_signature = "($self, opts)")]
+        pub(crate) fn cold_store(&mut self, opts: PyColdStoreOptions) {
+            self.0.cold_store = Some(opts.0);
+        }
+
+        #[pyo3(text_signature = "($self, budget)")]
+        pub(crate) fn latency_budget(&mut self, budget: PyLatencyBudget) {
+            self.0.latency_budget = Some(budget.0);
+        }
+
+        #[pyo3(text_signature = "($self, opts)")]
+        pub(crate) fn noise_model(&mut self, opts: PyNoiseModelOptions) {
+            self.0.noise_model = Some(opts.0);
+        }
+
+        #[pyo3(text_signature = "($self, opts)")]
+        pub(crate) fn density_estimation(&mut self, opts: PyDensityOptions) {
+            self.0.density = Some(opts.0);
+        }
+
+        #[pyo3(text_signature = "($self, opts)")]
+        pub(crate) fn quality_estimation(&mut self, opts: PyQualityOptions) {
+            self.0.quality = Some(opts.0);
+        }
+
         #[classattr]
         const __hash__: Option<Py<PyAny>> = None;
 
@@ -332,14 +990,24 @@ pub mod python {
 
 #[cfg(test)]
 mod tests {
+    use crate::trackers::history_fallback::python::PyHistoryFallback;
+    use crate::trackers::history_fallback::HistoryFallback;
+    use crate::trackers::latency_budget::python::PyLatencyBudget;
+    use crate::trackers::latency_budget::LatencyBudget;
     use crate::trackers::sort::python::PyPositionalMetricType;
     use crate::trackers::sort::PositionalMetricType;
     use crate::trackers::spatio_temporal_constraints::python::PySpatioTemporalConstraints;
     use crate::trackers::spatio_temporal_constraints::SpatioTemporalConstraints;
+    use crate::trackers::visual_sort::metric::class_options::python::PyVisualClassOptions;
+    use crate::trackers::visual_sort::metric::class_options::VisualClassOptions;
     use crate::trackers::visual_sort::metric::python::PyVisualSortMetricType;
     use crate::trackers::visual_sort::metric::VisualSortMetricType;
     use crate::trackers::visual_sort::options::python::PyVisualSortOptions;
     use crate::trackers::visual_sort::options::VisualSortOptions;
+    use crate::trackers::visual_sort::voting::VisualFusion;
+    use crate::trackers::visual_sort::whitening::python::PyFeatureWhitening;
+    use crate::trackers::visual_sort::whitening::FeatureWhitening;
+    use std::time::Duration;
 
     #[test]
     fn visual_sort_options_builder() {
@@ -354,9 +1022,26 @@ mod tests {
             .visual_minimal_quality_collect(0.5)
             .visual_max_observations(25)
             .visual_min_votes(5)
+            .visual_min_votes_fraction(0.5)
+            .visual_fusion(VisualFusion::reciprocal_rank(60.0))
+            .visual_class_options(
+                2,
+                VisualClassOptions::default()
+                    .visual_metric(VisualSortMetricType::cosine(0.5))
+                    .visual_minimal_area(10.0)
+            )
             .positional_min_confidence(0.13)
             .visual_minimal_own_area_percentage_use(0.1)
             .visual_minimal_own_area_percentage_collect(0.2)
+            .positional_max_depth_delta(1.5)
+            .visual_collect_rate_limit(2, 10)
+            .visual_collect_sampling(0.5)
+            .visual_whitening(FeatureWhitening::new(0.01, 100, true))
+            .history_fallback(HistoryFallback::EmptyBox)
+            .latency_budget(
+                LatencyBudget::new(Duration::from_millis(40), Duration::from_millis(20))
+                    .with_max_queue_depth(4)
+            )
             .spatio_temporal_constraints(
                 SpatioTemporalConstraints::default().constraints(&[(5, 7.0)])
             )
@@ -376,9 +1061,21 @@ mod tests {
         opts_builder.visual_minimal_own_area_percentage_use(0.1);
         opts_builder.visual_minimal_own_area_percentage_collect(0.2);
         opts_builder.visual_min_votes(5);
-        let mut constraints = PySpatioTemporalConstraints::new();
-        constraints.add_constraints(vec![(5, 7.0)]);
-        opts_builder.spatio_temporal_constraints(constraints);
+        opts_builder.visual_min_votes_fraction(0.5);
+        opts_builder.visual_rank_fusion(60.0);
+        let mut class_opts = PyVisualClassOptions::new();
+        class_opts.visual_metric(PyVisualSortMetricType::cosine(0.5));
+        class_opts.visual_minimal_area(10.0);
+        opts_builder.visual_class_options(2, class_opts);
+        opts_builder.positional_max_depth_delta(1.5);
+        opts_builder.visual_collect_rate_limit(2, 10);
+        opts_builder.visual_collect_sampling(0.5);
+        opts_builder.visual_whitening(PyFeatureWhitening(FeatureWhitening::new(0.01, 100, true)));
+        opts_builder.history_fallback(PyHistoryFallback::empty_box());
+        opts_builder.latency_budget(PyLatencyBudget::new(0.04, 0.02, Some(4), 1));
+        opts_builder.spatio_temporal_constraints(PySpatioTemporalConstraints(
+            SpatioTemporalConstraints::default().constraints(&[(5, 7.0)]),
+        ));
         let (opts_py, metric_py) = dbg!(opts_builder.0.build());
 
         assert_eq!(format!("{:?}", opts), format!("{:?}", opts_py));
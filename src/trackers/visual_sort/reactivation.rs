@@ -0,0 +1,184 @@
+use crate::store::TrackStore;
+use crate::track::notify::ChangeNotifier;
+use crate::track::Track;
+use crate::trackers::sort::VotingType;
+use crate::trackers::visual_sort::metric::VisualMetric;
+use crate::trackers::visual_sort::observation_attributes::VisualObservationAttributes;
+use crate::trackers::visual_sort::track_attributes::{VisualAttributes, VisualAttributesUpdate};
+use crate::trackers::visual_sort::voting::VisualVoting;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Configuration of the appearance-only reactivation of the idle tracks.
+///
+/// After a long occlusion the Kalman prediction of the track drifts away from the object, so the
+/// positional gates and the spatio-temporal constraints reject the detection and the object gets a
+/// new track. When the reactivation is enabled, the detections that are not associated by the
+/// regular voting are compared by their features only with the tracks of the scene that are idle
+/// for `min_idle_epochs..=max_idle_epochs` epochs, the track collecting `min_votes` visual votes is
+/// continued with the detection.
+///
+/// The tracks idle for more than the `max_idle_epochs` of the tracker are wasted, so the window is
+/// effectively limited by the tracker setting.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReactivationOptions {
+    min_idle_epochs: usize,
+    max_idle_epochs: usize,
+    min_votes: usize,
+}
+
+impl ReactivationOptions {
+    pub fn new(min_idle_epochs: usize, max_idle_epochs: usize, min_votes: usize) -> Self {
+        assert!(
+            min_idle_epochs > 0 && min_idle_epochs <= max_idle_epochs,
+            "Idle window must satisfy 0 < min_idle_epochs <= max_idle_epochs"
+        );
+        assert!(
+            min_votes > 0,
+            "The number of votes must be a positive number"
+        );
+        Self {
+            min_idle_epochs,
+            max_idle_epochs,
+            min_votes,
+        }
+    }
+
+    pub fn min_idle_epochs(&self) -> usize {
+        self.min_idle_epochs
+    }
+
+    pub fn max_idle_epochs(&self) -> usize {
+        self.max_idle_epochs
+    }
+
+    pub fn min_votes(&self) -> usize {
+        self.min_votes
+    }
+
+    /// Checks if the track idle for `idle_epochs` can be reactivated
+    ///
+    pub fn allows(&self, idle_epochs: usize) -> bool {
+        (self.min_idle_epochs..=self.max_idle_epochs).contains(&idle_epochs)
+    }
+}
+
+/// Runs the reactivation pass for the candidates left without the destination by the voting, the
+/// Kalman state of the reactivated tracks is extrapolated over the idle epochs
+///
+/// # Parameters
+/// * `store` - the store of the active tracks
+/// * `tracks` - the candidate tracks of the epoch
+/// * `winners` - the results of the regular voting, extended with the reactivated tracks
+/// * `voting` - the voting of the tracker
+/// * `opts` - the reactivation configuration
+///
+pub(crate) fn reactivate<N: ChangeNotifier>(
+    store: &mut TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes, N>,
+    tracks: &[Track<VisualAttributes, VisualMetric, VisualObservationAttributes, N>],
+    winners: &mut HashMap<u64, Vec<(u64, VotingType)>>,
+    voting: &VisualVoting,
+    opts: &ReactivationOptions,
+) {
+    let mut occupied = HashSet::new();
+    let unmatched = tracks
+        .iter()
+        .filter(|t| match winners.get(&t.get_track_id()) {
+            Some(dest) if dest[0].0 != t.get_track_id() => {
+                occupied.insert(dest[0].0);
+                false
+            }
+            _ => true,
+        })
+        .map(|t| {
+            let mut t = t.clone();
+            t.add_observation(
+                0,
+                None,
+                None,
+                Some(VisualAttributesUpdate::Reactivation(*opts)),
+            )
+            .unwrap();
+            t
+        })
+        .collect::<Vec<_>>();
+    if unmatched.is_empty() {
+        return;
+    }
+
+    let (dists, errs) = store.foreign_track_distances(unmatched, 0, false);
+    errs.log();
+    let reactivated = voting.reactivation_winners(dists, opts.min_votes(), &occupied);
+    for t in tracks {
+        if let Some(dest) = reactivated.get(&t.get_track_id()) {
+            let track_id = dest[0].0;
+            let epoch = t.get_attributes().last_updated_epoch;
+            if let Some(track) = store.get_store_mut(track_id as usize).get_mut(&track_id) {
+                track
+                    .add_observation(
+                        0,
+                        None,
+                        None,
+                        Some(VisualAttributesUpdate::WarmRestart { epoch }),
+                    )
+                    .unwrap();
+            }
+        }
+    }
+    winners.extend(reactivated);
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::ReactivationOptions;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "ReactivationOptions")]
+    #[derive(Debug, Clone, Copy)]
+    pub struct PyReactivationOptions(pub(crate) ReactivationOptions);
+
+    #[pymethods]
+    impl PyReactivationOptions {
+        #[new]
+        #[pyo3(signature = (min_idle_epochs, max_idle_epochs, min_votes = 1))]
+        pub(crate) fn new(
+            min_idle_epochs: usize,
+            max_idle_epochs: usize,
+            min_votes: usize,
+        ) -> Self {
+            Self(ReactivationOptions::new(
+                min_idle_epochs,
+                max_idle_epochs,
+                min_votes,
+            ))
+        }
+
+        #[getter]
+        fn min_idle_epochs(&self) -> usize {
+            self.0.min_idle_epochs()
+        }
+
+        #[getter]
+        fn max_idle_epochs(&self) -> usize {
+            self.0.max_idle_epochs()
+        }
+
+        #[getter]
+        fn min_votes(&self) -> usize {
+            self.0.min_votes()
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
@@ -1,38 +1,80 @@
 use crate::prelude::{NoopNotifier, ObservationBuilder, SortTrack, TrackStoreBuilder};
 use crate::store::TrackStore;
+use crate::track::notify::ChangeNotifier;
+use crate::track::state::TrackState;
 use crate::track::utils::FromVec;
 use crate::track::{Feature, Track};
+use crate::trackers::candidate_assignment::CandidateAssignment;
+use crate::trackers::distance_export::{DistanceSink, EpochDistances};
 use crate::trackers::epoch_db::EpochDb;
+use crate::trackers::observation_intake::ObservationIntake;
+use crate::trackers::predict_iter::PredictIter;
+use crate::trackers::predict_overrides::PredictOverrides;
 use crate::trackers::sort::VotingType::Positional;
-use crate::trackers::sort::{
-    AutoWaste, PositionalMetricType, SortAttributesOptions, DEFAULT_AUTO_WASTE_PERIODICITY,
-    MAHALANOBIS_NEW_TRACK_THRESHOLD,
-};
+use crate::trackers::sort::{AutoWaste, SortAttributesOptions, DEFAULT_AUTO_WASTE_PERIODICITY};
 use crate::trackers::tracker_api::TrackerAPI;
+use crate::trackers::visual_sort::cold_store::{promote, ColdStore, ColdTrack};
 use crate::trackers::visual_sort::metric::{VisualMetric, VisualMetricOptions};
 use crate::trackers::visual_sort::observation_attributes::VisualObservationAttributes;
 use crate::trackers::visual_sort::options::VisualSortOptions;
+use crate::trackers::visual_sort::reactivation::reactivate;
 use crate::trackers::visual_sort::track_attributes::{
     VisualAttributes, VisualAttributesUpdate, VisualSortLookup,
 };
-use crate::trackers::visual_sort::voting::VisualVoting;
+use crate::trackers::visual_sort::vector_store::VectorStoreBridge;
+use crate::trackers::visual_sort::voting::{effective_min_votes, track_min_votes, VisualVoting};
+use crate::trackers::visual_sort::whitening::FeatureStatistics;
 use crate::trackers::visual_sort::VisualSortObservation;
+use crate::utils::bbox::BoundingBox;
 use crate::utils::clipping::bbox_own_areas::{
     exclusively_owned_areas, exclusively_owned_areas_normalized_shares,
 };
-use crate::voting::Voting;
+use crate::voting::{Voting, VotingGate};
+use anyhow::Result;
+use log::warn;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Instant;
 
 // /// Easy to use Visual SORT tracker implementation
 // ///
-pub struct VisualSort {
-    store: RwLock<TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes>>,
-    wasted_store: RwLock<TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes>>,
+pub struct VisualSort<N: ChangeNotifier = NoopNotifier> {
+    store: RwLock<TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes, N>>,
+    wasted_store:
+        RwLock<TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes, N>>,
     metric_opts: Arc<VisualMetricOptions>,
     track_opts: Arc<SortAttributesOptions>,
+    options: VisualSortOptions,
+    intake: ObservationIntake,
     auto_waste: AutoWaste,
     track_id: u64,
+    gate: Option<VotingGate<VisualObservationAttributes>>,
+    vector_store: Option<Arc<VectorStoreBridge>>,
+    cold_store: ColdStore,
+    distance_sink: Option<Arc<dyn DistanceSink>>,
+    notifier: N,
+}
+
+/// Serializable state of [VisualSort](VisualSort) that is enough to continue the tracking after
+/// the restore.
+///
+/// The state keeps the options the tracker was created with, the epochs of the scenes and the
+/// active and the wasted tracks. The gating callback and the collected statistics (latency, noise
+/// and density estimates, duplicate counters) are not kept.
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisualSortState {
+    pub shards: usize,
+    pub options: VisualSortOptions,
+    pub auto_waste_periodicity: usize,
+    pub track_id: u64,
+    pub epochs: HashMap<u64, usize>,
+    pub tracks: Vec<TrackState<VisualAttributes, VisualObservationAttributes>>,
+    pub wasted: Vec<TrackState<VisualAttributes, VisualObservationAttributes>>,
+    #[serde(default)]
+    pub cold: Vec<ColdTrack>,
 }
 
 impl VisualSort {
@@ -43,6 +85,21 @@ impl VisualSort {
     /// * `opts` - tracker options
     ///
     pub fn new(shards: usize, opts: &VisualSortOptions) -> Self {
+        Self::new_with_notifier(NoopNotifier, shards, opts)
+    }
+
+    /// Creates the tracker from the previously taken state
+    ///
+    pub fn from_state(state: VisualSortState) -> Result<Self> {
+        Self::from_state_with_notifier(state, NoopNotifier)
+    }
+}
+
+impl<N: ChangeNotifier> VisualSort<N> {
+    /// Creates new tracker which sends the ids of the changed tracks of both the active and the
+    /// wasted stores to the notifier, the parameters are the same as for [new](VisualSort::new)
+    ///
+    pub fn new_with_notifier(notifier: N, shards: usize, opts: &VisualSortOptions) -> Self {
         let (track_opts, metric) = opts.clone().build();
         let track_opts = Arc::new(track_opts);
         let metric_opts = metric.opts.clone();
@@ -50,7 +107,8 @@ impl VisualSort {
             TrackStoreBuilder::new(shards)
                 .default_attributes(VisualAttributes::new(track_opts.clone()))
                 .metric(metric.clone())
-                .notifier(NoopNotifier)
+                .notifier(notifier.clone())
+                .nan_distance_policy(opts.get_nan_distance_policy())
                 .build(),
         );
 
@@ -58,7 +116,7 @@ impl VisualSort {
             TrackStoreBuilder::new(shards)
                 .default_attributes(VisualAttributes::new(track_opts.clone()))
                 .metric(metric)
-                .notifier(NoopNotifier)
+                .notifier(notifier.clone())
                 .build(),
         );
 
@@ -66,15 +124,76 @@ impl VisualSort {
             store,
             wasted_store,
             track_opts,
+            options: opts.clone(),
+            intake: opts.observation_intake(),
             track_id: 0,
             metric_opts,
-            auto_waste: AutoWaste {
-                periodicity: DEFAULT_AUTO_WASTE_PERIODICITY,
-                counter: DEFAULT_AUTO_WASTE_PERIODICITY,
-            },
+            auto_waste: AutoWaste::new(DEFAULT_AUTO_WASTE_PERIODICITY),
+            gate: None,
+            vector_store: None,
+            cold_store: ColdStore::default(),
+            distance_sink: None,
+            notifier,
+        }
+    }
+
+    /// Sets the gating callback invoked for every (candidate, track) distance before the voting;
+    /// the pairs rejected by the callback are never associated
+    ///
+    /// # Parameters
+    /// * `gate` - the callback, `None` removes the previously set one
+    ///
+    pub fn set_gate(&mut self, gate: Option<VotingGate<VisualObservationAttributes>>) {
+        self.gate = gate;
+    }
+
+    /// Sets the sink receiving the distances of the matched and the unmatched observations after
+    /// every association, `None` removes the previously set one
+    ///
+    pub fn set_distance_sink(&mut self, sink: Option<Arc<dyn DistanceSink>>) {
+        self.distance_sink = sink;
+    }
+
+    /// Sets the bridge that exports the aggregated embeddings of the tracks to the external vector
+    /// database. The tracks changed by the prediction are exported when the prediction completes
+    /// (for [predict_iter](Self::predict_iter) - when the next prediction starts), the wasted
+    /// tracks are exported for the last time when they leave the main store. The prediction only
+    /// queues the templates, the sink is called by the background thread of the bridge.
+    ///
+    /// # Parameters
+    /// * `bridge` - the bridge, `None` stops the export
+    ///
+    pub fn set_vector_store_bridge(&mut self, bridge: Option<Arc<VectorStoreBridge>>) {
+        self.vector_store = bridge;
+    }
+
+    fn sync_vector_store(&self) {
+        if let Some(bridge) = &self.vector_store {
+            if let Err(e) = bridge.sync(&self.store.read().unwrap()) {
+                warn!("Unable to export the track templates. Error is: {:?}", e);
+            }
         }
     }
 
+    /// Sets the overrides of the configuration applied to the predictions of the scene until
+    /// they are removed
+    ///
+    /// # Parameters
+    /// * `scene_id` - the scene the overrides are applied to
+    /// * `overrides` - the overrides, `None` restores the configured behavior
+    ///
+    pub fn set_predict_overrides(&mut self, scene_id: u64, overrides: Option<PredictOverrides>) {
+        self.track_opts.set_predict_overrides(scene_id, overrides);
+    }
+
+    /// Sets the true dimension of the observed features, the features are kept padded to the
+    /// multiple of 8 values and the padding is stripped when the tracks are
+    /// [exported](Self::export_arrow)
+    ///
+    pub fn set_feature_dimension(&mut self, dim: usize) {
+        self.store.write().unwrap().set_feature_dimension(0, dim);
+    }
+
     /// Receive tracking information for observed bboxes of `scene_id == 0`
     ///
     /// # Parameters
@@ -85,8 +204,164 @@ impl VisualSort {
         self.predict_with_scene(0, observations)
     }
 
+    /// Takes the serializable state of the tracker
+    ///
+    pub fn state(&self) -> VisualSortState {
+        let store = self.store.read().unwrap();
+        VisualSortState {
+            shards: store.shard_stats().len(),
+            options: self.options.clone(),
+            auto_waste_periodicity: self.auto_waste.periodicity,
+            track_id: self.track_id,
+            epochs: self
+                .track_opts
+                .epoch_db()
+                .as_ref()
+                .unwrap()
+                .read()
+                .unwrap()
+                .clone(),
+            tracks: store.dump(),
+            wasted: self.wasted_store.read().unwrap().dump(),
+            cold: self.cold_store.tracks(),
+        }
+    }
+
+    /// Exports the active tracks as the Arrow IPC streams of the track summaries and the
+    /// observations, see [export_arrow](TrackStore::export_arrow) for the columns
+    ///
+    pub fn export_arrow<W1: std::io::Write, W2: std::io::Write>(
+        &self,
+        summaries: W1,
+        observations: W2,
+    ) -> Result<()> {
+        self.store
+            .read()
+            .unwrap()
+            .export_arrow(summaries, observations)
+    }
+
+    /// Creates the tracker from the previously taken state, the ids of the changed tracks are sent
+    /// to the notifier
+    ///
+    pub fn from_state_with_notifier(state: VisualSortState, notifier: N) -> Result<Self> {
+        let mut tracker = Self::new_with_notifier(notifier, state.shards, &state.options);
+        tracker.set_auto_waste(state.auto_waste_periodicity);
+        tracker.track_id = state.track_id;
+        tracker
+            .store
+            .read()
+            .unwrap()
+            .id_registry()
+            .advance(state.track_id + 1);
+        *tracker
+            .track_opts
+            .epoch_db()
+            .as_ref()
+            .unwrap()
+            .write()
+            .unwrap() = state.epochs;
+
+        for (store, tracks) in [
+            (&tracker.store, state.tracks),
+            (&tracker.wasted_store, state.wasted),
+        ] {
+            let mut store = store.write().unwrap();
+            for mut track in tracks {
+                track.attributes.set_options(tracker.track_opts.clone());
+                store.restore(track)?;
+            }
+        }
+        for track in state.cold {
+            tracker.cold_store.insert(track);
+        }
+        Ok(tracker)
+    }
+
+    /// Stops the executor threads of the tracker, the tracker can't be used afterwards
+    ///
+    pub fn shutdown(&mut self) {
+        self.store.write().unwrap().shutdown();
+        self.wasted_store.write().unwrap().shutdown();
+    }
+
+    /// The number of duplicate custom_object_id found since the tracker creation
+    ///
+    pub fn duplicate_object_ids(&self) -> usize {
+        self.intake.duplicate_object_ids()
+    }
+
+    /// The number of the tracks kept in the cold store
+    ///
+    pub fn cold_store_size(&self) -> usize {
+        self.cold_store.len()
+    }
+
+    /// Moves the tracks of the scene idle for the long time to the cold store
+    ///
+    fn demote_idle_tracks(&mut self, scene_id: u64) {
+        let opts = match self.options.get_cold_store() {
+            Some(opts) => opts,
+            None => return,
+        };
+        let mut store = self.store.write().unwrap();
+        let ids = store
+            .lookup(VisualSortLookup::LongIdleLookup(
+                scene_id,
+                opts.demote_after(),
+            ))
+            .into_iter()
+            .map(|(track_id, _)| track_id)
+            .collect::<Vec<_>>();
+        for track in store.fetch_tracks(&ids) {
+            self.cold_store.insert(ColdTrack::new(
+                TrackState::from(&track),
+                opts.kept_features(),
+            ));
+        }
+    }
+
+    /// Moves the cold tracks of the scene idle for longer than the retention to the wasted store
+    ///
+    fn expire_cold_tracks(&mut self, scene_id: u64) {
+        let opts = match self.options.get_cold_store() {
+            Some(opts) => opts,
+            None => return,
+        };
+        let track_opts = self.track_opts.clone();
+        let expired = self.cold_store.take_matching(|t| {
+            t.scene_id() == scene_id
+                && track_opts
+                    .current_epoch_with_scene(scene_id)
+                    .map(|epoch| opts.expired(epoch.saturating_sub(t.last_updated_epoch())))
+                    .unwrap_or(false)
+        });
+        if expired.is_empty() {
+            return;
+        }
+        let metric = self.wasted_store.read().unwrap().metric().clone();
+        let mut tracks = expired
+            .into_iter()
+            .map(|t| {
+                let mut state = t.into_state();
+                state.attributes.set_options(track_opts.clone());
+                state.into_track(metric.clone(), self.notifier.clone())
+            })
+            .collect::<Vec<_>>();
+        for t in &mut tracks {
+            t.on_wasted();
+        }
+        self.on_tracks_wasted(&tracks);
+        let mut wasted_store = self.wasted_store.write().unwrap();
+        for t in &tracks {
+            wasted_store
+                .restore(TrackState::from(t))
+                .expect("Cannot be a error, copying track to wasted store");
+        }
+    }
+
     fn gen_track_id(&mut self) -> u64 {
-        self.track_id += 1;
+        self.track_id = self.store.read().unwrap().reserve_track_ids(1).start;
         self.track_id
     }
 
@@ -101,13 +376,170 @@ impl VisualSort {
         scene_id: u64,
         observations: &[VisualSortObservation],
     ) -> Vec<SortTrack> {
-        if self.auto_waste.counter == 0 {
-            self.auto_waste();
-            self.auto_waste.counter = self.auto_waste.periodicity;
-        } else {
-            self.auto_waste.counter -= 1;
+        self.predict_with_assignments(scene_id, observations).0
+    }
+
+    /// Receive tracking information for observed bboxes of `scene_id` with the tracks the
+    /// observations ended up in
+    ///
+    /// # Parameters
+    /// * `scene_id` - custom identifier for the group of observed objects;
+    /// * `observations` - object observations with (feature, feature_quality and bounding box).
+    ///
+    /// # Returns
+    /// the tracks and the assignments of the observations, the assignments are reported for the
+    /// tracks removed by the output filter as well
+    ///
+    pub fn predict_with_assignments(
+        &mut self,
+        scene_id: u64,
+        observations: &[VisualSortObservation],
+    ) -> (Vec<SortTrack>, Vec<CandidateAssignment>) {
+        self.predict_overridden(scene_id, observations, None)
+    }
+
+    fn predict_overridden(
+        &mut self,
+        scene_id: u64,
+        observations: &[VisualSortObservation],
+        overrides: Option<PredictOverrides>,
+    ) -> (Vec<SortTrack>, Vec<CandidateAssignment>) {
+        let started = Instant::now();
+        let mut res = Vec::default();
+        let (candidates, assignments) = self.assign(scene_id, observations, overrides);
+        for (t, dest) in candidates {
+            let track_id = match dest {
+                Some(dest) => {
+                    self.store
+                        .write()
+                        .unwrap()
+                        .merge_external_owned(dest, t, Some(&[0]), false)
+                        .unwrap();
+                    dest
+                }
+                None => self.store.write().unwrap().add_track(t).unwrap(),
+            };
+
+            let lock = self.store.read().unwrap();
+            let store = lock.get_store(track_id as usize);
+            let track = store.get(&track_id).unwrap();
+
+            res.push(SortTrack::from(track))
+        }
+
+        self.sync_vector_store();
+        self.track_opts.record_quality(scene_id, &res);
+        let res = self.track_opts.filter_output(res);
+        self.track_opts
+            .record_predict_latency(scene_id, started.elapsed(), 0);
+        (res, assignments)
+    }
+
+    /// Receive tracking information for observed bboxes of `scene_id` with the configuration
+    /// overridden for this prediction only
+    ///
+    /// # Parameters
+    /// * `scene_id` - custom identifier for the group of observed objects;
+    /// * `observations` - object observations with (feature, feature_quality and bounding box);
+    /// * `overrides` - the overrides layered over the configuration of the tracker.
+    ///
+    pub fn predict_with_overrides(
+        &mut self,
+        scene_id: u64,
+        observations: &[VisualSortObservation],
+        overrides: PredictOverrides,
+    ) -> Vec<SortTrack> {
+        self.predict_overridden(scene_id, observations, Some(overrides))
+            .0
+    }
+
+    /// Receive tracking information for observed bboxes of `scene_id == 0` as they are ready
+    ///
+    /// # Parameters
+    /// * `observations` - object observations with (feature, feature_quality and bounding box).
+    ///
+    pub fn predict_iter(
+        &mut self,
+        observations: &[VisualSortObservation],
+    ) -> PredictIter<'_, VisualAttributes, VisualMetric, VisualObservationAttributes, N> {
+        self.predict_iter_with_scene(0, observations)
+    }
+
+    /// Receive tracking information for observed bboxes of `scene_id` as they are ready. Unlike
+    /// [predict_with_scene](Self::predict_with_scene) the tracks are yielded in the order their
+    /// observations are merged.
+    ///
+    /// # Parameters
+    /// * `scene_id` - custom identifier for the group of observed objects;
+    /// * `observations` - object observations with (feature, feature_quality and bounding box).
+    ///
+    pub fn predict_iter_with_scene(
+        &mut self,
+        scene_id: u64,
+        observations: &[VisualSortObservation],
+    ) -> PredictIter<'_, VisualAttributes, VisualMetric, VisualObservationAttributes, N> {
+        let started = Instant::now();
+        let (candidates, _) = self.assign(scene_id, observations, None);
+        PredictIter::new(
+            &self.store,
+            &self.track_opts,
+            scene_id,
+            started,
+            |t| SortTrack::from(t),
+            candidates,
+        )
+    }
+
+    /// Builds the candidate tracks for the observations and votes for their destinations
+    ///
+    /// # Returns
+    /// the candidate tracks with the ids of the tracks they must be merged into, the candidates
+    /// without destinations get new ids; the assignments of the observations to the tracks
+    ///
+    #[allow(clippy::type_complexity)]
+    fn assign(
+        &mut self,
+        scene_id: u64,
+        observations: &[VisualSortObservation],
+        overrides: Option<PredictOverrides>,
+    ) -> (
+        Vec<(
+            Track<VisualAttributes, VisualMetric, VisualObservationAttributes, N>,
+            Option<u64>,
+        )>,
+        Vec<CandidateAssignment>,
+    ) {
+        self.sync_vector_store();
+        if self.auto_waste.tick(scene_id) {
+            self.expire_cold_tracks(scene_id);
+            self.auto_waste_scene(scene_id);
         }
 
+        let epoch = self.track_opts.next_epoch(scene_id).unwrap();
+        self.notify_epoch(scene_id, epoch);
+        self.waste_expired_scene(scene_id);
+        if !observations.is_empty() {
+            self.track_opts.mark_scene_active(scene_id);
+        }
+        self.demote_idle_tracks(scene_id);
+        let (indices, observations): (Vec<_>, Vec<_>) = self
+            .intake
+            .prepare(
+                scene_id,
+                epoch,
+                observations,
+                |o| &o.bounding_box,
+                |o, bounding_box| VisualSortObservation {
+                    bounding_box,
+                    ..o.clone()
+                },
+                |o| (o.custom_object_id, o.bounding_box.confidence),
+            )
+            .into_iter()
+            .unzip();
+        self.track_opts
+            .record_density(scene_id, observations.iter().map(|o| &o.bounding_box));
+
         let mut percentages = Vec::default();
         let use_own_area_percentage = self.metric_opts.visual_minimal_own_area_percentage_collect
             + self.metric_opts.visual_minimal_own_area_percentage_use
@@ -127,11 +559,18 @@ impl VisualSort {
         }
 
         let mut rng = rand::thread_rng();
-        let epoch = self.track_opts.next_epoch(scene_id).unwrap();
 
-        let mut tracks = observations
+        // the ignored observations are accounted by the own areas, but get no candidates
+        let indices = indices
+            .into_iter()
+            .zip(&observations)
+            .filter(|(_, o)| !o.ignored)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let tracks = observations
             .iter()
             .enumerate()
+            .filter(|(_, o)| !o.ignored)
             .map(|(i, o)| {
                 self.store
                     .read()
@@ -150,18 +589,26 @@ impl VisualSort {
                                     o.feature_quality.unwrap_or(1.0),
                                     o.bounding_box.clone(),
                                 )
-                            },
+                            }
+                            .with_depth(o.depth),
                         );
 
                         if let Some(feature) = &o.feature {
-                            obs = obs.observation(Feature::from_vec(feature.to_vec()));
+                            obs = obs.observation(Feature::from_vec(
+                                self.metric_opts.ingest_feature(scene_id, feature),
+                            ));
                         }
 
-                        obs.track_attributes_update(VisualAttributesUpdate::new_init_with_scene(
-                            epoch,
-                            scene_id,
-                            o.custom_object_id,
-                        ))
+                        obs.track_attributes_update(
+                            VisualAttributesUpdate::new_init_with_scene(
+                                epoch,
+                                scene_id,
+                                o.custom_object_id,
+                            )
+                            .with_class_id(o.class_id)
+                            .with_metadata(o.metadata.clone())
+                            .with_overrides(overrides),
+                        )
                         .build()
                     })
                     .build()
@@ -175,58 +622,196 @@ impl VisualSort {
                 .unwrap()
                 .foreign_track_distances(tracks.clone(), 0, false);
 
-        assert!(errs.all().is_empty());
+        errs.log();
+        let dists = dists.into_iter().collect::<Vec<_>>();
+        let scene_overrides = self
+            .track_opts
+            .predict_overrides(scene_id)
+            .layered(overrides);
         let voting = VisualVoting::new(
-            match self.metric_opts.positional_kind {
-                PositionalMetricType::Mahalanobis => MAHALANOBIS_NEW_TRACK_THRESHOLD,
-                PositionalMetricType::IoU(t) => t,
-            },
+            self.track_opts
+                .positional_threshold_with(&scene_overrides, self.metric_opts.positional_kind),
             f32::MAX,
-            self.metric_opts.visual_min_votes,
+            scene_overrides.visual_min_votes(self.metric_opts.visual_min_votes),
+        )
+        .with_track_min_votes(track_min_votes(
+            &self.store.read().unwrap(),
+            &dists,
+            &self.metric_opts,
+            &self.track_opts,
+            overrides,
+        ))
+        .with_fusion(self.metric_opts.visual_fusion)
+        .with_gate(self.gate.clone());
+        let mut winners = voting.winners(dists.iter().cloned());
+        if let Some(reactivation) = self.options.get_reactivation() {
+            reactivate(
+                &mut self.store.write().unwrap(),
+                &tracks,
+                &mut winners,
+                &voting,
+                &reactivation,
+            );
+        }
+        if self.options.get_cold_store().is_some() {
+            promote(
+                &mut self.store.write().unwrap(),
+                &mut self.cold_store,
+                &tracks,
+                &mut winners,
+                &self.metric_opts.visual_kind,
+                &self.track_opts,
+            );
+        }
+
+        if let Some(sink) = &self.distance_sink {
+            sink.export(EpochDistances::new(
+                scene_id,
+                epoch,
+                indices.iter().zip(&tracks).map(|(index, t)| {
+                    let source = t.get_track_id();
+                    let dest = winners
+                        .get(&source)
+                        .map(|dest| dest[0].0)
+                        .filter(|dest| *dest != source);
+                    (*index, source, dest)
+                }),
+                &dists,
+            ));
+        }
+
+        let candidates = tracks
+            .into_iter()
+            .map(|mut t| {
+                let source = t.get_track_id();
+                match winners.get(&source) {
+                    Some(dest) if dest[0].0 != source => {
+                        let (dest, vt) = dest[0];
+                        t.add_observation(
+                            0,
+                            None,
+                            None,
+                            Some(VisualAttributesUpdate::new_voting_type(vt)),
+                        )
+                        .unwrap();
+                        (t, Some(dest))
+                    }
+                    _ => {
+                        t.set_track_id(self.gen_track_id());
+                        (t, None)
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(bridge) = &self.vector_store {
+            let mut notifier = bridge.notifier();
+            for (t, dest) in &candidates {
+                notifier.send(dest.unwrap_or(t.get_track_id()));
+            }
+        }
+        let assignments = CandidateAssignment::from_candidates(
+            &indices,
+            candidates.iter().map(|(t, dest)| (t.get_track_id(), *dest)),
         );
-        let winners = voting.winners(dists);
-        let mut res = Vec::default();
-        for t in &mut tracks {
-            let source = t.get_track_id();
-            let track_id: u64 = if let Some(dest) = winners.get(&source) {
-                let (dest, vt) = dest[0];
-                if dest == source {
-                    let mut t = t.clone();
-                    let track_id = self.gen_track_id();
-                    t.set_track_id(track_id);
-                    self.store.write().unwrap().add_track(t).unwrap();
-                    track_id
-                } else {
-                    t.add_observation(
-                        0,
-                        None,
-                        None,
-                        Some(VisualAttributesUpdate::new_voting_type(vt)),
+        (candidates, assignments)
+    }
+
+    /// The visual votes the active track must collect to be associated by the features, it is
+    /// lower than `visual_min_votes` when the votes are scaled with
+    /// [visual_min_votes_fraction](VisualSortOptions::visual_min_votes_fraction)
+    ///
+    pub fn effective_min_votes(&self, track_id: u64) -> Option<usize> {
+        let store = self.store.read().unwrap();
+        let shard = store.get_store(track_id as usize);
+        shard.get(&track_id).map(|t| {
+            effective_min_votes(
+                t.get_attributes(),
+                &self.metric_opts,
+                &self.track_opts,
+                None,
+            )
+        })
+    }
+
+    /// Searches the active tracks which features are the closest to the query feature, the
+    /// tracks are compared by the appearance only, with the visual metric of the tracker
+    ///
+    /// # Parameters
+    /// * `feature` - the query feature
+    /// * `scene_id` - the scene to search in, all the tracked scenes are searched if not set
+    /// * `k` - the maximal number of the tracks returned
+    ///
+    /// # Returns
+    /// `(track_id, distance)` pairs ordered by the distance, only the tracks within the visual
+    /// threshold which collected enough features are returned
+    ///
+    pub fn search(&self, feature: &[f32], scene_id: Option<u64>, k: usize) -> Vec<(u64, f32)> {
+        let scenes = match scene_id {
+            Some(scene_id) => vec![scene_id],
+            None => TrackerAPI::scenes(self),
+        };
+        if scenes.is_empty() || k == 0 {
+            return vec![];
+        }
+        let queries = scenes
+            .into_iter()
+            .map(|scene_id| {
+                self.store
+                    .read()
+                    .unwrap()
+                    .new_track_random_id()
+                    .observation(
+                        ObservationBuilder::new(0)
+                            .observation_attributes(VisualObservationAttributes::new(
+                                1.0,
+                                BoundingBox::new(0.0, 0.0, 1.0, 1.0).as_xyaah(),
+                            ))
+                            .observation(Feature::from_vec(
+                                self.metric_opts.whiten_feature(scene_id, feature),
+                            ))
+                            .track_attributes_update(VisualAttributesUpdate::new_init_with_scene(
+                                0, scene_id, None,
+                            ))
+                            .build(),
                     )
+                    .build()
+                    .unwrap()
+            })
+            .map(|mut t| {
+                t.add_observation(0, None, None, Some(VisualAttributesUpdate::Search))
                     .unwrap();
-                    self.store
-                        .write()
-                        .unwrap()
-                        .merge_external(dest, t, Some(&[0]), false)
-                        .unwrap();
-                    dest
-                }
-            } else {
-                let mut t = t.clone();
-                let track_id = self.gen_track_id();
-                t.set_track_id(track_id);
-                self.store.write().unwrap().add_track(t).unwrap();
-                track_id
-            };
+                t
+            })
+            .collect::<Vec<_>>();
 
-            let lock = self.store.read().unwrap();
-            let store = lock.get_store(track_id as usize);
-            let track = store.get(&track_id).unwrap();
+        let (dists, errs) = self
+            .store
+            .write()
+            .unwrap()
+            .foreign_track_distances(queries, 0, false);
+        errs.log();
 
-            res.push(SortTrack::from(track))
+        let mut closest = HashMap::<u64, f32>::new();
+        for d in dists {
+            if let Some(distance) = d.feature_distance {
+                closest
+                    .entry(d.to)
+                    .and_modify(|c| *c = c.min(distance))
+                    .or_insert(distance);
+            }
         }
+        let mut results = closest.into_iter().collect::<Vec<_>>();
+        results.sort_by(|(l_id, l), (r_id, r)| l.total_cmp(r).then(l_id.cmp(r_id)));
+        results.truncate(k);
+        results
+    }
 
-        res
+    /// The estimated statistics of the features of the scene, they are collected when
+    /// [visual_whitening](VisualSortOptions::visual_whitening) is set
+    ///
+    pub fn feature_statistics(&self, scene_id: u64) -> Option<FeatureStatistics> {
+        self.metric_opts.feature_statistics.get(scene_id)
     }
 
     pub fn idle_tracks(&mut self) -> Vec<SortTrack> {
@@ -235,7 +820,7 @@ impl VisualSort {
 
     pub fn idle_tracks_with_scene(&mut self, scene_id: u64) -> Vec<SortTrack> {
         let store = self.store.read().unwrap();
-        store
+        let tracks = store
             .lookup(VisualSortLookup::IdleLookup(scene_id))
             .iter()
             .map(|(track_id, _status)| {
@@ -243,87 +828,134 @@ impl VisualSort {
                 let track = shard.get(track_id).unwrap();
                 SortTrack::from(track)
             })
-            .collect()
+            .collect();
+        self.track_opts.filter_output(tracks)
     }
 }
 
-impl
+impl<N: ChangeNotifier>
     TrackerAPI<
         VisualAttributes,
         VisualMetric,
         VisualObservationAttributes,
         SortAttributesOptions,
-        NoopNotifier,
-    > for VisualSort
+        N,
+    > for VisualSort<N>
 {
     fn get_auto_waste_obj_mut(&mut self) -> &mut AutoWaste {
         &mut self.auto_waste
     }
 
+    fn scene_lookup(&self, scene_id: u64) -> Option<VisualSortLookup> {
+        Some(VisualSortLookup::SceneLookup(scene_id))
+    }
+
+    fn expired_lookup(&self, scene_id: u64) -> Option<VisualSortLookup> {
+        self.track_opts
+            .max_track_lifetime()
+            .map(|_| VisualSortLookup::ExpiredLookup(scene_id))
+    }
+
     fn get_opts(&self) -> &SortAttributesOptions {
         &self.track_opts
     }
 
+    fn on_tracks_wasted(
+        &mut self,
+        tracks: &[Track<VisualAttributes, VisualMetric, VisualObservationAttributes, N>],
+    ) {
+        if let Some(bridge) = &self.vector_store {
+            if let Err(e) = bridge.waste(tracks) {
+                warn!(
+                    "Unable to export the wasted track templates. Error is: {:?}",
+                    e
+                );
+            }
+        }
+    }
+
     fn get_main_store_mut(
         &mut self,
-    ) -> RwLockWriteGuard<
-        TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes, NoopNotifier>,
-    > {
+    ) -> RwLockWriteGuard<TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes, N>>
+    {
         self.store.write().unwrap()
     }
 
     fn get_wasted_store_mut(
         &mut self,
-    ) -> RwLockWriteGuard<
-        TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes, NoopNotifier>,
-    > {
+    ) -> RwLockWriteGuard<TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes, N>>
+    {
         self.wasted_store.write().unwrap()
     }
 
     fn get_main_store(
         &self,
-    ) -> RwLockReadGuard<
-        TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes, NoopNotifier>,
-    > {
+    ) -> RwLockReadGuard<TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes, N>>
+    {
         self.store.read().unwrap()
     }
 
     fn get_wasted_store(
         &self,
-    ) -> RwLockReadGuard<
-        TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes, NoopNotifier>,
-    > {
+    ) -> RwLockReadGuard<TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes, N>>
+    {
         self.wasted_store.read().unwrap()
     }
 }
 
-impl From<&Track<VisualAttributes, VisualMetric, VisualObservationAttributes>> for SortTrack {
-    fn from(track: &Track<VisualAttributes, VisualMetric, VisualObservationAttributes>) -> Self {
+impl<N: ChangeNotifier> From<&Track<VisualAttributes, VisualMetric, VisualObservationAttributes, N>>
+    for SortTrack
+{
+    fn from(track: &Track<VisualAttributes, VisualMetric, VisualObservationAttributes, N>) -> Self {
         let attrs = track.get_attributes();
+        let (observed_bbox, predicted_bbox, clipped) = attrs.output_boxes();
         SortTrack {
             id: track.get_track_id(),
             custom_object_id: attrs.custom_object_id,
             voting_type: attrs.voting_type.unwrap_or(Positional),
             epoch: attrs.last_updated_epoch,
             scene_id: attrs.scene_id,
-            observed_bbox: attrs.observed_boxes.back().unwrap().clone(),
-            predicted_bbox: attrs.predicted_boxes.back().unwrap().clone(),
+            observed_bbox,
+            predicted_bbox,
             length: attrs.track_length,
+            tentative: attrs.track_length < attrs.required_track_length,
+            class: attrs.class_votes.majority(),
+            metadata: attrs.metadata.clone(),
+            created_epoch: attrs.created_epoch,
+            created_timestamp: attrs.created_timestamp,
+            clipped,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::store::nan_distances::NanDistancePolicy;
+    use crate::track::utils::FromVec;
     use crate::track::Observation;
+    use crate::trackers::distance_export::DistanceCollector;
+    use crate::trackers::latency_budget::LatencyBudget;
+    use crate::trackers::predict_overrides::PredictOverrides;
     use crate::trackers::sort::{PositionalMetricType, VotingType};
+    use crate::trackers::spatio_temporal_constraints::SpatioTemporalConstraints;
     use crate::trackers::tracker_api::TrackerAPI;
+    use crate::trackers::visual_sort::adaptive_threshold::AdaptiveVisualThreshold;
+    use crate::trackers::visual_sort::cold_store::ColdStoreOptions;
     use crate::trackers::visual_sort::metric::VisualSortMetricType;
     use crate::trackers::visual_sort::observation_attributes::VisualObservationAttributes;
     use crate::trackers::visual_sort::options::VisualSortOptions;
+    use crate::trackers::visual_sort::reactivation::ReactivationOptions;
     use crate::trackers::visual_sort::simple_api::VisualSort;
+    use crate::trackers::visual_sort::vector_store::{
+        TemplateAggregation, TrackTemplate, VectorStoreBridge, VectorStoreSink,
+    };
+    use crate::trackers::visual_sort::whitening::FeatureWhitening;
     use crate::trackers::visual_sort::{VisualSortObservation, WastedVisualSortTrack};
     use crate::utils::bbox::BoundingBox;
+    use crate::EPS;
+    use crossbeam::channel::{unbounded, Receiver};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
 
     #[test]
     fn visual_sort() {
@@ -664,32 +1296,729 @@ mod tests {
             .collect::<Vec<_>>();
         dbg!(&tracks);
     }
-}
 
-#[cfg(feature = "python")]
-pub mod python {
-    use pyo3::prelude::*;
+    #[test]
+    fn visual_sort_reactivation() {
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(10)
+            .visual_metric(VisualSortMetricType::Euclidean(1.0))
+            .positional_metric(PositionalMetricType::IoU(0.3))
+            .visual_minimal_track_length(2)
+            .visual_max_observations(3)
+            .spatio_temporal_constraints(
+                SpatioTemporalConstraints::default().constraints(&[(10, 1.0)]),
+            );
+        let feature = vec![1.0, 1.0];
+        let observe = |x: f32| {
+            VisualSortObservation::new(
+                Some(&feature),
+                Some(0.9),
+                BoundingBox::new(x, 1.0, 3.0, 5.0).as_xyaah(),
+                None,
+            )
+        };
+        let occlude = |tracker: &mut VisualSort| {
+            let id = (0..3)
+                .map(|i| tracker.predict(&[observe(i as f32 * 0.1)])[0].id)
+                .last()
+                .unwrap();
+            tracker.skip_epochs(4);
+            (id, tracker.predict(&[observe(50.0)]))
+        };
 
-    use crate::{
-        prelude::VisualSortObservation,
-        trackers::{
-            sort::python::PySortTrack,
-            tracker_api::TrackerAPI,
-            visual_sort::{
-                options::python::PyVisualSortOptions,
-                python::{PyVisualSortObservationSet, PyWastedVisualSortTrack},
-                WastedVisualSortTrack,
-            },
-        },
-    };
+        let mut tracker = VisualSort::new(1, &opts);
+        let (id, tracks) = occlude(&mut tracker);
+        assert_ne!(tracks[0].id, id);
 
-    use super::VisualSort;
+        let mut tracker = VisualSort::new(
+            1,
+            &opts.clone().reactivation(ReactivationOptions::new(3, 8, 1)),
+        );
+        let (id, tracks) = occlude(&mut tracker);
+        assert_eq!(tracks[0].id, id);
+        assert_eq!(tracks[0].length, 4);
+        assert!(matches!(tracks[0].voting_type, VotingType::Visual));
 
-    #[pyclass]
-    #[pyo3(name = "VisualSort")]
-    pub struct PyVisualSort(pub(crate) VisualSort);
+        // the tracks idle shorter than the window are not reactivated
+        let mut tracker = VisualSort::new(1, &opts.reactivation(ReactivationOptions::new(6, 8, 1)));
+        let (id, tracks) = occlude(&mut tracker);
+        assert_ne!(tracks[0].id, id);
+    }
 
-    #[pymethods]
+    #[test]
+    fn visual_sort_cold_store() {
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(3)
+            .visual_metric(VisualSortMetricType::Euclidean(1.0))
+            .positional_metric(PositionalMetricType::IoU(0.3))
+            .visual_minimal_track_length(2)
+            .visual_max_observations(3)
+            .cold_store(ColdStoreOptions::new(1, 2, Some(20)));
+        let feature = vec![1.0, 1.0];
+        let observe = |x: f32| {
+            VisualSortObservation::new(
+                Some(&feature),
+                Some(0.9),
+                BoundingBox::new(x, 1.0, 3.0, 5.0).as_xyaah(),
+                None,
+            )
+        };
+
+        let mut tracker = VisualSort::new(1, &opts);
+        tracker.set_auto_waste(0);
+        let id = (0..3)
+            .map(|i| tracker.predict(&[observe(i as f32 * 0.1)])[0].id)
+            .last()
+            .unwrap();
+        for _ in 0..10 {
+            tracker.predict(&[]);
+        }
+        assert!(tracker.wasted().is_empty());
+        assert_eq!(tracker.active_shard_stats().iter().sum::<usize>(), 0);
+        assert_eq!(tracker.cold_store_size(), 1);
+
+        let mut tracker = VisualSort::from_state(tracker.state()).unwrap();
+        assert_eq!(tracker.cold_store_size(), 1);
+        let tracks = tracker.predict(&[observe(50.0)]);
+        assert_eq!(tracks[0].id, id);
+        assert_eq!(tracks[0].length, 4);
+        assert!(matches!(tracks[0].voting_type, VotingType::Visual));
+        assert_eq!(tracker.cold_store_size(), 0);
+
+        // the cold tracks are wasted after the retention
+        for _ in 0..25 {
+            tracker.predict(&[]);
+        }
+        assert_eq!(tracker.cold_store_size(), 0);
+        let wasted = tracker.wasted();
+        assert_eq!(wasted.len(), 1);
+        assert_eq!(wasted[0].get_track_id(), id);
+    }
+
+    #[test]
+    fn visual_sort_nan_distance_policy() {
+        let opts = VisualSortOptions::default().nan_distance_policy(NanDistancePolicy::Error);
+        let mut tracker = VisualSort::new(1, &opts);
+        assert_eq!(tracker.nan_distance_policy(), NanDistancePolicy::Error);
+        tracker.set_nan_distance_policy(NanDistancePolicy::Worst);
+        assert_eq!(
+            tracker.get_main_store().nan_distance_policy(),
+            NanDistancePolicy::Worst
+        );
+    }
+
+    #[test]
+    fn visual_sort_state() {
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(3)
+            .visual_metric(VisualSortMetricType::Euclidean(1.0))
+            .positional_metric(PositionalMetricType::IoU(0.3))
+            .visual_minimal_track_length(2)
+            .visual_max_observations(3)
+            .class_freeze(2);
+        let mut tracker = VisualSort::new(2, &opts);
+        let feature = vec![1.0, 1.0];
+        let observe = |x: f32| {
+            VisualSortObservation::new(
+                Some(&feature),
+                Some(0.9),
+                BoundingBox::new(x, 1.0, 3.0, 5.0).as_xyaah(),
+                None,
+            )
+        };
+        for i in 0..3 {
+            tracker.predict(&[observe(i as f32 * 0.1)]);
+        }
+
+        let state = serde_json::to_string(&tracker.state()).unwrap();
+        let mut restored = VisualSort::from_state(serde_json::from_str(&state).unwrap()).unwrap();
+        assert_eq!(restored.get_opts().class_freeze(), Some(2));
+
+        // the features are restored, so the track is found far away with the visual voting
+        let expected = tracker.predict(&[observe(20.0)]);
+        let tracks = restored.predict(&[observe(20.0)]);
+        assert_eq!(tracks[0].id, expected[0].id);
+        assert_eq!(tracks[0].length, 4);
+        assert!(matches!(tracks[0].voting_type, VotingType::Visual));
+    }
+
+    #[test]
+    fn visual_sort_lifetime() {
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(1)
+            .kept_history_length(1)
+            .positional_metric(PositionalMetricType::IoU(0.3));
+        let mut tracker = VisualSort::new(1, &opts);
+        let observation = VisualSortObservation::new(
+            None,
+            None,
+            BoundingBox::new(1.0, 1.0, 3.0, 5.0).as_xyaah(),
+            None,
+        );
+        for _ in 0..3 {
+            assert_eq!(tracker.predict(&[observation.clone()])[0].created_epoch, 1);
+        }
+
+        let mut wasted = Vec::default();
+        while wasted.is_empty() {
+            tracker.predict(&[]);
+            wasted = tracker.wasted();
+        }
+        let wasted = WastedVisualSortTrack::from(wasted[0].clone());
+        assert_eq!(wasted.observed_epochs, vec![3]);
+        assert_eq!(wasted.created_epoch, 1);
+        assert_eq!(wasted.wasted_epoch, Some(tracker.current_epoch()));
+    }
+
+    #[test]
+    fn visual_sort_predict_overrides() {
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(3)
+            .visual_metric(VisualSortMetricType::Euclidean(1.0))
+            .positional_metric(PositionalMetricType::IoU(0.3))
+            .visual_minimal_track_length(2)
+            .visual_max_observations(3);
+        let mut tracker = VisualSort::new(1, &opts);
+        let feature = vec![1.0, 1.0];
+        let observe = |x: f32| {
+            VisualSortObservation::new(
+                Some(&feature),
+                Some(0.9),
+                BoundingBox::new(x, 1.0, 3.0, 5.0).as_xyaah(),
+                None,
+            )
+        };
+        let mut id = 0;
+        for i in 0..3 {
+            id = tracker.predict(&[observe(i as f32 * 0.1)])[0].id;
+        }
+
+        let tracks = tracker.predict_with_overrides(
+            0,
+            &[observe(20.0)],
+            PredictOverrides {
+                disable_visual: true,
+                ..Default::default()
+            },
+        );
+        assert_ne!(tracks[0].id, id);
+        assert_eq!(
+            tracker.get_opts().predict_overrides(0),
+            PredictOverrides::default()
+        );
+
+        let tracks = tracker.predict(&[observe(40.0)]);
+        assert!(matches!(tracks[0].voting_type, VotingType::Visual));
+    }
+
+    #[test]
+    fn visual_sort_ignored_observations() {
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(3)
+            .visual_metric(VisualSortMetricType::Euclidean(1.0))
+            .positional_metric(PositionalMetricType::IoU(0.3))
+            .visual_minimal_own_area_percentage_collect(0.1);
+        let mut tracker = VisualSort::new(1, &opts);
+        let feature = vec![1.0, 1.0];
+        let observe = |x: f32| {
+            VisualSortObservation::new(
+                Some(&feature),
+                Some(0.9),
+                BoundingBox::new(x, 1.0, 3.0, 5.0).as_xyaah(),
+                None,
+            )
+        };
+        let (tracks, assignments) =
+            tracker.predict_with_assignments(0, &[observe(0.0), observe(1.5).with_ignored(true)]);
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(assignments.len(), 1);
+        assert_eq!(assignments[0].index, 0);
+
+        // the ignored observation occludes the tracked one
+        let id = tracks[0].id;
+        let own_area = {
+            let lock = tracker.store.read().unwrap();
+            let store = lock.get_store(id as usize);
+            let observations = store.get(&id).unwrap().get_observations(0).unwrap();
+            observations[0]
+                .attr()
+                .as_ref()
+                .unwrap()
+                .own_area_percentage_opt()
+                .unwrap()
+        };
+        assert!(own_area < 0.9, "{own_area}");
+
+        let tracks = tracker.predict(&[observe(0.1).with_ignored(true)]);
+        assert!(tracks.is_empty());
+        assert_eq!(tracker.active_shard_stats().iter().sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn visual_sort_distance_export() {
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(3)
+            .visual_metric(VisualSortMetricType::Euclidean(1.0))
+            .positional_metric(PositionalMetricType::IoU(0.3))
+            .visual_minimal_track_length(2)
+            .visual_max_observations(3);
+        let mut tracker = VisualSort::new(1, &opts);
+        let collector = DistanceCollector::default();
+        tracker.set_distance_sink(Some(Arc::new(collector.clone())));
+        let observe = |x: f32, feature: &'static [f32]| {
+            VisualSortObservation::new(
+                Some(feature),
+                Some(0.9),
+                BoundingBox::new(x, 1.0, 3.0, 5.0).as_xyaah(),
+                None,
+            )
+        };
+        let mut id = 0;
+        for i in 0..3 {
+            id = tracker.predict(&[observe(i as f32 * 0.1, &[1.0, 1.0])])[0].id;
+        }
+        collector.drain();
+
+        tracker.predict(&[observe(0.3, &[1.0, 1.1]), observe(20.0, &[1.0, 1.5])]);
+        let exported = collector.drain();
+        assert_eq!(exported.len(), 1);
+        let matched = exported[0].matched[0];
+        assert_eq!(matched.index, 0);
+        assert_eq!(matched.distance.track_id, id);
+        assert!((matched.distance.visual.unwrap() - 0.1).abs() < 1e-4);
+        let nearest = exported[0].unmatched[0].nearest.unwrap();
+        assert_eq!(exported[0].unmatched[0].index, 1);
+        assert_eq!(nearest.track_id, id);
+        assert!((nearest.visual.unwrap() - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn visual_sort_whitening() {
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(3)
+            .visual_metric(VisualSortMetricType::Euclidean(1.0))
+            .positional_metric(PositionalMetricType::IoU(0.3))
+            .visual_whitening(FeatureWhitening::new(0.1, 3, true));
+        let mut tracker = VisualSort::new(1, &opts);
+        let features = [[1.0, 10.0], [3.0, 30.0], [2.0, 20.0]];
+        let mut id = 0;
+        for (i, f) in features.iter().enumerate() {
+            id = tracker.predict(&[VisualSortObservation::new(
+                Some(f),
+                Some(0.9),
+                BoundingBox::new(i as f32 * 0.1, 1.0, 3.0, 5.0).as_xyaah(),
+                None,
+            )])[0]
+                .id;
+        }
+        let stats = tracker.feature_statistics(0).unwrap();
+        assert_eq!(stats.samples(), 3);
+        assert!((stats.mean()[1] - 20.0).abs() < EPS);
+        assert!(tracker.feature_statistics(1).is_none());
+
+        // the last feature is whitened to the mean
+        let lock = tracker.store.read().unwrap();
+        let shard = lock.get_store(id as usize);
+        let features = shard
+            .get(&id)
+            .unwrap()
+            .get_observations(0)
+            .unwrap()
+            .iter()
+            .filter_map(|o| o.feature().as_ref().map(|f| Vec::<f32>::from_vec(f)))
+            .collect::<Vec<_>>();
+        assert!(
+            features.iter().any(|f| f.iter().all(|x| x.abs() < EPS)),
+            "{features:?}"
+        );
+    }
+
+    #[test]
+    fn visual_sort_latency_budget() {
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(3)
+            .visual_metric(VisualSortMetricType::Euclidean(1.0))
+            .positional_metric(PositionalMetricType::IoU(0.3))
+            .visual_minimal_track_length(2)
+            .visual_max_observations(3);
+        let mut tracker = VisualSort::new(1, &opts);
+        let feature = vec![1.0, 1.0];
+        let observe = |x: f32| {
+            VisualSortObservation::new(
+                Some(&feature),
+                Some(0.9),
+                BoundingBox::new(x, 1.0, 3.0, 5.0).as_xyaah(),
+                None,
+            )
+        };
+        let mut id = 0;
+        for i in 0..3 {
+            id = tracker.predict(&[observe(i as f32 * 0.1)])[0].id;
+        }
+
+        tracker
+            .get_opts()
+            .set_latency_budget(Some(LatencyBudget::new(Duration::ZERO, Duration::ZERO)));
+        assert!(!tracker.get_opts().latency_degraded(0));
+        assert_eq!(tracker.predict(&[observe(0.3)])[0].id, id);
+        assert!(tracker.get_opts().latency_degraded(0));
+        assert!(tracker.get_opts().predict_overrides(0).disable_visual);
+
+        let tracks = tracker.predict(&[observe(20.0)]);
+        assert_ne!(tracks[0].id, id);
+
+        tracker.get_opts().set_latency_budget(None);
+        let tracks = tracker.predict(&[observe(40.0)]);
+        assert_eq!(tracks[0].id, id);
+        assert!(matches!(tracks[0].voting_type, VotingType::Visual));
+    }
+
+    #[test]
+    fn visual_min_votes_fraction() {
+        let feature = vec![1.0, 1.0];
+        let observe = |x: f32| {
+            VisualSortObservation::new(
+                Some(&feature),
+                Some(0.9),
+                BoundingBox::new(x, 1.0, 3.0, 5.0).as_xyaah(),
+                None,
+            )
+        };
+        let opts = VisualSortOptions::default()
+            .visual_metric(VisualSortMetricType::Euclidean(1.0))
+            .positional_metric(PositionalMetricType::IoU(0.3))
+            .visual_minimal_track_length(2)
+            .visual_max_observations(5)
+            .visual_min_votes(4);
+
+        for (opts, votes, visual) in [
+            (opts.clone(), 4, false),
+            (opts.visual_min_votes_fraction(0.5), 2, true),
+        ] {
+            let mut tracker = VisualSort::new(1, &opts);
+            let mut id = 0;
+            for i in 0..3 {
+                id = tracker.predict(&[observe(i as f32 * 0.1)])[0].id;
+            }
+            assert_eq!(tracker.effective_min_votes(id), Some(votes));
+            assert_eq!(tracker.effective_min_votes(id + 1000), None);
+
+            let tracks = tracker.predict(&[observe(40.0)]);
+            assert_eq!(matches!(tracks[0].voting_type, VotingType::Visual), visual);
+            assert_eq!(tracks[0].id == id, visual);
+        }
+    }
+
+    #[test]
+    fn visual_adaptive_threshold() {
+        fn observe(x: f32, feature: &[f32]) -> VisualSortObservation<'_> {
+            VisualSortObservation::new(
+                Some(feature),
+                Some(0.9),
+                BoundingBox::new(x, 1.0, 3.0, 5.0).as_xyaah(),
+                None,
+            )
+        }
+
+        let run = |opts: VisualSortOptions| {
+            let mut tracker = VisualSort::new(1, &opts);
+            let mut id = 0;
+            for i in 0..5 {
+                let feature = [1.0, 1.0 + i as f32 * 0.01];
+                id = tracker.predict(&[observe(i as f32 * 0.1, &feature)])[0].id;
+            }
+            let attrs = {
+                let lock = tracker.store.read().unwrap();
+                let store = lock.get_store(id as usize);
+                store.get(&id).unwrap().get_attributes().clone()
+            };
+            let tracks = tracker.predict(&[observe(20.0, &[1.0, 1.5])]);
+            (tracks[0].id == id, attrs.visual_distance_stats)
+        };
+
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(3)
+            .visual_metric(VisualSortMetricType::Euclidean(1.0))
+            .positional_metric(PositionalMetricType::IoU(0.3))
+            .visual_minimal_track_length(2)
+            .visual_max_observations(3);
+
+        let (matched, stats) = run(opts.clone());
+        assert!(matched);
+        assert!(stats.is_empty());
+
+        let (matched, stats) =
+            run(opts.visual_adaptive_threshold(AdaptiveVisualThreshold::new(3.0, 10, 3)));
+        assert!(!matched);
+        assert_eq!(stats.len(), 4);
+        assert!((stats.mean().unwrap() - 0.01).abs() < 0.001);
+    }
+
+    #[test]
+    fn search() {
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(3)
+            .visual_metric(VisualSortMetricType::Euclidean(1.0))
+            .positional_metric(PositionalMetricType::IoU(0.3))
+            .visual_minimal_track_length(1);
+        let mut tracker = VisualSort::new(2, &opts);
+        assert!(tracker.search(&[1.0, 0.0], None, 3).is_empty());
+
+        let observe = |x: f32, feature: &'static [f32]| {
+            VisualSortObservation::new(
+                Some(feature),
+                Some(0.9),
+                BoundingBox::new(x, 1.0, 3.0, 5.0).as_xyaah(),
+                None,
+            )
+        };
+        let mut ids = vec![];
+        for _ in 0..2 {
+            ids = tracker
+                .predict_with_scene(1, &[observe(0.0, &[1.0, 0.0]), observe(50.0, &[1.0, 0.5])])
+                .iter()
+                .map(|t| t.id)
+                .collect::<Vec<_>>();
+        }
+        let other = tracker.predict_with_scene(2, &[observe(0.0, &[1.0, 0.1])])[0].id;
+
+        let found = tracker.search(&[1.0, 0.0], Some(1), 3);
+        assert_eq!(
+            found.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            vec![ids[0], ids[1]]
+        );
+        assert!(found[0].1.abs() < 1e-6);
+        assert!((found[1].1 - 0.5).abs() < 1e-3);
+
+        let found = tracker.search(&[1.0, 0.0], None, 2);
+        assert_eq!(
+            found.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            vec![ids[0], other]
+        );
+        assert!(tracker.search(&[10.0, 0.0], None, 2).is_empty());
+    }
+
+    #[test]
+    fn depth_gating() {
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(3)
+            .positional_metric(PositionalMetricType::IoU(0.3))
+            .positional_max_depth_delta(1.0);
+
+        let mut tracker = VisualSort::new(1, &opts);
+        let bbox = BoundingBox::new(1.0, 1.0, 3.0, 5.0).as_xyaah();
+
+        let mut observe = |depth: Option<f32>| {
+            let mut observation = VisualSortObservation::new(None, None, bbox.clone(), None);
+            if let Some(depth) = depth {
+                observation = observation.with_depth(depth);
+            }
+            tracker.predict(&[observation])[0].id
+        };
+
+        let t1 = observe(Some(5.0));
+        let t2 = observe(Some(5.5));
+        assert_eq!(t1, t2);
+
+        let t3 = observe(Some(8.0));
+        assert_ne!(t1, t3);
+
+        let t4 = observe(None);
+        assert!(t4 == t1 || t4 == t3);
+    }
+
+    #[test]
+    fn feature_collection_rate_limit() {
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(3)
+            .visual_max_observations(10)
+            .visual_collect_rate_limit(2, 3);
+
+        let mut tracker = VisualSort::new(1, &opts);
+        let bbox = BoundingBox::new(1.0, 1.0, 3.0, 5.0).as_xyaah();
+        let feature = vec![1.0, 1.0];
+
+        let mut collected = Vec::default();
+        for _ in 0..7 {
+            let t = tracker.predict(&[VisualSortObservation::new(
+                Some(&feature),
+                Some(0.9),
+                bbox.clone(),
+                None,
+            )])[0]
+                .clone();
+            let lock = tracker.store.read().unwrap();
+            let store = lock.get_store(t.id as usize);
+            let track = store.get(&t.id).unwrap();
+            collected.push(track.get_attributes().visual_features_collected_count);
+        }
+        assert_eq!(collected, vec![1, 2, 2, 3, 4, 4, 5]);
+    }
+
+    #[test]
+    fn feature_collection_sampling_seed() {
+        let collect = |seed| {
+            let opts = VisualSortOptions::default()
+                .visual_max_observations(100)
+                .visual_collect_sampling(0.5)
+                .visual_collect_sampling_seed(seed);
+            let mut tracker = VisualSort::new(1, &opts);
+            let feature = vec![1.0, 1.0];
+            (0..30)
+                .map(|i| {
+                    let bbox = BoundingBox::new(1.0 + i as f32 * 0.1, 1.0, 3.0, 5.0).as_xyaah();
+                    let t = tracker.predict(&[VisualSortObservation::new(
+                        Some(&feature),
+                        Some(0.9),
+                        bbox,
+                        None,
+                    )])[0]
+                        .clone();
+                    let lock = tracker.store.read().unwrap();
+                    let store = lock.get_store(t.id as usize);
+                    let track = store.get(&t.id).unwrap();
+                    track.get_attributes().visual_features_collected_count
+                })
+                .collect::<Vec<_>>()
+        };
+        let collected = collect(7);
+        assert_eq!(collected, collect(7));
+        assert!(*collected.last().unwrap() > 1 && *collected.last().unwrap() < 30);
+    }
+
+    #[test]
+    fn vector_store_export() {
+        #[derive(Clone, Default)]
+        struct MemorySink(Arc<Mutex<Vec<TrackTemplate>>>);
+
+        impl VectorStoreSink for MemorySink {
+            fn upsert(&self, templates: &[TrackTemplate]) -> anyhow::Result<()> {
+                self.0.lock().unwrap().extend_from_slice(templates);
+                Ok(())
+            }
+        }
+
+        let opts = VisualSortOptions::default()
+            .max_idle_epochs(3)
+            .visual_max_observations(10);
+        let mut tracker = VisualSort::new(1, &opts);
+        let sink = MemorySink::default();
+        let bridge = Arc::new(VectorStoreBridge::new(
+            TemplateAggregation::Ema { alpha: 0.5 },
+            Box::new(sink.clone()),
+        ));
+        tracker.set_vector_store_bridge(Some(bridge.clone()));
+        let bbox = BoundingBox::new(1.0, 1.0, 3.0, 5.0).as_xyaah();
+
+        let t1 = tracker.predict(&[VisualSortObservation::new(
+            Some(&[1.0, 0.0]),
+            Some(0.9),
+            bbox.clone(),
+            None,
+        )]);
+        let t2 = tracker.predict(&[VisualSortObservation::new(
+            Some(&[0.0, 1.0]),
+            Some(0.8),
+            bbox.clone(),
+            None,
+        )]);
+        assert_eq!(t1[0].id, t2[0].id);
+        bridge.flush();
+        {
+            let exported = sink.0.lock().unwrap();
+            assert_eq!(exported.len(), 2);
+            let last = exported.last().unwrap();
+            assert_eq!(last.track_id, t1[0].id);
+            assert_eq!(last.embedding[..2], [0.5, 0.5]);
+            assert_eq!(last.features, 2);
+            assert!((last.quality - 0.9).abs() < EPS);
+            assert!(!last.wasted);
+        }
+
+        tracker.skip_epochs(5);
+        bridge.flush();
+        let exported = sink.0.lock().unwrap();
+        assert_eq!(exported.len(), 3);
+        let last = exported.last().unwrap();
+        assert!(last.wasted);
+        assert_eq!(last.embedding[..2], [0.5, 0.5]);
+    }
+
+    #[test]
+    fn vector_store_slow_sink() {
+        struct BlockedSink(Mutex<Receiver<()>>, Arc<Mutex<Vec<TrackTemplate>>>);
+
+        impl VectorStoreSink for BlockedSink {
+            fn upsert(&self, templates: &[TrackTemplate]) -> anyhow::Result<()> {
+                let _ = self.0.lock().unwrap().recv();
+                self.1.lock().unwrap().extend_from_slice(templates);
+                Ok(())
+            }
+        }
+
+        let opts = VisualSortOptions::default().visual_max_observations(10);
+        let mut tracker = VisualSort::new(1, &opts);
+        let (release, blocked) = unbounded();
+        let exported = Arc::new(Mutex::new(Vec::new()));
+        let bridge = Arc::new(VectorStoreBridge::new_with_queue(
+            TemplateAggregation::Ema { alpha: 0.5 },
+            Box::new(BlockedSink(Mutex::new(blocked), exported.clone())),
+            1,
+        ));
+        tracker.set_vector_store_bridge(Some(bridge.clone()));
+        let bbox = BoundingBox::new(1.0, 1.0, 3.0, 5.0).as_xyaah();
+
+        // the sink doesn't respond, the predictions are not blocked and the templates are postponed
+        let mut tracks = Vec::new();
+        for _ in 0..5 {
+            tracks = tracker.predict(&[VisualSortObservation::new(
+                Some(&[1.0, 0.0]),
+                Some(0.9),
+                bbox.clone(),
+                None,
+            )]);
+        }
+        assert!(exported.lock().unwrap().is_empty());
+
+        drop(release);
+        bridge.flush();
+        let exported = exported.lock().unwrap();
+        let last = exported.last().unwrap();
+        assert_eq!(last.track_id, tracks[0].id);
+        assert_eq!(last.features, 5);
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use pyo3::exceptions::PyValueError;
+    use pyo3::prelude::*;
+    use pyo3::types::PyBytes;
+    use std::sync::Arc;
+
+    use crate::{
+        prelude::VisualSortObservation,
+        store::shard_stats::python::PyShardDistanceStats,
+        trackers::{
+            distance_export::{python::PyDistanceCollector, DistanceSink},
+            predict_overrides::python::PyPredictOverrides,
+            sort::python::PySortTrack,
+            tracker_api::TrackerAPI,
+            visual_sort::{
+                options::python::PyVisualSortOptions,
+                python::{PyVisualSortObservationSet, PyWastedVisualSortTrack},
+                WastedVisualSortTrack,
+            },
+        },
+    };
+
+    use super::VisualSort;
+
+    #[pyclass]
+    #[pyo3(name = "VisualSort")]
+    pub struct PyVisualSort(pub(crate) VisualSort);
+
+    #[pymethods]
     impl PyVisualSort {
         #[new]
         pub fn new(shards: i64, opts: &PyVisualSortOptions) -> Self {
@@ -697,6 +2026,65 @@ pub mod python {
             Self(VisualSort::new(shards.try_into().unwrap(), &opts.0))
         }
 
+        /// Serializes the state of the tracker (the options, the epochs and the tracks) to JSON
+        ///
+        #[pyo3(signature = ())]
+        pub fn state(&self, py: Python) -> PyResult<PyObject> {
+            let state = serde_json::to_vec(&self.0.state())
+                .map_err(|e| PyValueError::new_err(e.to_string()))?;
+            Ok(PyBytes::new(py, &state).into())
+        }
+
+        /// Exports the active tracks as two Arrow IPC streams: the track summaries and the
+        /// observations, both can be read with `pyarrow.ipc.open_stream`
+        ///
+        #[pyo3(signature = ())]
+        pub fn export_arrow(&self, py: Python) -> PyResult<(PyObject, PyObject)> {
+            let (mut summaries, mut observations) = (Vec::new(), Vec::new());
+            self.0
+                .export_arrow(&mut summaries, &mut observations)
+                .map_err(|e| PyValueError::new_err(e.to_string()))?;
+            Ok((
+                PyBytes::new(py, &summaries).into(),
+                PyBytes::new(py, &observations).into(),
+            ))
+        }
+
+        /// Creates the tracker from the state serialized with `state`
+        ///
+        #[staticmethod]
+        #[pyo3(signature = (state))]
+        pub fn from_state(state: &[u8]) -> PyResult<Self> {
+            let state =
+                serde_json::from_slice(state).map_err(|e| PyValueError::new_err(e.to_string()))?;
+            Ok(Self(
+                VisualSort::from_state(state).map_err(|e| PyValueError::new_err(e.to_string()))?,
+            ))
+        }
+
+        fn __reduce__(&self, py: Python) -> PyResult<(PyObject, (PyObject,))> {
+            Ok((
+                py.get_type::<Self>().getattr("from_state")?.into(),
+                (self.state(py)?,),
+            ))
+        }
+
+        /// Stops the executor threads of the tracker, the tracker can't be used afterwards
+        ///
+        #[pyo3(signature = ())]
+        pub fn shutdown(&mut self) {
+            self.0.shutdown()
+        }
+
+        fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+            slf
+        }
+
+        fn __exit__(&mut self, _exc_type: &PyAny, _exc_value: &PyAny, _traceback: &PyAny) -> bool {
+            self.0.shutdown();
+            false
+        }
+
         #[pyo3(signature = (n))]
         pub fn skip_epochs(&mut self, n: i64) {
             assert!(n > 0);
@@ -710,6 +2098,151 @@ pub mod python {
                 .skip_epochs_for_scene(scene_id.try_into().unwrap(), n.try_into().unwrap())
         }
 
+        #[pyo3(signature = (scene_id, overrides))]
+        pub fn set_predict_overrides(
+            &mut self,
+            scene_id: u64,
+            overrides: Option<PyPredictOverrides>,
+        ) {
+            self.0
+                .set_predict_overrides(scene_id, overrides.map(|o| o.0))
+        }
+
+        /// Sets the true dimension of the observed features, the padding of the features is
+        /// stripped when the tracks are exported with `export_arrow`
+        ///
+        #[pyo3(signature = (dim))]
+        pub fn set_feature_dimension(&mut self, dim: usize) {
+            self.0.set_feature_dimension(dim)
+        }
+
+        /// The object density of the scene estimated from the observations of the last epochs
+        ///
+        /// # Returns
+        /// `(objects, occupied_cells, density)` or `None` if the estimation is disabled or the scene has no observations accounted
+        ///
+        #[pyo3(signature = (scene_id))]
+        pub fn scene_density(&self, scene_id: u64) -> Option<(f32, usize, f32)> {
+            self.0
+                .get_opts()
+                .scene_density(scene_id)
+                .map(|d| (d.objects, d.occupied_cells, d.density))
+        }
+
+        /// The tracking quality of the scene estimated without the ground truth
+        ///
+        /// # Returns
+        /// `(new_tracks, updates, id_switches, fragmentations, id_switch_rate, fragmentation_rate)` or `None` if the estimation is disabled or the scene has no tracks accounted
+        ///
+        #[pyo3(signature = (scene_id))]
+        pub fn tracking_quality(
+            &self,
+            scene_id: u64,
+        ) -> Option<(usize, usize, usize, usize, f32, f32)> {
+            self.0.get_opts().tracking_quality(scene_id).map(|q| {
+                (
+                    q.new_tracks,
+                    q.updates,
+                    q.id_switches,
+                    q.fragmentations,
+                    q.id_switch_rate,
+                    q.fragmentation_rate,
+                )
+            })
+        }
+
+        /// Checks if the scene exceeded the latency budget, so its visual distances are skipped
+        ///
+        #[pyo3(signature = (scene_id))]
+        pub fn latency_degraded(&self, scene_id: u64) -> bool {
+            self.0.get_opts().latency_degraded(scene_id)
+        }
+
+        /// The estimated statistics of the features of the scene
+        ///
+        /// # Returns
+        /// `(mean, variance, samples)` or `None` if the whitening is disabled or the scene has no
+        /// features accounted
+        ///
+        #[pyo3(signature = (scene_id))]
+        pub fn feature_statistics(&self, scene_id: u64) -> Option<(Vec<f32>, Vec<f32>, usize)> {
+            self.0
+                .feature_statistics(scene_id)
+                .map(|s| (s.mean().to_vec(), s.variance().to_vec(), s.samples()))
+        }
+
+        /// Sets the collector receiving the distances of the matched and the unmatched observations
+        /// after every association, `None` stops the export
+        ///
+        #[pyo3(signature = (collector))]
+        pub fn set_distance_collector(&mut self, collector: Option<PyDistanceCollector>) {
+            self.0
+                .set_distance_sink(collector.map(|c| Arc::new(c.0) as Arc<dyn DistanceSink>))
+        }
+
+        /// The prediction latency percentiles of the scene in seconds
+        ///
+        /// # Returns
+        /// `(p50, p95, p99, samples)` or `None` if the scene has no predictions accounted
+        ///
+        #[pyo3(signature = (scene_id))]
+        pub fn predict_latency(&self, scene_id: u64) -> Option<(f64, f64, f64, usize)> {
+            self.0.get_opts().predict_latency(scene_id).map(|p| {
+                (
+                    p.p50.as_secs_f64(),
+                    p.p95.as_secs_f64(),
+                    p.p99.as_secs_f64(),
+                    p.samples,
+                )
+            })
+        }
+
+        /// The scenes which epochs are tracked
+        ///
+        #[pyo3(signature = ())]
+        pub fn scenes(&self) -> Vec<u64> {
+            TrackerAPI::scenes(&self.0)
+        }
+
+        /// The number of the tracks kept in the cold store
+        ///
+        #[pyo3(signature = ())]
+        pub fn cold_store_size(&self) -> usize {
+            self.0.cold_store_size()
+        }
+
+        /// Moves the active tracks of the scene to the wasted store and restarts its epochs
+        ///
+        #[pyo3(signature = (scene_id))]
+        pub fn reset_scene(&mut self, scene_id: u64) {
+            self.0.reset_scene(scene_id)
+        }
+
+        /// Resets the scenes which passed more than `n` of their epochs without the observations
+        ///
+        #[pyo3(signature = (n))]
+        pub fn clear_idle_scenes(&mut self, n: usize) -> Vec<u64> {
+            self.0.clear_idle_scenes(n)
+        }
+
+        /// Get the distance computation counters of the active tracks store per shard
+        ///
+        #[pyo3(signature = ())]
+        pub fn distance_stats(&self) -> Vec<PyShardDistanceStats> {
+            self.0
+                .distance_stats()
+                .into_iter()
+                .map(PyShardDistanceStats)
+                .collect()
+        }
+
+        /// Resets the distance computation counters
+        ///
+        #[pyo3(signature = ())]
+        pub fn reset_distance_stats(&self) {
+            self.0.reset_distance_stats()
+        }
+
         /// Get the amount of stored tracks per shard
         ///
         #[pyo3(signature = ())]
@@ -732,6 +2265,19 @@ pub mod python {
             self.0.current_epoch_with_scene(0).try_into().unwrap()
         }
 
+        /// The Kalman weights recommended for the scene by the noise model estimation
+        ///
+        /// # Returns
+        /// `(position_weight, velocity_weight, samples)` or `None` if the estimate is not ready
+        ///
+        #[pyo3(signature = (scene_id))]
+        pub fn noise_estimate(&self, scene_id: u64) -> Option<(f32, f32, usize)> {
+            self.0
+                .get_opts()
+                .noise_estimate(scene_id)
+                .map(|e| (e.position_weight, e.velocity_weight, e.samples))
+        }
+
         /// Get the current epoch for `scene_id`
         ///
         /// # Parameters
@@ -796,6 +2342,95 @@ pub mod python {
             })
         }
 
+        /// Receive tracking information for observed bboxes of `scene_id` with the tracks the
+        /// observations ended up in
+        ///
+        /// # Parameters
+        /// * `scene_id` - scene id provided by a user (class, camera id, etc...)
+        /// * `observation_set` - observation set
+        ///
+        /// # Returns
+        /// the tracks and the `(index, track_id, new_track)` assignments of the observations
+        ///
+        #[pyo3(signature = (scene_id, observation_set))]
+        pub fn predict_with_assignments(
+            &mut self,
+            scene_id: i64,
+            observation_set: &PyVisualSortObservationSet,
+        ) -> (Vec<PySortTrack>, Vec<(usize, u64, bool)>) {
+            assert!(scene_id >= 0);
+            let observations = observation_set
+                .0
+                .inner
+                .iter()
+                .map(|e| {
+                    VisualSortObservation::new(
+                        e.feature.as_deref(),
+                        e.feature_quality,
+                        e.bounding_box.clone(),
+                        e.custom_object_id,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let (tracks, assignments) = Python::with_gil(|py| {
+                py.allow_threads(|| {
+                    self.0
+                        .predict_with_assignments(scene_id.try_into().unwrap(), &observations)
+                })
+            });
+            (
+                unsafe {
+                    std::mem::transmute::<Vec<crate::prelude::SortTrack>, Vec<PySortTrack>>(tracks)
+                },
+                assignments
+                    .into_iter()
+                    .map(|a| (a.index, a.track_id, a.new_track))
+                    .collect(),
+            )
+        }
+
+        /// Receive tracking information for observed bboxes of `scene_id` with the configuration
+        /// overridden for this prediction only
+        ///
+        /// # Parameters
+        /// * `scene_id` - scene id provided by a user (class, camera id, etc...)
+        /// * `observation_set` - observation set
+        /// * `overrides` - the overrides layered over the configuration of the tracker
+        ///
+        #[pyo3(signature = (scene_id, observation_set, overrides))]
+        pub fn predict_with_overrides(
+            &mut self,
+            scene_id: i64,
+            observation_set: &PyVisualSortObservationSet,
+            overrides: PyPredictOverrides,
+        ) -> Vec<PySortTrack> {
+            assert!(scene_id >= 0);
+            let observations = observation_set
+                .0
+                .inner
+                .iter()
+                .map(|e| {
+                    VisualSortObservation::new(
+                        e.feature.as_deref(),
+                        e.feature_quality,
+                        e.bounding_box.clone(),
+                        e.custom_object_id,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            Python::with_gil(|py| {
+                py.allow_threads(|| unsafe {
+                    std::mem::transmute(self.0.predict_with_overrides(
+                        scene_id.try_into().unwrap(),
+                        &observations,
+                        overrides.0,
+                    ))
+                })
+            })
+        }
+
         /// Remove all the tracks with expired life
         ///
         #[pyo3(signature = ())]
@@ -836,5 +2471,32 @@ pub mod python {
                 })
             })
         }
+
+        /// Get the visual votes the active track must collect to be associated by the features
+        ///
+        #[pyo3(signature = (track_id))]
+        pub fn effective_min_votes(&self, track_id: u64) -> Option<usize> {
+            self.0.effective_min_votes(track_id)
+        }
+
+        /// Search the active tracks which features are the closest to the query feature
+        ///
+        /// # Parameters
+        /// * `feature` - the query feature
+        /// * `k` - the maximal number of the tracks returned
+        /// * `scene_id` - the scene to search in, all the tracked scenes are searched if not set
+        ///
+        /// # Returns
+        /// `(track_id, distance)` pairs ordered by the distance
+        ///
+        #[pyo3(signature = (feature, k, scene_id = None))]
+        pub fn search(
+            &self,
+            feature: Vec<f32>,
+            k: usize,
+            scene_id: Option<u64>,
+        ) -> Vec<(u64, f32)> {
+            Python::with_gil(|py| py.allow_threads(|| self.0.search(&feature, scene_id, k)))
+        }
     }
 }
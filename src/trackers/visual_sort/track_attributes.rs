@@ -1,29 +1,72 @@
+use crate::track::calibration::SceneCalibration;
 use crate::track::{
     Feature, LookupRequest, ObservationsDb, TrackAttributes, TrackAttributesUpdate, TrackStatus,
 };
+use crate::trackers::class_voting::ClassVotes;
 use crate::trackers::epoch_db::EpochDb;
 use crate::trackers::kalman_prediction::TrackAttributesKalmanPrediction;
-use crate::trackers::sort::{SortAttributesOptions, VotingType};
+use crate::trackers::metadata::merge_metadata;
+use crate::trackers::motion_model::MotionModel;
+use crate::trackers::predict_overrides::PredictOverrides;
+use crate::trackers::sort::{wall_clock_ms, SortAttributesOptions, VotingType};
+use crate::trackers::visual_sort::adaptive_threshold::VisualDistanceStats;
 use crate::trackers::visual_sort::observation_attributes::VisualObservationAttributes;
+use crate::trackers::visual_sort::reactivation::ReactivationOptions;
+use crate::trackers::waste_triggers::WasteContext;
+#[cfg(feature = "f16")]
+use crate::utils::bbox::compact::{compact_history, CompactUniversal2DBox};
 use crate::utils::bbox::Universal2DBox;
 use crate::utils::kalman::kalman_2d_box::DIM_2D_BOX_X2;
 use crate::utils::kalman::KalmanState;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::VecDeque;
 use std::sync::Arc;
 
 /// Universal visual_sort attributes for visual_sort trackers
 ///
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VisualAttributes {
     /// Boxes predicted by Kalman filter
+    ///
+    /// With the compact box history only the latest box is kept here, the preceding ones are
+    /// kept in `compact_predicted_boxes`, the full history is returned by
+    /// [predicted_boxes_history](Self::predicted_boxes_history)
+    ///
     pub predicted_boxes: VecDeque<Universal2DBox>,
     /// Boxes observed by detector
+    ///
+    /// With the compact box history only the latest box is kept here, the preceding ones are
+    /// kept in `compact_observed_boxes`, the full history is returned by
+    /// [observed_boxes_history](Self::observed_boxes_history)
+    ///
     pub observed_boxes: VecDeque<Universal2DBox>,
+    /// Boxes predicted by Kalman filter preceding `predicted_boxes`, kept with half precision
+    #[cfg(feature = "f16")]
+    pub compact_predicted_boxes: VecDeque<CompactUniversal2DBox>,
+    /// Boxes observed by detector preceding `observed_boxes`, kept with half precision
+    #[cfg(feature = "f16")]
+    pub compact_observed_boxes: VecDeque<CompactUniversal2DBox>,
     /// Features observed by feature extractor model
+    #[serde(with = "crate::track::state::features")]
     pub observed_features: VecDeque<Option<Feature>>,
+    /// Epochs when the observed boxes were collected
+    pub observed_epochs: VecDeque<usize>,
+    /// Wall-clock times (milliseconds since UNIX epoch) when the observed boxes were collected,
+    /// recorded only when [observation_timestamps](SortAttributesOptions::observation_timestamps)
+    /// is enabled, so they are aligned with the latest `observed_epochs`
+    pub observed_timestamps: VecDeque<u64>,
     /// The last epoch when attributes were updated
     pub last_updated_epoch: usize,
+    /// The epoch when the track was created
+    pub created_epoch: usize,
+    /// The wall-clock time (milliseconds since UNIX epoch) when the track was created
+    pub created_timestamp: u64,
+    /// The epoch when the track was wasted, `None` while the track is alive
+    pub wasted_epoch: Option<usize>,
+    /// The wall-clock time (milliseconds since UNIX epoch) when the track was wasted
+    pub wasted_timestamp: Option<u64>,
     /// The length of the track
     pub track_length: usize,
     /// Visual track elements amount collected
@@ -34,8 +77,36 @@ pub struct VisualAttributes {
     pub custom_object_id: Option<i64>,
     /// Last voting type
     pub voting_type: Option<VotingType>,
+    /// The last known distance from the observer to the object
+    pub depth: Option<f32>,
+    /// The number of observations the track must collect before it is reported
+    pub required_track_length: usize,
+    /// The epochs when the lastly collected features were added to the track
+    pub collected_feature_epochs: VecDeque<usize>,
+    /// The distances of the collected features to the closest features kept by the track
+    #[serde(default)]
+    pub visual_distance_stats: VisualDistanceStats,
+    /// The class labels of the observations
+    pub class_votes: ClassVotes,
+    /// The user metadata attached to the observations
+    pub metadata: Option<Value>,
+    /// The epoch the Kalman state was propagated to over the skipped epochs
+    #[serde(default)]
+    pub propagated_epoch: usize,
+    /// The candidate is compared with the idle tracks by the appearance only, the options define
+    /// the tracks it may reactivate
+    #[serde(skip)]
+    pub(crate) reactivation: Option<ReactivationOptions>,
+    /// The query is compared with all the tracks of the scene by the appearance only
+    #[serde(skip)]
+    pub(crate) search: bool,
+    /// The overrides of the prediction the candidate is built for, layered over the overrides of
+    /// the scene when the candidate is compared with the tracks
+    #[serde(skip)]
+    pub(crate) call_overrides: Option<PredictOverrides>,
 
     state: Option<KalmanState<{ DIM_2D_BOX_X2 }>>,
+    #[serde(skip)]
     opts: Arc<SortAttributesOptions>,
 }
 
@@ -43,10 +114,30 @@ impl Default for VisualAttributes {
     fn default() -> Self {
         Self {
             voting_type: None,
+            depth: None,
+            required_track_length: 0,
+            collected_feature_epochs: VecDeque::default(),
+            visual_distance_stats: VisualDistanceStats::default(),
+            class_votes: ClassVotes::default(),
+            metadata: None,
+            propagated_epoch: 0,
+            reactivation: None,
+            search: false,
+            call_overrides: None,
             predicted_boxes: VecDeque::default(),
             observed_boxes: VecDeque::default(),
+            #[cfg(feature = "f16")]
+            compact_predicted_boxes: VecDeque::default(),
+            #[cfg(feature = "f16")]
+            compact_observed_boxes: VecDeque::default(),
             observed_features: VecDeque::default(),
+            observed_epochs: VecDeque::default(),
+            observed_timestamps: VecDeque::default(),
             last_updated_epoch: 0,
+            created_epoch: 0,
+            created_timestamp: 0,
+            wasted_epoch: None,
+            wasted_timestamp: None,
             track_length: 0,
             visual_features_collected_count: 0,
             scene_id: 0,
@@ -70,23 +161,172 @@ impl VisualAttributes {
         }
     }
 
+    /// Attaches the attributes restored from the state to the options of the tracker
+    ///
+    pub(crate) fn set_options(&mut self, opts: Arc<SortAttributesOptions>) {
+        self.opts = opts;
+    }
+
     pub fn update_history(
         &mut self,
         observation_bbox: &Universal2DBox,
         predicted_bbox: &Universal2DBox,
         observation_feature: Option<Feature>,
     ) {
+        let timestamp = self.opts.observation_timestamps().then(wall_clock_ms);
+        if self.track_length == 0 {
+            self.created_epoch = self.last_updated_epoch;
+            self.created_timestamp = timestamp.unwrap_or_else(wall_clock_ms);
+            self.required_track_length = self
+                .opts
+                .required_track_length(self.scene_id, observation_bbox);
+        }
         self.track_length += 1;
 
         self.observed_boxes.push_back(observation_bbox.clone());
         self.predicted_boxes.push_back(predicted_bbox.clone());
+        #[cfg(feature = "f16")]
+        if self.opts.compact_box_history() {
+            compact_history(&mut self.observed_boxes, &mut self.compact_observed_boxes);
+            compact_history(&mut self.predicted_boxes, &mut self.compact_predicted_boxes);
+        }
         self.observed_features.push_back(observation_feature);
+        self.observed_epochs.push_back(self.last_updated_epoch);
+        self.observed_timestamps.extend(timestamp);
+
+        if self.opts.history_length > 0 && self.observed_epochs.len() > self.opts.history_length {
+            self.pop_oldest_boxes();
+            self.observed_features.pop_front();
+            self.observed_epochs.pop_front();
+        }
+        if self.observed_timestamps.len() > self.observed_epochs.len() {
+            self.observed_timestamps.pop_front();
+        }
+    }
+
+    fn pop_oldest_boxes(&mut self) {
+        #[cfg(feature = "f16")]
+        if self.compact_observed_boxes.pop_front().is_some() {
+            self.compact_predicted_boxes.pop_front();
+            return;
+        }
+        self.observed_boxes.pop_front();
+        self.predicted_boxes.pop_front();
+    }
+
+    /// Boxes observed by detector from the oldest to the latest, including the ones kept with half precision
+    ///
+    pub fn observed_boxes_history(&self) -> Vec<Universal2DBox> {
+        let history = self.observed_boxes.iter().cloned();
+        #[cfg(feature = "f16")]
+        let history = self
+            .compact_observed_boxes
+            .iter()
+            .map(Universal2DBox::from)
+            .chain(history);
+        history.collect()
+    }
+
+    /// Boxes predicted by Kalman filter from the oldest to the latest, including the ones kept with half precision
+    ///
+    pub fn predicted_boxes_history(&self) -> Vec<Universal2DBox> {
+        let history = self.predicted_boxes.iter().cloned();
+        #[cfg(feature = "f16")]
+        let history = self
+            .compact_predicted_boxes
+            .iter()
+            .map(Universal2DBox::from)
+            .chain(history);
+        history.collect()
+    }
+
+    /// Evaluates the waste triggers over the full box history of the track
+    ///
+    fn waste_triggered(&self) -> bool {
+        if !self.opts.has_waste_triggers() {
+            return false;
+        }
+        let observed_boxes = self.observed_boxes_history();
+        let predicted_boxes = self.predicted_boxes_history();
+        self.opts.waste_triggered(&WasteContext {
+            scene_id: self.scene_id,
+            current_epoch: self.opts.current_epoch_with_scene(self.scene_id),
+            created_epoch: self.created_epoch,
+            last_updated_epoch: self.last_updated_epoch,
+            track_length: self.track_length,
+            custom_object_id: self.custom_object_id,
+            observed_boxes: &observed_boxes,
+            predicted_boxes: &predicted_boxes,
+            metadata: self.metadata.as_ref(),
+        })
+    }
 
-        if self.opts.history_length > 0 && self.observed_boxes.len() > self.opts.history_length {
-            self.observed_boxes.pop_front();
-            self.predicted_boxes.pop_front();
+    /// Drops the history but the latest entries and the features kept within the attributes
+    ///
+    pub(crate) fn compact(&mut self) {
+        while self.observed_epochs.len() > 1 {
+            self.pop_oldest_boxes();
             self.observed_features.pop_front();
+            self.observed_epochs.pop_front();
         }
+        while self.observed_timestamps.len() > self.observed_epochs.len() {
+            self.observed_timestamps.pop_front();
+        }
+        self.observed_features.iter_mut().for_each(|f| *f = None);
+        self.collected_feature_epochs.clear();
+    }
+
+    /// Extrapolates the Kalman state of the track continued after the idle epochs to the epoch
+    /// preceding `epoch`, so the observation of `epoch` is applied to the state predicted over all
+    /// the elapsed epochs, with the covariance inflated by the motion noise of every epoch, rather
+    /// than to the state of the last update. The state already propagated over the skipped epochs
+    /// is propagated over the remaining ones only.
+    ///
+    pub(crate) fn warm_restart(&mut self, epoch: usize) {
+        let last_epoch = self.last_updated_epoch.max(self.propagated_epoch);
+        self.propagate_state(epoch.saturating_sub(last_epoch + 1));
+    }
+
+    /// The factor the positional metric of the candidate observed at `bbox` is scaled with
+    ///
+    pub(crate) fn congestion_factor(&self, bbox: &Universal2DBox) -> f32 {
+        self.opts.congestion_factor(self.scene_id, bbox)
+    }
+
+    /// The positional metric of the candidate from its squared Mahalanobis distance to the track
+    ///
+    pub(crate) fn mahalanobis_metric(
+        &self,
+        distance: f32,
+        confidence: f32,
+        factor: f32,
+    ) -> Option<f32> {
+        self.opts.mahalanobis_metric(distance, confidence, factor)
+    }
+
+    /// The overrides of the configuration for the predictions of the scene of the track
+    ///
+    pub(crate) fn predict_overrides(&self) -> PredictOverrides {
+        self.opts.predict_overrides(self.scene_id)
+    }
+
+    /// The latest observed and predicted boxes reported for the track, clipped to the frame when
+    /// the frame clamping is enabled
+    ///
+    pub(crate) fn output_boxes(&self) -> (Universal2DBox, Universal2DBox, bool) {
+        let (observed, predicted) = self.last_boxes();
+        self.opts.clamp_output(self.scene_id, &observed, &predicted)
+    }
+
+    /// The latest observed and predicted boxes, synthesized with the
+    /// [history fallback](SortAttributesOptions::history_fallback) when the history is empty
+    ///
+    pub(crate) fn last_boxes(&self) -> (Universal2DBox, Universal2DBox) {
+        let fallback = self.opts.history_fallback();
+        (
+            fallback.last_box(&self.observed_boxes, self.state),
+            fallback.last_box(&self.predicted_boxes, self.state),
+        )
     }
 }
 
@@ -100,17 +340,41 @@ impl TrackAttributesKalmanPrediction for VisualAttributes {
     }
 
     fn get_position_weight(&self) -> f32 {
-        self.opts.position_weight
+        self.opts.kalman_weights(self.scene_id).0
     }
 
     fn get_velocity_weight(&self) -> f32 {
-        self.opts.velocity_weight
+        self.opts.kalman_weights(self.scene_id).1
+    }
+
+    fn motion_model(&self) -> MotionModel {
+        self.opts.motion_model()
+    }
+
+    fn report_innovation(
+        &self,
+        prediction: &KalmanState<{ DIM_2D_BOX_X2 }>,
+        update: &KalmanState<{ DIM_2D_BOX_X2 }>,
+        observation_bbox: &Universal2DBox,
+    ) {
+        self.opts
+            .record_innovation(self.scene_id, prediction, update, observation_bbox);
+    }
+
+    fn state_frame(&self) -> Option<(f32, f32)> {
+        self.opts.state_frame(self.scene_id)
     }
 }
 
 #[derive(Clone, Debug)]
 pub enum VisualSortLookup {
     IdleLookup(u64),
+    /// The tracks of the scene idle for more than the number of epochs
+    LongIdleLookup(u64, usize),
+    /// All the tracks of the scene
+    SceneLookup(u64),
+    /// The tracks of the scene reached the lifetime limit
+    ExpiredLookup(u64),
 }
 
 impl LookupRequest<VisualAttributes, VisualObservationAttributes> for VisualSortLookup {
@@ -129,6 +393,22 @@ impl LookupRequest<VisualAttributes, VisualObservationAttributes> for VisualSort
                             .current_epoch_with_scene(attributes.scene_id)
                             .unwrap()
             }
+            VisualSortLookup::LongIdleLookup(scene_id, epochs) => {
+                *scene_id == attributes.scene_id
+                    && attributes
+                        .opts
+                        .current_epoch_with_scene(attributes.scene_id)
+                        .unwrap()
+                        .saturating_sub(attributes.last_updated_epoch)
+                        > *epochs
+            }
+            VisualSortLookup::SceneLookup(scene_id) => *scene_id == attributes.scene_id,
+            VisualSortLookup::ExpiredLookup(scene_id) => {
+                *scene_id == attributes.scene_id
+                    && attributes
+                        .opts
+                        .track_lifetime_expired(*scene_id, attributes.created_epoch)
+            }
         }
     }
 }
@@ -139,8 +419,19 @@ pub enum VisualAttributesUpdate {
         epoch: usize,
         scene_id: u64,
         custom_object_id: Option<i64>,
+        class_id: Option<i64>,
+        metadata: Option<Value>,
+        overrides: Option<PredictOverrides>,
     },
     VotingType(VotingType),
+    /// Marks the candidate compared with the idle tracks by the appearance only
+    Reactivation(ReactivationOptions),
+    /// Marks the query of the similarity search compared with the tracks by the appearance only
+    Search,
+    /// Extrapolates the Kalman state of the reactivated track to the epoch of the candidate
+    WarmRestart {
+        epoch: usize,
+    },
 }
 
 impl VisualAttributesUpdate {
@@ -153,9 +444,40 @@ impl VisualAttributesUpdate {
             epoch,
             scene_id,
             custom_object_id,
+            class_id: None,
+            metadata: None,
+            overrides: None,
         }
     }
 
+    /// Sets the class label of the observation, applies to the initial update only
+    ///
+    pub fn with_class_id(mut self, class_id: Option<i64>) -> Self {
+        if let Self::Init { class_id: c, .. } = &mut self {
+            *c = class_id;
+        }
+        self
+    }
+
+    /// Attaches the user metadata to the observation, applies to the initial update only
+    ///
+    pub fn with_metadata(mut self, metadata: Option<Value>) -> Self {
+        if let Self::Init { metadata: m, .. } = &mut self {
+            *m = metadata;
+        }
+        self
+    }
+
+    /// Attaches the overrides of the prediction the observation is passed to, applies to the
+    /// initial update only
+    ///
+    pub fn with_overrides(mut self, overrides: Option<PredictOverrides>) -> Self {
+        if let Self::Init { overrides: o, .. } = &mut self {
+            *o = overrides;
+        }
+        self
+    }
+
     pub fn new_voting_type(vt: VotingType) -> Self {
         Self::VotingType(vt)
     }
@@ -168,14 +490,31 @@ impl TrackAttributesUpdate<VisualAttributes> for VisualAttributesUpdate {
                 epoch,
                 scene_id,
                 custom_object_id,
+                class_id,
+                metadata,
+                overrides,
             } => {
                 attrs.last_updated_epoch = *epoch;
                 attrs.scene_id = *scene_id;
                 attrs.custom_object_id = *custom_object_id;
+                if let Some(class_id) = class_id {
+                    attrs.class_votes.vote(*class_id, attrs.opts.class_freeze());
+                }
+                merge_metadata(&mut attrs.metadata, metadata);
+                attrs.call_overrides = *overrides;
             }
             VisualAttributesUpdate::VotingType(vt) => {
                 attrs.voting_type = Some(*vt);
             }
+            VisualAttributesUpdate::Reactivation(opts) => {
+                attrs.reactivation = Some(*opts);
+            }
+            VisualAttributesUpdate::Search => {
+                attrs.search = true;
+            }
+            VisualAttributesUpdate::WarmRestart { epoch } => {
+                attrs.warm_restart(*epoch);
+            }
         }
         Ok(())
     }
@@ -186,18 +525,34 @@ impl TrackAttributes<VisualAttributes, VisualObservationAttributes> for VisualAt
     type Lookup = VisualSortLookup;
 
     fn compatible(&self, other: &VisualAttributes) -> bool {
+        if self.search {
+            return self.scene_id == other.scene_id;
+        }
+        if let Some(reactivation) = &self.reactivation {
+            let idle_epochs = self
+                .last_updated_epoch
+                .saturating_sub(other.last_updated_epoch);
+            return self.scene_id == other.scene_id
+                && !self
+                    .opts
+                    .lifetime_expired(other.created_epoch, self.last_updated_epoch)
+                && reactivation.allows(idle_epochs);
+        }
         if self.scene_id == other.scene_id {
-            let o1 = self.predicted_boxes.back().unwrap();
-            let o2 = other.predicted_boxes.back().unwrap();
+            let (_, o1) = self.last_boxes();
+            let (_, o2) = other.last_boxes();
 
             let epoch_delta = (self.last_updated_epoch as i128 - other.last_updated_epoch as i128)
                 .abs()
                 .try_into()
                 .unwrap();
 
-            let center_dist = Universal2DBox::dist_in_2r(o1, o2);
+            let center_dist = Universal2DBox::dist_in_2r(&o1, &o2);
 
             self.opts.max_idle_epochs() >= epoch_delta
+                && !self
+                    .opts
+                    .lifetime_expired(other.created_epoch, self.last_updated_epoch)
                 && self
                     .opts
                     .spatio_temporal_constraints
@@ -211,6 +566,11 @@ impl TrackAttributes<VisualAttributes, VisualObservationAttributes> for VisualAt
         self.last_updated_epoch = other.last_updated_epoch;
         self.custom_object_id = other.custom_object_id;
         self.voting_type = other.voting_type;
+        self.created_epoch = self.created_epoch.min(other.created_epoch);
+        self.created_timestamp = self.created_timestamp.min(other.created_timestamp);
+        self.class_votes
+            .merge(&other.class_votes, self.opts.class_freeze());
+        merge_metadata(&mut self.metadata, &other.metadata);
         Ok(())
     }
 
@@ -218,12 +578,74 @@ impl TrackAttributes<VisualAttributes, VisualObservationAttributes> for VisualAt
         &self,
         _observations: &ObservationsDb<VisualObservationAttributes>,
     ) -> Result<TrackStatus> {
+        if self.opts.vanished_at_exit(
+            self.scene_id,
+            self.last_updated_epoch,
+            self.observed_boxes.back(),
+        ) || self
+            .opts
+            .track_lifetime_expired(self.scene_id, self.created_epoch)
+            || self.waste_triggered()
+        {
+            return Ok(TrackStatus::Wasted);
+        }
         self.opts.baked(self.scene_id, self.last_updated_epoch)
     }
+
+    /// The state is propagated to the epoch preceding `epoch` only: the prediction of `epoch`
+    /// itself is made when the track is matched, so the continuous epochs propagate nothing
+    ///
+    fn on_epoch(&mut self, epoch: usize, scene_id: u64) {
+        let last_epoch = self.last_updated_epoch.max(self.propagated_epoch);
+        if scene_id == self.scene_id
+            && epoch > last_epoch + 1
+            && self.opts.skipped_epochs_prediction()
+        {
+            self.propagate_state(epoch - 1 - last_epoch);
+            self.propagated_epoch = epoch - 1;
+        }
+    }
+
+    fn on_wasted(&mut self) {
+        self.wasted_epoch = self.opts.current_epoch_with_scene(self.scene_id);
+        self.wasted_timestamp = Some(wall_clock_ms());
+    }
+
+    fn scene_calibration(&self) -> Option<Arc<SceneCalibration>> {
+        self.opts.scene_calibration(self.scene_id)
+    }
+
+    /// Both tracks inherit the box history, the counters, the class votes, the metadata and the
+    /// Kalman state. The appearance belongs to the original track: the new track starts without
+    /// the collected features (the feature history keeps its length aligned with the box
+    /// history), the visual distance statistics and the voting type. The custom object id stays
+    /// with the original track.
+    ///
+    fn split(&mut self) -> Result<Self> {
+        let mut forked = self.clone();
+        forked.custom_object_id = None;
+        forked.wasted_epoch = None;
+        forked.wasted_timestamp = None;
+        forked.voting_type = None;
+        forked.observed_features.iter_mut().for_each(|f| *f = None);
+        forked.visual_features_collected_count = 0;
+        forked.collected_feature_epochs.clear();
+        forked.visual_distance_stats = VisualDistanceStats::default();
+        forked.reactivation = None;
+        forked.search = false;
+        Ok(forked)
+    }
+
+    fn schema_name() -> &'static str {
+        "VisualAttributes"
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::track::utils::FromVec;
+    use crate::track::{Feature, TrackAttributes};
+    use crate::trackers::kalman_prediction::TrackAttributesKalmanPrediction;
     use crate::trackers::sort::SortAttributesOptions;
     use crate::trackers::spatio_temporal_constraints::SpatioTemporalConstraints;
     use crate::trackers::visual_sort::track_attributes::VisualAttributes;
@@ -240,7 +662,8 @@ mod tests {
             SpatioTemporalConstraints::default(),
             1.0 / 20.0,
             1.0 / 160.0,
-        );
+        )
+        .with_observation_timestamps();
         let mut attributes = VisualAttributes::new(Arc::new(opts));
         attributes.update_history(
             &BoundingBox::new(0.0, 3.0, 5.0, 7.0).as_xyaah(),
@@ -262,6 +685,83 @@ mod tests {
         assert_eq!(attributes.observed_boxes.len(), 1);
         assert_eq!(attributes.predicted_boxes.len(), 1);
         assert_eq!(attributes.observed_features.len(), 1);
+        assert_eq!(attributes.observed_epochs.len(), 1);
+        assert_eq!(attributes.observed_timestamps.len(), 1);
         assert_eq!(attributes.track_length, 2);
     }
+
+    #[test]
+    fn warm_restart() {
+        let opts = SortAttributesOptions::new(
+            Some(RwLock::new(HashMap::default())),
+            5,
+            1,
+            SpatioTemporalConstraints::default(),
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let mut attributes = VisualAttributes::new(Arc::new(opts));
+        for i in 0..5 {
+            attributes
+                .make_prediction(&BoundingBox::new(10.0 * i as f32, 3.0, 5.0, 7.0).as_xyaah());
+            attributes.last_updated_epoch = i + 1;
+        }
+        let last = attributes.state.unwrap();
+
+        let mut restarted = attributes.clone();
+        restarted.warm_restart(6);
+        assert_eq!(restarted.state.unwrap().mean(), last.mean());
+
+        restarted.warm_restart(10);
+        let state = restarted.state.unwrap();
+        let velocity = last.mean()[5];
+        assert!(velocity > 0.0);
+        assert!((state.mean()[0] - (last.mean()[0] + 4.0 * velocity)).abs() < 1e-3);
+
+        let mut skipped = attributes.clone();
+        skipped.on_epoch(7, 0);
+        assert_eq!(skipped.state.unwrap().mean(), last.mean());
+        skipped.opts.set_skipped_epochs_prediction(true);
+        skipped.on_epoch(7, 1);
+        assert_eq!(skipped.state.unwrap().mean(), last.mean());
+        skipped.on_epoch(6, 0);
+        assert_eq!(skipped.state.unwrap().mean(), last.mean());
+        skipped.on_epoch(7, 0);
+        assert_eq!(skipped.propagated_epoch, 6);
+        assert!((skipped.state.unwrap().mean()[0] - (last.mean()[0] + velocity)).abs() < 1e-3);
+        skipped.warm_restart(10);
+        assert_eq!(skipped.state.unwrap().mean(), state.mean());
+    }
+
+    #[test]
+    fn split() {
+        let opts = SortAttributesOptions::new(
+            Some(RwLock::new(HashMap::default())),
+            5,
+            3,
+            SpatioTemporalConstraints::default(),
+            1.0 / 20.0,
+            1.0 / 160.0,
+        );
+        let mut attributes = VisualAttributes::new(Arc::new(opts));
+        let bbox = BoundingBox::new(0.0, 3.0, 5.0, 7.0).as_xyaah();
+        attributes.make_prediction(&bbox);
+        attributes.update_history(&bbox, &bbox, Some(Feature::from_vec(vec![1.0, 0.0])));
+        attributes.visual_features_collected_count = 1;
+        attributes.custom_object_id = Some(7);
+
+        let forked = attributes.split().unwrap();
+        assert_eq!(forked.observed_boxes, attributes.observed_boxes);
+        assert_eq!(forked.track_length, attributes.track_length);
+        assert_eq!(
+            forked.state.unwrap().mean(),
+            attributes.state.unwrap().mean()
+        );
+        assert_eq!(forked.observed_features.len(), 1);
+        assert!(forked.observed_features[0].is_none());
+        assert_eq!(forked.visual_features_collected_count, 0);
+        assert_eq!(forked.custom_object_id, None);
+        assert!(attributes.observed_features[0].is_some());
+        assert_eq!(attributes.custom_object_id, Some(7));
+    }
 }
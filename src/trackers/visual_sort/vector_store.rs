@@ -0,0 +1,493 @@
+use crate::store::TrackStore;
+use crate::track::notify::ChangeNotifier;
+use crate::track::utils::FromVec;
+use crate::track::{ObservationMetric, Track};
+use crate::trackers::visual_sort::observation_attributes::VisualObservationAttributes;
+use crate::trackers::visual_sort::track_attributes::VisualAttributes;
+use crate::Errors;
+use anyhow::Result;
+use crossbeam::channel::{bounded, Receiver, Sender};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::thread::{spawn, JoinHandle};
+
+/// The number of the upsert requests waiting for the sink before the exports are postponed
+///
+pub const DEFAULT_VECTOR_STORE_QUEUE: usize = 16;
+
+/// The way the features of the track are aggregated into the exported embedding
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TemplateAggregation {
+    /// The exponential moving average of the features, `alpha` is the weight of the new feature
+    Ema { alpha: f32 },
+    /// The feature of the observation with the highest quality collected by the track
+    BestShot,
+}
+
+/// The aggregated embedding of the track with the track metadata pushed to the external vector
+/// database
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackTemplate {
+    pub track_id: u64,
+    pub scene_id: u64,
+    pub custom_object_id: Option<i64>,
+    pub class_id: Option<i64>,
+    /// The aggregated embedding, zero-padded to the multiple of 8 as the features kept by the tracks
+    pub embedding: Vec<f32>,
+    /// The highest quality of the features collected by the track
+    pub quality: f32,
+    /// The number of the features aggregated into the embedding
+    pub features: usize,
+    pub created_timestamp: u64,
+    /// The wall-clock time (milliseconds since UNIX epoch) of the last observation
+    pub updated_timestamp: u64,
+    /// The track is wasted, the template is not updated anymore
+    pub wasted: bool,
+    pub metadata: Option<Value>,
+}
+
+/// The adapter to the external vector database. The sink is called from the background thread of
+/// the [bridge](VectorStoreBridge), so the slow requests don't delay the predictions.
+///
+pub trait VectorStoreSink: Send + Sync {
+    /// Inserts or replaces the templates, the track id is the key of the template
+    ///
+    fn upsert(&self, templates: &[TrackTemplate]) -> Result<()>;
+}
+
+/// The transport that delivers the serialized request to the vector database (REST/gRPC client)
+///
+pub type VectorStoreTransport = Arc<dyn Fn(&str) -> Result<()> + Send + Sync>;
+
+/// Reference sink that serializes the templates into the JSON upsert request
+///
+/// ```json
+/// {"collection": "tracks", "points": [{"id": 1, "vector": [...], "payload": {...}}]}
+/// ```
+///
+/// and hands it to the transport, e.g. the HTTP client posting the body to the vector database.
+///
+#[derive(Clone)]
+pub struct JsonVectorStoreSink {
+    collection: String,
+    transport: VectorStoreTransport,
+}
+
+impl JsonVectorStoreSink {
+    pub fn new(collection: &str, transport: VectorStoreTransport) -> Self {
+        Self {
+            collection: collection.to_string(),
+            transport,
+        }
+    }
+
+    /// Builds the upsert request body
+    ///
+    pub fn request(&self, templates: &[TrackTemplate]) -> Value {
+        let points = templates
+            .iter()
+            .map(|t| {
+                json!({
+                    "id": t.track_id,
+                    "vector": t.embedding,
+                    "payload": {
+                        "scene_id": t.scene_id,
+                        "custom_object_id": t.custom_object_id,
+                        "class_id": t.class_id,
+                        "quality": t.quality,
+                        "features": t.features,
+                        "created_timestamp": t.created_timestamp,
+                        "updated_timestamp": t.updated_timestamp,
+                        "wasted": t.wasted,
+                        "metadata": t.metadata,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({ "collection": self.collection, "points": points })
+    }
+}
+
+impl VectorStoreSink for JsonVectorStoreSink {
+    fn upsert(&self, templates: &[TrackTemplate]) -> Result<()> {
+        (self.transport)(&self.request(templates).to_string())
+    }
+}
+
+/// The notifier that marks the changed tracks to be exported on the next
+/// [sync](VectorStoreBridge::sync)
+///
+#[derive(Clone, Debug, Default)]
+pub struct TemplateNotifier(Arc<Mutex<HashSet<u64>>>);
+
+impl ChangeNotifier for TemplateNotifier {
+    fn send(&mut self, id: u64) {
+        self.0.lock().unwrap().insert(id);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct AggregatedTemplate {
+    embedding: Vec<f32>,
+    quality: f32,
+    features: usize,
+    collected: usize,
+}
+
+enum SinkCommand {
+    Upsert(Vec<TrackTemplate>),
+    Flush(Sender<()>),
+}
+
+/// The bridge that keeps the aggregated embeddings of the tracks in the external vector database.
+///
+/// The tracks changed by the merges are marked by the [notifier](Self::notifier) and exported by
+/// [sync](Self::sync); the wasted tracks are exported for the last time by [waste](Self::waste),
+/// so the gallery of the database can be searched after the tracks are gone from the stores.
+///
+/// The templates are aggregated by the caller and passed to the sink by the background thread
+/// through the bounded queue. When the queue is full, the templates are kept in the backlog and
+/// sent with the next export, so the sink receives the latest template of every track. The queued
+/// templates are passed to the sink before the bridge is dropped.
+///
+pub struct VectorStoreBridge {
+    aggregation: TemplateAggregation,
+    notifier: TemplateNotifier,
+    templates: Mutex<HashMap<u64, AggregatedTemplate>>,
+    backlog: Mutex<Vec<TrackTemplate>>,
+    sender: Option<Sender<SinkCommand>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl VectorStoreBridge {
+    pub fn new(aggregation: TemplateAggregation, sink: Box<dyn VectorStoreSink>) -> Self {
+        Self::new_with_queue(aggregation, sink, DEFAULT_VECTOR_STORE_QUEUE)
+    }
+
+    /// Creates the bridge with the custom length of the queue of the sink
+    ///
+    /// # Parameters
+    /// * `aggregation` - the way the features are aggregated into the embeddings;
+    /// * `sink` - the adapter to the vector database;
+    /// * `queue` - the number of the upsert requests waiting for the sink before the exports
+    ///   are postponed.
+    ///
+    /// # Panics
+    /// * if the EMA alpha is not contained in `(0.0..=1.0)`;
+    /// * if the queue is empty.
+    ///
+    pub fn new_with_queue(
+        aggregation: TemplateAggregation,
+        sink: Box<dyn VectorStoreSink>,
+        queue: usize,
+    ) -> Self {
+        if let TemplateAggregation::Ema { alpha } = aggregation {
+            assert!(
+                alpha > 0.0 && alpha <= 1.0,
+                "EMA alpha must be contained in (0.0..=1.0)"
+            );
+        }
+        assert!(queue > 0, "The queue must hold at least one request");
+        let (sender, receiver) = bounded(queue);
+        Self {
+            aggregation,
+            notifier: TemplateNotifier::default(),
+            templates: Mutex::new(HashMap::default()),
+            backlog: Mutex::new(Vec::default()),
+            sender: Some(sender),
+            worker: Some(spawn(move || Self::run_sink(sink, receiver))),
+        }
+    }
+
+    fn run_sink(sink: Box<dyn VectorStoreSink>, commands: Receiver<SinkCommand>) {
+        for command in commands {
+            match command {
+                SinkCommand::Upsert(templates) => {
+                    if let Err(e) = sink.upsert(&templates) {
+                        warn!("Unable to upsert the track templates. Error is: {:?}", e);
+                    }
+                }
+                SinkCommand::Flush(done) => {
+                    let _ = done.send(());
+                }
+            }
+        }
+    }
+
+    /// Blocks until the templates exported before the call, including the backlog, are passed to
+    /// the sink
+    ///
+    pub fn flush(&self) {
+        let sender = self.sender.as_ref().unwrap();
+        let backlog = std::mem::take(&mut *self.backlog.lock().unwrap());
+        if !backlog.is_empty() && sender.send(SinkCommand::Upsert(backlog)).is_err() {
+            return;
+        }
+        let (done, finished) = bounded(1);
+        if sender.send(SinkCommand::Flush(done)).is_ok() {
+            let _ = finished.recv();
+        }
+    }
+
+    /// The notifier to build the tracks and the stores with, the changed tracks are exported on
+    /// the next sync
+    ///
+    pub fn notifier(&self) -> TemplateNotifier {
+        self.notifier.clone()
+    }
+
+    /// Exports the changed tracks kept by the store, the changed tracks missing in the store are
+    /// skipped
+    ///
+    /// # Returns
+    /// * `Ok(n)` - the number of the templates queued for the sink;
+    /// * `Err(e)` - with [VectorStoreBacklog](Errors::VectorStoreBacklog) if the queue is full,
+    ///   the templates are sent with the next export.
+    ///
+    pub fn sync<M, N>(
+        &self,
+        store: &TrackStore<VisualAttributes, M, VisualObservationAttributes, N>,
+    ) -> Result<usize>
+    where
+        M: ObservationMetric<VisualAttributes, VisualObservationAttributes>,
+        N: ChangeNotifier,
+    {
+        let changed = std::mem::take(&mut *self.notifier.0.lock().unwrap());
+        let templates = changed
+            .into_iter()
+            .flat_map(|track_id| {
+                let shard = store.get_store(track_id as usize);
+                shard
+                    .get(&track_id)
+                    .and_then(|track| self.aggregate(track, false))
+            })
+            .collect::<Vec<_>>();
+        self.push(templates)
+    }
+
+    /// Exports the final templates of the wasted tracks and forgets their aggregated embeddings
+    ///
+    /// # Returns
+    /// the same as [sync](Self::sync)
+    ///
+    pub fn waste<M, N>(
+        &self,
+        tracks: &[Track<VisualAttributes, M, VisualObservationAttributes, N>],
+    ) -> Result<usize>
+    where
+        M: ObservationMetric<VisualAttributes, VisualObservationAttributes>,
+        N: ChangeNotifier,
+    {
+        let templates = tracks
+            .iter()
+            .flat_map(|track| self.aggregate(track, true))
+            .collect::<Vec<_>>();
+        let mut aggregated = self.templates.lock().unwrap();
+        let mut changed = self.notifier.0.lock().unwrap();
+        for track in tracks {
+            aggregated.remove(&track.get_track_id());
+            changed.remove(&track.get_track_id());
+        }
+        drop((aggregated, changed));
+        self.push(templates)
+    }
+
+    fn push(&self, templates: Vec<TrackTemplate>) -> Result<usize> {
+        let mut backlog = self.backlog.lock().unwrap();
+        // the postponed templates are replaced with the fresh ones of the same tracks
+        let latest = std::mem::take(&mut *backlog)
+            .into_iter()
+            .chain(templates)
+            .map(|t| (t.track_id, t))
+            .collect::<HashMap<_, _>>();
+        if latest.is_empty() {
+            return Ok(0);
+        }
+        let mut templates = latest.into_values().collect::<Vec<_>>();
+        templates.sort_by_key(|t| t.track_id);
+        let len = templates.len();
+        match self
+            .sender
+            .as_ref()
+            .unwrap()
+            .try_send(SinkCommand::Upsert(templates))
+        {
+            Ok(()) => Ok(len),
+            Err(e) => {
+                if let SinkCommand::Upsert(templates) = e.into_inner() {
+                    *backlog = templates;
+                }
+                Err(Errors::VectorStoreBacklog(len).into())
+            }
+        }
+    }
+
+    /// Folds the features collected since the previous export into the embedding of the track
+    ///
+    /// # Returns
+    /// the template, `None` if the track has never collected a feature
+    ///
+    fn aggregate<M, N>(
+        &self,
+        track: &Track<VisualAttributes, M, VisualObservationAttributes, N>,
+        wasted: bool,
+    ) -> Option<TrackTemplate>
+    where
+        M: ObservationMetric<VisualAttributes, VisualObservationAttributes>,
+        N: ChangeNotifier,
+    {
+        let attrs = track.get_attributes();
+        let mut templates = self.templates.lock().unwrap();
+        let aggregated = templates.entry(track.get_track_id()).or_default();
+
+        match self.aggregation {
+            TemplateAggregation::Ema { alpha } => {
+                let fresh = attrs
+                    .visual_features_collected_count
+                    .saturating_sub(aggregated.collected);
+                let mut features = attrs
+                    .observed_features
+                    .iter()
+                    .rev()
+                    .flatten()
+                    .take(fresh)
+                    .collect::<Vec<_>>();
+                features.reverse();
+                for f in features {
+                    let f = Vec::<f32>::from_vec(f);
+                    if aggregated.embedding.len() == f.len() {
+                        for (e, v) in aggregated.embedding.iter_mut().zip(f) {
+                            *e = (1.0 - alpha) * *e + alpha * v;
+                        }
+                    } else {
+                        aggregated.embedding = f;
+                    }
+                    aggregated.features += 1;
+                }
+                aggregated.quality = track
+                    .get_observations(0)
+                    .into_iter()
+                    .flatten()
+                    .filter(|o| o.feature().is_some())
+                    .flat_map(|o| o.attr().as_ref().map(|a| a.visual_quality()))
+                    .fold(aggregated.quality, f32::max);
+            }
+            TemplateAggregation::BestShot => {
+                let best = track
+                    .get_observations(0)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|o| match (o.attr(), o.feature()) {
+                        (Some(a), Some(f)) => Some((a.visual_quality(), f)),
+                        _ => None,
+                    })
+                    .max_by(|(q1, _), (q2, _)| q1.total_cmp(q2));
+                if let Some((quality, f)) = best {
+                    if aggregated.embedding.is_empty() || quality > aggregated.quality {
+                        aggregated.embedding = Vec::<f32>::from_vec(f);
+                        aggregated.quality = quality;
+                    }
+                }
+                aggregated.features = attrs.visual_features_collected_count;
+            }
+        }
+        aggregated.collected = attrs.visual_features_collected_count;
+
+        if aggregated.embedding.is_empty() {
+            return None;
+        }
+
+        Some(TrackTemplate {
+            track_id: track.get_track_id(),
+            scene_id: attrs.scene_id,
+            custom_object_id: attrs.custom_object_id,
+            class_id: attrs.class_votes.majority().map(|c| c.class_id),
+            embedding: aggregated.embedding.clone(),
+            quality: aggregated.quality,
+            features: aggregated.features,
+            created_timestamp: attrs.created_timestamp,
+            updated_timestamp: attrs
+                .observed_timestamps
+                .back()
+                .copied()
+                .unwrap_or(attrs.created_timestamp),
+            wasted,
+            metadata: attrs.metadata.clone(),
+        })
+    }
+}
+
+impl Drop for VectorStoreBridge {
+    fn drop(&mut self) {
+        self.flush();
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::visual_sort::vector_store::{
+        JsonVectorStoreSink, TemplateAggregation, TrackTemplate, VectorStoreBridge, VectorStoreSink,
+    };
+    use anyhow::Result;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct MemorySink(Arc<Mutex<Vec<TrackTemplate>>>);
+
+    impl VectorStoreSink for MemorySink {
+        fn upsert(&self, templates: &[TrackTemplate]) -> Result<()> {
+            self.0.lock().unwrap().extend_from_slice(templates);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn json_request() {
+        let requests = Arc::new(Mutex::new(Vec::default()));
+        let sent = requests.clone();
+        let sink = JsonVectorStoreSink::new(
+            "tracks",
+            Arc::new(move |body: &str| {
+                sent.lock().unwrap().push(body.to_string());
+                Ok(())
+            }),
+        );
+        let template = TrackTemplate {
+            track_id: 3,
+            scene_id: 1,
+            custom_object_id: None,
+            class_id: Some(2),
+            embedding: vec![1.0, 0.0],
+            quality: 0.5,
+            features: 1,
+            created_timestamp: 10,
+            updated_timestamp: 20,
+            wasted: false,
+            metadata: None,
+        };
+        sink.upsert(&[template]).unwrap();
+        let body: serde_json::Value = serde_json::from_str(&requests.lock().unwrap()[0]).unwrap();
+        assert_eq!(body["collection"], "tracks");
+        assert_eq!(body["points"][0]["id"], 3);
+        assert_eq!(body["points"][0]["vector"][0], 1.0);
+        assert_eq!(body["points"][0]["payload"]["class_id"], 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_alpha() {
+        VectorStoreBridge::new(
+            TemplateAggregation::Ema { alpha: 0.0 },
+            Box::<MemorySink>::default(),
+        );
+    }
+}
@@ -1,18 +1,104 @@
+use crate::store::TrackStore;
+use crate::track::notify::ChangeNotifier;
 use crate::track::ObservationMetricOk;
+use crate::trackers::predict_overrides::PredictOverrides;
 use crate::trackers::sort::voting::SortVoting;
-use crate::trackers::sort::VotingType;
+use crate::trackers::sort::{SortAttributesOptions, VotingType};
+use crate::trackers::visual_sort::metric::{VisualMetric, VisualMetricOptions};
 use crate::trackers::visual_sort::observation_attributes::VisualObservationAttributes;
+use crate::trackers::visual_sort::track_attributes::VisualAttributes;
 use crate::utils::bbox::Universal2DBox;
 use crate::voting::best::BestFitVoting;
-use crate::voting::Voting;
+use crate::voting::{Voting, VotingGate};
 use itertools::Itertools;
 use log::debug;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
+/// How the visual and the positional distances are combined by the voting
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum VisualFusion {
+    /// The candidates are associated by the features first, the remaining ones are associated by
+    /// the positional metric
+    #[default]
+    Sequential,
+    /// The tracks of every candidate are ranked separately by the closest feature and by the
+    /// positional metric, the ranks are combined with the reciprocal rank fusion
+    /// `sum(1 / (k + rank))` and the pairs with the best scores are associated. The scales of the
+    /// distances don't matter, only their order does.
+    ReciprocalRank(f32),
+}
+
+impl VisualFusion {
+    /// The reciprocal rank fusion with the smoothing constant `k`, `60` is the common choice
+    ///
+    pub fn reciprocal_rank(k: f32) -> Self {
+        assert!(k >= 0.0, "The constant must be a non-negative number");
+        VisualFusion::ReciprocalRank(k)
+    }
+}
+
 pub struct VisualVoting {
     positional_threshold: f32,
     max_allowed_feature_distance: f32,
     min_winner_feature_votes: usize,
+    track_min_votes: HashMap<u64, usize>,
+    fusion: VisualFusion,
+    gate: Option<VotingGate<VisualObservationAttributes>>,
+}
+
+/// The visual votes the track must collect to be associated by the features. When
+/// [visual_min_votes_fraction](crate::trackers::visual_sort::options::VisualSortOptions::visual_min_votes_fraction)
+/// is set, the votes are scaled with the number of the features the track keeps, so the tracks
+/// keeping fewer features than `visual_min_votes` still can be associated by the features. The
+/// `overrides` of the prediction are layered over the overrides of the scene.
+///
+pub(crate) fn effective_min_votes(
+    attrs: &VisualAttributes,
+    metric_opts: &VisualMetricOptions,
+    track_opts: &SortAttributesOptions,
+    overrides: Option<PredictOverrides>,
+) -> usize {
+    let min_votes = track_opts
+        .predict_overrides(attrs.scene_id)
+        .layered(overrides)
+        .visual_min_votes(metric_opts.visual_min_votes);
+    match metric_opts.visual_min_votes_fraction {
+        Some(fraction) => {
+            let stored = attrs.visual_features_collected_count as f32;
+            ((fraction * stored).ceil() as usize).max(1).min(min_votes)
+        }
+        None => min_votes,
+    }
+}
+
+/// Computes the effective visual votes of the destination tracks of the distances, the map is
+/// empty unless the votes are scaled
+///
+pub(crate) fn track_min_votes<N: ChangeNotifier>(
+    store: &TrackStore<VisualAttributes, VisualMetric, VisualObservationAttributes, N>,
+    distances: &[ObservationMetricOk<VisualObservationAttributes>],
+    metric_opts: &VisualMetricOptions,
+    track_opts: &SortAttributesOptions,
+    overrides: Option<PredictOverrides>,
+) -> HashMap<u64, usize> {
+    if metric_opts.visual_min_votes_fraction.is_none() {
+        return HashMap::default();
+    }
+    let tracks = distances.iter().map(|d| d.to).collect::<HashSet<_>>();
+    tracks
+        .into_iter()
+        .filter_map(|track_id| {
+            let shard = store.get_store(track_id as usize);
+            shard.get(&track_id).map(|t| {
+                (
+                    track_id,
+                    effective_min_votes(t.get_attributes(), metric_opts, track_opts, overrides),
+                )
+            })
+        })
+        .collect()
 }
 
 impl VisualVoting {
@@ -25,7 +111,147 @@ impl VisualVoting {
             positional_threshold,
             max_allowed_feature_distance,
             min_winner_feature_votes,
+            track_min_votes: HashMap::default(),
+            fusion: VisualFusion::default(),
+            gate: None,
+        }
+    }
+
+    /// Sets how the visual and the positional distances are combined
+    ///
+    pub fn with_fusion(mut self, fusion: VisualFusion) -> Self {
+        self.fusion = fusion;
+        self
+    }
+
+    /// Overrides the visual votes required by the destination tracks
+    ///
+    pub fn with_track_min_votes(mut self, track_min_votes: HashMap<u64, usize>) -> Self {
+        self.track_min_votes = track_min_votes;
+        self
+    }
+
+    /// Sets the gating predicate, the distances rejected by the predicate don't participate in the voting
+    ///
+    pub fn with_gate(mut self, gate: Option<VotingGate<VisualObservationAttributes>>) -> Self {
+        self.gate = gate;
+        self
+    }
+
+    /// Votes for the reactivation of the idle tracks by the features only, look at
+    /// [ReactivationOptions](crate::trackers::visual_sort::reactivation::ReactivationOptions)
+    ///
+    /// # Parameters
+    /// * `distances` - the appearance distances between the unmatched candidates and the idle tracks
+    /// * `min_votes` - the visual votes the idle track must collect to be reactivated
+    /// * `occupied_tracks` - the tracks already chosen by the other candidates
+    ///
+    pub fn reactivation_winners<T>(
+        &self,
+        distances: T,
+        min_votes: usize,
+        occupied_tracks: &HashSet<u64>,
+    ) -> HashMap<u64, Vec<(u64, VotingType)>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<VisualObservationAttributes>>,
+    {
+        let voting: BestFitVoting<VisualObservationAttributes> =
+            BestFitVoting::new(self.max_allowed_feature_distance, min_votes);
+        voting
+            .winners(distances.into_iter().filter(|e| {
+                !occupied_tracks.contains(&e.to)
+                    && self.gate.as_ref().map(|gate| gate(e)).unwrap_or(true)
+            }))
+            .into_iter()
+            .filter(|(from, w)| w[0].winner_track != *from)
+            .map(|(from, w)| (from, vec![(w[0].winner_track, VotingType::Visual)]))
+            .collect()
+    }
+
+    fn rank_fusion_winners<T>(&self, distances: T, k: f32) -> HashMap<u64, Vec<(u64, VotingType)>>
+    where
+        T: IntoIterator<Item = ObservationMetricOk<VisualObservationAttributes>>,
+    {
+        // (votes, closest feature distance, positional metric) of the pairs
+        let mut pairs: HashMap<(u64, u64), (usize, f32, Option<f32>)> = HashMap::new();
+        for e in distances
+            .into_iter()
+            .filter(|e| self.gate.as_ref().map(|gate| gate(e)).unwrap_or(true))
+        {
+            let pair = pairs.entry((e.from, e.to)).or_insert((0, f32::MAX, None));
+            if let Some(d) = e
+                .feature_distance
+                .filter(|d| *d <= self.max_allowed_feature_distance)
+            {
+                pair.0 += 1;
+                pair.1 = pair.1.min(d);
+            }
+            if let Some(m) = e
+                .attribute_metric
+                .filter(|m| *m >= self.positional_threshold)
+            {
+                pair.2 = Some(pair.2.map_or(m, |p: f32| p.max(m)));
+            }
+        }
+
+        let mut candidates = HashMap::<u64, Vec<_>>::new();
+        for ((from, to), (votes, distance, metric)) in pairs {
+            let min_votes = self
+                .track_min_votes
+                .get(&to)
+                .copied()
+                .unwrap_or(self.min_winner_feature_votes);
+            let distance = Some(distance).filter(|_| votes >= min_votes);
+            if distance.is_some() || metric.is_some() {
+                candidates
+                    .entry(from)
+                    .or_default()
+                    .push((to, distance, metric));
+            }
+        }
+
+        let mut scores = Vec::new();
+        for (from, tracks) in candidates {
+            let rank = |track: u64, ordered: &[(u64, f32)]| {
+                ordered
+                    .iter()
+                    .position(|(t, _)| *t == track)
+                    .map(|r| 1.0 / (k + r as f32 + 1.0))
+                    .unwrap_or(0.0)
+            };
+            let by_distance = tracks
+                .iter()
+                .flat_map(|(t, d, _)| d.map(|d| (*t, d)))
+                .sorted_by(|l, r| l.1.total_cmp(&r.1).then(l.0.cmp(&r.0)))
+                .collect::<Vec<_>>();
+            let by_metric = tracks
+                .iter()
+                .flat_map(|(t, _, m)| m.map(|m| (*t, m)))
+                .sorted_by(|l, r| r.1.total_cmp(&l.1).then(l.0.cmp(&r.0)))
+                .collect::<Vec<_>>();
+            for (to, distance, _) in &tracks {
+                let score = rank(*to, &by_distance) + rank(*to, &by_metric);
+                let voting_type = if distance.is_some() {
+                    VotingType::Visual
+                } else {
+                    VotingType::Positional
+                };
+                scores.push((score, from, *to, voting_type));
+            }
+        }
+        scores.sort_by(|l, r| r.0.total_cmp(&l.0).then(l.1.cmp(&r.1)).then(l.2.cmp(&r.2)));
+        debug!("Rank fusion scores: {:#?}", &scores);
+
+        let mut occupied = HashSet::new();
+        let mut winners = HashMap::new();
+        for (_, from, to, voting_type) in scores {
+            if winners.contains_key(&from) || occupied.contains(&to) {
+                continue;
+            }
+            occupied.insert(to);
+            winners.insert(from, vec![(to, voting_type)]);
         }
+        winners
     }
 }
 
@@ -49,12 +275,19 @@ impl Voting<VisualObservationAttributes> for VisualVoting {
     where
         T: IntoIterator<Item = ObservationMetricOk<VisualObservationAttributes>>,
     {
+        if let VisualFusion::ReciprocalRank(k) = self.fusion {
+            return self.rank_fusion_winners(distances, k);
+        }
         let topn_feature_voting: BestFitVoting<VisualObservationAttributes> = BestFitVoting::new(
             self.max_allowed_feature_distance,
             self.min_winner_feature_votes,
-        );
+        )
+        .with_track_min_votes(self.track_min_votes.clone());
 
-        let (distances, distances_clone) = distances.into_iter().tee();
+        let (distances, distances_clone) = distances
+            .into_iter()
+            .filter(|e| self.gate.as_ref().map(|gate| gate(e)).unwrap_or(true))
+            .tee();
 
         let feature_winners = topn_feature_voting.winners(distances);
         debug!("TopN winners: {:#?}", &feature_winners);
@@ -103,8 +336,9 @@ impl Voting<VisualObservationAttributes> for VisualVoting {
 #[cfg(test)]
 mod voting_tests {
     use crate::track::ObservationMetricOk;
-    use crate::trackers::visual_sort::voting::{VisualVoting, VotingType};
+    use crate::trackers::visual_sort::voting::{VisualFusion, VisualVoting, VotingType};
     use crate::voting::Voting;
+    use std::collections::HashSet;
 
     #[test]
     fn test_visual_match() {
@@ -113,6 +347,27 @@ mod voting_tests {
         assert!(matches!(w.get(&1).unwrap()[0].1, VotingType::Visual));
     }
 
+    #[test]
+    fn test_reactivation() {
+        let v = VisualVoting::new(0.3, 0.7, 1);
+        let distances = vec![
+            ObservationMetricOk::new(1, 2, None, Some(0.2)),
+            ObservationMetricOk::new(1, 2, None, Some(0.3)),
+            ObservationMetricOk::new(1, 3, None, Some(0.1)),
+            ObservationMetricOk::new(4, 3, None, Some(0.1)),
+        ];
+        let w = v.reactivation_winners(distances.clone(), 2, &HashSet::new());
+        assert_eq!(w.len(), 1);
+        assert!(matches!(w.get(&1).unwrap()[0], (2, VotingType::Visual)));
+
+        let w = v.reactivation_winners(distances, 1, &HashSet::from([2]));
+        assert_eq!(w.len(), 1);
+        assert!(matches!(
+            w.values().next().unwrap()[0],
+            (3, VotingType::Visual)
+        ));
+    }
+
     #[test]
     fn test_positional_match() {
         let v = VisualVoting::new(0.3, 0.7, 2);
@@ -200,4 +455,21 @@ mod voting_tests {
 
         assert!(w.get(&11).is_none());
     }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let distances = vec![
+            ObservationMetricOk::new(1, 2, Some(0.1), Some(0.2)),
+            ObservationMetricOk::new(1, 3, Some(0.8), Some(0.4)),
+            ObservationMetricOk::new(4, 2, Some(0.5), None),
+        ];
+        let w = VisualVoting::new(0.3, 0.7, 1).winners(distances.clone());
+        assert!(matches!(w.get(&1).unwrap()[0], (2, VotingType::Visual)));
+
+        let v = VisualVoting::new(0.3, 0.7, 1).with_fusion(VisualFusion::reciprocal_rank(60.0));
+        let w = v.winners(distances);
+        assert_eq!(w.len(), 2);
+        assert!(matches!(w.get(&1).unwrap()[0], (3, VotingType::Visual)));
+        assert!(matches!(w.get(&4).unwrap()[0], (2, VotingType::Positional)));
+    }
 }
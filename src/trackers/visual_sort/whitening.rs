@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// The floor of the variance the features are divided by
+///
+const MIN_VARIANCE: f32 = 1e-6;
+
+/// The online estimation of the diagonal covariance of the features of the scene and the
+/// optional whitening of the features at ingestion.
+///
+/// The mean and the variance of every component of the features are the exponential moving
+/// estimates, `alpha` is the weight of the new feature, the estimates are the plain averages
+/// until the scene collects `1 / alpha` features. When `whiten` is set and the scene collected at
+/// least `warmup` features, the features are replaced with `(feature - mean) / std`, so the
+/// Euclidean distances of the cameras with the different embedding statistics are comparable.
+/// The features stored in the tracks are whitened with the estimates of the moment they were
+/// collected.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FeatureWhitening {
+    pub alpha: f32,
+    pub warmup: usize,
+    pub whiten: bool,
+}
+
+impl FeatureWhitening {
+    pub fn new(alpha: f32, warmup: usize, whiten: bool) -> Self {
+        assert!(
+            alpha > 0.0 && alpha <= 1.0,
+            "Alpha must lay between (0.0 and 1.0]"
+        );
+        assert!(warmup > 0, "Warmup must be a positive number");
+        Self {
+            alpha,
+            warmup,
+            whiten,
+        }
+    }
+}
+
+/// The estimated statistics of the features of the scene
+///
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeatureStatistics {
+    mean: Vec<f32>,
+    variance: Vec<f32>,
+    samples: usize,
+}
+
+impl FeatureStatistics {
+    pub fn mean(&self) -> &[f32] {
+        &self.mean
+    }
+
+    /// The diagonal of the covariance matrix
+    ///
+    pub fn variance(&self) -> &[f32] {
+        &self.variance
+    }
+
+    /// The number of the features accounted, the statistics are restarted when the dimension of
+    /// the features changes
+    ///
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    fn record(&mut self, feature: &[f32], alpha: f32) {
+        if feature.len() != self.mean.len() {
+            *self = Self::default();
+            self.mean = vec![0.0; feature.len()];
+            self.variance = vec![0.0; feature.len()];
+        }
+        self.samples += 1;
+        let alpha = alpha.max(1.0 / self.samples as f32);
+        for ((m, v), x) in self.mean.iter_mut().zip(&mut self.variance).zip(feature) {
+            let delta = x - *m;
+            *m += alpha * delta;
+            *v = (1.0 - alpha) * (*v + alpha * delta * delta);
+        }
+    }
+
+    /// Whitens the feature with the statistics
+    ///
+    pub fn whiten(&self, feature: &[f32]) -> Vec<f32> {
+        assert_eq!(
+            feature.len(),
+            self.mean.len(),
+            "The dimension of the feature must match the statistics"
+        );
+        feature
+            .iter()
+            .zip(self.mean.iter().zip(&self.variance))
+            .map(|(x, (m, v))| (x - m) / v.max(MIN_VARIANCE).sqrt())
+            .collect()
+    }
+}
+
+/// The feature statistics of the scenes
+///
+#[derive(Debug, Default)]
+pub struct SceneFeatureStatistics(RwLock<HashMap<u64, FeatureStatistics>>);
+
+impl SceneFeatureStatistics {
+    /// Accounts the feature of the scene
+    ///
+    /// # Returns
+    /// the feature whitened with the updated statistics when the whitening is enabled and the
+    /// scene is warmed up, the feature as is otherwise
+    ///
+    pub fn ingest(&self, scene_id: u64, feature: &[f32], opts: &FeatureWhitening) -> Vec<f32> {
+        let mut scenes = self.0.write().unwrap();
+        let stats = scenes.entry(scene_id).or_default();
+        stats.record(feature, opts.alpha);
+        if opts.whiten && stats.samples >= opts.warmup {
+            stats.whiten(feature)
+        } else {
+            feature.to_vec()
+        }
+    }
+
+    /// Whitens the feature with the current statistics of the scene without accounting it
+    ///
+    pub fn whiten(&self, scene_id: u64, feature: &[f32], opts: &FeatureWhitening) -> Vec<f32> {
+        match self.0.read().unwrap().get(&scene_id) {
+            Some(stats)
+                if opts.whiten
+                    && stats.samples >= opts.warmup
+                    && stats.mean.len() == feature.len() =>
+            {
+                stats.whiten(feature)
+            }
+            _ => feature.to_vec(),
+        }
+    }
+
+    pub fn get(&self, scene_id: u64) -> Option<FeatureStatistics> {
+        self.0.read().unwrap().get(&scene_id).cloned()
+    }
+
+    pub fn reset(&self, scene_id: u64) {
+        self.0.write().unwrap().remove(&scene_id);
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::FeatureWhitening;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "FeatureWhitening")]
+    #[derive(Clone, Copy, Debug)]
+    pub struct PyFeatureWhitening(pub FeatureWhitening);
+
+    #[pymethods]
+    impl PyFeatureWhitening {
+        #[new]
+        #[pyo3(signature = (alpha, warmup, whiten = true))]
+        fn new(alpha: f32, warmup: usize, whiten: bool) -> Self {
+            PyFeatureWhitening(FeatureWhitening::new(alpha, warmup, whiten))
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{self:?}")
+        }
+
+        fn __str__(&self) -> String {
+            format!("{self:#?}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::visual_sort::whitening::{FeatureWhitening, SceneFeatureStatistics};
+    use crate::EPS;
+
+    #[test]
+    fn statistics() {
+        let opts = FeatureWhitening::new(0.5, 3, true);
+        let stats = SceneFeatureStatistics::default();
+        assert_eq!(stats.ingest(1, &[1.0, 10.0], &opts), vec![1.0, 10.0]);
+        assert_eq!(stats.ingest(1, &[3.0, 30.0], &opts), vec![3.0, 30.0]);
+        let s = stats.get(1).unwrap();
+        assert_eq!(s.samples(), 2);
+        assert!((s.mean()[0] - 2.0).abs() < EPS);
+        assert!((s.mean()[1] - 20.0).abs() < EPS);
+        assert!((s.variance()[0] - 1.0).abs() < EPS);
+        assert!((s.variance()[1] - 100.0).abs() < EPS);
+
+        // the components are scaled to the same spread
+        let whitened = stats.ingest(1, &[4.0, 40.0], &opts);
+        assert!(whitened[0] > 0.5);
+        assert!((whitened[0] - whitened[1]).abs() < EPS);
+        assert!(stats.get(2).is_none());
+        assert_eq!(stats.whiten(2, &[2.0, 20.0], &opts), vec![2.0, 20.0]);
+
+        // the dimension change restarts the statistics
+        stats.ingest(1, &[1.0], &opts);
+        assert_eq!(stats.get(1).unwrap().samples(), 1);
+        stats.reset(1);
+        assert!(stats.get(1).is_none());
+    }
+}
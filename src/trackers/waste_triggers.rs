@@ -0,0 +1,294 @@
+use crate::utils::bbox::Universal2DBox;
+#[cfg(feature = "clipping")]
+use geo::{Intersects, LineString, Polygon};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+/// The view of the track the waste triggers are evaluated over
+///
+#[derive(Debug, Clone, Copy)]
+pub struct WasteContext<'a> {
+    pub scene_id: u64,
+    /// The current epoch of the scene, `None` if the scene is unknown
+    pub current_epoch: Option<usize>,
+    pub created_epoch: usize,
+    pub last_updated_epoch: usize,
+    pub track_length: usize,
+    pub custom_object_id: Option<i64>,
+    /// The lastly observed boxes from the oldest to the latest, at most `history_length` of them,
+    /// including the ones kept with half precision
+    pub observed_boxes: &'a [Universal2DBox],
+    /// The lastly predicted boxes from the oldest to the latest, at most `history_length` of them,
+    /// including the ones kept with half precision
+    pub predicted_boxes: &'a [Universal2DBox],
+    pub metadata: Option<&'a Value>,
+}
+
+/// The condition that wastes the track in addition to the idle epochs expiry
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WasteTrigger {
+    /// The last observed box of the track doesn't intersect the region of interest, requires the
+    /// `clipping` feature
+    #[cfg(feature = "clipping")]
+    OutsideRegion(Vec<(f32, f32)>),
+    /// The confidences of the last `epochs` observed boxes are below the `threshold`. The trigger
+    /// never fires when the box history of the tracker is shorter than `epochs`.
+    LowConfidence { threshold: f32, epochs: usize },
+}
+
+impl WasteTrigger {
+    /// The region of interest defined by at least 3 vertices
+    ///
+    #[cfg(feature = "clipping")]
+    pub fn outside_region(vertices: &[(f32, f32)]) -> Self {
+        assert!(
+            vertices.len() >= 3,
+            "The region must be defined by at least 3 vertices"
+        );
+        WasteTrigger::OutsideRegion(vertices.to_vec())
+    }
+
+    pub fn low_confidence(threshold: f32, epochs: usize) -> Self {
+        assert!(epochs > 0, "The number of epochs must be a positive number");
+        WasteTrigger::LowConfidence { threshold, epochs }
+    }
+
+    pub fn triggered(&self, context: &WasteContext) -> bool {
+        match self {
+            #[cfg(feature = "clipping")]
+            WasteTrigger::OutsideRegion(vertices) => context
+                .observed_boxes
+                .last()
+                .map(|bbox| {
+                    let region = Polygon::new(
+                        LineString::from(
+                            vertices
+                                .iter()
+                                .map(|(x, y)| (*x as f64, *y as f64))
+                                .collect::<Vec<_>>(),
+                        ),
+                        vec![],
+                    );
+                    !region.intersects(&Polygon::from(bbox))
+                })
+                .unwrap_or(false),
+            WasteTrigger::LowConfidence { threshold, epochs } => {
+                context.observed_boxes.len() >= *epochs
+                    && context
+                        .observed_boxes
+                        .iter()
+                        .rev()
+                        .take(*epochs)
+                        .all(|b| b.confidence < *threshold)
+            }
+        }
+    }
+}
+
+/// The custom waste condition
+///
+#[derive(Clone)]
+pub struct WastePredicate(Arc<dyn Fn(&WasteContext) -> bool + Send + Sync>);
+
+impl WastePredicate {
+    pub fn new(predicate: impl Fn(&WasteContext) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(predicate))
+    }
+}
+
+impl Debug for WastePredicate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("WastePredicate")
+    }
+}
+
+/// The waste triggers of the scenes.
+///
+/// The triggers are evaluated when the status of the track is checked during the waste sweep of
+/// its scene, the track is wasted when any of the triggers registered for all the scenes or for
+/// its scene fires. The track keeps being matched until the sweep. The custom predicates are not
+/// serialized, so they are not kept in the tracker state and must be registered again after the
+/// restore.
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WasteTriggers {
+    triggers: Vec<(Option<u64>, WasteTrigger)>,
+    #[serde(skip)]
+    predicates: Vec<(Option<u64>, WastePredicate)>,
+}
+
+impl WasteTriggers {
+    /// Adds the trigger for all the scenes
+    ///
+    pub fn trigger(mut self, trigger: WasteTrigger) -> Self {
+        self.triggers.push((None, trigger));
+        self
+    }
+
+    /// Adds the trigger for the scene
+    ///
+    pub fn scene_trigger(mut self, scene_id: u64, trigger: WasteTrigger) -> Self {
+        self.triggers.push((Some(scene_id), trigger));
+        self
+    }
+
+    /// Adds the custom predicate for all the scenes
+    ///
+    pub fn predicate(
+        mut self,
+        predicate: impl Fn(&WasteContext) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.predicates.push((None, WastePredicate::new(predicate)));
+        self
+    }
+
+    /// Adds the custom predicate for the scene
+    ///
+    pub fn scene_predicate(
+        mut self,
+        scene_id: u64,
+        predicate: impl Fn(&WasteContext) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.predicates
+            .push((Some(scene_id), WastePredicate::new(predicate)));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triggers.is_empty() && self.predicates.is_empty()
+    }
+
+    /// Checks if any of the triggers of the track scene fires
+    ///
+    pub fn triggered(&self, context: &WasteContext) -> bool {
+        let applies = |scene_id: &Option<u64>| scene_id.map_or(true, |s| s == context.scene_id);
+        self.triggers
+            .iter()
+            .any(|(s, t)| applies(s) && t.triggered(context))
+            || self
+                .predicates
+                .iter()
+                .any(|(s, p)| applies(s) && (p.0)(context))
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::{WasteTrigger, WasteTriggers};
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "WasteTriggers")]
+    #[derive(Debug, Clone, Default)]
+    pub struct PyWasteTriggers(pub(crate) WasteTriggers);
+
+    impl PyWasteTriggers {
+        fn add(&mut self, scene_id: Option<u64>, trigger: WasteTrigger) {
+            let triggers = self.0.clone();
+            self.0 = match scene_id {
+                Some(scene_id) => triggers.scene_trigger(scene_id, trigger),
+                None => triggers.trigger(trigger),
+            };
+        }
+    }
+
+    #[pymethods]
+    impl PyWasteTriggers {
+        #[new]
+        pub(crate) fn new() -> Self {
+            Self::default()
+        }
+
+        #[pyo3(signature = (vertices, scene_id = None))]
+        pub(crate) fn outside_region(&mut self, vertices: Vec<(f32, f32)>, scene_id: Option<u64>) {
+            self.add(scene_id, WasteTrigger::outside_region(&vertices));
+        }
+
+        #[pyo3(signature = (threshold, epochs, scene_id = None))]
+        pub(crate) fn low_confidence(
+            &mut self,
+            threshold: f32,
+            epochs: usize,
+            scene_id: Option<u64>,
+        ) {
+            self.add(scene_id, WasteTrigger::low_confidence(threshold, epochs));
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "clipping")]
+    use crate::trackers::waste_triggers::WasteTrigger;
+    use crate::trackers::waste_triggers::{WasteContext, WasteTriggers};
+    #[cfg(feature = "clipping")]
+    use crate::utils::bbox::BoundingBox;
+    use crate::utils::bbox::Universal2DBox;
+
+    fn context(scene_id: u64, boxes: &[Universal2DBox]) -> WasteContext<'_> {
+        WasteContext {
+            scene_id,
+            current_epoch: Some(10),
+            created_epoch: 1,
+            last_updated_epoch: 10,
+            track_length: boxes.len(),
+            custom_object_id: None,
+            observed_boxes: boxes,
+            predicted_boxes: boxes,
+            metadata: None,
+        }
+    }
+
+    #[cfg(feature = "clipping")]
+    #[test]
+    fn triggers() {
+        let triggers = WasteTriggers::default()
+            .scene_trigger(
+                1,
+                WasteTrigger::outside_region(&[(0.0, 0.0), (50.0, 0.0), (50.0, 50.0), (0.0, 50.0)]),
+            )
+            .trigger(WasteTrigger::low_confidence(0.3, 2));
+
+        let inside = BoundingBox::new_with_confidence(10.0, 10.0, 10.0, 10.0, 0.9).as_xyaah();
+        let outside = BoundingBox::new_with_confidence(70.0, 10.0, 10.0, 10.0, 0.9).as_xyaah();
+        let weak = BoundingBox::new_with_confidence(10.0, 10.0, 10.0, 10.0, 0.1).as_xyaah();
+
+        let boxes = vec![inside.clone()];
+        assert!(!triggers.triggered(&context(1, &boxes)));
+
+        let boxes = vec![inside.clone(), outside];
+        assert!(triggers.triggered(&context(1, &boxes)));
+        assert!(!triggers.triggered(&context(2, &boxes)));
+
+        let boxes = vec![weak.clone()];
+        assert!(!triggers.triggered(&context(2, &boxes)));
+        let boxes = vec![inside, weak.clone(), weak];
+        assert!(triggers.triggered(&context(2, &boxes)));
+    }
+
+    #[test]
+    fn predicates() {
+        let triggers = WasteTriggers::default().scene_predicate(3, |c| c.track_length > 1);
+        let boxes = vec![Universal2DBox::default(); 2];
+        assert!(triggers.triggered(&context(3, &boxes)));
+        assert!(!triggers.triggered(&context(1, &boxes)));
+
+        let restored: WasteTriggers =
+            serde_json::from_str(&serde_json::to_string(&triggers).unwrap()).unwrap();
+        assert!(restored.is_empty());
+    }
+}
@@ -0,0 +1,321 @@
+use crossbeam::channel::{Receiver, RecvTimeoutError, Sender};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::thread::{spawn, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The options of the watchdog of the batch tracker threads.
+///
+/// The worker is stalled when it is busy with the same request for longer than `stall_timeout`,
+/// e.g. when the user metric deadlocks by acquiring the store. The workers are checked every
+/// `check_period`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WatchdogOptions {
+    stall_timeout: Duration,
+    check_period: Duration,
+}
+
+impl WatchdogOptions {
+    pub fn new(stall_timeout: Duration, check_period: Duration) -> Self {
+        assert!(
+            !check_period.is_zero(),
+            "The check period must be a positive duration"
+        );
+        assert!(
+            check_period <= stall_timeout,
+            "The check period must not exceed the stall timeout"
+        );
+        Self {
+            stall_timeout,
+            check_period,
+        }
+    }
+
+    pub fn stall_timeout(&self) -> Duration {
+        self.stall_timeout
+    }
+
+    pub fn check_period(&self) -> Duration {
+        self.check_period
+    }
+}
+
+/// The thread watched by the watchdog
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Worker {
+    /// The computation of the distances by the store shard executors
+    Distances,
+    /// The voting thread with the index
+    Voting(usize),
+}
+
+impl fmt::Display for Worker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Worker::Distances => write!(f, "distances"),
+            Worker::Voting(i) => write!(f, "voting thread {i}"),
+        }
+    }
+}
+
+/// The report of the stalled worker, the worker is reported once per request
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stall {
+    pub worker: Worker,
+    /// The time the worker was busy when the stall was detected
+    pub busy_for: Duration,
+}
+
+#[derive(Debug, Default)]
+struct WatchdogState {
+    /// The start of the request the worker is busy with and the flag of the reported stall
+    busy: HashMap<Worker, (Instant, bool)>,
+    stalls: usize,
+    subscribers: Vec<Sender<Stall>>,
+}
+
+impl WatchdogState {
+    fn check(&mut self, stall_timeout: Duration) {
+        let now = Instant::now();
+        let mut stalled = Vec::default();
+        for (worker, (since, reported)) in self.busy.iter_mut() {
+            let busy_for = now.saturating_duration_since(*since);
+            if !*reported && busy_for > stall_timeout {
+                *reported = true;
+                stalled.push(Stall {
+                    worker: *worker,
+                    busy_for,
+                });
+            }
+        }
+        for stall in stalled {
+            error!(
+                "Watchdog: {} made no progress for {:?}",
+                stall.worker, stall.busy_for
+            );
+            self.stalls += 1;
+            self.subscribers.retain(|s| s.send(stall).is_ok());
+        }
+    }
+}
+
+/// Watches the threads of the batch tracker and reports the ones that made no progress for the
+/// [stall timeout](WatchdogOptions::stall_timeout). The stalls are logged, counted and sent to the
+/// [subscribers](Self::subscribe). The checks run in the background thread stopped when the
+/// watchdog is dropped.
+///
+pub struct Watchdog {
+    opts: WatchdogOptions,
+    state: Arc<Mutex<WatchdogState>>,
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl fmt::Debug for Watchdog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("opts", &self.opts)
+            .finish()
+    }
+}
+
+impl Watchdog {
+    pub fn new(opts: WatchdogOptions) -> Self {
+        let state = Arc::new(Mutex::new(WatchdogState::default()));
+        let (stop, stop_rx) = crossbeam::channel::bounded::<()>(0);
+        let thread_state = state.clone();
+        let thread = spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(opts.check_period) {
+                thread_state.lock().unwrap().check(opts.stall_timeout)
+            }
+        });
+        Self {
+            opts,
+            state,
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn options(&self) -> WatchdogOptions {
+        self.opts
+    }
+
+    /// Marks the worker busy until the guard is dropped
+    ///
+    pub fn busy(&self, worker: Worker) -> WatchdogGuard {
+        self.state
+            .lock()
+            .unwrap()
+            .busy
+            .insert(worker, (Instant::now(), false));
+        WatchdogGuard {
+            worker,
+            state: self.state.clone(),
+        }
+    }
+
+    /// The workers that are currently stalled
+    ///
+    pub fn stalled(&self) -> Vec<Stall> {
+        let now = Instant::now();
+        self.state
+            .lock()
+            .unwrap()
+            .busy
+            .iter()
+            .map(|(worker, (since, _))| Stall {
+                worker: *worker,
+                busy_for: now.saturating_duration_since(*since),
+            })
+            .filter(|s| s.busy_for > self.opts.stall_timeout)
+            .collect()
+    }
+
+    /// The number of the stalls detected since the watchdog was started
+    ///
+    pub fn stalls(&self) -> usize {
+        self.state.lock().unwrap().stalls
+    }
+
+    /// The channel the detected stalls are sent to
+    ///
+    pub fn subscribe(&self) -> Receiver<Stall> {
+        let (tx, rx) = crossbeam::channel::unbounded();
+        self.state.lock().unwrap().subscribers.push(tx);
+        rx
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("Watchdog thread panicked");
+            }
+        }
+    }
+}
+
+/// Marks the worker busy while alive
+///
+pub struct WatchdogGuard {
+    worker: Worker,
+    state: Arc<Mutex<WatchdogState>>,
+}
+
+impl Drop for WatchdogGuard {
+    fn drop(&mut self) {
+        self.state.lock().unwrap().busy.remove(&self.worker);
+    }
+}
+
+/// The watchdog of the batch tracker shared with its threads, enabled and disabled at runtime
+///
+#[cfg(feature = "batch")]
+#[derive(Debug, Default, Clone)]
+pub(crate) struct SharedWatchdog(Arc<std::sync::RwLock<Option<Arc<Watchdog>>>>);
+
+#[cfg(feature = "batch")]
+impl SharedWatchdog {
+    /// Enables (restarting the stall counter) or disables the watchdog
+    ///
+    pub fn set(&self, opts: Option<WatchdogOptions>) {
+        *self.0.write().unwrap() = opts.map(|o| Arc::new(Watchdog::new(o)));
+    }
+
+    pub fn get(&self) -> Option<Arc<Watchdog>> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn options(&self) -> Option<WatchdogOptions> {
+        self.0.read().unwrap().as_ref().map(|w| w.options())
+    }
+
+    pub fn stalls(&self) -> usize {
+        self.0.read().unwrap().as_ref().map_or(0, |w| w.stalls())
+    }
+
+    pub fn subscribe(&self) -> Option<Receiver<Stall>> {
+        self.0.read().unwrap().as_ref().map(|w| w.subscribe())
+    }
+
+    /// Marks the worker busy until the guard is dropped, `None` when the watchdog is disabled
+    ///
+    pub fn watch(&self, worker: Worker) -> Option<WatchdogGuard> {
+        self.0.read().unwrap().as_ref().map(|w| w.busy(worker))
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::WatchdogOptions;
+    use pyo3::prelude::*;
+    use std::time::Duration;
+
+    #[pyclass]
+    #[pyo3(name = "WatchdogOptions")]
+    #[derive(Debug, Clone, Copy)]
+    pub struct PyWatchdogOptions(pub(crate) WatchdogOptions);
+
+    #[pymethods]
+    impl PyWatchdogOptions {
+        /// The durations are in seconds
+        ///
+        #[new]
+        #[pyo3(signature = (stall_timeout, check_period = 0.1))]
+        pub(crate) fn new(stall_timeout: f64, check_period: f64) -> Self {
+            Self(WatchdogOptions::new(
+                Duration::from_secs_f64(stall_timeout),
+                Duration::from_secs_f64(check_period),
+            ))
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trackers::watchdog::{Watchdog, WatchdogOptions, Worker};
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    #[test]
+    fn stalls() {
+        let ms = Duration::from_millis;
+        let watchdog = Watchdog::new(WatchdogOptions::new(ms(30), ms(5)));
+        let stalls = watchdog.subscribe();
+
+        drop(watchdog.busy(Worker::Distances));
+        let guard = watchdog.busy(Worker::Voting(1));
+        sleep(ms(60));
+        assert_eq!(watchdog.stalls(), 1);
+        assert_eq!(watchdog.stalled().len(), 1);
+        let stall = stalls.try_recv().unwrap();
+        assert_eq!(stall.worker, Worker::Voting(1));
+        assert!(stall.busy_for > ms(30));
+
+        // the stall is reported once
+        sleep(ms(20));
+        assert_eq!(watchdog.stalls(), 1);
+        drop(guard);
+        assert!(watchdog.stalled().is_empty());
+    }
+}
@@ -4,6 +4,7 @@ pub mod bbox;
 
 /// Bounding box intersection calculation for oriented bounding boxes
 ///
+#[cfg(feature = "clipping")]
 pub mod clipping;
 
 /// Auxiliary traits implementations for primitive types
@@ -19,3 +20,6 @@ pub mod kalman;
 
 /// 2D Points stuff
 pub mod point;
+
+/// Minimal Arrow IPC streaming format codec
+pub(crate) mod arrow;
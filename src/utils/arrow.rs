@@ -0,0 +1,1279 @@
+use crate::Errors;
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::io::{ErrorKind, Read, Write};
+
+const MESSAGE_SCHEMA: u8 = 1;
+const MESSAGE_RECORD_BATCH: u8 = 3;
+const METADATA_V5: i16 = 4;
+
+const TYPE_INT: u8 = 2;
+const TYPE_FLOATING_POINT: u8 = 3;
+const TYPE_UTF8: u8 = 5;
+const TYPE_BOOL: u8 = 6;
+const TYPE_LIST: u8 = 12;
+const TYPE_STRUCT: u8 = 13;
+const TYPE_FIXED_SIZE_LIST: u8 = 16;
+const TYPE_LARGE_UTF8: u8 = 20;
+const TYPE_LARGE_LIST: u8 = 21;
+
+const PRECISION_SINGLE: i16 = 1;
+const PRECISION_DOUBLE: i16 = 2;
+
+const CONTINUATION: u32 = 0xFFFF_FFFF;
+
+const EXTENSION_NAME: &str = "ARROW:extension:name";
+const JSON_EXTENSION: &str = "arrow.json";
+
+fn malformed(message: impl Into<String>) -> anyhow::Error {
+    Errors::ArrowStream(message.into()).into()
+}
+
+/// Column of the record batch.
+///
+/// Only the types the tracks are exported with are supported. When the stream is read, the
+/// integers of any width are widened to `Int64` or `UInt64`, `LargeUtf8` is read as `Utf8` and
+/// `LargeList` is read as `List`.
+///
+/// `Json` is the `utf8` column marked with the canonical `arrow.json` extension type, it keeps
+/// the values which do not fit the other types.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ArrowArray {
+    UInt64(Vec<Option<u64>>),
+    Int64(Vec<Option<i64>>),
+    Float32(Vec<Option<f32>>),
+    Float64(Vec<Option<f64>>),
+    Boolean(Vec<Option<bool>>),
+    Utf8(Vec<Option<String>>),
+    Json(Vec<Option<String>>),
+    Struct {
+        validity: Vec<bool>,
+        fields: Vec<(String, ArrowArray)>,
+    },
+    List {
+        validity: Vec<bool>,
+        offsets: Vec<usize>,
+        values: Box<ArrowArray>,
+    },
+    FixedSizeList {
+        validity: Vec<bool>,
+        size: usize,
+        values: Box<ArrowArray>,
+    },
+}
+
+impl ArrowArray {
+    /// The list column of the variable length lists
+    ///
+    pub(crate) fn list<T>(
+        lists: Vec<Option<Vec<T>>>,
+        values: impl FnOnce(Vec<Option<T>>) -> ArrowArray,
+    ) -> Self {
+        let mut offsets = vec![0];
+        let validity = lists.iter().map(Option::is_some).collect();
+        let mut items = Vec::new();
+        for l in lists {
+            items.extend(l.into_iter().flatten().map(Some));
+            offsets.push(items.len());
+        }
+        ArrowArray::List {
+            validity,
+            offsets,
+            values: Box::new(values(items)),
+        }
+    }
+
+    /// The list column of the lists of the same length `size`, the null lists are filled with
+    /// the null values
+    ///
+    pub(crate) fn fixed_size_list<T>(
+        size: usize,
+        lists: Vec<Option<Vec<T>>>,
+        values: impl FnOnce(Vec<Option<T>>) -> ArrowArray,
+    ) -> Self {
+        let validity = lists.iter().map(Option::is_some).collect();
+        let mut items = Vec::new();
+        for l in lists {
+            match l {
+                Some(l) => {
+                    assert_eq!(l.len(), size, "All lists must be of the same length");
+                    items.extend(l.into_iter().map(Some))
+                }
+                None => items.extend((0..size).map(|_| None)),
+            }
+        }
+        ArrowArray::FixedSizeList {
+            validity,
+            size,
+            values: Box::new(values(items)),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            ArrowArray::UInt64(v) => v.len(),
+            ArrowArray::Int64(v) => v.len(),
+            ArrowArray::Float32(v) => v.len(),
+            ArrowArray::Float64(v) => v.len(),
+            ArrowArray::Boolean(v) => v.len(),
+            ArrowArray::Utf8(v) | ArrowArray::Json(v) => v.len(),
+            ArrowArray::Struct { validity, .. }
+            | ArrowArray::List { validity, .. }
+            | ArrowArray::FixedSizeList { validity, .. } => validity.len(),
+        }
+    }
+
+    /// The column of the JSON values, the type is inferred from the values:
+    /// * booleans are written as `bool`;
+    /// * numbers are written as `int64` if all of them are signed integers, `uint64` if all of
+    ///   them are unsigned integers and `float64` otherwise;
+    /// * strings are written as `utf8`;
+    /// * objects are written as `struct` with the union of the keys as the fields, the absent
+    ///   keys are null;
+    /// * arrays are written as `list` of the inferred item type;
+    /// * the values of the mixed kinds are written as `arrow.json` strings;
+    /// * the column of the nulls only is written as `utf8`.
+    ///
+    /// So the column of the values of the same serializable type is of the same type unless the
+    /// integers exceed `i64::MAX` or all the values of the optional field are null.
+    ///
+    pub(crate) fn from_json(values: Vec<Value>) -> Self {
+        let all = |kind: fn(&Value) -> bool| values.iter().filter(|v| !v.is_null()).all(kind);
+        if all(Value::is_null) {
+            ArrowArray::Utf8(vec![None; values.len()])
+        } else if all(Value::is_boolean) {
+            ArrowArray::Boolean(values.iter().map(Value::as_bool).collect())
+        } else if all(Value::is_i64) {
+            ArrowArray::Int64(values.iter().map(Value::as_i64).collect())
+        } else if all(Value::is_u64) {
+            ArrowArray::UInt64(values.iter().map(Value::as_u64).collect())
+        } else if all(Value::is_number) {
+            ArrowArray::Float64(values.iter().map(Value::as_f64).collect())
+        } else if all(Value::is_string) {
+            ArrowArray::Utf8(
+                values
+                    .iter()
+                    .map(|v| v.as_str().map(String::from))
+                    .collect(),
+            )
+        } else if all(Value::is_object) {
+            let mut keys = Vec::<String>::new();
+            for k in values
+                .iter()
+                .filter_map(Value::as_object)
+                .flat_map(Map::keys)
+            {
+                if !keys.contains(k) {
+                    keys.push(k.clone());
+                }
+            }
+            let fields = keys
+                .into_iter()
+                .map(|k| {
+                    let column = values
+                        .iter()
+                        .map(|v| v.get(&k).cloned().unwrap_or(Value::Null))
+                        .collect();
+                    (k, ArrowArray::from_json(column))
+                })
+                .collect();
+            ArrowArray::Struct {
+                validity: values.iter().map(|v| !v.is_null()).collect(),
+                fields,
+            }
+        } else if all(Value::is_array) {
+            ArrowArray::list(
+                values
+                    .into_iter()
+                    .map(|v| match v {
+                        Value::Array(items) => Some(items),
+                        _ => None,
+                    })
+                    .collect(),
+                |items| ArrowArray::from_json(items.into_iter().flatten().collect()),
+            )
+        } else {
+            ArrowArray::Json(
+                values
+                    .iter()
+                    .map(|v| (!v.is_null()).then(|| v.to_string()))
+                    .collect(),
+            )
+        }
+    }
+
+    /// The values of the column as JSON, the null values are `Value::Null`, the null fields of
+    /// the structs are omitted
+    ///
+    pub(crate) fn json_values(&self) -> Result<Vec<Value>> {
+        fn values<T>(v: &[Option<T>], f: impl Fn(&T) -> Value) -> Vec<Value> {
+            v.iter()
+                .map(|x| x.as_ref().map(&f).unwrap_or(Value::Null))
+                .collect()
+        }
+
+        Ok(match self {
+            ArrowArray::UInt64(v) => values(v, |x| Value::from(*x)),
+            ArrowArray::Int64(v) => values(v, |x| Value::from(*x)),
+            ArrowArray::Float32(v) => values(v, |x| Value::from(*x as f64)),
+            ArrowArray::Float64(v) => values(v, |x| Value::from(*x)),
+            ArrowArray::Boolean(v) => values(v, |x| Value::from(*x)),
+            ArrowArray::Utf8(v) => values(v, |x| Value::from(x.as_str())),
+            ArrowArray::Json(v) => v
+                .iter()
+                .map(|x| match x {
+                    Some(x) => serde_json::from_str(x)
+                        .map_err(|e| malformed(format!("invalid JSON value: {e}"))),
+                    None => Ok(Value::Null),
+                })
+                .collect::<Result<_>>()?,
+            ArrowArray::Struct { validity, fields } => {
+                let columns = fields
+                    .iter()
+                    .map(|(name, f)| Ok((name, f.json_values()?)))
+                    .collect::<Result<Vec<_>>>()?;
+                if columns.iter().any(|(_, c)| c.len() < validity.len()) {
+                    return Err(malformed("struct field is shorter than the struct"));
+                }
+                validity
+                    .iter()
+                    .enumerate()
+                    .map(|(i, valid)| {
+                        if !valid {
+                            return Value::Null;
+                        }
+                        Value::Object(
+                            columns
+                                .iter()
+                                .filter(|(_, c)| !c[i].is_null())
+                                .map(|(name, c)| (name.to_string(), c[i].clone()))
+                                .collect(),
+                        )
+                    })
+                    .collect()
+            }
+            ArrowArray::List { .. } | ArrowArray::FixedSizeList { .. } => self
+                .lists(|items| Ok(items.json_values()?.into_iter().map(Some).collect()))?
+                .into_iter()
+                .map(|l| l.map(Value::Array).unwrap_or(Value::Null))
+                .collect(),
+        })
+    }
+
+    /// The values of the integer column, the negative values are rejected
+    ///
+    pub(crate) fn u64_values(&self) -> Result<Vec<Option<u64>>> {
+        match self {
+            ArrowArray::UInt64(v) => Ok(v.clone()),
+            ArrowArray::Int64(v) => v
+                .iter()
+                .map(|x| {
+                    x.map(|x| {
+                        u64::try_from(x).map_err(|_| malformed(format!("negative id value {x}")))
+                    })
+                    .transpose()
+                })
+                .collect(),
+            _ => Err(malformed("integer column expected")),
+        }
+    }
+
+    /// The values of the floating point column
+    ///
+    pub(crate) fn f32_values(&self) -> Result<Vec<Option<f32>>> {
+        match self {
+            ArrowArray::Float32(v) => Ok(v.clone()),
+            ArrowArray::Float64(v) => Ok(v.iter().map(|x| x.map(|x| x as f32)).collect()),
+            _ => Err(malformed("floating point column expected")),
+        }
+    }
+
+    /// The lists of the list column, the null items of the lists are rejected
+    ///
+    pub(crate) fn lists<T: Clone>(
+        &self,
+        values: impl FnOnce(&ArrowArray) -> Result<Vec<Option<T>>>,
+    ) -> Result<Vec<Option<Vec<T>>>> {
+        let (validity, bounds, items) = match self {
+            ArrowArray::List {
+                validity,
+                offsets,
+                values: items,
+            } => (
+                validity,
+                offsets.windows(2).map(|w| (w[0], w[1])).collect::<Vec<_>>(),
+                items,
+            ),
+            ArrowArray::FixedSizeList {
+                validity,
+                size,
+                values: items,
+            } => (
+                validity,
+                (0..validity.len())
+                    .map(|i| (i * size, (i + 1) * size))
+                    .collect(),
+                items,
+            ),
+            _ => return Err(malformed("list column expected")),
+        };
+        let items = values(items)?;
+        validity
+            .iter()
+            .zip(bounds)
+            .map(|(valid, (start, end))| {
+                if !valid {
+                    return Ok(None);
+                }
+                items
+                    .get(start..end)
+                    .ok_or_else(|| malformed("list offsets are out of the values"))?
+                    .iter()
+                    .map(|x| x.clone().ok_or_else(|| malformed("null list item")))
+                    .collect::<Result<Vec<_>>>()
+                    .map(Some)
+            })
+            .collect()
+    }
+}
+
+/// Record batches of the stream with the names of the columns
+///
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ArrowTable {
+    pub names: Vec<String>,
+    pub batches: Vec<Vec<ArrowArray>>,
+}
+
+impl ArrowTable {
+    /// The index of the column
+    ///
+    pub(crate) fn column(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+}
+
+/// Writes the columns as the Arrow IPC stream with a single record batch
+///
+pub(crate) fn write_stream<W: Write, S: AsRef<str>>(
+    mut w: W,
+    columns: &[(S, ArrowArray)],
+) -> Result<()> {
+    let rows = columns.first().map(|(_, c)| c.len()).unwrap_or(0);
+    assert!(
+        columns.iter().all(|(_, c)| c.len() == rows),
+        "All columns must be of the same length"
+    );
+    let schema = FbTable::default().with(0, FbValue::I16(0)).with(
+        1,
+        FbValue::Tables(
+            columns
+                .iter()
+                .map(|(n, c)| schema_field(n.as_ref(), c))
+                .collect(),
+        ),
+    );
+    write_message(&mut w, MESSAGE_SCHEMA, schema, &[])?;
+
+    let mut body = Body::default();
+    for (_, c) in columns {
+        body.array(c);
+    }
+    let batch = FbTable::default()
+        .with(0, FbValue::I64(rows as i64))
+        .with(1, FbValue::Structs(body.nodes))
+        .with(2, FbValue::Structs(body.buffers));
+    write_message(&mut w, MESSAGE_RECORD_BATCH, batch, &body.data)?;
+
+    w.write_all(&CONTINUATION.to_le_bytes())?;
+    w.write_all(&0u32.to_le_bytes())?;
+    Ok(())
+}
+
+/// Reads the Arrow IPC stream. Dictionaries and compressed bodies are not supported.
+///
+pub(crate) fn read_stream<R: Read>(mut r: R) -> Result<ArrowTable> {
+    let mut schema = None;
+    let mut table = ArrowTable::default();
+    while let Some((metadata, body)) = read_message(&mut r)? {
+        let message = FbRef::root(&metadata)?;
+        let header = message
+            .table(2)?
+            .ok_or_else(|| malformed("message without header"))?;
+        match message.u8(1)? {
+            MESSAGE_SCHEMA => {
+                let fields = header
+                    .tables(1)?
+                    .into_iter()
+                    .map(parse_field)
+                    .collect::<Result<Vec<_>>>()?;
+                table.names = fields.iter().map(|(n, _)| n.clone()).collect();
+                schema = Some(fields.into_iter().map(|(_, t)| t).collect::<Vec<_>>());
+            }
+            MESSAGE_RECORD_BATCH => {
+                let types = schema
+                    .as_ref()
+                    .ok_or_else(|| malformed("record batch before schema"))?;
+                if header.field(3)?.is_some() {
+                    return Err(malformed("compressed record batches are not supported"));
+                }
+                let mut reader = BatchReader {
+                    body: &body,
+                    nodes: header.structs(1)?.into_iter(),
+                    buffers: header.structs(2)?.into_iter(),
+                };
+                table.batches.push(
+                    types
+                        .iter()
+                        .map(|t| reader.array(t))
+                        .collect::<Result<Vec<_>>>()?,
+                );
+            }
+            t => return Err(malformed(format!("unsupported message type {t}"))),
+        }
+    }
+    if schema.is_none() {
+        return Err(malformed("stream without schema"));
+    }
+    Ok(table)
+}
+
+fn write_message<W: Write>(w: &mut W, header_type: u8, header: FbTable, body: &[u8]) -> Result<()> {
+    let message = FbTable::default()
+        .with(0, FbValue::I16(METADATA_V5))
+        .with(1, FbValue::U8(header_type))
+        .with(2, FbValue::Table(header))
+        .with(3, FbValue::I64(body.len() as i64));
+    let metadata = FbBuilder::finish(&message);
+    w.write_all(&CONTINUATION.to_le_bytes())?;
+    w.write_all(&(metadata.len() as u32).to_le_bytes())?;
+    w.write_all(&metadata)?;
+    w.write_all(body)?;
+    Ok(())
+}
+
+fn read_message<R: Read>(r: &mut R) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+    let mut word = [0u8; 4];
+    match r.read_exact(&mut word) {
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        res => res?,
+    }
+    // streams written before the continuation marker was introduced start with the length
+    if u32::from_le_bytes(word) == CONTINUATION {
+        r.read_exact(&mut word)?;
+    }
+    let len = u32::from_le_bytes(word) as usize;
+    if len == 0 {
+        return Ok(None);
+    }
+    let mut metadata = vec![0; len];
+    r.read_exact(&mut metadata)?;
+    let body_len = FbRef::root(&metadata)?.i64(3)?;
+    let body_len =
+        usize::try_from(body_len).map_err(|_| malformed("negative message body length"))?;
+    let mut body = vec![0; body_len];
+    r.read_exact(&mut body)?;
+    Ok(Some((metadata, body)))
+}
+
+fn schema_field(name: &str, array: &ArrowArray) -> FbTable {
+    let int = |signed| {
+        FbTable::default()
+            .with(0, FbValue::I32(64))
+            .with(1, FbValue::Bool(signed))
+    };
+    let float = |precision| FbTable::default().with(0, FbValue::I16(precision));
+    let (type_id, type_table, children) = match array {
+        ArrowArray::UInt64(_) => (TYPE_INT, int(false), vec![]),
+        ArrowArray::Int64(_) => (TYPE_INT, int(true), vec![]),
+        ArrowArray::Float32(_) => (TYPE_FLOATING_POINT, float(PRECISION_SINGLE), vec![]),
+        ArrowArray::Float64(_) => (TYPE_FLOATING_POINT, float(PRECISION_DOUBLE), vec![]),
+        ArrowArray::Boolean(_) => (TYPE_BOOL, FbTable::default(), vec![]),
+        ArrowArray::Utf8(_) | ArrowArray::Json(_) => (TYPE_UTF8, FbTable::default(), vec![]),
+        ArrowArray::Struct { fields, .. } => (
+            TYPE_STRUCT,
+            FbTable::default(),
+            fields.iter().map(|(n, f)| schema_field(n, f)).collect(),
+        ),
+        ArrowArray::List { values, .. } => (
+            TYPE_LIST,
+            FbTable::default(),
+            vec![schema_field("item", values)],
+        ),
+        ArrowArray::FixedSizeList { size, values, .. } => (
+            TYPE_FIXED_SIZE_LIST,
+            FbTable::default().with(0, FbValue::I32(*size as i32)),
+            vec![schema_field("item", values)],
+        ),
+    };
+    let field = FbTable::default()
+        .with(0, FbValue::Str(name.to_string()))
+        .with(1, FbValue::Bool(true))
+        .with(2, FbValue::U8(type_id))
+        .with(3, FbValue::Table(type_table))
+        .with(5, FbValue::Tables(children));
+    match array {
+        ArrowArray::Json(_) => field.with(
+            6,
+            FbValue::Tables(vec![FbTable::default()
+                .with(0, FbValue::Str(EXTENSION_NAME.to_string()))
+                .with(1, FbValue::Str(JSON_EXTENSION.to_string()))]),
+        ),
+        _ => field,
+    }
+}
+
+#[derive(Debug, Clone)]
+enum DataType {
+    Int { bytes: usize, signed: bool },
+    Float { bytes: usize },
+    Bool,
+    Utf8 { large: bool, json: bool },
+    Struct { fields: Vec<(String, DataType)> },
+    List { large: bool, item: Box<DataType> },
+    FixedSizeList { size: usize, item: Box<DataType> },
+}
+
+fn parse_field(field: FbRef) -> Result<(String, DataType)> {
+    let name = field.string(0)?.unwrap_or_default();
+    if field.field(4)?.is_some() {
+        return Err(malformed(format!(
+            "dictionary encoded column {name} is not supported"
+        )));
+    }
+    let type_table = || {
+        field
+            .table(3)?
+            .ok_or_else(|| malformed(format!("column {name} without type")))
+    };
+    let item = || -> Result<Box<DataType>> {
+        let children = field.tables(5)?;
+        let child = children
+            .first()
+            .ok_or_else(|| malformed(format!("list column {name} without item")))?;
+        Ok(Box::new(parse_field(*child)?.1))
+    };
+    let mut json = false;
+    for kv in field.tables(6)? {
+        json |= kv.string(0)?.as_deref() == Some(EXTENSION_NAME)
+            && kv.string(1)?.as_deref() == Some(JSON_EXTENSION);
+    }
+    let data_type = match field.u8(2)? {
+        TYPE_INT => {
+            let t = type_table()?;
+            let bits = t.i32(0)?;
+            if ![8, 16, 32, 64].contains(&bits) {
+                return Err(malformed(format!("unsupported integer width {bits}")));
+            }
+            DataType::Int {
+                bytes: bits as usize / 8,
+                signed: t.bool(1)?,
+            }
+        }
+        TYPE_FLOATING_POINT => match type_table()?.i16(0)? {
+            PRECISION_SINGLE => DataType::Float { bytes: 4 },
+            PRECISION_DOUBLE => DataType::Float { bytes: 8 },
+            _ => return Err(malformed(format!("half precision column {name}"))),
+        },
+        TYPE_BOOL => DataType::Bool,
+        TYPE_UTF8 => DataType::Utf8 { large: false, json },
+        TYPE_LARGE_UTF8 => DataType::Utf8 { large: true, json },
+        TYPE_STRUCT => DataType::Struct {
+            fields: field
+                .tables(5)?
+                .into_iter()
+                .map(parse_field)
+                .collect::<Result<_>>()?,
+        },
+        TYPE_LIST => DataType::List {
+            large: false,
+            item: item()?,
+        },
+        TYPE_LARGE_LIST => DataType::List {
+            large: true,
+            item: item()?,
+        },
+        TYPE_FIXED_SIZE_LIST => {
+            let size = type_table()?.i32(0)?;
+            DataType::FixedSizeList {
+                size: usize::try_from(size).map_err(|_| malformed("negative list size"))?,
+                item: item()?,
+            }
+        }
+        t => return Err(malformed(format!("unsupported type {t} of column {name}"))),
+    };
+    Ok((name, data_type))
+}
+
+/// The body of the record batch: the buffers and the field nodes in the depth-first order
+///
+#[derive(Default)]
+struct Body {
+    data: Vec<u8>,
+    nodes: Vec<[i64; 2]>,
+    buffers: Vec<[i64; 2]>,
+}
+
+impl Body {
+    fn buffer(&mut self, bytes: &[u8]) {
+        let offset = self.data.len();
+        self.data.extend_from_slice(bytes);
+        self.data.resize((self.data.len() + 7) / 8 * 8, 0);
+        self.buffers.push([offset as i64, bytes.len() as i64]);
+    }
+
+    fn bitmap(bits: impl Iterator<Item = bool>) -> Vec<u8> {
+        let mut bitmap = Vec::new();
+        for (i, bit) in bits.enumerate() {
+            if i % 8 == 0 {
+                bitmap.push(0);
+            }
+            if bit {
+                bitmap[i / 8] |= 1 << (i % 8);
+            }
+        }
+        bitmap
+    }
+
+    fn validity(&mut self, validity: impl ExactSizeIterator<Item = bool> + Clone) {
+        let len = validity.len();
+        let nulls = validity.clone().filter(|valid| !valid).count();
+        self.nodes.push([len as i64, nulls as i64]);
+        if nulls == 0 {
+            self.buffer(&[]);
+        } else {
+            self.buffer(&Self::bitmap(validity));
+        }
+    }
+
+    fn primitive<T: Copy + Default, const N: usize>(
+        &mut self,
+        values: &[Option<T>],
+        to_bytes: fn(T) -> [u8; N],
+    ) {
+        self.validity(values.iter().map(Option::is_some));
+        let bytes = values
+            .iter()
+            .flat_map(|v| to_bytes(v.unwrap_or_default()))
+            .collect::<Vec<_>>();
+        self.buffer(&bytes);
+    }
+
+    fn array(&mut self, array: &ArrowArray) {
+        match array {
+            ArrowArray::UInt64(v) => self.primitive(v, u64::to_le_bytes),
+            ArrowArray::Int64(v) => self.primitive(v, i64::to_le_bytes),
+            ArrowArray::Float32(v) => self.primitive(v, f32::to_le_bytes),
+            ArrowArray::Float64(v) => self.primitive(v, f64::to_le_bytes),
+            ArrowArray::Boolean(v) => {
+                self.validity(v.iter().map(Option::is_some));
+                self.buffer(&Self::bitmap(v.iter().map(|b| b.unwrap_or_default())));
+            }
+            ArrowArray::Struct { validity, fields } => {
+                self.validity(validity.iter().copied());
+                for (_, f) in fields {
+                    self.array(f);
+                }
+            }
+            ArrowArray::Utf8(v) | ArrowArray::Json(v) => {
+                self.validity(v.iter().map(Option::is_some));
+                let mut offsets = vec![0i32];
+                let mut data = Vec::new();
+                for s in v {
+                    data.extend_from_slice(s.as_deref().unwrap_or_default().as_bytes());
+                    offsets.push(data.len() as i32);
+                }
+                let offsets = offsets
+                    .into_iter()
+                    .flat_map(i32::to_le_bytes)
+                    .collect::<Vec<_>>();
+                self.buffer(&offsets);
+                self.buffer(&data);
+            }
+            ArrowArray::List {
+                validity,
+                offsets,
+                values,
+            } => {
+                self.validity(validity.iter().copied());
+                let offsets = offsets
+                    .iter()
+                    .flat_map(|o| (*o as i32).to_le_bytes())
+                    .collect::<Vec<_>>();
+                self.buffer(&offsets);
+                self.array(values);
+            }
+            ArrowArray::FixedSizeList {
+                validity, values, ..
+            } => {
+                self.validity(validity.iter().copied());
+                self.array(values);
+            }
+        }
+    }
+}
+
+struct BatchReader<'a> {
+    body: &'a [u8],
+    nodes: std::vec::IntoIter<[i64; 2]>,
+    buffers: std::vec::IntoIter<[i64; 2]>,
+}
+
+impl<'a> BatchReader<'a> {
+    fn buffer(&mut self) -> Result<&'a [u8]> {
+        let [offset, len] = self
+            .buffers
+            .next()
+            .ok_or_else(|| malformed("missing buffer"))?;
+        let (offset, len) = (offset as usize, len as usize);
+        self.body
+            .get(offset..offset.saturating_add(len))
+            .ok_or_else(|| malformed("buffer is out of the message body"))
+    }
+
+    fn chunks<const N: usize>(&mut self, len: usize) -> Result<Vec<[u8; N]>> {
+        let buffer = self.buffer()?;
+        if buffer.len() < len * N {
+            return Err(malformed("buffer is shorter than the column"));
+        }
+        Ok(buffer
+            .chunks_exact(N)
+            .take(len)
+            .map(|c| c.try_into().unwrap())
+            .collect())
+    }
+
+    fn offsets(&mut self, len: usize, large: bool) -> Result<Vec<usize>> {
+        let offsets = if large {
+            self.chunks::<8>(len + 1)?
+                .into_iter()
+                .map(|c| usize::try_from(i64::from_le_bytes(c)).ok())
+                .collect::<Option<Vec<_>>>()
+        } else {
+            self.chunks::<4>(len + 1)?
+                .into_iter()
+                .map(|c| usize::try_from(i32::from_le_bytes(c)).ok())
+                .collect::<Option<Vec<_>>>()
+        };
+        offsets.ok_or_else(|| malformed("negative offset"))
+    }
+
+    fn array(&mut self, data_type: &DataType) -> Result<ArrowArray> {
+        let [len, nulls] = self
+            .nodes
+            .next()
+            .ok_or_else(|| malformed("missing field node"))?;
+        let len = usize::try_from(len).map_err(|_| malformed("negative column length"))?;
+        let bitmap = self.buffer()?;
+        let validity = if nulls == 0 {
+            vec![true; len]
+        } else if bitmap.len() < (len + 7) / 8 {
+            return Err(malformed("validity bitmap is shorter than the column"));
+        } else {
+            (0..len)
+                .map(|i| bitmap[i / 8] & (1 << (i % 8)) != 0)
+                .collect()
+        };
+        Ok(match data_type {
+            DataType::Int { bytes, signed } => {
+                let values = match bytes {
+                    1 => self
+                        .chunks::<1>(len)?
+                        .into_iter()
+                        .map(|c| (c[0] as i8) as i64)
+                        .collect::<Vec<_>>(),
+                    2 => self
+                        .chunks::<2>(len)?
+                        .into_iter()
+                        .map(|c| i16::from_le_bytes(c) as i64)
+                        .collect(),
+                    4 => self
+                        .chunks::<4>(len)?
+                        .into_iter()
+                        .map(|c| i32::from_le_bytes(c) as i64)
+                        .collect(),
+                    _ => self
+                        .chunks::<8>(len)?
+                        .into_iter()
+                        .map(i64::from_le_bytes)
+                        .collect(),
+                };
+                if *signed {
+                    ArrowArray::Int64(with_validity(values, &validity))
+                } else {
+                    // the sign extension is undone by the mask of the width
+                    let mask = u64::MAX >> (64 - bytes * 8);
+                    ArrowArray::UInt64(with_validity(
+                        values.into_iter().map(|v| v as u64 & mask).collect(),
+                        &validity,
+                    ))
+                }
+            }
+            DataType::Float { bytes: 4 } => ArrowArray::Float32(with_validity(
+                self.chunks::<4>(len)?
+                    .into_iter()
+                    .map(f32::from_le_bytes)
+                    .collect(),
+                &validity,
+            )),
+            DataType::Float { .. } => ArrowArray::Float64(with_validity(
+                self.chunks::<8>(len)?
+                    .into_iter()
+                    .map(f64::from_le_bytes)
+                    .collect(),
+                &validity,
+            )),
+            DataType::Bool => {
+                let bitmap = self.buffer()?;
+                if bitmap.len() < (len + 7) / 8 {
+                    return Err(malformed("boolean buffer is shorter than the column"));
+                }
+                ArrowArray::Boolean(with_validity(
+                    (0..len)
+                        .map(|i| bitmap[i / 8] & (1 << (i % 8)) != 0)
+                        .collect(),
+                    &validity,
+                ))
+            }
+            DataType::Struct { fields } => ArrowArray::Struct {
+                validity,
+                fields: fields
+                    .iter()
+                    .map(|(name, t)| Ok((name.clone(), self.array(t)?)))
+                    .collect::<Result<_>>()?,
+            },
+            DataType::Utf8 { large, json } => {
+                let offsets = self.offsets(len, *large)?;
+                let data = self.buffer()?;
+                let values = offsets
+                    .windows(2)
+                    .map(|w| {
+                        let bytes = data
+                            .get(w[0]..w[1])
+                            .ok_or_else(|| malformed("string offsets are out of the data"))?;
+                        String::from_utf8(bytes.to_vec())
+                            .map_err(|_| malformed("string is not valid UTF-8"))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let values = with_validity(values, &validity);
+                if *json {
+                    ArrowArray::Json(values)
+                } else {
+                    ArrowArray::Utf8(values)
+                }
+            }
+            DataType::List { large, item } => {
+                let offsets = self.offsets(len, *large)?;
+                ArrowArray::List {
+                    validity,
+                    offsets,
+                    values: Box::new(self.array(item)?),
+                }
+            }
+            DataType::FixedSizeList { size, item } => ArrowArray::FixedSizeList {
+                validity,
+                size: *size,
+                values: Box::new(self.array(item)?),
+            },
+        })
+    }
+}
+
+fn with_validity<T>(values: Vec<T>, validity: &[bool]) -> Vec<Option<T>> {
+    values
+        .into_iter()
+        .zip(validity)
+        .map(|(v, valid)| valid.then_some(v))
+        .collect()
+}
+
+/// The value of the flatbuffers table field
+///
+enum FbValue {
+    Bool(bool),
+    U8(u8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    Str(String),
+    Table(FbTable),
+    Tables(Vec<FbTable>),
+    /// The vector of the structs of two longs, e.g. `FieldNode` and `Buffer`
+    Structs(Vec<[i64; 2]>),
+}
+
+impl FbValue {
+    /// The size of the value kept inline in the table, the references are 32 bit offsets
+    ///
+    fn inline_size(&self) -> usize {
+        match self {
+            FbValue::Bool(_) | FbValue::U8(_) => 1,
+            FbValue::I16(_) => 2,
+            FbValue::I64(_) => 8,
+            _ => 4,
+        }
+    }
+}
+
+#[derive(Default)]
+struct FbTable(Vec<(usize, FbValue)>);
+
+impl FbTable {
+    fn with(mut self, slot: usize, value: FbValue) -> Self {
+        self.0.push((slot, value));
+        self
+    }
+}
+
+/// Flatbuffers serializer which places the referenced objects after the referencing tables,
+/// so all the offsets point forward as the format requires
+///
+struct FbBuilder {
+    buf: Vec<u8>,
+}
+
+impl FbBuilder {
+    fn finish(root: &FbTable) -> Vec<u8> {
+        let mut builder = FbBuilder { buf: vec![0; 4] };
+        let root = builder.table(root);
+        builder.patch(0, root);
+        builder.align(8);
+        builder.buf
+    }
+
+    fn align(&mut self, n: usize) {
+        self.buf.resize((self.buf.len() + n - 1) / n * n, 0);
+    }
+
+    fn put(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn patch(&mut self, at: usize, target: usize) {
+        let offset = (target - at) as u32;
+        self.buf[at..at + 4].copy_from_slice(&offset.to_le_bytes());
+    }
+
+    fn table(&mut self, table: &FbTable) -> usize {
+        let slots = table.0.iter().map(|(s, _)| s + 1).max().unwrap_or(0);
+        let mut size = 4;
+        let mut layout = Vec::with_capacity(table.0.len());
+        for (_, value) in &table.0 {
+            let n = value.inline_size();
+            size = (size + n - 1) / n * n;
+            layout.push(size);
+            size += n;
+        }
+
+        self.align(2);
+        let vtable = self.buf.len();
+        let mut entries = vec![0u16; slots];
+        for ((slot, _), offset) in table.0.iter().zip(&layout) {
+            entries[*slot] = *offset as u16;
+        }
+        self.put(&((4 + 2 * slots) as u16).to_le_bytes());
+        self.put(&(size as u16).to_le_bytes());
+        for e in entries {
+            self.put(&e.to_le_bytes());
+        }
+
+        self.align(8);
+        let start = self.buf.len();
+        self.buf.resize(start + size, 0);
+        self.buf[start..start + 4].copy_from_slice(&((start - vtable) as i32).to_le_bytes());
+        let mut references = Vec::new();
+        for ((_, value), offset) in table.0.iter().zip(&layout) {
+            let at = start + offset;
+            let mut set = |bytes: &[u8]| self.buf[at..at + bytes.len()].copy_from_slice(bytes);
+            match value {
+                FbValue::Bool(v) => set(&[*v as u8]),
+                FbValue::U8(v) => set(&[*v]),
+                FbValue::I16(v) => set(&v.to_le_bytes()),
+                FbValue::I32(v) => set(&v.to_le_bytes()),
+                FbValue::I64(v) => set(&v.to_le_bytes()),
+                _ => references.push((at, value)),
+            }
+        }
+        for (at, value) in references {
+            let target = self.object(value);
+            self.patch(at, target);
+        }
+        start
+    }
+
+    fn object(&mut self, value: &FbValue) -> usize {
+        match value {
+            FbValue::Str(s) => {
+                self.align(4);
+                let start = self.buf.len();
+                self.put(&(s.len() as u32).to_le_bytes());
+                self.put(s.as_bytes());
+                self.put(&[0]);
+                start
+            }
+            FbValue::Table(t) => self.table(t),
+            FbValue::Tables(tables) => {
+                self.align(4);
+                let start = self.buf.len();
+                self.put(&(tables.len() as u32).to_le_bytes());
+                self.buf.resize(start + 4 + 4 * tables.len(), 0);
+                for (i, t) in tables.iter().enumerate() {
+                    let target = self.table(t);
+                    self.patch(start + 4 + 4 * i, target);
+                }
+                start
+            }
+            FbValue::Structs(structs) => {
+                // the structs are aligned to 8 bytes after the length
+                self.align(8);
+                self.put(&[0; 4]);
+                let start = self.buf.len();
+                self.put(&(structs.len() as u32).to_le_bytes());
+                for s in structs {
+                    self.put(&s[0].to_le_bytes());
+                    self.put(&s[1].to_le_bytes());
+                }
+                start
+            }
+            _ => unreachable!("Scalars are kept inline"),
+        }
+    }
+}
+
+/// Reference to the flatbuffers table
+///
+#[derive(Clone, Copy)]
+struct FbRef<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> FbRef<'a> {
+    fn root(buf: &'a [u8]) -> Result<Self> {
+        let pos = Self::read_u32(buf, 0)? as usize;
+        Ok(Self { buf, pos })
+    }
+
+    fn bytes<const N: usize>(buf: &[u8], pos: usize) -> Result<[u8; N]> {
+        buf.get(pos..pos.saturating_add(N))
+            .map(|b| b.try_into().unwrap())
+            .ok_or_else(|| malformed("truncated message metadata"))
+    }
+
+    fn read_u32(buf: &[u8], pos: usize) -> Result<u32> {
+        Ok(u32::from_le_bytes(Self::bytes(buf, pos)?))
+    }
+
+    /// The position of the field value, `None` if the field is absent
+    ///
+    fn field(&self, slot: usize) -> Result<Option<usize>> {
+        let soffset = i32::from_le_bytes(Self::bytes(self.buf, self.pos)?) as i64;
+        let vtable = usize::try_from(self.pos as i64 - soffset)
+            .map_err(|_| malformed("vtable is out of the message metadata"))?;
+        let vtable_size = u16::from_le_bytes(Self::bytes(self.buf, vtable)?) as usize;
+        let entry = 4 + 2 * slot;
+        if entry + 2 > vtable_size {
+            return Ok(None);
+        }
+        let offset = u16::from_le_bytes(Self::bytes(self.buf, vtable + entry)?) as usize;
+        Ok((offset != 0).then_some(self.pos + offset))
+    }
+
+    fn scalar<const N: usize>(&self, slot: usize) -> Result<[u8; N]> {
+        match self.field(slot)? {
+            Some(pos) => Self::bytes(self.buf, pos),
+            None => Ok([0; N]),
+        }
+    }
+
+    fn u8(&self, slot: usize) -> Result<u8> {
+        Ok(self.scalar::<1>(slot)?[0])
+    }
+
+    fn bool(&self, slot: usize) -> Result<bool> {
+        Ok(self.u8(slot)? != 0)
+    }
+
+    fn i16(&self, slot: usize) -> Result<i16> {
+        Ok(i16::from_le_bytes(self.scalar(slot)?))
+    }
+
+    fn i32(&self, slot: usize) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.scalar(slot)?))
+    }
+
+    fn i64(&self, slot: usize) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.scalar(slot)?))
+    }
+
+    fn reference(&self, slot: usize) -> Result<Option<usize>> {
+        self.field(slot)?
+            .map(|pos| Ok(pos + Self::read_u32(self.buf, pos)? as usize))
+            .transpose()
+    }
+
+    fn table(&self, slot: usize) -> Result<Option<FbRef<'a>>> {
+        Ok(self
+            .reference(slot)?
+            .map(|pos| FbRef { buf: self.buf, pos }))
+    }
+
+    fn string(&self, slot: usize) -> Result<Option<String>> {
+        self.reference(slot)?
+            .map(|pos| {
+                let len = Self::read_u32(self.buf, pos)? as usize;
+                let bytes = self
+                    .buf
+                    .get(pos + 4..pos + 4 + len)
+                    .ok_or_else(|| malformed("truncated message metadata"))?;
+                String::from_utf8(bytes.to_vec()).map_err(|_| malformed("name is not valid UTF-8"))
+            })
+            .transpose()
+    }
+
+    fn tables(&self, slot: usize) -> Result<Vec<FbRef<'a>>> {
+        let Some(pos) = self.reference(slot)? else {
+            return Ok(vec![]);
+        };
+        let len = Self::read_u32(self.buf, pos)? as usize;
+        (0..len)
+            .map(|i| {
+                let at = pos + 4 + 4 * i;
+                Ok(FbRef {
+                    buf: self.buf,
+                    pos: at + Self::read_u32(self.buf, at)? as usize,
+                })
+            })
+            .collect()
+    }
+
+    fn structs(&self, slot: usize) -> Result<Vec<[i64; 2]>> {
+        let Some(pos) = self.reference(slot)? else {
+            return Ok(vec![]);
+        };
+        let len = Self::read_u32(self.buf, pos)? as usize;
+        (0..len)
+            .map(|i| {
+                let at = pos + 4 + 16 * i;
+                Ok([
+                    i64::from_le_bytes(Self::bytes(self.buf, at)?),
+                    i64::from_le_bytes(Self::bytes(self.buf, at + 8)?),
+                ])
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::arrow::{read_stream, write_stream, ArrowArray};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn round_trip() {
+        let columns = [
+            (
+                "id",
+                ArrowArray::UInt64(vec![Some(1), Some(u64::MAX), None]),
+            ),
+            (
+                "name",
+                ArrowArray::Utf8(vec![Some("a".into()), None, Some("ccc".into())]),
+            ),
+            (
+                "history",
+                ArrowArray::list(
+                    vec![Some(vec![1u64, 2]), Some(vec![]), None],
+                    ArrowArray::UInt64,
+                ),
+            ),
+            (
+                "feature",
+                ArrowArray::fixed_size_list(
+                    2,
+                    vec![Some(vec![0.5f32, 1.0]), None, Some(vec![2.0, 3.0])],
+                    ArrowArray::Float32,
+                ),
+            ),
+        ];
+        let mut stream = Vec::new();
+        write_stream(&mut stream, &columns).unwrap();
+        assert_eq!(stream.len() % 8, 0);
+
+        let table = read_stream(stream.as_slice()).unwrap();
+        assert_eq!(table.names, vec!["id", "name", "history", "feature"]);
+        assert_eq!(table.batches.len(), 1);
+        for (read, (_, written)) in table.batches[0].iter().zip(columns.iter()) {
+            assert_eq!(read, written);
+        }
+        assert_eq!(
+            table.batches[0][3].lists(ArrowArray::f32_values).unwrap(),
+            vec![Some(vec![0.5, 1.0]), None, Some(vec![2.0, 3.0])]
+        );
+        assert_eq!(table.column("history"), Some(2));
+    }
+
+    #[test]
+    fn json_columns() {
+        let values = vec![
+            json!({"id": 1, "name": "a", "box": [0.5, 1], "flag": true, "any": 1, "opt": null}),
+            json!({"id": -2, "name": "b", "box": [], "flag": false, "any": "x", "big": u64::MAX}),
+            Value::Null,
+        ];
+        let column = ArrowArray::from_json(values.clone());
+        let ArrowArray::Struct { validity, fields } = &column else {
+            panic!("struct column expected");
+        };
+        assert_eq!(validity, &vec![true, true, false]);
+        let types = fields
+            .iter()
+            .map(|(name, f)| {
+                let t = match f {
+                    ArrowArray::Int64(_) => "int64",
+                    ArrowArray::UInt64(_) => "uint64",
+                    ArrowArray::Utf8(_) => "utf8",
+                    ArrowArray::Json(_) => "json",
+                    ArrowArray::Boolean(_) => "bool",
+                    ArrowArray::List { values, .. } => {
+                        assert!(matches!(**values, ArrowArray::Float64(_)));
+                        "list"
+                    }
+                    _ => "other",
+                };
+                (name.as_str(), t)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![
+                ("any", "json"),
+                ("box", "list"),
+                ("flag", "bool"),
+                ("id", "int64"),
+                ("name", "utf8"),
+                ("opt", "utf8"),
+                ("big", "uint64"),
+            ]
+        );
+
+        let mut stream = Vec::new();
+        write_stream(&mut stream, &[("attributes", column.clone())]).unwrap();
+        let table = read_stream(stream.as_slice()).unwrap();
+        assert_eq!(table.batches[0][0], column);
+        assert_eq!(
+            table.batches[0][0].json_values().unwrap(),
+            vec![
+                json!({"id": 1, "name": "a", "box": [0.5, 1.0], "flag": true, "any": 1}),
+                json!({"id": -2, "name": "b", "box": [], "flag": false, "any": "x", "big": u64::MAX}),
+                Value::Null,
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(read_stream(&[0xFFu8, 0xFF, 0xFF, 0xFF, 8, 0, 0, 0, 1][..]).is_err());
+        assert!(read_stream(&[][..]).is_err());
+    }
+}
@@ -1,10 +1,22 @@
 use crate::track::ObservationAttributes;
+#[cfg(feature = "clipping")]
 use crate::utils::clipping::sutherland_hodgman_clip;
 use crate::Errors::GenericBBoxConversionError;
 use crate::{Errors, EPS};
+#[cfg(feature = "clipping")]
 use geo::{Area, Coord, LineString, Polygon};
+use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
 
+/// Boxes stored with half precision
+///
+#[cfg(feature = "f16")]
+pub mod compact;
+
+/// Normalized and right-handed coordinate systems of the boxes with the conversions to pixels
+///
+pub mod coordinates;
+
 /// Bounding box in the format (left, top, width, height)
 ///
 #[derive(Clone, Default, Debug, Copy)]
@@ -75,7 +87,7 @@ impl BoundingBox {
 }
 
 /// Bounding box in the format (x, y, angle, aspect, height)
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Serialize, Deserialize)]
 pub struct Universal2DBox {
     pub xc: f32,
     pub yc: f32,
@@ -83,9 +95,18 @@ pub struct Universal2DBox {
     pub aspect: f32,
     pub height: f32,
     pub confidence: f32,
-    _vertex_cache: Option<Polygon<f64>>,
+    #[serde(skip)]
+    _vertex_cache: Option<Vertices>,
 }
 
+/// The vertices of the box used to intersect the oriented boxes
+///
+#[cfg(feature = "clipping")]
+type Vertices = Polygon<f64>;
+
+#[cfg(not(feature = "clipping"))]
+type Vertices = [(f64, f64); 4];
+
 impl Clone for Universal2DBox {
     fn clone(&self) -> Self {
         Universal2DBox::new_with_confidence(
@@ -165,11 +186,43 @@ impl Universal2DBox {
         w * self.height
     }
 
+    /// The corners of the box
+    ///
+    fn corners(&self) -> [(f64, f64); 4] {
+        let angle = self.angle.unwrap_or(0.0) as f64;
+        let height = self.height as f64;
+        let aspect = self.aspect as f64;
+
+        let c = angle.cos();
+        let s = angle.sin();
+
+        let half_width = height * aspect / 2.0;
+        let half_height = height / 2.0;
+
+        let r1x = -half_width * c - half_height * s;
+        let r1y = -half_width * s + half_height * c;
+
+        let r2x = half_width * c - half_height * s;
+        let r2y = half_width * s + half_height * c;
+
+        let x = self.xc as f64;
+        let y = self.yc as f64;
+
+        [
+            (x + r1x, y + r1y),
+            (x + r2x, y + r2y),
+            (x - r1x, y - r1y),
+            (x - r2x, y - r2y),
+        ]
+    }
+
+    #[cfg(feature = "clipping")]
     #[inline]
     pub fn get_vertices(&self) -> Polygon {
         Polygon::from(self)
     }
 
+    #[cfg(feature = "clipping")]
     #[inline]
     pub fn get_cached_vertices(&self) -> &Option<Polygon<f64>> {
         &self._vertex_cache
@@ -178,7 +231,14 @@ impl Universal2DBox {
     #[inline]
     pub fn gen_vertices(&mut self) -> &Self {
         if self.angle.is_some() {
-            self._vertex_cache = Some(self.get_vertices());
+            #[cfg(feature = "clipping")]
+            {
+                self._vertex_cache = Some(self.get_vertices());
+            }
+            #[cfg(not(feature = "clipping"))]
+            {
+                self._vertex_cache = Some(self.corners());
+            }
         }
         self
     }
@@ -213,6 +273,7 @@ impl Universal2DBox {
         self.confidence = confidence;
     }
 
+    #[cfg(feature = "clipping")]
     pub fn sutherland_hodgman_clip(mut self, mut clipping: Universal2DBox) -> Polygon<f64> {
         if self.angle.is_none() {
             self.rotate_mut(0.0);
@@ -284,52 +345,96 @@ impl TryFrom<&Universal2DBox> for BoundingBox {
     }
 }
 
+#[cfg(feature = "clipping")]
 impl From<&Universal2DBox> for Polygon<f64> {
     fn from(b: &Universal2DBox) -> Self {
-        let angle = b.angle.unwrap_or(0.0) as f64;
-        let height = b.height as f64;
-        let aspect = b.aspect as f64;
+        Polygon::new(
+            LineString(
+                b.corners()
+                    .into_iter()
+                    .map(|(x, y)| Coord { x, y })
+                    .collect(),
+            ),
+            vec![],
+        )
+    }
+}
 
-        let c = angle.cos();
-        let s = angle.sin();
+/// The area of the intersection of the convex polygons clipped with the Sutherland-Hodgman
+/// algorithm, the same way `utils::clipping::sutherland_hodgman_clip` does without `geo`
+///
+#[cfg(any(test, not(feature = "clipping")))]
+fn convex_intersection_area(subject: &[(f64, f64)], clipping: &[(f64, f64)]) -> f64 {
+    let is_inside = |q: (f64, f64), p1: (f64, f64), p2: (f64, f64)| {
+        (p2.0 - p1.0) * (q.1 - p1.1) - (p2.1 - p1.1) * (q.0 - p1.0) <= 0.0
+    };
+    let intersection = |cp1: (f64, f64), cp2: (f64, f64), s: (f64, f64), e: (f64, f64)| {
+        let dc = (cp1.0 - cp2.0, cp1.1 - cp2.1);
+        let dp = (s.0 - e.0, s.1 - e.1);
+        let n1 = cp1.0 * cp2.1 - cp1.1 * cp2.0;
+        let n2 = s.0 * e.1 - s.1 * e.0;
+        let n3 = 1.0 / (dc.0 * dp.1 - dc.1 * dp.0);
+        ((n1 * dp.0 - n2 * dc.0) * n3, (n1 * dp.1 - n2 * dc.1) * n3)
+    };
+
+    let mut polygon = subject.to_vec();
+    for (i, &edge_end) in clipping.iter().enumerate() {
+        let edge_start = clipping[(i + clipping.len() - 1) % clipping.len()];
+        let input = std::mem::take(&mut polygon);
+        for (j, &end) in input.iter().enumerate() {
+            let start = input[(j + input.len() - 1) % input.len()];
+            if is_inside(end, edge_start, edge_end) {
+                if !is_inside(start, edge_start, edge_end) {
+                    polygon.push(intersection(start, end, edge_start, edge_end));
+                }
+                polygon.push(end);
+            } else if is_inside(start, edge_start, edge_end) {
+                polygon.push(intersection(start, end, edge_start, edge_end));
+            }
+        }
+    }
 
-        let half_width = height * aspect / 2.0;
-        let half_height = height / 2.0;
+    let doubled = (0..polygon.len())
+        .map(|i| {
+            let (x1, y1) = polygon[i];
+            let (x2, y2) = polygon[(i + 1) % polygon.len()];
+            x1 * y2 - x2 * y1
+        })
+        .sum::<f64>();
+    doubled.abs() / 2.0
+}
 
-        let r1x = -half_width * c - half_height * s;
-        let r1y = -half_width * s + half_height * c;
+#[cfg(test)]
+mod convex_clipping {
+    use crate::utils::bbox::{convex_intersection_area, Universal2DBox};
+    use crate::EPS;
+    use std::f32::consts::PI;
 
-        let r2x = half_width * c - half_height * s;
-        let r2y = half_width * s + half_height * c;
+    #[test]
+    fn oriented_boxes() {
+        let bbox1 = Universal2DBox::new(0.0, 0.0, Some(2.0), 0.5, 2.0);
+        let bbox2 = Universal2DBox::new(0.0, 0.0, Some(2.0 + PI / 2.0), 0.5, 2.0);
+        let area = convex_intersection_area(&bbox1.corners(), &bbox2.corners());
+        assert!((area - 1.0).abs() < EPS as f64);
+        assert!(
+            (Universal2DBox::intersection(&bbox1, &bbox2) - area).abs() < EPS as f64,
+            "The built-in clipping must match the clipping with geo"
+        );
 
-        let x = b.xc as f64;
-        let y = b.yc as f64;
+        let bbox3 = Universal2DBox::new(10.0, 0.0, Some(2.0 + PI / 2.0), 0.5, 2.0);
+        let area = convex_intersection_area(&bbox1.corners(), &bbox3.corners());
+        assert!(area.abs() < EPS as f64);
 
-        Polygon::new(
-            LineString(vec![
-                Coord {
-                    x: x + r1x,
-                    y: y + r1y,
-                },
-                Coord {
-                    x: x + r2x,
-                    y: y + r2y,
-                },
-                Coord {
-                    x: x - r1x,
-                    y: y - r1y,
-                },
-                Coord {
-                    x: x - r2x,
-                    y: y - r2y,
-                },
-            ]),
-            vec![],
-        )
+        let shifted = Universal2DBox::new(0.5, 0.25, None, 2.0, 1.0);
+        let area = convex_intersection_area(
+            &Universal2DBox::new(0.0, 0.0, None, 2.0, 1.0).corners(),
+            &shifted.corners(),
+        );
+        assert!((area - 1.5 * 0.75).abs() < EPS as f64);
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "clipping"))]
 mod polygons {
     use crate::track::ObservationAttributes;
     use crate::utils::bbox::Universal2DBox;
@@ -411,6 +516,14 @@ impl ObservationAttributes for BoundingBox {
             _ => None,
         }
     }
+
+    fn metric_object_is_nan(o: &Self::MetricObject) -> bool {
+        o.is_nan()
+    }
+
+    fn schema_name() -> &'static str {
+        "BoundingBox"
+    }
 }
 
 impl PartialEq<Self> for BoundingBox {
@@ -489,22 +602,29 @@ impl Universal2DBox {
             let mut l = l.clone();
             let mut r = r.clone();
 
-            if l.get_cached_vertices().is_none() {
+            if l._vertex_cache.is_none() {
                 let angle = l.angle.unwrap_or(0.0);
                 l.rotate_mut(angle);
                 l.gen_vertices();
             }
 
-            if r.get_cached_vertices().is_none() {
+            if r._vertex_cache.is_none() {
                 let angle = r.angle.unwrap_or(0.0);
                 r.rotate_mut(angle);
                 r.gen_vertices();
             }
 
-            let p1 = l.get_cached_vertices().as_ref().unwrap();
-            let p2 = r.get_cached_vertices().as_ref().unwrap();
+            let p1 = l._vertex_cache.as_ref().unwrap();
+            let p2 = r._vertex_cache.as_ref().unwrap();
 
-            sutherland_hodgman_clip(p1, p2).unsigned_area()
+            #[cfg(feature = "clipping")]
+            {
+                sutherland_hodgman_clip(p1, p2).unsigned_area()
+            }
+            #[cfg(not(feature = "clipping"))]
+            {
+                convex_intersection_area(p1, p2)
+            }
         }
     }
 }
@@ -532,6 +652,14 @@ impl ObservationAttributes for Universal2DBox {
             _ => None,
         }
     }
+
+    fn metric_object_is_nan(o: &Self::MetricObject) -> bool {
+        o.is_nan()
+    }
+
+    fn schema_name() -> &'static str {
+        "Universal2DBox"
+    }
 }
 
 impl PartialEq<Self> for Universal2DBox {
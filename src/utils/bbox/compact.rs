@@ -0,0 +1,97 @@
+use crate::utils::bbox::Universal2DBox;
+use half::f16;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// [Universal2DBox](Universal2DBox) stored with half precision, takes 14 bytes (the optional
+/// angle takes 4 of them) instead of 24 (and the vertex cache).
+///
+/// The values keep 11 significant bits, so the coordinates within `[1024; 2048)` are stored with
+/// 1 px precision, within `[2048; 4096)` - with 2 px precision, and so on up to 32 px precision
+/// within `[32768; 65504]`. The confidence and the angle keep about 3 decimal digits.
+///
+/// The largest value of the half precision is 65504, the values beyond `[-65504; 65504]` are
+/// clamped to the bounds, so the boxes of the huge frames or the coordinates far outside the
+/// frame are distorted in the history.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CompactUniversal2DBox {
+    xc: f16,
+    yc: f16,
+    angle: Option<f16>,
+    aspect: f16,
+    height: f16,
+    confidence: f16,
+}
+
+/// Converts the value to the half precision, the values out of the range are clamped
+///
+fn half(v: f32) -> f16 {
+    f16::from_f32(v.clamp(f16::MIN.to_f32(), f16::MAX.to_f32()))
+}
+
+impl From<&Universal2DBox> for CompactUniversal2DBox {
+    fn from(b: &Universal2DBox) -> Self {
+        Self {
+            xc: half(b.xc),
+            yc: half(b.yc),
+            angle: b.angle.map(half),
+            aspect: half(b.aspect),
+            height: half(b.height),
+            confidence: half(b.confidence),
+        }
+    }
+}
+
+impl From<&CompactUniversal2DBox> for Universal2DBox {
+    fn from(b: &CompactUniversal2DBox) -> Self {
+        Universal2DBox::new_with_confidence(
+            b.xc.to_f32(),
+            b.yc.to_f32(),
+            b.angle.map(f16::to_f32),
+            b.aspect.to_f32(),
+            b.height.to_f32(),
+            b.confidence.to_f32(),
+        )
+    }
+}
+
+/// Moves all the boxes but the last one to the compact history
+///
+pub(crate) fn compact_history(
+    boxes: &mut VecDeque<Universal2DBox>,
+    compact: &mut VecDeque<CompactUniversal2DBox>,
+) {
+    while boxes.len() > 1 {
+        let b = boxes.pop_front().unwrap();
+        compact.push_back(CompactUniversal2DBox::from(&b));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::bbox::compact::CompactUniversal2DBox;
+    use crate::utils::bbox::Universal2DBox;
+
+    #[test]
+    fn conversion() {
+        let b = Universal2DBox::new_with_confidence(1500.3, 20.5, Some(0.5), 0.5, 100.0, 0.9);
+        let compact = CompactUniversal2DBox::from(&b);
+        let restored = Universal2DBox::from(&compact);
+        assert!((restored.xc - b.xc).abs() <= 1.0);
+        assert_eq!(restored.yc, 20.5);
+        assert!((restored.angle.unwrap() - 0.5).abs() < 1e-3);
+        assert_eq!(restored.aspect, 0.5);
+        assert_eq!(restored.height, 100.0);
+        assert!((restored.confidence - 0.9).abs() < 1e-3);
+        assert_eq!(std::mem::size_of::<CompactUniversal2DBox>(), 14);
+    }
+
+    #[test]
+    fn overflow() {
+        let b = Universal2DBox::new_with_confidence(70000.0, -1e6, None, 0.5, 100.0, 0.9);
+        let restored = Universal2DBox::from(&CompactUniversal2DBox::from(&b));
+        assert_eq!(restored.xc, 65504.0);
+        assert_eq!(restored.yc, -65504.0);
+    }
+}
@@ -0,0 +1,310 @@
+use crate::utils::bbox::Universal2DBox;
+use serde::{Deserialize, Serialize};
+
+/// The coordinate system of the boxes produced by the detector.
+///
+/// The trackers work in the pixel coordinates with the origin in the top-left corner of the frame
+/// and the vertical axis pointing down, so the area and the distance thresholds (e.g.
+/// `visual_minimal_area`) are measured in pixels. The boxes in the normalized `[0, 1]` coordinates
+/// or in the right-handed coordinates (the origin in the bottom-left corner, the vertical axis
+/// pointing up) are validated and converted to pixels at ingestion with
+/// [ObservationTransformer::Coordinates](crate::trackers::observation_pipeline::ObservationTransformer::Coordinates),
+/// the reported boxes are converted back with [box_from_pixels](Self::box_from_pixels).
+///
+/// The axis-aligned boxes are converted exactly, the rotated boxes keep their sizes ratio and
+/// the angle (mirrored for the right-handed system) which is exact for the square frames only.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CoordinateSystem {
+    width: f32,
+    height: f32,
+    normalized: bool,
+    right_handed: bool,
+}
+
+impl CoordinateSystem {
+    /// The pixel coordinates of the frame `width x height`
+    ///
+    pub fn pixel(width: f32, height: f32) -> Self {
+        assert!(
+            width > 0.0 && height > 0.0,
+            "The frame sizes must be positive numbers"
+        );
+        Self {
+            width,
+            height,
+            normalized: false,
+            right_handed: false,
+        }
+    }
+
+    /// The coordinates normalized by the sizes of the frame `width x height`
+    ///
+    pub fn normalized(width: f32, height: f32) -> Self {
+        Self {
+            normalized: true,
+            ..Self::pixel(width, height)
+        }
+    }
+
+    /// The same system with the origin in the bottom-left corner and the vertical axis pointing up
+    ///
+    pub fn right_handed(self) -> Self {
+        Self {
+            right_handed: true,
+            ..self
+        }
+    }
+
+    /// The width of the frame in pixels
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// The height of the frame in pixels
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn is_normalized(&self) -> bool {
+        self.normalized
+    }
+
+    pub fn is_right_handed(&self) -> bool {
+        self.right_handed
+    }
+
+    /// The number of pixels in the horizontal and the vertical units of the system
+    ///
+    fn scale(&self) -> (f32, f32) {
+        if self.normalized {
+            (self.width, self.height)
+        } else {
+            (1.0, 1.0)
+        }
+    }
+
+    /// Checks that the box is defined in the system: the values are finite, the sizes are positive
+    /// and the center lays within the frame. The pixel boxes passed as normalized fail the check.
+    ///
+    pub fn is_valid(&self, bbox: &Universal2DBox) -> bool {
+        let (sx, sy) = self.scale();
+        [bbox.xc, bbox.yc, bbox.aspect, bbox.height]
+            .iter()
+            .chain(bbox.angle.iter())
+            .all(|v| v.is_finite())
+            && bbox.aspect > 0.0
+            && bbox.height > 0.0
+            && (0.0..=self.width / sx).contains(&bbox.xc)
+            && (0.0..=self.height / sy).contains(&bbox.yc)
+    }
+
+    /// Converts the box to the pixel coordinates with the vertical axis pointing down
+    ///
+    pub fn box_to_pixels(&self, bbox: &Universal2DBox) -> Universal2DBox {
+        let (sx, sy) = self.scale();
+        let (yc, angle) = if self.right_handed {
+            (self.height - bbox.yc * sy, bbox.angle.map(|a| -a))
+        } else {
+            (bbox.yc * sy, bbox.angle)
+        };
+        Universal2DBox::new_with_confidence(
+            bbox.xc * sx,
+            yc,
+            angle,
+            bbox.aspect * sx / sy,
+            bbox.height * sy,
+            bbox.confidence,
+        )
+    }
+
+    /// Converts the box from the pixel coordinates with the vertical axis pointing down
+    ///
+    pub fn box_from_pixels(&self, bbox: &Universal2DBox) -> Universal2DBox {
+        let (sx, sy) = self.scale();
+        let (yc, angle) = if self.right_handed {
+            (self.height - bbox.yc, bbox.angle.map(|a| -a))
+        } else {
+            (bbox.yc, bbox.angle)
+        };
+        Universal2DBox::new_with_confidence(
+            bbox.xc / sx,
+            yc / sy,
+            angle,
+            bbox.aspect * sy / sx,
+            bbox.height / sy,
+            bbox.confidence,
+        )
+    }
+
+    /// Converts the area measured in the units of the system to pixels
+    ///
+    pub fn area_to_pixels(&self, area: f32) -> f32 {
+        let (sx, sy) = self.scale();
+        area * sx * sy
+    }
+
+    /// Converts the area measured in pixels to the units of the system
+    ///
+    pub fn area_from_pixels(&self, area: f32) -> f32 {
+        let (sx, sy) = self.scale();
+        area / (sx * sy)
+    }
+
+    /// The area of the box in pixels
+    ///
+    pub fn pixel_area(&self, bbox: &Universal2DBox) -> f32 {
+        self.box_to_pixels(bbox).area()
+    }
+
+    /// The radius of the box in pixels. Unlike the area the radius of the normalized box can't be
+    /// converted with the scale factor unless the frame is square.
+    ///
+    pub fn pixel_radius(&self, bbox: &Universal2DBox) -> f32 {
+        self.box_to_pixels(bbox).get_radius()
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::CoordinateSystem;
+    use crate::utils::bbox::python::PyUniversal2DBox;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "CoordinateSystem")]
+    #[derive(Debug, Clone, Copy)]
+    pub struct PyCoordinateSystem(pub(crate) CoordinateSystem);
+
+    #[pymethods]
+    impl PyCoordinateSystem {
+        #[staticmethod]
+        pub fn pixel(width: f32, height: f32) -> Self {
+            Self(CoordinateSystem::pixel(width, height))
+        }
+
+        #[staticmethod]
+        pub fn normalized(width: f32, height: f32) -> Self {
+            Self(CoordinateSystem::normalized(width, height))
+        }
+
+        pub fn right_handed(&self) -> Self {
+            Self(self.0.right_handed())
+        }
+
+        #[getter]
+        fn width(&self) -> f32 {
+            self.0.width()
+        }
+
+        #[getter]
+        fn height(&self) -> f32 {
+            self.0.height()
+        }
+
+        #[getter]
+        fn is_normalized(&self) -> bool {
+            self.0.is_normalized()
+        }
+
+        #[getter]
+        fn is_right_handed(&self) -> bool {
+            self.0.is_right_handed()
+        }
+
+        fn is_valid(&self, bbox: &PyUniversal2DBox) -> bool {
+            self.0.is_valid(&bbox.0)
+        }
+
+        fn box_to_pixels(&self, bbox: &PyUniversal2DBox) -> PyUniversal2DBox {
+            PyUniversal2DBox(self.0.box_to_pixels(&bbox.0))
+        }
+
+        fn box_from_pixels(&self, bbox: &PyUniversal2DBox) -> PyUniversal2DBox {
+            PyUniversal2DBox(self.0.box_from_pixels(&bbox.0))
+        }
+
+        fn area_to_pixels(&self, area: f32) -> f32 {
+            self.0.area_to_pixels(area)
+        }
+
+        fn area_from_pixels(&self, area: f32) -> f32 {
+            self.0.area_from_pixels(area)
+        }
+
+        fn pixel_area(&self, bbox: &PyUniversal2DBox) -> f32 {
+            self.0.pixel_area(&bbox.0)
+        }
+
+        fn pixel_radius(&self, bbox: &PyUniversal2DBox) -> f32 {
+            self.0.pixel_radius(&bbox.0)
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{:?}", self.0)
+        }
+
+        fn __str__(&self) -> String {
+            format!("{:#?}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::bbox::coordinates::CoordinateSystem;
+    use crate::utils::bbox::{BoundingBox, Universal2DBox};
+    use crate::EPS;
+
+    #[test]
+    fn normalized() {
+        let system = CoordinateSystem::normalized(200.0, 100.0);
+        let bbox: Universal2DBox = BoundingBox::new_with_confidence(0.1, 0.2, 0.5, 0.4, 0.7).into();
+        assert!(system.is_valid(&bbox));
+
+        let pixels = BoundingBox::try_from(system.box_to_pixels(&bbox)).unwrap();
+        assert!((pixels.left - 20.0).abs() < EPS);
+        assert!((pixels.top - 20.0).abs() < EPS);
+        assert!((pixels.width - 100.0).abs() < EPS);
+        assert!((pixels.height - 40.0).abs() < EPS);
+        assert!((pixels.confidence - 0.7).abs() < EPS);
+
+        assert!((system.pixel_area(&bbox) - 4000.0).abs() < 0.01);
+        assert!((system.area_to_pixels(bbox.area()) - 4000.0).abs() < 0.01);
+        assert!((system.area_from_pixels(4000.0) - bbox.area()).abs() < EPS);
+        assert!((system.pixel_radius(&bbox) - (100.0f32.hypot(40.0) / 2.0)).abs() < 0.01);
+
+        let back = system.box_from_pixels(&system.box_to_pixels(&bbox));
+        assert!((back.xc - bbox.xc).abs() < EPS);
+        assert!((back.yc - bbox.yc).abs() < EPS);
+        assert!((back.aspect - bbox.aspect).abs() < EPS);
+        assert!((back.height - bbox.height).abs() < EPS);
+
+        let pixel_box: Universal2DBox = BoundingBox::new(20.0, 20.0, 100.0, 40.0).into();
+        assert!(!system.is_valid(&pixel_box));
+        assert!(CoordinateSystem::pixel(200.0, 100.0).is_valid(&pixel_box));
+    }
+
+    #[test]
+    fn right_handed() {
+        let system = CoordinateSystem::pixel(200.0, 100.0).right_handed();
+        let bbox = Universal2DBox::new(50.0, 30.0, Some(10.0), 2.0, 10.0);
+        let pixels = system.box_to_pixels(&bbox);
+        assert!((pixels.xc - 50.0).abs() < EPS);
+        assert!((pixels.yc - 70.0).abs() < EPS);
+        assert_eq!(pixels.angle, Some(-10.0));
+
+        let back = system.box_from_pixels(&pixels);
+        assert!((back.yc - 30.0).abs() < EPS);
+        assert_eq!(back.angle, Some(10.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_frame() {
+        CoordinateSystem::normalized(0.0, 100.0);
+    }
+}
@@ -1,7 +1,11 @@
 use crate::prelude::{BoundingBox, Universal2DBox};
 use crate::Errors;
 use nalgebra::{SMatrix, SVector};
+use serde::{Deserialize, Serialize};
 
+/// Chi-square gating and scoring of the Mahalanobis distances
+///
+pub mod gating;
 /// Kalman filter for the prediction of axis-aligned and oriented bounding boxes
 ///
 pub mod kalman_2d_box;
@@ -12,12 +16,34 @@ pub mod kalman_2d_point;
 ///
 pub mod kalman_2d_point_vec;
 
+/// The legacy cost scale: the inverted cost of the pair within the gate is `CHI2_UPPER_BOUND - distance`
+///
 pub const CHI2_UPPER_BOUND: f32 = 100.0;
 
+/// The 0.90 quantiles of the chi-square distribution with 1 to 9 degrees of freedom
+///
+pub const CHI2INV90: [f32; 9] = [
+    2.7055, 4.6052, 6.2514, 7.7794, 9.2364, 10.645, 12.017, 13.362, 14.684,
+];
+
+/// The 0.95 quantiles of the chi-square distribution with 1 to 9 degrees of freedom
+///
 pub const CHI2INV95: [f32; 9] = [
     3.8415, 5.9915, 7.8147, 9.4877, 11.070, 12.592, 14.067, 15.507, 16.919,
 ];
 
+/// The 0.975 quantiles of the chi-square distribution with 1 to 9 degrees of freedom
+///
+pub const CHI2INV975: [f32; 9] = [
+    5.0239, 7.3778, 9.3484, 11.143, 12.833, 14.449, 16.013, 17.535, 19.023,
+];
+
+/// The 0.99 quantiles of the chi-square distribution with 1 to 9 degrees of freedom
+///
+pub const CHI2INV99: [f32; 9] = [
+    6.6349, 9.2103, 11.345, 13.277, 15.086, 16.812, 18.475, 20.090, 21.666,
+];
+
 macro_rules! pretty_print {
     ($arr:expr) => {{
         let indent = 4;
@@ -38,7 +64,7 @@ macro_rules! pretty_print {
 
 /// Kalman filter current state
 ///
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct KalmanState<const X: usize> {
     mean: SVector<f32, X>,
     covariance: SMatrix<f32, X, X>,
@@ -51,6 +77,12 @@ impl<const X: usize> KalmanState<X> {
         eprintln!("Mean={}", pretty_print!(self.mean.transpose()));
         eprintln!("Covariance={}", pretty_print!(self.covariance));
     }
+
+    /// the state vector
+    ///
+    pub fn mean(&self) -> &SVector<f32, X> {
+        &self.mean
+    }
 }
 
 impl<const X: usize> TryFrom<KalmanState<X>> for Universal2DBox {
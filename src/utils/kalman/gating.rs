@@ -0,0 +1,186 @@
+use crate::utils::kalman::{CHI2INV90, CHI2INV95, CHI2INV975, CHI2INV99};
+use serde::{Deserialize, Serialize};
+
+/// The quantile of the chi-square distribution the gate is placed at.
+///
+/// The squared Mahalanobis distance between the prediction and the observation of the same
+/// object is chi-square distributed with the degrees of freedom equal to the measurement
+/// dimension, so the gate at the quantile `q` keeps the fraction `q` of the true matches.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GatingQuantile {
+    Q90,
+    #[default]
+    Q95,
+    Q975,
+    Q99,
+}
+
+impl GatingQuantile {
+    /// The quantile for the probability, `None` if the probability is not tabulated
+    ///
+    pub fn from_probability(p: f32) -> Option<Self> {
+        [Self::Q90, Self::Q95, Self::Q975, Self::Q99]
+            .into_iter()
+            .find(|q| (q.probability() - p).abs() < 1e-4)
+    }
+
+    pub fn probability(&self) -> f32 {
+        match self {
+            GatingQuantile::Q90 => 0.9,
+            GatingQuantile::Q95 => 0.95,
+            GatingQuantile::Q975 => 0.975,
+            GatingQuantile::Q99 => 0.99,
+        }
+    }
+
+    /// The value of the inverse chi-square distribution
+    ///
+    /// # Parameters
+    /// * `dof` - the degrees of freedom, `1..=9`
+    ///
+    pub fn chi2inv(&self, dof: usize) -> f32 {
+        assert!(
+            (1..=9).contains(&dof),
+            "Degrees of freedom must lay within [1:9]"
+        );
+        let table = match self {
+            GatingQuantile::Q90 => &CHI2INV90,
+            GatingQuantile::Q95 => &CHI2INV95,
+            GatingQuantile::Q975 => &CHI2INV975,
+            GatingQuantile::Q99 => &CHI2INV99,
+        };
+        table[dof - 1]
+    }
+}
+
+/// Chi-square gating and scoring of the squared Mahalanobis distances.
+///
+/// The pairs farther than the gate `chi2inv(q, dof)` are rejected, the pairs within the gate are
+/// scored with `1 - distance / gate`, so the score is unit-free and lays in `(0.0..=1.0]`: `1.0`
+/// is the perfect match, `0.5` is the pair in the middle of the gate. The trackers multiply the
+/// score with the detection confidence like the IoU, and associate the pairs which score is
+/// greater than `min_score`.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MahalanobisGating {
+    pub quantile: GatingQuantile,
+    pub min_score: f32,
+}
+
+impl MahalanobisGating {
+    pub fn new(quantile: GatingQuantile, min_score: f32) -> Self {
+        assert!(
+            (0.0..1.0).contains(&min_score),
+            "Minimal score must lay within [0.0:1.0)"
+        );
+        Self {
+            quantile,
+            min_score,
+        }
+    }
+
+    /// The gate for the squared Mahalanobis distance
+    ///
+    pub fn gate(&self, dof: usize) -> f32 {
+        self.quantile.chi2inv(dof)
+    }
+
+    /// Scores the squared Mahalanobis distance
+    ///
+    /// # Returns
+    /// the score within `(0.0..=1.0]`, `None` if the distance is outside of the gate
+    ///
+    pub fn score(&self, distance: f32, dof: usize) -> Option<f32> {
+        let gate = self.gate(dof);
+        if distance < gate {
+            Some(1.0 - distance.max(0.0) / gate)
+        } else {
+            None
+        }
+    }
+
+    /// The squared Mahalanobis distance the score corresponds to
+    ///
+    pub fn distance(&self, score: f32, dof: usize) -> f32 {
+        (1.0 - score) * self.gate(dof)
+    }
+}
+
+#[cfg(feature = "python")]
+pub mod python {
+    use super::{GatingQuantile, MahalanobisGating};
+    use pyo3::exceptions::PyValueError;
+    use pyo3::prelude::*;
+
+    #[pyclass]
+    #[pyo3(name = "MahalanobisGating")]
+    #[derive(Clone, Copy, Debug)]
+    pub struct PyMahalanobisGating(pub MahalanobisGating);
+
+    #[pymethods]
+    impl PyMahalanobisGating {
+        #[new]
+        #[pyo3(signature = (quantile = 0.95, min_score = 0.0))]
+        fn new(quantile: f32, min_score: f32) -> PyResult<Self> {
+            let quantile = GatingQuantile::from_probability(quantile).ok_or_else(|| {
+                PyValueError::new_err("Quantile must be one of 0.9, 0.95, 0.975, 0.99")
+            })?;
+            Ok(PyMahalanobisGating(MahalanobisGating::new(
+                quantile, min_score,
+            )))
+        }
+
+        #[pyo3(signature = (dof = 5))]
+        fn gate(&self, dof: usize) -> f32 {
+            self.0.gate(dof)
+        }
+
+        #[pyo3(signature = (distance, dof = 5))]
+        fn score(&self, distance: f32, dof: usize) -> Option<f32> {
+            self.0.score(distance, dof)
+        }
+
+        #[classattr]
+        const __hash__: Option<Py<PyAny>> = None;
+
+        fn __repr__(&self) -> String {
+            format!("{self:?}")
+        }
+
+        fn __str__(&self) -> String {
+            format!("{self:#?}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::kalman::gating::{GatingQuantile, MahalanobisGating};
+    use crate::utils::kalman::kalman_2d_box::DIM_2D_BOX;
+    use crate::EPS;
+
+    #[test]
+    fn score() {
+        let gating = MahalanobisGating::default();
+        let gate = gating.gate(DIM_2D_BOX);
+        assert_eq!(gate, 11.070);
+        assert_eq!(gating.score(0.0, DIM_2D_BOX), Some(1.0));
+        assert!((gating.score(gate / 2.0, DIM_2D_BOX).unwrap() - 0.5).abs() < EPS);
+        assert!((gating.distance(0.5, DIM_2D_BOX) - gate / 2.0).abs() < EPS);
+        assert_eq!(gating.score(gate, DIM_2D_BOX), None);
+
+        let wide = MahalanobisGating::new(GatingQuantile::Q99, 0.0);
+        assert!(wide.score(gate, DIM_2D_BOX).is_some());
+    }
+
+    #[test]
+    fn quantiles() {
+        assert_eq!(
+            GatingQuantile::from_probability(0.975),
+            Some(GatingQuantile::Q975)
+        );
+        assert_eq!(GatingQuantile::from_probability(0.8), None);
+        assert_eq!(GatingQuantile::Q90.chi2inv(2), 4.6052);
+    }
+}
@@ -147,6 +147,9 @@ impl Universal2DBoxKalmanFilter {
         KalmanState { mean, covariance }
     }
 
+    /// The squared Mahalanobis distance between the projected state and the measurement, for the
+    /// true matches it is chi-square distributed with 5 degrees of freedom
+    ///
     pub fn distance(&self, state: KalmanState<DIM_2D_BOX_X2>, measurement: &Universal2DBox) -> f32 {
         let (mean, covariance) = (state.mean, state.covariance);
         let projected_state = self.project(mean, covariance);
@@ -169,6 +172,15 @@ impl Universal2DBoxKalmanFilter {
         res.component_mul(&res).sum()
     }
 
+    /// The legacy cost of the squared Mahalanobis distance: the distance itself (`inverted` is
+    /// `false`) or `CHI2_UPPER_BOUND - distance` (`inverted` is `true`) within the 0.95 gate of the
+    /// chi-square distribution with 5 degrees of freedom. Outside of the gate the cost is
+    /// `CHI2_UPPER_BOUND` or `0.0` respectively.
+    ///
+    /// The scale of the cost has no statistical meaning, use
+    /// [MahalanobisGating](crate::utils::kalman::gating::MahalanobisGating) to score the distances
+    /// with the configurable gate.
+    ///
     pub fn calculate_cost(distance: f32, inverted: bool) -> f32 {
         if !inverted {
             if distance > CHI2INV95[4] {
@@ -184,6 +196,27 @@ impl Universal2DBoxKalmanFilter {
     }
 }
 
+impl KalmanState<DIM_2D_BOX_X2> {
+    /// Keeps the center of the box within the frame `[0, width] x [0, height]`, the velocity along
+    /// the clamped axis is reset, so the next predictions don't move the box further outside
+    ///
+    /// # Returns
+    /// `true` if the center was clamped
+    ///
+    pub fn clamp_center(&mut self, width: f32, height: f32) -> bool {
+        let mut clamped = false;
+        for (i, max) in [width, height].into_iter().enumerate() {
+            let v = self.mean[i].clamp(0.0, max);
+            if v != self.mean[i] {
+                self.mean[i] = v;
+                self.mean[DIM_2D_BOX + i] = 0.0;
+                clamped = true;
+            }
+        }
+        clamped
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::utils::bbox::{BoundingBox, Universal2DBox};
@@ -221,6 +254,16 @@ mod tests {
         assert_eq!(p, est_p);
     }
 
+    #[test]
+    fn clamp_center() {
+        let f = Universal2DBoxKalmanFilter::default();
+        let mut state = f.initiate(&Universal2DBox::new(-5.0, 10.0, None, 0.5, 10.0));
+        assert!(state.clamp_center(100.0, 50.0));
+        let p = Universal2DBox::try_from(state).unwrap();
+        assert_eq!(p, Universal2DBox::new(0.0, 10.0, None, 0.5, 10.0));
+        assert!(!state.clamp_center(100.0, 50.0));
+    }
+
     #[test]
     fn gating_distance() {
         let f = Universal2DBoxKalmanFilter::default();
@@ -3,27 +3,65 @@ pub mod nms_py;
 
 use crate::utils::bbox::Universal2DBox;
 use itertools::Itertools;
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 #[derive(Clone, Debug)]
 struct Candidate<'a> {
     bbox: &'a Universal2DBox,
     rank: f32,
-    index: usize,
 }
 
 impl<'a> Candidate<'a> {
-    pub fn new(bbox: &'a Universal2DBox, rank: &Option<f32>, index: usize) -> Self {
+    pub fn new(bbox: &'a Universal2DBox, rank: &Option<f32>) -> Self {
         Self {
             bbox,
             rank: rank.unwrap_or(bbox.height),
-            index,
         }
     }
 }
 
+/// Spatial hash of the candidates by the circles circumscribed around the boxes, so only the
+/// candidates which circles share a cell are compared
+///
+struct Grid {
+    cell: f32,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl Grid {
+    fn new(candidates: &[Candidate]) -> Self {
+        let diameter = candidates
+            .iter()
+            .map(|c| 2.0 * c.bbox.get_radius())
+            .sum::<f32>()
+            / candidates.len().max(1) as f32;
+        let mut grid = Self {
+            cell: diameter.max(f32::EPSILON),
+            cells: HashMap::default(),
+        };
+        for (pos, c) in candidates.iter().enumerate() {
+            for key in grid.keys(c.bbox) {
+                grid.cells.entry(key).or_default().push(pos);
+            }
+        }
+        grid
+    }
+
+    fn keys(&self, bbox: &Universal2DBox) -> impl Iterator<Item = (i64, i64)> {
+        let r = bbox.get_radius();
+        let cell = |v: f32| (v / self.cell).floor() as i64;
+        let (x0, x1) = (cell(bbox.xc - r), cell(bbox.xc + r));
+        let (y0, y1) = (cell(bbox.yc - r), cell(bbox.yc + r));
+        (x0..=x1).flat_map(move |x| (y0..=y1).map(move |y| (x, y)))
+    }
+}
+
 /// NMS algorithm implementation
 ///
+/// The boxes are processed in the order of decreasing score, every box that is kept suppresses
+/// the boxes with lower scores. The boxes are bucketed by a spatial grid, so the boxes that are
+/// far from each other are never compared.
+///
 /// # Parameters
 /// * `detections` - boxes with optional scores to filter out with NMS; if `detection.1` is `None`, that the score is set as `detection.0.height`;
 /// * `nms_threshold` - when to exclude the box from set by NMS;
@@ -40,37 +78,123 @@ pub fn nms(
         .filter(|(e, score)| {
             score.unwrap_or(f32::MAX) > score_threshold && e.height > 0.0 && e.aspect > 0.0
         })
-        .enumerate()
-        .map(|(index, (b, score))| Candidate::new(b, score, index))
+        .map(|(b, score)| Candidate::new(b, score))
         .sorted_by(|a, b| b.rank.partial_cmp(&a.rank).unwrap())
         .collect::<Vec<_>>();
 
-    let mut excluded = HashSet::new();
+    let grid = Grid::new(&nms_boxes);
+    let mut excluded = vec![false; nms_boxes.len()];
+    // the last kept box the candidate was compared with, prevents repeated comparisons of the
+    // candidates sharing several cells with the kept box
+    let mut compared_with = vec![usize::MAX; nms_boxes.len()];
 
-    for (index, cb) in nms_boxes.iter().enumerate() {
-        if excluded.contains(&cb.index) {
+    for (pos, cb) in nms_boxes.iter().enumerate() {
+        if excluded[pos] {
             continue;
         }
 
-        for ob in &nms_boxes[index + 1..] {
-            if excluded.contains(&ob.index) {
-                continue;
-            }
+        for key in grid.keys(cb.bbox) {
+            for &other in grid.cells.get(&key).into_iter().flatten() {
+                if other <= pos || excluded[other] || compared_with[other] == pos {
+                    continue;
+                }
+                compared_with[other] = pos;
+
+                let ob = &nms_boxes[other];
+                if Universal2DBox::too_far(cb.bbox, ob.bbox) {
+                    continue;
+                }
 
-            let metric = Universal2DBox::intersection(cb.bbox, ob.bbox) as f32 / ob.bbox.area();
-            if metric > nms_threshold {
-                excluded.insert(ob.index);
+                let metric = Universal2DBox::intersection(cb.bbox, ob.bbox) as f32 / ob.bbox.area();
+                if metric > nms_threshold {
+                    excluded[other] = true;
+                }
             }
         }
     }
 
     nms_boxes
         .into_iter()
-        .filter(|e| !excluded.contains(&e.index))
-        .map(|e| e.bbox)
+        .zip(excluded)
+        .filter(|(_, excluded)| !excluded)
+        .map(|(e, _)| e.bbox)
         .collect()
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::utils::bbox::Universal2DBox;
+    use crate::utils::nms::nms;
+    use rand::Rng;
+
+    #[test]
+    fn suppression() {
+        let bboxes = [
+            (Universal2DBox::new(0.0, 0.0, None, 1.0, 5.0), None),
+            (Universal2DBox::new(0.0, 0.0, None, 1.05, 5.1), None),
+            (Universal2DBox::new(0.0, 0.0, None, 1.0, 4.9), None),
+            (Universal2DBox::new(3.0, 4.0, None, 1.0, 4.5), None),
+            (Universal2DBox::new(100.0, 100.0, None, 1.0, 5.0), Some(0.1)),
+            (Universal2DBox::new(100.5, 100.0, None, 1.0, 5.0), Some(0.9)),
+            (Universal2DBox::new(300.0, 0.0, None, 0.5, 50.0), None),
+            (Universal2DBox::new(-100.0, 0.0, None, 1.0, 0.0), None),
+        ];
+        let res = nms(&bboxes, 0.8, None);
+        assert_eq!(
+            res,
+            vec![&bboxes[6].0, &bboxes[1].0, &bboxes[3].0, &bboxes[5].0]
+        );
+
+        let res = nms(&bboxes, 0.8, Some(0.5));
+        assert!(!res.contains(&&bboxes[4].0));
+    }
+
+    #[test]
+    fn same_as_exhaustive() {
+        let mut rng = rand::thread_rng();
+        let bboxes = (0..300)
+            .map(|_| {
+                (
+                    Universal2DBox::new(
+                        rng.gen_range(0.0..500.0),
+                        rng.gen_range(0.0..500.0),
+                        None,
+                        rng.gen_range(0.3..2.0),
+                        rng.gen_range(5.0..80.0),
+                    ),
+                    Some(rng.gen_range(0.0..1.0)),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut sorted = bboxes
+            .iter()
+            .map(|(b, s)| (b, s.unwrap()))
+            .collect::<Vec<_>>();
+        sorted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        let mut excluded = vec![false; sorted.len()];
+        for i in 0..sorted.len() {
+            if excluded[i] {
+                continue;
+            }
+            for j in i + 1..sorted.len() {
+                let (cb, ob) = (sorted[i].0, sorted[j].0);
+                if !excluded[j] && Universal2DBox::intersection(cb, ob) as f32 / ob.area() > 0.5 {
+                    excluded[j] = true;
+                }
+            }
+        }
+        let expected = sorted
+            .into_iter()
+            .zip(excluded)
+            .filter(|(_, e)| !e)
+            .map(|((b, _), _)| b)
+            .collect::<Vec<_>>();
+
+        assert_eq!(nms(&bboxes, 0.5, None), expected);
+    }
+}
+
 // /// NMS algorithm implementation
 // ///
 // /// # Parameters
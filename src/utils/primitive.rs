@@ -13,6 +13,14 @@ impl ObservationAttributes for f32 {
             None
         }
     }
+
+    fn metric_object_is_nan(o: &Self::MetricObject) -> bool {
+        o.is_nan()
+    }
+
+    fn schema_name() -> &'static str {
+        "f32"
+    }
 }
 
 impl ObservationAttributes for () {
@@ -24,4 +32,8 @@ impl ObservationAttributes for () {
     ) -> Option<Self::MetricObject> {
         None
     }
+
+    fn schema_name() -> &'static str {
+        "()"
+    }
 }
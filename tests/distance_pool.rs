@@ -0,0 +1,90 @@
+use similari::examples::{vec2, UnboundAttrs, UnboundMetric};
+use similari::prelude::{NoopNotifier, ObservationBuilder, TrackStoreBuilder};
+use similari::track::store::TrackStore;
+use similari::track::Track;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts the allocations of all the threads of the test process
+///
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+type Store = TrackStore<UnboundAttrs, UnboundMetric, f32, NoopNotifier>;
+
+fn track(store: &Store, x: f32) -> Track<UnboundAttrs, UnboundMetric, f32> {
+    store
+        .new_track_random_id()
+        .observation(ObservationBuilder::new(0).observation(vec2(x, 0.0)).build())
+        .build()
+        .unwrap()
+}
+
+/// The number of the allocations of the steady-state distance computation for the store of
+/// `tracks` tracks
+///
+fn allocations(tracks: usize) -> usize {
+    let mut store: Store = TrackStoreBuilder::new(4)
+        .default_attributes(UnboundAttrs)
+        .metric(UnboundMetric)
+        .notifier(NoopNotifier)
+        .build();
+    for i in 0..tracks {
+        let t = track(&store, i as f32);
+        store.add_track(t).unwrap();
+    }
+    let candidates = (0..4)
+        .map(|i| track(&store, -(i as f32)))
+        .collect::<Vec<_>>();
+
+    let compute = |store: &mut Store| {
+        let (dists, errs) = store.foreign_track_distances(candidates.clone(), 0, false);
+        assert_eq!(dists.into_iter().count(), candidates.len() * tracks);
+        assert!(errs.all().is_empty());
+    };
+
+    // the buffers grow to the sizes they need
+    for _ in 0..10 {
+        compute(&mut store);
+    }
+
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    for _ in 0..10 {
+        compute(&mut store);
+    }
+    (ALLOCATIONS.load(Ordering::SeqCst) - before) / 10
+}
+
+#[test]
+fn steady_state_distances_do_not_allocate_results() {
+    let small = allocations(10);
+    let large = allocations(1000);
+    // the remaining allocations are the channels and the candidate copies, they don't depend on
+    // the number of the computed distances
+    assert!(
+        large <= small + 16,
+        "{} allocations for 4000 distances, {} allocations for 40 distances",
+        large,
+        small
+    );
+}